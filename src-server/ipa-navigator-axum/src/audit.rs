@@ -0,0 +1,57 @@
+//! Append-only record of operator actions and user data deletions
+//!
+//! Each action is logged under the `audit` tracing target and, when the job
+//! store is configured, appended to its `audit_log` table, which refuses
+//! updates and deletes. Payloads are kept only as a SHA-256 digest, so the log
+//! can confirm what was sent without holding the data itself.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::store;
+
+/// Actor recorded for requests carrying the admin token
+pub const ADMIN: &str = "admin";
+
+/// Actor recorded for a signed-in user acting on their own data
+pub fn user_actor(user_id: &str) -> String {
+    format!("user:{}", user_id)
+}
+
+/// Record that `actor` performed `action` on `target`, with `payload` as sent
+pub fn record(actor: &str, action: &str, target: Option<&str>, payload: &impl Serialize) {
+    let digest = payload_digest(payload);
+    let recorded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64);
+
+    info!(
+        target: "audit",
+        actor,
+        action,
+        target = target.unwrap_or("-"),
+        payload_digest = %digest,
+        "Audited {}",
+        action
+    );
+
+    let (actor, action, target) = (
+        actor.to_string(),
+        action.to_string(),
+        target.map(str::to_string),
+    );
+    store::record(move |store| async move {
+        store
+            .audit_appended(recorded_at, &actor, &action, target.as_deref(), &digest)
+            .await
+    });
+}
+
+/// Hex SHA-256 of the payload's JSON encoding
+fn payload_digest(payload: &impl Serialize) -> String {
+    let json = serde_json::to_vec(payload).unwrap_or_default();
+    format!("{:x}", Sha256::digest(json))
+}
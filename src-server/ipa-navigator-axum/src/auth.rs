@@ -0,0 +1,49 @@
+use std::env;
+use std::sync::LazyLock;
+
+use axum::{
+    extract::Request,
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::error::Error;
+
+// Token expected in `Authorization: Bearer <token>` for admin routes
+static ADMIN_TOKEN: LazyLock<Option<String>> = LazyLock::new(|| {
+    let token = env::var("ADMIN_TOKEN")
+        .ok()
+        .filter(|t| !t.trim().is_empty());
+    if token.is_none() {
+        tracing::warn!("ADMIN_TOKEN is not set, admin routes are disabled");
+    }
+    token
+});
+
+/// Middleware rejecting requests without the admin bearer token
+///
+/// Admin routes are refused entirely when `ADMIN_TOKEN` is not configured.
+pub async fn require_admin(request: Request, next: Next) -> Response {
+    let Some(expected) = ADMIN_TOKEN.as_deref() else {
+        return Error::Unauthorized("Admin access is not configured".to_string()).into_response();
+    };
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => Error::Unauthorized("Invalid or missing admin token".to_string()).into_response(),
+    }
+}
+
+/// Compare secrets without leaking the position of the first mismatch
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
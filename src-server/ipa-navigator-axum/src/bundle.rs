@@ -0,0 +1,449 @@
+//! Zip bundles of batch synthesis: a WAV file per sentence and a manifest
+//!
+//! Lesson tooling downloads a whole text in one request rather than a clip
+//! per sentence. Bundles are written as they are sent: each WAV file is
+//! encoded in slices and stored uncompressed, with its checksum and size in a
+//! data descriptor after it rather than in its header, so neither the files
+//! nor the archive are ever held whole. Audio hardly deflates, so storing it
+//! costs little.
+
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use axum::body::Body;
+use crc32fast::Hasher;
+use ipa_navigator_kokoro::{
+    prelude::SAMPLE_RATE,
+    wav::{WavFormat, WavStreamEncoder, data_len},
+};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error};
+
+use crate::media::STREAM_CHUNK_SAMPLES;
+
+/// Pieces of an archive queued for sending while the next are written
+const BUNDLE_BUFFER_PIECES: usize = 4;
+
+/// Bytes gathered before a piece is sent
+const BUNDLE_PIECE_BYTES: usize = 64 * 1024;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+const LOCAL_HEADER_LEN: u64 = 30;
+const DATA_DESCRIPTOR_LEN: u64 = 16;
+const CENTRAL_HEADER_LEN: u64 = 46;
+const END_OF_CENTRAL_DIRECTORY_LEN: u64 = 22;
+
+/// Version 2.0 of the zip format, enough for stored files with data descriptors
+const ZIP_VERSION: u16 = 20;
+
+/// Sizes and checksum follow the data, and names are UTF-8
+const ZIP_FLAGS: u16 = 0x0008 | 0x0800;
+
+/// 1 January 1980 in DOS format, the earliest a zip file can be dated, so the
+/// same audio always gives the same archive
+const ZIP_DATE: u16 = (1 << 5) | 1;
+
+/// The audio of a batch job, kept per sentence
+#[derive(Debug, Clone)]
+pub struct AudioBundle {
+    pub items: Vec<BundleItem>,
+}
+
+/// One sentence and its audio
+#[derive(Debug, Clone)]
+pub struct BundleItem {
+    pub text: String,
+    /// Id of the clip, the same as a lesson clip reading the same text the same way
+    pub cache_key: String,
+    pub samples: Arc<[f32]>,
+}
+
+/// `manifest.json` of a bundle, listing its files in reading order
+#[derive(Debug, Serialize)]
+pub struct BundleManifest {
+    pub sample_rate: u32,
+    pub duration_secs: f64,
+    pub items: Vec<BundleManifestItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BundleManifestItem {
+    pub file: String,
+    pub text: String,
+    pub duration_secs: f64,
+    pub cache_key: String,
+}
+
+impl AudioBundle {
+    pub fn manifest(&self) -> BundleManifest {
+        let items: Vec<BundleManifestItem> = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| BundleManifestItem {
+                file: file_name(index),
+                text: item.text.clone(),
+                duration_secs: item.samples.len() as f64 / SAMPLE_RATE as f64,
+                cache_key: item.cache_key.clone(),
+            })
+            .collect();
+
+        BundleManifest {
+            sample_rate: SAMPLE_RATE,
+            duration_secs: items.iter().map(|item| item.duration_secs).sum(),
+            items,
+        }
+    }
+
+    /// Length in bytes of the archive [`write_zip`](Self::write_zip) writes
+    pub fn zip_len(&self) -> io::Result<usize> {
+        let entry_len = |name: &str, len: usize| {
+            LOCAL_HEADER_LEN
+                + DATA_DESCRIPTOR_LEN
+                + CENTRAL_HEADER_LEN
+                + 2 * name.len() as u64
+                + len as u64
+        };
+
+        let mut len = entry_len(MANIFEST_FILE, self.manifest_json()?.len());
+        for (index, item) in self.items.iter().enumerate() {
+            len += entry_len(&file_name(index), wav_len(item.samples.len())?);
+        }
+        Ok((len + END_OF_CENTRAL_DIRECTORY_LEN) as usize)
+    }
+
+    /// Write the bundle as a zip archive, encoding each WAV file as it goes
+    pub fn write_zip(&self, writer: impl Write) -> io::Result<()> {
+        let mut zip = StreamingZip::new(writer);
+
+        for (index, item) in self.items.iter().enumerate() {
+            zip.start_file(file_name(index))?;
+            let mut encoder = WavStreamEncoder::new(WavFormat::default());
+            zip.write_all(
+                &encoder
+                    .header(Some(item.samples.len()))
+                    .map_err(io::Error::other)?,
+            )?;
+            for slice in item.samples.chunks(STREAM_CHUNK_SAMPLES) {
+                zip.write_all(&encoder.encode(slice))?;
+            }
+            zip.write_all(&encoder.finish())?;
+        }
+
+        zip.start_file(MANIFEST_FILE.to_string())?;
+        zip.write_all(&self.manifest_json()?)?;
+
+        zip.finish()?;
+        Ok(())
+    }
+
+    fn manifest_json(&self) -> io::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(&self.manifest()).map_err(io::Error::from)
+    }
+}
+
+/// Stream a bundle as a zip archive, written in the background while earlier pieces are sent
+///
+/// A failure part way ends the stream with an error, so the client sees a
+/// truncated download rather than an archive that looks complete.
+pub fn bundle_body(bundle: AudioBundle) -> Body {
+    let (sender, receiver) = mpsc::channel::<io::Result<Vec<u8>>>(BUNDLE_BUFFER_PIECES);
+
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+
+        let mut writer = ChannelWriter {
+            sender: sender.clone(),
+            piece: Vec::with_capacity(BUNDLE_PIECE_BYTES),
+        };
+        let written = bundle.write_zip(&mut writer).and_then(|_| writer.flush());
+        match written {
+            Ok(()) => {}
+            // The client went away, so there is no one to tell
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                debug!("Bundle download abandoned")
+            }
+            Err(e) => {
+                error!("Bundle failed: {}", e);
+                let _ = sender.blocking_send(Err(e));
+            }
+        }
+    });
+
+    Body::from_stream(ReceiverStream::new(receiver))
+}
+
+/// Name of an item's file, numbered from 1 so files sort in reading order
+fn file_name(index: usize) -> String {
+    format!("{:03}.wav", index + 1)
+}
+
+fn wav_len(samples: usize) -> io::Result<usize> {
+    let header = WavStreamEncoder::new(WavFormat::default())
+        .header(Some(samples))
+        .map_err(io::Error::other)?;
+    Ok(header.len() + data_len(&WavFormat::default(), samples))
+}
+
+/// Writer sending what is written down a channel in pieces of about [`BUNDLE_PIECE_BYTES`]
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Vec<u8>>>,
+    piece: Vec<u8>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.piece.extend_from_slice(data);
+        if self.piece.len() >= BUNDLE_PIECE_BYTES {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.piece.is_empty() {
+            return Ok(());
+        }
+        let piece = std::mem::replace(&mut self.piece, Vec::with_capacity(BUNDLE_PIECE_BYTES));
+        self.sender
+            .blocking_send(Ok(piece))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
+
+/// A file written to the archive, recorded for the central directory
+struct ZipEntry {
+    name: String,
+    offset: u32,
+    crc: u32,
+    size: u32,
+}
+
+/// Zip archive of stored files written front to back, never seeking
+///
+/// `zip::ZipWriter` goes back to fill in each file's header once the file is
+/// written, which a response body cannot do.
+struct StreamingZip<W> {
+    writer: W,
+    offset: u64,
+    entries: Vec<ZipEntry>,
+    /// Checksum and size of the file being written
+    current: Option<(Hasher, u64)>,
+}
+
+impl<W: Write> StreamingZip<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+            current: None,
+        }
+    }
+
+    fn start_file(&mut self, name: String) -> io::Result<()> {
+        self.end_file()?;
+
+        let offset = fits_u32(self.offset)?;
+        let mut header = Vec::with_capacity(LOCAL_HEADER_LEN as usize + name.len());
+        put_u32(&mut header, 0x0403_4b50);
+        put_u16(&mut header, ZIP_VERSION);
+        put_u16(&mut header, ZIP_FLAGS);
+        put_u16(&mut header, 0); // Stored
+        put_u16(&mut header, 0); // Time
+        put_u16(&mut header, ZIP_DATE);
+        // Checksum and sizes follow the data
+        header.extend_from_slice(&[0; 12]);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 0); // Extra field length
+        header.extend_from_slice(name.as_bytes());
+        self.put(&header)?;
+
+        self.entries.push(ZipEntry {
+            name,
+            offset,
+            crc: 0,
+            size: 0,
+        });
+        self.current = Some((Hasher::new(), 0));
+        Ok(())
+    }
+
+    fn end_file(&mut self) -> io::Result<()> {
+        let Some((hasher, size)) = self.current.take() else {
+            return Ok(());
+        };
+        let crc = hasher.finalize();
+        let size = fits_u32(size)?;
+
+        let mut descriptor = Vec::with_capacity(DATA_DESCRIPTOR_LEN as usize);
+        put_u32(&mut descriptor, 0x0807_4b50);
+        put_u32(&mut descriptor, crc);
+        put_u32(&mut descriptor, size);
+        put_u32(&mut descriptor, size);
+        self.put(&descriptor)?;
+
+        if let Some(entry) = self.entries.last_mut() {
+            entry.crc = crc;
+            entry.size = size;
+        }
+        Ok(())
+    }
+
+    /// Write the central directory after the last file
+    fn finish(mut self) -> io::Result<W> {
+        self.end_file()?;
+
+        let start = fits_u32(self.offset)?;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            put_u32(&mut directory, 0x0201_4b50);
+            put_u16(&mut directory, ZIP_VERSION); // Made by
+            put_u16(&mut directory, ZIP_VERSION); // Needed
+            put_u16(&mut directory, ZIP_FLAGS);
+            put_u16(&mut directory, 0); // Stored
+            put_u16(&mut directory, 0); // Time
+            put_u16(&mut directory, ZIP_DATE);
+            put_u32(&mut directory, entry.crc);
+            put_u32(&mut directory, entry.size);
+            put_u32(&mut directory, entry.size);
+            put_u16(&mut directory, entry.name.len() as u16);
+            // Extra field and comment lengths, disk, and attributes
+            directory.extend_from_slice(&[0; 12]);
+            put_u32(&mut directory, entry.offset);
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_len = directory.len() as u32;
+        let count = u16::try_from(self.entries.len())
+            .map_err(|_| too_large("Too many files for a zip archive"))?;
+
+        put_u32(&mut directory, 0x0605_4b50);
+        put_u16(&mut directory, 0); // This disk
+        put_u16(&mut directory, 0); // Disk with the directory
+        put_u16(&mut directory, count);
+        put_u16(&mut directory, count);
+        put_u32(&mut directory, directory_len);
+        put_u32(&mut directory, start);
+        put_u16(&mut directory, 0); // Comment length
+        self.put(&directory)?;
+
+        Ok(self.writer)
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for StreamingZip<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let Some((hasher, size)) = self.current.as_mut() else {
+            return Err(io::Error::other("No file started in the zip archive"));
+        };
+        hasher.update(data);
+        *size += data.len() as u64;
+        self.put(data)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn put_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Offsets and sizes past 4 GiB would need Zip64, which bundles are far too small for
+fn fits_u32(value: u64) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| too_large("Bundle too large for a zip archive"))
+}
+
+fn too_large(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use io::{Cursor, Read};
+    use ipa_navigator_kokoro::wav::encode_wav;
+    use zip::ZipArchive;
+
+    fn bundle() -> AudioBundle {
+        let tone = |len: usize| -> Arc<[f32]> {
+            (0..len)
+                .map(|i| (i as f32 / 11.0).sin() * 0.3)
+                .collect::<Vec<_>>()
+                .into()
+        };
+        AudioBundle {
+            items: vec![
+                BundleItem {
+                    text: "The cat sat.".to_string(),
+                    cache_key: "a".repeat(32),
+                    samples: tone(SAMPLE_RATE as usize * 3 / 2),
+                },
+                BundleItem {
+                    text: "It purred.".to_string(),
+                    cache_key: "b".repeat(32),
+                    samples: tone(SAMPLE_RATE as usize / 2),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_bundle_is_a_readable_zip() {
+        let bundle = bundle();
+        let mut archive_data = Vec::new();
+        bundle.write_zip(&mut archive_data).unwrap();
+        assert_eq!(archive_data.len(), bundle.zip_len().unwrap());
+
+        let mut archive = ZipArchive::new(Cursor::new(archive_data)).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert_eq!(names.len(), 3);
+
+        for (index, item) in bundle.items.iter().enumerate() {
+            let mut wav_data = Vec::new();
+            archive
+                .by_name(&file_name(index))
+                .unwrap()
+                .read_to_end(&mut wav_data)
+                .unwrap();
+            assert_eq!(
+                wav_data,
+                encode_wav(&item.samples, &WavFormat::default()).unwrap()
+            );
+        }
+
+        let manifest: serde_json::Value =
+            serde_json::from_reader(archive.by_name(MANIFEST_FILE).unwrap()).unwrap();
+        assert_eq!(manifest["items"][0]["file"], "001.wav");
+        assert_eq!(manifest["items"][1]["cache_key"], "b".repeat(32));
+        assert_eq!(manifest["items"][0]["duration_secs"], 1.5);
+        assert_eq!(manifest["duration_secs"], 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_body_streams_the_archive() {
+        let bundle = bundle();
+        let mut expected = Vec::new();
+        bundle.write_zip(&mut expected).unwrap();
+
+        let body = to_bytes(bundle_body(bundle), usize::MAX).await.unwrap();
+        assert_eq!(body, expected);
+    }
+}
@@ -0,0 +1,235 @@
+//! Cache of assessment responses, so resubmitting a recording skips alignment
+//!
+//! Responses are kept in memory and, when configured, in Convex so they
+//! survive restarts and are shared between server instances. A tenant's
+//! responses are keyed by the tenant and kept in its own Convex deployment,
+//! which needs the same `ASSESSMENT_CACHE_TOKEN`. The in-memory capacity can
+//! be changed while serving, see [`crate::config::ConfigManager`].
+
+use std::env;
+use std::num::NonZeroUsize;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use lru::LruCache;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::convex::{CONVEX, ConvexError, ConvexHttp};
+use crate::handlers::mfa::{AssessmentInput, PronunciationResponse};
+use crate::store;
+use crate::tenants::Tenant;
+
+/// Responses kept in memory when `ASSESSMENT_CACHE_SIZE` is not set
+const DEFAULT_CAPACITY: usize = 200;
+
+/// Longest wait for Convex before treating the cache as missed
+const CONVEX_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct CacheConfig {
+    /// Responses kept in memory; 0 disables the cache
+    pub capacity: usize,
+    /// Shared secret checked by the Convex cache functions; responses are
+    /// only stored in Convex when this and `CONVEX_DEPLOYMENT_URL` are set
+    pub convex_token: Option<String>,
+}
+
+impl CacheConfig {
+    pub fn from_env() -> Self {
+        let capacity = env::var("ASSESSMENT_CACHE_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+
+        let convex_token = env::var("ASSESSMENT_CACHE_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+
+        Self {
+            capacity,
+            convex_token,
+        }
+    }
+}
+
+/// Cache shared by all assessment requests
+pub static ASSESSMENT_CACHE: LazyLock<AssessmentCache> =
+    LazyLock::new(|| AssessmentCache::new(CacheConfig::from_env()));
+
+/// Key identifying an assessment of `input` with `backend` by everything that affects its result
+///
+/// Each part is length-prefixed so different splits of the same bytes never collide.
+/// The voice paced against is only hashed when given, so keys without one are unchanged.
+/// The tenant, whose lexicon and voices change the result, is hashed after a
+/// marker so it can never be mistaken for a voice.
+pub(crate) fn cache_key(input: &AssessmentInput, backend: &str) -> String {
+    let pace_voice = input.pace_voice.map(|voice| voice.name());
+    let tenant = input.tenant.as_ref().map(Tenant::id);
+
+    let mut hasher = Sha256::new();
+    for part in [
+        &input.audio[..],
+        input.transcript.as_bytes(),
+        input.dialect.dictionary_name().as_bytes(),
+        input.strictness.as_str().as_bytes(),
+        input.locale.code().as_bytes(),
+        backend.as_bytes(),
+    ]
+    .into_iter()
+    .chain(pace_voice.map(str::as_bytes))
+    .chain(
+        tenant
+            .into_iter()
+            .flat_map(|tenant| [&b"tenant"[..], tenant.as_bytes()]),
+    ) {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+pub struct AssessmentCache {
+    /// Responses in memory, none while the cache is disabled
+    memory: Mutex<Option<LruCache<String, PronunciationResponse>>>,
+    /// Secret for the Convex cache functions, none to keep responses in memory only
+    convex_token: Option<String>,
+}
+
+impl AssessmentCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            memory: Mutex::new(NonZeroUsize::new(config.capacity).map(LruCache::new)),
+            convex_token: config.convex_token,
+        }
+    }
+
+    /// Convex copy of the responses to `tenant`'s requests: its own deployment, or the server's
+    fn convex(&self, tenant: Option<&Tenant>) -> Option<ConvexStore> {
+        let token = self.convex_token.clone()?;
+        let client = tenant.and_then(Tenant::convex).or(CONVEX.as_ref())?;
+        Some(ConvexStore {
+            client: client.clone(),
+            token,
+        })
+    }
+
+    /// Keep at most `capacity` responses in memory, dropping the least recently
+    /// used; 0 disables the cache, including its Convex copy
+    pub fn resize(&self, capacity: usize) {
+        let mut memory = self.memory.lock().unwrap_or_else(|e| e.into_inner());
+        match (NonZeroUsize::new(capacity), memory.as_mut()) {
+            (Some(capacity), Some(cache)) => cache.resize(capacity),
+            (Some(capacity), None) => *memory = Some(LruCache::new(capacity)),
+            (None, _) => *memory = None,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.memory.lock().is_ok_and(|memory| memory.is_some())
+    }
+
+    /// Look up a response in memory, then in `tenant`'s Convex deployment
+    ///
+    /// Convex failures are logged and treated as a miss.
+    pub async fn get(&self, key: &str, tenant: Option<&Tenant>) -> Option<PronunciationResponse> {
+        if let Some(response) = self.memory.lock().ok()?.as_mut()?.get(key).cloned() {
+            record_hit(key);
+            return Some(response);
+        }
+
+        let convex = self.convex(tenant)?;
+        let lookup = tokio::time::timeout(CONVEX_TIMEOUT, convex.get(key));
+        let response = match lookup.await {
+            Ok(Ok(response)) => response?,
+            Ok(Err(e)) => {
+                warn!("Failed to read cached assessment from Convex: {}", e);
+                return None;
+            }
+            Err(_) => {
+                warn!("Timed out reading cached assessment from Convex");
+                return None;
+            }
+        };
+
+        if let Ok(mut memory) = self.memory.lock()
+            && let Some(memory) = memory.as_mut()
+        {
+            memory.put(key.to_string(), response.clone());
+        }
+        record_hit(key);
+        Some(response)
+    }
+
+    /// Store a response in memory, and in `tenant`'s Convex deployment in the background
+    pub fn put(&self, key: String, response: &PronunciationResponse, tenant: Option<&Tenant>) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let stored = key.clone();
+        store::record(move |store| async move { store.cache_stored(&stored).await });
+
+        if let Some(convex) = self.convex(tenant) {
+            let key = key.clone();
+            let response = response.clone();
+            tokio::spawn(async move {
+                if let Err(e) = convex.put(&key, &response).await {
+                    warn!("Failed to write cached assessment to Convex: {}", e);
+                }
+            });
+        }
+
+        if let Ok(mut memory) = self.memory.lock()
+            && let Some(memory) = memory.as_mut()
+        {
+            memory.put(key, response.clone());
+        }
+    }
+}
+
+/// Count a cache hit in the job store
+fn record_hit(key: &str) {
+    let key = key.to_string();
+    store::record(move |store| async move { store.cache_hit(&key).await });
+}
+
+/// Responses stored through the `functions/assessmentCache` Convex functions
+#[derive(Clone)]
+struct ConvexStore {
+    client: ConvexHttp,
+    token: String,
+}
+
+impl ConvexStore {
+    async fn get(&self, key: &str) -> Result<Option<PronunciationResponse>, ConvexError> {
+        let value = self
+            .client
+            .query(
+                "functions/assessmentCache:get",
+                json!({ "token": self.token, "key": key }),
+                None,
+            )
+            .await?;
+
+        match value.as_str() {
+            Some(response) => serde_json::from_str(response).map(Some).map_err(|e| {
+                ConvexError::InvalidResponse(format!("Invalid cached response: {}", e))
+            }),
+            None => Ok(None),
+        }
+    }
+
+    async fn put(&self, key: &str, response: &PronunciationResponse) -> Result<(), ConvexError> {
+        let response = serde_json::to_string(response)
+            .map_err(|e| ConvexError::InvalidResponse(e.to_string()))?;
+        self.client
+            .mutation(
+                "functions/assessmentCache:put",
+                json!({ "token": self.token, "key": key, "response": response }),
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+//! Classroom assignments and their submissions, stored in Convex
+//!
+//! Teachers assign sets of sentences to a classroom, students' recordings of
+//! them are scored by the server, and each score is stored per sentence for
+//! the teacher to review. Every call runs as the signed-in user, so Convex
+//! decides who may manage or submit to a classroom.
+//!
+//! Recordings are only kept when `SUBMISSION_AUDIO_RETENTION_DAYS` is set, and
+//! Convex deletes them once that many days have passed. Since classrooms may
+//! include minors, `SUBMISSION_AUDIO_ANONYMIZE` disguises the voice first.
+
+use std::env;
+use std::sync::LazyLock;
+
+use ipa_navigator_mfa::anonymize::DEFAULT_PITCH_SEMITONES;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::convex::{ConvexError, UserConvex};
+use crate::error::Error;
+use crate::identity::AuthUser;
+
+pub struct RecordingConfig {
+    /// Days recordings are kept for review; none are stored when 0
+    pub retention_days: u32,
+    /// Disguise the voice before storing, see [`ipa_navigator_mfa::anonymize`]
+    pub anonymize: bool,
+    /// Pitch shift applied when anonymizing, in semitones
+    pub pitch_semitones: f32,
+}
+
+impl RecordingConfig {
+    pub fn from_env() -> Self {
+        let retention_days = env::var("SUBMISSION_AUDIO_RETENTION_DAYS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+
+        let anonymize = env::var("SUBMISSION_AUDIO_ANONYMIZE")
+            .map(|s| matches!(s.trim(), "1" | "true"))
+            .unwrap_or(false);
+
+        let pitch_semitones = env::var("SUBMISSION_AUDIO_PITCH_SEMITONES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|semitones: &f32| semitones.is_finite() && *semitones != 0.0)
+            .unwrap_or(DEFAULT_PITCH_SEMITONES);
+
+        Self {
+            retention_days,
+            anonymize,
+            pitch_semitones,
+        }
+    }
+}
+
+/// How submission recordings are kept, shared by all requests
+pub static RECORDINGS: LazyLock<RecordingConfig> = LazyLock::new(RecordingConfig::from_env);
+
+/// A recording stored in Convex for review
+#[derive(Debug, Clone)]
+pub struct StoredRecording {
+    pub storage_id: String,
+    /// Milliseconds since the Unix epoch
+    pub expires_at: i64,
+}
+
+/// A set of sentences assigned to a classroom
+#[derive(Debug, Clone, Deserialize)]
+pub struct Assignment {
+    #[serde(rename = "_id")]
+    pub id: String,
+    #[serde(rename = "classroomId")]
+    pub classroom_id: String,
+    pub title: String,
+    /// "us" or "uk"
+    pub dialect: String,
+    pub sentences: Vec<String>,
+    /// Milliseconds since the Unix epoch
+    pub due_date: Option<f64>,
+    pub created_at: f64,
+}
+
+/// An assignment to create
+#[derive(Debug, Serialize)]
+pub struct NewAssignment {
+    pub title: String,
+    pub dialect: String,
+    pub sentences: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<i64>,
+}
+
+/// How one expected phoneme of a submission was pronounced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhonemeScore {
+    pub expected: String,
+    pub actual: String,
+    pub score: f64,
+}
+
+/// A student's scored recording of one sentence
+#[derive(Debug, Clone, Deserialize)]
+pub struct Submission {
+    #[serde(rename = "_id")]
+    pub id: String,
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    /// Only included when listing an assignment's submissions
+    pub student_name: Option<String>,
+    pub sentence_index: f64,
+    pub overall_score: f64,
+    pub phonemes: Vec<PhonemeScore>,
+    /// Submitted after the due date
+    pub late: bool,
+    /// Milliseconds since the Unix epoch
+    pub submitted_at: f64,
+    /// The kept recording, only included when listing an assignment's submissions
+    pub audio_url: Option<String>,
+}
+
+/// Classroom calls made as one signed-in user
+pub struct Classroom<'a> {
+    convex: UserConvex<'a>,
+}
+
+impl<'a> Classroom<'a> {
+    pub fn for_user(user: &'a AuthUser) -> Result<Self, Error> {
+        UserConvex::for_user(user).map(|convex| Self { convex })
+    }
+
+    /// Create an assignment, if the user teaches the classroom
+    pub async fn create_assignment(
+        &self,
+        classroom_id: &str,
+        assignment: &NewAssignment,
+    ) -> Result<String, ConvexError> {
+        let mut args = json!(assignment);
+        args["classroomId"] = json!(classroom_id);
+
+        self.convex
+            .mutation("functions/assignments:createAssignment", args)
+            .await
+    }
+
+    /// The assignment, unless it does not exist or the user is not in its classroom
+    pub async fn assignment(&self, assignment_id: &str) -> Result<Option<Assignment>, ConvexError> {
+        self.convex
+            .query(
+                "functions/assignments:getAssignment",
+                json!({ "assignmentId": assignment_id }),
+            )
+            .await
+    }
+
+    /// Store a WAV recording for review, returning its storage id
+    pub async fn store_recording(&self, wav: Vec<u8>) -> Result<String, ConvexError> {
+        self.convex.store_file(wav, "audio/wav").await
+    }
+
+    /// Store the user's scored recording of a sentence
+    pub async fn submit(
+        &self,
+        assignment_id: &str,
+        sentence_index: usize,
+        overall_score: f64,
+        phonemes: &[PhonemeScore],
+        recording: Option<&StoredRecording>,
+    ) -> Result<String, ConvexError> {
+        let mut args = json!({
+            "assignmentId": assignment_id,
+            "sentence_index": sentence_index,
+            "overall_score": overall_score,
+            "phonemes": phonemes,
+        });
+        if let Some(recording) = recording {
+            args["audioId"] = json!(recording.storage_id);
+            args["audio_expires_at"] = json!(recording.expires_at);
+        }
+
+        self.convex
+            .mutation("functions/assignments:submitAttempt", args)
+            .await
+    }
+
+    /// Every submission to an assignment, if the user teaches its classroom
+    pub async fn submissions(&self, assignment_id: &str) -> Result<Vec<Submission>, ConvexError> {
+        self.convex
+            .query(
+                "functions/assignments:getSubmissions",
+                json!({ "assignmentId": assignment_id }),
+            )
+            .await
+    }
+
+    /// A classroom's submissions since `since`, in milliseconds since the Unix epoch
+    pub async fn classroom_submissions(
+        &self,
+        classroom_id: &str,
+        since: Option<i64>,
+    ) -> Result<Vec<Submission>, ConvexError> {
+        let mut args = json!({ "classroomId": classroom_id });
+        if let Some(since) = since {
+            args["since"] = json!(since);
+        }
+
+        self.convex
+            .query("functions/assignments:getClassroomSubmissions", args)
+            .await
+    }
+}
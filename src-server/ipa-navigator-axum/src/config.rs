@@ -1,8 +1,56 @@
+//! Server configuration
+//!
+//! [`Config`] is read from the environment once at startup. Settings that are
+//! safe to change while serving can also be set in the JSON file named by
+//! `CONFIG_FILE`, which the [`ConfigManager`] applies at startup and again
+//! whenever the file changes or an operator calls
+//! `POST /api/admin/config/reload`:
+//!
+//! ```json
+//! {
+//!   "tts_concurrency": 16,
+//!   "assess_concurrency": 4,
+//!   "assessment_cache_size": 200,
+//!   "synthesis_cache_size": 50,
+//!   "cors_origins": ["https://ipa-navigator.example.com"],
+//!   "scoring_profile": "intermediate"
+//! }
+//! ```
+//!
+//! Settings left out of the file keep their value from the environment, and a
+//! file that fails to parse leaves every setting as it was. Each change is
+//! recorded in the audit log.
+
 use std::env;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::time::Duration;
+
+use axum::http::HeaderValue;
+use ipa_navigator_kokoro::espeak::EspeakDataSource;
+use ipa_navigator_kokoro::tts::CACHE_CAPACITY;
+use ipa_navigator_mfa::scoring::Strictness;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::audit;
+use crate::cache::{ASSESSMENT_CACHE, CacheConfig};
+use crate::handlers::tts::loaded_tts;
+use crate::limits::{ASSESS_ROUTES, LimitConfig, TTS_ROUTES};
+
+/// Actor recorded for changes picked up from the config file
+pub const CONFIG_FILE_ACTOR: &str = "config-file";
+
+/// How long to wait for an editor to finish writing before reading the file
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
 
 pub struct Config {
     pub port: u16,
     pub host: String,
+    /// Where espeak-ng's data directory is found, from `ESPEAK_DATA`
+    pub espeak_data: EspeakDataSource,
 }
 
 impl Config {
@@ -14,6 +62,401 @@ impl Config {
 
         let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
 
-        Self { port, host }
+        Self {
+            port,
+            host,
+            espeak_data: EspeakDataSource::from_env(),
+        }
+    }
+}
+
+/// Manager of the settings file named by `CONFIG_FILE`
+pub static CONFIG_MANAGER: LazyLock<ConfigManager> = LazyLock::new(ConfigManager::from_env);
+
+/// Settings as written in the config file, each optional
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SettingsFile {
+    pub tts_concurrency: Option<usize>,
+    pub assess_concurrency: Option<usize>,
+    pub assessment_cache_size: Option<usize>,
+    pub synthesis_cache_size: Option<usize>,
+    pub cors_origins: Option<Vec<String>>,
+    pub scoring_profile: Option<String>,
+}
+
+/// Settings that can change while the server is running
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeSettings {
+    /// Synthesis requests handled at once
+    pub tts_concurrency: usize,
+    /// Assessment requests handled at once
+    pub assess_concurrency: usize,
+    /// Assessment responses kept in memory; 0 disables the cache
+    pub assessment_cache_size: usize,
+    /// Synthesized clips kept in memory
+    pub synthesis_cache_size: NonZeroUsize,
+    /// Origins browsers may call the API from, any if none
+    pub cors_origins: Option<Vec<String>>,
+    /// Strictness used when a request does not name one
+    pub scoring_profile: Strictness,
+}
+
+/// A setting whose value changed, for the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SettingChange {
+    pub setting: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+impl RuntimeSettings {
+    /// Settings from the environment, before any config file is read
+    pub fn from_env() -> Self {
+        let limits = LimitConfig::from_env();
+
+        Self {
+            tts_concurrency: limits.tts,
+            assess_concurrency: limits.assess,
+            assessment_cache_size: CacheConfig::from_env().capacity,
+            synthesis_cache_size: NonZeroUsize::new(CACHE_CAPACITY).unwrap_or(NonZeroUsize::MIN),
+            cors_origins: None,
+            scoring_profile: Strictness::default(),
+        }
+    }
+
+    /// These settings overridden by the ones in `file`
+    pub fn with_file(&self, file: SettingsFile) -> Result<Self, String> {
+        let positive = |name: &str, value: Option<usize>, current: usize| match value {
+            Some(0) => Err(format!("{} must be at least 1", name)),
+            Some(value) => Ok(value),
+            None => Ok(current),
+        };
+
+        let cors_origins = match file.cors_origins {
+            Some(origins) => {
+                if let Some(origin) = origins
+                    .iter()
+                    .find(|origin| HeaderValue::from_str(origin).is_err())
+                {
+                    return Err(format!("Invalid CORS origin: {}", origin));
+                }
+                Some(origins)
+            }
+            None => self.cors_origins.clone(),
+        };
+
+        let scoring_profile = match file.scoring_profile {
+            Some(name) => Strictness::parse(&name)
+                .ok_or_else(|| format!("Unsupported scoring profile: {}", name))?,
+            None => self.scoring_profile,
+        };
+
+        Ok(Self {
+            tts_concurrency: positive(
+                "tts_concurrency",
+                file.tts_concurrency,
+                self.tts_concurrency,
+            )?,
+            assess_concurrency: positive(
+                "assess_concurrency",
+                file.assess_concurrency,
+                self.assess_concurrency,
+            )?,
+            assessment_cache_size: file
+                .assessment_cache_size
+                .unwrap_or(self.assessment_cache_size),
+            synthesis_cache_size: match file.synthesis_cache_size {
+                Some(size) => NonZeroUsize::new(size)
+                    .ok_or_else(|| "synthesis_cache_size must be at least 1".to_string())?,
+                None => self.synthesis_cache_size,
+            },
+            cors_origins,
+            scoring_profile,
+        })
+    }
+
+    /// Settings that differ in `other`, with their old and new values
+    pub fn changes(&self, other: &Self) -> Vec<SettingChange> {
+        let origins = |origins: &Option<Vec<String>>| match origins {
+            Some(origins) => origins.join(","),
+            None => "*".to_string(),
+        };
+        let values = |settings: &Self| {
+            [
+                ("tts_concurrency", settings.tts_concurrency.to_string()),
+                (
+                    "assess_concurrency",
+                    settings.assess_concurrency.to_string(),
+                ),
+                (
+                    "assessment_cache_size",
+                    settings.assessment_cache_size.to_string(),
+                ),
+                (
+                    "synthesis_cache_size",
+                    settings.synthesis_cache_size.to_string(),
+                ),
+                ("cors_origins", origins(&settings.cors_origins)),
+                (
+                    "scoring_profile",
+                    settings.scoring_profile.as_str().to_string(),
+                ),
+            ]
+        };
+
+        values(self)
+            .into_iter()
+            .zip(values(other))
+            .filter(|((_, from), (_, to))| from != to)
+            .map(|((setting, from), (_, to))| SettingChange { setting, from, to })
+            .collect()
+    }
+
+    /// Whether a browser at `origin` may call the API
+    pub fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        self.cors_origins.as_ref().is_none_or(|origins| {
+            origins
+                .iter()
+                .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+        })
+    }
+}
+
+/// Applies the settings file while the server runs
+pub struct ConfigManager {
+    path: Option<PathBuf>,
+    /// Settings from the environment, which the file overrides
+    base: RuntimeSettings,
+    current: RwLock<RuntimeSettings>,
+    /// Kept alive for as long as the file is watched
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl ConfigManager {
+    pub fn new(path: Option<PathBuf>, base: RuntimeSettings) -> Self {
+        Self {
+            path,
+            current: RwLock::new(base.clone()),
+            base,
+            watcher: Mutex::new(None),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let path = env::var("CONFIG_FILE")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
+        Self::new(path, RuntimeSettings::from_env())
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The settings in effect
+    pub fn settings(&self) -> RuntimeSettings {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Read the file and apply the settings that changed, recording each
+    /// change in the audit log as made by `actor`
+    ///
+    /// Without a config file there is nothing to reload, and no changes.
+    pub fn reload(&self, actor: &str) -> Result<Vec<SettingChange>, String> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let file: SettingsFile = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        let settings = self.base.with_file(file)?;
+
+        let changes = self.settings().changes(&settings);
+        apply(&settings, &changes);
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = settings;
+
+        for change in &changes {
+            audit::record(actor, "config.set", Some(change.setting), change);
+            info!(
+                "Set {} from {} to {}",
+                change.setting, change.from, change.to
+            );
+        }
+        Ok(changes)
+    }
+
+    /// Reload the settings whenever the file changes, until the server stops
+    ///
+    /// The file's directory is watched rather than the file, since editors
+    /// often save by replacing the file.
+    pub fn watch(&'static self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let directory = path
+            .parent()
+            .filter(|directory| !directory.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let name = path.file_name().map(|name| name.to_os_string());
+
+        let (changed, mut changes) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event)
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                        && event
+                            .paths
+                            .iter()
+                            .any(|changed| changed.file_name() == name.as_deref()) =>
+                {
+                    let _ = changed.send(());
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Config file watch failed: {}", e),
+            })
+            .map_err(|e| format!("Failed to watch {}: {}", path.display(), e))?;
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("Failed to watch {}: {}", path.display(), e))?;
+        *self.watcher.lock().unwrap_or_else(|e| e.into_inner()) = Some(watcher);
+
+        tokio::spawn(async move {
+            while changes.recv().await.is_some() {
+                // One save can raise several events, so wait for them all and reload once
+                tokio::time::sleep(RELOAD_DEBOUNCE).await;
+                while changes.try_recv().is_ok() {}
+
+                if let Err(e) = self.reload(CONFIG_FILE_ACTOR) {
+                    warn!("Keeping the current settings: {}", e);
+                }
+            }
+        });
+
+        info!("Watching {} for setting changes", path.display());
+        Ok(())
+    }
+}
+
+/// Apply changed settings to the parts of the server they control
+///
+/// CORS origins and the scoring profile are read from the settings for each
+/// request, so they need nothing applied.
+fn apply(settings: &RuntimeSettings, changes: &[SettingChange]) {
+    for change in changes {
+        match change.setting {
+            "tts_concurrency" => TTS_ROUTES.set_limit(settings.tts_concurrency),
+            "assess_concurrency" => ASSESS_ROUTES.set_limit(settings.assess_concurrency),
+            "assessment_cache_size" => ASSESSMENT_CACHE.resize(settings.assessment_cache_size),
+            "synthesis_cache_size" => {
+                if let Some(tts) = loaded_tts() {
+                    tts.resize_cache(settings.synthesis_cache_size);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Default scoring strictness, as currently configured
+pub fn scoring_profile() -> Strictness {
+    CONFIG_MANAGER.settings().scoring_profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> RuntimeSettings {
+        RuntimeSettings {
+            tts_concurrency: 16,
+            assess_concurrency: 4,
+            assessment_cache_size: 200,
+            synthesis_cache_size: NonZeroUsize::new(50).unwrap(),
+            cors_origins: None,
+            scoring_profile: Strictness::Intermediate,
+        }
+    }
+
+    #[test]
+    fn test_file_overrides_only_its_settings() {
+        let file: SettingsFile = serde_json::from_str(
+            r#"{ "assess_concurrency": 2, "cors_origins": ["https://a.example"], "scoring_profile": "strict" }"#,
+        )
+        .unwrap();
+        let settings = base().with_file(file).unwrap();
+
+        assert_eq!(settings.tts_concurrency, 16);
+        assert_eq!(settings.assess_concurrency, 2);
+        assert_eq!(settings.scoring_profile, Strictness::Strict);
+        assert_eq!(
+            base().changes(&settings),
+            vec![
+                SettingChange {
+                    setting: "assess_concurrency",
+                    from: "4".to_string(),
+                    to: "2".to_string(),
+                },
+                SettingChange {
+                    setting: "cors_origins",
+                    from: "*".to_string(),
+                    to: "https://a.example".to_string(),
+                },
+                SettingChange {
+                    setting: "scoring_profile",
+                    from: "intermediate".to_string(),
+                    to: "strict".to_string(),
+                },
+            ]
+        );
+        assert!(settings.allows_origin(&HeaderValue::from_static("https://a.example")));
+        assert!(!settings.allows_origin(&HeaderValue::from_static("https://b.example")));
+        assert!(base().allows_origin(&HeaderValue::from_static("https://b.example")));
+    }
+
+    #[test]
+    fn test_rejects_unsafe_settings() {
+        let file = |json: &str| serde_json::from_str::<SettingsFile>(json);
+
+        assert!(
+            base()
+                .with_file(file(r#"{ "tts_concurrency": 0 }"#).unwrap())
+                .is_err()
+        );
+        assert!(
+            base()
+                .with_file(file(r#"{ "synthesis_cache_size": 0 }"#).unwrap())
+                .is_err()
+        );
+        assert!(
+            base()
+                .with_file(file(r#"{ "scoring_profile": "harsh" }"#).unwrap())
+                .is_err()
+        );
+        assert!(
+            base()
+                .with_file(file(r#"{ "cors_origins": ["bad\norigin"] }"#).unwrap())
+                .is_err()
+        );
+        assert!(file(r#"{ "port": 80 }"#).is_err());
+        // A disabled assessment cache is allowed
+        assert!(
+            base()
+                .with_file(file(r#"{ "assessment_cache_size": 0 }"#).unwrap())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_reload_without_a_file_changes_nothing() {
+        let manager = ConfigManager::new(None, base());
+        assert_eq!(manager.reload(audit::ADMIN), Ok(Vec::new()));
+        assert_eq!(manager.settings(), base());
     }
 }
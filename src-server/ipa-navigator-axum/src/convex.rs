@@ -0,0 +1,428 @@
+//! Client for calling Convex queries and mutations over its HTTP API
+//!
+//! One client is shared by all requests, reusing its connections. Calls that
+//! fail because Convex could not be reached are retried with exponential
+//! backoff, and after repeated failures the client stops calling Convex for a
+//! while, so an outage fails requests quickly instead of tying them up.
+
+use std::env;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use thiserror::Error;
+
+use crate::error::Error;
+use crate::identity::AuthUser;
+
+/// Longest wait for a Convex function call
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Attempts at a call, including the first
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled for each one after
+const BASE_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Longest wait between retries
+const MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Consecutive failed calls that open the circuit
+const FAILURE_THRESHOLD: u32 = 5;
+
+/// How long an open circuit fails calls before letting one through to test Convex
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+/// Deployment named by `CONVEX_DEPLOYMENT_URL`, or none if it is not set
+pub static CONVEX: LazyLock<Option<ConvexHttp>> = LazyLock::new(|| {
+    let url = env::var("CONVEX_DEPLOYMENT_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())?;
+
+    ConvexHttp::new(&url)
+        .inspect_err(|e| tracing::warn!("Convex client disabled: {}", e))
+        .ok()
+});
+
+#[derive(Debug, Error)]
+pub enum ConvexError {
+    /// The request never reached Convex, so it is safe to send again
+    #[error("Convex is unreachable: {0}")]
+    Unreachable(String),
+
+    /// The request timed out or Convex failed, so it may have run
+    #[error("Convex is unavailable: {0}")]
+    Unavailable(String),
+
+    /// Calls are failing fast after repeated failures
+    #[error("Convex calls are paused after repeated failures, retrying in {}s", .0.as_secs().max(1))]
+    CircuitOpen(Duration),
+
+    /// The function ran and threw an error
+    #[error("{0}")]
+    Function(String),
+
+    #[error("Invalid Convex response: {0}")]
+    InvalidResponse(String),
+}
+
+impl ConvexError {
+    /// Whether the failure says Convex itself is unhealthy, rather than the call
+    fn is_outage(&self) -> bool {
+        matches!(
+            self,
+            ConvexError::Unreachable(_) | ConvexError::Unavailable(_)
+        )
+    }
+
+    /// Whether the call can be sent again without risking running it twice
+    fn is_retryable(&self, idempotent: bool) -> bool {
+        match self {
+            ConvexError::Unreachable(_) => true,
+            ConvexError::Unavailable(_) => idempotent,
+            _ => false,
+        }
+    }
+}
+
+impl From<ConvexError> for Error {
+    fn from(e: ConvexError) -> Self {
+        match e {
+            ConvexError::Unreachable(_)
+            | ConvexError::Unavailable(_)
+            | ConvexError::CircuitOpen(_) => Error::ServiceUnavailable(e.to_string()),
+            ConvexError::Function(_) | ConvexError::InvalidResponse(_) => {
+                Error::InternalServerError(e.to_string())
+            }
+        }
+    }
+}
+
+/// Map a failed call made for a user to a response
+///
+/// Errors thrown by the function are refusals, such as a user acting without
+/// permission or naming a document that does not exist, so they are the
+/// client's fault rather than the server's.
+pub fn user_call_error(e: ConvexError) -> Error {
+    match e {
+        ConvexError::Function(message) => Error::BadRequest(message),
+        e => Error::from(e),
+    }
+}
+
+/// Result of a Convex HTTP API call
+#[derive(Debug, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum ConvexResult {
+    Success {
+        value: Value,
+    },
+    Error {
+        #[serde(rename = "errorMessage")]
+        error_message: String,
+    },
+}
+
+/// Tracks consecutive failures and pauses calls while Convex is down
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    failures: u32,
+    /// When calls may resume, while the circuit is open
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Fail fast while open; once the pause is over, let calls through to test Convex
+    fn check(&self) -> Result<(), ConvexError> {
+        match self.open_until {
+            Some(until) if until > Instant::now() => {
+                Err(ConvexError::CircuitOpen(until - Instant::now()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record(&mut self, outage: bool) {
+        if !outage {
+            self.failures = 0;
+            self.open_until = None;
+            return;
+        }
+
+        self.failures += 1;
+        // A failed test after a pause reopens the circuit straight away
+        if self.failures >= FAILURE_THRESHOLD {
+            if self.open_until.is_none() {
+                tracing::warn!(
+                    "Pausing Convex calls for {:?} after {} failures",
+                    OPEN_DURATION,
+                    self.failures
+                );
+            }
+            self.open_until = Some(Instant::now() + OPEN_DURATION);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ConvexHttp {
+    client: reqwest::Client,
+    url: String,
+    breaker: Arc<Mutex<CircuitBreaker>>,
+}
+
+impl ConvexHttp {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            breaker: Arc::new(Mutex::new(CircuitBreaker::default())),
+        })
+    }
+
+    /// Check that the deployment answers, without running any function
+    pub async fn ping(&self) -> Result<(), String> {
+        self.client
+            .get(format!("{}/version", self.url))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Whether calls are currently paused after repeated failures
+    pub fn is_circuit_open(&self) -> bool {
+        self.breaker().check().is_err()
+    }
+
+    /// Run a query, e.g. `functions/chapters:list`
+    ///
+    /// `auth` is a user's `Authorization` header value, forwarded so the
+    /// function runs as that user. Queries are retried whenever Convex fails.
+    pub async fn query(
+        &self,
+        path: &str,
+        args: Value,
+        auth: Option<&str>,
+    ) -> Result<Value, ConvexError> {
+        self.call("query", path, args, auth, true).await
+    }
+
+    /// Run a mutation, see [`ConvexHttp::query`]
+    ///
+    /// Mutations are only retried when they never reached Convex, since one
+    /// that timed out may already have run.
+    pub async fn mutation(
+        &self,
+        path: &str,
+        args: Value,
+        auth: Option<&str>,
+    ) -> Result<Value, ConvexError> {
+        self.call("mutation", path, args, auth, false).await
+    }
+
+    /// Upload a file to a URL from `files:generateUploadUrl`, returning its storage id
+    ///
+    /// Uploads are not retried, since each URL accepts one upload.
+    pub async fn upload(
+        &self,
+        upload_url: &str,
+        bytes: Vec<u8>,
+        content_type: &str,
+    ) -> Result<String, ConvexError> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Uploaded {
+            storage_id: String,
+        }
+
+        self.breaker().check()?;
+        let result = self
+            .client
+            .post(upload_url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(bytes)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        self.breaker()
+            .record(result.as_ref().is_err_and(|e| !e.is_status()));
+
+        result
+            .map_err(|e| ConvexError::Unavailable(e.to_string()))?
+            .json::<Uploaded>()
+            .await
+            .map(|uploaded| uploaded.storage_id)
+            .map_err(|e| ConvexError::InvalidResponse(e.to_string()))
+    }
+
+    /// Download a stored file from a URL returned by `ctx.storage.getUrl`
+    pub async fn download(&self, url: &str) -> Result<Vec<u8>, ConvexError> {
+        self.breaker().check()?;
+        let result = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        self.breaker()
+            .record(result.as_ref().is_err_and(|e| !e.is_status()));
+
+        result
+            .map_err(|e| ConvexError::Unavailable(e.to_string()))?
+            .bytes()
+            .await
+            .map(|bytes| bytes.to_vec())
+            .map_err(|e| ConvexError::Unavailable(e.to_string()))
+    }
+
+    fn breaker(&self) -> std::sync::MutexGuard<'_, CircuitBreaker> {
+        self.breaker.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn call(
+        &self,
+        kind: &str,
+        path: &str,
+        args: Value,
+        auth: Option<&str>,
+        idempotent: bool,
+    ) -> Result<Value, ConvexError> {
+        let body = json!({ "path": path, "args": args, "format": "json" });
+        let mut backoff = BASE_BACKOFF;
+
+        for attempt in 1.. {
+            self.breaker().check()?;
+
+            let result = self.send(kind, &body, auth).await;
+            let outage = result.as_ref().is_err_and(ConvexError::is_outage);
+            self.breaker().record(outage);
+
+            match result {
+                Err(e) if attempt < MAX_ATTEMPTS && e.is_retryable(idempotent) => {
+                    tracing::debug!(
+                        "Retrying Convex {} {} in {:?} after attempt {}: {}",
+                        kind,
+                        path,
+                        backoff,
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                result => return result,
+            }
+        }
+        unreachable!("the last attempt always returns")
+    }
+
+    async fn send(
+        &self,
+        kind: &str,
+        body: &Value,
+        auth: Option<&str>,
+    ) -> Result<Value, ConvexError> {
+        let mut request = self
+            .client
+            .post(format!("{}/api/{}", self.url, kind))
+            .json(body);
+        if let Some(auth) = auth {
+            request = request.header(reqwest::header::AUTHORIZATION, auth);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            if e.is_connect() {
+                ConvexError::Unreachable(e.to_string())
+            } else {
+                ConvexError::Unavailable(e.to_string())
+            }
+        })?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ConvexError::Unavailable(e.to_string()))?;
+
+        // Function errors come back as JSON with an error status, so read the body regardless
+        match serde_json::from_str(&text) {
+            Ok(ConvexResult::Success { value }) => Ok(value),
+            Ok(ConvexResult::Error { error_message }) => Err(ConvexError::Function(error_message)),
+            Err(_) if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => {
+                Err(ConvexError::Unavailable(format!("HTTP {}", status)))
+            }
+            Err(e) => Err(ConvexError::InvalidResponse(e.to_string())),
+        }
+    }
+}
+
+/// Calls made as a signed-in user, so Convex applies their permissions
+pub struct UserConvex<'a> {
+    convex: &'a ConvexHttp,
+    user: &'a AuthUser,
+}
+
+impl<'a> UserConvex<'a> {
+    /// Calls to the deployment holding the user's data
+    pub fn for_user(user: &'a AuthUser) -> Result<Self, Error> {
+        let convex = user.session.convex()?;
+
+        Ok(Self { convex, user })
+    }
+
+    /// Run a query and read its result as `T`
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        args: Value,
+    ) -> Result<T, ConvexError> {
+        let value = self
+            .convex
+            .query(path, args, Some(self.user.authorization()))
+            .await?;
+        parse(value)
+    }
+
+    /// Run a mutation and read its result as `T`
+    pub async fn mutation<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        args: Value,
+    ) -> Result<T, ConvexError> {
+        let value = self
+            .convex
+            .mutation(path, args, Some(self.user.authorization()))
+            .await?;
+        parse(value)
+    }
+
+    /// Store a file in Convex as the user, returning its storage id
+    pub async fn store_file(
+        &self,
+        bytes: Vec<u8>,
+        content_type: &str,
+    ) -> Result<String, ConvexError> {
+        let upload_url: String = self
+            .mutation("functions/files:generateUploadUrl", json!({}))
+            .await?;
+        self.convex.upload(&upload_url, bytes, content_type).await
+    }
+
+    /// Download a file the user can see, from a URL returned by Convex
+    pub async fn fetch_file(&self, url: &str) -> Result<Vec<u8>, ConvexError> {
+        self.convex.download(url).await
+    }
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, ConvexError> {
+    serde_json::from_value(value).map_err(|e| ConvexError::InvalidResponse(e.to_string()))
+}
@@ -0,0 +1,470 @@
+//! Checks of every subsystem the server depends on, run once at startup
+//!
+//! The model, voice embeddings, espeak-ng, pronunciation dictionaries, aligner,
+//! and Convex are checked before the server binds, and the results logged as a
+//! table. With fail-fast enabled a failed required check stops the server from
+//! starting, rather than the first request finding the subsystem missing.
+
+use std::env;
+use std::fs::File;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use ipa_navigator_kokoro::{
+    constants::ASSETS_PATH, espeak::check_espeak, manifest::VoiceManifest, voices::ALL_VOICES,
+};
+use ipa_navigator_mfa::{
+    aligner::{AlignerBackend, get_aligner},
+    container::{CONTAINER_MANAGER, ContainerState},
+    docker::{MfaDialect, unmanaged_mfa_version, uses_managed_container},
+};
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::convex::CONVEX;
+
+/// Longest wait for any one check when `STARTUP_CHECK_TIMEOUT_SECS` is not set
+const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Whether failed checks stop the server, and how long each may take
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupPolicy {
+    /// Refuse to start when a required subsystem fails its check
+    pub fail_fast: bool,
+    pub check_timeout: Duration,
+}
+
+impl StartupPolicy {
+    /// Read `STARTUP_FAIL_FAST`, which defaults to on when `APP_ENV` is "production",
+    /// and `STARTUP_CHECK_TIMEOUT_SECS`
+    pub fn from_env() -> Self {
+        let check_timeout = env::var("STARTUP_CHECK_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs| secs > 0)
+            .map_or(DEFAULT_CHECK_TIMEOUT, Duration::from_secs);
+
+        Self {
+            fail_fast: fail_fast(
+                env::var("STARTUP_FAIL_FAST").ok().as_deref(),
+                env::var("APP_ENV").ok().as_deref(),
+            ),
+            check_timeout,
+        }
+    }
+}
+
+/// Whether to fail fast given `STARTUP_FAIL_FAST` and `APP_ENV`, the first winning if it is a boolean
+fn fail_fast(setting: Option<&str>, app_env: Option<&str>) -> bool {
+    match setting.map(|s| s.trim().to_lowercase()).as_deref() {
+        Some("1" | "true") => true,
+        Some("0" | "false") => false,
+        _ => app_env.is_some_and(|env| env.trim().eq_ignore_ascii_case("production")),
+    }
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticStatus {
+    Ok,
+    /// Usable, but partly broken
+    Warning,
+    Failed,
+    /// Not configured, so not checked
+    Skipped,
+}
+
+impl DiagnosticStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticStatus::Ok => "ok",
+            DiagnosticStatus::Warning => "warning",
+            DiagnosticStatus::Failed => "failed",
+            DiagnosticStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Result of checking one subsystem
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub subsystem: &'static str,
+    /// Whether the server is unusable without the subsystem
+    pub required: bool,
+    pub status: DiagnosticStatus,
+    pub detail: String,
+    pub millis: u64,
+}
+
+/// Results of every startup check
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    pub checks: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Required subsystems that failed their check
+    pub fn blocking(&self) -> Vec<&Diagnostic> {
+        self.checks
+            .iter()
+            .filter(|check| check.required && check.status == DiagnosticStatus::Failed)
+            .collect()
+    }
+
+    /// The results as an aligned plain-text table, one row per subsystem
+    pub fn table(&self) -> String {
+        let mut table = format!(
+            "{:<12} {:<8} {:<8} {:>8}  {}",
+            "subsystem", "status", "required", "time", "detail"
+        );
+        for check in &self.checks {
+            table.push_str(&format!(
+                "\n{:<12} {:<8} {:<8} {:>5} ms  {}",
+                check.subsystem,
+                check.status.as_str(),
+                if check.required { "yes" } else { "no" },
+                check.millis,
+                check.detail
+            ));
+        }
+        table
+    }
+
+    /// Log the table, then each failure and warning on its own line
+    pub fn log(&self) {
+        for line in self.table().lines() {
+            info!("{}", line);
+        }
+        for check in &self.checks {
+            match check.status {
+                DiagnosticStatus::Failed if check.required => {
+                    error!("{} check failed: {}", check.subsystem, check.detail)
+                }
+                DiagnosticStatus::Failed | DiagnosticStatus::Warning => {
+                    warn!("{} check: {}", check.subsystem, check.detail)
+                }
+                DiagnosticStatus::Ok | DiagnosticStatus::Skipped => {}
+            }
+        }
+    }
+}
+
+/// Check every subsystem at once, each within the policy's timeout
+///
+/// Also points espeak-ng at its data directory, so it must run before anything phonemizes.
+pub async fn run_startup_checks(config: &Config, policy: &StartupPolicy) -> Diagnostics {
+    let timeout = policy.check_timeout;
+    let espeak_data = config.espeak_data.clone();
+
+    let (model, voices, espeak, dictionaries, aligner, convex) = tokio::join!(
+        blocking("model", true, timeout, check_model),
+        blocking("voices", true, timeout, check_voices),
+        blocking("espeak", true, timeout, move || {
+            match check_espeak(&espeak_data) {
+                Ok(dir) => (DiagnosticStatus::Ok, format!("Data at {}", dir.display())),
+                Err(e) => (DiagnosticStatus::Failed, e.to_string()),
+            }
+        }),
+        blocking("dictionaries", true, timeout, check_dictionaries),
+        blocking("aligner", true, timeout, check_aligner),
+        timed("convex", false, timeout, check_convex()),
+    );
+
+    Diagnostics {
+        checks: vec![model, voices, espeak, dictionaries, aligner, convex],
+    }
+}
+
+/// Run a blocking check on the blocking pool
+async fn blocking(
+    subsystem: &'static str,
+    required: bool,
+    timeout: Duration,
+    check: impl FnOnce() -> (DiagnosticStatus, String) + Send + 'static,
+) -> Diagnostic {
+    let check = async move {
+        tokio::task::spawn_blocking(check)
+            .await
+            .unwrap_or_else(|e| (DiagnosticStatus::Failed, format!("Check panicked: {}", e)))
+    };
+    timed(subsystem, required, timeout, check).await
+}
+
+/// Run a check with a timeout and record how long it took
+async fn timed(
+    subsystem: &'static str,
+    required: bool,
+    timeout: Duration,
+    check: impl Future<Output = (DiagnosticStatus, String)>,
+) -> Diagnostic {
+    let started = Instant::now();
+    let (status, detail) = tokio::time::timeout(timeout, check)
+        .await
+        .unwrap_or_else(|_| {
+            let message = format!("Timed out after {}s", timeout.as_secs());
+            (DiagnosticStatus::Failed, message)
+        });
+
+    Diagnostic {
+        subsystem,
+        required,
+        status,
+        detail,
+        millis: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Check the ONNX model is present and, if `KOKORO_MODEL_SHA256` is set, has that digest
+fn check_model() -> (DiagnosticStatus, String) {
+    let path = PathBuf::from(format!("{}/Kokoro/model.onnx", *ASSETS_PATH));
+    let expected = env::var("KOKORO_MODEL_SHA256")
+        .ok()
+        .filter(|digest| !digest.trim().is_empty());
+
+    match verify_file(&path, expected.as_deref()) {
+        Ok(detail) => (DiagnosticStatus::Ok, detail),
+        Err(e) => (DiagnosticStatus::Failed, e),
+    }
+}
+
+/// Check a file exists and is not empty, then compare its SHA-256 with `expected` if given
+fn verify_file(path: &Path, expected: Option<&str>) -> Result<String, String> {
+    let bytes = path
+        .metadata()
+        .map_err(|e| format!("{} is missing: {}", path.display(), e))?
+        .len();
+    if bytes == 0 {
+        return Err(format!("{} is empty", path.display()));
+    }
+    let size = format!("{} MiB", bytes / (1024 * 1024));
+
+    let Some(expected) = expected else {
+        return Ok(format!("{}, {} (digest not checked)", path.display(), size));
+    };
+
+    let mut hasher = Sha256::new();
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let digest = format!("{:x}", hasher.finalize());
+
+    if !digest.eq_ignore_ascii_case(expected.trim()) {
+        return Err(format!(
+            "{} has SHA-256 {}, expected {}",
+            path.display(),
+            digest,
+            expected.trim()
+        ));
+    }
+    Ok(format!("{}, {}, SHA-256 matches", path.display(), size))
+}
+
+/// Check every voice file against the manifest, warning if only some pass
+fn check_voices() -> (DiagnosticStatus, String) {
+    let manifest = match VoiceManifest::load() {
+        Ok(manifest) => manifest,
+        Err(e) => return (DiagnosticStatus::Failed, e.to_string()),
+    };
+
+    let failed: Vec<String> = ALL_VOICES
+        .iter()
+        .filter_map(|voice| {
+            let result = std::fs::read(voice.path())
+                .map_err(|e| format!("{}: {}", voice.name(), e))
+                .and_then(|data| {
+                    manifest
+                        .verify(voice.file_name(), &data)
+                        .map_err(|e| format!("{}: {}", voice.name(), e))
+                });
+            result.err()
+        })
+        .collect();
+
+    let total = ALL_VOICES.len();
+    if failed.is_empty() {
+        (DiagnosticStatus::Ok, format!("{} voices verified", total))
+    } else {
+        let status = if failed.len() == total {
+            DiagnosticStatus::Failed
+        } else {
+            DiagnosticStatus::Warning
+        };
+        let detail = format!(
+            "{} of {} voices failed: {}",
+            failed.len(),
+            total,
+            failed.join("; ")
+        );
+        (status, detail)
+    }
+}
+
+/// Check the pronunciation dictionary of every dialect is present
+fn check_dictionaries() -> (DiagnosticStatus, String) {
+    let missing: Vec<String> = MfaDialect::ALL
+        .iter()
+        .map(|dialect| dialect.dictionary_path())
+        .filter(|path| path.metadata().map_or(true, |meta| meta.len() == 0))
+        .map(|path| path.display().to_string())
+        .collect();
+
+    if missing.is_empty() {
+        let detail = format!("{} dictionaries found", MfaDialect::ALL.len());
+        (DiagnosticStatus::Ok, detail)
+    } else {
+        let detail = format!("Missing or empty: {}", missing.join(", "));
+        (DiagnosticStatus::Failed, detail)
+    }
+}
+
+/// Check the configured aligner can run: MFA, through its container if managed, or the CTC model
+///
+/// A stopped MFA container passes, since it is started on the first alignment.
+/// Without a managed container, `mfa version` is run in the aligner's environment.
+fn check_aligner() -> (DiagnosticStatus, String) {
+    match AlignerBackend::from_env() {
+        AlignerBackend::Ctc => match get_aligner() {
+            Ok(_) => (DiagnosticStatus::Ok, "CTC model loaded".to_string()),
+            Err(e) => (DiagnosticStatus::Failed, format!("{:#}", e)),
+        },
+        AlignerBackend::Mfa if !uses_managed_container() => match unmanaged_mfa_version() {
+            Ok(version) => (DiagnosticStatus::Ok, format!("MFA {} found", version)),
+            Err(e) => (DiagnosticStatus::Failed, format!("{:#}", e)),
+        },
+        AlignerBackend::Mfa => {
+            if let Err(e) = CONTAINER_MANAGER.validate_mounts() {
+                return (DiagnosticStatus::Failed, format!("{:#}", e));
+            }
+            let name = &CONTAINER_MANAGER.config().name;
+            match CONTAINER_MANAGER.state() {
+                Ok(ContainerState::Running) => match CONTAINER_MANAGER.health_check() {
+                    Ok(true) => (
+                        DiagnosticStatus::Ok,
+                        format!("Container {} is healthy", name),
+                    ),
+                    Ok(false) => (
+                        DiagnosticStatus::Failed,
+                        format!("Container {} fails its health check", name),
+                    ),
+                    Err(e) => (DiagnosticStatus::Failed, format!("{:#}", e)),
+                },
+                Ok(state) => (
+                    DiagnosticStatus::Ok,
+                    format!(
+                        "Container {} is {}, started on the first alignment",
+                        name,
+                        state.as_str()
+                    ),
+                ),
+                Err(e) => (
+                    DiagnosticStatus::Failed,
+                    format!("Container runtime unreachable: {:#}", e),
+                ),
+            }
+        }
+    }
+}
+
+/// Check the Convex deployment answers, if one is configured
+async fn check_convex() -> (DiagnosticStatus, String) {
+    match CONVEX.as_ref() {
+        None => (
+            DiagnosticStatus::Skipped,
+            "CONVEX_DEPLOYMENT_URL is not set".to_string(),
+        ),
+        Some(convex) => match convex.ping().await {
+            Ok(()) => (DiagnosticStatus::Ok, "Deployment answers".to_string()),
+            Err(e) => (
+                DiagnosticStatus::Failed,
+                format!("Convex unreachable: {}", e),
+            ),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(subsystem: &'static str, required: bool, status: DiagnosticStatus) -> Diagnostic {
+        Diagnostic {
+            subsystem,
+            required,
+            status,
+            detail: "detail".to_string(),
+            millis: 12,
+        }
+    }
+
+    #[test]
+    fn test_fail_fast_defaults_on_in_production() {
+        assert!(!fail_fast(None, None));
+        assert!(!fail_fast(None, Some("development")));
+        assert!(fail_fast(None, Some("Production")));
+        assert!(fail_fast(Some("true"), None));
+        assert!(!fail_fast(Some("0"), Some("production")));
+        // A setting that is not a boolean leaves the default
+        assert!(fail_fast(Some("maybe"), Some("production")));
+    }
+
+    #[test]
+    fn test_only_required_failures_block() {
+        let diagnostics = Diagnostics {
+            checks: vec![
+                diagnostic("model", true, DiagnosticStatus::Ok),
+                diagnostic("voices", true, DiagnosticStatus::Warning),
+                diagnostic("aligner", true, DiagnosticStatus::Failed),
+                diagnostic("convex", false, DiagnosticStatus::Failed),
+            ],
+        };
+
+        let blocking: Vec<&str> = diagnostics
+            .blocking()
+            .iter()
+            .map(|check| check.subsystem)
+            .collect();
+        assert_eq!(blocking, ["aligner"]);
+
+        let table = diagnostics.table();
+        assert_eq!(table.lines().count(), 5);
+        assert!(table.lines().next().unwrap().starts_with("subsystem"));
+        assert!(table.contains("convex       failed   no          12 ms  detail"));
+    }
+
+    #[test]
+    fn test_verify_file_checks_presence_and_digest() {
+        let dir = env::temp_dir().join(format!("diagnostics-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.onnx");
+
+        assert!(verify_file(&path, None).unwrap_err().contains("missing"));
+
+        std::fs::write(&path, b"").unwrap();
+        assert!(verify_file(&path, None).unwrap_err().contains("empty"));
+
+        std::fs::write(&path, b"test").unwrap();
+        let digest = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        assert!(verify_file(&path, None).unwrap().contains("not checked"));
+        assert!(
+            verify_file(&path, Some(digest))
+                .unwrap()
+                .contains("matches")
+        );
+        assert!(
+            verify_file(&path, Some(&digest.to_uppercase()))
+                .unwrap()
+                .contains("matches")
+        );
+        assert!(
+            verify_file(&path, Some(&"0".repeat(64)))
+                .unwrap_err()
+                .contains("expected")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+//! Backends doing the heavy work behind the synthesis and assessment handlers
+//!
+//! Handlers reach the Kokoro model and the aligner through the [`TtsEngine`]
+//! and [`AssessmentEngine`] traits in [`AppState`], so tests can swap in
+//! lightweight engines that need neither the ONNX model nor Docker.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::extract::FromRef;
+use ipa_navigator_kokoro::{
+    error::TtsError, normalize::NormalizeOptions, tts::Synthesis, voices::VoiceType,
+};
+use ipa_navigator_mfa::{
+    aligner::{AlignerBackend, get_aligner},
+    asr::{TranscriptCheck, verify_transcript},
+    docker::MfaDialect,
+    mfa_parser::MfaSegment,
+};
+
+use crate::handlers::tts::get_tts;
+use crate::tenants::Tenants;
+
+/// Text-to-speech
+pub trait TtsEngine: Send + Sync {
+    /// Synthesize text, leaving out sentences that would not finish before `deadline`
+    fn synthesize_until(
+        &self,
+        text: &str,
+        voice: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+        deadline: Instant,
+    ) -> Result<Synthesis, TtsError>;
+
+    /// Synthesize all of the text
+    fn synthesize(
+        &self,
+        text: &str,
+        voice: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+    ) -> Result<Vec<f32>, TtsError>;
+
+    /// Whether synthesizing the text would be answered from the cache
+    fn is_cached(
+        &self,
+        text: &str,
+        voice: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+    ) -> Result<bool, TtsError>;
+
+    /// Synthesize token IDs a client tokenized itself, checking them against the vocabulary
+    fn synthesize_tokens(
+        &self,
+        tokens: &[i64],
+        voice: &VoiceType,
+        speed: f32,
+    ) -> Result<Vec<f32>, TtsError>;
+}
+
+/// Transcript verification and forced alignment of recordings
+pub trait AssessmentEngine: Send + Sync {
+    /// Short name of the alignment backend, used in logs and assessment cache keys
+    fn name(&self) -> &'static str;
+
+    /// Check what the recording says against the transcript, or `None` if no recognizer is configured
+    fn verify(&self, audio: &[u8], transcript: &str) -> anyhow::Result<Option<TranscriptCheck>>;
+
+    /// Align a recording against its transcript
+    fn align(
+        &self,
+        audio: &[u8],
+        transcript: &str,
+        dialect: MfaDialect,
+    ) -> anyhow::Result<Vec<MfaSegment>>;
+}
+
+/// The shared Kokoro model, loaded on first use
+pub struct Kokoro;
+
+impl TtsEngine for Kokoro {
+    fn synthesize_until(
+        &self,
+        text: &str,
+        voice: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+        deadline: Instant,
+    ) -> Result<Synthesis, TtsError> {
+        get_tts()?.process_tts_until(text, voice, speed, options, deadline)
+    }
+
+    fn synthesize(
+        &self,
+        text: &str,
+        voice: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+    ) -> Result<Vec<f32>, TtsError> {
+        let audio = get_tts()?.process_tts_with(text, voice, speed, options)?;
+        Ok(audio.iter().copied().collect())
+    }
+
+    fn is_cached(
+        &self,
+        text: &str,
+        voice: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+    ) -> Result<bool, TtsError> {
+        get_tts()?.is_cached(text, voice, speed, options)
+    }
+
+    fn synthesize_tokens(
+        &self,
+        tokens: &[i64],
+        voice: &VoiceType,
+        speed: f32,
+    ) -> Result<Vec<f32>, TtsError> {
+        let audio = get_tts()?.synthesize_tokens(tokens, voice, speed)?;
+        Ok(audio.iter().copied().collect())
+    }
+}
+
+/// The recognizer and aligner selected by the environment
+pub struct Mfa;
+
+impl AssessmentEngine for Mfa {
+    fn name(&self) -> &'static str {
+        AlignerBackend::from_env().as_str()
+    }
+
+    fn verify(&self, audio: &[u8], transcript: &str) -> anyhow::Result<Option<TranscriptCheck>> {
+        verify_transcript(audio, transcript)
+    }
+
+    fn align(
+        &self,
+        audio: &[u8],
+        transcript: &str,
+        dialect: MfaDialect,
+    ) -> anyhow::Result<Vec<MfaSegment>> {
+        get_aligner()?.align(audio, transcript, dialect)
+    }
+}
+
+/// Engines and tenants shared by the handlers
+#[derive(Clone)]
+pub struct AppState {
+    pub tts: Arc<dyn TtsEngine>,
+    pub assessment: Arc<dyn AssessmentEngine>,
+    pub tenants: Arc<Tenants>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            tts: Arc::new(Kokoro),
+            assessment: Arc::new(Mfa),
+            tenants: Arc::new(Tenants::from_env()),
+        }
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn TtsEngine> {
+    fn from_ref(state: &AppState) -> Self {
+        state.tts.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn AssessmentEngine> {
+    fn from_ref(state: &AppState) -> Self {
+        state.assessment.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Tenants> {
+    fn from_ref(state: &AppState) -> Self {
+        state.tenants.clone()
+    }
+}
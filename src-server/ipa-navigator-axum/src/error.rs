@@ -1,9 +1,11 @@
 use axum::{
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use thiserror::Error;
 
+use crate::scheduler::QueueFull;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Not found: {0}")]
@@ -12,8 +14,35 @@ pub enum Error {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Internal server error: {0}")]
     InternalServerError(String),
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    #[error("Service busy: {message}")]
+    Busy {
+        message: String,
+        retry_after_secs: u64,
+    },
+}
+
+impl From<QueueFull> for Error {
+    fn from(full: QueueFull) -> Self {
+        Error::Busy {
+            message: format!(
+                "Too many {} TTS requests, try again later",
+                full.priority.as_str()
+            ),
+            retry_after_secs: full.retry_after_secs,
+        }
+    }
 }
 
 impl IntoResponse for Error {
@@ -21,7 +50,21 @@ impl IntoResponse for Error {
         let (status, error_message) = match self {
             Error::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             Error::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            Error::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            Error::Conflict(msg) => (StatusCode::CONFLICT, msg),
             Error::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            Error::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            Error::Busy {
+                message,
+                retry_after_secs,
+            } => {
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, retry_after_secs.to_string())],
+                    message,
+                )
+                    .into_response();
+            }
         };
 
         (status, error_message).into_response()
@@ -0,0 +1,373 @@
+//! Bulk export of recorded phoneme scores for research, as CSV or Parquet
+//!
+//! Rows are read from the job store a page at a time and encoded as they
+//! arrive, so an export of several months never holds more than one page in
+//! memory. Each row is one phoneme of an assessment:
+//!
+//! | Column          | CSV     | Parquet | Meaning                                                  |
+//! |-----------------|---------|---------|----------------------------------------------------------|
+//! | `id`            | integer | INT64   | Row id, increasing in recording order                    |
+//! | `assessment_id` | text    | UTF8    | Random id shared by the phonemes of one assessment       |
+//! | `recorded_at`   | integer | INT64   | When the assessment finished, in ms since the Unix epoch |
+//! | `dictionary`    | text    | UTF8    | Pronunciation dictionary, e.g. `english_us_mfa`          |
+//! | `strictness`    | text    | UTF8    | `beginner`, `intermediate`, or `strict`                  |
+//! | `engine`        | text    | UTF8    | Assessment backend, e.g. `mfa`                           |
+//! | `position`      | integer | INT64   | Index of the phoneme within the assessment, from 0       |
+//! | `expected`      | text    | UTF8    | Dictionary phoneme, empty if the learner added one       |
+//! | `actual`        | text    | UTF8    | Phoneme heard, empty if the learner left it out          |
+//! | `score`         | decimal | DOUBLE  | Score of the phoneme, from 0 to 1                        |
+//! | `start_time`    | decimal | DOUBLE  | Start of the phoneme, in seconds from the recording start |
+//! | `end_time`      | decimal | DOUBLE  | End of the phoneme, in seconds from the recording start  |
+//!
+//! Nothing identifying the learner or their recording is stored or exported.
+
+use std::io::{self, Write};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use anyhow::{Context, Result};
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use axum::body::Body;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::error;
+
+use crate::store::{JobStore, PhonemeResultRecord};
+
+/// Rows read from the store and encoded at a time, and rows per Parquet row group
+pub const EXPORT_PAGE_ROWS: u32 = 10_000;
+
+/// Encoded pages buffered ahead of a slow client
+const EXPORT_BUFFER_PAGES: usize = 2;
+
+/// Column names, in export order
+pub const COLUMNS: [&str; 12] = [
+    "id",
+    "assessment_id",
+    "recorded_at",
+    "dictionary",
+    "strictness",
+    "engine",
+    "position",
+    "expected",
+    "actual",
+    "score",
+    "start_time",
+    "end_time",
+];
+
+static PARQUET_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
+    let field = |index: usize, data_type| Field::new(COLUMNS[index], data_type, false);
+    Arc::new(Schema::new(vec![
+        field(0, DataType::Int64),
+        field(1, DataType::Utf8),
+        field(2, DataType::Int64),
+        field(3, DataType::Utf8),
+        field(4, DataType::Utf8),
+        field(5, DataType::Utf8),
+        field(6, DataType::Int64),
+        field(7, DataType::Utf8),
+        field(8, DataType::Utf8),
+        field(9, DataType::Float64),
+        field(10, DataType::Float64),
+        field(11, DataType::Float64),
+    ]))
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "parquet" => Some(ExportFormat::Parquet),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Encodes pages of rows into consecutive chunks of one file
+enum Encoder {
+    Csv { header_written: bool },
+    // Boxed, since the writer holds its schema and properties inline
+    Parquet(Box<ArrowWriter<SharedBuffer>>, SharedBuffer),
+}
+
+impl Encoder {
+    fn new(format: ExportFormat) -> Result<Self> {
+        match format {
+            ExportFormat::Csv => Ok(Encoder::Csv {
+                header_written: false,
+            }),
+            ExportFormat::Parquet => {
+                let buffer = SharedBuffer::default();
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .set_max_row_group_size(EXPORT_PAGE_ROWS as usize)
+                    .build();
+                let writer =
+                    ArrowWriter::try_new(buffer.clone(), PARQUET_SCHEMA.clone(), Some(properties))
+                        .context("Failed to start the Parquet file")?;
+                Ok(Encoder::Parquet(Box::new(writer), buffer))
+            }
+        }
+    }
+
+    /// Encode a page of rows, returning the bytes that complete
+    fn page(&mut self, rows: &[PhonemeResultRecord]) -> Result<Vec<u8>> {
+        match self {
+            Encoder::Csv { header_written } => {
+                let mut csv = csv::Writer::from_writer(Vec::new());
+                if !*header_written {
+                    csv.write_record(COLUMNS)?;
+                    *header_written = true;
+                }
+                for row in rows {
+                    csv.write_record([
+                        row.id.to_string().as_str(),
+                        &row.assessment_id,
+                        &row.recorded_at.to_string(),
+                        &row.dictionary,
+                        &row.strictness,
+                        &row.engine,
+                        &row.position.to_string(),
+                        &row.expected,
+                        &row.actual,
+                        &row.score.to_string(),
+                        &row.start_time.to_string(),
+                        &row.end_time.to_string(),
+                    ])?;
+                }
+                csv.into_inner().context("Failed to write CSV rows")
+            }
+            Encoder::Parquet(writer, buffer) => {
+                if !rows.is_empty() {
+                    writer.write(&record_batch(rows)?)?;
+                    // Each page is its own row group, so only one is ever buffered
+                    writer.flush()?;
+                }
+                Ok(buffer.take())
+            }
+        }
+    }
+
+    /// Bytes ending the file
+    fn finish(mut self) -> Result<Vec<u8>> {
+        match self {
+            // An empty export still names its columns
+            Encoder::Csv { .. } => self.page(&[]),
+            Encoder::Parquet(writer, buffer) => {
+                writer
+                    .close()
+                    .context("Failed to finish the Parquet file")?;
+                Ok(buffer.take())
+            }
+        }
+    }
+}
+
+fn record_batch(rows: &[PhonemeResultRecord]) -> Result<RecordBatch> {
+    let ints = |value: fn(&PhonemeResultRecord) -> i64| -> ArrayRef {
+        Arc::new(Int64Array::from_iter_values(rows.iter().map(value)))
+    };
+    let strings = |value: fn(&PhonemeResultRecord) -> &str| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(rows.iter().map(value)))
+    };
+    let floats = |value: fn(&PhonemeResultRecord) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(rows.iter().map(value)))
+    };
+
+    RecordBatch::try_new(
+        PARQUET_SCHEMA.clone(),
+        vec![
+            ints(|row| row.id),
+            strings(|row| &row.assessment_id),
+            ints(|row| row.recorded_at),
+            strings(|row| &row.dictionary),
+            strings(|row| &row.strictness),
+            strings(|row| &row.engine),
+            ints(|row| row.position),
+            strings(|row| &row.expected),
+            strings(|row| &row.actual),
+            floats(|row| row.score),
+            floats(|row| row.start_time),
+            floats(|row| row.end_time),
+        ],
+    )
+    .context("Failed to build a Parquet row group")
+}
+
+/// Output of the Parquet writer, drained after each row group
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        self.0
+            .lock()
+            .map(|mut buffer| std::mem::take(&mut *buffer))
+            .unwrap_or_default()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .map_err(|_| io::Error::other("Parquet buffer poisoned"))?
+            .extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stream the phoneme results recorded from `from` up to `to`, in milliseconds since the Unix epoch
+///
+/// Pages are read and encoded in the background while earlier ones are sent.
+/// A failure part way ends the stream with an error, so the client sees a
+/// truncated download rather than a file that looks complete.
+pub fn export_body(store: &'static JobStore, from: i64, to: i64, format: ExportFormat) -> Body {
+    let (sender, receiver) = mpsc::channel::<io::Result<Vec<u8>>>(EXPORT_BUFFER_PAGES);
+
+    tokio::spawn(async move {
+        if let Err(e) = send_export(store, from, to, format, &sender).await {
+            error!("Export failed: {:#}", e);
+            let _ = sender.send(Err(io::Error::other(e.to_string()))).await;
+        }
+    });
+
+    Body::from_stream(ReceiverStream::new(receiver))
+}
+
+async fn send_export(
+    store: &JobStore,
+    from: i64,
+    to: i64,
+    format: ExportFormat,
+    sender: &mpsc::Sender<io::Result<Vec<u8>>>,
+) -> Result<()> {
+    let mut encoder = Encoder::new(format)?;
+    let mut after_id = 0;
+
+    loop {
+        let rows = store
+            .phoneme_results_page(from, to, after_id, EXPORT_PAGE_ROWS)
+            .await
+            .context("Failed to read phoneme results")?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        let chunk = encoder.page(&rows)?;
+        if sender.send(Ok(chunk)).await.is_err() {
+            // The client went away
+            return Ok(());
+        }
+        if rows.len() < EXPORT_PAGE_ROWS as usize {
+            break;
+        }
+    }
+
+    let _ = sender.send(Ok(encoder.finish()?)).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn row(id: i64, expected: &str, actual: &str) -> PhonemeResultRecord {
+        PhonemeResultRecord {
+            id,
+            assessment_id: "a1".to_string(),
+            recorded_at: 1_700_000_000_000,
+            dictionary: "english_us_mfa".to_string(),
+            strictness: "intermediate".to_string(),
+            engine: "mfa".to_string(),
+            position: id - 1,
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+            score: 0.5,
+            start_time: 0.1,
+            end_time: 0.2,
+        }
+    }
+
+    fn encode(format: ExportFormat, pages: &[&[PhonemeResultRecord]]) -> Vec<u8> {
+        let mut encoder = Encoder::new(format).unwrap();
+        let mut file = Vec::new();
+        for page in pages {
+            file.extend(encoder.page(page).unwrap());
+        }
+        file.extend(encoder.finish().unwrap());
+        file
+    }
+
+    #[test]
+    fn test_csv_pages_share_one_header() {
+        let csv = encode(
+            ExportFormat::Csv,
+            &[&[row(1, "θ", "s")], &[row(2, "a, b", "")]],
+        );
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].starts_with("1,a1,1700000000000,english_us_mfa,"));
+        assert!(lines[2].contains(",\"a, b\",,0.5,"));
+
+        let empty = encode(ExportFormat::Csv, &[]);
+        assert_eq!(String::from_utf8(empty).unwrap().trim(), COLUMNS.join(","));
+    }
+
+    #[test]
+    fn test_parquet_pages_form_one_file() {
+        let parquet = encode(
+            ExportFormat::Parquet,
+            &[&[row(1, "θ", "s"), row(2, "ɪ", "ɪ")], &[row(3, "", "ə")]],
+        );
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(parquet))
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 3);
+        assert_eq!(batches[0].schema(), *PARQUET_SCHEMA);
+        let expected = batches[0]
+            .column(7)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(expected.value(0), "θ");
+    }
+}
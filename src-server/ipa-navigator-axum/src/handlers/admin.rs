@@ -0,0 +1,695 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use ipa_navigator_kokoro::phonemizer::PHONEME_CACHE;
+use ipa_navigator_kokoro::voices::{
+    ALL_VOICES, CALIBRATION_GAIN_RANGE, CALIBRATION_SPEED_RANGE, VoiceCalibration, VoiceType,
+};
+use ipa_navigator_mfa::{
+    container::CONTAINER_MANAGER, scoring::reload_dictionaries, storage::STORAGE,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::audit;
+use crate::config::{CONFIG_MANAGER, RuntimeSettings, SettingChange, scoring_profile};
+use crate::engines::{AssessmentEngine, TtsEngine};
+use crate::error::Error;
+use crate::export::{ExportFormat, export_body};
+use crate::handlers::tts::{loaded_tts, parse_voice};
+use crate::limits::route_groups;
+use crate::media::mark_synthesis_modified;
+use crate::retention::{ArtifactKind, DeletionReason, RETENTION_POLICY, RetentionOutcome, enforce};
+use crate::selftest::{SelfTestReport, min_score_from_env, run_self_test};
+use crate::store::{self, JOB_STORE, JobStore, MAX_QUERY_LIMIT};
+
+/// Status of the managed MFA container
+#[derive(Debug, Serialize)]
+pub struct MfaStatusResponse {
+    /// Container runtime the container runs on
+    pub runtime: &'static str,
+    pub name: String,
+    pub image: String,
+    pub state: &'static str,
+    pub healthy: bool,
+    pub restart_count: u32,
+    pub last_error: Option<String>,
+}
+
+/// Handler reporting the state and health of the MFA container
+pub async fn mfa_status() -> Result<Json<MfaStatusResponse>, Error> {
+    let report = tokio::task::spawn_blocking(|| CONTAINER_MANAGER.report())
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Status check failed: {}", e)))?;
+
+    Ok(Json(MfaStatusResponse {
+        runtime: report.runtime.as_str(),
+        name: report.name,
+        image: report.image,
+        state: report.state.as_str(),
+        healthy: report.healthy,
+        restart_count: report.restart_count,
+        last_error: report.last_error,
+    }))
+}
+
+/// Resources re-read by a reload
+#[derive(Debug, Serialize)]
+pub struct ReloadResponse {
+    /// Pronunciation dictionaries that were cached and have been re-read
+    pub dictionaries: Vec<&'static str>,
+
+    /// Number of voice embeddings re-read, or none if TTS has not been loaded yet
+    pub voices: Option<usize>,
+}
+
+/// Handler re-reading dictionaries and voices from disk without a restart
+pub async fn reload() -> Result<Json<ReloadResponse>, Error> {
+    let (dictionaries, voices) = tokio::task::spawn_blocking(|| {
+        let dictionaries = reload_dictionaries().map_err(|e| {
+            Error::InternalServerError(format!("Failed to reload dictionaries: {}", e))
+        })?;
+
+        let voices = loaded_tts()
+            .map(|tts| tts.reload_voices())
+            .transpose()
+            .map_err(|e| Error::InternalServerError(format!("Failed to reload voices: {}", e)))?;
+
+        Ok::<_, Error>((dictionaries, voices))
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Reload failed: {}", e)))??;
+
+    if voices.is_some() {
+        mark_synthesis_modified();
+    }
+    audit::record(audit::ADMIN, "reload", None, &());
+
+    info!(
+        "Reloaded dictionaries {:?} and {:?} voices",
+        dictionaries, voices
+    );
+
+    Ok(Json(ReloadResponse {
+        dictionaries,
+        voices,
+    }))
+}
+
+/// Settings in effect, which the config file can change while serving
+#[derive(Debug, Serialize)]
+pub struct ConfigResponse {
+    /// The watched config file, if any
+    pub file: Option<String>,
+    pub tts_concurrency: usize,
+    pub assess_concurrency: usize,
+    pub assessment_cache_size: usize,
+    pub synthesis_cache_size: usize,
+
+    /// Origins browsers may call the API from, any if none
+    pub cors_origins: Option<Vec<String>>,
+
+    pub scoring_profile: &'static str,
+}
+
+/// Settings changed by a reload
+#[derive(Debug, Serialize)]
+pub struct ConfigReloadResponse {
+    pub changes: Vec<SettingChange>,
+    pub settings: ConfigResponse,
+}
+
+fn config_response(settings: RuntimeSettings) -> ConfigResponse {
+    ConfigResponse {
+        file: CONFIG_MANAGER.path().map(|path| path.display().to_string()),
+        tts_concurrency: settings.tts_concurrency,
+        assess_concurrency: settings.assess_concurrency,
+        assessment_cache_size: settings.assessment_cache_size,
+        synthesis_cache_size: settings.synthesis_cache_size.get(),
+        cors_origins: settings.cors_origins,
+        scoring_profile: settings.scoring_profile.as_str(),
+    }
+}
+
+/// Handler reporting the settings in effect
+pub async fn config() -> Json<ConfigResponse> {
+    Json(config_response(CONFIG_MANAGER.settings()))
+}
+
+/// Handler re-reading the config file now rather than waiting for it to be noticed
+pub async fn reload_config() -> Result<Json<ConfigReloadResponse>, Error> {
+    if CONFIG_MANAGER.path().is_none() {
+        return Err(Error::ServiceUnavailable(
+            "No config file is configured; set CONFIG_FILE".to_string(),
+        ));
+    }
+
+    let changes = CONFIG_MANAGER
+        .reload(audit::ADMIN)
+        .map_err(Error::BadRequest)?;
+
+    Ok(Json(ConfigReloadResponse {
+        changes,
+        settings: config_response(CONFIG_MANAGER.settings()),
+    }))
+}
+
+/// Calibration applied to one voice
+#[derive(Debug, Serialize)]
+pub struct VoiceCalibrationResponse {
+    pub voice: &'static str,
+
+    /// Multiplier applied to the requested speed
+    pub speed: f32,
+
+    /// Offset applied to the output level, in decibels
+    pub gain_db: f32,
+
+    /// Whether an operator has overridden the voice's default calibration
+    pub overridden: bool,
+}
+
+/// New calibration for a voice
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VoiceCalibrationRequest {
+    pub speed: f32,
+    pub gain_db: f32,
+}
+
+fn calibration_response(voice: VoiceType) -> VoiceCalibrationResponse {
+    let calibration = voice.calibration();
+    VoiceCalibrationResponse {
+        voice: voice.name(),
+        speed: calibration.speed,
+        gain_db: calibration.gain_db,
+        overridden: voice.has_calibration_override(),
+    }
+}
+
+/// Handler listing the calibration of every voice
+pub async fn voice_calibrations() -> Json<Vec<VoiceCalibrationResponse>> {
+    Json(
+        ALL_VOICES
+            .iter()
+            .copied()
+            .map(calibration_response)
+            .collect(),
+    )
+}
+
+/// Handler overriding a voice's default speed and gain
+pub async fn set_voice_calibration(
+    Path(voice): Path<String>,
+    Json(request): Json<VoiceCalibrationRequest>,
+) -> Result<Json<VoiceCalibrationResponse>, Error> {
+    let voice = parse_voice(&voice).map_err(Error::NotFound)?;
+
+    let calibration = VoiceCalibration {
+        speed: request.speed,
+        gain_db: request.gain_db,
+    };
+    if !calibration.is_valid() {
+        return Err(Error::BadRequest(format!(
+            "Speed must be between {} and {}, and gain between {} and {} dB",
+            CALIBRATION_SPEED_RANGE.start(),
+            CALIBRATION_SPEED_RANGE.end(),
+            CALIBRATION_GAIN_RANGE.start(),
+            CALIBRATION_GAIN_RANGE.end()
+        )));
+    }
+
+    voice.set_calibration(Some(calibration));
+    mark_synthesis_modified();
+    audit::record(
+        audit::ADMIN,
+        "voice_calibration.set",
+        Some(voice.name()),
+        &request,
+    );
+    info!("Calibrated voice {} to {:?}", voice.name(), calibration);
+
+    Ok(Json(calibration_response(voice)))
+}
+
+/// Handler restoring a voice's default calibration
+pub async fn reset_voice_calibration(
+    Path(voice): Path<String>,
+) -> Result<Json<VoiceCalibrationResponse>, Error> {
+    let voice = parse_voice(&voice).map_err(Error::NotFound)?;
+
+    voice.set_calibration(None);
+    mark_synthesis_modified();
+    audit::record(
+        audit::ADMIN,
+        "voice_calibration.reset",
+        Some(voice.name()),
+        &(),
+    );
+    info!("Restored default calibration of voice {}", voice.name());
+
+    Ok(Json(calibration_response(voice)))
+}
+
+/// Jobs returned by the job history endpoint when no limit is given
+const DEFAULT_JOB_LIMIT: u32 = 100;
+
+/// Query for the job history
+#[derive(Debug, Deserialize)]
+pub struct JobHistoryQuery {
+    /// Milliseconds since the Unix epoch; jobs created earlier are left out
+    pub since: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// A recorded job
+#[derive(Debug, Serialize)]
+pub struct JobHistoryResponse {
+    pub id: String,
+    pub kind: String,
+    pub status: String,
+    pub created_at: i64,
+    pub finished_at: Option<i64>,
+    pub duration_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Handler listing jobs recorded in the job store, oldest first
+pub async fn job_history(
+    Query(query): Query<JobHistoryQuery>,
+) -> Result<Json<Vec<JobHistoryResponse>>, Error> {
+    let store = job_store()?;
+    let limit = query_limit(query.limit)?;
+
+    let records = store
+        .jobs_since(query.since.unwrap_or(0), limit)
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Failed to read job store: {}", e)))?;
+
+    Ok(Json(
+        records
+            .into_iter()
+            .map(|record| JobHistoryResponse {
+                id: record.id,
+                kind: record.kind,
+                status: record.status,
+                created_at: record.created_at,
+                finished_at: record.finished_at,
+                duration_ms: record.duration_ms,
+                error: record.error,
+            })
+            .collect(),
+    ))
+}
+
+/// Query for the audit log
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    /// Milliseconds since the Unix epoch; entries recorded earlier are left out
+    pub since: Option<i64>,
+
+    /// Only entries for this action, e.g. "voice_calibration.set"
+    pub action: Option<String>,
+
+    pub limit: Option<u32>,
+}
+
+/// An audited action
+#[derive(Debug, Serialize)]
+pub struct AuditEntryResponse {
+    pub id: i64,
+    pub recorded_at: i64,
+
+    /// "admin", or "user:<id>" for users acting on their own data
+    pub actor: String,
+
+    pub action: String,
+    pub target: Option<String>,
+
+    /// Hex SHA-256 of the action's JSON payload
+    pub payload_digest: String,
+}
+
+/// Handler listing the audit log, oldest first
+pub async fn audit_log(
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Vec<AuditEntryResponse>>, Error> {
+    let store = job_store()?;
+    let limit = query_limit(query.limit)?;
+
+    let records = store
+        .audit_since(query.since.unwrap_or(0), query.action.as_deref(), limit)
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Failed to read audit log: {}", e)))?;
+
+    Ok(Json(
+        records
+            .into_iter()
+            .map(|record| AuditEntryResponse {
+                id: record.id,
+                recorded_at: record.recorded_at,
+                actor: record.actor,
+                action: record.action,
+                target: record.target,
+                payload_digest: record.payload_digest,
+            })
+            .collect(),
+    ))
+}
+
+/// Query for the research export
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportQuery {
+    /// Milliseconds since the Unix epoch; results recorded earlier are left out
+    pub from: Option<i64>,
+
+    /// Milliseconds since the Unix epoch; results recorded at or after it are left out
+    /// (default: now)
+    pub to: Option<i64>,
+
+    /// "csv" or "parquet" (default: "csv")
+    pub format: Option<String>,
+}
+
+/// Handler streaming every recorded phoneme score in a time range, see [`crate::export`]
+pub async fn export(Query(query): Query<ExportQuery>) -> Result<Response, Error> {
+    let store = job_store()?;
+
+    let format_name = query.format.as_deref().unwrap_or("csv");
+    let format = ExportFormat::parse(format_name).ok_or_else(|| {
+        Error::BadRequest(format!(
+            "Unsupported export format: {} (expected csv or parquet)",
+            format_name
+        ))
+    })?;
+
+    let from = query.from.unwrap_or(0);
+    let to = query.to.unwrap_or_else(|| store::millis(SystemTime::now()));
+    if from >= to {
+        return Err(Error::BadRequest("from must be before to".to_string()));
+    }
+
+    audit::record(audit::ADMIN, "export", None, &query);
+    info!(
+        "Exporting phoneme results from {} to {} as {}",
+        from,
+        to,
+        format.extension()
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"phoneme-results-{}-{}.{}\"",
+                    from,
+                    to,
+                    format.extension()
+                ),
+            ),
+        ],
+        export_body(store, from, to, format),
+    )
+        .into_response())
+}
+
+/// Load on a group of concurrency-limited routes
+#[derive(Debug, Serialize)]
+pub struct QueueStatsResponse {
+    pub group: &'static str,
+    pub limit: usize,
+    pub in_flight: usize,
+    pub waiting: usize,
+
+    /// Requests given a slot since startup
+    pub admitted: u64,
+
+    pub average_wait_ms: f64,
+    pub max_wait_ms: f64,
+}
+
+/// Handler reporting how busy the synthesis and assessment routes are
+pub async fn queue_stats() -> Json<Vec<QueueStatsResponse>> {
+    Json(
+        route_groups()
+            .into_iter()
+            .map(|group| {
+                let snapshot = group.snapshot();
+                QueueStatsResponse {
+                    group: group.name,
+                    limit: snapshot.limit,
+                    in_flight: snapshot.in_flight,
+                    waiting: snapshot.waiting,
+                    admitted: snapshot.admitted,
+                    average_wait_ms: snapshot.average_wait.as_secs_f64() * 1000.0,
+                    max_wait_ms: snapshot.max_wait.as_secs_f64() * 1000.0,
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Effectiveness of the cache of espeak output shared by synthesis and `/api/phonemize`
+#[derive(Debug, Serialize)]
+pub struct PhonemizerStatsResponse {
+    /// Lookups answered from the cache since startup
+    pub hits: u64,
+    pub misses: u64,
+    /// Share of lookups answered from the cache, from 0 to 1
+    pub hit_rate: f64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+/// Handler reporting how often phonemization is answered from the cache
+pub async fn phonemizer_stats() -> Json<PhonemizerStatsResponse> {
+    let stats = PHONEME_CACHE.stats();
+    Json(PhonemizerStatsResponse {
+        hits: stats.hits,
+        misses: stats.misses,
+        hit_rate: stats.hit_rate(),
+        entries: stats.entries,
+        capacity: stats.capacity,
+    })
+}
+
+/// Space taken by MFA job directories, against the quota
+#[derive(Debug, Serialize)]
+pub struct StorageResponse {
+    pub root: String,
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+    /// Age after which job directories are removed as abandoned
+    pub max_age_secs: u64,
+    pub job_dirs: usize,
+    /// Usage by tenant id, "shared" for requests without a tenant
+    pub tenants: BTreeMap<String, TenantStorageResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TenantStorageResponse {
+    pub used_bytes: u64,
+    pub job_dirs: usize,
+}
+
+/// Abandoned job directories removed by a sweep
+#[derive(Debug, Serialize)]
+pub struct StorageSweepResponse {
+    pub removed: usize,
+    pub storage: StorageResponse,
+}
+
+fn storage_response() -> Result<StorageResponse, Error> {
+    let usage = STORAGE
+        .usage()
+        .map_err(|e| Error::InternalServerError(format!("Failed to read job storage: {:#}", e)))?;
+
+    Ok(StorageResponse {
+        root: STORAGE.root().display().to_string(),
+        used_bytes: usage.used_bytes,
+        quota_bytes: usage.quota_bytes,
+        max_age_secs: STORAGE.config().max_age.as_secs(),
+        job_dirs: usage.job_dirs,
+        tenants: usage
+            .tenants
+            .into_iter()
+            .map(|(tenant, usage)| {
+                (
+                    tenant,
+                    TenantStorageResponse {
+                        used_bytes: usage.used_bytes,
+                        job_dirs: usage.job_dirs,
+                    },
+                )
+            })
+            .collect(),
+    })
+}
+
+/// Handler reporting the space MFA job directories take, by tenant
+pub async fn storage() -> Result<Json<StorageResponse>, Error> {
+    tokio::task::spawn_blocking(storage_response)
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Storage check failed: {}", e)))?
+        .map(Json)
+}
+
+/// Handler removing abandoned MFA job directories now rather than before the next job
+pub async fn sweep_storage() -> Result<Json<StorageSweepResponse>, Error> {
+    let (removed, storage) = tokio::task::spawn_blocking(|| {
+        let removed = STORAGE.sweep().map_err(|e| {
+            Error::InternalServerError(format!("Failed to sweep job storage: {:#}", e))
+        })?;
+        Ok::<_, Error>((removed, storage_response()?))
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Storage sweep failed: {}", e)))??;
+
+    audit::record(audit::ADMIN, "sweep_storage", None, &removed);
+    info!("Swept {} abandoned MFA job directories", removed);
+
+    Ok(Json(StorageSweepResponse { removed, storage }))
+}
+
+fn job_store() -> Result<&'static JobStore, Error> {
+    JOB_STORE.as_ref().ok_or_else(|| {
+        Error::ServiceUnavailable("Job store is not configured; set JOB_STORE_PATH".to_string())
+    })
+}
+
+fn query_limit(limit: Option<u32>) -> Result<u32, Error> {
+    let limit = limit.unwrap_or(DEFAULT_JOB_LIMIT);
+    if !(1..=MAX_QUERY_LIMIT).contains(&limit) {
+        return Err(Error::BadRequest(format!(
+            "Limit must be between 1 and {}",
+            MAX_QUERY_LIMIT
+        )));
+    }
+    Ok(limit)
+}
+
+/// The configured retention policy, in seconds
+#[derive(Debug, Serialize)]
+pub struct RetentionPolicyResponse {
+    pub keep_last: Option<usize>,
+    pub max_age_secs: Option<u64>,
+    pub grace_secs: u64,
+    /// Seconds between runs of the background task, none if it does not run
+    pub interval_secs: Option<u64>,
+}
+
+/// An artifact the retention policy deletes, or would delete on a dry run
+#[derive(Debug, Serialize)]
+pub struct RetentionDeletionResponse {
+    pub kind: ArtifactKind,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub bytes: u64,
+    pub age_secs: u64,
+    pub reason: DeletionReason,
+}
+
+/// What the retention policy deleted, or would delete on a dry run
+#[derive(Debug, Serialize)]
+pub struct RetentionResponse {
+    pub dry_run: bool,
+    pub policy: RetentionPolicyResponse,
+    pub examined: usize,
+    /// Artifacts kept because they are flagged
+    pub flagged: usize,
+    pub deletions: Vec<RetentionDeletionResponse>,
+    pub deleted: usize,
+    pub freed_bytes: u64,
+}
+
+fn retention_response(outcome: RetentionOutcome, dry_run: bool) -> RetentionResponse {
+    let policy = &*RETENTION_POLICY;
+    let now = SystemTime::now();
+
+    RetentionResponse {
+        dry_run,
+        policy: RetentionPolicyResponse {
+            keep_last: policy.keep_last,
+            max_age_secs: policy.max_age.map(|max_age| max_age.as_secs()),
+            grace_secs: policy.grace.as_secs(),
+            interval_secs: policy.interval.map(|interval| interval.as_secs()),
+        },
+        examined: outcome.plan.examined,
+        flagged: outcome.plan.flagged,
+        deletions: outcome
+            .plan
+            .deletions
+            .into_iter()
+            .map(|deletion| RetentionDeletionResponse {
+                age_secs: now
+                    .duration_since(deletion.artifact.modified)
+                    .unwrap_or_default()
+                    .as_secs(),
+                kind: deletion.artifact.kind,
+                name: deletion.artifact.name,
+                owner: deletion.artifact.owner,
+                bytes: deletion.artifact.bytes,
+                reason: deletion.reason,
+            })
+            .collect(),
+        deleted: outcome.deleted,
+        freed_bytes: outcome.freed_bytes,
+    }
+}
+
+async fn run_retention(dry_run: bool) -> Result<RetentionResponse, Error> {
+    let outcome = tokio::task::spawn_blocking(move || enforce(&RETENTION_POLICY, dry_run))
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Retention task failed: {}", e)))?
+        .map_err(|e| {
+            Error::InternalServerError(format!("Failed to apply the retention policy: {:#}", e))
+        })?;
+    Ok(retention_response(outcome, dry_run))
+}
+
+/// Handler reporting what the retention policy would delete, without deleting anything
+pub async fn retention_plan() -> Result<Json<RetentionResponse>, Error> {
+    run_retention(true).await.map(Json)
+}
+
+/// Handler applying the retention policy now rather than at the background task's next run
+pub async fn enforce_retention() -> Result<Json<RetentionResponse>, Error> {
+    let response = run_retention(false).await?;
+
+    audit::record(audit::ADMIN, "enforce_retention", None, &response.deleted);
+    info!(
+        "Retention policy deleted {} of {} artifacts",
+        response.deleted, response.examined
+    );
+
+    Ok(Json(response))
+}
+
+/// Handler synthesizing and assessing a short phrase end to end, answering 503 if it fails
+pub async fn self_test(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    State(engine): State<Arc<dyn AssessmentEngine>>,
+) -> Result<(StatusCode, Json<SelfTestReport>), Error> {
+    let report = tokio::task::spawn_blocking(move || {
+        run_self_test(&*tts, &*engine, scoring_profile(), min_score_from_env())
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Self-test failed: {}", e)))?;
+
+    let status = if report.passed {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok((status, Json(report)))
+}
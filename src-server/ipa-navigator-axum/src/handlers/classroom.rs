@@ -0,0 +1,553 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use ipa_navigator_kokoro::normalize::normalize_text;
+use ipa_navigator_mfa::anonymize::{AnonymizeOptions, anonymize_wav};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::classroom::{
+    Assignment, Classroom, NewAssignment, PhonemeScore, RECORDINGS, StoredRecording, Submission,
+};
+use crate::config::scoring_profile;
+use crate::convex::user_call_error;
+use crate::engines::{AssessmentEngine, TtsEngine};
+use crate::error::Error;
+use crate::handlers::mfa::{
+    AssessmentInput, PronunciationRequest, PronunciationResponse, parse_dialect, run_assessment,
+};
+use crate::identity::AuthUser;
+
+/// Most sentences in one assignment
+const MAX_SENTENCES: usize = 50;
+
+/// Longest sentence accepted, in characters
+const MAX_SENTENCE_CHARS: usize = 500;
+
+/// Length of a day, in milliseconds
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Longest assignment title, in characters
+const MAX_TITLE_CHARS: usize = 100;
+
+/// Attempts at a phoneme needed before it is reported as a weakness, when not given
+const DEFAULT_MIN_ATTEMPTS: usize = 5;
+
+/// Weaknesses returned when no limit is given
+const DEFAULT_WEAKNESS_LIMIT: usize = 10;
+
+/// Request to assign a set of sentences to a classroom
+#[derive(Debug, Deserialize)]
+pub struct CreateAssignmentRequest {
+    pub title: String,
+    pub sentences: Vec<String>,
+
+    /// Dialect the sentences are scored against (default: "us")
+    #[serde(default = "default_dialect")]
+    pub dialect: String,
+
+    /// Milliseconds since the Unix epoch; submissions after it are marked late
+    pub due_date: Option<i64>,
+}
+
+fn default_dialect() -> String {
+    "us".to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssignmentCreatedResponse {
+    pub assignment_id: String,
+}
+
+/// An assignment as shown to its classroom
+#[derive(Debug, Serialize)]
+pub struct AssignmentResponse {
+    pub id: String,
+    pub classroom_id: String,
+    pub title: String,
+    pub dialect: String,
+    pub sentences: Vec<String>,
+    pub due_date: Option<i64>,
+    pub created_at: i64,
+}
+
+impl From<Assignment> for AssignmentResponse {
+    fn from(assignment: Assignment) -> Self {
+        Self {
+            id: assignment.id,
+            classroom_id: assignment.classroom_id,
+            title: assignment.title,
+            dialect: assignment.dialect,
+            sentences: assignment.sentences,
+            due_date: assignment.due_date.map(|due| due as i64),
+            created_at: assignment.created_at as i64,
+        }
+    }
+}
+
+/// A student's recording of one sentence of an assignment
+#[derive(Debug, Deserialize)]
+pub struct SubmissionRequest {
+    /// Base64-encoded audio data (WAV format expected)
+    pub audio: String,
+
+    /// Position of the recorded sentence in the assignment, from 0
+    pub sentence_index: usize,
+
+    /// Scoring strictness: "beginner", "intermediate", or "strict" (default: the
+    /// configured scoring profile, "intermediate" unless changed)
+    #[serde(default = "default_strictness")]
+    pub strictness: String,
+
+    /// Language of the feedback: "en", "ms", or "zh" (default: from `Accept-Language`, else "en")
+    pub locale: Option<String>,
+}
+
+fn default_strictness() -> String {
+    scoring_profile().as_str().to_string()
+}
+
+/// The assessment of a submitted recording
+#[derive(Debug, Serialize)]
+pub struct SubmissionResponse {
+    /// Convex id of the stored submission, or none if the wrong sentence was recorded
+    pub submission_id: Option<String>,
+    pub assessment: PronunciationResponse,
+}
+
+/// One student's progress through an assignment
+#[derive(Debug, Serialize)]
+pub struct StudentSubmissionsResponse {
+    pub user_id: String,
+    pub name: String,
+
+    /// Best score for each sentence, or none if it has not been submitted
+    pub best_scores: Vec<Option<f64>>,
+
+    /// Mean of the best scores of the submitted sentences
+    pub average_score: f64,
+
+    /// Sentences submitted at least once
+    pub completed: usize,
+
+    pub attempts: usize,
+
+    /// Whether any submission came after the due date
+    pub late: bool,
+
+    /// Milliseconds since the Unix epoch
+    pub last_submitted_at: i64,
+
+    /// Latest kept recording of each sentence, or none if there is no recording
+    pub recording_urls: Vec<Option<String>>,
+}
+
+/// Query for a classroom's phoneme weaknesses
+#[derive(Debug, Deserialize)]
+pub struct WeaknessQuery {
+    /// Only count submissions from this time on, in milliseconds since the Unix epoch
+    pub since: Option<i64>,
+
+    /// Attempts at a phoneme needed before it is reported (default: 5)
+    pub min_attempts: Option<usize>,
+
+    /// Most phonemes returned (default: 10)
+    pub limit: Option<usize>,
+}
+
+/// A phoneme the class finds hard
+#[derive(Debug, Serialize)]
+pub struct PhonemeWeaknessResponse {
+    pub phoneme: String,
+
+    /// Mean score of every attempt at the phoneme
+    pub average_score: f64,
+
+    pub attempts: usize,
+
+    /// Students who attempted the phoneme
+    pub students: usize,
+
+    /// What the phoneme was most often pronounced as instead, if anything
+    pub common_substitution: Option<String>,
+}
+
+/// Handle requests to assign a set of sentences to a classroom
+///
+/// Only the classroom's teacher may create assignments.
+pub async fn create_assignment(
+    user: AuthUser,
+    Path(classroom_id): Path<String>,
+    Json(request): Json<CreateAssignmentRequest>,
+) -> Result<(StatusCode, Json<AssignmentCreatedResponse>), Error> {
+    let title = request.title.trim().to_string();
+    if title.is_empty() || title.chars().count() > MAX_TITLE_CHARS {
+        return Err(Error::BadRequest(format!(
+            "Title must be between 1 and {} characters",
+            MAX_TITLE_CHARS
+        )));
+    }
+
+    let sentences: Vec<String> = request
+        .sentences
+        .iter()
+        .map(|sentence| normalize_text(sentence.trim()))
+        .filter(|sentence| !sentence.is_empty())
+        .collect();
+    if sentences.is_empty() || sentences.len() > MAX_SENTENCES {
+        return Err(Error::BadRequest(format!(
+            "Assignments must have between 1 and {} sentences",
+            MAX_SENTENCES
+        )));
+    }
+    if sentences
+        .iter()
+        .any(|sentence| sentence.chars().count() > MAX_SENTENCE_CHARS)
+    {
+        return Err(Error::BadRequest(format!(
+            "Sentences must be at most {} characters",
+            MAX_SENTENCE_CHARS
+        )));
+    }
+
+    parse_dialect(&request.dialect)?;
+    if request.due_date.is_some_and(|due| due <= now_millis()) {
+        return Err(Error::BadRequest(
+            "Due date must be in the future".to_string(),
+        ));
+    }
+
+    let assignment = NewAssignment {
+        title,
+        dialect: request.dialect.to_lowercase(),
+        sentences,
+        due_date: request.due_date,
+    };
+    let assignment_id = Classroom::for_user(&user)?
+        .create_assignment(&classroom_id, &assignment)
+        .await
+        .map_err(user_call_error)?;
+
+    info!(
+        "Created assignment {} with {} sentences in classroom {}",
+        assignment_id,
+        assignment.sentences.len(),
+        classroom_id
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(AssignmentCreatedResponse { assignment_id }),
+    ))
+}
+
+/// Handle requests for an assignment, from its classroom's teacher or students
+pub async fn assignment(
+    user: AuthUser,
+    Path(assignment_id): Path<String>,
+) -> Result<Json<AssignmentResponse>, Error> {
+    find_assignment(&Classroom::for_user(&user)?, &assignment_id)
+        .await
+        .map(|assignment| Json(assignment.into()))
+}
+
+/// Handle a student's recording of one sentence of an assignment
+///
+/// The recording is assessed against the sentence and its score stored for
+/// the teacher. Recordings of a different sentence are assessed but not stored.
+/// When recordings are kept for review, the audio is stored alongside the
+/// score, anonymized first if configured.
+pub async fn submit(
+    State(engine): State<Arc<dyn AssessmentEngine>>,
+    State(tts): State<Arc<dyn TtsEngine>>,
+    user: AuthUser,
+    Path(assignment_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<SubmissionRequest>,
+) -> Result<Json<SubmissionResponse>, Error> {
+    let classroom = Classroom::for_user(&user)?;
+    let assignment = find_assignment(&classroom, &assignment_id).await?;
+    let transcript = assignment
+        .sentences
+        .get(request.sentence_index)
+        .cloned()
+        .ok_or_else(|| {
+            Error::BadRequest(format!(
+                "Assignment has no sentence {}",
+                request.sentence_index
+            ))
+        })?;
+
+    let input = AssessmentInput::parse(
+        PronunciationRequest {
+            audio: request.audio,
+            transcript,
+            dialect: assignment.dialect,
+            strictness: request.strictness,
+            locale: request.locale,
+            start_word: None,
+            end_word: None,
+            personalized: false,
+            pace: false,
+            voice: None,
+        },
+        &headers,
+        user.session.tenant.as_ref(),
+        Some(user.session.subject.clone()),
+    )?;
+    let audio = (RECORDINGS.retention_days > 0).then(|| input.audio.clone());
+    let assessment = run_assessment(engine, tts, input, |_| {}).await?;
+
+    if assessment.wrong_sentence_detected {
+        return Ok(Json(SubmissionResponse {
+            submission_id: None,
+            assessment,
+        }));
+    }
+
+    let phonemes: Vec<PhonemeScore> = assessment
+        .phoneme_details
+        .iter()
+        .map(|detail| PhonemeScore {
+            expected: detail.expected.clone(),
+            actual: detail.actual.clone(),
+            score: detail.score,
+        })
+        .collect();
+    let recording = match audio {
+        Some(audio) => keep_recording(&classroom, audio).await,
+        None => None,
+    };
+    let submission_id = classroom
+        .submit(
+            &assignment_id,
+            request.sentence_index,
+            assessment.overall_score,
+            &phonemes,
+            recording.as_ref(),
+        )
+        .await
+        .map_err(user_call_error)?;
+
+    info!(
+        "Stored submission {} for sentence {} of assignment {}",
+        submission_id, request.sentence_index, assignment_id
+    );
+
+    Ok(Json(SubmissionResponse {
+        submission_id: Some(submission_id),
+        assessment,
+    }))
+}
+
+/// Handle requests for each student's submissions to an assignment
+///
+/// Only the classroom's teacher may list submissions. Students are sorted by name.
+pub async fn submissions(
+    user: AuthUser,
+    Path(assignment_id): Path<String>,
+) -> Result<Json<Vec<StudentSubmissionsResponse>>, Error> {
+    let classroom = Classroom::for_user(&user)?;
+    let assignment = find_assignment(&classroom, &assignment_id).await?;
+    let submissions = classroom
+        .submissions(&assignment_id)
+        .await
+        .map_err(user_call_error)?;
+
+    Ok(Json(summarize_students(&assignment, &submissions)))
+}
+
+/// Handle requests for the phonemes a classroom scores worst on
+///
+/// Only the classroom's teacher may see them. Phonemes attempted fewer than
+/// `min_attempts` times are left out, since a few attempts say little.
+pub async fn weaknesses(
+    user: AuthUser,
+    Path(classroom_id): Path<String>,
+    Query(query): Query<WeaknessQuery>,
+) -> Result<Json<Vec<PhonemeWeaknessResponse>>, Error> {
+    let submissions = Classroom::for_user(&user)?
+        .classroom_submissions(&classroom_id, query.since)
+        .await
+        .map_err(user_call_error)?;
+
+    let mut weaknesses = phoneme_weaknesses(
+        &submissions,
+        query.min_attempts.unwrap_or(DEFAULT_MIN_ATTEMPTS).max(1),
+    );
+    weaknesses.truncate(query.limit.unwrap_or(DEFAULT_WEAKNESS_LIMIT));
+
+    Ok(Json(weaknesses))
+}
+
+async fn find_assignment(classroom: &Classroom<'_>, id: &str) -> Result<Assignment, Error> {
+    classroom
+        .assignment(id)
+        .await
+        .map_err(user_call_error)?
+        .ok_or_else(|| Error::NotFound(format!("Unknown assignment: {}", id)))
+}
+
+/// Group submissions by student, keeping the best score for each sentence
+/// Store a submission's recording for review, anonymized if configured
+///
+/// Failures are logged rather than returned, so the score is still stored.
+async fn keep_recording(classroom: &Classroom<'_>, audio: Vec<u8>) -> Option<StoredRecording> {
+    let wav = if RECORDINGS.anonymize {
+        let options = AnonymizeOptions {
+            pitch_semitones: RECORDINGS.pitch_semitones,
+            ..AnonymizeOptions::new(Uuid::new_v4().as_u64_pair().0)
+        };
+        match tokio::task::spawn_blocking(move || anonymize_wav(&audio, &options)).await {
+            Ok(Ok(wav)) => wav,
+            Ok(Err(e)) => {
+                warn!("Not keeping recording that failed to anonymize: {}", e);
+                return None;
+            }
+            Err(e) => {
+                warn!("Not keeping recording, anonymizing panicked: {}", e);
+                return None;
+            }
+        }
+    } else {
+        audio
+    };
+
+    match classroom.store_recording(wav).await {
+        Ok(storage_id) => Some(StoredRecording {
+            storage_id,
+            expires_at: now_millis() + RECORDINGS.retention_days as i64 * DAY_MS,
+        }),
+        Err(e) => {
+            warn!("Failed to keep recording: {}", e);
+            None
+        }
+    }
+}
+
+fn summarize_students(
+    assignment: &Assignment,
+    submissions: &[Submission],
+) -> Vec<StudentSubmissionsResponse> {
+    let mut students: HashMap<&str, StudentSubmissionsResponse> = HashMap::new();
+
+    for submission in submissions {
+        let student = students
+            .entry(submission.user_id.as_str())
+            .or_insert_with(|| StudentSubmissionsResponse {
+                user_id: submission.user_id.clone(),
+                name: submission
+                    .student_name
+                    .clone()
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                best_scores: vec![None; assignment.sentences.len()],
+                average_score: 0.0,
+                completed: 0,
+                attempts: 0,
+                late: false,
+                last_submitted_at: 0,
+                recording_urls: vec![None; assignment.sentences.len()],
+            });
+
+        student.attempts += 1;
+        student.late |= submission.late;
+        student.last_submitted_at = student
+            .last_submitted_at
+            .max(submission.submitted_at as i64);
+        // Convex lists each student's submissions oldest first, so later recordings win
+        if let Some(url) = &submission.audio_url
+            && let Some(slot) = student
+                .recording_urls
+                .get_mut(submission.sentence_index as usize)
+        {
+            *slot = Some(url.clone());
+        }
+        if let Some(best) = student
+            .best_scores
+            .get_mut(submission.sentence_index as usize)
+        {
+            *best = Some(best.map_or(submission.overall_score, |best: f64| {
+                best.max(submission.overall_score)
+            }));
+        }
+    }
+
+    let mut students: Vec<_> = students.into_values().collect();
+    for student in &mut students {
+        let scores: Vec<f64> = student.best_scores.iter().flatten().copied().collect();
+        student.completed = scores.len();
+        if !scores.is_empty() {
+            student.average_score = scores.iter().sum::<f64>() / scores.len() as f64;
+        }
+    }
+
+    students.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.user_id.cmp(&b.user_id)));
+    students
+}
+
+/// Average each expected phoneme's scores across the class, worst first
+fn phoneme_weaknesses(
+    submissions: &[Submission],
+    min_attempts: usize,
+) -> Vec<PhonemeWeaknessResponse> {
+    #[derive(Default)]
+    struct Tally<'a> {
+        total: f64,
+        attempts: usize,
+        students: HashSet<&'a str>,
+        substitutions: BTreeMap<&'a str, usize>,
+    }
+
+    let mut tallies: HashMap<&str, Tally> = HashMap::new();
+    for submission in submissions {
+        for phoneme in &submission.phonemes {
+            let tally = tallies.entry(phoneme.expected.as_str()).or_default();
+            tally.total += phoneme.score;
+            tally.attempts += 1;
+            tally.students.insert(submission.user_id.as_str());
+            if !phoneme.actual.is_empty() && phoneme.actual != phoneme.expected {
+                *tally
+                    .substitutions
+                    .entry(phoneme.actual.as_str())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    let mut weaknesses: Vec<_> = tallies
+        .into_iter()
+        .filter(|(_, tally)| tally.attempts >= min_attempts)
+        .map(|(phoneme, tally)| PhonemeWeaknessResponse {
+            phoneme: phoneme.to_string(),
+            average_score: tally.total / tally.attempts as f64,
+            attempts: tally.attempts,
+            students: tally.students.len(),
+            // Ties go to the first substitution in sort order, so results are stable
+            common_substitution: tally
+                .substitutions
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(actual, _)| actual.to_string()),
+        })
+        .collect();
+
+    weaknesses.sort_by(|a, b| {
+        a.average_score
+            .total_cmp(&b.average_score)
+            .then_with(|| a.phoneme.cmp(&b.phoneme))
+    });
+    weaknesses
+}
+
+/// Milliseconds since the Unix epoch
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
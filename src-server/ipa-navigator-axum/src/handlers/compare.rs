@@ -0,0 +1,258 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::Json,
+    http::{HeaderMap, header},
+    response::IntoResponse,
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use ipa_navigator_mfa::{
+    aligner::get_aligner,
+    audio::{normalize_wav, read_wav_mono},
+    mfa_parser::MfaSegment,
+    storage::with_owner,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{Span, error, info};
+
+use crate::error::Error;
+use crate::handlers::{
+    mfa::parse_dialect,
+    tts::{get_tts, tenant_reference_voice, tenant_voice},
+};
+use crate::identity::Session;
+use crate::tenants::Tenant;
+
+/// Request for a side-by-side comparison with a reference recording
+#[derive(Debug, Deserialize)]
+pub struct CompareRequest {
+    /// Base64-encoded audio data (WAV format expected)
+    pub audio: String,
+
+    /// Plain text transcript of the spoken words
+    pub transcript: String,
+
+    /// Dialect to align against and speak the reference in (default: "us")
+    #[serde(default = "default_dialect")]
+    pub dialect: String,
+
+    /// Voice for the reference clip, as accepted by `/api/tts` (default: depends on dialect)
+    pub voice: Option<String>,
+}
+
+fn default_dialect() -> String {
+    "us".to_string()
+}
+
+/// Timings sent as the JSON part of the comparison response
+#[derive(Debug, Serialize)]
+pub struct CompareTimings {
+    pub transcript: String,
+    pub user: ClipTimings,
+    pub reference: ClipTimings,
+}
+
+/// Aligned word and phoneme boundaries of one clip, in seconds
+#[derive(Debug, Serialize)]
+pub struct ClipTimings {
+    pub duration: f64,
+    pub words: Vec<SegmentTiming>,
+    pub phonemes: Vec<SegmentTiming>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SegmentTiming {
+    pub label: String,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+/// Handle requests comparing a recording with a synthesized reference
+///
+/// Responds with `multipart/form-data` holding a `timings` JSON part followed
+/// by the normalized `user` clip and the `reference` clip as WAV files.
+pub async fn compare(
+    session: Option<Session>,
+    tenant: Option<Tenant>,
+    Json(request): Json<CompareRequest>,
+) -> Result<impl IntoResponse, Error> {
+    info!(
+        "Processing comparison request for {} chars of text",
+        request.transcript.chars().count()
+    );
+
+    let audio_data = BASE64
+        .decode(&request.audio)
+        .map_err(|e| Error::BadRequest(format!("Invalid audio data format: {}", e)))?;
+
+    let dialect = parse_dialect(&request.dialect)?;
+    let voice = match request.voice.as_deref() {
+        Some(voice) => tenant_voice(tenant.as_ref(), voice).map_err(Error::BadRequest)?,
+        None => tenant_reference_voice(tenant.as_ref(), dialect),
+    };
+
+    let transcript = request.transcript.clone();
+    let tenant_id = tenant.as_ref().map(|tenant| tenant.id().to_string());
+    let user = session.map(|session| session.subject);
+    let span = Span::current();
+    let (user_wav, reference_wav, timings) =
+        tokio::task::spawn_blocking(move || -> Result<_, Error> {
+            let _span = span.enter();
+
+            let user_wav = normalize_wav(&audio_data)
+                .map_err(|e| Error::BadRequest(format!("Invalid audio data: {}", e)))?;
+
+            let tts = get_tts().map_err(|e| {
+                Error::InternalServerError(format!("TTS initialization error: {}", e))
+            })?;
+            let audio = tts
+                .process_tts(&transcript, &voice, 1.0)
+                .map_err(|e| Error::InternalServerError(format!("TTS processing error: {}", e)))?;
+            let samples = audio.as_slice().ok_or_else(|| {
+                Error::InternalServerError("Failed to convert audio data".to_string())
+            })?;
+            let reference_wav = tts
+                .audio_to_wav(samples)
+                .map_err(|e| e.to_string())
+                .and_then(|wav| normalize_wav(&wav).map_err(|e| e.to_string()))
+                .map_err(|e| {
+                    Error::InternalServerError(format!("Failed to encode reference audio: {}", e))
+                })?;
+
+            // Both clips are aligned against the same transcript so their phonemes line up
+            let align = |wav: &[u8]| {
+                with_owner(tenant_id.as_deref(), user.as_deref(), || {
+                    get_aligner().and_then(|aligner| aligner.align(wav, &transcript, dialect))
+                })
+                .map_err(|e| {
+                    error!("Alignment error: {:?}", e);
+                    Error::InternalServerError(format!("Failed to align audio: {}", e))
+                })
+            };
+            let timings = CompareTimings {
+                transcript: transcript.clone(),
+                user: clip_timings(&align(&user_wav)?, &user_wav),
+                reference: clip_timings(&align(&reference_wav)?, &reference_wav),
+            };
+
+            Ok((user_wav, reference_wav, timings))
+        })
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Comparison task failed: {}", e)))??;
+
+    let timings = serde_json::to_vec(&timings)
+        .map_err(|e| Error::InternalServerError(format!("Failed to encode timings: {}", e)))?;
+
+    let parts = [
+        Part {
+            name: "timings",
+            filename: None,
+            content_type: "application/json",
+            body: &timings,
+        },
+        Part {
+            name: "user",
+            filename: Some("user.wav"),
+            content_type: "audio/wav",
+            body: &user_wav,
+        },
+        Part {
+            name: "reference",
+            filename: Some("reference.wav"),
+            content_type: "audio/wav",
+            body: &reference_wav,
+        },
+    ];
+    let boundary = choose_boundary(&parts);
+    let body = encode_multipart(&parts, &boundary);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        format!("multipart/form-data; boundary={}", boundary)
+            .parse()
+            .unwrap(),
+    );
+
+    Ok((headers, body))
+}
+
+fn clip_timings(segments: &[MfaSegment], wav: &[u8]) -> ClipTimings {
+    let timings = |segment_type: &str| {
+        segments
+            .iter()
+            .filter(|s| s.segment_type == segment_type && !s.label.is_empty())
+            .map(|s| SegmentTiming {
+                label: s.label.clone(),
+                start_time: s.begin,
+                end_time: s.end,
+            })
+            .collect()
+    };
+
+    ClipTimings {
+        duration: wav_duration(wav),
+        words: timings("word"),
+        phonemes: timings("phone"),
+    }
+}
+
+fn wav_duration(wav: &[u8]) -> f64 {
+    read_wav_mono(wav)
+        .map(|(samples, sample_rate)| samples.len() as f64 / sample_rate as f64)
+        .unwrap_or_default()
+}
+
+/// One part of a multipart response body
+pub(crate) struct Part<'a> {
+    pub name: &'static str,
+    pub filename: Option<&'static str>,
+    pub content_type: &'static str,
+    pub body: &'a [u8],
+}
+
+/// Pick a boundary that does not occur in any part
+pub(crate) fn choose_boundary(parts: &[Part]) -> String {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+
+    (0u32..)
+        .map(|attempt| format!("ipa-navigator-{:x}-{}", seed, attempt))
+        .find(|boundary| {
+            parts.iter().all(|part| {
+                !part
+                    .body
+                    .windows(boundary.len())
+                    .any(|w| w == boundary.as_bytes())
+            })
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn encode_multipart(parts: &[Part], boundary: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    for part in parts {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        match part.filename {
+            Some(filename) => body.extend_from_slice(
+                format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n",
+                    part.name, filename
+                )
+                .as_bytes(),
+            ),
+            None => body.extend_from_slice(
+                format!("Content-Disposition: form-data; name=\"{}\"\r\n", part.name).as_bytes(),
+            ),
+        }
+        body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", part.content_type).as_bytes());
+        body.extend_from_slice(part.body);
+        body.extend_from_slice(b"\r\n");
+    }
+
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
@@ -0,0 +1,350 @@
+use axum::extract::Json;
+use ipa_navigator_core::ipa_input::{GradedSymbol, grade_transcription};
+use ipa_navigator_kokoro::normalize::normalize_text;
+use ipa_navigator_mfa::{
+    exercise::build_exercises,
+    scoring::{Strictness, cached_dictionary, dictionary_word, rubric},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::error::Error;
+use crate::handlers::{
+    ipa::{MAX_IPA_INPUT_CHARS, percent_encode},
+    mfa::parse_dialect,
+    tts::{tenant_reference_voice, tenant_voice},
+};
+use crate::identity::AuthUser;
+
+/// Longest text accepted, in characters
+pub(crate) const MAX_TEXT_CHARS: usize = 10_000;
+
+/// Most exercises created from one text
+const MAX_EXERCISES: usize = 50;
+
+/// Longest title derived from the first sentence, in characters
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Request to turn a pasted paragraph into a practice deck
+#[derive(Debug, Deserialize)]
+pub struct ExercisesFromTextRequest {
+    pub text: String,
+
+    /// Deck title (default: the start of the first sentence)
+    pub title: Option<String>,
+
+    /// Dialect of the transcriptions and reference audio (default: "us")
+    #[serde(default = "default_dialect")]
+    pub dialect: String,
+
+    /// Voice for the reference audio (default: the dialect's reference voice)
+    pub voice: Option<String>,
+}
+
+fn default_dialect() -> String {
+    "us".to_string()
+}
+
+fn default_strictness() -> String {
+    "intermediate".to_string()
+}
+
+/// A learner's transcription of a word, to grade against the dictionary
+#[derive(Debug, Deserialize)]
+pub struct TranscriptionRequest {
+    pub word: String,
+
+    /// The learner's IPA, with or without slashes or brackets
+    pub ipa: String,
+
+    /// Dialect whose dictionary gives the reference (default: "us")
+    #[serde(default = "default_dialect")]
+    pub dialect: String,
+
+    /// Grading strictness: "beginner", "intermediate", or "strict" (default: "intermediate")
+    #[serde(default = "default_strictness")]
+    pub strictness: String,
+}
+
+/// Grade of a transcription, symbol by symbol
+#[derive(Debug, Serialize)]
+pub struct TranscriptionGradeResponse {
+    pub word: String,
+
+    /// Dictionary transcription of the word
+    pub reference: String,
+
+    /// The learner's IPA after normalization, which symbol offsets refer to
+    pub normalized: String,
+
+    pub score: f64,
+
+    /// Whether every symbol earned full credit
+    pub correct: bool,
+
+    pub symbols: Vec<GradedSymbolDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GradedSymbolDetail {
+    /// Reference phoneme, empty for a typed symbol the reference lacks
+    pub expected: String,
+
+    /// Typed symbol, empty for a reference phoneme left out
+    pub typed: String,
+
+    /// Character offsets of the typed symbol in the normalized IPA
+    pub start: Option<usize>,
+    pub end: Option<usize>,
+
+    pub score: f64,
+    pub correct: bool,
+}
+
+impl From<GradedSymbol> for GradedSymbolDetail {
+    fn from(graded: GradedSymbol) -> Self {
+        let correct = graded.is_correct();
+        Self {
+            expected: graded.expected.unwrap_or_default(),
+            start: graded.typed.as_ref().map(|typed| typed.start),
+            end: graded.typed.as_ref().map(|typed| typed.end),
+            typed: graded.typed.map(|typed| typed.symbol).unwrap_or_default(),
+            score: graded.score,
+            correct,
+        }
+    }
+}
+
+/// The stored practice deck
+#[derive(Debug, Serialize)]
+pub struct ExerciseDeckResponse {
+    /// Convex id of the deck
+    pub deck_id: String,
+    pub title: String,
+    pub dialect: String,
+    pub items: Vec<ExerciseItem>,
+}
+
+/// One sentence of the deck
+#[derive(Debug, Serialize)]
+pub struct ExerciseItem {
+    pub text: String,
+
+    /// Dictionary transcription of the sentence, words separated by spaces
+    pub ipa: String,
+
+    pub words: Vec<ExerciseWord>,
+
+    /// Words without a dictionary pronunciation, which will not be scored
+    pub missing_words: Vec<String>,
+
+    /// Endpoint returning the sentence spoken by the reference voice
+    pub reference_audio_url: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExerciseWord {
+    pub word: String,
+    pub ipa: String,
+}
+
+/// Handle requests to build and store a practice deck from free text
+///
+/// Convex stores the deck for the signed-in user.
+pub async fn from_text(
+    user: AuthUser,
+    Json(request): Json<ExercisesFromTextRequest>,
+) -> Result<Json<ExerciseDeckResponse>, Error> {
+    let convex = user.session.convex()?;
+
+    if request.text.chars().count() > MAX_TEXT_CHARS {
+        return Err(Error::BadRequest(format!(
+            "Text must be at most {} characters",
+            MAX_TEXT_CHARS
+        )));
+    }
+
+    let dialect = parse_dialect(&request.dialect)?;
+    let voice = match request.voice.as_deref() {
+        Some(voice) => {
+            tenant_voice(user.session.tenant.as_ref(), voice).map_err(Error::BadRequest)?
+        }
+        None => tenant_reference_voice(user.session.tenant.as_ref(), dialect),
+    };
+
+    let text = normalize_text(&request.text);
+    let exercises = tokio::task::spawn_blocking(move || {
+        cached_dictionary(dialect).map(|dictionary| build_exercises(&dictionary, &text))
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Exercise task failed: {}", e)))?
+    .map_err(|e| Error::InternalServerError(format!("Failed to load dictionary: {}", e)))?;
+
+    if exercises.is_empty() {
+        return Err(Error::BadRequest("Text contains no sentences".to_string()));
+    }
+    if exercises.len() > MAX_EXERCISES {
+        return Err(Error::BadRequest(format!(
+            "Text must have at most {} sentences",
+            MAX_EXERCISES
+        )));
+    }
+
+    let items: Vec<ExerciseItem> = exercises
+        .into_iter()
+        .map(|exercise| {
+            let words: Vec<ExerciseWord> = exercise
+                .words
+                .into_iter()
+                .map(|(word, phonemes)| ExerciseWord {
+                    word,
+                    ipa: phonemes.join(""),
+                })
+                .collect();
+
+            ExerciseItem {
+                ipa: words
+                    .iter()
+                    .map(|word| word.ipa.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                words,
+                missing_words: exercise.missing_words,
+                reference_audio_url: format!(
+                    "/api/tts?text={}&voice={}",
+                    percent_encode(&exercise.text),
+                    voice.name()
+                ),
+                text: exercise.text,
+            }
+        })
+        .collect();
+
+    let title = request
+        .title
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| items[0].text.chars().take(MAX_TITLE_CHARS).collect());
+    let dialect_code = request.dialect.to_lowercase();
+
+    info!(
+        "Creating practice deck '{}' with {} items for user {}",
+        title,
+        items.len(),
+        user.user_id
+    );
+
+    let deck_id = convex
+        .mutation(
+            "functions/exercises:createDeck",
+            json!({
+                "title": title,
+                "dialect": dialect_code,
+                "source_text": request.text,
+                "items": items,
+            }),
+            Some(user.authorization()),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to store practice deck: {}", e);
+            Error::from(e)
+        })?
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::InternalServerError("Convex returned no deck id".to_string()))?;
+
+    Ok(Json(ExerciseDeckResponse {
+        deck_id,
+        title,
+        dialect: dialect_code,
+        items,
+    }))
+}
+
+/// Handle requests to grade a typed transcription of a word
+///
+/// The typed IPA is aligned with the dictionary pronunciation the same way
+/// spoken phonemes are when scoring audio, so near misses earn partial credit.
+pub async fn grade(
+    Json(request): Json<TranscriptionRequest>,
+) -> Result<Json<TranscriptionGradeResponse>, Error> {
+    let word = dictionary_word(&request.word);
+    if word.is_empty() || request.word.trim().contains(char::is_whitespace) {
+        return Err(Error::BadRequest(
+            "A single word must be transcribed".to_string(),
+        ));
+    }
+    if request.ipa.trim().is_empty() || request.ipa.chars().count() > MAX_IPA_INPUT_CHARS {
+        return Err(Error::BadRequest(format!(
+            "IPA must have between 1 and {} characters",
+            MAX_IPA_INPUT_CHARS
+        )));
+    }
+
+    let dialect = parse_dialect(&request.dialect)?;
+    let strictness = Strictness::parse(&request.strictness).ok_or_else(|| {
+        Error::BadRequest(format!("Unsupported strictness: {}", request.strictness))
+    })?;
+
+    let dictionary = tokio::task::spawn_blocking(move || cached_dictionary(dialect))
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Dictionary task failed: {}", e)))?
+        .map_err(|e| Error::InternalServerError(format!("Failed to load dictionary: {}", e)))?;
+    let reference = dictionary
+        .get(&word)
+        .ok_or_else(|| Error::NotFound(format!("No dictionary pronunciation for: {}", word)))?;
+
+    let grade = grade_transcription(
+        &request.ipa,
+        reference,
+        dialect.rhoticity(),
+        &rubric(strictness),
+    );
+    let symbols: Vec<GradedSymbolDetail> = grade
+        .symbols
+        .into_iter()
+        .map(GradedSymbolDetail::from)
+        .collect();
+
+    Ok(Json(TranscriptionGradeResponse {
+        word,
+        reference: reference.join(""),
+        normalized: grade.normalized,
+        score: grade.score,
+        correct: symbols.iter().all(|symbol| symbol.correct),
+        symbols,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_grade_rejects_bad_requests() {
+        let request = |word: &str, ipa: &str, strictness: &str| TranscriptionRequest {
+            word: word.to_string(),
+            ipa: ipa.to_string(),
+            dialect: default_dialect(),
+            strictness: strictness.to_string(),
+        };
+
+        let long_ipa = "a".repeat(MAX_IPA_INPUT_CHARS + 1);
+        for request in [
+            request("two words", "tu wɝdz", "intermediate"),
+            request("123", "wʌn", "intermediate"),
+            request("cat", " ", "intermediate"),
+            request("cat", &long_ipa, "intermediate"),
+            request("cat", "kæt", "lenient"),
+        ] {
+            let word = request.word.clone();
+            let result = grade(Json(request)).await;
+            assert!(
+                matches!(result, Err(Error::BadRequest(_))),
+                "{:?} should be rejected",
+                word
+            );
+        }
+    }
+}
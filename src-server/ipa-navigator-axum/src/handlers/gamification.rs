@@ -0,0 +1,222 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::extract::{Json, Path};
+use ipa_navigator_core::gamification::{
+    Attempt, civil_date, day_of, day_start, earned_badges, streak, week_of, week_start,
+    weekly_leaderboard,
+};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::convex::user_call_error;
+use crate::error::Error;
+use crate::identity::AuthUser;
+use crate::practice::Practice;
+
+/// Users shown on the weekly leaderboard
+const LEADERBOARD_SIZE: usize = 10;
+
+/// Attempts in a week needed to appear on its leaderboard
+const LEADERBOARD_MIN_ATTEMPTS: usize = 3;
+
+/// A user's streak, badges, and standing this week
+#[derive(Debug, Serialize)]
+pub struct GamificationResponse {
+    pub user_id: String,
+    pub name: String,
+    pub total_attempts: usize,
+    pub streak: StreakResponse,
+
+    /// Badges earned through practice
+    pub badges: Vec<BadgeResponse>,
+
+    pub week: WeeklyStandingResponse,
+
+    /// Best users this week, highest first
+    pub leaderboard: Vec<LeaderboardEntryResponse>,
+}
+
+/// Consecutive days practiced, in GMT+8
+#[derive(Debug, Serialize)]
+pub struct StreakResponse {
+    /// Days in the streak ending today, or yesterday if today has no practice yet
+    pub current: u32,
+    pub longest: u32,
+
+    /// "YYYY-MM-DD"
+    pub last_practice_date: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BadgeResponse {
+    pub id: &'static str,
+    pub name: &'static str,
+
+    /// "bronze", "silver", or "gold"
+    pub tier: &'static str,
+
+    /// Whether this request stored the badge for the user
+    pub newly_awarded: bool,
+}
+
+/// The user's place in this week's leaderboard
+#[derive(Debug, Serialize)]
+pub struct WeeklyStandingResponse {
+    /// Monday the week started, "YYYY-MM-DD"
+    pub week_start: String,
+
+    pub attempts: usize,
+    pub average_accuracy: Option<f64>,
+
+    /// None until the user has enough attempts this week to be ranked
+    pub rank: Option<usize>,
+
+    /// Users ranked this week
+    pub ranked_users: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardEntryResponse {
+    pub rank: usize,
+    pub user_id: String,
+    pub name: String,
+    pub average_accuracy: f64,
+    pub attempts: usize,
+}
+
+/// Handle requests for a user's streak, badges, and weekly leaderboard
+///
+/// `id` is a Convex user id, or `me` for the signed-in user. Badges the
+/// signed-in user has earned but not yet been awarded are stored for them,
+/// which notifies them as it does in the app.
+pub async fn user_gamification(
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<GamificationResponse>, Error> {
+    let user_id = match id.as_str() {
+        "me" => user.user_id.clone(),
+        _ => id,
+    };
+    let practice = Practice::for_user(&user)?;
+
+    let today = day_of(now_millis());
+    let week = week_of(today);
+    let week_begins = week_start(week);
+
+    let (history, weekly) = tokio::join!(
+        practice.history(&user_id),
+        practice.since(day_start(week_begins))
+    );
+    let history = history
+        .map_err(user_call_error)?
+        .ok_or_else(|| Error::NotFound(format!("Unknown user: {}", user_id)))?;
+    let weekly = weekly.map_err(user_call_error)?;
+
+    let attempts: Vec<Attempt> = history
+        .attempts
+        .iter()
+        .copied()
+        .map(Attempt::from)
+        .collect();
+    let streak = streak(&attempts, today);
+
+    // Only the user's own badges can be stored, since Convex awards them to the caller
+    let stored: HashSet<&str> = history.badge_ids.iter().map(String::as_str).collect();
+    let mut badges = Vec::new();
+    for badge in earned_badges(&attempts, &streak) {
+        let mut newly_awarded = false;
+        if user_id == user.user_id && !stored.contains(badge.id()) {
+            match practice.award_badge(badge).await {
+                Ok(()) => {
+                    info!("Awarded badge {} to user {}", badge.id(), user_id);
+                    newly_awarded = true;
+                }
+                Err(e) => warn!("Failed to award badge {}: {}", badge.id(), e),
+            }
+        }
+
+        badges.push(BadgeResponse {
+            id: badge.id(),
+            name: badge.name(),
+            tier: badge.tier(),
+            newly_awarded,
+        });
+    }
+
+    let names: HashMap<&str, &str> = weekly
+        .iter()
+        .map(|entry| (entry.user_id.as_str(), entry.name.as_str()))
+        .collect();
+    let users: Vec<(String, Vec<Attempt>)> = weekly
+        .iter()
+        .map(|entry| {
+            let attempts = entry.attempts.iter().copied().map(Attempt::from).collect();
+            (entry.user_id.clone(), attempts)
+        })
+        .collect();
+    let board = weekly_leaderboard(&users, week, LEADERBOARD_MIN_ATTEMPTS);
+
+    let this_week: Vec<&Attempt> = attempts
+        .iter()
+        .filter(|attempt| week_of(day_of(attempt.timestamp_ms)) == week)
+        .collect();
+    let standing = WeeklyStandingResponse {
+        week_start: format_date(week_begins),
+        attempts: this_week.len(),
+        average_accuracy: (!this_week.is_empty()).then(|| {
+            this_week
+                .iter()
+                .map(|attempt| attempt.accuracy)
+                .sum::<f64>()
+                / this_week.len() as f64
+        }),
+        rank: board
+            .iter()
+            .find(|entry| entry.user == user_id)
+            .map(|entry| entry.rank),
+        ranked_users: board.len(),
+    };
+
+    let leaderboard = board
+        .into_iter()
+        .take(LEADERBOARD_SIZE)
+        .map(|entry| LeaderboardEntryResponse {
+            rank: entry.rank,
+            name: names
+                .get(entry.user.as_str())
+                .unwrap_or(&"Unknown")
+                .to_string(),
+            user_id: entry.user,
+            average_accuracy: entry.average_accuracy,
+            attempts: entry.attempts,
+        })
+        .collect();
+
+    Ok(Json(GamificationResponse {
+        user_id,
+        name: history.name,
+        total_attempts: attempts.len(),
+        streak: StreakResponse {
+            current: streak.current,
+            longest: streak.longest,
+            last_practice_date: streak.last_practice_day.map(format_date),
+        },
+        badges,
+        week: standing,
+        leaderboard,
+    }))
+}
+
+/// A day as "YYYY-MM-DD"
+fn format_date(day: i64) -> String {
+    let (year, month, day) = civil_date(day);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Milliseconds since the Unix epoch
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
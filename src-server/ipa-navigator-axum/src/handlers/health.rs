@@ -1,14 +1,269 @@
-use axum::{Json, http::StatusCode};
-use serde::Serialize;
+use std::future::Future;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use axum::{Json, extract::Query, http::StatusCode};
+use ipa_navigator_mfa::{container::CONTAINER_MANAGER, docker::MfaDialect};
+use serde::{Deserialize, Serialize};
+
+use crate::convex::CONVEX;
+use crate::handlers::tts::{get_tts, loaded_tts, reference_voice};
+use crate::handlers::voices::{DegradedVoice, degraded_voices};
+
+/// Longest wait for any one subsystem check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Free space in the job directory below which alignment is reported as at risk
+const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct HealthQuery {
+    /// Also check every subsystem the server depends on
+    #[serde(default)]
+    deep: bool,
+}
 
 #[derive(Debug, Serialize)]
 pub struct HealthResponse {
+    /// "ok", or in deep mode "degraded" or "error" when a subsystem is
+    status: &'static str,
+
+    /// Per-subsystem results, in deep mode only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checks: Option<Vec<SubsystemCheck>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubsystemCheck {
+    name: &'static str,
+
+    /// "ok", "degraded", "error", or "skipped"
     status: &'static str,
+
+    latency_ms: f64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+
+    /// Free disk space, for the job directory check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    free_bytes: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadyResponse {
+    /// "ready", "degraded" when some voices failed to load, or "loading"
+    status: &'static str,
+
+    voices_loaded: usize,
+
+    degraded_voices: Vec<DegradedVoice>,
+}
+
+/// Outcome of a check before it is timed
+enum CheckOutcome {
+    Ok(Option<String>),
+    Degraded(String),
+    Error(String),
+    Skipped(String),
 }
 
 /// Handler for health check endpoint
-pub async fn health_check() -> (StatusCode, Json<HealthResponse>) {
-    let response = HealthResponse { status: "ok" };
+///
+/// With `?deep=true`, runs a tiny TTS inference and checks MFA, Convex, and
+/// free disk space for job directories, responding 503 if any of them fails.
+pub async fn health_check(Query(query): Query<HealthQuery>) -> (StatusCode, Json<HealthResponse>) {
+    if !query.deep {
+        let response = HealthResponse {
+            status: "ok",
+            checks: None,
+        };
+        return (StatusCode::OK, Json(response));
+    }
+
+    let (tts, mfa, convex, disk) = tokio::join!(
+        timed("tts", check_tts()),
+        timed("mfa", check_mfa()),
+        timed("convex", check_convex()),
+        timed("job_storage", check_job_storage()),
+    );
+    let checks = vec![tts, mfa, convex, disk];
 
+    let (status_code, status) = if checks.iter().any(|check| check.status == "error") {
+        (StatusCode::SERVICE_UNAVAILABLE, "error")
+    } else if checks.iter().any(|check| check.status == "degraded") {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+
+    let response = HealthResponse {
+        status,
+        checks: Some(checks),
+    };
+    (status_code, Json(response))
+}
+
+/// Handler for the readiness endpoint
+///
+/// Responds 503 until the TTS model and voices have loaded, then 200, listing
+/// any voices that failed to load.
+pub async fn ready() -> (StatusCode, Json<ReadyResponse>) {
+    let Some(tts) = loaded_tts() else {
+        let response = ReadyResponse {
+            status: "loading",
+            voices_loaded: 0,
+            degraded_voices: Vec::new(),
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(response));
+    };
+
+    let degraded_voices = degraded_voices(&tts);
+    let response = ReadyResponse {
+        status: if degraded_voices.is_empty() {
+            "ready"
+        } else {
+            "degraded"
+        },
+        voices_loaded: tts.available_voices().len(),
+        degraded_voices,
+    };
     (StatusCode::OK, Json(response))
 }
+
+/// Run a check with a timeout and record how long it took
+async fn timed(
+    name: &'static str,
+    check: impl Future<Output = (CheckOutcome, Option<u64>)>,
+) -> SubsystemCheck {
+    let started = Instant::now();
+    let (outcome, free_bytes) = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| {
+            let message = format!("Timed out after {}s", CHECK_TIMEOUT.as_secs());
+            (CheckOutcome::Error(message), None)
+        });
+
+    let (status, detail) = match outcome {
+        CheckOutcome::Ok(detail) => ("ok", detail),
+        CheckOutcome::Degraded(detail) => ("degraded", Some(detail)),
+        CheckOutcome::Error(detail) => ("error", Some(detail)),
+        CheckOutcome::Skipped(detail) => ("skipped", Some(detail)),
+    };
+
+    SubsystemCheck {
+        name,
+        status,
+        latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+        detail,
+        free_bytes,
+    }
+}
+
+/// Synthesize one short word to show the ONNX session responds
+async fn check_tts() -> (CheckOutcome, Option<u64>) {
+    let result = tokio::task::spawn_blocking(|| {
+        let voice = reference_voice(MfaDialect::AmericanEnglish);
+        get_tts()
+            .and_then(|tts| tts.synthesize("ok", &voice, 1.0))
+            .map(|audio| audio.len())
+    })
+    .await;
+
+    let outcome = match result {
+        Ok(Ok(samples)) => CheckOutcome::Ok(Some(format!("Synthesized {} samples", samples))),
+        Ok(Err(e)) => CheckOutcome::Error(format!("Inference failed: {}", e)),
+        Err(e) => CheckOutcome::Error(format!("Check task failed: {}", e)),
+    };
+    (outcome, None)
+}
+
+/// Check the managed MFA container is running and passes its health check
+async fn check_mfa() -> (CheckOutcome, Option<u64>) {
+    if !CONTAINER_MANAGER.config().enabled {
+        let message = "MFA container management is disabled".to_string();
+        return (CheckOutcome::Skipped(message), None);
+    }
+
+    let outcome = match tokio::task::spawn_blocking(|| CONTAINER_MANAGER.report()).await {
+        Ok(report) if report.healthy => CheckOutcome::Ok(Some(format!(
+            "Container {} is {} on {}",
+            report.name,
+            report.state.as_str(),
+            report.runtime.as_str()
+        ))),
+        Ok(report) => CheckOutcome::Error(report.last_error.unwrap_or_else(|| {
+            format!(
+                "Container {} is {} and unhealthy on {}",
+                report.name,
+                report.state.as_str(),
+                report.runtime.as_str()
+            )
+        })),
+        Err(e) => CheckOutcome::Error(format!("Check task failed: {}", e)),
+    };
+    (outcome, None)
+}
+
+/// Check the Convex deployment answers
+async fn check_convex() -> (CheckOutcome, Option<u64>) {
+    let outcome = match CONVEX.as_ref() {
+        None => CheckOutcome::Skipped("CONVEX_DEPLOYMENT_URL is not set".to_string()),
+        Some(convex) => match convex.ping().await {
+            Ok(()) if convex.is_circuit_open() => CheckOutcome::Degraded(
+                "Convex answers, but calls are paused after repeated failures".to_string(),
+            ),
+            Ok(()) => CheckOutcome::Ok(None),
+            Err(e) => CheckOutcome::Error(format!("Convex unreachable: {}", e)),
+        },
+    };
+    (outcome, None)
+}
+
+/// Check there is room to write MFA job directories
+async fn check_job_storage() -> (CheckOutcome, Option<u64>) {
+    let jobs_dir = CONTAINER_MANAGER.config().jobs_dir.clone();
+
+    match tokio::task::spawn_blocking(move || free_space(&jobs_dir)).await {
+        Ok(Ok(free)) if free < MIN_FREE_BYTES => {
+            let message = format!("Only {} MiB free", free / (1024 * 1024));
+            (CheckOutcome::Degraded(message), Some(free))
+        }
+        Ok(Ok(free)) => (CheckOutcome::Ok(None), Some(free)),
+        Ok(Err(e)) => (CheckOutcome::Error(e), None),
+        Err(e) => (
+            CheckOutcome::Error(format!("Check task failed: {}", e)),
+            None,
+        ),
+    }
+}
+
+/// Bytes available on the filesystem holding a directory, or its nearest existing ancestor
+fn free_space(dir: &Path) -> Result<u64, String> {
+    let existing = dir
+        .ancestors()
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("No existing parent of {}", dir.display()))?;
+
+    // POSIX output: a header line, then "filesystem blocks used available capacity mount"
+    let output = Command::new("df")
+        .arg("-Pk")
+        .arg(existing)
+        .output()
+        .map_err(|e| format!("Failed to run df: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "df failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .nth(1)
+        .and_then(|line| line.split_whitespace().nth(3))
+        .and_then(|available| available.parse::<u64>().ok())
+        .map(|kib| kib * 1024)
+        .ok_or_else(|| "Unexpected df output".to_string())
+}
@@ -0,0 +1,596 @@
+use axum::{
+    extract::{Json, Path, Query},
+    http::HeaderMap,
+    response::Response,
+};
+use ipa_navigator_core::chart::{
+    CONSONANT_COLUMNS, CONSONANT_ROWS, DIACRITICS, consonants, diphthongs, vowels,
+};
+use ipa_navigator_core::ipa_input::{InvalidSymbol, validate_ipa};
+use ipa_navigator_kokoro::vocab::VOCABULARY;
+use ipa_navigator_mfa::{
+    articulation::{ExampleWord, articulation_info, articulation_info_in, example_words},
+    corpus::{DrillSentence, MAX_DRILL_SENTENCES, drill_sentences},
+    docker::MfaDialect,
+    localization::{Locale, describe_phoneme, localize},
+    phoneme::{PhonemeFeatures, SimilarityWeights, parse_ipa},
+    scoring::SIMILARITY_MATRIX,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::handlers::{
+    mfa::{parse_dialect, request_locale},
+    tts::{get_tts, tenant_reference_voice},
+};
+use crate::media::{AudioClip, SYNTHESIZED_CACHE_CONTROL, synthesis_modified};
+use crate::tenants::Tenant;
+
+/// Articulation metadata for one IPA chart symbol
+#[derive(Debug, Serialize)]
+pub struct IpaSymbolResponse {
+    pub symbol: String,
+
+    /// Conventional description, e.g. "voiceless dental fricative"
+    pub description: String,
+
+    pub features: PhonemeFeatures,
+
+    /// How to place the articulators, one entry per place/manner/height/backness
+    pub hints: Vec<ArticulationHint>,
+
+    pub examples: DialectExamples,
+
+    /// Endpoints returning an example word spoken in each dialect
+    pub audio_url: DialectAudio,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArticulationHint {
+    /// "place", "manner", "height", or "backness"
+    pub category: &'static str,
+    pub value: &'static str,
+    pub hint: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DialectExamples {
+    pub us: Vec<ExampleWordDetail>,
+    pub uk: Vec<ExampleWordDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExampleWordDetail {
+    pub word: String,
+    pub ipa: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DialectAudio {
+    pub us: String,
+    pub uk: String,
+}
+
+/// Query for the symbol metadata endpoint
+#[derive(Debug, Deserialize)]
+pub struct IpaInfoQuery {
+    /// Language of the description and hints: "en", "ms", or "zh"
+    /// (default: from `Accept-Language`, else "en")
+    pub locale: Option<String>,
+}
+
+/// Query for the symbol audio endpoint
+#[derive(Debug, Deserialize)]
+pub struct IpaAudioQuery {
+    /// Dialect to speak the example in (default: "us")
+    pub dialect: Option<String>,
+}
+
+/// Query for the symbol drill sentences endpoint
+#[derive(Debug, Deserialize)]
+pub struct IpaSentencesQuery {
+    /// Dialect whose dictionary transcribes the sentences (default: "us")
+    pub dialect: Option<String>,
+
+    /// Number of sentences to return (default: 5, at most 20)
+    pub count: Option<usize>,
+}
+
+/// Sentences for drilling one symbol, densest in it first
+#[derive(Debug, Serialize)]
+pub struct IpaSentencesResponse {
+    pub symbol: String,
+    pub dialect: String,
+    pub sentences: Vec<DrillSentenceDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DrillSentenceDetail {
+    pub text: String,
+
+    /// Dictionary transcription of the sentence, words separated by spaces
+    pub ipa: String,
+
+    /// Occurrences of the symbol in the transcription
+    pub target_count: usize,
+
+    /// Share of the sentence's phonemes that are the symbol
+    pub density: f64,
+
+    /// Whether the sentence was filled from a template rather than the built-in corpus
+    pub generated: bool,
+
+    /// Endpoint returning the sentence spoken by the reference voice
+    pub reference_audio_url: String,
+}
+
+/// Longest typed transcription checked, in characters
+pub(crate) const MAX_IPA_INPUT_CHARS: usize = 500;
+
+/// Request to check a typed IPA transcription
+#[derive(Debug, Deserialize)]
+pub struct IpaValidateRequest {
+    pub ipa: String,
+}
+
+/// Which symbols of a typed transcription are usable, with corrections for the rest
+#[derive(Debug, Serialize)]
+pub struct IpaValidationResponse {
+    /// The transcription with slashes, brackets and ASCII stand-ins cleaned up
+    pub normalized: String,
+
+    pub valid: bool,
+
+    /// Symbols of the normalized transcription, without word or syllable boundaries
+    pub symbols: Vec<String>,
+
+    pub invalid: Vec<InvalidSymbolDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvalidSymbolDetail {
+    pub symbol: String,
+
+    /// Character offsets of the symbol in the normalized transcription
+    pub start: usize,
+    pub end: usize,
+
+    /// "unknown" for symbols outside the phoneme table, or "unspeakable" for
+    /// symbols using characters the synthesizer does not read
+    pub reason: &'static str,
+
+    /// Valid symbols the learner may have meant, closest first
+    pub suggestions: Vec<String>,
+}
+
+impl From<InvalidSymbol> for InvalidSymbolDetail {
+    fn from(invalid: InvalidSymbol) -> Self {
+        Self {
+            symbol: invalid.symbol.symbol,
+            start: invalid.symbol.start,
+            end: invalid.symbol.end,
+            reason: invalid.reason.as_str(),
+            suggestions: invalid.suggestions,
+        }
+    }
+}
+
+/// The IPA chart, laid out from the same phoneme features used in scoring
+#[derive(Debug, Serialize)]
+pub struct IpaChartResponse {
+    /// Rows of the consonant grid, top to bottom
+    pub consonant_rows: Vec<ConsonantRowDetail>,
+
+    /// Places labelling the columns of the consonant grid, front of the mouth to back
+    pub consonant_columns: Vec<&'static str>,
+
+    pub consonants: Vec<ConsonantCellDetail>,
+
+    /// Monophthongs on the vowel trapezoid
+    pub vowels: Vec<VowelPointDetail>,
+
+    /// Diphthongs, drawn as glides between two vowels of the trapezoid
+    pub diphthongs: Vec<DiphthongDetail>,
+
+    pub diacritics: Vec<DiacriticDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsonantRowDetail {
+    pub manner: &'static str,
+    pub lateral: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsonantCellDetail {
+    #[serde(flatten)]
+    pub symbol: ChartSymbolDetail,
+
+    /// Indices into `consonant_rows` and `consonant_columns`
+    pub row: usize,
+    pub column: usize,
+
+    /// Voiced consonants go on the right of the cell, voiceless on the left
+    pub voiced: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VowelPointDetail {
+    #[serde(flatten)]
+    pub symbol: ChartSymbolDetail,
+
+    /// From front (0.0) to back (1.0)
+    pub x: f64,
+
+    /// From close (0.0) to open (1.0)
+    pub y: f64,
+
+    /// Rounded vowels go on the right of their point, unrounded on the left
+    pub rounded: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiphthongDetail {
+    #[serde(flatten)]
+    pub symbol: ChartSymbolDetail,
+
+    /// Vowels the glide starts and ends at
+    pub onset: &'static str,
+    pub offset: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiacriticDetail {
+    pub mark: String,
+
+    /// What the mark adds, e.g. "aspirated"
+    pub name: &'static str,
+
+    /// `name` in the requested locale
+    pub description: String,
+
+    /// The mark on a typical symbol
+    pub example: &'static str,
+}
+
+/// What every symbol of the chart links to
+#[derive(Debug, Serialize)]
+pub struct ChartSymbolDetail {
+    pub symbol: &'static str,
+
+    /// Conventional description, e.g. "voiceless dental fricative"
+    pub description: String,
+
+    pub features: PhonemeFeatures,
+
+    /// Endpoint returning the symbol's articulation metadata and examples
+    pub info_url: String,
+
+    /// Endpoints returning an example word spoken in each dialect
+    pub audio_url: DialectAudio,
+}
+
+/// Feature similarity of every pair of phonemes, for visualizing phoneme distances
+#[derive(Debug, Serialize)]
+pub struct SimilarityMatrixResponse {
+    /// Phonemes labelling the rows and columns
+    pub symbols: Vec<&'static str>,
+
+    /// `similarities[i][j]` is how alike `symbols[i]` and `symbols[j]` are, from 0 to 1
+    pub similarities: Vec<Vec<f64>>,
+
+    /// Feature weights the similarities were computed with
+    pub weights: SimilarityWeights,
+}
+
+/// Handle requests for the similarity matrix used when scoring
+pub async fn similarity_matrix() -> Json<SimilarityMatrixResponse> {
+    let symbols = SIMILARITY_MATRIX.symbols().to_vec();
+    let similarities = symbols
+        .iter()
+        .filter_map(|symbol| SIMILARITY_MATRIX.row(symbol))
+        .map(<[f64]>::to_vec)
+        .collect();
+
+    Json(SimilarityMatrixResponse {
+        symbols,
+        similarities,
+        weights: *SIMILARITY_MATRIX.weights(),
+    })
+}
+
+/// Handle requests for the whole IPA chart, with metadata and audio for each symbol
+pub async fn chart(
+    headers: HeaderMap,
+    Query(query): Query<IpaInfoQuery>,
+) -> Result<Json<IpaChartResponse>, Error> {
+    let locale = request_locale(query.locale.as_deref(), &headers)?;
+
+    Ok(Json(IpaChartResponse {
+        consonant_rows: CONSONANT_ROWS
+            .iter()
+            .map(|&(manner, lateral)| ConsonantRowDetail {
+                manner: manner.as_str(),
+                lateral,
+            })
+            .collect(),
+        consonant_columns: CONSONANT_COLUMNS
+            .iter()
+            .map(|place| place.as_str())
+            .collect(),
+        consonants: consonants()
+            .into_iter()
+            .filter_map(|cell| {
+                Some(ConsonantCellDetail {
+                    symbol: chart_symbol(cell.symbol, locale)?,
+                    row: cell.row,
+                    column: cell.column,
+                    voiced: cell.voiced,
+                })
+            })
+            .collect(),
+        vowels: vowels()
+            .into_iter()
+            .filter_map(|point| {
+                Some(VowelPointDetail {
+                    symbol: chart_symbol(point.symbol, locale)?,
+                    x: point.x,
+                    y: point.y,
+                    rounded: point.rounded,
+                })
+            })
+            .collect(),
+        diphthongs: diphthongs()
+            .into_iter()
+            .filter_map(|glide| {
+                Some(DiphthongDetail {
+                    symbol: chart_symbol(glide.symbol, locale)?,
+                    onset: glide.onset,
+                    offset: glide.offset,
+                })
+            })
+            .collect(),
+        diacritics: DIACRITICS
+            .iter()
+            .map(|diacritic| DiacriticDetail {
+                mark: diacritic.mark.to_string(),
+                name: diacritic.name,
+                description: localize(locale, &format!("term-{}", diacritic.name), &[]),
+                example: diacritic.example,
+            })
+            .collect(),
+    }))
+}
+
+fn chart_symbol(symbol: &'static str, locale: Locale) -> Option<ChartSymbolDetail> {
+    let parsed = parse_ipa(symbol)?;
+    let encoded = percent_encode(symbol);
+
+    Some(ChartSymbolDetail {
+        symbol,
+        description: describe_phoneme(&parsed, locale),
+        features: parsed.features,
+        info_url: format!("/api/ipa/{}", encoded),
+        audio_url: DialectAudio {
+            us: format!("/api/ipa/{}/audio?dialect=us", encoded),
+            uk: format!("/api/ipa/{}/audio?dialect=uk", encoded),
+        },
+    })
+}
+
+/// Handle requests to check IPA typed in transcription exercises
+///
+/// Symbols must have known phonetic features and be readable by the
+/// synthesizer, so every accepted transcription can be scored and played back.
+pub async fn validate(
+    Json(request): Json<IpaValidateRequest>,
+) -> Result<Json<IpaValidationResponse>, Error> {
+    if request.ipa.chars().count() > MAX_IPA_INPUT_CHARS {
+        return Err(Error::BadRequest(format!(
+            "IPA must be at most {} characters",
+            MAX_IPA_INPUT_CHARS
+        )));
+    }
+
+    let validation = validate_ipa(&request.ipa, |c| VOCABULARY.contains_key(&c));
+
+    Ok(Json(IpaValidationResponse {
+        valid: validation.is_valid(),
+        normalized: validation.normalized,
+        symbols: validation
+            .symbols
+            .into_iter()
+            .map(|symbol| symbol.symbol)
+            .collect(),
+        invalid: validation
+            .invalid
+            .into_iter()
+            .map(InvalidSymbolDetail::from)
+            .collect(),
+    }))
+}
+
+/// Handle requests for a symbol's articulation metadata
+pub async fn symbol_info(
+    headers: HeaderMap,
+    Path(symbol): Path<String>,
+    Query(query): Query<IpaInfoQuery>,
+) -> Result<Json<IpaSymbolResponse>, Error> {
+    let locale = request_locale(query.locale.as_deref(), &headers)?;
+    let info = articulation_info_in(&symbol, locale)
+        .ok_or_else(|| Error::NotFound(format!("Unknown IPA symbol: {}", symbol)))?;
+
+    let lookup = symbol.clone();
+    let (us, uk) = tokio::task::spawn_blocking(move || {
+        example_words(&lookup, MfaDialect::AmericanEnglish)
+            .and_then(|us| example_words(&lookup, MfaDialect::BritishEnglish).map(|uk| (us, uk)))
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Example lookup failed: {}", e)))?
+    .map_err(|e| Error::InternalServerError(format!("Failed to load dictionary: {}", e)))?;
+
+    let hints = [
+        info.place
+            .map(|(place, hint)| ("place", place.as_str(), hint)),
+        info.manner
+            .map(|(manner, hint)| ("manner", manner.as_str(), hint)),
+        info.height
+            .map(|(height, hint)| ("height", height.as_str(), hint)),
+        info.backness
+            .map(|(backness, hint)| ("backness", backness.as_str(), hint)),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(category, value, hint)| ArticulationHint {
+        category,
+        value,
+        hint,
+    })
+    .collect();
+
+    let encoded = percent_encode(&symbol);
+
+    Ok(Json(IpaSymbolResponse {
+        symbol: info.symbol,
+        description: info.description,
+        features: info.features,
+        hints,
+        examples: DialectExamples {
+            us: us.into_iter().map(example_detail).collect(),
+            uk: uk.into_iter().map(example_detail).collect(),
+        },
+        audio_url: DialectAudio {
+            us: format!("/api/ipa/{}/audio?dialect=us", encoded),
+            uk: format!("/api/ipa/{}/audio?dialect=uk", encoded),
+        },
+    }))
+}
+
+/// Handle requests for a spoken example of a symbol
+pub async fn symbol_audio(
+    tenant: Option<Tenant>,
+    headers: HeaderMap,
+    Path(symbol): Path<String>,
+    Query(query): Query<IpaAudioQuery>,
+) -> Result<Response, Error> {
+    let dialect = parse_dialect(query.dialect.as_deref().unwrap_or("us"))?;
+
+    if articulation_info(&symbol).is_none() {
+        return Err(Error::NotFound(format!("Unknown IPA symbol: {}", symbol)));
+    }
+
+    let voice = tenant_reference_voice(tenant.as_ref(), dialect);
+    let lookup = symbol.clone();
+    let span = tracing::Span::current();
+    let wav_data = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, Error> {
+        let _span = span.enter();
+
+        let example = example_words(&lookup, dialect)
+            .map_err(|e| Error::InternalServerError(format!("Failed to load dictionary: {}", e)))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::NotFound(format!("No example word for /{}/", lookup)))?;
+
+        let tts = get_tts()
+            .map_err(|e| Error::InternalServerError(format!("TTS initialization error: {}", e)))?;
+        let audio = tts
+            .process_tts(&example.word, &voice, 1.0)
+            .map_err(|e| Error::InternalServerError(format!("TTS processing error: {}", e)))?;
+        let samples = audio.as_slice().ok_or_else(|| {
+            Error::InternalServerError("Failed to convert audio data".to_string())
+        })?;
+
+        tts.audio_to_wav(samples)
+            .map_err(|e| Error::InternalServerError(e.to_string()))
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Synthesis task failed: {}", e)))??;
+
+    Ok(AudioClip::wav(wav_data, synthesis_modified(), SYNTHESIZED_CACHE_CONTROL).respond(&headers))
+}
+
+/// Handle requests for sentences dense in a symbol, for targeted drilling
+pub async fn symbol_sentences(
+    tenant: Option<Tenant>,
+    Path(symbol): Path<String>,
+    Query(query): Query<IpaSentencesQuery>,
+) -> Result<Json<IpaSentencesResponse>, Error> {
+    let dialect_code = query.dialect.as_deref().unwrap_or("us").to_lowercase();
+    let dialect = parse_dialect(&dialect_code)?;
+
+    let count = query.count.unwrap_or(5);
+    if count == 0 || count > MAX_DRILL_SENTENCES {
+        return Err(Error::BadRequest(format!(
+            "count must be between 1 and {}",
+            MAX_DRILL_SENTENCES
+        )));
+    }
+
+    let info = articulation_info(&symbol)
+        .ok_or_else(|| Error::NotFound(format!("Unknown IPA symbol: {}", symbol)))?;
+
+    let lookup = symbol.clone();
+    let sentences = tokio::task::spawn_blocking(move || drill_sentences(&lookup, dialect, count))
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Sentence lookup failed: {}", e)))?
+        .map_err(|e| Error::InternalServerError(format!("Failed to load dictionary: {}", e)))?;
+
+    if sentences.is_empty() {
+        return Err(Error::NotFound(format!(
+            "No {} words contain /{}/",
+            dialect_code, symbol
+        )));
+    }
+
+    let voice = tenant_reference_voice(tenant.as_ref(), dialect);
+    let sentences = sentences
+        .into_iter()
+        .map(|sentence| drill_detail(sentence, voice.name()))
+        .collect();
+
+    Ok(Json(IpaSentencesResponse {
+        symbol: info.symbol,
+        dialect: dialect_code,
+        sentences,
+    }))
+}
+
+fn drill_detail(sentence: DrillSentence, voice: &str) -> DrillSentenceDetail {
+    DrillSentenceDetail {
+        ipa: sentence
+            .words
+            .iter()
+            .map(|(_, phonemes)| phonemes.join(""))
+            .collect::<Vec<_>>()
+            .join(" "),
+        target_count: sentence.target_count,
+        density: sentence.density(),
+        generated: sentence.generated,
+        reference_audio_url: format!(
+            "/api/tts?text={}&voice={}",
+            percent_encode(&sentence.text),
+            voice
+        ),
+        text: sentence.text,
+    }
+}
+
+fn example_detail(example: ExampleWord) -> ExampleWordDetail {
+    ExampleWordDetail {
+        word: example.word,
+        ipa: example.phonemes.join(""),
+    }
+}
+
+/// Percent-encode a path segment or query value, since IPA symbols are not URL-safe
+pub(crate) fn percent_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,289 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    extract::{Json, Path, Query},
+    http::{HeaderMap, StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use serde::{Deserialize, Serialize};
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+
+use crate::bundle::bundle_body;
+use crate::error::Error;
+use crate::handlers::narration::Chapter;
+use crate::identity::Session;
+use crate::jobs::{JOBS, Job, JobEvent, JobKind, JobOutput};
+use crate::media::{AudioClip, JOB_CACHE_CONTROL};
+use crate::report::{REPORT_FONT, render_html, render_pdf};
+
+/// How often an idle event stream sends a comment to keep proxies from closing it
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Where to follow a newly created job
+#[derive(Debug, Serialize)]
+pub struct JobCreatedResponse {
+    pub job_id: String,
+    pub status_url: String,
+    pub events_url: String,
+    pub result_url: String,
+}
+
+/// Query for the assessment report endpoint
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    /// "html" or "pdf" (default: "html")
+    pub format: Option<String>,
+}
+
+/// Query for the assessment segment endpoint, in seconds from the start of the recording
+#[derive(Debug, Deserialize)]
+pub struct SegmentQuery {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Current state of a job
+#[derive(Debug, Serialize)]
+pub struct JobStatusResponse {
+    pub id: String,
+    pub kind: JobKind,
+
+    /// Most recent event
+    pub latest: Option<JobEvent>,
+
+    /// Every event so far, oldest first
+    pub events: Vec<JobEvent>,
+}
+
+/// Accepted response pointing the client at a job's endpoints
+pub(crate) fn job_created(job: &Job) -> (StatusCode, Json<JobCreatedResponse>) {
+    (
+        StatusCode::ACCEPTED,
+        Json(JobCreatedResponse {
+            job_id: job.id.clone(),
+            status_url: format!("/api/jobs/{}", job.id),
+            events_url: format!("/api/jobs/{}/events", job.id),
+            result_url: format!("/api/jobs/{}/result", job.id),
+        }),
+    )
+}
+
+/// The job with this id, unless it belongs to another user
+///
+/// Other users' jobs are reported as unknown rather than forbidden, so their
+/// ids cannot be probed.
+fn find_job(id: &str, session: Option<&Session>) -> Result<Arc<Job>, Error> {
+    JOBS.get(id)
+        .filter(|job| match &job.owner {
+            Some(owner) => session.is_some_and(|session| &session.subject == owner),
+            None => true,
+        })
+        .ok_or_else(|| Error::NotFound(format!("Unknown job: {}", id)))
+}
+
+/// Handle requests for a job's status
+pub async fn status(
+    session: Option<Session>,
+    Path(id): Path<String>,
+) -> Result<Json<JobStatusResponse>, Error> {
+    let job = find_job(&id, session.as_ref())?;
+    let events = job.events();
+
+    Ok(Json(JobStatusResponse {
+        id: job.id.clone(),
+        kind: job.kind,
+        latest: events.last().cloned(),
+        events,
+    }))
+}
+
+/// Handle requests to stream a job's progress as server-sent events
+///
+/// Past events are replayed first, and the stream ends after the job's
+/// `done` or `failed` event. Each event is named after its stage.
+pub async fn events(
+    session: Option<Session>,
+    Path(id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+    let job = find_job(&id, session.as_ref())?;
+    let (history, receiver) = job.subscribe();
+
+    // Subscribers that fall behind skip the missed events rather than failing
+    let live = BroadcastStream::new(receiver).filter_map(Result::ok);
+    let stream = tokio_stream::iter(history)
+        .chain(live)
+        .map(|event| Ok(sse_event(&event)));
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
+
+fn sse_event(event: &JobEvent) -> Event {
+    Event::default()
+        .event(event.stage.as_str())
+        .json_data(event)
+        .unwrap_or_else(|_| Event::default().event(event.stage.as_str()))
+}
+
+/// Handle requests for a finished job's output
+///
+/// TTS and narration jobs return WAV audio, or TTS jobs a zip bundle when
+/// requested with `format=zip`, and assessment jobs their JSON assessment.
+pub async fn result(
+    session: Option<Session>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Response, Error> {
+    let job = find_job(&id, session.as_ref())?;
+
+    match job.output() {
+        Some(JobOutput::Audio(wav_data)) => {
+            let finished = job.finished().unwrap_or_else(SystemTime::now);
+            Ok(AudioClip::wav(wav_data, finished, JOB_CACHE_CONTROL).respond(&headers))
+        }
+        Some(JobOutput::Narration(narration)) => {
+            let finished = job.finished().unwrap_or_else(SystemTime::now);
+            Ok(AudioClip::wav(narration.audio, finished, JOB_CACHE_CONTROL).respond(&headers))
+        }
+        Some(JobOutput::Assessment(assessed)) => Ok(Json(assessed.response).into_response()),
+        Some(JobOutput::LessonAudio(manifest)) => Ok(Json(manifest).into_response()),
+        Some(JobOutput::Bundle(bundle)) => {
+            let len = bundle
+                .zip_len()
+                .map_err(|e| Error::InternalServerError(e.to_string()))?;
+            let disposition = format!("attachment; filename=\"tts-{}.zip\"", job.id);
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "application/zip".to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                    (header::CONTENT_LENGTH, len.to_string()),
+                    (header::CACHE_CONTROL, JOB_CACHE_CONTROL.to_string()),
+                ],
+                bundle_body(bundle),
+            )
+                .into_response())
+        }
+        None => Err(unfinished(&job)),
+    }
+}
+
+/// Handle requests for where each section starts in a finished narration job's audio
+pub async fn chapters(
+    session: Option<Session>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<Chapter>>, Error> {
+    let job = find_job(&id, session.as_ref())?;
+
+    match job.output() {
+        Some(JobOutput::Narration(narration)) => Ok(Json(narration.chapters)),
+        Some(_) => Err(Error::NotFound(format!("Job {} is not a narration", id))),
+        None => Err(unfinished(&job)),
+    }
+}
+
+/// Handle requests for a finished assessment job as a shareable HTML or PDF report
+///
+/// PDF reports need a font covering the IPA symbols, configured with `REPORT_FONT`.
+pub async fn report(
+    session: Option<Session>,
+    Path(id): Path<String>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Response, Error> {
+    let job = find_job(&id, session.as_ref())?;
+    let assessed = match job.output() {
+        Some(JobOutput::Assessment(assessed)) => assessed,
+        Some(_) => return Err(Error::NotFound(format!("Job {} is not an assessment", id))),
+        None => return Err(unfinished(&job)),
+    };
+
+    match query.format.as_deref().unwrap_or("html") {
+        "html" => Ok((
+            [
+                (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+                (header::CACHE_CONTROL, JOB_CACHE_CONTROL),
+            ],
+            render_html(&assessed),
+        )
+            .into_response()),
+        "pdf" => {
+            let font = REPORT_FONT.as_deref().ok_or_else(|| {
+                Error::ServiceUnavailable("PDF reports are not configured".to_string())
+            })?;
+            let pdf = tokio::task::spawn_blocking(move || render_pdf(&assessed, font))
+                .await
+                .map_err(|e| Error::InternalServerError(format!("Report task failed: {}", e)))?
+                .map_err(|e| Error::InternalServerError(format!("{:#}", e)))?;
+
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "application/pdf".to_string()),
+                    (header::CACHE_CONTROL, JOB_CACHE_CONTROL.to_string()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"assessment-{}.pdf\"", job.id),
+                    ),
+                ],
+                pdf,
+            )
+                .into_response())
+        }
+        format => Err(Error::BadRequest(format!(
+            "Unsupported report format: {} (expected html or pdf)",
+            format
+        ))),
+    }
+}
+
+/// Handle requests for part of a finished assessment job's recording as WAV
+///
+/// `start` and `end` are usually the times of a phoneme or word in the
+/// assessment, so learners can replay exactly what was heard.
+pub async fn segment(
+    session: Option<Session>,
+    Path(id): Path<String>,
+    Query(query): Query<SegmentQuery>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let SegmentQuery { start, end } = query;
+    if !(start.is_finite() && end.is_finite() && start >= 0.0 && start < end) {
+        return Err(Error::BadRequest(format!(
+            "Invalid segment {}..{}: start must be at least 0 and before end",
+            start, end
+        )));
+    }
+
+    let job = find_job(&id, session.as_ref())?;
+    let assessed = match job.output() {
+        Some(JobOutput::Assessment(assessed)) => assessed,
+        Some(_) => return Err(Error::NotFound(format!("Job {} is not an assessment", id))),
+        None => return Err(unfinished(&job)),
+    };
+
+    let wav_data = assessed
+        .segment(start, end)
+        .map_err(|e| Error::InternalServerError(format!("{:#}", e)))?
+        .ok_or_else(|| {
+            Error::BadRequest(format!(
+                "Segment {}..{} starts after the end of the recording",
+                start, end
+            ))
+        })?;
+
+    let finished = job.finished().unwrap_or_else(SystemTime::now);
+    Ok(AudioClip::wav(wav_data, finished, JOB_CACHE_CONTROL).respond(&headers))
+}
+
+/// Why a job without output has no result yet
+fn unfinished(job: &Job) -> Error {
+    let stage = job.latest().map(|event| event.stage);
+    match stage {
+        Some(stage) if stage.is_terminal() => {
+            Error::NotFound(format!("Job {} produced no result", job.id))
+        }
+        _ => Error::Conflict(format!("Job {} has not finished", job.id)),
+    }
+}
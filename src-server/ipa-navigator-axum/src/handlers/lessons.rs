@@ -0,0 +1,472 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use ipa_navigator_kokoro::{
+    error::TtsError,
+    normalize::NormalizeOptions,
+    prelude::SAMPLE_RATE,
+    voices::VoiceType,
+    wav::{WavFormat, encode_wav},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{Span, error, info};
+
+use crate::engines::TtsEngine;
+use crate::error::Error;
+use crate::handlers::jobs::{JobCreatedResponse, job_created};
+use crate::handlers::tts::{normalize_options, tenant_voice};
+use crate::identity::Session;
+use crate::jobs::{JOBS, Job, JobEvent, JobKind, JobOutput, JobStage};
+use crate::lesson_audio::{ClipSpec, LESSON_AUDIO, LessonAudioStore, wav_duration_secs};
+use crate::media::{AudioClip, SYNTHESIZED_CACHE_CONTROL};
+use crate::scheduler::{Admission, Priority, SCHEDULER, run_chunks};
+use crate::tenants::Tenant;
+
+/// Most sentences in one lesson
+const MAX_LESSON_SENTENCES: usize = 200;
+
+/// Longest sentence accepted, in characters
+const MAX_SENTENCE_CHARS: usize = 500;
+
+/// Most voices reading each sentence
+const MAX_LESSON_VOICES: usize = 6;
+
+/// Sentences of a lesson to read with a mix of voices
+#[derive(Debug, Deserialize)]
+pub struct LessonAudioRequest {
+    /// Echoed in the manifest, so clients can match it to their lesson
+    pub lesson_id: Option<String>,
+
+    pub sentences: Vec<String>,
+
+    /// Voices reading every sentence; the configured mix if not given
+    pub voices: Option<Vec<String>>,
+
+    pub speed: Option<f32>,
+
+    /// Comma-separated expansions or normalization stages to skip, e.g. "St.,numbers"
+    pub disable_expansions: Option<String>,
+}
+
+/// Every clip made for a lesson, by sentence
+#[derive(Debug, Clone, Serialize)]
+pub struct LessonManifest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lesson_id: Option<String>,
+    pub voices: Vec<&'static str>,
+    pub sentences: Vec<LessonSentence>,
+}
+
+/// A sentence of the lesson and its reading in each voice
+#[derive(Debug, Clone, Serialize)]
+pub struct LessonSentence {
+    pub text: String,
+    pub clips: Vec<LessonClip>,
+}
+
+/// A stored reading of a sentence
+#[derive(Debug, Clone, Serialize)]
+pub struct LessonClip {
+    /// Stable id, the same whenever the sentence is read the same way
+    pub id: String,
+    pub voice: &'static str,
+    pub language: &'static str,
+    pub duration_secs: f64,
+    /// Where the clip's WAV audio is served
+    pub url: String,
+    /// Whether the clip was already stored, so was not synthesized again
+    pub reused: bool,
+}
+
+/// Handle requests to make reference audio for a lesson in a background job
+///
+/// Every sentence is read by every voice in the mix, so learners hear each
+/// one in more than one accent. Clips already stored are reused. The job's
+/// result is a [`LessonManifest`] listing each clip's id and URL.
+pub async fn generate(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
+    session: Option<Session>,
+    Json(request): Json<LessonAudioRequest>,
+) -> Result<(StatusCode, Json<JobCreatedResponse>), Error> {
+    let speed = request.speed.unwrap_or(1.0);
+    if !(0.5..=2.0).contains(&speed) {
+        return Err(Error::BadRequest(
+            "Speed must be between 0.5 and 2.0".to_string(),
+        ));
+    }
+
+    let sentences: Vec<String> = request
+        .sentences
+        .iter()
+        .map(|sentence| sentence.trim().to_string())
+        .filter(|sentence| !sentence.is_empty())
+        .collect();
+    if sentences.is_empty() || sentences.len() > MAX_LESSON_SENTENCES {
+        return Err(Error::BadRequest(format!(
+            "A lesson needs between 1 and {} sentences",
+            MAX_LESSON_SENTENCES
+        )));
+    }
+    if sentences
+        .iter()
+        .any(|sentence| sentence.chars().count() > MAX_SENTENCE_CHARS)
+    {
+        return Err(Error::BadRequest(format!(
+            "Sentences must be at most {} characters",
+            MAX_SENTENCE_CHARS
+        )));
+    }
+
+    let voices = lesson_voices(
+        tenant.as_ref(),
+        request.voices.as_deref(),
+        &LESSON_AUDIO.config().voices,
+    )?;
+
+    let variant = request.disable_expansions.clone().unwrap_or_default();
+    let options = normalize_options(tenant.as_ref(), request.disable_expansions.as_deref());
+    let admission = SCHEDULER.admit(Priority::Batch)?;
+
+    let job = JOBS.create(JobKind::LessonAudio, session.map(|session| session.subject));
+    info!(
+        "Queued lesson audio job {} with {} sentences and {} voices, speed={}",
+        job.id,
+        sentences.len(),
+        voices.len(),
+        speed
+    );
+
+    let plan = LessonPlan {
+        lesson_id: request.lesson_id,
+        sentences,
+        voices,
+        speed,
+        variant,
+        tenant: tenant.as_ref().map(|tenant| tenant.id().to_string()),
+    };
+    let worker = job.clone();
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+
+        match read_lesson(
+            tts.as_ref(),
+            &LESSON_AUDIO,
+            &worker,
+            &admission,
+            &plan,
+            &options,
+        ) {
+            Ok(manifest) => worker.complete(JobOutput::LessonAudio(manifest)),
+            Err(e) => {
+                error!("Lesson audio job {} failed: {}", worker.id, e);
+                worker.fail(format!("TTS processing error: {}", e));
+            }
+        }
+    });
+
+    Ok(job_created(&job))
+}
+
+/// Handle requests for a stored lesson clip
+pub async fn clip(headers: HeaderMap, Path(id): Path<String>) -> Result<Response, Error> {
+    let lookup = id.clone();
+    let (wav_data, modified) = tokio::task::spawn_blocking(move || LESSON_AUDIO.get(&lookup))
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Clip lookup failed: {}", e)))?
+        .map_err(|e| Error::InternalServerError(format!("Failed to read clip: {}", e)))?
+        .ok_or_else(|| Error::NotFound(format!("No lesson clip {}", id)))?;
+
+    Ok(AudioClip::wav(wav_data, modified, SYNTHESIZED_CACHE_CONTROL).respond(&headers))
+}
+
+/// Voices named in the request, or the configured mix, as the tenant may use them
+///
+/// A voice of the mix the tenant may not use is replaced by the closest it
+/// may, and voices named in the request must all be allowed.
+fn lesson_voices(
+    tenant: Option<&Tenant>,
+    requested: Option<&[String]>,
+    mix: &[VoiceType],
+) -> Result<Vec<VoiceType>, Error> {
+    let voices: Vec<VoiceType> = match requested {
+        Some(names) => names
+            .iter()
+            .map(|name| tenant_voice(tenant, name))
+            .collect::<Result<_, _>>()
+            .map_err(Error::BadRequest)?,
+        None => mix
+            .iter()
+            .map(|&voice| tenant.map_or(voice, |tenant| tenant.voice_or(voice)))
+            .collect(),
+    };
+
+    let mut unique = Vec::with_capacity(voices.len());
+    for voice in voices {
+        if !unique.contains(&voice) {
+            unique.push(voice);
+        }
+    }
+    if unique.is_empty() || unique.len() > MAX_LESSON_VOICES {
+        return Err(Error::BadRequest(format!(
+            "A lesson needs between 1 and {} voices",
+            MAX_LESSON_VOICES
+        )));
+    }
+    Ok(unique)
+}
+
+/// What a lesson audio job reads, and everything its clip ids depend on
+struct LessonPlan {
+    lesson_id: Option<String>,
+    sentences: Vec<String>,
+    voices: Vec<VoiceType>,
+    speed: f32,
+    variant: String,
+    tenant: Option<String>,
+}
+
+impl LessonPlan {
+    fn spec<'a>(&'a self, sentence: &'a str, voice: VoiceType) -> ClipSpec<'a> {
+        ClipSpec {
+            text: sentence,
+            voice,
+            speed: self.speed,
+            variant: &self.variant,
+            tenant: self.tenant.as_deref(),
+        }
+    }
+}
+
+/// Read every sentence in every voice, storing the clips not already stored
+///
+/// Clips are synthesized several at once if the model has the sessions, each
+/// taking an inference slot, so waiting interactive requests run between them.
+fn read_lesson(
+    tts: &dyn TtsEngine,
+    store: &LessonAudioStore,
+    job: &Job,
+    admission: &Admission,
+    plan: &LessonPlan,
+    options: &NormalizeOptions,
+) -> Result<LessonManifest, TtsError> {
+    // Every reading, sentence by sentence, with the duration of any already stored
+    let readings: Vec<(usize, VoiceType, String, Option<f64>)> = plan
+        .sentences
+        .iter()
+        .enumerate()
+        .flat_map(|(index, sentence)| {
+            plan.voices.iter().map(move |&voice| {
+                let id = plan.spec(sentence, voice).id();
+                let stored = store
+                    .get(&id)
+                    .ok()
+                    .flatten()
+                    .and_then(|(wav_data, _)| wav_duration_secs(&wav_data));
+                (index, voice, id, stored)
+            })
+        })
+        .collect();
+
+    let missing: Vec<&(usize, VoiceType, String, Option<f64>)> = readings
+        .iter()
+        .filter(|(_, _, _, stored)| stored.is_none())
+        .collect();
+    let total = missing.len();
+    let durations = run_chunks(
+        admission,
+        &missing,
+        SCHEDULER.max_parallel_inferences(),
+        |(index, voice, id, _)| {
+            let samples = tts.synthesize(&plan.sentences[*index], voice, plan.speed, options)?;
+            store.put(id, &encode_wav(&samples, &WavFormat::default())?)?;
+            Ok::<_, TtsError>(samples.len() as f64 / SAMPLE_RATE as f64)
+        },
+        |done| {
+            job.emit(
+                JobEvent::new(
+                    JobStage::Synthesizing,
+                    format!("Clip {}/{} synthesized", done, total),
+                )
+                .with_progress(done, total),
+            )
+        },
+    )?;
+
+    let mut durations = durations.into_iter();
+    let mut sentences: Vec<LessonSentence> = plan
+        .sentences
+        .iter()
+        .map(|text| LessonSentence {
+            text: text.clone(),
+            clips: Vec::with_capacity(plan.voices.len()),
+        })
+        .collect();
+    for (index, voice, id, stored) in readings {
+        let duration_secs = stored.or_else(|| durations.next()).unwrap_or_default();
+        sentences[index].clips.push(LessonClip {
+            url: format!("/api/lessons/audio/{}", id),
+            id,
+            voice: voice.name(),
+            language: voice.language(),
+            duration_secs,
+            reused: stored.is_some(),
+        });
+    }
+
+    Ok(LessonManifest {
+        lesson_id: plan.lesson_id.clone(),
+        voices: plan.voices.iter().map(VoiceType::name).collect(),
+        sentences,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    use ipa_navigator_kokoro::tts::Synthesis;
+
+    use crate::lesson_audio::LessonAudioConfig;
+
+    /// Reads a tenth of a second per character, counting how often it is asked to
+    #[derive(Default)]
+    struct MockTts {
+        calls: AtomicUsize,
+    }
+
+    impl TtsEngine for MockTts {
+        fn synthesize_until(
+            &self,
+            text: &str,
+            voice: &VoiceType,
+            speed: f32,
+            options: &NormalizeOptions,
+            _deadline: Instant,
+        ) -> Result<Synthesis, TtsError> {
+            Ok(Synthesis {
+                samples: self.synthesize(text, voice, speed, options)?,
+                truncated: false,
+                sentences_synthesized: 1,
+                sentences_total: 1,
+            })
+        }
+
+        fn synthesize(
+            &self,
+            text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<Vec<f32>, TtsError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(vec![0.1; text.chars().count() * SAMPLE_RATE as usize / 10])
+        }
+
+        fn is_cached(
+            &self,
+            _text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<bool, TtsError> {
+            Ok(false)
+        }
+
+        fn synthesize_tokens(
+            &self,
+            _tokens: &[i64],
+            _voice: &VoiceType,
+            _speed: f32,
+        ) -> Result<Vec<f32>, TtsError> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn voice(name: &str) -> VoiceType {
+        VoiceType::from_name(name).unwrap()
+    }
+
+    #[test]
+    fn test_lesson_voices() {
+        let mix = [
+            voice("american_female_bella"),
+            voice("british_male_george"),
+            voice("american_female_bella"),
+        ];
+        let voices = lesson_voices(None, None, &mix).unwrap();
+        assert_eq!(voices, mix[..2]);
+
+        let requested = vec!["british_female_lily".to_string()];
+        let voices = lesson_voices(None, Some(&requested), &mix).unwrap();
+        assert_eq!(voices, vec![voice("british_female_lily")]);
+
+        let unknown = vec!["nobody".to_string()];
+        assert!(matches!(
+            lesson_voices(None, Some(&unknown), &mix),
+            Err(Error::BadRequest(_))
+        ));
+        assert!(matches!(
+            lesson_voices(None, Some(&[]), &mix),
+            Err(Error::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_read_lesson_reuses_stored_clips() {
+        let dir = env::temp_dir().join(format!("lesson-read-test-{}", std::process::id()));
+        let store = LessonAudioStore::new(LessonAudioConfig {
+            dir: dir.clone(),
+            voices: Vec::new(),
+        });
+        let plan = |sentences: &[&str]| LessonPlan {
+            lesson_id: Some("unit-1".to_string()),
+            sentences: sentences.iter().map(|s| s.to_string()).collect(),
+            voices: vec![voice("american_female_bella"), voice("british_male_george")],
+            speed: 1.0,
+            variant: String::new(),
+            tenant: None,
+        };
+        let tts = MockTts::default();
+        let options = NormalizeOptions::default();
+
+        let read = |plan: &LessonPlan| {
+            let job = JOBS.create(JobKind::LessonAudio, None);
+            let admission = SCHEDULER.admit(Priority::Batch).unwrap();
+            tokio::task::block_in_place(|| {
+                read_lesson(&tts, &store, &job, &admission, plan, &options)
+            })
+            .unwrap()
+        };
+
+        let manifest = read(&plan(&["The cat sat.", "A dog ran."]));
+        assert_eq!(tts.calls.load(Ordering::Relaxed), 4);
+        assert_eq!(manifest.lesson_id.as_deref(), Some("unit-1"));
+        assert_eq!(manifest.sentences.len(), 2);
+        let first = &manifest.sentences[0];
+        assert_eq!(first.clips.len(), 2);
+        assert_eq!(first.clips[0].voice, "american_female_bella");
+        assert_eq!(first.clips[1].language, "en");
+        assert!((first.clips[0].duration_secs - 1.2).abs() < 1e-3);
+        assert!(first.clips.iter().all(|clip| !clip.reused));
+        assert!(store.get(&first.clips[1].id).unwrap().is_some());
+
+        // Only the new sentence is synthesized, and the shared one keeps its ids
+        let again = read(&plan(&["The cat sat.", "A bird sang."]));
+        assert_eq!(tts.calls.load(Ordering::Relaxed), 6);
+        assert_eq!(again.sentences[0].clips[0].id, first.clips[0].id);
+        assert!(again.sentences[0].clips[0].reused);
+        assert!((again.sentences[0].clips[0].duration_secs - 1.2).abs() < 1e-3);
+        assert!(!again.sentences[1].clips[0].reused);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,11 +1,47 @@
-use axum::extract::Json;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use axum::{
+    extract::{Json, State},
+    http::{HeaderMap, StatusCode, header::ACCEPT_LANGUAGE},
+};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-// use ipa_navigator_mfa::{api::assess_pronunciation, docker::MfaDialect};
+use ipa_navigator_core::baseline::{MIN_CALIBRATION_ATTEMPTS, ScoredPhoneme, SpeakerBaseline};
+use ipa_navigator_kokoro::{
+    normalize::NormalizeOptions,
+    voices::VoiceType,
+    wav::{WavFormat, encode_wav},
+};
+use ipa_navigator_mfa::{
+    docker::MfaDialect,
+    feedback::{generate_feedback_in, generate_feedback_with_clarity},
+    g2p::transcript_fragment,
+    isolated::{assess_word as assess_isolated_word, target_word},
+    localization::{Locale, localize},
+    mfa_parser::MfaSegment,
+    pace::{DEFAULT_PACE_TOLERANCE, compare_pace},
+    scoring::{PhonemeAccuracy, Strictness, rubric, score_segments},
+    storage::{QuotaExceeded, with_owner},
+    timeline::AlignmentTiers,
+};
 
 use serde::{Deserialize, Serialize};
-use tracing::{error, info};
+use tracing::{Instrument, Span, error, info, warn};
+use uuid::Uuid;
 
+use crate::cache::{ASSESSMENT_CACHE, cache_key};
+use crate::config::scoring_profile;
+use crate::engines::{AssessmentEngine, TtsEngine};
 use crate::error::Error;
+use crate::handlers::jobs::{JobCreatedResponse, job_created};
+use crate::handlers::tts::{tenant_reference_voice, tenant_voice};
+use crate::identity::{AuthUser, Session};
+use crate::jobs::{JOBS, JobEvent, JobKind, JobOutput, JobStage};
+use crate::limits::ASSESS_ROUTES;
+use crate::practice::Practice;
+use crate::report::AssessedRecording;
+use crate::store;
+use crate::tenants::Tenant;
 
 /// Request for pronunciation assessment
 #[derive(Debug, Deserialize)]
@@ -19,89 +55,1422 @@ pub struct PronunciationRequest {
     /// Dialect for pronunciation comparison (default: "us")
     #[serde(default = "default_dialect")]
     pub dialect: String,
+
+    /// Scoring strictness: "beginner", "intermediate", or "strict" (default: the
+    /// configured scoring profile, "intermediate" unless changed)
+    #[serde(default = "default_strictness")]
+    pub strictness: String,
+
+    /// Language of the feedback: "en", "ms", or "zh" (default: from `Accept-Language`, else "en")
+    pub locale: Option<String>,
+
+    /// Index of the first word to assess, counting whitespace-separated words from 0
+    ///
+    /// With `end_word`, the recording holds only these words of the transcript,
+    /// as when retrying a single word; character ranges in the response still
+    /// index the whole transcript.
+    pub start_word: Option<usize>,
+
+    /// Index after the last word to assess (default: the end of the transcript)
+    pub end_word: Option<usize>,
+
+    /// Also score against the signed-in user's personal baseline (default: false)
+    #[serde(default)]
+    pub personalized: bool,
+
+    /// Also compare each word's duration with a reading by a reference voice (default: false)
+    #[serde(default)]
+    pub pace: bool,
+
+    /// Voice reading the reference for `pace`, as accepted by `/api/tts` (default: depends on dialect)
+    pub voice: Option<String>,
+}
+
+/// Request to assess a recording of a single word, as in flashcard drills
+#[derive(Debug, Deserialize)]
+pub struct WordAssessmentRequest {
+    /// Base64-encoded audio data (WAV format expected)
+    pub audio: String,
+
+    /// The one word the recording should hold
+    pub word: String,
+
+    /// Dialect for pronunciation comparison (default: "us")
+    #[serde(default = "default_dialect")]
+    pub dialect: String,
+
+    /// Scoring strictness, as for `/api/pronunciation` (default: the configured scoring profile)
+    #[serde(default = "default_strictness")]
+    pub strictness: String,
+
+    /// Language of the feedback: "en", "ms", or "zh" (default: from `Accept-Language`, else "en")
+    pub locale: Option<String>,
 }
 
 fn default_dialect() -> String {
     "us".to_string()
 }
 
+fn default_strictness() -> String {
+    scoring_profile().as_str().to_string()
+}
+
 /// Response for pronunciation assessment
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PronunciationResponse {
     /// Overall pronunciation score (0.0-1.0)
     pub overall_score: f64,
 
     /// Detailed assessment of each phoneme
     pub phoneme_details: Vec<PhonemeAssessmentDetail>,
+
+    /// Tips for the phonemes that were mispronounced, worst first
+    pub feedback: Vec<String>,
+
+    /// Whether the recording appears to be of a different sentence, in which case it is not scored
+    pub wrong_sentence_detected: bool,
+
+    /// Result of the speech recognition check, when enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript_check: Option<TranscriptCheckDetail>,
+
+    /// Score against the user's personal baseline, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub personalized: Option<PersonalizedScoreDetail>,
+
+    /// Each word's duration against the reference voice's, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pace: Option<PaceDetail>,
+
+    /// How the sentence ended compared with the pattern expected for it, when measured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intonation: Option<IntonationDetail>,
+
+    /// Loudness and clarity of each word, flagging those too quiet or muffled to assess
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clarity: Vec<WordClarityDetail>,
+
+    /// Expected and aligned phonemes on one time axis, to draw both as a timeline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiers: Option<AlignmentTiersDetail>,
+}
+
+/// Response for a single-word assessment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordAssessmentResponse {
+    pub word: String,
+
+    /// Whether the word was heard in the recording; if not, it is not scored
+    pub detected: bool,
+
+    /// How confidently the word was aligned and matched (0.0-1.0)
+    pub confidence: f64,
+
+    /// Pronunciation score of the word (0.0-1.0), 0.0 when not detected
+    pub overall_score: f64,
+
+    pub phoneme_details: Vec<PhonemeAssessmentDetail>,
+
+    /// Tips for the phonemes that were mispronounced, or to say the word again if not detected
+    pub feedback: Vec<String>,
 }
 
 /// Detailed information about an individual phoneme
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhonemeAssessmentDetail {
     pub expected: String,
     pub actual: String,
     pub score: f64,
     pub start_time: f64,
     pub end_time: f64,
+
+    /// Character range of the transcript spelling the expected phoneme, end exclusive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub char_start: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub char_end: Option<usize>,
+
+    /// How sure the aligner is of where the phoneme was said (0.0-1.0), if it reported a score
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+impl From<PhonemeAccuracy> for PhonemeAssessmentDetail {
+    fn from(detail: PhonemeAccuracy) -> Self {
+        Self {
+            expected: detail.expected,
+            actual: detail.actual,
+            score: detail.score,
+            start_time: detail.start_time,
+            end_time: detail.end_time,
+            char_start: detail.char_span.map(|span| span.start),
+            char_end: detail.char_span.map(|span| span.end),
+            confidence: detail.confidence,
+        }
+    }
+}
+
+/// The expected pronunciation and what the aligner found, as two parallel tiers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlignmentTiersDetail {
+    /// Dictionary phonemes with nominal durations, stretched over the span of the speech
+    pub expected: Vec<ExpectedSegmentDetail>,
+    /// Phones as aligned in the recording
+    pub actual: Vec<ActualSegmentDetail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpectedSegmentDetail {
+    pub phoneme: String,
+    pub word: String,
+    /// Typical duration of the phoneme in read speech, in seconds
+    pub nominal_duration: f64,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActualSegmentDetail {
+    pub phoneme: String,
+    /// Word the phone was aligned within, empty if none
+    pub word: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+impl From<AlignmentTiers> for AlignmentTiersDetail {
+    fn from(tiers: AlignmentTiers) -> Self {
+        Self {
+            expected: tiers
+                .expected
+                .into_iter()
+                .map(|segment| ExpectedSegmentDetail {
+                    phoneme: segment.phoneme,
+                    word: segment.word,
+                    nominal_duration: segment.nominal_duration,
+                    start_time: segment.start_time,
+                    end_time: segment.end_time,
+                })
+                .collect(),
+            actual: tiers
+                .actual
+                .into_iter()
+                .map(|segment| ActualSegmentDetail {
+                    phoneme: segment.phoneme,
+                    word: segment.word,
+                    start_time: segment.start_time,
+                    end_time: segment.end_time,
+                    confidence: segment.confidence,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What the speech recognition pass heard compared to the expected transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptCheckDetail {
+    pub heard: String,
+    pub similarity: f64,
+    pub diff: Vec<WordDiffDetail>,
+}
+
+/// How an attempt compares with the user's own earlier attempts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonalizedScoreDetail {
+    /// Attempts the baseline is calibrated from
+    pub attempts: usize,
+    /// Attempts needed before the baseline is calibrated
+    pub attempts_needed: usize,
+    /// Score relative to the baseline (0.0-1.0), where 0.5 is the user's usual level
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Overall score the user usually gets for these phonemes (0.0-1.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_score: Option<f64>,
+    /// Phonemes the user systematically pronounces differently, worst first
+    pub deviations: Vec<DeviationDetail>,
+}
+
+/// A phoneme the user usually realizes as another
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviationDetail {
+    pub phoneme: String,
+    /// Empty when the phoneme is usually left out
+    pub usual_realization: String,
+    /// Share of calibration attempts the realization was heard in
+    pub share: f64,
+    pub mean_score: f64,
+}
+
+/// How fast the recording was spoken compared with a reference voice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaceDetail {
+    pub voice: String,
+    /// Speaking time over the reference's, above 1.0 when slower
+    pub tempo_ratio: f64,
+    pub words: Vec<WordPaceDetail>,
+}
+
+/// One word's duration in the recording and in the reference, in seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordPaceDetail {
+    pub word: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub duration: f64,
+    pub reference_duration: f64,
+    /// Seconds longer than the reference took, negative when faster
+    pub delta: f64,
+    /// "too_fast", "on_pace", or "too_slow"
+    pub verdict: String,
+}
+
+/// Sentence-final pitch movement compared with the pattern expected for the sentence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntonationDetail {
+    /// "statement", "yes_no_question", or "wh_question"
+    pub sentence_type: String,
+    /// "rise", "level", or "fall"
+    pub expected_contour: String,
+    pub final_contour: String,
+    /// Pitch change over the last word, in semitones, positive when rising
+    pub final_change: f64,
+    pub matches_expected: bool,
+    /// Median pitch of the recording, in Hz
+    pub median_f0: f64,
+    pub words: Vec<WordPitchDetail>,
+}
+
+/// Pitch of one word, in Hz, absent if none of it was voiced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordPitchDetail {
+    pub word: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub mean_f0: Option<f64>,
+    pub min_f0: Option<f64>,
+    pub max_f0: Option<f64>,
+}
+
+/// How clearly one word was said
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordClarityDetail {
+    pub word: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// RMS level in dBFS, and relative to the recording's median word
+    pub rms_db: f64,
+    pub relative_db: f64,
+    /// High-frequency energy relative to the whole, in dB, lower when duller
+    pub spectral_tilt: f64,
+    /// 0.0 (inaudible or muffled) to 1.0 (clear)
+    pub clarity: f64,
+    /// "too_quiet", "trailing_off", or "muffled" when the word could not be assessed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue: Option<String>,
+}
+
+/// One word of the transcript diff
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordDiffDetail {
+    /// "match", "substitution", "insertion", or "deletion"
+    pub kind: String,
+    pub expected: Option<String>,
+    pub heard: Option<String>,
+}
+
+/// Parse a dialect code as used in requests ("us" or "uk")
+pub(crate) fn parse_dialect(dialect: &str) -> Result<MfaDialect, Error> {
+    match dialect.to_lowercase().as_str() {
+        "us" => Ok(MfaDialect::AmericanEnglish),
+        "uk" => Ok(MfaDialect::BritishEnglish),
+        _ => Err(Error::BadRequest(format!(
+            "Unsupported dialect: {}",
+            dialect
+        ))),
+    }
+}
+
+/// Locale to write learner-facing text in
+///
+/// A locale named in the request must be supported; otherwise the
+/// `Accept-Language` header is negotiated, falling back to English.
+pub(crate) fn request_locale(locale: Option<&str>, headers: &HeaderMap) -> Result<Locale, Error> {
+    if let Some(locale) = locale {
+        return Locale::parse(locale)
+            .ok_or_else(|| Error::BadRequest(format!("Unsupported locale: {}", locale)));
+    }
+
+    Ok(headers
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Locale::from_accept_language)
+        .unwrap_or_default())
+}
+
+/// A validated assessment request
+pub(crate) struct AssessmentInput {
+    pub audio: Vec<u8>,
+    /// The words aligned and scored
+    pub transcript: String,
+    /// The transcript as sent, of which `transcript` may be a fragment
+    pub full_transcript: String,
+    /// Character index in `full_transcript` that `transcript` starts at
+    pub char_offset: usize,
+    pub dialect: MfaDialect,
+    pub strictness: Strictness,
+    pub locale: Locale,
+    /// Voice to compare the pace of each word with, if asked for
+    pub pace_voice: Option<VoiceType>,
+    /// Tenant whose root the recording is written under for alignment, and whose cache serves it
+    pub tenant: Option<Tenant>,
+    /// Signed-in user the alignment's job directory is recorded as belonging to
+    pub user: Option<String>,
+}
+
+impl AssessmentInput {
+    pub fn parse(
+        request: PronunciationRequest,
+        headers: &HeaderMap,
+        tenant: Option<&Tenant>,
+        user: Option<String>,
+    ) -> Result<Self, Error> {
+        // Decode base64 audio data
+        let audio = BASE64
+            .decode(&request.audio)
+            .map_err(|e| Error::BadRequest(format!("Invalid audio data format: {}", e)))?;
+
+        // Determine dialect
+        let dialect = parse_dialect(&request.dialect)?;
+
+        let strictness = Strictness::parse(&request.strictness).ok_or_else(|| {
+            Error::BadRequest(format!("Unsupported strictness: {}", request.strictness))
+        })?;
+
+        let locale = request_locale(request.locale.as_deref(), headers)?;
+
+        let pace_voice = match (request.pace, request.voice.as_deref()) {
+            (false, _) => None,
+            (true, Some(voice)) => Some(tenant_voice(tenant, voice).map_err(Error::BadRequest)?),
+            (true, None) => Some(tenant_reference_voice(tenant, dialect)),
+        };
+
+        let (transcript, char_offset) = match (request.start_word, request.end_word) {
+            (None, None) => (request.transcript.clone(), 0),
+            (start, end) => {
+                let words = request.transcript.split_whitespace().count();
+                let range = start.unwrap_or(0)..end.unwrap_or(words);
+                let fragment =
+                    transcript_fragment(&request.transcript, range.clone()).ok_or_else(|| {
+                        Error::BadRequest(format!(
+                            "Word range {}..{} is not within the transcript's {} words",
+                            range.start, range.end, words
+                        ))
+                    })?;
+                (fragment.text.to_string(), fragment.char_offset)
+            }
+        };
+
+        Ok(Self {
+            audio,
+            transcript,
+            full_transcript: request.transcript,
+            char_offset,
+            dialect,
+            strictness,
+            locale,
+            pace_voice,
+            tenant: tenant.cloned(),
+            user,
+        })
+    }
 }
 
-/*
 /// Handle pronunciation assessment requests
 pub async fn assess(
+    State(engine): State<Arc<dyn AssessmentEngine>>,
+    State(tts): State<Arc<dyn TtsEngine>>,
+    session: Option<Session>,
+    tenant: Option<Tenant>,
+    headers: HeaderMap,
     Json(request): Json<PronunciationRequest>,
 ) -> Result<Json<PronunciationResponse>, Error> {
     info!(
-        "Processing pronunciation assessment request for text: '{}'",
-        request.transcript
+        "Processing pronunciation assessment request for {} chars of text",
+        request.transcript.chars().count()
     );
 
-    // Decode base64 audio data
-    let audio_data = BASE64
+    let owner = session.as_ref().map(|session| session.subject.clone());
+    let user = personalized_user(request.personalized, session).await?;
+    let input = AssessmentInput::parse(request, &headers, tenant.as_ref(), owner)?;
+    let mut response = run_assessment(engine, tts, input, |_| {}).await?;
+    if let Some(user) = &user {
+        response.personalized = personalized_score(user, &response).await;
+    }
+
+    Ok(Json(response))
+}
+
+/// Handle requests to assess a recording in the background
+///
+/// Responds at once with the job's URLs; progress is streamed from
+/// `/api/jobs/{id}/events` and the assessment read from `/api/jobs/{id}/result`,
+/// or as a shareable document from `/api/assess/jobs/{id}/report`; parts of the
+/// recording can be replayed from `/api/assess/jobs/{id}/segment`.
+/// A signed-in user's job is only visible to them.
+pub async fn assess_job(
+    State(engine): State<Arc<dyn AssessmentEngine>>,
+    State(tts): State<Arc<dyn TtsEngine>>,
+    session: Option<Session>,
+    tenant: Option<Tenant>,
+    headers: HeaderMap,
+    Json(request): Json<PronunciationRequest>,
+) -> Result<(StatusCode, Json<JobCreatedResponse>), Error> {
+    let owner = session.as_ref().map(|session| session.subject.clone());
+    let user = personalized_user(request.personalized, session).await?;
+    let input = AssessmentInput::parse(request, &headers, tenant.as_ref(), owner.clone())?;
+
+    let job = JOBS.create(JobKind::Assessment, owner);
+    info!("Queued assessment job {}", job.id);
+
+    let worker = job.clone();
+    tokio::spawn(
+        async move {
+            // The job outlives its request, so waits for an assessment slot itself
+            let _slot = ASSESS_ROUTES.acquire().await;
+            let progress = worker.clone();
+            // Kept for the job's report and segments
+            let (transcript, dialect, audio) = (
+                input.full_transcript.clone(),
+                input.dialect,
+                input.audio.clone(),
+            );
+            match run_assessment(engine, tts, input, move |event| progress.emit(event)).await {
+                Ok(mut response) => {
+                    if let Some(user) = &user {
+                        response.personalized = personalized_score(user, &response).await;
+                    }
+                    worker.complete(JobOutput::Assessment(Box::new(AssessedRecording::new(
+                        response, transcript, dialect, audio,
+                    ))))
+                }
+                Err(e) => worker.fail(e.to_string()),
+            }
+        }
+        .instrument(Span::current()),
+    );
+
+    Ok(job_created(&job))
+}
+
+/// Handle requests to assess a recording of a single word
+///
+/// A quicker path than `/api/pronunciation` for flashcard drills: the word is
+/// aligned and scored without transcript verification or the sentence-level
+/// analyses. If the alignment suggests the word was never said, the response
+/// says it was not detected instead of scoring it.
+pub async fn assess_word(
+    State(engine): State<Arc<dyn AssessmentEngine>>,
+    session: Option<Session>,
+    tenant: Option<Tenant>,
+    headers: HeaderMap,
+    Json(request): Json<WordAssessmentRequest>,
+) -> Result<Json<WordAssessmentResponse>, Error> {
+    let audio = BASE64
         .decode(&request.audio)
         .map_err(|e| Error::BadRequest(format!("Invalid audio data format: {}", e)))?;
+    let word = target_word(&request.word)
+        .ok_or_else(|| Error::BadRequest(format!("Expected a single word: {}", request.word)))?
+        .to_string();
+    let dialect = parse_dialect(&request.dialect)?;
+    let strictness = Strictness::parse(&request.strictness).ok_or_else(|| {
+        Error::BadRequest(format!("Unsupported strictness: {}", request.strictness))
+    })?;
+    let locale = request_locale(request.locale.as_deref(), &headers)?;
+    let rubric = rubric(strictness);
+    let user = session.map(|session| session.subject);
+
+    let span = Span::current();
+    let attempt = tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        with_owner(tenant.as_ref().map(Tenant::id), user.as_deref(), || {
+            let segments = engine.align(&audio, &word, dialect)?;
+            assess_isolated_word(&segments, &word, dialect, &rubric)
+        })
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Assessment task failed: {}", e)))?
+    .map_err(|e| {
+        error!("Word assessment error: {:?}", e);
+        alignment_error(e, "Failed to assess the word")
+    })?;
 
-    // Determine dialect
-    let dialect = match request.dialect.to_lowercase().as_str() {
-        "us" => MfaDialect::AmericanEnglish,
-        "uk" => MfaDialect::BritishEnglish,
-        _ => {
-            return Err(Error::BadRequest(format!(
-                "Unsupported dialect: {}",
-                request.dialect
-            )));
+    let Some(assessment) = attempt.assessment else {
+        info!(
+            "Word '{}' not detected, confidence {:.2}",
+            attempt.word, attempt.confidence
+        );
+        return Ok(Json(WordAssessmentResponse {
+            feedback: vec![localize(
+                locale,
+                "tip-word-not-detected",
+                &[("word", &attempt.word)],
+            )],
+            word: attempt.word,
+            detected: false,
+            confidence: attempt.confidence,
+            overall_score: 0.0,
+            phoneme_details: Vec::new(),
+        }));
+    };
+
+    Ok(Json(WordAssessmentResponse {
+        feedback: generate_feedback_in(&assessment.phoneme_details, locale),
+        word: attempt.word,
+        detected: true,
+        confidence: attempt.confidence,
+        overall_score: assessment.overall_score,
+        phoneme_details: assessment
+            .phoneme_details
+            .into_iter()
+            .map(PhonemeAssessmentDetail::from)
+            .collect(),
+    }))
+}
+
+/// The user to compare against their own baseline, if one was asked for
+///
+/// Checked before assessing, so a request that cannot be personalized fails fast.
+async fn personalized_user(
+    personalized: bool,
+    session: Option<Session>,
+) -> Result<Option<AuthUser>, Error> {
+    if !personalized {
+        return Ok(None);
+    }
+
+    let session = session.ok_or_else(|| {
+        Error::Unauthorized("Sign in to score against your own baseline".to_string())
+    })?;
+    AuthUser::from_session(session).await.map(Some)
+}
+
+/// Score a response against the baseline calibrated from the user's first attempts
+///
+/// Personal scoring is best-effort; if the attempts cannot be read the
+/// absolute score is still returned.
+async fn personalized_score(
+    user: &AuthUser,
+    response: &PronunciationResponse,
+) -> Option<PersonalizedScoreDetail> {
+    if response.wrong_sentence_detected {
+        return None;
+    }
+
+    let attempts =
+        async { Ok::<_, Error>(Practice::for_user(user)?.calibration_attempts().await?) };
+    let attempts: Vec<Vec<ScoredPhoneme>> = match attempts.await {
+        Ok(attempts) => attempts
+            .into_iter()
+            .map(|attempt| attempt.phonemes.into_iter().map(Into::into).collect())
+            .collect(),
+        Err(e) => {
+            warn!("Failed to read calibration attempts: {}", e);
+            return None;
         }
     };
 
-    info!("Using dialect: {:?}", dialect);
+    let Some(baseline) = SpeakerBaseline::calibrate(&attempts) else {
+        return Some(PersonalizedScoreDetail {
+            attempts: attempts.len(),
+            attempts_needed: MIN_CALIBRATION_ATTEMPTS,
+            score: None,
+            baseline_score: None,
+            deviations: Vec::new(),
+        });
+    };
+
+    let phonemes: Vec<ScoredPhoneme> = response
+        .phoneme_details
+        .iter()
+        .map(|detail| ScoredPhoneme {
+            expected: detail.expected.clone(),
+            actual: detail.actual.clone(),
+            score: detail.score,
+        })
+        .collect();
+    let score = baseline.score(&phonemes);
 
-    // Process through MFA
-    let assessment =
-        assess_pronunciation(&audio_data, &request.transcript, dialect).map_err(|e| {
-            error!("MFA processing error: {:?}", e);
-            Error::InternalServerError(format!("Failed to process pronunciation assessment: {}", e))
-        })?;
+    Some(PersonalizedScoreDetail {
+        attempts: baseline.attempts,
+        attempts_needed: MIN_CALIBRATION_ATTEMPTS,
+        score: score.map(|score| score.score),
+        baseline_score: score.map(|score| score.baseline),
+        deviations: baseline
+            .deviations()
+            .into_iter()
+            .map(|deviation| DeviationDetail {
+                phoneme: deviation.phoneme.to_string(),
+                usual_realization: deviation.usual_realization.to_string(),
+                share: deviation.share,
+                mean_score: deviation.mean_score,
+            })
+            .collect(),
+    })
+}
+
+/// Assess a recording with `engine`, reporting each stage to `progress`
+///
+/// When the input asks for pace, the transcript is read by its voice with
+/// `tts` and aligned too, so each word's duration can be compared.
+pub(crate) async fn run_assessment(
+    engine: Arc<dyn AssessmentEngine>,
+    tts: Arc<dyn TtsEngine>,
+    input: AssessmentInput,
+    progress: impl Fn(JobEvent) + Send + 'static,
+) -> Result<PronunciationResponse, Error> {
+    // Identical resubmissions are answered without aligning again
+    let key = cache_key(&input, engine.name());
+    if let Some(response) = ASSESSMENT_CACHE.get(&key, input.tenant.as_ref()).await {
+        info!("Serving cached assessment");
+        return Ok(place_in_transcript(response, input.char_offset));
+    }
+
+    let AssessmentInput {
+        audio: audio_data,
+        transcript,
+        char_offset,
+        dialect,
+        strictness,
+        locale,
+        pace_voice,
+        tenant,
+        user,
+        ..
+    } = input;
+    let rubric = rubric(strictness);
+
+    info!(
+        "Using dialect: {:?}, strictness: {}",
+        dialect,
+        strictness.as_str()
+    );
+
+    let engine_name = engine.name();
+
+    // Verify and align off the async runtime with the configured backends
+    let span = Span::current();
+    let tenant_id = tenant.as_ref().map(|tenant| tenant.id().to_string());
+    let (transcript_check, assessment, pace) = tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        with_owner(tenant_id.as_deref(), user.as_deref(), || {
+            // Verification is best-effort; a failing recogniser should not block scoring
+            let check = engine.verify(&audio_data, &transcript).unwrap_or_else(|e| {
+                warn!("Transcript verification failed: {:?}", e);
+                None
+            });
+
+            if check.as_ref().is_some_and(|c| c.wrong_sentence) {
+                return Ok((check, None, None));
+            }
+
+            progress(JobEvent::new(
+                JobStage::Aligning,
+                format!("Aligning with {}", engine.name()),
+            ));
+            let segments = engine.align(&audio_data, &transcript, dialect)?;
+
+            progress(JobEvent::new(JobStage::Scoring, "Scoring phonemes"));
+            let assessment = score_segments(&segments, &transcript, dialect, &rubric)?
+                .with_audio_analysis(&audio_data, &segments);
+
+            // Pace is best-effort too, so a failed reference reading still leaves the score
+            let pace = pace_voice.and_then(|voice| {
+                progress(JobEvent::new(
+                    JobStage::Synthesizing,
+                    format!("Reading the reference with {}", voice.name()),
+                ));
+                pace_detail(
+                    engine.as_ref(),
+                    tts.as_ref(),
+                    &segments,
+                    &transcript,
+                    dialect,
+                    voice,
+                )
+                .inspect_err(|e| warn!("Pace comparison failed: {:?}", e))
+                .ok()
+                .flatten()
+            });
+
+            Ok::<_, anyhow::Error>((check, Some(assessment), pace))
+        })
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Assessment task failed: {}", e)))?
+    .map_err(|e| {
+        error!("MFA processing error: {:?}", e);
+        alignment_error(e, "Failed to process pronunciation assessment")
+    })?;
+
+    let transcript_check = transcript_check.map(|check| TranscriptCheckDetail {
+        heard: check.heard,
+        similarity: check.similarity,
+        diff: check
+            .diff
+            .into_iter()
+            .map(|d| WordDiffDetail {
+                kind: d.kind.as_str().to_string(),
+                expected: d.expected,
+                heard: d.heard,
+            })
+            .collect(),
+    });
+
+    let Some(assessment) = assessment else {
+        info!("Recording does not match the expected transcript, skipping scoring");
+        let response = PronunciationResponse {
+            overall_score: 0.0,
+            phoneme_details: Vec::new(),
+            feedback: vec![localize(locale, "tip-wrong-sentence", &[])],
+            wrong_sentence_detected: true,
+            transcript_check,
+            personalized: None,
+            pace: None,
+            intonation: None,
+            clarity: Vec::new(),
+            tiers: None,
+        };
+        ASSESSMENT_CACHE.put(key, &response, tenant.as_ref());
+        return Ok(response);
+    };
 
     info!(
         "Pronunciation assessment complete, overall score: {:.2}%",
         assessment.overall_score * 100.0
     );
 
+    let feedback =
+        generate_feedback_with_clarity(&assessment.phoneme_details, &assessment.clarity, locale);
+    let clarity = assessment
+        .clarity
+        .into_iter()
+        .map(|word| WordClarityDetail {
+            word: word.word,
+            start_time: word.start_time,
+            end_time: word.end_time,
+            rms_db: word.rms_db,
+            relative_db: word.relative_db,
+            spectral_tilt: word.spectral_tilt,
+            clarity: word.clarity,
+            issue: word.issue.map(|issue| issue.as_str().to_string()),
+        })
+        .collect();
+    let intonation = assessment.intonation.map(|intonation| IntonationDetail {
+        sentence_type: intonation.sentence_type.as_str().to_string(),
+        expected_contour: intonation.expected_contour.as_str().to_string(),
+        final_contour: intonation.final_contour.as_str().to_string(),
+        final_change: intonation.final_change,
+        matches_expected: intonation.matches_expected(),
+        median_f0: intonation.median_f0,
+        words: intonation
+            .words
+            .into_iter()
+            .map(|word| WordPitchDetail {
+                word: word.word,
+                start_time: word.start_time,
+                end_time: word.end_time,
+                mean_f0: word.mean_f0,
+                min_f0: word.min_f0,
+                max_f0: word.max_f0,
+            })
+            .collect(),
+    });
+
     // Convert to API response format
     let response = PronunciationResponse {
         overall_score: assessment.overall_score,
+        feedback,
         phoneme_details: assessment
             .phoneme_details
             .into_iter()
-            .map(|detail| PhonemeAssessmentDetail {
-                expected: detail.expected,
-                actual: detail.actual,
-                score: detail.score,
-                start_time: detail.start_time,
-                end_time: detail.end_time,
-            })
+            .map(PhonemeAssessmentDetail::from)
             .collect(),
+        wrong_sentence_detected: false,
+        transcript_check,
+        personalized: None,
+        pace,
+        intonation,
+        clarity,
+        tiers: Some(assessment.tiers.into()),
     };
+    // Without the pace asked for, a retry should compare it again rather than be served this
+    if pace_voice.is_none() || response.pace.is_some() {
+        ASSESSMENT_CACHE.put(key, &response, tenant.as_ref());
+    }
 
-    Ok(Json(response))
+    // Kept without the transcript or audio, for research export
+    let details = response.phoneme_details.clone();
+    let recorded_at = SystemTime::now();
+    store::record(move |store| async move {
+        store
+            .phonemes_recorded(
+                &Uuid::new_v4().to_string(),
+                recorded_at,
+                dialect.dictionary_name(),
+                strictness.as_str(),
+                engine_name,
+                &details,
+            )
+            .await
+    });
+
+    Ok(place_in_transcript(response, char_offset))
+}
+
+/// Error response for a failed assessment, unavailable rather than failed when job storage is full
+fn alignment_error(e: anyhow::Error, action: &str) -> Error {
+    if e.downcast_ref::<QuotaExceeded>().is_some() {
+        Error::ServiceUnavailable(e.to_string())
+    } else {
+        Error::InternalServerError(format!("{}: {}", action, e))
+    }
+}
+
+/// Compare the pace of each aligned word with a reading of the transcript by `voice`
+fn pace_detail(
+    engine: &dyn AssessmentEngine,
+    tts: &dyn TtsEngine,
+    segments: &[MfaSegment],
+    transcript: &str,
+    dialect: MfaDialect,
+    voice: VoiceType,
+) -> anyhow::Result<Option<PaceDetail>> {
+    // Read as written, since the reference is aligned against the transcript as written
+    let samples = tts.synthesize(transcript, &voice, 1.0, &NormalizeOptions::default())?;
+    let reference_wav = encode_wav(&samples, &WavFormat::default())?;
+    let reference = engine.align(&reference_wav, transcript, dialect)?;
+
+    Ok(
+        compare_pace(segments, &reference, DEFAULT_PACE_TOLERANCE).map(|pace| PaceDetail {
+            voice: voice.name().to_string(),
+            tempo_ratio: pace.tempo_ratio(),
+            words: pace
+                .words
+                .into_iter()
+                .map(|word| WordPaceDetail {
+                    word: word.word,
+                    start_time: word.start_time,
+                    end_time: word.end_time,
+                    duration: word.duration,
+                    reference_duration: word.reference_duration,
+                    delta: word.delta,
+                    verdict: word.verdict.as_str().to_string(),
+                })
+                .collect(),
+        }),
+    )
+}
+
+/// Move character ranges from a fragment of the transcript to the whole transcript
+///
+/// Responses are cached by fragment, which may recur at other places in other
+/// transcripts, so ranges are only moved once the response is served.
+fn place_in_transcript(
+    mut response: PronunciationResponse,
+    char_offset: usize,
+) -> PronunciationResponse {
+    if char_offset > 0 {
+        for detail in &mut response.phoneme_details {
+            detail.char_start = detail.char_start.map(|start| start + char_offset);
+            detail.char_end = detail.char_end.map(|end| end + char_offset);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenants::{TenantConfig, Tenants};
+    use ipa_navigator_mfa::{
+        asr::{TranscriptCheck, compare_transcripts},
+        mfa_parser::MfaSegment,
+    };
+
+    /// Engine answering with a fixed transcript check and alignment
+    struct MockAssessment {
+        heard: Option<&'static str>,
+        alignment: Result<Vec<MfaSegment>, &'static str>,
+        /// Alignment of synthesized WAV audio, the reference read for pace
+        reference: Vec<MfaSegment>,
+    }
+
+    impl AssessmentEngine for MockAssessment {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn verify(
+            &self,
+            _audio: &[u8],
+            transcript: &str,
+        ) -> anyhow::Result<Option<TranscriptCheck>> {
+            Ok(self
+                .heard
+                .map(|heard| compare_transcripts(transcript, heard, 0.5)))
+        }
+
+        fn align(
+            &self,
+            audio: &[u8],
+            _transcript: &str,
+            _dialect: MfaDialect,
+        ) -> anyhow::Result<Vec<MfaSegment>> {
+            if audio.starts_with(b"RIFF") {
+                return Ok(self.reference.clone());
+            }
+            self.alignment.clone().map_err(anyhow::Error::msg)
+        }
+    }
+
+    /// Engine reading every text as a second of silence
+    struct MockTts;
+
+    impl TtsEngine for MockTts {
+        fn synthesize_until(
+            &self,
+            text: &str,
+            voice: &VoiceType,
+            speed: f32,
+            options: &NormalizeOptions,
+            _deadline: std::time::Instant,
+        ) -> Result<ipa_navigator_kokoro::tts::Synthesis, ipa_navigator_kokoro::error::TtsError>
+        {
+            let samples = self.synthesize(text, voice, speed, options)?;
+            Ok(ipa_navigator_kokoro::tts::Synthesis {
+                samples,
+                truncated: false,
+                sentences_synthesized: 1,
+                sentences_total: 1,
+            })
+        }
+
+        fn synthesize(
+            &self,
+            _text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<Vec<f32>, ipa_navigator_kokoro::error::TtsError> {
+            Ok(vec![0.0; 24000])
+        }
+
+        fn is_cached(
+            &self,
+            _text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<bool, ipa_navigator_kokoro::error::TtsError> {
+            Ok(false)
+        }
+
+        fn synthesize_tokens(
+            &self,
+            _tokens: &[i64],
+            _voice: &VoiceType,
+            _speed: f32,
+        ) -> Result<Vec<f32>, ipa_navigator_kokoro::error::TtsError> {
+            Ok(vec![0.0; 24000])
+        }
+    }
+
+    fn engine(
+        heard: Option<&'static str>,
+        alignment: Result<Vec<MfaSegment>, &'static str>,
+    ) -> State<Arc<dyn AssessmentEngine>> {
+        State(Arc::new(MockAssessment {
+            heard,
+            alignment,
+            reference: Vec::new(),
+        }))
+    }
+
+    fn tts() -> State<Arc<dyn TtsEngine>> {
+        State(Arc::new(MockTts))
+    }
+
+    /// Assessments are cached by audio, so each test sends different bytes
+    fn request(audio: &[u8], transcript: &str) -> PronunciationRequest {
+        PronunciationRequest {
+            audio: BASE64.encode(audio),
+            transcript: transcript.to_string(),
+            dialect: default_dialect(),
+            strictness: default_strictness(),
+            locale: None,
+            start_word: None,
+            end_word: None,
+            personalized: false,
+            pace: false,
+            voice: None,
+        }
+    }
+
+    #[test]
+    fn test_tenants_do_not_share_cached_assessments() {
+        let config = |id: &str| TenantConfig {
+            id: id.to_string(),
+            api_key: format!("{}-key", id),
+            voices: None,
+            lexicon: Default::default(),
+            requests_per_minute: None,
+            convex_url: None,
+        };
+        let tenants = Tenants::new(vec![config("north"), config("south")]).unwrap();
+        let key = |tenant: Option<&Tenant>| {
+            let input =
+                AssessmentInput::parse(request(b"shared", "this"), &HeaderMap::new(), tenant, None)
+                    .unwrap();
+            cache_key(&input, "mfa")
+        };
+
+        let keys = [
+            key(None),
+            key(tenants.find("north-key")),
+            key(tenants.find("south-key")),
+        ];
+        assert_ne!(keys[0], keys[1]);
+        assert_ne!(keys[0], keys[2]);
+        assert_ne!(keys[1], keys[2]);
+        assert_eq!(keys[1], key(tenants.find("north-key")));
+    }
+
+    #[tokio::test]
+    async fn test_assess_scores_alignment() {
+        let alignment = vec![
+            MfaSegment::phone("ð", 0.0, 0.1),
+            MfaSegment::phone("ɪ", 0.1, 0.2),
+            MfaSegment::phone("s", 0.2, 0.3),
+        ];
+
+        let Json(response) = assess(
+            engine(None, Ok(alignment)),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(request(b"scored", "this")),
+        )
+        .await
+        .unwrap();
+
+        assert!(!response.wrong_sentence_detected);
+        assert!(!response.phoneme_details.is_empty());
+        assert!((0.0..=1.0).contains(&response.overall_score));
+        assert!(response.transcript_check.is_none());
+
+        let tiers = response.tiers.expect("expected and actual tiers");
+        assert_eq!(tiers.actual.len(), 3);
+        let last = tiers.expected.last().expect("expected phonemes");
+        assert!((last.end_time - 0.3).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_assess_skips_scoring_a_different_sentence() {
+        let Json(response) = assess(
+            engine(Some("completely unrelated words"), Err("should not align")),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(request(b"different sentence", "this is a test")),
+        )
+        .await
+        .unwrap();
+
+        assert!(response.wrong_sentence_detected);
+        assert_eq!(response.overall_score, 0.0);
+        assert!(response.phoneme_details.is_empty());
+        assert!(response.tiers.is_none());
+        assert!(response.transcript_check.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_assess_reports_alignment_failure() {
+        let result = assess(
+            engine(None, Err("aligner unavailable")),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(request(b"unaligned", "this is a test")),
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::InternalServerError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_assess_rejects_bad_requests() {
+        let mut invalid_audio = request(b"", "this");
+        invalid_audio.audio = "not base64!".to_string();
+        let result = assess(
+            engine(None, Ok(Vec::new())),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(invalid_audio),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+
+        let mut unknown_dialect = request(b"dialect", "this");
+        unknown_dialect.dialect = "fr".to_string();
+        let result = assess(
+            engine(None, Ok(Vec::new())),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(unknown_dialect),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_assess_localizes_feedback() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_LANGUAGE, "fr, ms;q=0.9".parse().unwrap());
+
+        let Json(response) = assess(
+            engine(Some("completely unrelated words"), Err("should not align")),
+            tts(),
+            None,
+            None,
+            headers.clone(),
+            Json(request(b"malay feedback", "this is a test")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            response.feedback,
+            vec![localize(Locale::Malay, "tip-wrong-sentence", &[])]
+        );
+
+        // The request field overrides the header
+        let mut mandarin = request(b"mandarin feedback", "this is a test");
+        mandarin.locale = Some("zh-CN".to_string());
+        let Json(response) = assess(
+            engine(Some("completely unrelated words"), Err("should not align")),
+            tts(),
+            None,
+            None,
+            headers.clone(),
+            Json(mandarin),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            response.feedback,
+            vec![localize(Locale::Mandarin, "tip-wrong-sentence", &[])]
+        );
+
+        let mut unsupported = request(b"unsupported locale", "this");
+        unsupported.locale = Some("fr".to_string());
+        let result = assess(
+            engine(None, Ok(Vec::new())),
+            tts(),
+            None,
+            None,
+            headers,
+            Json(unsupported),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_assess_word_range() {
+        let alignment = vec![
+            MfaSegment::phone("ð", 0.0, 0.1),
+            MfaSegment::phone("ɪ", 0.1, 0.2),
+            MfaSegment::phone("s", 0.2, 0.3),
+        ];
+        let mut fragment = request(b"fragment", "Read  this again");
+        fragment.start_word = Some(1);
+        fragment.end_word = Some(2);
+
+        let Json(response) = assess(
+            engine(None, Ok(alignment)),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(fragment),
+        )
+        .await
+        .unwrap();
+
+        // Ranges index the whole transcript, where "this" starts at character 6
+        assert!(!response.phoneme_details.is_empty());
+        assert_eq!(response.phoneme_details[0].char_start, Some(6));
+        assert!(
+            response
+                .phoneme_details
+                .iter()
+                .filter_map(|detail| detail.char_end)
+                .all(|end| end <= 10)
+        );
+
+        let mut past_the_end = request(b"past the end", "Read this again");
+        past_the_end.start_word = Some(2);
+        past_the_end.end_word = Some(4);
+        let result = assess(
+            engine(None, Ok(Vec::new())),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(past_the_end),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_assess_word_detects_the_word() {
+        let request = |word: &str| WordAssessmentRequest {
+            audio: BASE64.encode(b"word"),
+            word: word.to_string(),
+            dialect: default_dialect(),
+            strictness: default_strictness(),
+            locale: None,
+        };
+        let said = vec![
+            MfaSegment::word("this", 0.2, 0.5),
+            MfaSegment::phone("ð", 0.2, 0.3),
+            MfaSegment::phone("ɪ", 0.3, 0.4),
+            MfaSegment::phone("s", 0.4, 0.5),
+        ];
+
+        let Json(response) = assess_word(
+            engine(None, Ok(said)),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(request("This!")),
+        )
+        .await
+        .unwrap();
+        assert!(response.detected);
+        assert_eq!(response.word, "This");
+        assert_eq!(response.phoneme_details.len(), 3);
+
+        let Json(response) = assess_word(
+            engine(None, Ok(vec![MfaSegment::word("", 0.0, 1.0)])),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(request("this")),
+        )
+        .await
+        .unwrap();
+        assert!(!response.detected);
+        assert!(response.phoneme_details.is_empty());
+        assert!(response.feedback[0].contains("\"this\""));
+
+        let result = assess_word(
+            engine(None, Err("should not align")),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(request("two words")),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_personalized_scoring_needs_sign_in() {
+        let mut personalized = request(b"personalized", "this");
+        personalized.personalized = true;
+        let result = assess(
+            engine(None, Err("should not align")),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(personalized),
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_assess_compares_pace_with_the_reference_voice() {
+        let alignment = vec![
+            MfaSegment::word("this", 0.0, 0.6),
+            MfaSegment::phone("ð", 0.0, 0.2),
+            MfaSegment::phone("ɪ", 0.2, 0.4),
+            MfaSegment::phone("s", 0.4, 0.6),
+        ];
+        let engine = State(Arc::new(MockAssessment {
+            heard: None,
+            alignment: Ok(alignment),
+            reference: vec![MfaSegment::word("this", 0.1, 0.4)],
+        }) as Arc<dyn AssessmentEngine>);
+
+        let mut paced = request(b"paced", "this");
+        paced.pace = true;
+        let Json(response) = assess(
+            engine.clone(),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(paced),
+        )
+        .await
+        .unwrap();
+
+        let pace = response.pace.unwrap();
+        assert_eq!(pace.voice, "american_female_bella");
+        assert_eq!(pace.words.len(), 1);
+        assert_eq!(pace.words[0].verdict, "too_slow");
+        assert!((pace.words[0].delta - 0.3).abs() < 1e-9);
+        assert!((pace.tempo_ratio - 2.0).abs() < 1e-9);
+
+        let Json(unpaced) = assess(
+            engine.clone(),
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(request(b"paced", "this")),
+        )
+        .await
+        .unwrap();
+        assert!(unpaced.pace.is_none());
+
+        let mut unknown_voice = request(b"unknown voice", "this");
+        unknown_voice.pace = true;
+        unknown_voice.voice = Some("robot".to_string());
+        let result = assess(
+            engine,
+            tts(),
+            None,
+            None,
+            HeaderMap::new(),
+            Json(unknown_voice),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BadRequest(_))));
+    }
 }
- */
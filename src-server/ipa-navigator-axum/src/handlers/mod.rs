@@ -1,3 +1,17 @@
+pub mod admin;
+pub mod classroom;
+pub mod compare;
+pub mod exercises;
+pub mod gamification;
 pub mod health;
+pub mod ipa;
+pub mod jobs;
+pub mod lessons;
 pub mod mfa;
+pub mod narration;
+pub mod privacy;
+pub mod progress;
+pub mod spell;
+pub mod text;
 pub mod tts;
+pub mod voices;
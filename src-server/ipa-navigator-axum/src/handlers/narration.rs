@@ -0,0 +1,415 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+};
+use ipa_navigator_kokoro::{
+    error::TtsError,
+    normalize::NormalizeOptions,
+    prelude::SAMPLE_RATE,
+    segment::split_sentences,
+    tts::SENTENCE_PAUSE_SECS,
+    voices::VoiceType,
+    wav::{WavFormat, encode_wav},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{Span, error, info};
+
+use crate::engines::TtsEngine;
+use crate::error::Error;
+use crate::handlers::jobs::{JobCreatedResponse, job_created};
+use crate::handlers::tts::{normalize_options, tenant_voice};
+use crate::identity::Session;
+use crate::jobs::{JOBS, Job, JobEvent, JobKind, JobOutput, JobStage};
+use crate::scheduler::{Admission, Priority, SCHEDULER, run_chunks};
+use crate::tenants::Tenant;
+
+/// Longest narration accepted, in characters across the title and every section
+const MAX_NARRATION_CHARS: usize = 50_000;
+
+/// Most sections in one narration
+const MAX_SECTIONS: usize = 100;
+
+/// Silence after a heading and between paragraphs, in seconds
+const PARAGRAPH_PAUSE_SECS: f32 = 0.8;
+
+/// Silence between the title and each section, in seconds
+const CHAPTER_PAUSE_SECS: f32 = 2.0;
+
+/// Structured content to narrate, such as a lesson text
+#[derive(Debug, Deserialize)]
+pub struct NarrationRequest {
+    /// Read first, before the sections
+    pub title: Option<String>,
+
+    pub sections: Vec<NarrationSection>,
+
+    pub voice: String,
+    pub speed: Option<f32>,
+
+    /// Comma-separated expansions or normalization stages to skip, e.g. "St.,numbers"
+    pub disable_expansions: Option<String>,
+}
+
+/// A chapter of the narration, read as its heading followed by its paragraphs
+#[derive(Debug, Deserialize)]
+pub struct NarrationSection {
+    pub heading: Option<String>,
+    pub paragraphs: Vec<String>,
+}
+
+/// Where a section starts and ends in the narrated audio
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chapter {
+    /// The section's heading, or "Section n" if it has none
+    pub title: String,
+    pub start_secs: f64,
+    pub end_secs: f64,
+}
+
+/// Narrated audio and where each section is in it
+#[derive(Debug, Clone)]
+pub struct Narration {
+    /// WAV audio
+    pub audio: Vec<u8>,
+    pub chapters: Vec<Chapter>,
+}
+
+/// A piece of the narration, read with the same voice and speed as the rest
+#[derive(Debug, PartialEq)]
+struct Passage {
+    /// The sentences read, in order
+    sentences: Vec<String>,
+
+    /// Silence before the passage, in seconds; none before the first
+    pause_before: f32,
+
+    /// Section the passage belongs to, or none for the title
+    section: Option<usize>,
+}
+
+/// Handle requests to narrate structured content in a background job
+///
+/// Each section is read as its heading then its paragraphs, with longer
+/// silences between sections than between paragraphs. The job's result is
+/// the WAV audio, and `/api/jobs/{id}/chapters` gives where each section
+/// starts, for building listening material from lesson texts.
+pub async fn narrate(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
+    session: Option<Session>,
+    Json(request): Json<NarrationRequest>,
+) -> Result<(StatusCode, Json<JobCreatedResponse>), Error> {
+    let voice = tenant_voice(tenant.as_ref(), &request.voice).map_err(Error::BadRequest)?;
+
+    let speed = request.speed.unwrap_or(1.0);
+    if !(0.5..=2.0).contains(&speed) {
+        return Err(Error::BadRequest(
+            "Speed must be between 0.5 and 2.0".to_string(),
+        ));
+    }
+
+    if request.sections.is_empty() || request.sections.len() > MAX_SECTIONS {
+        return Err(Error::BadRequest(format!(
+            "A narration needs between 1 and {} sections",
+            MAX_SECTIONS
+        )));
+    }
+    let chars: usize = request
+        .title
+        .iter()
+        .chain(
+            request
+                .sections
+                .iter()
+                .flat_map(|section| section.heading.iter().chain(&section.paragraphs)),
+        )
+        .map(|text| text.chars().count())
+        .sum();
+    if chars > MAX_NARRATION_CHARS {
+        return Err(Error::BadRequest(format!(
+            "Narration must be at most {} characters",
+            MAX_NARRATION_CHARS
+        )));
+    }
+
+    let passages = plan_passages(&request);
+    if passages.is_empty() {
+        return Err(Error::BadRequest(
+            "Narration contains no sentences".to_string(),
+        ));
+    }
+    let titles = chapter_titles(&request.sections);
+
+    let options = normalize_options(tenant.as_ref(), request.disable_expansions.as_deref());
+    let admission = SCHEDULER.admit(Priority::Batch)?;
+
+    let job = JOBS.create(JobKind::Narration, session.map(|session| session.subject));
+    info!(
+        "Queued narration job {} with {} sections, voice={:?}, speed={}",
+        job.id,
+        titles.len(),
+        voice,
+        speed
+    );
+
+    let worker = job.clone();
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+
+        let narration = synthesize_passages(
+            tts.as_ref(),
+            &worker,
+            &admission,
+            &passages,
+            &voice,
+            speed,
+            &options,
+        )
+        .and_then(|samples| {
+            Ok(Narration {
+                audio: encode_wav(&samples.samples, &WavFormat::default())?,
+                chapters: chapters(&titles, &samples.section_spans),
+            })
+        });
+        match narration {
+            Ok(narration) => worker.complete(JobOutput::Narration(narration)),
+            Err(e) => {
+                error!("Narration job {} failed: {}", worker.id, e);
+                worker.fail(format!("TTS processing error: {}", e));
+            }
+        }
+    });
+
+    Ok(job_created(&job))
+}
+
+/// Break the content into passages of sentences, each with the silence before it
+///
+/// Empty headings and paragraphs are skipped, but a section left with
+/// nothing to read still gets a chapter, so chapters line up with sections.
+fn plan_passages(request: &NarrationRequest) -> Vec<Passage> {
+    let mut passages = Vec::new();
+    // Whether the text had anything to read
+    let mut push = |text: &str, pause: f32, section: Option<usize>| {
+        let sentences = split_sentences(text);
+        let read = !sentences.is_empty();
+        if read {
+            passages.push(Passage {
+                sentences,
+                pause_before: pause,
+                section,
+            });
+        }
+        read
+    };
+
+    if let Some(title) = &request.title {
+        push(title, 0.0, None);
+    }
+    for (index, section) in request.sections.iter().enumerate() {
+        let mut pause = CHAPTER_PAUSE_SECS;
+        for text in section.heading.iter().chain(&section.paragraphs) {
+            if push(text, pause, Some(index)) {
+                pause = PARAGRAPH_PAUSE_SECS;
+            }
+        }
+    }
+
+    // Nothing comes before the first passage to pause after
+    if let Some(first) = passages.first_mut() {
+        first.pause_before = 0.0;
+    }
+    passages
+}
+
+fn chapter_titles(sections: &[NarrationSection]) -> Vec<String> {
+    sections
+        .iter()
+        .enumerate()
+        .map(|(index, section)| {
+            section
+                .heading
+                .as_deref()
+                .map(str::trim)
+                .filter(|heading| !heading.is_empty())
+                .map_or_else(|| format!("Section {}", index + 1), str::to_string)
+        })
+        .collect()
+}
+
+/// Samples of the whole narration, and the sample range each section covers
+struct NarratedSamples {
+    samples: Vec<f32>,
+    section_spans: Vec<(usize, usize, usize)>,
+}
+
+/// Synthesize every sentence, joining them in order with the planned silences
+///
+/// Sentences are synthesized several at once if the model has the sessions.
+/// An inference slot is taken for each sentence, so waiting interactive
+/// requests run between them.
+fn synthesize_passages(
+    tts: &dyn TtsEngine,
+    job: &Job,
+    admission: &Admission,
+    passages: &[Passage],
+    voice: &VoiceType,
+    speed: f32,
+    options: &NormalizeOptions,
+) -> Result<NarratedSamples, TtsError> {
+    let silence = |secs: f32| vec![0.0; (SAMPLE_RATE as f32 * secs) as usize];
+    let sentence_pause = silence(SENTENCE_PAUSE_SECS);
+
+    let sentences: Vec<&String> = passages
+        .iter()
+        .flat_map(|passage| &passage.sentences)
+        .collect();
+    let total = sentences.len();
+    let mut audio = run_chunks(
+        admission,
+        &sentences,
+        SCHEDULER.max_parallel_inferences(),
+        |sentence| tts.synthesize(sentence, voice, speed, options),
+        |done| {
+            job.emit(
+                JobEvent::new(
+                    JobStage::Synthesizing,
+                    format!("Sentence {}/{} synthesized", done, total),
+                )
+                .with_progress(done, total),
+            )
+        },
+    )?
+    .into_iter();
+
+    let mut samples = Vec::new();
+    let mut section_spans: Vec<(usize, usize, usize)> = Vec::new();
+    for passage in passages {
+        samples.extend(silence(passage.pause_before));
+        let start = samples.len();
+
+        for (index, sentence_audio) in audio.by_ref().take(passage.sentences.len()).enumerate() {
+            if index > 0 {
+                samples.extend_from_slice(&sentence_pause);
+            }
+            samples.extend(sentence_audio);
+        }
+
+        if let Some(section) = passage.section {
+            match section_spans.last_mut() {
+                Some(span) if span.0 == section => span.2 = samples.len(),
+                _ => section_spans.push((section, start, samples.len())),
+            }
+        }
+    }
+
+    Ok(NarratedSamples {
+        samples,
+        section_spans,
+    })
+}
+
+/// A chapter for every section, sections with nothing read marked where they would have begun
+fn chapters(titles: &[String], section_spans: &[(usize, usize, usize)]) -> Vec<Chapter> {
+    let to_secs = |sample: usize| sample as f64 / SAMPLE_RATE as f64;
+
+    let mut position = 0;
+    titles
+        .iter()
+        .enumerate()
+        .map(|(index, title)| {
+            let (start, end) = section_spans
+                .iter()
+                .find(|span| span.0 == index)
+                .map_or((position, position), |span| (span.1, span.2));
+            position = end;
+            Chapter {
+                title: title.clone(),
+                start_secs: to_secs(start),
+                end_secs: to_secs(end),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(heading: Option<&str>, paragraphs: &[&str]) -> NarrationSection {
+        NarrationSection {
+            heading: heading.map(str::to_string),
+            paragraphs: paragraphs.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    fn request(title: Option<&str>, sections: Vec<NarrationSection>) -> NarrationRequest {
+        NarrationRequest {
+            title: title.map(str::to_string),
+            sections,
+            voice: "american_female_bella".to_string(),
+            speed: None,
+            disable_expansions: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_passages_pauses_between_sections_and_paragraphs() {
+        let request = request(
+            Some("Vowels"),
+            vec![
+                section(Some("Short vowels"), &["Sit. Set.", "Sat."]),
+                section(None, &["", "Long vowels."]),
+            ],
+        );
+
+        let passages = plan_passages(&request);
+        let pauses: Vec<(f32, Option<usize>)> = passages
+            .iter()
+            .map(|passage| (passage.pause_before, passage.section))
+            .collect();
+
+        assert_eq!(
+            pauses,
+            vec![
+                (0.0, None),
+                (CHAPTER_PAUSE_SECS, Some(0)),
+                (PARAGRAPH_PAUSE_SECS, Some(0)),
+                (PARAGRAPH_PAUSE_SECS, Some(0)),
+                // The empty paragraph is skipped without taking the chapter pause
+                (CHAPTER_PAUSE_SECS, Some(1)),
+            ]
+        );
+        assert_eq!(passages[2].sentences.len(), 2);
+    }
+
+    #[test]
+    fn test_plan_passages_has_no_leading_silence_without_title() {
+        let passages = plan_passages(&request(None, vec![section(Some("One"), &["Two."])]));
+
+        assert_eq!(passages[0].pause_before, 0.0);
+        assert_eq!(passages[1].pause_before, PARAGRAPH_PAUSE_SECS);
+    }
+
+    #[test]
+    fn test_chapters_cover_every_section() {
+        let titles = chapter_titles(&[
+            section(Some("  Intro "), &["Hello."]),
+            section(Some(""), &[]),
+            section(None, &["Bye."]),
+        ]);
+        assert_eq!(titles, vec!["Intro", "Section 2", "Section 3"]);
+
+        let rate = SAMPLE_RATE as usize;
+        let chapters = chapters(&titles, &[(0, rate, 2 * rate), (2, 4 * rate, 5 * rate)]);
+
+        let times: Vec<(f64, f64)> = chapters
+            .iter()
+            .map(|chapter| (chapter.start_secs, chapter.end_secs))
+            .collect();
+        assert_eq!(times, vec![(1.0, 2.0), (2.0, 2.0), (4.0, 5.0)]);
+    }
+}
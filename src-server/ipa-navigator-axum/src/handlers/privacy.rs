@@ -0,0 +1,205 @@
+use std::io::{self, Cursor, Write};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Json, Path},
+    http::header,
+    response::IntoResponse,
+};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::audit;
+use crate::convex::user_call_error;
+use crate::error::Error;
+use crate::identity::{AuthUser, forget_user};
+use crate::jobs::{JOBS, Job, JobOutput};
+use crate::privacy::{Privacy, exported_recordings};
+
+/// Most deletion batches run by one request, bounding how long it can take
+const MAX_DELETE_BATCHES: usize = 200;
+
+/// What was removed for a user
+#[derive(Debug, Serialize)]
+pub struct DataDeletionResponse {
+    /// Convex documents deleted, or revoked if shared with others
+    pub deleted_records: u64,
+
+    /// Jobs whose output the server was holding
+    pub deleted_jobs: usize,
+}
+
+/// Handle requests for a zip of everything stored about the signed-in user
+///
+/// `id` is the user's Convex id, or `me`. The zip holds `data.json` with their
+/// Convex records, `recordings/` with kept submission recordings, and `jobs/`
+/// with the output of their recent jobs.
+pub async fn export(user: AuthUser, Path(id): Path<String>) -> Result<impl IntoResponse, Error> {
+    require_self(&user, &id)?;
+    let privacy = Privacy::for_user(&user)?;
+
+    let data = privacy.export().await.map_err(user_call_error)?;
+    let mut recordings = Vec::new();
+    for recording in exported_recordings(&data) {
+        // The recording may have expired since the export was read
+        match privacy.recording(&recording).await {
+            Ok(audio) => recordings.push((recording.submission_id, audio)),
+            Err(e) => warn!(
+                "Leaving recording {} out of export: {}",
+                recording.submission_id, e
+            ),
+        }
+    }
+    let jobs = JOBS.owned_by(&user.session.subject);
+
+    let archive = tokio::task::spawn_blocking(move || {
+        let data = serde_json::to_vec_pretty(&data)
+            .map_err(|e| Error::InternalServerError(format!("Failed to encode data: {}", e)))?;
+        build_archive(&data, &recordings, &jobs)
+            .map_err(|e| Error::InternalServerError(format!("Failed to build export: {}", e)))
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Export failed: {}", e)))??;
+
+    info!(
+        "User {} exported their data ({} bytes)",
+        user.user_id,
+        archive.len()
+    );
+    audit::record(
+        &audit::user_actor(&user.user_id),
+        "user_data.export",
+        Some(&user.user_id),
+        &(),
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"ipa-navigator-data.zip\"",
+            ),
+        ],
+        archive,
+    ))
+}
+
+/// Handle requests to delete everything stored about the signed-in user
+///
+/// `id` is the user's Convex id, or `me`. Their Convex records and kept
+/// recordings are deleted in batches, and chapters and classrooms they
+/// created are revoked and archived since others use them. Their jobs are
+/// dropped from memory. Cached assessments are keyed by a hash of the audio
+/// and hold none of it, and MFA working files are removed as each job ends,
+/// so the server keeps nothing else. A request cut short can be repeated to
+/// finish the deletion.
+pub async fn delete_data(
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<DataDeletionResponse>, Error> {
+    require_self(&user, &id)?;
+    let privacy = Privacy::for_user(&user)?;
+
+    let deleted_jobs = JOBS.remove_owned_by(&user.session.subject);
+    let mut deleted_records = 0;
+    let mut done = false;
+    for _ in 0..MAX_DELETE_BATCHES {
+        let progress = privacy.delete_batch().await.map_err(user_call_error)?;
+        deleted_records += progress.deleted;
+        if progress.done {
+            done = true;
+            break;
+        }
+    }
+
+    info!(
+        "User {} deleted their data: {} records, {} jobs, complete: {}",
+        user.user_id, deleted_records, deleted_jobs, done
+    );
+    audit::record(
+        &audit::user_actor(&user.user_id),
+        "user_data.delete",
+        Some(&user.user_id),
+        &json!({
+            "deleted_records": deleted_records,
+            "deleted_jobs": deleted_jobs,
+            "complete": done,
+        }),
+    );
+    if !done {
+        return Err(Error::ServiceUnavailable(
+            "Deletion is still in progress, repeat the request to finish it".to_string(),
+        ));
+    }
+    forget_user(&user.session);
+
+    Ok(Json(DataDeletionResponse {
+        deleted_records,
+        deleted_jobs,
+    }))
+}
+
+/// Only the signed-in user's own data may be exported or deleted
+fn require_self(user: &AuthUser, id: &str) -> Result<(), Error> {
+    if id == "me" || id == user.user_id {
+        Ok(())
+    } else {
+        Err(Error::Unauthorized(
+            "You can only export or delete your own data".to_string(),
+        ))
+    }
+}
+
+fn build_archive(
+    data: &[u8],
+    recordings: &[(String, Vec<u8>)],
+    jobs: &[Arc<Job>],
+) -> zip::result::ZipResult<Vec<u8>> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    zip.start_file("data.json", options)?;
+    zip.write_all(data)?;
+
+    for (submission_id, audio) in recordings {
+        zip.start_file(format!("recordings/{}.wav", submission_id), options)?;
+        zip.write_all(audio)?;
+    }
+
+    for job in jobs {
+        match job.output() {
+            Some(JobOutput::Audio(audio)) => {
+                zip.start_file(format!("jobs/{}.wav", job.id), options)?;
+                zip.write_all(&audio)?;
+            }
+            Some(JobOutput::Assessment(assessment)) => {
+                zip.start_file(format!("jobs/{}.json", job.id), options)?;
+                serde_json::to_writer_pretty(&mut zip, &assessment.response)
+                    .map_err(io::Error::from)?;
+            }
+            Some(JobOutput::Narration(narration)) => {
+                zip.start_file(format!("jobs/{}.wav", job.id), options)?;
+                zip.write_all(&narration.audio)?;
+                zip.start_file(format!("jobs/{}.chapters.json", job.id), options)?;
+                serde_json::to_writer_pretty(&mut zip, &narration.chapters)
+                    .map_err(io::Error::from)?;
+            }
+            Some(JobOutput::LessonAudio(manifest)) => {
+                zip.start_file(format!("jobs/{}.json", job.id), options)?;
+                serde_json::to_writer_pretty(&mut zip, &manifest).map_err(io::Error::from)?;
+            }
+            Some(JobOutput::Bundle(bundle)) => {
+                // Audio hardly deflates, so the bundle is stored as it is
+                let stored = options.compression_method(CompressionMethod::Stored);
+                zip.start_file(format!("jobs/{}.zip", job.id), stored)?;
+                bundle.write_zip(&mut zip)?;
+            }
+            None => {}
+        }
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
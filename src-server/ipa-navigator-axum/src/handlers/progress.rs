@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::extract::{Json, Query};
+use ipa_navigator_core::trend::{DEFAULT_SMOOTHING, Sample, ScoreTrend, score_trend};
+use serde::{Deserialize, Serialize};
+
+use crate::convex::user_call_error;
+use crate::error::Error;
+use crate::identity::AuthUser;
+use crate::practice::{PhonemeHistoryAttempt, Practice};
+
+/// Days of practice charted by default
+const DEFAULT_DAYS: u32 = 90;
+
+/// Most days of practice charted at once
+const MAX_DAYS: u32 = 365;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct TrendQuery {
+    /// Days of practice to chart, ending now (default: 90, at most 365)
+    pub days: Option<u32>,
+
+    /// Weight of the newest score in the moving average, above 0 and at most 1 (default: 0.3)
+    pub smoothing: Option<f64>,
+}
+
+/// How the signed-in user's scores have changed, overall and for each phoneme
+#[derive(Debug, Serialize)]
+pub struct PhonemeTrendsResponse {
+    /// Start of the charted period, in milliseconds since the Unix epoch
+    pub since: i64,
+    pub smoothing: f64,
+    pub overall: TrendResponse,
+
+    /// Phonemes practiced in the period, in symbol order
+    pub phonemes: Vec<PhonemeTrendResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhonemeTrendResponse {
+    pub phoneme: String,
+    #[serde(flatten)]
+    pub trend: TrendResponse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrendResponse {
+    /// One point per attempt, oldest first
+    pub points: Vec<TrendPointResponse>,
+
+    /// Change in smoothed score per week, None until there are enough attempts
+    pub rate_per_week: Option<f64>,
+
+    /// "improving", "plateau", or "regressing", None until there are enough attempts
+    pub trend: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrendPointResponse {
+    /// Milliseconds since the Unix epoch
+    pub timestamp: i64,
+    /// Score of the attempt (0.0-1.0)
+    pub score: f64,
+    /// Moving average up to and including the attempt (0.0-1.0)
+    pub smoothed: f64,
+}
+
+impl From<ScoreTrend> for TrendResponse {
+    fn from(trend: ScoreTrend) -> Self {
+        TrendResponse {
+            points: trend
+                .points
+                .into_iter()
+                .map(|point| TrendPointResponse {
+                    timestamp: point.timestamp_ms,
+                    score: point.score,
+                    smoothed: point.smoothed,
+                })
+                .collect(),
+            rate_per_week: trend.rate_per_week,
+            trend: trend.trend.map(|trend| trend.as_str()),
+        }
+    }
+}
+
+/// Handle requests for the signed-in user's rate of improvement
+///
+/// Each attempt contributes one point to the overall series, and one point to
+/// each phoneme it was scored on, averaging repeats of a phoneme within it.
+pub async fn phoneme_trends(
+    user: AuthUser,
+    Query(query): Query<TrendQuery>,
+) -> Result<Json<PhonemeTrendsResponse>, Error> {
+    let days = query.days.unwrap_or(DEFAULT_DAYS);
+    if days == 0 || days > MAX_DAYS {
+        return Err(Error::BadRequest(format!(
+            "days must be between 1 and {}",
+            MAX_DAYS
+        )));
+    }
+    let smoothing = query.smoothing.unwrap_or(DEFAULT_SMOOTHING);
+    if !(smoothing > 0.0 && smoothing <= 1.0) {
+        return Err(Error::BadRequest(
+            "smoothing must be above 0 and at most 1".to_string(),
+        ));
+    }
+
+    let since = now_millis() - i64::from(days) * DAY_MS;
+    let attempts = Practice::for_user(&user)?
+        .phoneme_history(since)
+        .await
+        .map_err(user_call_error)?;
+
+    let overall: Vec<Sample> = attempts
+        .iter()
+        .map(|attempt| Sample {
+            timestamp_ms: attempt.created_at as i64,
+            score: attempt.overall_accuracy,
+        })
+        .collect();
+
+    Ok(Json(PhonemeTrendsResponse {
+        since,
+        smoothing,
+        overall: score_trend(&overall, smoothing).into(),
+        phonemes: phoneme_samples(&attempts)
+            .into_iter()
+            .map(|(phoneme, samples)| PhonemeTrendResponse {
+                phoneme,
+                trend: score_trend(&samples, smoothing).into(),
+            })
+            .collect(),
+    }))
+}
+
+/// One sample per attempt for each phoneme expected in it
+fn phoneme_samples(attempts: &[PhonemeHistoryAttempt]) -> BTreeMap<String, Vec<Sample>> {
+    let mut samples: BTreeMap<String, Vec<Sample>> = BTreeMap::new();
+    for attempt in attempts {
+        let mut scores: BTreeMap<&str, (f64, usize)> = BTreeMap::new();
+        for phoneme in &attempt.phonemes {
+            // Insertions were not expected, so have no phoneme to chart
+            if phoneme.target_phoneme.is_empty() {
+                continue;
+            }
+            let (total, count) = scores.entry(&phoneme.target_phoneme).or_default();
+            *total += phoneme.accuracy;
+            *count += 1;
+        }
+
+        for (phoneme, (total, count)) in scores {
+            samples
+                .entry(phoneme.to_string())
+                .or_default()
+                .push(Sample {
+                    timestamp_ms: attempt.created_at as i64,
+                    score: total / count as f64,
+                });
+        }
+    }
+    samples
+}
+
+/// Milliseconds since the Unix epoch
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::practice::PracticePhoneme;
+
+    fn phoneme(target: &str, accuracy: f64) -> PracticePhoneme {
+        PracticePhoneme {
+            target_phoneme: target.to_string(),
+            detected_phoneme: target.to_string(),
+            accuracy,
+        }
+    }
+
+    #[test]
+    fn test_one_sample_per_attempt_and_phoneme() {
+        let attempts = vec![
+            PhonemeHistoryAttempt {
+                created_at: 1000.0,
+                overall_accuracy: 0.5,
+                phonemes: vec![phoneme("ð", 0.2), phoneme("ð", 0.6), phoneme("", 0.0)],
+            },
+            PhonemeHistoryAttempt {
+                created_at: 2000.0,
+                overall_accuracy: 0.9,
+                phonemes: vec![phoneme("ð", 0.9), phoneme("s", 1.0)],
+            },
+        ];
+
+        let samples = phoneme_samples(&attempts);
+        assert_eq!(samples.keys().collect::<Vec<_>>(), ["s", "ð"]);
+        assert_eq!(samples["ð"].len(), 2);
+        assert!((samples["ð"][0].score - 0.4).abs() < 1e-9);
+        assert_eq!(samples["ð"][1].timestamp_ms, 2000);
+        assert_eq!(samples["s"].len(), 1);
+    }
+}
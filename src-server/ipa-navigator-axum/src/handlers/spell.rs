@@ -0,0 +1,386 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Json, State},
+    http::{HeaderMap, header},
+    response::IntoResponse,
+};
+use ipa_navigator_kokoro::{
+    error::TtsError,
+    normalize::NormalizeOptions,
+    prelude::SAMPLE_RATE,
+    teaching::{PauseLength, PauseUnit, TeachingMode},
+    voices::VoiceType,
+    wav::{WavFormat, encode_wav},
+};
+use ipa_navigator_mfa::{
+    scoring::cached_dictionary,
+    syllables::{Syllable, spell_syllables},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{Span, info};
+
+use crate::engines::TtsEngine;
+use crate::error::Error;
+use crate::handlers::{
+    compare::{Part, SegmentTiming, choose_boundary, encode_multipart},
+    mfa::parse_dialect,
+    tts::{tenant_reference_voice, tenant_voice},
+};
+use crate::scheduler::{Priority, SCHEDULER};
+use crate::tenants::Tenant;
+
+/// Most letters in a word to spell
+const MAX_WORD_LETTERS: usize = 30;
+
+/// Silence between spelled letters, in seconds
+const LETTER_PAUSE_SECS: f32 = 0.25;
+
+/// Silence between the spelled, syllable-by-syllable and natural readings, in seconds
+const SEGMENT_PAUSE_SECS: f32 = 1.0;
+
+/// Request to hear a word spelled, split into syllables and said naturally
+#[derive(Debug, Deserialize)]
+pub struct SpellRequest {
+    /// A single word
+    pub word: String,
+
+    /// Dialect whose dictionary syllabifies the word (default: "us")
+    #[serde(default = "default_dialect")]
+    pub dialect: String,
+
+    /// Voice to read with, as accepted by `/api/tts` (default: depends on dialect)
+    pub voice: Option<String>,
+
+    pub speed: Option<f32>,
+}
+
+fn default_dialect() -> String {
+    "us".to_string()
+}
+
+/// Timings sent as the JSON part of the spelling response
+#[derive(Debug, Serialize)]
+pub struct SpellTimings {
+    pub word: String,
+    pub duration: f64,
+
+    /// The "spelled", "syllables" and "natural" readings, in order
+    pub segments: Vec<SegmentTiming>,
+
+    /// Each letter of the spelled reading
+    pub letters: Vec<SegmentTiming>,
+
+    /// The word's syllables from the dictionary, in order
+    pub syllables: Vec<SyllableDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyllableDetail {
+    pub spelling: String,
+    pub ipa: String,
+}
+
+impl From<Syllable> for SyllableDetail {
+    fn from(syllable: Syllable) -> Self {
+        Self {
+            spelling: syllable.spelling,
+            ipa: syllable.phonemes.join(""),
+        }
+    }
+}
+
+/// Handle requests to hear a word spelled out, syllable by syllable and at natural speed
+///
+/// The three readings are joined into one clip. Syllables come from the
+/// dialect's dictionary, and the syllable-by-syllable reading pauses between
+/// them in teaching mode. Responds with `multipart/form-data` holding a
+/// `timings` JSON part followed by the `audio` WAV file.
+pub async fn spell(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
+    Json(request): Json<SpellRequest>,
+) -> Result<impl IntoResponse, Error> {
+    let word = request.word.trim().to_string();
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    if word.contains(char::is_whitespace) {
+        return Err(Error::BadRequest(
+            "Only a single word can be spelled".to_string(),
+        ));
+    }
+    if letters.is_empty() || letters.len() > MAX_WORD_LETTERS {
+        return Err(Error::BadRequest(format!(
+            "Word must have between 1 and {} letters",
+            MAX_WORD_LETTERS
+        )));
+    }
+
+    let dialect = parse_dialect(&request.dialect)?;
+    let voice = match request.voice.as_deref() {
+        Some(voice) => tenant_voice(tenant.as_ref(), voice).map_err(Error::BadRequest)?,
+        None => tenant_reference_voice(tenant.as_ref(), dialect),
+    };
+    let speed = request.speed.unwrap_or(1.0);
+    if !(0.5..=2.0).contains(&speed) {
+        return Err(Error::BadRequest(
+            "Speed must be between 0.5 and 2.0".to_string(),
+        ));
+    }
+
+    let admission = SCHEDULER.admit(Priority::Interactive)?;
+    let _permit = admission.acquire().await;
+    info!(
+        "Spelling a {}-letter word, voice={:?}, speed={}",
+        letters.len(),
+        voice,
+        speed
+    );
+
+    let span = Span::current();
+    let (wav, timings) = tokio::task::spawn_blocking(move || -> Result<_, Error> {
+        let _span = span.enter();
+
+        let dictionary = cached_dictionary(dialect)
+            .map_err(|e| Error::InternalServerError(format!("Failed to load dictionary: {}", e)))?;
+        let syllables = spell_syllables(&dictionary, &word)
+            .ok_or_else(|| Error::NotFound(format!("No dictionary pronunciation for: {}", word)))?;
+
+        let WordReading {
+            samples,
+            segments,
+            letters: letter_timings,
+        } = read_word(tts.as_ref(), &word, &letters, &voice, speed)
+            .map_err(|e| Error::InternalServerError(format!("TTS processing error: {}", e)))?;
+
+        let timings = SpellTimings {
+            word,
+            duration: samples.len() as f64 / SAMPLE_RATE as f64,
+            segments,
+            letters: letter_timings,
+            syllables: syllables.into_iter().map(SyllableDetail::from).collect(),
+        };
+        let wav = encode_wav(&samples, &WavFormat::default())
+            .map_err(|e| Error::InternalServerError(e.to_string()))?;
+        Ok((wav, timings))
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Synthesis task failed: {}", e)))??;
+
+    let timings = serde_json::to_vec(&timings)
+        .map_err(|e| Error::InternalServerError(format!("Failed to encode timings: {}", e)))?;
+
+    let parts = [
+        Part {
+            name: "timings",
+            filename: None,
+            content_type: "application/json",
+            body: &timings,
+        },
+        Part {
+            name: "audio",
+            filename: Some("spell.wav"),
+            content_type: "audio/wav",
+            body: &wav,
+        },
+    ];
+    let boundary = choose_boundary(&parts);
+    let body = encode_multipart(&parts, &boundary);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        format!("multipart/form-data; boundary={}", boundary)
+            .parse()
+            .unwrap(),
+    );
+
+    Ok((headers, body))
+}
+
+/// Audio of the three readings of a word, with where each reading and letter falls
+struct WordReading {
+    samples: Vec<f32>,
+    /// The "spelled", "syllables" and "natural" readings, in order
+    segments: Vec<SegmentTiming>,
+    letters: Vec<SegmentTiming>,
+}
+
+/// Synthesize the three readings, timing each reading and letter
+///
+/// Letters are synthesized one at a time, so each is timed exactly and
+/// reused from the cache across words.
+fn read_word(
+    tts: &dyn TtsEngine,
+    word: &str,
+    letters: &[char],
+    voice: &VoiceType,
+    speed: f32,
+) -> Result<WordReading, TtsError> {
+    let plain = NormalizeOptions::default();
+    let silence = |secs: f32| vec![0.0; (SAMPLE_RATE as f32 * secs) as usize];
+    let secs = |sample: usize| sample as f64 / SAMPLE_RATE as f64;
+    let timing = |label: String, start: usize, end: usize| SegmentTiming {
+        label,
+        start_time: secs(start),
+        end_time: secs(end),
+    };
+
+    let mut samples = Vec::new();
+    let mut letter_timings = Vec::with_capacity(letters.len());
+    for (index, letter) in letters.iter().enumerate() {
+        if index > 0 {
+            samples.extend(silence(LETTER_PAUSE_SECS));
+        }
+        // Capitals are read as letter names, where "a" alone would be the article
+        let name = letter.to_uppercase().to_string();
+        let start = samples.len();
+        samples.extend(tts.synthesize(&name, voice, speed, &plain)?);
+        letter_timings.push(timing(name, start, samples.len()));
+    }
+    let mut segments = vec![timing("spelled".to_string(), 0, samples.len())];
+
+    let readings = [
+        (
+            "syllables",
+            plain
+                .clone()
+                .with_teaching(TeachingMode::new(PauseUnit::Syllable, PauseLength::Long)),
+        ),
+        ("natural", plain),
+    ];
+    for (label, options) in readings {
+        samples.extend(silence(SEGMENT_PAUSE_SECS));
+        let start = samples.len();
+        samples.extend(tts.synthesize(word, voice, speed, &options)?);
+        segments.push(timing(label.to_string(), start, samples.len()));
+    }
+
+    Ok(WordReading {
+        samples,
+        segments,
+        letters: letter_timings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    use ipa_navigator_kokoro::tts::Synthesis;
+
+    use crate::handlers::tts::reference_voice;
+
+    /// Speaks a tenth of a second per character, twice as long in teaching mode
+    struct MockTts;
+
+    impl TtsEngine for MockTts {
+        fn synthesize_until(
+            &self,
+            text: &str,
+            voice: &VoiceType,
+            speed: f32,
+            options: &NormalizeOptions,
+            _deadline: Instant,
+        ) -> Result<Synthesis, TtsError> {
+            Ok(Synthesis {
+                samples: self.synthesize(text, voice, speed, options)?,
+                truncated: false,
+                sentences_synthesized: 1,
+                sentences_total: 1,
+            })
+        }
+
+        fn synthesize(
+            &self,
+            text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            options: &NormalizeOptions,
+        ) -> Result<Vec<f32>, TtsError> {
+            let stretch = if options.teaching().is_some() { 2 } else { 1 };
+            let len = text.chars().count() * stretch * SAMPLE_RATE as usize / 10;
+            Ok(vec![0.5; len])
+        }
+
+        fn is_cached(
+            &self,
+            _text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<bool, TtsError> {
+            Ok(false)
+        }
+
+        fn synthesize_tokens(
+            &self,
+            tokens: &[i64],
+            _voice: &VoiceType,
+            _speed: f32,
+        ) -> Result<Vec<f32>, TtsError> {
+            Ok(vec![0.5; tokens.len() * SAMPLE_RATE as usize / 10])
+        }
+    }
+
+    #[test]
+    fn test_read_word_times_each_reading() {
+        let voice = reference_voice(parse_dialect("us").unwrap());
+        let WordReading {
+            samples,
+            segments,
+            letters,
+        } = read_word(&MockTts, "cat", &['c', 'a', 't'], &voice, 1.0).unwrap();
+
+        let labels: Vec<&str> = letters.iter().map(|l| l.label.as_str()).collect();
+        assert_eq!(labels, vec!["C", "A", "T"]);
+        assert!((letters[1].start_time - 0.35).abs() < 1e-6);
+
+        let spans: Vec<(&str, f64, f64)> = segments
+            .iter()
+            .map(|s| (s.label.as_str(), s.start_time, s.end_time))
+            .collect();
+        let expected = [
+            ("spelled", 0.0, 0.8),
+            ("syllables", 1.8, 2.4),
+            ("natural", 3.4, 3.7),
+        ];
+        for ((label, start, end), (expected_label, expected_start, expected_end)) in
+            spans.into_iter().zip(expected)
+        {
+            assert_eq!(label, expected_label);
+            assert!(
+                (start - expected_start).abs() < 1e-6,
+                "{} starts at {}",
+                label,
+                start
+            );
+            assert!(
+                (end - expected_end).abs() < 1e-6,
+                "{} ends at {}",
+                label,
+                end
+            );
+        }
+        assert_eq!(samples.len(), (3.7 * SAMPLE_RATE as f64).round() as usize);
+    }
+
+    #[tokio::test]
+    async fn test_spell_rejects_bad_requests() {
+        let request = |word: &str| SpellRequest {
+            word: word.to_string(),
+            dialect: default_dialect(),
+            voice: None,
+            speed: None,
+        };
+
+        for word in ["two words", "", "1234", &"a".repeat(MAX_WORD_LETTERS + 1)] {
+            let result = spell(State(Arc::new(MockTts)), None, Json(request(word))).await;
+            assert!(
+                matches!(result, Err(Error::BadRequest(_))),
+                "{:?} should be rejected",
+                word
+            );
+        }
+    }
+}
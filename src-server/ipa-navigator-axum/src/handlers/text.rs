@@ -0,0 +1,145 @@
+use axum::extract::Json;
+use ipa_navigator_kokoro::{normalize::normalize_text, phonemizer::PHONEME_CACHE};
+use ipa_navigator_mfa::{
+    difficulty::estimate_difficulty, docker::MfaDialect, scoring::cached_dictionary,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::handlers::{exercises::MAX_TEXT_CHARS, mfa::parse_dialect};
+
+/// Request to estimate how hard a text is to pronounce
+#[derive(Debug, Deserialize)]
+pub struct TextDifficultyRequest {
+    pub text: String,
+
+    /// Dialect whose dictionary transcribes the text (default: "us")
+    #[serde(default = "default_dialect")]
+    pub dialect: String,
+}
+
+fn default_dialect() -> String {
+    "us".to_string()
+}
+
+/// Suggested lesson level of a text and the measures behind it
+#[derive(Debug, Serialize)]
+pub struct TextDifficultyResponse {
+    /// "beginner", "intermediate", or "advanced"
+    pub level: &'static str,
+
+    /// Overall difficulty from 0 to 1
+    pub score: f64,
+
+    /// Rarity of the text's phonemes in English, from 0 to 1
+    pub phoneme_rarity: f64,
+
+    /// Consonant cluster complexity, from 0 to 1
+    pub cluster_complexity: f64,
+
+    /// Average words per sentence
+    pub sentence_length: f64,
+
+    pub words: usize,
+    pub sentences: usize,
+
+    /// Rare phonemes in the text, rarest first
+    pub rare_phonemes: Vec<String>,
+
+    /// Words without a dictionary pronunciation, which only count toward sentence length
+    pub missing_words: Vec<String>,
+}
+
+/// Handle requests to estimate a text's difficulty for lesson leveling
+pub async fn difficulty(
+    Json(request): Json<TextDifficultyRequest>,
+) -> Result<Json<TextDifficultyResponse>, Error> {
+    if request.text.chars().count() > MAX_TEXT_CHARS {
+        return Err(Error::BadRequest(format!(
+            "Text must be at most {} characters",
+            MAX_TEXT_CHARS
+        )));
+    }
+
+    let dialect = parse_dialect(&request.dialect)?;
+    let text = normalize_text(&request.text);
+
+    let difficulty = tokio::task::spawn_blocking(move || {
+        cached_dictionary(dialect).map(|dictionary| estimate_difficulty(&dictionary, &text))
+    })
+    .await
+    .map_err(|e| Error::InternalServerError(format!("Difficulty task failed: {}", e)))?
+    .map_err(|e| Error::InternalServerError(format!("Failed to load dictionary: {}", e)))?;
+
+    if difficulty.words == 0 {
+        return Err(Error::BadRequest("Text contains no words".to_string()));
+    }
+
+    Ok(Json(TextDifficultyResponse {
+        level: difficulty.level.as_str(),
+        score: difficulty.score,
+        phoneme_rarity: difficulty.phoneme_rarity,
+        cluster_complexity: difficulty.cluster_complexity,
+        sentence_length: difficulty.sentence_length,
+        words: difficulty.words,
+        sentences: difficulty.sentences,
+        rare_phonemes: difficulty.rare_phonemes,
+        missing_words: difficulty.missing_words,
+    }))
+}
+
+/// Request to transcribe text into phonemes as the TTS voices say it
+#[derive(Debug, Deserialize)]
+pub struct PhonemizeRequest {
+    pub text: String,
+
+    /// "us" or "uk" (default: "us")
+    #[serde(default = "default_dialect")]
+    pub dialect: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhonemizeResponse {
+    /// The text after normalization, as it was phonemized
+    pub text: String,
+
+    /// espeak language the text was phonemized in
+    pub language: &'static str,
+
+    pub phonemes: String,
+}
+
+/// Handle requests to phonemize text, sharing synthesis's cache of espeak output
+pub async fn phonemize(
+    Json(request): Json<PhonemizeRequest>,
+) -> Result<Json<PhonemizeResponse>, Error> {
+    if request.text.chars().count() > MAX_TEXT_CHARS {
+        return Err(Error::BadRequest(format!(
+            "Text must be at most {} characters",
+            MAX_TEXT_CHARS
+        )));
+    }
+
+    let language = match parse_dialect(&request.dialect)? {
+        MfaDialect::AmericanEnglish => "en-us",
+        MfaDialect::BritishEnglish => "en",
+    };
+    let text = normalize_text(&request.text);
+    if text.is_empty() {
+        return Err(Error::BadRequest("Text is empty".to_string()));
+    }
+
+    let phonemes = {
+        let text = text.clone();
+        tokio::task::spawn_blocking(move || PHONEME_CACHE.phonemize(&text, language))
+            .await
+            .map_err(|e| Error::InternalServerError(format!("Phonemize task failed: {}", e)))?
+            .map_err(Error::InternalServerError)?
+    };
+
+    Ok(Json(PhonemizeResponse {
+        text,
+        language,
+        phonemes,
+    }))
+}
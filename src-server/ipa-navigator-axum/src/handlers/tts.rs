@@ -1,31 +1,86 @@
 use axum::{
-    extract::Json,
+    extract::{Json, Query, State},
     http::{HeaderMap, StatusCode, header},
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use ipa_navigator_kokoro::{
     error::TtsError,
-    tts::KokoroTTS,
-    voices::{
-        AmericanFemaleVoice, AmericanMaleVoice, BritishFemaleVoice, BritishMaleVoice, VoiceType,
-    },
+    normalize::NormalizeOptions,
+    prelude::SAMPLE_RATE,
+    segment::split_sentences,
+    teaching::{PauseLength, PauseUnit, TeachingMode},
+    tokenize::{strip_padding, tokens_to_phonemes, validate_tokens},
+    tts::{KokoroTTS, SENTENCE_PAUSE_SECS, Synthesis},
+    voices::{AmericanFemaleVoice, BritishFemaleVoice, VoiceType},
+    wav::{WavFormat, WaveformPeaks, encode_wav},
 };
+use ipa_navigator_mfa::docker::MfaDialect;
 
 use serde::{Deserialize, Serialize};
+use std::env;
 use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::bundle::{AudioBundle, BundleItem};
+use crate::config::CONFIG_MANAGER;
+use crate::engines::TtsEngine;
+use crate::error::Error;
+use crate::handlers::jobs::{JobCreatedResponse, job_created};
+use crate::identity::Session;
+use crate::jobs::{JOBS, Job, JobEvent, JobKind, JobOutput, JobStage};
+use crate::lesson_audio::ClipSpec;
+use crate::media::{
+    AudioClip, SYNTHESIZED_CACHE_CONTROL, TRUNCATED_CACHE_CONTROL, synthesis_modified, wav_body,
+};
+use crate::scheduler::{Admission, Priority, QueueFull, SCHEDULER, run_chunks};
+use crate::tenants::Tenant;
+
+// Longest text accepted by the batch endpoint, in characters
+const MAX_BATCH_TEXT_CHARS: usize = 20_000;
+
+// Time a synthesis request may take when `TTS_DEADLINE_SECS` is not set, in seconds,
+// leaving room under the 30s HTTP timeout to send what was synthesized
+const DEFAULT_DEADLINE_SECS: u64 = 25;
+
+// Header set on audio missing its later sentences because synthesis ran out of time
+const TRUNCATED_HEADER: &str = "x-tts-truncated";
+
+// Most phrases one prefetch request may warm, well under the synthesis cache's capacity
+const MAX_PREFETCH_PHRASES: usize = 20;
+
+// Peaks returned by the waveform endpoint by default, enough for a full-width waveform
+const DEFAULT_PEAK_POINTS: usize = 800;
+
+// Most peaks the waveform endpoint returns
+const MAX_PEAK_POINTS: usize = 10_000;
+
+// Peak value of a full-scale sample
+const PEAK_SCALE: f32 = 127.0;
+
+// Time allowed for a synthesis request, counted from when it arrives
+static SYNTHESIS_DEADLINE: LazyLock<Duration> = LazyLock::new(|| {
+    let secs = env::var("TTS_DEADLINE_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|&secs| secs > 0)
+        .unwrap_or(DEFAULT_DEADLINE_SECS);
+    Duration::from_secs(secs)
+});
 
 // Static TTS instance initialized lazily
 static TTS_INSTANCE: LazyLock<Mutex<Option<Arc<KokoroTTS>>>> = LazyLock::new(|| Mutex::new(None));
 
 // Get a reference to the TTS instance
-fn get_tts() -> Result<Arc<KokoroTTS>, TtsError> {
+pub(crate) fn get_tts() -> Result<Arc<KokoroTTS>, TtsError> {
     let mut tts_guard = TTS_INSTANCE
         .lock()
         .map_err(|_| TtsError::ModelLoadError("Failed to acquire TTS instance lock".to_string()))?;
 
     if tts_guard.is_none() {
-        // Initialize TTS if not already done
+        // Initialize TTS if not already done, with the cache size currently configured
         let tts = KokoroTTS::new()?;
+        tts.resize_cache(CONFIG_MANAGER.settings().synthesis_cache_size);
         *tts_guard = Some(Arc::new(tts));
     }
 
@@ -34,12 +89,105 @@ fn get_tts() -> Result<Arc<KokoroTTS>, TtsError> {
         .ok_or_else(|| TtsError::ModelLoadError("TTS initialization failed".to_string()))
 }
 
+// Load the TTS model and voices ahead of the first request, so readiness is reported promptly
+pub fn preload_tts() -> Result<Arc<KokoroTTS>, TtsError> {
+    get_tts()
+}
+
+// Get the TTS instance only if it has already been loaded
+pub(crate) fn loaded_tts() -> Option<Arc<KokoroTTS>> {
+    TTS_INSTANCE.lock().ok().and_then(|guard| guard.clone())
+}
+
 // Request model for TTS endpoint
 #[derive(Debug, Deserialize)]
 pub struct TtsRequest {
     text: String,
     voice: String,
     speed: Option<f32>,
+    // Comma-separated expansions or normalization stages to skip, e.g. "St.,numbers"
+    disable_expansions: Option<String>,
+    // Teaching mode for shadowing: pause between "words" or "syllables"
+    teaching: Option<String>,
+    // Length of teaching mode's pauses: "short", "medium" (default) or "long"
+    pause: Option<String>,
+}
+
+// Request model for the batch TTS endpoint
+#[derive(Debug, Deserialize)]
+pub struct TtsBatchRequest {
+    text: String,
+    voice: String,
+    speed: Option<f32>,
+    // Comma-separated expansions or normalization stages to skip, e.g. "St.,numbers"
+    disable_expansions: Option<String>,
+}
+
+// Query for the batch TTS endpoint: "wav" (default) for one file, or "zip" for a
+// bundle of a file per sentence and a manifest
+#[derive(Debug, Deserialize)]
+pub struct TtsBatchQuery {
+    format: Option<String>,
+}
+
+// Request model for the prefetch endpoint: phrases the learner is likely to hear next
+#[derive(Debug, Deserialize)]
+pub struct TtsPrefetchRequest {
+    phrases: Vec<String>,
+    voice: String,
+    speed: Option<f32>,
+    disable_expansions: Option<String>,
+}
+
+// Response model for the prefetch endpoint
+#[derive(Debug, Serialize)]
+pub struct TtsPrefetchResponse {
+    // Phrases queued for synthesis in the background
+    accepted: usize,
+}
+
+// Query for the waveform peaks endpoint: a TTS request and how many peaks to return
+#[derive(Debug, Deserialize)]
+pub struct TtsPeaksRequest {
+    text: String,
+    voice: String,
+    speed: Option<f32>,
+    disable_expansions: Option<String>,
+    teaching: Option<String>,
+    pause: Option<String>,
+    points: Option<usize>,
+}
+
+// Response model for the waveform peaks endpoint
+#[derive(Debug, Serialize)]
+pub struct WaveformPeaksResponse {
+    sample_rate: u32,
+    duration_secs: f64,
+    samples_per_peak: usize,
+    // Minimum and maximum of each window, scaled to -127..=127
+    peaks: Vec<[i8; 2]>,
+    // Whether later sentences were left out because synthesis ran out of time
+    truncated: bool,
+}
+
+// Request model for the token endpoint, for clients that phonemize and tokenize themselves
+#[derive(Debug, Deserialize)]
+pub struct TtsTokensRequest {
+    // Kokoro vocabulary IDs, optionally with the padding token 0 at either end
+    tokens: Vec<i64>,
+    voice: String,
+    speed: Option<f32>,
+}
+
+// Response model for the token endpoint
+#[derive(Debug, Serialize)]
+pub struct TtsTokensResponse {
+    // The tokens read back as phonemes, so clients can check what was spoken
+    phonemes: String,
+    sample_rate: u32,
+    duration_secs: f64,
+    // Base64-encoded WAV
+    audio: String,
 }
 
 // Response model for TTS endpoint errors
@@ -48,39 +196,201 @@ pub struct TtsErrorResponse {
     error: String,
 }
 
+// Normalization options disabling the expansions named in a request, respelling
+// words in the tenant's lexicon
+pub(crate) fn normalize_options(
+    tenant: Option<&Tenant>,
+    disable_expansions: Option<&str>,
+) -> NormalizeOptions {
+    let options = disable_expansions
+        .into_iter()
+        .flat_map(|names| names.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .fold(NormalizeOptions::default(), NormalizeOptions::disable);
+    match tenant.and_then(Tenant::lexicon) {
+        Some(lexicon) => options.with_lexicon(lexicon.clone()),
+        None => options,
+    }
+}
+
+// Teaching mode named in a request, if any; a pause length alone leaves it off
+fn teaching_mode(
+    teaching: Option<&str>,
+    pause: Option<&str>,
+) -> Result<Option<TeachingMode>, String> {
+    let Some(teaching) = teaching else {
+        return Ok(None);
+    };
+    let unit = PauseUnit::from_name(teaching).ok_or_else(|| {
+        format!(
+            "Unsupported teaching mode: {} (expected words or syllables)",
+            teaching
+        )
+    })?;
+    let pause = match pause {
+        Some(pause) => PauseLength::from_name(pause).ok_or_else(|| {
+            format!(
+                "Unsupported pause length: {} (expected short, medium or long)",
+                pause
+            )
+        })?,
+        None => PauseLength::default(),
+    };
+    Ok(Some(TeachingMode::new(unit, pause)))
+}
+
+// Status and body of a failed TTS request
+type TtsFailure = (StatusCode, Json<TtsErrorResponse>);
+
+// Default voice used for reference recordings in each dialect
+pub(crate) fn reference_voice(dialect: MfaDialect) -> VoiceType {
+    match dialect {
+        MfaDialect::AmericanEnglish => VoiceType::AmericanFemale(AmericanFemaleVoice::Bella),
+        MfaDialect::BritishEnglish => VoiceType::BritishFemale(BritishFemaleVoice::Emma),
+    }
+}
+
+// Default voice for reference recordings in a dialect, or the closest the tenant may use
+pub(crate) fn tenant_reference_voice(tenant: Option<&Tenant>, dialect: MfaDialect) -> VoiceType {
+    let voice = reference_voice(dialect);
+    tenant.map_or(voice, |tenant| tenant.voice_or(voice))
+}
+
 // Helper function to parse voice string to VoiceType
-fn parse_voice(voice_str: &str) -> Result<VoiceType, String> {
-    match voice_str {
-        "american_female_bella" => Ok(VoiceType::AmericanFemale(AmericanFemaleVoice::Bella)),
-        "american_female_nicole" => Ok(VoiceType::AmericanFemale(AmericanFemaleVoice::Nicole)),
-        "american_female_sky" => Ok(VoiceType::AmericanFemale(AmericanFemaleVoice::Sky)),
-        "american_male_fenrir" => Ok(VoiceType::AmericanMale(AmericanMaleVoice::Fenrir)),
-        "american_male_michael" => Ok(VoiceType::AmericanMale(AmericanMaleVoice::Michael)),
-        "american_male_puck" => Ok(VoiceType::AmericanMale(AmericanMaleVoice::Puck)),
-        "british_female_emma" => Ok(VoiceType::BritishFemale(BritishFemaleVoice::Emma)),
-        "british_female_isabella" => Ok(VoiceType::BritishFemale(BritishFemaleVoice::Isabella)),
-        "british_female_lily" => Ok(VoiceType::BritishFemale(BritishFemaleVoice::Lily)),
-        "british_male_fable" => Ok(VoiceType::BritishMale(BritishMaleVoice::Fable)),
-        "british_male_george" => Ok(VoiceType::BritishMale(BritishMaleVoice::George)),
-        "british_male_lewis" => Ok(VoiceType::BritishMale(BritishMaleVoice::Lewis)),
-        _ => Err(format!("Unsupported voice: {}", voice_str)),
+pub(crate) fn parse_voice(voice_str: &str) -> Result<VoiceType, String> {
+    VoiceType::from_name(voice_str).ok_or_else(|| format!("Unsupported voice: {}", voice_str))
+}
+
+// Parse a voice named in a request, refusing voices the tenant may not use
+pub(crate) fn tenant_voice(tenant: Option<&Tenant>, voice_str: &str) -> Result<VoiceType, String> {
+    let voice = parse_voice(voice_str)?;
+    match tenant {
+        Some(tenant) if !tenant.allows_voice(&voice) => {
+            Err(format!("Voice {} is not available", voice_str))
+        }
+        _ => Ok(voice),
     }
 }
 
+// 503 response with Retry-After for a request turned away because too many are queued
+fn busy_response(full: QueueFull) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, full.retry_after_secs.to_string())],
+        Json(TtsErrorResponse {
+            error: "Too many TTS requests, try again later".to_string(),
+        }),
+    )
+        .into_response()
+}
+
 // TTS endpoint handler
 pub async fn synthesize_speech(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
     Json(request): Json<TtsRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<TtsErrorResponse>)> {
-    let tts = get_tts().map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(TtsErrorResponse {
-                error: format!("TTS initialization error: {}", e),
-            }),
-        )
-    })?;
+) -> Result<impl IntoResponse, Response> {
+    let deadline = Instant::now() + *SYNTHESIS_DEADLINE;
+    let admission = SCHEDULER
+        .admit(Priority::Interactive)
+        .map_err(busy_response)?;
+    let permit = admission.acquire().await;
+    let (mut headers, synthesis) = speak(tts.as_ref(), request, tenant.as_ref(), deadline)
+        .map_err(IntoResponse::into_response)?;
+    drop(permit);
+
+    // Encoded while it is sent, so the WAV file is never held whole
+    let (body, len) = wav_body(synthesis.samples, WavFormat::default())
+        .map_err(|e| encoding_failure(e).into_response())?;
+    tracing::debug!("Streaming audio of {} bytes", len);
+    headers.insert(header::CONTENT_LENGTH, len.into());
+    Ok((headers, body))
+}
+
+// TTS endpoint taking the request as query parameters, so audio can be linked by URL
+pub async fn speech_audio(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
+    request_headers: HeaderMap,
+    Query(request): Query<TtsRequest>,
+) -> Result<Response, Response> {
+    let deadline = Instant::now() + *SYNTHESIS_DEADLINE;
+    let admission = SCHEDULER
+        .admit(Priority::Interactive)
+        .map_err(busy_response)?;
+    let permit = admission.acquire().await;
+    let (mut headers, synthesis) = speak(tts.as_ref(), request, tenant.as_ref(), deadline)
+        .map_err(IntoResponse::into_response)?;
+    drop(permit);
+
+    // Ranges are served from the whole file
+    let wav_data = encode_wav(&synthesis.samples, &WavFormat::default())
+        .map_err(|e| encoding_failure(e).into_response())?;
+    tracing::debug!("Generated audio of {} bytes", wav_data.len());
+
+    // Linked clips are cached by browsers and CDNs, and seekable by range, unless cut short
+    let truncated = headers.remove(TRUNCATED_HEADER);
+    let cache_control = match truncated {
+        Some(_) => TRUNCATED_CACHE_CONTROL,
+        None => SYNTHESIZED_CACHE_CONTROL,
+    };
+    let mut response =
+        AudioClip::wav(wav_data, synthesis_modified(), cache_control).respond(&request_headers);
+    if let Some(disposition) = headers.remove(header::CONTENT_DISPOSITION) {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    if let Some(truncated) = truncated {
+        response.headers_mut().insert(TRUNCATED_HEADER, truncated);
+    }
+    Ok(response)
+}
+
+// Synthesize a request into audio with the headers of a WAV response, marked truncated
+// if it ran out of time
+fn speak(
+    tts: &dyn TtsEngine,
+    request: TtsRequest,
+    tenant: Option<&Tenant>,
+    deadline: Instant,
+) -> Result<(HeaderMap, Synthesis), TtsFailure> {
+    let synthesis = synthesize_samples(tts, &request, tenant, deadline)?;
 
-    let voice = parse_voice(&request.voice)
+    // Set up headers for audio response
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "audio/wav".parse().unwrap());
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"tts.wav\"").parse().unwrap(),
+    );
+    if synthesis.truncated {
+        headers.insert(TRUNCATED_HEADER, "true".parse().unwrap());
+    }
+
+    Ok((headers, synthesis))
+}
+
+fn encoding_failure(e: TtsError) -> TtsFailure {
+    tracing::error!("WAV encoding error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(TtsErrorResponse {
+            error: e.to_string(),
+        }),
+    )
+}
+
+// Synthesize a request into samples, leaving out sentences that would not finish
+// before the deadline
+fn synthesize_samples(
+    tts: &dyn TtsEngine,
+    request: &TtsRequest,
+    tenant: Option<&Tenant>,
+    deadline: Instant,
+) -> Result<Synthesis, TtsFailure> {
+    let voice = tenant_voice(tenant, &request.voice)
         .map_err(|e| (StatusCode::BAD_REQUEST, Json(TtsErrorResponse { error: e })))?;
 
     let speed = request.speed.unwrap_or(1.0);
@@ -93,46 +403,659 @@ pub async fn synthesize_speech(
         ));
     }
 
+    let teaching = teaching_mode(request.teaching.as_deref(), request.pause.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(TtsErrorResponse { error: e })))?;
+
     tracing::info!(
-        "Processing TTS request: text='{}', voice={:?}, speed={}",
-        request.text,
+        "Processing TTS request: {} chars, voice={:?}, speed={}, teaching={:?}",
+        request.text.chars().count(),
         voice,
-        speed
+        speed,
+        teaching
     );
 
     // Process the text to speech
-    let result = tts.process_tts(&request.text, &voice, speed).map_err(|e| {
-        tracing::error!("TTS processing error: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
+    let mut options = normalize_options(tenant, request.disable_expansions.as_deref());
+    if let Some(teaching) = teaching {
+        options = options.with_teaching(teaching);
+    }
+    tts.synthesize_until(&request.text, &voice, speed, &options, deadline)
+        .map_err(|e| {
+            tracing::error!("TTS processing error: {}", e);
+            let status = match e {
+                TtsError::DeadlineExceeded(_) => StatusCode::GATEWAY_TIMEOUT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (
+                status,
+                Json(TtsErrorResponse {
+                    error: format!("TTS processing error: {}", e),
+                }),
+            )
+        })
+}
+
+// Waveform peaks endpoint handler, a JSON sidecar describing the clip `GET /api/tts`
+// returns for the same query, so waveforms render without decoding the audio
+pub async fn speech_peaks(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
+    Query(request): Query<TtsPeaksRequest>,
+) -> Result<Json<WaveformPeaksResponse>, Response> {
+    let points = request.points.unwrap_or(DEFAULT_PEAK_POINTS);
+    if !(1..=MAX_PEAK_POINTS).contains(&points) {
+        return Err((
+            StatusCode::BAD_REQUEST,
             Json(TtsErrorResponse {
-                error: format!("TTS processing error: {}", e),
+                error: format!("Points must be between 1 and {}", MAX_PEAK_POINTS),
             }),
         )
-    })?;
+            .into_response());
+    }
 
-    // Get the slice from ndarray
-    let audio_slice = result.as_slice().ok_or_else(|| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(TtsErrorResponse {
-                error: "Failed to convert audio data".to_string(),
+    let tts_request = TtsRequest {
+        text: request.text,
+        voice: request.voice,
+        speed: request.speed,
+        disable_expansions: request.disable_expansions,
+        teaching: request.teaching,
+        pause: request.pause,
+    };
+    let deadline = Instant::now() + *SYNTHESIS_DEADLINE;
+    let admission = SCHEDULER
+        .admit(Priority::Interactive)
+        .map_err(busy_response)?;
+    let permit = admission.acquire().await;
+    let synthesis = synthesize_samples(tts.as_ref(), &tts_request, tenant.as_ref(), deadline)
+        .map_err(IntoResponse::into_response)?;
+    drop(permit);
+    let samples = synthesis.samples;
+    let peaks = WaveformPeaks::from_samples(&samples, points);
+
+    // Scaled to whole numbers to keep the JSON compact
+    let scale = |sample: f32| (sample.clamp(-1.0, 1.0) * PEAK_SCALE).round() as i8;
+    Ok(Json(WaveformPeaksResponse {
+        sample_rate: SAMPLE_RATE,
+        duration_secs: samples.len() as f64 / SAMPLE_RATE as f64,
+        samples_per_peak: peaks.samples_per_peak,
+        peaks: peaks
+            .peaks
+            .into_iter()
+            .map(|(min, max)| [scale(min), scale(max)])
+            .collect(),
+        truncated: synthesis.truncated,
+    }))
+}
+
+// Token endpoint handler, synthesizing token IDs as given, without normalization,
+// phonemization or the cache
+pub async fn synthesize_tokens(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
+    Json(request): Json<TtsTokensRequest>,
+) -> Result<Json<TtsTokensResponse>, Error> {
+    let voice = tenant_voice(tenant.as_ref(), &request.voice).map_err(Error::BadRequest)?;
+
+    let speed = request.speed.unwrap_or(1.0);
+    if !(0.5..=2.0).contains(&speed) {
+        return Err(Error::BadRequest(
+            "Speed must be between 0.5 and 2.0".to_string(),
+        ));
+    }
+    validate_tokens(&request.tokens).map_err(|e| Error::BadRequest(e.to_string()))?;
+
+    tracing::info!(
+        "Processing TTS token request: {} tokens, voice={:?}, speed={}",
+        request.tokens.len(),
+        voice,
+        speed
+    );
+
+    let admission = SCHEDULER.admit(Priority::Interactive)?;
+    let permit = admission.acquire().await;
+    let samples = tts
+        .synthesize_tokens(&request.tokens, &voice, speed)
+        .map_err(|e| match e {
+            TtsError::TokenizationError(_) => Error::BadRequest(e.to_string()),
+            _ => {
+                tracing::error!("TTS processing error: {}", e);
+                Error::InternalServerError(format!("TTS processing error: {}", e))
+            }
+        })?;
+    drop(permit);
+
+    let wav_data = encode_wav(&samples, &WavFormat::default())
+        .map_err(|e| Error::InternalServerError(e.to_string()))?;
+
+    Ok(Json(TtsTokensResponse {
+        phonemes: tokens_to_phonemes(strip_padding(&request.tokens)),
+        sample_rate: SAMPLE_RATE,
+        duration_secs: samples.len() as f64 / SAMPLE_RATE as f64,
+        audio: BASE64.encode(wav_data),
+    }))
+}
+
+// Batch TTS endpoint handler, synthesizing long text sentence by sentence in a background job
+// owned by the signed-in user, if any
+pub async fn synthesize_batch(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
+    session: Option<Session>,
+    Query(query): Query<TtsBatchQuery>,
+    Json(request): Json<TtsBatchRequest>,
+) -> Result<(StatusCode, Json<JobCreatedResponse>), Error> {
+    let bundled = match query.format.as_deref().unwrap_or("wav") {
+        "wav" => false,
+        "zip" => true,
+        format => {
+            return Err(Error::BadRequest(format!(
+                "Unsupported format: {} (expected wav or zip)",
+                format
+            )));
+        }
+    };
+    let voice = tenant_voice(tenant.as_ref(), &request.voice).map_err(Error::BadRequest)?;
+
+    let speed = request.speed.unwrap_or(1.0);
+    if !(0.5..=2.0).contains(&speed) {
+        return Err(Error::BadRequest(
+            "Speed must be between 0.5 and 2.0".to_string(),
+        ));
+    }
+
+    if request.text.chars().count() > MAX_BATCH_TEXT_CHARS {
+        return Err(Error::BadRequest(format!(
+            "Text must be at most {} characters",
+            MAX_BATCH_TEXT_CHARS
+        )));
+    }
+    let chunks = split_sentences(&request.text);
+    if chunks.is_empty() {
+        return Err(Error::BadRequest("Text contains no sentences".to_string()));
+    }
+
+    let options = normalize_options(tenant.as_ref(), request.disable_expansions.as_deref());
+    let admission = SCHEDULER.admit(Priority::Batch)?;
+
+    let job = JOBS.create(JobKind::Tts, session.map(|session| session.subject));
+    tracing::info!(
+        "Queued TTS job {} with {} chunks, voice={:?}, speed={}",
+        job.id,
+        chunks.len(),
+        voice,
+        speed
+    );
+
+    // Bundled sentences are keyed as lesson clips reading them the same way would be
+    let variant = request.disable_expansions.unwrap_or_default();
+    let tenant_id = tenant.as_ref().map(|tenant| tenant.id().to_string());
+
+    let worker = job.clone();
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+
+        let output = synthesize_chunks(
+            tts.as_ref(),
+            &worker,
+            &admission,
+            &chunks,
+            &voice,
+            speed,
+            &options,
+        )
+        .and_then(|audio| {
+            if bundled {
+                let items = chunks
+                    .into_iter()
+                    .zip(audio)
+                    .map(|(text, samples)| BundleItem {
+                        cache_key: ClipSpec {
+                            text: &text,
+                            voice,
+                            speed,
+                            variant: &variant,
+                            tenant: tenant_id.as_deref(),
+                        }
+                        .id(),
+                        text,
+                        samples: samples.into(),
+                    })
+                    .collect();
+                Ok(JobOutput::Bundle(AudioBundle { items }))
+            } else {
+                encode_wav(&join_sentences(audio), &WavFormat::default()).map(JobOutput::Audio)
+            }
+        });
+        match output {
+            Ok(output) => worker.complete(output),
+            Err(e) => {
+                tracing::error!("TTS job {} failed: {}", worker.id, e);
+                worker.fail(format!("TTS processing error: {}", e));
+            }
+        }
+    });
+
+    Ok(job_created(&job))
+}
+
+// Prefetch endpoint handler, synthesizing upcoming phrases into the cache at batch priority
+// so their audio is ready by the time the learner reaches them
+pub async fn prefetch(
+    State(tts): State<Arc<dyn TtsEngine>>,
+    tenant: Option<Tenant>,
+    Json(request): Json<TtsPrefetchRequest>,
+) -> Result<(StatusCode, Json<TtsPrefetchResponse>), Error> {
+    let voice = tenant_voice(tenant.as_ref(), &request.voice).map_err(Error::BadRequest)?;
+
+    let speed = request.speed.unwrap_or(1.0);
+    if !(0.5..=2.0).contains(&speed) {
+        return Err(Error::BadRequest(
+            "Speed must be between 0.5 and 2.0".to_string(),
+        ));
+    }
+
+    let phrases: Vec<String> = request
+        .phrases
+        .into_iter()
+        .filter(|phrase| !phrase.trim().is_empty())
+        .collect();
+    if phrases.len() > MAX_PREFETCH_PHRASES {
+        return Err(Error::BadRequest(format!(
+            "At most {} phrases can be prefetched at once",
+            MAX_PREFETCH_PHRASES
+        )));
+    }
+
+    let options = normalize_options(tenant.as_ref(), request.disable_expansions.as_deref());
+    let admission = SCHEDULER.admit(Priority::Batch)?;
+    let accepted = phrases.len();
+
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+
+        for phrase in &phrases {
+            // Phrases the learner has already heard are still warm
+            if tts
+                .is_cached(phrase, &voice, speed, &options)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let _permit = tokio::runtime::Handle::current().block_on(admission.acquire());
+            if let Err(e) = tts.synthesize(phrase, &voice, speed, &options) {
+                tracing::warn!("Failed to prefetch a phrase: {}", e);
+            }
+        }
+        tracing::debug!("Finished prefetching {} phrases", phrases.len());
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(TtsPrefetchResponse { accepted })))
+}
+
+// Synthesize the chunks, several at once if the model has the sessions, returning their
+// audio in their original order
+//
+// An inference slot is taken for each chunk, so waiting interactive requests run between chunks
+fn synthesize_chunks(
+    tts: &dyn TtsEngine,
+    job: &Job,
+    admission: &Admission,
+    chunks: &[String],
+    voice: &VoiceType,
+    speed: f32,
+    options: &NormalizeOptions,
+) -> Result<Vec<Vec<f32>>, TtsError> {
+    run_chunks(
+        admission,
+        chunks,
+        SCHEDULER.max_parallel_inferences(),
+        |chunk| tts.synthesize(chunk, voice, speed, options),
+        |done| {
+            job.emit(
+                JobEvent::new(
+                    JobStage::Synthesizing,
+                    format!("Chunk {}/{} synthesized", done, chunks.len()),
+                )
+                .with_progress(done, chunks.len()),
+            )
+        },
+    )
+}
+
+// Join the audio of consecutive sentences with a pause between them
+fn join_sentences(audio: Vec<Vec<f32>>) -> Vec<f32> {
+    let pause = vec![0.0; (SAMPLE_RATE as f32 * SENTENCE_PAUSE_SECS) as usize];
+
+    let mut samples = Vec::new();
+    for (index, chunk_audio) in audio.into_iter().enumerate() {
+        if index > 0 {
+            samples.extend_from_slice(&pause);
+        }
+        samples.extend(chunk_audio);
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenants::{TenantConfig, Tenants};
+    use axum::body::to_bytes;
+
+    // Engine answering with a tone instead of running the model
+    enum MockTts {
+        Speaks,
+        Truncates,
+        MissesDeadline,
+    }
+
+    impl TtsEngine for MockTts {
+        fn synthesize_until(
+            &self,
+            text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+            _deadline: Instant,
+        ) -> Result<Synthesis, TtsError> {
+            let samples = tone(text);
+            match self {
+                MockTts::Speaks => Ok(Synthesis {
+                    samples,
+                    truncated: false,
+                    sentences_synthesized: 1,
+                    sentences_total: 1,
+                }),
+                MockTts::Truncates => Ok(Synthesis {
+                    samples,
+                    truncated: true,
+                    sentences_synthesized: 1,
+                    sentences_total: 2,
+                }),
+                MockTts::MissesDeadline => Err(TtsError::DeadlineExceeded(
+                    "no sentence finished".to_string(),
+                )),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<Vec<f32>, TtsError> {
+            Ok(tone(text))
+        }
+
+        fn is_cached(
+            &self,
+            _text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<bool, TtsError> {
+            Ok(false)
+        }
+
+        fn synthesize_tokens(
+            &self,
+            tokens: &[i64],
+            _voice: &VoiceType,
+            _speed: f32,
+        ) -> Result<Vec<f32>, TtsError> {
+            match self {
+                MockTts::MissesDeadline => {
+                    Err(TtsError::InferenceError("model unavailable".to_string()))
+                }
+                _ => Ok(tone(&"a".repeat(tokens.len()))),
+            }
+        }
+    }
+
+    // A tenth of a second of tone per character
+    fn tone(text: &str) -> Vec<f32> {
+        (0..text.len() * SAMPLE_RATE as usize / 10)
+            .map(|i| 0.5 * (i as f32 * 0.05).sin())
+            .collect()
+    }
+
+    fn engine(tts: MockTts) -> State<Arc<dyn TtsEngine>> {
+        State(Arc::new(tts))
+    }
+
+    fn request(voice: &str, speed: Option<f32>) -> TtsRequest {
+        TtsRequest {
+            text: "Hello there.".to_string(),
+            voice: voice.to_string(),
+            speed,
+            disable_expansions: None,
+            teaching: None,
+            pause: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_speech_returns_wav() {
+        let response = synthesize_speech(
+            engine(MockTts::Speaks),
+            None,
+            Json(request("american_female_bella", None)),
+        )
+        .await
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "audio/wav");
+        assert!(response.headers().get(TRUNCATED_HEADER).is_none());
+        let len: usize = response.headers()[header::CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..4], b"RIFF");
+        // The streamed file is the one encoded whole
+        assert_eq!(body.len(), len);
+        assert_eq!(
+            body,
+            encode_wav(&tone("Hello there."), &WavFormat::default()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_speech_rejects_bad_requests() {
+        let unknown_voice =
+            synthesize_speech(engine(MockTts::Speaks), None, Json(request("robot", None)))
+                .await
+                .into_response();
+        assert_eq!(unknown_voice.status(), StatusCode::BAD_REQUEST);
+
+        let too_fast = synthesize_speech(
+            engine(MockTts::Speaks),
+            None,
+            Json(request("american_female_bella", Some(3.0))),
+        )
+        .await
+        .into_response();
+        assert_eq!(too_fast.status(), StatusCode::BAD_REQUEST);
+
+        let unknown_teaching = synthesize_speech(
+            engine(MockTts::Speaks),
+            None,
+            Json(TtsRequest {
+                teaching: Some("phonemes".to_string()),
+                ..request("american_female_bella", None)
             }),
         )
-    })?;
+        .await
+        .into_response();
+        assert_eq!(unknown_teaching.status(), StatusCode::BAD_REQUEST);
+    }
 
-    // Convert to WAV
-    let wav_data = tts.audio_to_wav(audio_slice);
-    tracing::debug!("Generated audio of {} bytes", wav_data.len());
+    #[tokio::test]
+    async fn test_tenant_voices_and_lexicon() {
+        let tenants = Tenants::new(vec![TenantConfig {
+            id: "north".to_string(),
+            api_key: "key".to_string(),
+            voices: Some(vec!["british_female_emma".to_string()]),
+            lexicon: [("Ngaio".to_string(), "Nye oh".to_string())].into(),
+            requests_per_minute: None,
+            convex_url: None,
+        }])
+        .unwrap();
+        let tenant = tenants.find("key").cloned();
 
-    // Set up headers for audio response
-    let mut headers = HeaderMap::new();
-    headers.insert(header::CONTENT_TYPE, "audio/wav".parse().unwrap());
-    headers.insert(
-        header::CONTENT_DISPOSITION,
-        format!("attachment; filename=\"tts.wav\"").parse().unwrap(),
-    );
+        let other_voice = synthesize_speech(
+            engine(MockTts::Speaks),
+            tenant.clone(),
+            Json(request("american_female_bella", None)),
+        )
+        .await
+        .into_response();
+        assert_eq!(other_voice.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            tenant_reference_voice(tenant.as_ref(), MfaDialect::AmericanEnglish),
+            VoiceType::BritishFemale(BritishFemaleVoice::Emma)
+        );
 
-    // Return the WAV data with appropriate headers
-    Ok((headers, wav_data))
+        let options = normalize_options(tenant.as_ref(), None);
+        assert_eq!(options.lexicon().unwrap().respell("Ngaio"), "Nye oh");
+        assert_eq!(normalize_options(None, None).lexicon(), None);
+    }
+
+    #[test]
+    fn test_teaching_mode() {
+        assert_eq!(teaching_mode(None, Some("long")), Ok(None));
+        assert_eq!(
+            teaching_mode(Some("syllables"), None),
+            Ok(Some(TeachingMode::new(
+                PauseUnit::Syllable,
+                PauseLength::Medium
+            )))
+        );
+        assert!(teaching_mode(Some("words"), Some("forever")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_audio_is_marked_and_not_cached() {
+        let response = speech_audio(
+            engine(MockTts::Truncates),
+            None,
+            HeaderMap::new(),
+            Query(request("american_female_bella", None)),
+        )
+        .await
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[TRUNCATED_HEADER], "true");
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            TRUNCATED_CACHE_CONTROL
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missed_deadline_is_gateway_timeout() {
+        let response = synthesize_speech(
+            engine(MockTts::MissesDeadline),
+            None,
+            Json(request("american_female_bella", None)),
+        )
+        .await
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_speech_peaks() {
+        let peaks_request = |points| TtsPeaksRequest {
+            text: "Hello there.".to_string(),
+            voice: "american_female_bella".to_string(),
+            speed: None,
+            disable_expansions: None,
+            teaching: None,
+            pause: None,
+            points: Some(points),
+        };
+
+        let Json(peaks) = speech_peaks(engine(MockTts::Truncates), None, Query(peaks_request(10)))
+            .await
+            .unwrap();
+        assert_eq!(peaks.sample_rate, SAMPLE_RATE);
+        assert!((peaks.duration_secs - 1.2).abs() < 1e-6);
+        assert!(!peaks.peaks.is_empty() && peaks.peaks.len() <= 10);
+        assert!(peaks.truncated);
+
+        let no_points = speech_peaks(engine(MockTts::Speaks), None, Query(peaks_request(0)))
+            .await
+            .unwrap_err();
+        assert_eq!(no_points.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_limits_phrases() {
+        let prefetch_request = |count| TtsPrefetchRequest {
+            phrases: vec!["Hello.".to_string(); count],
+            voice: "american_female_bella".to_string(),
+            speed: None,
+            disable_expansions: None,
+        };
+
+        let (status, Json(response)) =
+            prefetch(engine(MockTts::Speaks), None, Json(prefetch_request(3)))
+                .await
+                .unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(response.accepted, 3);
+
+        let too_many = prefetch(
+            engine(MockTts::Speaks),
+            None,
+            Json(prefetch_request(MAX_PREFETCH_PHRASES + 1)),
+        )
+        .await;
+        assert!(matches!(too_many, Err(Error::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_tokens_echoes_phonemes() {
+        let tokens_request = |tokens: Vec<i64>| TtsTokensRequest {
+            tokens,
+            voice: "american_female_bella".to_string(),
+            speed: None,
+        };
+
+        let Json(response) = synthesize_tokens(
+            engine(MockTts::Speaks),
+            None,
+            Json(tokens_request(vec![0, 50, 83, 54, 156, 57, 135, 0])),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.phonemes, "həlˈoʊ");
+        assert_eq!(response.sample_rate, SAMPLE_RATE);
+        assert!(response.duration_secs > 0.0);
+        assert!(BASE64.decode(&response.audio).unwrap().starts_with(b"RIFF"));
+
+        for tokens in [vec![], vec![50, 9999], vec![50; 600]] {
+            let rejected =
+                synthesize_tokens(engine(MockTts::Speaks), None, Json(tokens_request(tokens)))
+                    .await;
+            assert!(matches!(rejected, Err(Error::BadRequest(_))));
+        }
+
+        let failed = synthesize_tokens(
+            engine(MockTts::MissesDeadline),
+            None,
+            Json(tokens_request(vec![50, 83])),
+        )
+        .await;
+        assert!(matches!(failed, Err(Error::InternalServerError(_))));
+    }
 }
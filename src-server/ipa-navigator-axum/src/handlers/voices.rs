@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Path, Query},
+};
+use ipa_navigator_kokoro::{
+    tts::KokoroTTS,
+    voices::{ALL_VOICES, VoiceType},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::handlers::tts::get_tts;
+use crate::tenants::Tenant;
+
+/// A voice and whether it can be used
+#[derive(Debug, Serialize)]
+pub struct VoiceResponse {
+    pub name: &'static str,
+    pub language: &'static str,
+
+    /// Whether the voice's embedding loaded
+    pub available: bool,
+
+    /// Why the voice failed to load
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Query for voices similar to one, at most `limit` of them
+#[derive(Debug, Deserialize)]
+pub struct SimilarVoicesQuery {
+    pub limit: Option<usize>,
+}
+
+/// A voice and how close its style is to the one asked about
+#[derive(Debug, Serialize)]
+pub struct SimilarVoiceResponse {
+    pub name: &'static str,
+    pub language: &'static str,
+
+    /// Cosine similarity of the voices' averaged style vectors, from -1 to 1
+    pub similarity: f32,
+}
+
+/// Voices similar to one, most similar first
+#[derive(Debug, Serialize)]
+pub struct SimilarVoicesResponse {
+    pub voice: &'static str,
+    pub similar: Vec<SimilarVoiceResponse>,
+}
+
+/// Most voices returned by the similar voices endpoint
+const MAX_SIMILAR_VOICES: usize = 20;
+
+/// A voice that failed to load
+#[derive(Debug, Serialize)]
+pub struct DegradedVoice {
+    pub voice: &'static str,
+    pub error: String,
+}
+
+/// Voices that failed to load, so the server is running with fewer
+pub(crate) fn degraded_voices(tts: &KokoroTTS) -> Vec<DegradedVoice> {
+    tts.failed_voices()
+        .into_iter()
+        .map(|(voice, error)| DegradedVoice {
+            voice: voice.name(),
+            error,
+        })
+        .collect()
+}
+
+/// Load the shared TTS, which holds the voice embeddings, without blocking the runtime
+async fn loaded_tts() -> Result<Arc<KokoroTTS>, Error> {
+    tokio::task::spawn_blocking(get_tts)
+        .await
+        .map_err(|e| Error::InternalServerError(format!("Loading TTS failed: {}", e)))?
+        .map_err(|e| Error::ServiceUnavailable(format!("TTS is unavailable: {}", e)))
+}
+
+/// Handler listing every voice the tenant may use and whether it loaded
+pub async fn list(tenant: Option<Tenant>) -> Result<Json<Vec<VoiceResponse>>, Error> {
+    let tts = loaded_tts().await?;
+    let failed = tts.failed_voices();
+
+    Ok(Json(
+        ALL_VOICES
+            .iter()
+            .filter(|voice| {
+                tenant
+                    .as_ref()
+                    .is_none_or(|tenant| tenant.allows_voice(voice))
+            })
+            .map(|voice| {
+                let error = failed
+                    .iter()
+                    .find(|(failed_voice, _)| failed_voice == voice)
+                    .map(|(_, error)| error.clone());
+                VoiceResponse {
+                    name: voice.name(),
+                    language: voice.language(),
+                    available: error.is_none(),
+                    error,
+                }
+            })
+            .collect(),
+    ))
+}
+
+/// Handler listing the voices most like one, so learners can try a similar voice
+///
+/// Only voices the tenant may use are suggested, and a voice the tenant may
+/// not use is reported as unknown.
+pub async fn similar(
+    tenant: Option<Tenant>,
+    Path(name): Path<String>,
+    Query(query): Query<SimilarVoicesQuery>,
+) -> Result<Json<SimilarVoicesResponse>, Error> {
+    let limit = query.limit.unwrap_or(5);
+    if limit == 0 || limit > MAX_SIMILAR_VOICES {
+        return Err(Error::BadRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_SIMILAR_VOICES
+        )));
+    }
+
+    let allowed = |voice: &VoiceType| {
+        tenant
+            .as_ref()
+            .is_none_or(|tenant| tenant.allows_voice(voice))
+    };
+    let voice = VoiceType::from_name(&name)
+        .filter(allowed)
+        .ok_or_else(|| Error::NotFound(format!("Unknown voice: {}", name)))?;
+
+    let similarity = loaded_tts().await?.voice_similarity();
+    let similar = similarity.similar(&voice).ok_or_else(|| {
+        Error::ServiceUnavailable(format!("Voice {} is not loaded", voice.name()))
+    })?;
+
+    Ok(Json(SimilarVoicesResponse {
+        voice: voice.name(),
+        similar: similar
+            .iter()
+            .filter(|similar| allowed(&similar.voice))
+            .take(limit)
+            .map(|similar| SimilarVoiceResponse {
+                name: similar.voice.name(),
+                language: similar.voice.language(),
+                similarity: similar.similarity,
+            })
+            .collect(),
+    }))
+}
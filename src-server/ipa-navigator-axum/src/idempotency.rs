@@ -0,0 +1,382 @@
+//! Replaying responses to retried requests carrying an `Idempotency-Key`
+//!
+//! Synthesizing a batch or assessing a recording takes seconds, so a client
+//! on a flaky connection that retries after a dropped response would start the
+//! same work again. Requests sending the same key, from the same tenant and
+//! user, instead get the first response back until the key expires. Keys are
+//! kept in memory, so they do not survive restarts or span server instances.
+
+use std::env;
+use std::num::NonZeroUsize;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{Body, Bytes, HttpBody, to_bytes},
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+use crate::identity::Session;
+use crate::tenants::Tenant;
+
+/// Header naming a request, so retries of it can be recognised
+pub static IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Header marking a response as a replay of an earlier one
+pub static IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// How long responses are replayed when `IDEMPOTENCY_TTL_SECS` is not set
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Keys remembered when `IDEMPOTENCY_MAX_KEYS` is not set
+const DEFAULT_MAX_KEYS: NonZeroUsize = NonZeroUsize::new(10_000).unwrap();
+
+/// Longest key accepted
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Largest request body read to fingerprint the request
+const MAX_REQUEST_BYTES: usize = 16 * 1024 * 1024;
+
+/// Largest response body kept for replay; larger responses are not replayed
+const MAX_RESPONSE_BYTES: u64 = 32 * 1024 * 1024;
+
+pub struct IdempotencyConfig {
+    /// How long after a request its response is replayed
+    pub ttl: Duration,
+    /// Keys remembered at once, dropping the least recently used
+    pub max_keys: NonZeroUsize,
+}
+
+impl IdempotencyConfig {
+    pub fn from_env() -> Self {
+        let ttl = env::var("IDEMPOTENCY_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+
+        let max_keys = env::var("IDEMPOTENCY_MAX_KEYS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .and_then(NonZeroUsize::new)
+            .unwrap_or(DEFAULT_MAX_KEYS);
+
+        Self { ttl, max_keys }
+    }
+}
+
+/// Keys shared by all idempotent routes
+pub static IDEMPOTENCY: LazyLock<IdempotencyStore> =
+    LazyLock::new(|| IdempotencyStore::new(IdempotencyConfig::from_env()));
+
+/// Hash of everything identifying a request, so a key reused for a different one is refused
+type Fingerprint = [u8; 32];
+
+/// A response kept to be replayed
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl IntoResponse for StoredResponse {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, self.headers, self.body).into_response();
+        response.headers_mut().insert(
+            IDEMPOTENT_REPLAYED.clone(),
+            HeaderValue::from_static("true"),
+        );
+        response
+    }
+}
+
+#[derive(Debug)]
+enum State {
+    /// The first request is still being handled
+    InFlight,
+    Done(StoredResponse),
+}
+
+#[derive(Debug)]
+struct Entry {
+    fingerprint: Fingerprint,
+    created: Instant,
+    state: State,
+}
+
+/// What to do with a request carrying a key
+#[derive(Debug)]
+enum Claim {
+    /// The key is new, handle the request
+    Proceed,
+    Replay(StoredResponse),
+    /// A request with the key is being handled
+    InFlight,
+    /// The key was used for a different request
+    Mismatch,
+}
+
+pub struct IdempotencyStore {
+    ttl: Duration,
+    entries: Mutex<LruCache<String, Entry>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(config: IdempotencyConfig) -> Self {
+        Self {
+            ttl: config.ttl,
+            entries: Mutex::new(LruCache::new(config.max_keys)),
+        }
+    }
+
+    /// Claim `scope` for a request, unless it is already known
+    fn claim(&self, scope: &str, fingerprint: Fingerprint) -> Claim {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = entries.get(scope)
+            && entry.created.elapsed() < self.ttl
+        {
+            return if entry.fingerprint != fingerprint {
+                Claim::Mismatch
+            } else {
+                match &entry.state {
+                    State::InFlight => Claim::InFlight,
+                    State::Done(response) => Claim::Replay(response.clone()),
+                }
+            };
+        }
+
+        entries.put(
+            scope.to_string(),
+            Entry {
+                fingerprint,
+                created: Instant::now(),
+                state: State::InFlight,
+            },
+        );
+        Claim::Proceed
+    }
+
+    /// Keep the response to a claimed request, or release the key so it can be retried
+    fn complete(&self, scope: &str, response: Option<StoredResponse>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match response {
+            Some(response) => {
+                if let Some(entry) = entries.peek_mut(scope) {
+                    entry.state = State::Done(response);
+                }
+            }
+            None => {
+                entries.pop(scope);
+            }
+        }
+    }
+
+    /// Handle `request`, replaying the response to an earlier request with the same key
+    async fn handle(&self, request: Request, next: Next) -> Response {
+        let Some(key) = request.headers().get(&IDEMPOTENCY_KEY) else {
+            return next.run(request).await;
+        };
+        let key = match key.to_str() {
+            Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+            _ => {
+                return Error::BadRequest(format!(
+                    "Idempotency-Key must be 1 to {} visible ASCII characters",
+                    MAX_KEY_LENGTH
+                ))
+                .into_response();
+            }
+        };
+
+        let (parts, body) = request.into_parts();
+        let bytes = match to_bytes(body, MAX_REQUEST_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return Error::BadRequest(format!("Failed to read request body: {}", e))
+                    .into_response();
+            }
+        };
+        let path = parts
+            .uri
+            .path_and_query()
+            .map_or(parts.uri.path(), |path| path.as_str());
+        let fingerprint = fingerprint(parts.method.as_str(), path, &bytes);
+        let scope = scope(
+            parts.extensions.get::<Tenant>(),
+            parts.extensions.get::<Session>(),
+            &key,
+        );
+
+        match self.claim(&scope, fingerprint) {
+            Claim::Proceed => {}
+            Claim::Replay(response) => return response.into_response(),
+            Claim::InFlight => {
+                return Error::Conflict(
+                    "A request with this Idempotency-Key is still being handled".to_string(),
+                )
+                .into_response();
+            }
+            Claim::Mismatch => {
+                return Error::BadRequest(
+                    "Idempotency-Key was already used for a different request".to_string(),
+                )
+                .into_response();
+            }
+        }
+
+        // Release the key if the client goes away before the response is ready
+        let mut claimed = Claimed {
+            store: self,
+            scope: &scope,
+            done: false,
+        };
+        let response = next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await;
+        let (response, stored) = keep(response).await;
+        self.complete(&scope, stored);
+        claimed.done = true;
+        response
+    }
+}
+
+/// Releases a claimed key when dropped before the request completes
+struct Claimed<'a> {
+    store: &'a IdempotencyStore,
+    scope: &'a str,
+    done: bool,
+}
+
+impl Drop for Claimed<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.store.complete(self.scope, None);
+        }
+    }
+}
+
+/// Keys are per tenant and user, so one user's key never replays another's response
+fn scope(tenant: Option<&Tenant>, session: Option<&Session>, key: &str) -> String {
+    format!(
+        "{}\n{}\n{}",
+        tenant.map_or("", Tenant::id),
+        session.map_or("", |session| session.subject.as_str()),
+        key
+    )
+}
+
+/// Hash of a request, each part length-prefixed so different splits never collide
+fn fingerprint(method: &str, path: &str, body: &[u8]) -> Fingerprint {
+    let mut hasher = Sha256::new();
+    for part in [method.as_bytes(), path.as_bytes(), body] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Copy of `response` to replay, if it should be
+///
+/// Server errors are not kept, so a retry gets another attempt, and nor are
+/// streamed or very large responses.
+async fn keep(response: Response) -> (Response, Option<StoredResponse>) {
+    let size = response.body().size_hint().exact();
+    if response.status().is_server_error() || size.is_none_or(|size| size > MAX_RESPONSE_BYTES) {
+        return (response, None);
+    }
+
+    let (parts, body) = response.into_parts();
+    match to_bytes(body, MAX_RESPONSE_BYTES as usize).await {
+        Ok(bytes) => {
+            let stored = StoredResponse {
+                status: parts.status,
+                headers: parts.headers.clone(),
+                body: bytes.clone(),
+            };
+            (Response::from_parts(parts, Body::from(bytes)), Some(stored))
+        }
+        Err(e) => (
+            Error::InternalServerError(format!("Failed to read response body: {}", e))
+                .into_response(),
+            None,
+        ),
+    }
+}
+
+/// Middleware replaying the response to an earlier request with the same `Idempotency-Key`
+///
+/// Requests without the header are handled as usual. A key reused for a
+/// different request is refused, as is a retry while the first is still
+/// being handled.
+pub async fn replay(request: Request, next: Next) -> Response {
+    IDEMPOTENCY.handle(request, next).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(ttl: Duration) -> IdempotencyStore {
+        IdempotencyStore::new(IdempotencyConfig {
+            ttl,
+            max_keys: NonZeroUsize::new(2).unwrap(),
+        })
+    }
+
+    fn response(body: &'static str) -> StoredResponse {
+        StoredResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_replays_completed_requests() {
+        let store = store(DEFAULT_TTL);
+        let request = fingerprint("POST", "/api/tts/batch", b"{}");
+
+        assert!(matches!(store.claim("a", request), Claim::Proceed));
+        assert!(matches!(store.claim("a", request), Claim::InFlight));
+
+        store.complete("a", Some(response("done")));
+        match store.claim("a", request) {
+            Claim::Replay(stored) => assert_eq!(stored.body, "done"),
+            other => panic!("expected a replay, got {:?}", other),
+        }
+
+        let other = fingerprint("POST", "/api/tts/batch", b"{\"texts\":[]}");
+        assert!(matches!(store.claim("a", other), Claim::Mismatch));
+        assert!(matches!(store.claim("b", other), Claim::Proceed));
+    }
+
+    #[test]
+    fn test_released_and_expired_keys_can_be_reused() {
+        let store = store(DEFAULT_TTL);
+        let request = fingerprint("POST", "/api/pronunciation", b"audio");
+
+        assert!(matches!(store.claim("a", request), Claim::Proceed));
+        store.complete("a", None);
+        assert!(matches!(store.claim("a", request), Claim::Proceed));
+
+        let store = self::store(Duration::ZERO);
+        assert!(matches!(store.claim("a", request), Claim::Proceed));
+        store.complete("a", Some(response("done")));
+        assert!(matches!(store.claim("a", request), Claim::Proceed));
+    }
+
+    #[test]
+    fn test_fingerprints_and_scopes_are_unambiguous() {
+        assert_ne!(
+            fingerprint("POST", "/a", b"b"),
+            fingerprint("POST", "/ab", b"")
+        );
+        assert_ne!(scope(None, None, "key"), scope(None, None, "other"));
+    }
+}
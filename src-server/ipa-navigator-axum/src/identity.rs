@@ -0,0 +1,341 @@
+//! Signed-in users, identified by the Clerk token the frontend sends to Convex
+//!
+//! The frontend authenticates to Convex with a JWT issued by Clerk. When a
+//! request carries it as `Authorization: Bearer <token>`, the token is checked
+//! against Clerk's signing keys and handlers receive the [`Session`]. Handlers
+//! that act on a user's Convex data take an [`AuthUser`], which also resolves
+//! the user's Convex id, so clients never send user ids themselves. Requests
+//! without a token pass through anonymously; those with an invalid one are
+//! rejected. A session made for a tenant with its own Convex deployment runs
+//! its Convex calls there.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::env;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts, Request},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Validation,
+    jwk::{Jwk, JwkSet},
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+use crate::convex::{CONVEX, ConvexHttp};
+use crate::error::Error;
+use crate::tenants::Tenant;
+
+/// Audience of tokens minted for Convex, its `applicationID` in `auth.config.ts`
+const AUDIENCE: &str = "convex";
+
+/// Longest wait for Clerk's signing keys
+const JWKS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long fetched signing keys are trusted before fetching them again
+const JWKS_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Shortest wait between fetches prompted by tokens signed with an unknown key
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(60);
+
+/// How long a user's Convex id is reused before asking Convex again
+const USER_ID_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Most Convex user ids remembered at once
+const MAX_CACHED_USERS: usize = 1000;
+
+/// Verifier for the Clerk instance at `CLERK_ISSUER_URL`, or none if it is not set
+static VERIFIER: LazyLock<Option<TokenVerifier>> = LazyLock::new(|| {
+    let issuer = env::var("CLERK_ISSUER_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty());
+    if issuer.is_none() {
+        warn!("CLERK_ISSUER_URL is not set, signed-in users are not identified");
+    }
+
+    TokenVerifier::new(issuer?.trim_end_matches('/'))
+        .inspect_err(|e| warn!("Sign-in disabled: {}", e))
+        .ok()
+});
+
+/// Convex user ids by tenant and Clerk user id
+static USER_IDS: LazyLock<Mutex<HashMap<String, (String, Instant)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A request's verified Clerk token
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// Clerk user id, the token's subject
+    pub subject: String,
+    /// The `Authorization` header, forwarded to run Convex functions as this user
+    pub authorization: String,
+    /// The tenant the request was made for, if the server is shared
+    pub tenant: Option<Tenant>,
+}
+
+impl Session {
+    /// The Convex deployment holding this user's data
+    pub fn convex(&self) -> Result<&ConvexHttp, Error> {
+        self.tenant
+            .as_ref()
+            .and_then(Tenant::convex)
+            .or(CONVEX.as_ref())
+            .ok_or_else(|| Error::ServiceUnavailable("Convex is not configured".to_string()))
+    }
+
+    /// Key of the user's cached Convex id, since each tenant's deployment has its own ids
+    fn cache_key(&self) -> String {
+        match &self.tenant {
+            Some(tenant) => format!("{}/{}", tenant.id(), self.subject),
+            None => self.subject.clone(),
+        }
+    }
+}
+
+/// A signed-in user, with their Convex id
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    /// Id of the user's document in the Convex `users` table
+    pub user_id: String,
+    pub session: Session,
+}
+
+impl AuthUser {
+    /// The user signed in with `session`
+    pub async fn from_session(session: Session) -> Result<Self, Error> {
+        let user_id = resolve_user_id(&session).await?;
+        Ok(AuthUser { user_id, session })
+    }
+
+    /// The `Authorization` header to forward to Convex
+    pub fn authorization(&self) -> &str {
+        &self.session.authorization
+    }
+}
+
+/// Claims read from a Clerk token, beyond those checked by the validation
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+}
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+struct TokenVerifier {
+    client: reqwest::Client,
+    issuer: String,
+    keys: RwLock<Option<CachedKeys>>,
+}
+
+impl TokenVerifier {
+    fn new(issuer: &str) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(JWKS_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            client,
+            issuer: issuer.to_string(),
+            keys: RwLock::new(None),
+        })
+    }
+
+    /// Check a token's signature, issuer, audience, and expiry
+    async fn verify(&self, token: &str) -> Result<Claims, Error> {
+        let invalid = |e: jsonwebtoken::errors::Error| {
+            Error::Unauthorized(format!("Invalid session token: {}", e))
+        };
+
+        let header = jsonwebtoken::decode_header(token).map_err(invalid)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| Error::Unauthorized("Invalid session token: no key id".to_string()))?;
+        let key = self.key(&kid).await?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[AUDIENCE]);
+        validation.set_issuer(&[&self.issuer]);
+
+        jsonwebtoken::decode::<Claims>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(invalid)
+    }
+
+    /// The signing key with this id, fetching Clerk's keys if it is not known
+    async fn key(&self, kid: &str) -> Result<DecodingKey, Error> {
+        let find = |cached: &Option<CachedKeys>| {
+            cached
+                .as_ref()
+                .filter(|cached| cached.fetched_at.elapsed() < JWKS_MAX_AGE)
+                .and_then(|cached| cached.keys.find(kid))
+                .map(decoding_key)
+        };
+
+        if let Some(key) = find(&*self.keys.read().await) {
+            return key;
+        }
+
+        let mut cached = self.keys.write().await;
+        // Another request may have fetched the keys while this one waited
+        if let Some(key) = find(&cached) {
+            return key;
+        }
+        // Tokens naming unknown keys must not make every request fetch them again
+        if cached
+            .as_ref()
+            .is_some_and(|cached| cached.fetched_at.elapsed() < JWKS_MIN_REFRESH)
+        {
+            return Err(unknown_key(kid));
+        }
+
+        debug!("Fetching Clerk signing keys");
+        let keys = self.fetch_keys().await?;
+        let key = keys.find(kid).map(decoding_key);
+        *cached = Some(CachedKeys {
+            keys,
+            fetched_at: Instant::now(),
+        });
+
+        key.unwrap_or_else(|| Err(unknown_key(kid)))
+    }
+
+    async fn fetch_keys(&self) -> Result<JwkSet, Error> {
+        let unavailable = |e: reqwest::Error| {
+            Error::ServiceUnavailable(format!("Failed to fetch Clerk keys: {}", e))
+        };
+
+        self.client
+            .get(format!("{}/.well-known/jwks.json", self.issuer))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(unavailable)?
+            .json()
+            .await
+            .map_err(unavailable)
+    }
+}
+
+fn decoding_key(jwk: &Jwk) -> Result<DecodingKey, Error> {
+    DecodingKey::from_jwk(jwk)
+        .map_err(|e| Error::Unauthorized(format!("Unusable signing key: {}", e)))
+}
+
+fn unknown_key(kid: &str) -> Error {
+    Error::Unauthorized(format!("Invalid session token: unknown key {}", kid))
+}
+
+/// Middleware verifying the bearer token, if any, and attaching its [`Session`]
+///
+/// Without `CLERK_ISSUER_URL`, tokens are ignored and every request is anonymous.
+pub async fn identify(mut request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+
+    if let (Some(token), Some(verifier)) = (token, VERIFIER.as_ref()) {
+        match verifier.verify(&token).await {
+            Ok(claims) => {
+                let tenant = request.extensions().get::<Tenant>().cloned();
+                request.extensions_mut().insert(Session {
+                    subject: claims.sub,
+                    authorization: format!("Bearer {}", token),
+                    tenant,
+                });
+            }
+            Err(e) => return e.into_response(),
+        }
+    }
+
+    next.run(request).await
+}
+
+/// The user's Convex id, creating their user document on first sign-in
+async fn resolve_user_id(session: &Session) -> Result<String, Error> {
+    let key = session.cache_key();
+    if let Ok(users) = USER_IDS.lock()
+        && let Some((user_id, stored_at)) = users.get(&key)
+        && stored_at.elapsed() < USER_ID_TTL
+    {
+        return Ok(user_id.clone());
+    }
+
+    let convex = session.convex()?;
+    // The same mutation the frontend runs on sign-in, so the user document always exists
+    let user_id = convex
+        .mutation(
+            "functions/users:store",
+            json!({}),
+            Some(&session.authorization),
+        )
+        .await?
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::InternalServerError("Convex returned no user id".to_string()))?;
+
+    if let Ok(mut users) = USER_IDS.lock() {
+        if users.len() >= MAX_CACHED_USERS {
+            users.retain(|_, (_, stored_at)| stored_at.elapsed() < USER_ID_TTL);
+        }
+        if users.len() >= MAX_CACHED_USERS {
+            users.clear();
+        }
+        users.insert(key, (user_id.clone(), Instant::now()));
+    }
+
+    Ok(user_id)
+}
+
+/// Forget a user's cached Convex id, after their user document is deleted
+pub fn forget_user(session: &Session) {
+    if let Ok(mut users) = USER_IDS.lock() {
+        users.remove(&session.cache_key());
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Session {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Session>()
+            .cloned()
+            .ok_or_else(|| Error::Unauthorized("Sign in to continue".to_string()))
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for Session {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Session>().cloned())
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session = <Session as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+        AuthUser::from_session(session).await
+    }
+}
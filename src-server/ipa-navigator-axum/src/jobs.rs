@@ -0,0 +1,330 @@
+//! Long-running TTS, narration and assessment jobs, tracked so clients can follow their progress
+//!
+//! Each job keeps the events it has emitted and broadcasts new ones, so a
+//! client subscribing late still sees the whole history.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::bundle::AudioBundle;
+use crate::handlers::lessons::LessonManifest;
+use crate::handlers::narration::Narration;
+use crate::report::AssessedRecording;
+use crate::store;
+
+/// How long a finished job and its result are kept
+const JOB_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Most jobs tracked at once; the oldest finished jobs are dropped first
+const MAX_JOBS: usize = 200;
+
+/// Events buffered for each subscriber before it starts missing some
+const EVENT_BUFFER: usize = 64;
+
+/// Jobs shared by all requests
+pub static JOBS: LazyLock<JobRegistry> = LazyLock::new(JobRegistry::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Tts,
+    Assessment,
+    Narration,
+    LessonAudio,
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::Tts => "tts",
+            JobKind::Assessment => "assessment",
+            JobKind::Narration => "narration",
+            JobKind::LessonAudio => "lesson_audio",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStage {
+    Queued,
+    Synthesizing,
+    Aligning,
+    Scoring,
+    Done,
+    Failed,
+}
+
+impl JobStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStage::Queued => "queued",
+            JobStage::Synthesizing => "synthesizing",
+            JobStage::Aligning => "aligning",
+            JobStage::Scoring => "scoring",
+            JobStage::Done => "done",
+            JobStage::Failed => "failed",
+        }
+    }
+
+    /// Whether the job will emit no further events
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, JobStage::Done | JobStage::Failed)
+    }
+}
+
+/// A progress update, e.g. "chunk 3/10 synthesized"
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub stage: JobStage,
+    pub message: String,
+
+    /// Steps completed and total, for stages that report them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl JobEvent {
+    pub fn new(stage: JobStage, message: impl Into<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+
+        Self {
+            stage,
+            message: message.into(),
+            completed: None,
+            total: None,
+            timestamp,
+        }
+    }
+
+    pub fn with_progress(mut self, completed: usize, total: usize) -> Self {
+        self.completed = Some(completed);
+        self.total = Some(total);
+        self
+    }
+}
+
+/// What a finished job produced
+#[derive(Debug, Clone)]
+pub enum JobOutput {
+    /// WAV audio
+    Audio(Vec<u8>),
+    /// Boxed, since it keeps the recording and the full response
+    Assessment(Box<AssessedRecording>),
+    Narration(Narration),
+    LessonAudio(LessonManifest),
+    /// Audio of each sentence, sent as a zip bundle
+    Bundle(AudioBundle),
+}
+
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub created_at: SystemTime,
+    /// Clerk user id of the signed-in user who created the job
+    pub owner: Option<String>,
+    state: Mutex<JobState>,
+}
+
+struct JobState {
+    events: Vec<JobEvent>,
+    /// Dropped once the job finishes, which ends every subscription
+    sender: Option<broadcast::Sender<JobEvent>>,
+    output: Option<JobOutput>,
+    finished_at: Option<SystemTime>,
+}
+
+impl Job {
+    fn new(id: String, kind: JobKind, owner: Option<String>) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+
+        Self {
+            id,
+            kind,
+            created_at: SystemTime::now(),
+            owner,
+            state: Mutex::new(JobState {
+                events: vec![JobEvent::new(JobStage::Queued, "Queued")],
+                sender: Some(sender),
+                output: None,
+                finished_at: None,
+            }),
+        }
+    }
+
+    /// Record and broadcast a progress event
+    ///
+    /// Events after the job has finished are ignored.
+    pub fn emit(&self, event: JobEvent) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.finished_at.is_some() {
+            return;
+        }
+
+        if let Some(sender) = &state.sender {
+            // No subscribers is not an error; late subscribers replay the history
+            let _ = sender.send(event.clone());
+        }
+        state.events.push(event);
+    }
+
+    /// Store the job's output and emit its final event
+    pub fn complete(&self, output: JobOutput) {
+        self.finish(JobEvent::new(JobStage::Done, "Done"), Some(output));
+    }
+
+    pub fn fail(&self, error: impl Into<String>) {
+        self.finish(JobEvent::new(JobStage::Failed, error), None);
+    }
+
+    fn finish(&self, event: JobEvent, output: Option<JobOutput>) {
+        let stage = event.stage;
+        let error = (stage == JobStage::Failed).then(|| event.message.clone());
+        self.emit(event);
+
+        let finished_at = SystemTime::now();
+        if let Ok(mut state) = self.state.lock() {
+            state.output = output;
+            state.sender = None;
+            state.finished_at = Some(finished_at);
+        }
+
+        let (id, kind, created_at) = (self.id.clone(), self.kind, self.created_at);
+        store::record(move |store| async move {
+            store
+                .job_finished(&id, kind, stage, created_at, finished_at, error.as_deref())
+                .await
+        });
+    }
+
+    /// Events so far, and a receiver for later ones
+    ///
+    /// The receiver closes once the job finishes, straight away if it already has.
+    pub fn subscribe(&self) -> (Vec<JobEvent>, broadcast::Receiver<JobEvent>) {
+        let state = self.state.lock().ok();
+        let events = state
+            .as_ref()
+            .map(|state| state.events.clone())
+            .unwrap_or_default();
+        let receiver = match state.as_ref().and_then(|state| state.sender.as_ref()) {
+            Some(sender) => sender.subscribe(),
+            // Its sender is dropped at once, so the receiver is already closed
+            None => broadcast::channel(1).1,
+        };
+
+        (events, receiver)
+    }
+
+    pub fn events(&self) -> Vec<JobEvent> {
+        self.subscribe().0
+    }
+
+    /// Most recent event
+    pub fn latest(&self) -> Option<JobEvent> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|state| state.events.last().cloned())
+    }
+
+    pub fn output(&self) -> Option<JobOutput> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|state| state.output.clone())
+    }
+
+    /// When the job finished, if it has
+    pub fn finished(&self) -> Option<SystemTime> {
+        self.state.lock().ok().and_then(|state| state.finished_at)
+    }
+}
+
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<String, Arc<Job>>>,
+}
+
+impl JobRegistry {
+    /// Register a new job in the queued stage
+    ///
+    /// A job with an `owner` is only visible to that user.
+    pub fn create(&self, kind: JobKind, owner: Option<String>) -> Arc<Job> {
+        let job = Arc::new(Job::new(uuid::Uuid::new_v4().to_string(), kind, owner));
+
+        if let Ok(mut jobs) = self.jobs.lock() {
+            prune(&mut jobs);
+            jobs.insert(job.id.clone(), job.clone());
+        }
+
+        let (id, created_at) = (job.id.clone(), job.created_at);
+        store::record(move |store| async move { store.job_created(&id, kind, created_at).await });
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Job>> {
+        self.jobs.lock().ok()?.get(id).cloned()
+    }
+
+    /// Jobs created by a user, oldest first
+    pub fn owned_by(&self, owner: &str) -> Vec<Arc<Job>> {
+        let Ok(jobs) = self.jobs.lock() else {
+            return Vec::new();
+        };
+
+        let mut owned: Vec<Arc<Job>> = jobs
+            .values()
+            .filter(|job| job.owner.as_deref() == Some(owner))
+            .cloned()
+            .collect();
+        owned.sort_by_key(|job| job.created_at);
+        owned
+    }
+
+    /// Drop every job created by a user, returning how many there were
+    ///
+    /// Running jobs finish, but their output can no longer be fetched.
+    pub fn remove_owned_by(&self, owner: &str) -> usize {
+        let Ok(mut jobs) = self.jobs.lock() else {
+            return 0;
+        };
+
+        let before = jobs.len();
+        jobs.retain(|_, job| job.owner.as_deref() != Some(owner));
+        before - jobs.len()
+    }
+}
+
+/// Drop expired jobs, then the oldest finished ones while over the limit
+fn prune(jobs: &mut HashMap<String, Arc<Job>>) {
+    jobs.retain(|_, job| {
+        job.finished()
+            .is_none_or(|finished| finished.elapsed().unwrap_or_default() < JOB_RETENTION)
+    });
+
+    if jobs.len() >= MAX_JOBS {
+        let mut finished: Vec<(SystemTime, String)> = jobs
+            .values()
+            .filter_map(|job| Some((job.finished()?, job.id.clone())))
+            .collect();
+        finished.sort();
+
+        let excess = jobs.len() + 1 - MAX_JOBS;
+        for (_, id) in finished.into_iter().take(excess) {
+            jobs.remove(&id);
+        }
+    }
+}
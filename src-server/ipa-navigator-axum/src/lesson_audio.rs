@@ -0,0 +1,315 @@
+//! Stored reference audio for lesson sentences, read by a mix of voices
+//!
+//! Each clip is kept on disk under an id derived from what was read and how,
+//! so generating a lesson again, or another lesson sharing a sentence, reuses
+//! the clips already made instead of synthesizing them again. Clips are
+//! written to `LESSON_AUDIO_DIR` and served by `/api/lessons/audio/{id}`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+use ipa_navigator_kokoro::voices::VoiceType;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+/// Voices reading each sentence when `LESSON_VOICE_MIX` is not set: both
+/// accents, each read by a woman and a man
+const DEFAULT_VOICE_MIX: [&str; 4] = [
+    "american_female_bella",
+    "british_male_george",
+    "british_female_emma",
+    "american_male_michael",
+];
+
+/// Hex digits of the hash kept in a clip id
+const CLIP_ID_LEN: usize = 32;
+
+/// Shared store for the configured directory
+pub static LESSON_AUDIO: LazyLock<LessonAudioStore> =
+    LazyLock::new(|| LessonAudioStore::new(LessonAudioConfig::from_env()));
+
+/// Where lesson clips are kept and which voices read them by default
+#[derive(Debug, Clone)]
+pub struct LessonAudioConfig {
+    pub dir: PathBuf,
+    pub voices: Vec<VoiceType>,
+}
+
+impl LessonAudioConfig {
+    /// Read `LESSON_AUDIO_DIR` and `LESSON_VOICE_MIX`, a comma-separated list of voice names
+    pub fn from_env() -> Self {
+        let dir = env::var_os("LESSON_AUDIO_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| env::temp_dir().join("ipa-navigator-lesson-audio"));
+        let voices = env::var("LESSON_VOICE_MIX")
+            .ok()
+            .map(|mix| parse_voice_mix(&mix))
+            .filter(|voices| !voices.is_empty())
+            .unwrap_or_else(default_voice_mix);
+        Self { dir, voices }
+    }
+}
+
+/// Voices named in a mix, skipping unknown names and repeats
+pub fn parse_voice_mix(mix: &str) -> Vec<VoiceType> {
+    let mut voices = Vec::new();
+    for name in mix
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        match VoiceType::from_name(name) {
+            Some(voice) if !voices.contains(&voice) => voices.push(voice),
+            Some(_) => {}
+            None => warn!("Ignoring unknown voice {} in LESSON_VOICE_MIX", name),
+        }
+    }
+    voices
+}
+
+fn default_voice_mix() -> Vec<VoiceType> {
+    DEFAULT_VOICE_MIX
+        .iter()
+        .filter_map(|name| VoiceType::from_name(name))
+        .collect()
+}
+
+/// What a clip reads and how, from which its id is derived
+#[derive(Debug, Clone, Copy)]
+pub struct ClipSpec<'a> {
+    pub text: &'a str,
+    pub voice: VoiceType,
+    pub speed: f32,
+    /// Normalization settings that change what is read, e.g. skipped expansions
+    pub variant: &'a str,
+    /// Tenant whose lexicon respells words, if any
+    pub tenant: Option<&'a str>,
+}
+
+impl ClipSpec<'_> {
+    /// Id of the clip, the same whenever the same text is read the same way
+    ///
+    /// Whitespace in the text is collapsed, so reformatting a lesson keeps its ids.
+    pub fn id(&self) -> String {
+        let text = self.text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        let mut hasher = Sha256::new();
+        for part in [
+            text.as_str(),
+            self.voice.name(),
+            &self.speed.to_bits().to_string(),
+            self.variant,
+            self.tenant.unwrap_or_default(),
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        let digest = hasher.finalize();
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        hex[..CLIP_ID_LEN].to_string()
+    }
+}
+
+/// A clip found on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredClip {
+    pub id: String,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    pub bytes: u64,
+}
+
+/// Lesson clips on disk, one WAV file per id
+pub struct LessonAudioStore {
+    config: LessonAudioConfig,
+}
+
+impl LessonAudioStore {
+    pub fn new(config: LessonAudioConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &LessonAudioConfig {
+        &self.config
+    }
+
+    /// WAV audio of a stored clip and when it was written, or `None` if there is none
+    pub fn get(&self, id: &str) -> io::Result<Option<(Vec<u8>, SystemTime)>> {
+        let Some(path) = self.path(id) else {
+            return Ok(None);
+        };
+        match fs::read(&path) {
+            Ok(wav_data) => {
+                let modified = fs::metadata(&path)?.modified()?;
+                Ok(Some((wav_data, modified)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Store a clip, replacing any with the same id
+    ///
+    /// Written to a temporary file first, so a clip being served is never half-written.
+    pub fn put(&self, id: &str, wav_data: &[u8]) -> io::Result<()> {
+        let path = self.path(id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid clip id {}", id),
+            )
+        })?;
+        fs::create_dir_all(&self.config.dir)?;
+
+        let partial = path.with_extension(format!("wav.{}.partial", std::process::id()));
+        fs::write(&partial, wav_data)?;
+        fs::rename(&partial, &path).inspect_err(|_| {
+            let _ = fs::remove_file(&partial);
+        })
+    }
+
+    /// Every clip in the store, none if the directory does not exist yet
+    ///
+    /// Files the store did not make, such as partial writes, are left out.
+    pub fn clips(&self) -> io::Result<Vec<StoredClip>> {
+        let entries = match fs::read_dir(&self.config.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut clips = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let Some(id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".wav"))
+            else {
+                continue;
+            };
+            if self.path(id).as_ref() != Some(&path) {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            clips.push(StoredClip {
+                id: id.to_string(),
+                modified: metadata.modified()?,
+                bytes: metadata.len(),
+                path,
+            });
+        }
+        Ok(clips)
+    }
+
+    /// Path of a clip, or `None` if the id is not one this store makes
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        let valid = id.len() == CLIP_ID_LEN && id.bytes().all(|b| b.is_ascii_hexdigit());
+        valid.then(|| self.config.dir.join(format!("{}.wav", id)))
+    }
+}
+
+/// Length in seconds of WAV audio, from its format and data chunks
+pub fn wav_duration_secs(wav_data: &[u8]) -> Option<f64> {
+    let u32_at = |at: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            wav_data.get(at..at + 4)?.try_into().ok()?,
+        ))
+    };
+    if wav_data.get(0..4)? != b"RIFF" || wav_data.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut byte_rate = None;
+    let mut at = 12;
+    while let Some(id) = wav_data.get(at..at + 4) {
+        let size = u32_at(at + 4)? as usize;
+        match id {
+            b"fmt " => byte_rate = u32_at(at + 16),
+            b"data" => return Some(size as f64 / byte_rate.filter(|&rate| rate > 0)? as f64),
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        at += 8 + size + size % 2;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipa_navigator_kokoro::{
+        prelude::SAMPLE_RATE,
+        wav::{WavFormat, encode_wav},
+    };
+
+    fn spec(text: &str) -> ClipSpec<'_> {
+        ClipSpec {
+            text,
+            voice: VoiceType::from_name("american_female_bella").unwrap(),
+            speed: 1.0,
+            variant: "",
+            tenant: None,
+        }
+    }
+
+    #[test]
+    fn test_clip_ids_are_stable() {
+        let id = spec("The cat sat.").id();
+        assert_eq!(id.len(), CLIP_ID_LEN);
+        assert_eq!(spec(" The  cat\nsat. ").id(), id);
+
+        assert_ne!(spec("The cat sat!").id(), id);
+        let british = ClipSpec {
+            voice: VoiceType::from_name("british_male_george").unwrap(),
+            ..spec("The cat sat.")
+        };
+        assert_ne!(british.id(), id);
+        let tenant = ClipSpec {
+            tenant: Some("school"),
+            ..spec("The cat sat.")
+        };
+        assert_ne!(tenant.id(), id);
+    }
+
+    #[test]
+    fn test_voice_mix() {
+        let voices =
+            parse_voice_mix("british_male_lewis, nobody,british_male_lewis,american_male_puck");
+        let names: Vec<&str> = voices.iter().map(VoiceType::name).collect();
+        assert_eq!(names, vec!["british_male_lewis", "american_male_puck"]);
+        assert_eq!(default_voice_mix().len(), DEFAULT_VOICE_MIX.len());
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = env::temp_dir().join(format!("lesson-audio-test-{}", std::process::id()));
+        let store = LessonAudioStore::new(LessonAudioConfig {
+            dir: dir.clone(),
+            voices: default_voice_mix(),
+        });
+
+        let wav_data =
+            encode_wav(&vec![0.0; SAMPLE_RATE as usize / 2], &WavFormat::default()).unwrap();
+        let id = spec("Hello.").id();
+        assert!(store.get(&id).unwrap().is_none());
+        store.put(&id, &wav_data).unwrap();
+
+        let (stored, _) = store.get(&id).unwrap().unwrap();
+        assert_eq!(stored, wav_data);
+        let clips = store.clips().unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].id, id);
+        assert_eq!(clips[0].bytes, wav_data.len() as u64);
+        assert!((wav_duration_secs(&stored).unwrap() - 0.5).abs() < 1e-6);
+
+        // Ids are never used as paths unless the store could have made them
+        assert!(store.get("../../etc/passwd").unwrap().is_none());
+        assert!(store.put("../escape", &wav_data).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
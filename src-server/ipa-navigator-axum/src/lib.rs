@@ -1,8 +1,33 @@
+pub mod audit;
+pub mod auth;
+pub mod bundle;
+pub mod cache;
+pub mod classroom;
 pub mod config;
+pub mod convex;
+pub mod diagnostics;
+pub mod engines;
 pub mod error;
+pub mod export;
 pub mod handlers;
+pub mod idempotency;
+pub mod identity;
+pub mod jobs;
+pub mod lesson_audio;
+pub mod limits;
+pub mod media;
+pub mod practice;
+pub mod privacy;
+pub mod report;
+pub mod request_log;
+pub mod retention;
 pub mod routes;
+pub mod scheduler;
+pub mod selftest;
+pub mod store;
+pub mod tenants;
 
 pub use config::Config;
+pub use engines::AppState;
 pub use error::Error;
-pub use routes::create_router;
+pub use routes::{create_router, create_router_with};
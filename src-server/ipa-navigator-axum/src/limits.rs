@@ -0,0 +1,234 @@
+//! Per-route concurrency limits with queue-time metrics
+//!
+//! Synthesis and assessment each get their own limit on requests handled at
+//! once, so a burst of alignment requests waits its turn instead of tying up
+//! the runtime and starving lightweight endpoints like `/api/ipa`. Requests
+//! over a limit wait for a slot rather than being refused, and how long they
+//! waited is recorded for `GET /api/admin/queues`. Limits can be changed
+//! while serving, see [`crate::config::ConfigManager`].
+
+use std::env;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use axum::{extract::Request, middleware::Next, response::Response};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::limit::GlobalConcurrencyLimitLayer;
+
+/// Synthesis requests handled at once when `TTS_CONCURRENCY_LIMIT` is not set
+const DEFAULT_TTS_LIMIT: usize = 16;
+
+/// Assessment requests handled at once when `ASSESS_CONCURRENCY_LIMIT` is not set
+const DEFAULT_ASSESS_LIMIT: usize = 4;
+
+/// Waits longer than this are logged, since they mean the limit is too low for the load
+const SLOW_QUEUE_WAIT: Duration = Duration::from_secs(5);
+
+pub struct LimitConfig {
+    /// Synthesis requests handled at once
+    pub tts: usize,
+    /// Assessment requests handled at once
+    pub assess: usize,
+}
+
+impl LimitConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: usize| {
+            env::var(name)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&limit| limit > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            tts: var("TTS_CONCURRENCY_LIMIT", DEFAULT_TTS_LIMIT),
+            assess: var("ASSESS_CONCURRENCY_LIMIT", DEFAULT_ASSESS_LIMIT),
+        }
+    }
+}
+
+/// Routes sharing one concurrency limit
+pub struct RouteGroup {
+    pub name: &'static str,
+    limit: AtomicUsize,
+    semaphore: Arc<Semaphore>,
+    waiting: AtomicUsize,
+    admitted: AtomicU64,
+    total_wait_us: AtomicU64,
+    max_wait_us: AtomicU64,
+}
+
+/// A group's load and how long its requests have waited
+#[derive(Debug, Clone, Copy)]
+pub struct QueueSnapshot {
+    pub limit: usize,
+    /// Requests being handled now
+    pub in_flight: usize,
+    /// Requests waiting for a slot now
+    pub waiting: usize,
+    /// Requests that have been given a slot since startup
+    pub admitted: u64,
+    pub average_wait: Duration,
+    pub max_wait: Duration,
+}
+
+impl RouteGroup {
+    fn new(name: &'static str, limit: usize) -> Self {
+        Self {
+            name,
+            limit: AtomicUsize::new(limit),
+            semaphore: Arc::new(Semaphore::new(limit)),
+            waiting: AtomicUsize::new(0),
+            admitted: AtomicU64::new(0),
+            total_wait_us: AtomicU64::new(0),
+            max_wait_us: AtomicU64::new(0),
+        }
+    }
+
+    /// Layer limiting every route it is applied to together
+    ///
+    /// `Router::layer` wraps each route separately, so the limit shares one
+    /// semaphore rather than giving each route its own.
+    pub fn limit_layer(&self) -> GlobalConcurrencyLimitLayer {
+        GlobalConcurrencyLimitLayer::with_semaphore(self.semaphore.clone())
+    }
+
+    /// Requests handled at once
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Change how many requests are handled at once
+    ///
+    /// Raising the limit admits waiting requests straight away. Lowering it
+    /// takes slots out of use as requests holding them finish, so requests
+    /// already being handled are never interrupted.
+    pub fn set_limit(&self, limit: usize) {
+        let previous = self.limit.swap(limit, Ordering::Relaxed);
+        if limit > previous {
+            self.semaphore.add_permits(limit - previous);
+        } else if limit < previous {
+            let surplus = (previous - limit) as u32;
+            let semaphore = self.semaphore.clone();
+            tokio::spawn(async move {
+                if let Ok(permits) = semaphore.acquire_many_owned(surplus).await {
+                    permits.forget();
+                }
+            });
+        }
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let admitted = self.admitted.load(Ordering::Relaxed);
+        let total_wait_us = self.total_wait_us.load(Ordering::Relaxed);
+        let limit = self.limit();
+
+        QueueSnapshot {
+            limit,
+            // Until a lowered limit takes its surplus slots, more can be available than the limit
+            in_flight: limit.saturating_sub(self.semaphore.available_permits()),
+            waiting: self.waiting.load(Ordering::Relaxed),
+            admitted,
+            average_wait: Duration::from_micros(total_wait_us.checked_div(admitted).unwrap_or(0)),
+            max_wait: Duration::from_micros(self.max_wait_us.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Wait for a slot outside of a request, for work that outlives the request queueing it
+    pub async fn acquire(&'static self) -> OwnedSemaphorePermit {
+        let queued = self.enqueued();
+        let _guard = WaitGuard {
+            group: self,
+            queued: queued.clone(),
+        };
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("route group semaphores are never closed");
+        self.admitted(&queued);
+        permit
+    }
+
+    fn enqueued(&self) -> Queued {
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        Queued {
+            at: Instant::now(),
+            admitted: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn admitted(&self, queued: &Queued) {
+        if !queued.admitted.swap(true, Ordering::Relaxed) {
+            self.record_wait(queued.at.elapsed());
+        }
+    }
+
+    fn record_wait(&self, wait: Duration) {
+        let wait_us = wait.as_micros() as u64;
+        self.waiting.fetch_sub(1, Ordering::Relaxed);
+        self.admitted.fetch_add(1, Ordering::Relaxed);
+        self.total_wait_us.fetch_add(wait_us, Ordering::Relaxed);
+        self.max_wait_us.fetch_max(wait_us, Ordering::Relaxed);
+
+        if wait >= SLOW_QUEUE_WAIT {
+            tracing::warn!("Request waited {:?} for a {} slot", wait, self.name);
+        }
+    }
+}
+
+static CONFIG: LazyLock<LimitConfig> = LazyLock::new(LimitConfig::from_env);
+
+/// Text-to-speech routes
+pub static TTS_ROUTES: LazyLock<RouteGroup> = LazyLock::new(|| RouteGroup::new("tts", CONFIG.tts));
+
+/// Pronunciation assessment routes
+pub static ASSESS_ROUTES: LazyLock<RouteGroup> =
+    LazyLock::new(|| RouteGroup::new("assess", CONFIG.assess));
+
+/// Every limited group, for reporting
+pub fn route_groups() -> [&'static RouteGroup; 2] {
+    [&TTS_ROUTES, &ASSESS_ROUTES]
+}
+
+/// When a request started waiting for a slot, and whether it has been given one
+#[derive(Clone)]
+struct Queued {
+    at: Instant,
+    admitted: Arc<AtomicBool>,
+}
+
+/// Middleware outside the limit, noting when a request starts waiting
+pub async fn enqueue(group: &'static RouteGroup, mut request: Request, next: Next) -> Response {
+    let queued = group.enqueued();
+    request.extensions_mut().insert(queued.clone());
+
+    // A request dropped while waiting never reaches `admit`, so count it out here
+    let _guard = WaitGuard { group, queued };
+    next.run(request).await
+}
+
+/// Middleware inside the limit, recording how long the request waited
+pub async fn admit(group: &'static RouteGroup, request: Request, next: Next) -> Response {
+    if let Some(queued) = request.extensions().get::<Queued>() {
+        group.admitted(queued);
+    }
+    next.run(request).await
+}
+
+/// Counts a request out of the waiting total if it ends before being admitted
+struct WaitGuard {
+    group: &'static RouteGroup,
+    queued: Queued,
+}
+
+impl Drop for WaitGuard {
+    fn drop(&mut self) {
+        if !self.queued.admitted.swap(true, Ordering::Relaxed) {
+            self.group.waiting.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
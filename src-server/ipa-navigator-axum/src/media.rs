@@ -0,0 +1,256 @@
+//! Serving audio clips with caching headers and byte-range support
+//!
+//! Browsers request ranges to seek within longer recordings, and CDNs rely on
+//! `Cache-Control` and `Last-Modified` to cache and revalidate clips. Clips
+//! sent whole without ranges can instead be encoded as they are sent, see
+//! [`WavStream`].
+
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::{LazyLock, Mutex};
+use std::task::{Context, Poll, ready};
+use std::time::SystemTime;
+
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use ipa_navigator_kokoro::{
+    error::TtsError,
+    prelude::SAMPLE_RATE,
+    wav::{WavFormat, WavStreamEncoder, data_len},
+};
+use tokio_stream::{Stream, StreamExt};
+
+/// Samples encoded at a time when streaming a clip, a second at the model's rate
+pub(crate) const STREAM_CHUNK_SAMPLES: usize = SAMPLE_RATE as usize;
+
+/// Cache policy for synthesized clips, which only change when voices are reloaded
+pub const SYNTHESIZED_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Cache policy for clips cut short to meet a deadline, which a retry may complete
+pub const TRUNCATED_CACHE_CONTROL: &str = "no-store";
+
+/// Cache policy for job results, which belong to one client and expire with the job
+pub const JOB_CACHE_CONTROL: &str = "private, max-age=3600";
+
+// Synthesized clips are deterministic, so they last changed when the voices were loaded
+static SYNTHESIS_MODIFIED: LazyLock<Mutex<SystemTime>> =
+    LazyLock::new(|| Mutex::new(SystemTime::now()));
+
+/// When the voices behind synthesized clips were last loaded
+pub fn synthesis_modified() -> SystemTime {
+    SYNTHESIS_MODIFIED
+        .lock()
+        .map_or_else(|_| SystemTime::now(), |modified| *modified)
+}
+
+/// Record that voices were reloaded, so cached clips are revalidated
+pub fn mark_synthesis_modified() {
+    if let Ok(mut modified) = SYNTHESIS_MODIFIED.lock() {
+        *modified = SystemTime::now();
+    }
+}
+
+/// An audio clip to send, possibly in part
+pub struct AudioClip {
+    pub data: Vec<u8>,
+    pub content_type: &'static str,
+    pub last_modified: SystemTime,
+    pub cache_control: &'static str,
+}
+
+impl AudioClip {
+    pub fn wav(data: Vec<u8>, last_modified: SystemTime, cache_control: &'static str) -> Self {
+        Self {
+            data,
+            content_type: "audio/wav",
+            last_modified,
+            cache_control,
+        }
+    }
+
+    /// Respond to a request, honouring `If-Modified-Since`, `Range`, and `If-Range`
+    pub fn respond(self, request: &HeaderMap) -> Response {
+        let last_modified = httpdate::fmt_http_date(self.last_modified);
+        let len = self.data.len();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(self.cache_control),
+        );
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(self.content_type),
+        );
+        if let Ok(value) = HeaderValue::from_str(&last_modified) {
+            headers.insert(header::LAST_MODIFIED, value);
+        }
+
+        if not_modified_since(request, self.last_modified) {
+            return (StatusCode::NOT_MODIFIED, headers).into_response();
+        }
+
+        // A range is only honoured if the client's copy is still current
+        let range = request
+            .get(header::RANGE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|_| {
+                request
+                    .get(header::IF_RANGE)
+                    .is_none_or(|value| value.as_bytes() == last_modified.as_bytes())
+            })
+            .and_then(|range| parse_range(range, len));
+
+        match range {
+            None => (StatusCode::OK, headers, self.data).into_response(),
+            Some(Some((start, end))) => {
+                if let Ok(value) =
+                    HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len))
+                {
+                    headers.insert(header::CONTENT_RANGE, value);
+                }
+                let data = self.data[start..=end].to_vec();
+                (StatusCode::PARTIAL_CONTENT, headers, data).into_response()
+            }
+            Some(None) => {
+                if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                    headers.insert(header::CONTENT_RANGE, value);
+                }
+                (StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response()
+            }
+        }
+    }
+}
+
+/// WAV audio encoded piece by piece as its samples arrive, so neither the
+/// whole file nor the whole clip need be held at once
+///
+/// The header is sent first, sized for the clip if its length is known up
+/// front, and otherwise left at its maximum for players to read until the
+/// stream ends.
+pub struct WavStream<S> {
+    samples: S,
+    encoder: WavStreamEncoder,
+    header: Option<Bytes>,
+    finished: bool,
+}
+
+impl<S> WavStream<S>
+where
+    S: Stream<Item = Vec<f32>> + Unpin,
+{
+    /// Encode the samples `samples` yields, `input_samples` in all if known
+    pub fn new(
+        samples: S,
+        format: WavFormat,
+        input_samples: Option<usize>,
+    ) -> Result<Self, TtsError> {
+        let encoder = WavStreamEncoder::new(format);
+        let header = encoder.header(input_samples)?;
+        Ok(Self {
+            samples,
+            encoder,
+            header: Some(header.into()),
+            finished: false,
+        })
+    }
+}
+
+impl<S> Stream for WavStream<S>
+where
+    S: Stream<Item = Vec<f32>> + Unpin,
+{
+    type Item = Bytes;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        let this = &mut *self;
+        if let Some(header) = this.header.take() {
+            return Poll::Ready(Some(header));
+        }
+
+        while !this.finished {
+            let bytes = match ready!(Pin::new(&mut this.samples).poll_next(cx)) {
+                Some(samples) => this.encoder.encode(&samples),
+                None => {
+                    this.finished = true;
+                    this.encoder.finish()
+                }
+            };
+            // Resampling may hold back all of a short slice
+            if !bytes.is_empty() {
+                return Poll::Ready(Some(bytes.into()));
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+/// A response body encoding `samples` as WAV while it is sent, and its length in bytes
+pub fn wav_body(samples: Vec<f32>, format: WavFormat) -> Result<(Body, usize), TtsError> {
+    let input_samples = samples.len();
+    let mut at = 0;
+    let slices = std::iter::from_fn(move || {
+        let slice = samples.get(at..)?;
+        if slice.is_empty() {
+            return None;
+        }
+        let slice = slice[..slice.len().min(STREAM_CHUNK_SAMPLES)].to_vec();
+        at += slice.len();
+        Some(slice)
+    });
+
+    let stream = WavStream::new(tokio_stream::iter(slices), format, Some(input_samples))?;
+    let len = stream.header.as_ref().map_or(0, Bytes::len) + data_len(&format, input_samples);
+    Ok((Body::from_stream(stream.map(Ok::<_, Infallible>)), len))
+}
+
+/// Whether `If-Modified-Since` shows the client already has this version
+fn not_modified_since(request: &HeaderMap, last_modified: SystemTime) -> bool {
+    let Some(since) = request
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+    else {
+        return false;
+    };
+
+    // HTTP dates have whole-second precision
+    let truncated =
+        httpdate::parse_http_date(&httpdate::fmt_http_date(last_modified)).unwrap_or(last_modified);
+    truncated <= since
+}
+
+/// Parse a single `bytes=` range into inclusive offsets
+///
+/// Returns `None` for headers to ignore, such as other units, malformed
+/// values, and multiple ranges, and `Some(None)` for ranges outside the clip.
+fn parse_range(range: &str, len: usize) -> Option<Option<(usize, usize)>> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // A suffix range: the last `end` bytes
+        let suffix: usize = end.parse().ok()?;
+        (suffix > 0 && len > 0).then(|| (len.saturating_sub(suffix), len - 1))
+    } else {
+        let start: usize = start.parse().ok()?;
+        let end = match end {
+            "" => None,
+            end => Some(end.parse::<usize>().ok()?),
+        };
+        if end.is_some_and(|end| end < start) {
+            return None;
+        }
+        (start < len).then(|| (start, end.map_or(len - 1, |end| end.min(len - 1))))
+    };
+
+    Some(range)
+}
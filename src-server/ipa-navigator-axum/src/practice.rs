@@ -0,0 +1,150 @@
+//! Practice attempts and badges stored in Convex
+//!
+//! Streaks, leaderboards, badges, and personal baselines are computed on the
+//! server from the raw attempts, with the math in `ipa_navigator_core`.
+
+use ipa_navigator_core::baseline::ScoredPhoneme;
+use ipa_navigator_core::gamification::{Attempt, Badge};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::convex::{ConvexError, UserConvex};
+use crate::error::Error;
+use crate::identity::AuthUser;
+
+/// One scored attempt at an excerpt
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PracticeAttempt {
+    pub overall_accuracy: f64,
+    /// Milliseconds since the Unix epoch
+    pub created_at: f64,
+}
+
+impl From<PracticeAttempt> for Attempt {
+    fn from(attempt: PracticeAttempt) -> Self {
+        Attempt {
+            timestamp_ms: attempt.created_at as i64,
+            accuracy: attempt.overall_accuracy,
+        }
+    }
+}
+
+/// Earliest attempts a personal baseline is calibrated from
+pub const CALIBRATION_ATTEMPTS: usize = 10;
+
+/// One phoneme of a stored attempt
+#[derive(Debug, Clone, Deserialize)]
+pub struct PracticePhoneme {
+    pub target_phoneme: String,
+    pub detected_phoneme: String,
+    pub accuracy: f64,
+}
+
+impl From<PracticePhoneme> for ScoredPhoneme {
+    fn from(phoneme: PracticePhoneme) -> Self {
+        ScoredPhoneme {
+            expected: phoneme.target_phoneme,
+            actual: phoneme.detected_phoneme,
+            score: phoneme.accuracy,
+        }
+    }
+}
+
+/// An attempt with the phonemes it was scored on
+#[derive(Debug, Clone, Deserialize)]
+pub struct CalibrationAttempt {
+    /// Milliseconds since the Unix epoch
+    pub created_at: f64,
+    pub phonemes: Vec<PracticePhoneme>,
+}
+
+/// An attempt with its overall accuracy and the phonemes it was scored on
+#[derive(Debug, Clone, Deserialize)]
+pub struct PhonemeHistoryAttempt {
+    /// Milliseconds since the Unix epoch
+    pub created_at: f64,
+    pub overall_accuracy: f64,
+    pub phonemes: Vec<PracticePhoneme>,
+}
+
+/// Everything a user has practiced, and the badges already stored for them
+#[derive(Debug, Clone, Deserialize)]
+pub struct PracticeHistory {
+    pub name: String,
+    pub attempts: Vec<PracticeAttempt>,
+    pub badge_ids: Vec<String>,
+}
+
+/// A user's attempts over a period
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserPractice {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    pub name: String,
+    pub attempts: Vec<PracticeAttempt>,
+}
+
+/// Practice calls made as one signed-in user
+pub struct Practice<'a> {
+    convex: UserConvex<'a>,
+}
+
+impl<'a> Practice<'a> {
+    pub fn for_user(user: &'a AuthUser) -> Result<Self, Error> {
+        UserConvex::for_user(user).map(|convex| Self { convex })
+    }
+
+    /// A user's history, or none if there is no such user
+    pub async fn history(&self, user_id: &str) -> Result<Option<PracticeHistory>, ConvexError> {
+        self.convex
+            .query(
+                "functions/gamification:getPracticeHistory",
+                json!({ "userId": user_id }),
+            )
+            .await
+    }
+
+    /// Every user's attempts since `since`, in milliseconds since the Unix epoch
+    pub async fn since(&self, since: i64) -> Result<Vec<UserPractice>, ConvexError> {
+        self.convex
+            .query(
+                "functions/gamification:getPracticeSince",
+                json!({ "since": since }),
+            )
+            .await
+    }
+
+    /// The signed-in user's first [`CALIBRATION_ATTEMPTS`] attempts, oldest first
+    pub async fn calibration_attempts(&self) -> Result<Vec<CalibrationAttempt>, ConvexError> {
+        self.convex
+            .query(
+                "functions/gamification:getCalibrationAttempts",
+                json!({ "limit": CALIBRATION_ATTEMPTS }),
+            )
+            .await
+    }
+
+    /// The signed-in user's attempts since `since`, in milliseconds since the Unix epoch
+    pub async fn phoneme_history(
+        &self,
+        since: i64,
+    ) -> Result<Vec<PhonemeHistoryAttempt>, ConvexError> {
+        self.convex
+            .query(
+                "functions/gamification:getPhonemeHistory",
+                json!({ "since": since }),
+            )
+            .await
+    }
+
+    /// Store a badge for the signed-in user, notifying them
+    pub async fn award_badge(&self, badge: Badge) -> Result<(), ConvexError> {
+        self.convex
+            .mutation::<serde_json::Value>(
+                "functions/gamification:earnBadge",
+                json!({ "badgeId": badge.id(), "badgeName": badge.name() }),
+            )
+            .await
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,75 @@
+//! Exporting and deleting everything stored about a user, in Convex
+//!
+//! Convex holds the user's practice, classroom, and social records and any
+//! kept recordings of their submissions. Every call runs as the signed-in
+//! user, so only their own data can be exported or deleted.
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::convex::{ConvexError, UserConvex};
+use crate::error::Error;
+use crate::identity::AuthUser;
+
+/// Progress of one call deleting a user's data
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct DeletionProgress {
+    /// Whether everything, including the user document, is gone
+    pub done: bool,
+    /// Documents deleted, or revoked if shared with others, by the call
+    pub deleted: u64,
+}
+
+/// A kept recording listed in an export
+#[derive(Debug, Clone)]
+pub struct ExportedRecording {
+    pub submission_id: String,
+    pub url: String,
+}
+
+/// Privacy calls made as one signed-in user
+pub struct Privacy<'a> {
+    convex: UserConvex<'a>,
+}
+
+impl<'a> Privacy<'a> {
+    pub fn for_user(user: &'a AuthUser) -> Result<Self, Error> {
+        UserConvex::for_user(user).map(|convex| Self { convex })
+    }
+
+    /// Every record stored about the user, as Convex returns it
+    pub async fn export(&self) -> Result<Value, ConvexError> {
+        self.convex
+            .query("functions/privacy:exportMyData", json!({}))
+            .await
+    }
+
+    /// Delete the next batch of the user's data
+    ///
+    /// Convex limits how much one mutation may delete, so call this until it is done.
+    pub async fn delete_batch(&self) -> Result<DeletionProgress, ConvexError> {
+        self.convex
+            .mutation("functions/privacy:deleteMyData", json!({}))
+            .await
+    }
+
+    /// Download a kept recording
+    pub async fn recording(&self, recording: &ExportedRecording) -> Result<Vec<u8>, ConvexError> {
+        self.convex.fetch_file(&recording.url).await
+    }
+}
+
+/// Recordings still kept for the submissions in an export
+pub fn exported_recordings(export: &Value) -> Vec<ExportedRecording> {
+    export["submissions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|submission| {
+            Some(ExportedRecording {
+                submission_id: submission["_id"].as_str()?.to_string(),
+                url: submission["audio_url"].as_str()?.to_string(),
+            })
+        })
+        .collect()
+}
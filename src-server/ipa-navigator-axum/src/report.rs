@@ -0,0 +1,569 @@
+//! Shareable HTML and PDF reports of an assessment, for teachers to attach to student records
+//!
+//! A report shows the overall score, a breakdown of each word of the
+//! transcript, the feedback tips and a thumbnail of the recording's waveform.
+//! PDF reports embed the font at `REPORT_FONT`, which must cover the IPA
+//! symbols; without one only HTML reports are available.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use ipa_navigator_kokoro::wav::WaveformPeaks;
+use ipa_navigator_mfa::{
+    audio::{read_wav_mono, write_wav_mono},
+    docker::MfaDialect,
+};
+use printpdf::{
+    Color, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point,
+    Rgb,
+};
+use tracing::warn;
+
+use crate::handlers::mfa::PronunciationResponse;
+
+/// Windows of the recording drawn in the waveform thumbnail
+pub const WAVEFORM_POINTS: usize = 240;
+
+/// Words scoring at least this are shown as good, matching the feedback threshold
+const GOOD_SCORE: f64 = ipa_navigator_mfa::feedback::FEEDBACK_SCORE_THRESHOLD;
+
+/// Font embedded in PDF reports, read once from `REPORT_FONT`
+pub static REPORT_FONT: LazyLock<Option<Vec<u8>>> = LazyLock::new(|| {
+    let path = env::var("REPORT_FONT").ok()?;
+    fs::read(&path)
+        .inspect_err(|e| warn!("Failed to read report font {}: {}", path, e))
+        .ok()
+});
+
+/// An assessment with what its report shows besides the response
+#[derive(Debug, Clone)]
+pub struct AssessedRecording {
+    pub response: PronunciationResponse,
+    pub transcript: String,
+    pub dialect: MfaDialect,
+    pub assessed_at: SystemTime,
+    /// Minimum and maximum of each window of the recording, empty if it could not be read
+    pub waveform: Vec<(f32, f32)>,
+    /// The recording as uploaded, so learners can replay parts of it
+    pub audio: Vec<u8>,
+}
+
+/// How one word of the transcript was pronounced
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordBreakdown {
+    pub word: String,
+    /// Dictionary phonemes of the word, joined
+    pub expected: String,
+    /// Phonemes heard in their place, joined
+    pub heard: String,
+    /// Mean score of the word's phonemes; `None` for words that were not scored
+    pub score: Option<f64>,
+}
+
+impl AssessedRecording {
+    pub fn new(
+        response: PronunciationResponse,
+        transcript: String,
+        dialect: MfaDialect,
+        audio: Vec<u8>,
+    ) -> Self {
+        let waveform = read_wav_mono(&audio)
+            .map(|(samples, _)| WaveformPeaks::from_samples(&samples, WAVEFORM_POINTS).peaks)
+            .unwrap_or_default();
+
+        Self {
+            response,
+            transcript,
+            dialect,
+            assessed_at: SystemTime::now(),
+            waveform,
+            audio,
+        }
+    }
+
+    /// The recording from `start` to `end` seconds as a mono WAV file
+    ///
+    /// The range is cut short at the end of the recording; none if it starts
+    /// at or after the end.
+    pub fn segment(&self, start: f64, end: f64) -> Result<Option<Vec<u8>>> {
+        let (samples, sample_rate) = read_wav_mono(&self.audio)?;
+        let sample_at = |seconds: f64| {
+            ((seconds.max(0.0) * sample_rate as f64).round() as usize).min(samples.len())
+        };
+        let (first, last) = (sample_at(start), sample_at(end));
+        if first >= last {
+            return Ok(None);
+        }
+
+        write_wav_mono(&samples[first..last], sample_rate).map(Some)
+    }
+
+    /// The transcript's words with the phonemes whose spelling falls within each
+    pub fn words(&self) -> Vec<WordBreakdown> {
+        let mut words = Vec::new();
+        let mut start = None;
+        for (i, c) in self.transcript.chars().chain([' ']).enumerate() {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(i),
+                (true, Some(word_start)) => {
+                    words.push((word_start, i));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+
+        words
+            .into_iter()
+            .map(|(word_start, word_end)| {
+                let phonemes: Vec<_> = self
+                    .response
+                    .phoneme_details
+                    .iter()
+                    .filter(|detail| {
+                        detail
+                            .char_start
+                            .is_some_and(|start| (word_start..word_end).contains(&start))
+                    })
+                    .collect();
+
+                let score = (!phonemes.is_empty()).then(|| {
+                    phonemes.iter().map(|detail| detail.score).sum::<f64>() / phonemes.len() as f64
+                });
+
+                WordBreakdown {
+                    word: self
+                        .transcript
+                        .chars()
+                        .skip(word_start)
+                        .take(word_end - word_start)
+                        .collect(),
+                    expected: phonemes.iter().map(|d| d.expected.as_str()).collect(),
+                    heard: phonemes.iter().map(|d| d.actual.as_str()).collect(),
+                    score,
+                }
+            })
+            .collect()
+    }
+}
+
+fn dialect_name(dialect: MfaDialect) -> &'static str {
+    match dialect {
+        MfaDialect::AmericanEnglish => "American English",
+        MfaDialect::BritishEnglish => "British English",
+    }
+}
+
+fn percent(score: f64) -> String {
+    format!("{:.0}%", score * 100.0)
+}
+
+/// Escape text for HTML element content and attribute values
+fn escape_html(text: &str) -> String {
+    text.chars()
+        .fold(String::with_capacity(text.len()), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                '\'' => out.push_str("&#39;"),
+                _ => out.push(c),
+            }
+            out
+        })
+}
+
+/// Render the report as a standalone HTML page
+pub fn render_html(report: &AssessedRecording) -> String {
+    let response = &report.response;
+    let mut html = String::new();
+
+    html.push_str(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Pronunciation assessment</title>\n<style>\n\
+         body { font-family: \"DejaVu Sans\", \"Noto Sans\", sans-serif; max-width: 48em; margin: 2em auto; color: #222; }\n\
+         table { border-collapse: collapse; width: 100%; }\n\
+         th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; }\n\
+         .good { color: #1a7f37; } .weak { color: #c62828; } .unscored { color: #888; }\n\
+         .waveform { width: 100%; height: 80px; background: #f5f5f5; }\n\
+         </style>\n</head>\n<body>\n",
+    );
+
+    let _ = writeln!(html, "<h1>Pronunciation assessment</h1>");
+    let _ = writeln!(
+        html,
+        "<p><strong>Transcript:</strong> {}<br><strong>Dialect:</strong> {}<br><strong>Assessed:</strong> {}</p>",
+        escape_html(&report.transcript),
+        dialect_name(report.dialect),
+        httpdate::fmt_http_date(report.assessed_at)
+    );
+
+    if response.wrong_sentence_detected {
+        let _ = writeln!(
+            html,
+            "<p class=\"weak\">The recording appears to be of a different sentence, so it was not scored.</p>"
+        );
+    } else {
+        let _ = writeln!(
+            html,
+            "<h2>Overall score: {}</h2>",
+            percent(response.overall_score)
+        );
+    }
+
+    if !report.waveform.is_empty() {
+        let _ = writeln!(
+            html,
+            "<svg class=\"waveform\" viewBox=\"0 0 {} 2\" preserveAspectRatio=\"none\" role=\"img\" aria-label=\"Waveform of the recording\">\n<path d=\"{}\" stroke=\"#3f6ad8\" stroke-width=\"0.6\" vector-effect=\"non-scaling-stroke\"/>\n</svg>",
+            report.waveform.len(),
+            waveform_path(&report.waveform)
+        );
+    }
+
+    let words = report.words();
+    if !response.phoneme_details.is_empty() {
+        html.push_str(
+            "<h2>Words</h2>\n<table>\n<tr><th>Word</th><th>Expected</th><th>Heard</th><th>Score</th></tr>\n",
+        );
+        for word in &words {
+            let (class, score) = match word.score {
+                Some(score) if score >= GOOD_SCORE => ("good", percent(score)),
+                Some(score) => ("weak", percent(score)),
+                None => ("unscored", "not scored".to_string()),
+            };
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>/{}/</td><td>/{}/</td><td class=\"{}\">{}</td></tr>",
+                escape_html(&word.word),
+                escape_html(&word.expected),
+                escape_html(&word.heard),
+                class,
+                score
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    if !response.feedback.is_empty() {
+        html.push_str("<h2>Tips</h2>\n<ul>\n");
+        for tip in &response.feedback {
+            let _ = writeln!(html, "<li>{}</li>", escape_html(tip));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// SVG path drawing a vertical line per window, in a viewBox two units high
+fn waveform_path(waveform: &[(f32, f32)]) -> String {
+    waveform
+        .iter()
+        .enumerate()
+        .fold(String::new(), |mut path, (x, &(min, max))| {
+            let _ = write!(
+                path,
+                "M{}.5 {:.3}V{:.3}",
+                x,
+                1.0 - max.clamp(-1.0, 1.0),
+                1.0 - min.clamp(-1.0, 1.0)
+            );
+            path
+        })
+}
+
+// A4 portrait, in millimetres
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+
+/// Millimetres per typographic point
+const MM_PER_PT: f32 = 0.3528;
+
+/// Characters per line of 10pt text across the page, before wrapping
+const WRAP_CHARS: usize = 90;
+
+/// Render the report as a PDF, with text set in `font`
+pub fn render_pdf(report: &AssessedRecording, font: &[u8]) -> Result<Vec<u8>> {
+    let (doc, page, layer) = PdfDocument::new(
+        "Pronunciation assessment",
+        Mm(PAGE_WIDTH),
+        Mm(PAGE_HEIGHT),
+        "Report",
+    );
+    let font = doc
+        .add_external_font(font)
+        .context("Failed to load report font")?;
+    let layer = doc.get_page(page).get_layer(layer);
+    let mut pdf = PdfCursor {
+        doc,
+        layer,
+        font,
+        y: PAGE_HEIGHT - MARGIN,
+    };
+    let response = &report.response;
+
+    pdf.text("Pronunciation assessment", 18.0);
+    pdf.wrapped(&format!("Transcript: {}", report.transcript), 10.0);
+    pdf.text(&format!("Dialect: {}", dialect_name(report.dialect)), 10.0);
+    pdf.text(
+        &format!("Assessed: {}", httpdate::fmt_http_date(report.assessed_at)),
+        10.0,
+    );
+    pdf.gap(4.0);
+
+    if response.wrong_sentence_detected {
+        pdf.wrapped(
+            "The recording appears to be of a different sentence, so it was not scored.",
+            12.0,
+        );
+    } else {
+        pdf.text(
+            &format!("Overall score: {}", percent(response.overall_score)),
+            14.0,
+        );
+    }
+
+    if !report.waveform.is_empty() {
+        pdf.waveform(&report.waveform, 25.0);
+    }
+
+    if !response.phoneme_details.is_empty() {
+        pdf.gap(4.0);
+        pdf.text("Words", 14.0);
+        let columns = [0.0, 50.0, 95.0, 140.0];
+        pdf.row(&columns, &["Word", "Expected", "Heard", "Score"], 10.0);
+        for word in report.words() {
+            let expected = format!("/{}/", word.expected);
+            let heard = format!("/{}/", word.heard);
+            let score = word.score.map_or_else(|| "not scored".to_string(), percent);
+            pdf.row(&columns, &[&word.word, &expected, &heard, &score], 10.0);
+        }
+    }
+
+    if !response.feedback.is_empty() {
+        pdf.gap(4.0);
+        pdf.text("Tips", 14.0);
+        for tip in &response.feedback {
+            pdf.wrapped(&format!("• {}", tip), 10.0);
+        }
+    }
+
+    pdf.doc
+        .save_to_bytes()
+        .context("Failed to write the PDF report")
+}
+
+/// Writes lines down the page, starting new pages as it fills
+struct PdfCursor {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    font: IndirectFontRef,
+    /// Baseline of the next line, from the bottom of the page
+    y: f32,
+}
+
+impl PdfCursor {
+    /// Make room for content `height` tall, on a new page if needed
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Report");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn text(&mut self, text: &str, size: f32) {
+        self.row(&[0.0], &[text], size);
+    }
+
+    /// Text in columns starting at the given offsets from the margin
+    fn row(&mut self, columns: &[f32], cells: &[&str], size: f32) {
+        let line_height = size * MM_PER_PT * 1.5;
+        self.reserve(line_height);
+        self.y -= size * MM_PER_PT;
+        for (&x, cell) in columns.iter().zip(cells) {
+            self.layer
+                .use_text(*cell, size, Mm(MARGIN + x), Mm(self.y), &self.font);
+        }
+        self.y -= line_height - size * MM_PER_PT;
+    }
+
+    /// Text wrapped at word boundaries to fit the page
+    fn wrapped(&mut self, text: &str, size: f32) {
+        for line in wrap(text, WRAP_CHARS * 10 / size as usize) {
+            self.text(&line, size);
+        }
+    }
+
+    fn waveform(&mut self, waveform: &[(f32, f32)], height: f32) {
+        self.reserve(height + 4.0);
+        self.y -= 2.0;
+        let middle = self.y - height / 2.0;
+        let step = (PAGE_WIDTH - 2.0 * MARGIN) / waveform.len() as f32;
+
+        self.layer
+            .set_outline_color(Color::Rgb(Rgb::new(0.25, 0.42, 0.85, None)));
+        self.layer.set_outline_thickness(0.5);
+        for (i, &(min, max)) in waveform.iter().enumerate() {
+            let x = MARGIN + (i as f32 + 0.5) * step;
+            self.layer.add_line(Line {
+                points: vec![
+                    (
+                        Point::new(Mm(x), Mm(middle + max.clamp(-1.0, 1.0) * height / 2.0)),
+                        false,
+                    ),
+                    (
+                        Point::new(Mm(x), Mm(middle + min.clamp(-1.0, 1.0) * height / 2.0)),
+                        false,
+                    ),
+                ],
+                is_closed: false,
+            });
+        }
+        self.y -= height + 2.0;
+    }
+}
+
+/// Split text into lines of at most `width` characters, keeping longer words whole
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let fits = line.chars().count() + 1 + word.chars().count() <= width;
+        if !line.is_empty() && !fits {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::mfa::PhonemeAssessmentDetail;
+
+    fn detail(
+        expected: &str,
+        actual: &str,
+        score: f64,
+        chars: (usize, usize),
+    ) -> PhonemeAssessmentDetail {
+        PhonemeAssessmentDetail {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+            score,
+            start_time: 0.0,
+            end_time: 0.0,
+            char_start: Some(chars.0),
+            char_end: Some(chars.1),
+            confidence: None,
+        }
+    }
+
+    fn report() -> AssessedRecording {
+        AssessedRecording {
+            response: PronunciationResponse {
+                overall_score: 0.75,
+                phoneme_details: vec![
+                    detail("ð", "d", 0.5, (0, 2)),
+                    detail("ɪ", "ɪ", 1.0, (2, 3)),
+                    detail("s", "s", 1.0, (3, 4)),
+                    detail("k", "k", 1.0, (12, 13)),
+                ],
+                feedback: vec!["Your /ð/ sounded like /d/ — <try> again".to_string()],
+                wrong_sentence_detected: false,
+                transcript_check: None,
+                personalized: None,
+                pace: None,
+                intonation: None,
+                clarity: Vec::new(),
+                tiers: None,
+            },
+            transcript: "This  xyzzy cat".to_string(),
+            dialect: MfaDialect::AmericanEnglish,
+            assessed_at: SystemTime::UNIX_EPOCH,
+            waveform: vec![(-0.5, 0.5), (-0.1, 0.2)],
+            audio: write_wav_mono(&[0.0, 0.25, 0.5, 0.75, -0.5, -0.25], 4).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_words_group_phonemes_by_spelling() {
+        let words = report().words();
+
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[0].word, "This");
+        assert_eq!(words[0].expected, "ðɪs");
+        assert_eq!(words[0].heard, "dɪs");
+        assert!((words[0].score.unwrap() - 5.0 / 6.0).abs() < 1e-9);
+        assert_eq!(words[1].score, None);
+        assert_eq!(words[2].word, "cat");
+        assert_eq!(words[2].score, Some(1.0));
+    }
+
+    #[test]
+    fn test_segment_slices_the_recording() {
+        let recording = report();
+
+        let (samples, sample_rate) =
+            read_wav_mono(&recording.segment(0.5, 1.0).unwrap().unwrap()).unwrap();
+        assert_eq!(sample_rate, 4);
+        assert_eq!(samples.len(), 2);
+        assert!((samples[0] - 0.5).abs() < 1e-3);
+
+        // Cut short at the end of the recording
+        let (samples, _) = read_wav_mono(&recording.segment(1.0, 10.0).unwrap().unwrap()).unwrap();
+        assert_eq!(samples.len(), 2);
+
+        assert_eq!(recording.segment(1.5, 2.0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_render_html_escapes_text() {
+        let html = render_html(&report());
+
+        assert!(html.contains("Overall score: 75%"));
+        assert!(html.contains("&lt;try&gt;"));
+        assert!(!html.contains("<try>"));
+        assert!(html.contains("<svg class=\"waveform\""));
+        assert!(html.contains("not scored"));
+    }
+
+    #[test]
+    #[ignore = "Requires REPORT_FONT to name a TrueType font"]
+    fn test_render_pdf() {
+        let font = REPORT_FONT
+            .as_deref()
+            .expect("REPORT_FONT should be readable");
+        let pdf = render_pdf(&report(), font).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("one two three four", 9),
+            vec!["one two", "three", "four"]
+        );
+        assert_eq!(wrap("unbreakable", 4), vec!["unbreakable"]);
+        assert!(wrap("  ", 10).is_empty());
+    }
+}
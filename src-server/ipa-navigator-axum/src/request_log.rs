@@ -0,0 +1,268 @@
+//! One log line per request, without the learner's text or speech
+//!
+//! Each request is logged with its method, path, status and latency, plus the
+//! voice and text length of synthesis and assessment requests. Text,
+//! transcripts and audio are redacted down to a configurable prefix, which is
+//! empty unless set, so production logs can be read without exposing what
+//! learners said. Query strings are left out of the path for the same reason.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Query, Request},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use tracing::{info, warn};
+
+/// Largest JSON body read for logging; larger bodies are logged without their fields
+const MAX_INSPECTED_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Shown instead of redacted content
+const REDACTED: &str = "[redacted]";
+
+pub struct RequestLogConfig {
+    /// Whether requests are logged at all, from `REQUEST_LOG` ("off" disables)
+    pub enabled: bool,
+
+    /// Characters of text and transcripts to log, from `REQUEST_LOG_TEXT_PREFIX`
+    pub text_prefix: usize,
+
+    /// Characters of base64 audio to log, from `REQUEST_LOG_AUDIO_PREFIX`
+    pub audio_prefix: usize,
+}
+
+impl RequestLogConfig {
+    pub fn from_env() -> Self {
+        let prefix = |name: &str| {
+            env::var(name)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0)
+        };
+
+        Self {
+            enabled: !env::var("REQUEST_LOG")
+                .is_ok_and(|value| matches!(value.to_lowercase().as_str(), "off" | "0" | "false")),
+            text_prefix: prefix("REQUEST_LOG_TEXT_PREFIX"),
+            audio_prefix: prefix("REQUEST_LOG_AUDIO_PREFIX"),
+        }
+    }
+}
+
+static CONFIG: LazyLock<RequestLogConfig> = LazyLock::new(RequestLogConfig::from_env);
+
+/// What a request says about its content, redacted for logging
+#[derive(Debug, Default, PartialEq)]
+struct ContentSummary {
+    voice: Option<String>,
+    text_chars: Option<usize>,
+    text: Option<String>,
+    audio_bytes: Option<usize>,
+    audio: Option<String>,
+}
+
+impl ContentSummary {
+    /// Summarize the fields of a query string or JSON body
+    fn from_fields<'a>(
+        mut field: impl FnMut(&str) -> Option<&'a Value>,
+        config: &RequestLogConfig,
+    ) -> Self {
+        let text = ["text", "transcript", "word"]
+            .into_iter()
+            .find_map(|name| field(name).and_then(Value::as_str))
+            .map(str::to_string)
+            .or_else(|| {
+                // Prefetched phrases, counted together
+                field("phrases").and_then(Value::as_array).map(|phrases| {
+                    phrases
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+            });
+        let audio = field("audio").and_then(Value::as_str);
+
+        Self {
+            voice: field("voice").and_then(Value::as_str).map(str::to_string),
+            text_chars: text.as_ref().map(|text| text.chars().count()),
+            text: text.as_deref().map(|text| redact(text, config.text_prefix)),
+            audio_bytes: audio.map(str::len),
+            audio: audio.map(|audio| redact(audio, config.audio_prefix)),
+        }
+    }
+
+    fn from_query(query: &HashMap<String, String>, config: &RequestLogConfig) -> Self {
+        let values: HashMap<&str, Value> = query
+            .iter()
+            .map(|(name, value)| (name.as_str(), Value::String(value.clone())))
+            .collect();
+        Self::from_fields(|name| values.get(name), config)
+    }
+
+    fn from_json(body: &[u8], config: &RequestLogConfig) -> Self {
+        match serde_json::from_slice::<Value>(body) {
+            Ok(Value::Object(fields)) => Self::from_fields(|name| fields.get(name), config),
+            _ => Self::default(),
+        }
+    }
+}
+
+/// The first `prefix` characters of `value`, marked if anything was left out
+fn redact(value: &str, prefix: usize) -> String {
+    if prefix == 0 {
+        return REDACTED.to_string();
+    }
+    match value.char_indices().nth(prefix) {
+        Some((end, _)) => format!("{}…", &value[..end]),
+        None => value.to_string(),
+    }
+}
+
+/// Log each request once it has been answered
+pub async fn log_requests(request: Request, next: Next) -> Response {
+    let config = &*CONFIG;
+    if !config.enabled {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let mut summary = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .map(|Query(query)| ContentSummary::from_query(&query, config))
+        .unwrap_or_default();
+
+    let request = if inspectable_json(request.headers()) {
+        let (parts, body) = request.into_parts();
+        let bytes = match to_bytes(body, MAX_INSPECTED_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("Failed to read request body: {}", e),
+                )
+                    .into_response();
+            }
+        };
+        let body_summary = ContentSummary::from_json(&bytes, config);
+        if body_summary != ContentSummary::default() {
+            summary = body_summary;
+        }
+        Request::from_parts(parts, Body::from(bytes))
+    } else {
+        request
+    };
+
+    let response = next.run(request).await;
+    let status = response.status().as_u16();
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    macro_rules! log {
+        ($level:ident) => {
+            $level!(
+                target: "request",
+                %method,
+                path,
+                status,
+                latency_ms,
+                voice = summary.voice.as_deref(),
+                text_chars = summary.text_chars,
+                text = summary.text.as_deref(),
+                audio_bytes = summary.audio_bytes,
+                audio = summary.audio.as_deref(),
+                "{} {} {} in {:.1}ms",
+                method,
+                path,
+                status,
+                latency_ms
+            )
+        };
+    }
+    if response.status().is_server_error() {
+        log!(warn);
+    } else {
+        log!(info);
+    }
+
+    response
+}
+
+/// Whether the body is JSON of a known size small enough to read for logging
+fn inspectable_json(headers: &HeaderMap) -> bool {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    is_json && length.is_some_and(|length| length <= MAX_INSPECTED_BODY_BYTES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(text_prefix: usize, audio_prefix: usize) -> RequestLogConfig {
+        RequestLogConfig {
+            enabled: true,
+            text_prefix,
+            audio_prefix,
+        }
+    }
+
+    #[test]
+    fn test_redact_keeps_prefix() {
+        assert_eq!(redact("secret words", 0), REDACTED);
+        assert_eq!(redact("secret words", 6), "secret…");
+        assert_eq!(redact("ðɪs", 2), "ðɪ…");
+        assert_eq!(redact("short", 10), "short");
+    }
+
+    #[test]
+    fn test_summary_of_assessment_body() {
+        let body =
+            br#"{"audio": "UklGRiQAAABXQVZF", "transcript": "this is a test", "dialect": "us"}"#;
+
+        let summary = ContentSummary::from_json(body, &config(0, 4));
+
+        assert_eq!(summary.text_chars, Some(14));
+        assert_eq!(summary.text.as_deref(), Some(REDACTED));
+        assert_eq!(summary.audio_bytes, Some(16));
+        assert_eq!(summary.audio.as_deref(), Some("UklG…"));
+        assert_eq!(summary.voice, None);
+    }
+
+    #[test]
+    fn test_summary_of_synthesis_query_and_prefetch() {
+        let query = HashMap::from([
+            ("text".to_string(), "Hello there".to_string()),
+            ("voice".to_string(), "american_female_bella".to_string()),
+        ]);
+        let summary = ContentSummary::from_query(&query, &config(5, 0));
+        assert_eq!(summary.voice.as_deref(), Some("american_female_bella"));
+        assert_eq!(summary.text_chars, Some(11));
+        assert_eq!(summary.text.as_deref(), Some("Hello…"));
+
+        let body = br#"{"phrases": ["one", "two"], "voice": "british_male_george"}"#;
+        let summary = ContentSummary::from_json(body, &config(0, 0));
+        assert_eq!(summary.text_chars, Some(7));
+        assert_eq!(summary.audio, None);
+
+        assert_eq!(
+            ContentSummary::from_json(b"not json", &config(0, 0)),
+            ContentSummary::default()
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! Retention policy for stored job artifacts
+//!
+//! MFA job directories left on disk and the lesson clips kept for batch audio
+//! are deleted by one policy: beyond the newest `RETENTION_KEEP_LAST` of each
+//! owner, or once older than `RETENTION_MAX_AGE_DAYS`. MFA job directories
+//! are owned by the signed-in user who made the request, as recorded in the
+//! directory; lesson clips and jobs of anonymous requests are shared, so only
+//! the age applies to them. An artifact is flagged, and always kept, by a `.keep` file
+//! inside its job directory or beside its clip, e.g. `<id>.keep`.
+//!
+//! A background task applies the policy every `RETENTION_INTERVAL_SECS`, and
+//! the admin API reports what it would delete without deleting anything.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use ipa_navigator_mfa::storage::STORAGE;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::lesson_audio::LESSON_AUDIO;
+
+/// Name of the file that flags an artifact to be kept
+pub const KEEP_MARKER: &str = ".keep";
+
+/// Age below which nothing is deleted when `RETENTION_GRACE_SECS` is not set
+const DEFAULT_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// Time between runs of the background task when `RETENTION_INTERVAL_SECS` is not set
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Policy configured from the environment
+pub static RETENTION_POLICY: LazyLock<RetentionPolicy> = LazyLock::new(RetentionPolicy::from_env);
+
+/// Which stored artifacts to delete
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Newest artifacts kept for each owner, all if unset
+    pub keep_last: Option<usize>,
+    /// Age after which artifacts are deleted, never if unset
+    pub max_age: Option<Duration>,
+    /// Age below which artifacts are never deleted, so jobs in progress are left alone
+    pub grace: Duration,
+    /// Time between runs of the background task, which does not run if unset
+    pub interval: Option<Duration>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_last: None,
+            max_age: None,
+            grace: DEFAULT_GRACE,
+            interval: Some(DEFAULT_INTERVAL),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Read `RETENTION_KEEP_LAST`, `RETENTION_MAX_AGE_DAYS`, `RETENTION_GRACE_SECS`,
+    /// and `RETENTION_INTERVAL_SECS`, where an interval of 0 turns the background task off
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(name).ok().and_then(|s| s.parse::<u64>().ok());
+
+        Self {
+            keep_last: var("RETENTION_KEEP_LAST").map(|n| n as usize),
+            max_age: var("RETENTION_MAX_AGE_DAYS")
+                .filter(|&days| days > 0)
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            grace: var("RETENTION_GRACE_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.grace),
+            interval: match var("RETENTION_INTERVAL_SECS") {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => defaults.interval,
+            },
+        }
+    }
+
+    /// Whether the policy deletes nothing
+    pub fn is_empty(&self) -> bool {
+        self.keep_last.is_none() && self.max_age.is_none()
+    }
+
+    /// Decide which of `artifacts` to delete as of `now`
+    pub fn plan(&self, artifacts: Vec<Artifact>, now: SystemTime) -> RetentionPlan {
+        let examined = artifacts.len();
+        let age = |artifact: &Artifact| now.duration_since(artifact.modified).unwrap_or_default();
+
+        let mut flagged = 0;
+        let mut deletions = Vec::new();
+        let mut by_owner: HashMap<String, Vec<Artifact>> = HashMap::new();
+        for artifact in artifacts {
+            if artifact.flagged {
+                flagged += 1;
+            } else if age(&artifact) < self.grace {
+                // Still counts towards its owner's newest, but is never deleted
+                if let Some(owner) = artifact.owner.clone() {
+                    by_owner.entry(owner).or_default().push(artifact);
+                }
+            } else if self.max_age.is_some_and(|max_age| age(&artifact) > max_age) {
+                deletions.push(Deletion {
+                    artifact,
+                    reason: DeletionReason::Expired,
+                });
+            } else if let Some(owner) = artifact.owner.clone() {
+                by_owner.entry(owner).or_default().push(artifact);
+            }
+        }
+
+        if let Some(keep_last) = self.keep_last {
+            for (_, mut owned) in by_owner {
+                owned.sort_by_key(|artifact| Reverse(artifact.modified));
+                for artifact in owned.into_iter().skip(keep_last) {
+                    if age(&artifact) >= self.grace {
+                        deletions.push(Deletion {
+                            artifact,
+                            reason: DeletionReason::OverLimit,
+                        });
+                    }
+                }
+            }
+        }
+
+        deletions.sort_by_key(|deletion| deletion.artifact.modified);
+        RetentionPlan {
+            examined,
+            flagged,
+            deletions,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// A directory MFA aligned a job in
+    MfaJob,
+    /// A stored lesson clip
+    LessonClip,
+}
+
+/// A stored file or directory the policy may delete
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    /// Directory name of a job, or id of a clip
+    pub name: String,
+    /// Owner whose newest artifacts are kept, none for shared artifacts
+    pub owner: Option<String>,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    pub bytes: u64,
+    pub flagged: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionReason {
+    /// Older than the maximum age
+    Expired,
+    /// Older than its owner's newest artifacts kept
+    OverLimit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deletion {
+    pub artifact: Artifact,
+    pub reason: DeletionReason,
+}
+
+/// What a policy deletes from the stored artifacts, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPlan {
+    pub examined: usize,
+    /// Artifacts kept because they are flagged
+    pub flagged: usize,
+    pub deletions: Vec<Deletion>,
+}
+
+/// A plan and what carrying it out achieved
+#[derive(Debug, Clone, Default)]
+pub struct RetentionOutcome {
+    pub plan: RetentionPlan,
+    /// Artifacts deleted, none on a dry run
+    pub deleted: usize,
+    pub freed_bytes: u64,
+}
+
+/// Every MFA job directory and lesson clip on disk
+pub fn stored_artifacts() -> Result<Vec<Artifact>> {
+    let mut artifacts = Vec::new();
+
+    for job in STORAGE.stored_job_dirs()? {
+        artifacts.push(Artifact {
+            kind: ArtifactKind::MfaJob,
+            name: job
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            owner: job.user,
+            flagged: job.path.join(KEEP_MARKER).exists(),
+            path: job.path,
+            modified: job.modified,
+            bytes: job.bytes,
+        });
+    }
+
+    for clip in LESSON_AUDIO.clips()? {
+        artifacts.push(Artifact {
+            kind: ArtifactKind::LessonClip,
+            owner: None,
+            flagged: clip.path.with_extension("keep").exists(),
+            name: clip.id,
+            path: clip.path,
+            modified: clip.modified,
+            bytes: clip.bytes,
+        });
+    }
+
+    Ok(artifacts)
+}
+
+/// Plan the policy over the stored artifacts and, unless a dry run, delete what it selects
+///
+/// Artifacts that fail to delete are skipped with a warning.
+pub fn enforce(policy: &RetentionPolicy, dry_run: bool) -> Result<RetentionOutcome> {
+    let plan = policy.plan(stored_artifacts()?, SystemTime::now());
+    if dry_run {
+        return Ok(RetentionOutcome {
+            plan,
+            deleted: 0,
+            freed_bytes: 0,
+        });
+    }
+
+    let (mut deleted, mut freed_bytes) = (0, 0);
+    for deletion in &plan.deletions {
+        let artifact = &deletion.artifact;
+        let removed = match artifact.kind {
+            ArtifactKind::MfaJob => fs::remove_dir_all(&artifact.path),
+            ArtifactKind::LessonClip => fs::remove_file(&artifact.path),
+        };
+        match removed {
+            Ok(()) => {
+                deleted += 1;
+                freed_bytes += artifact.bytes;
+            }
+            Err(e) => warn!("Failed to delete {:?}: {}", artifact.path, e),
+        }
+    }
+
+    if deleted > 0 {
+        info!(
+            "Retention policy deleted {} artifacts, freeing {} bytes",
+            deleted, freed_bytes
+        );
+    }
+    Ok(RetentionOutcome {
+        plan,
+        deleted,
+        freed_bytes,
+    })
+}
+
+/// Apply the configured policy in the background at its interval
+///
+/// Does nothing if the policy deletes nothing or has no interval.
+pub fn spawn_enforcer() {
+    let policy = &*RETENTION_POLICY;
+    let Some(interval) = policy.interval.filter(|_| !policy.is_empty()) else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            match tokio::task::spawn_blocking(|| enforce(policy, false)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("Retention policy failed: {:#}", e),
+                Err(e) => warn!("Retention task failed: {}", e),
+            }
+        }
+    });
+    info!("Applying the retention policy every {:?}", interval);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn artifact(name: &str, owner: Option<&str>, age: Duration, now: SystemTime) -> Artifact {
+        Artifact {
+            kind: ArtifactKind::MfaJob,
+            name: name.to_string(),
+            owner: owner.map(str::to_string),
+            path: PathBuf::from(name),
+            modified: now - age,
+            bytes: 10,
+            flagged: false,
+        }
+    }
+
+    fn deleted(plan: &RetentionPlan) -> Vec<(&str, DeletionReason)> {
+        plan.deletions
+            .iter()
+            .map(|d| (d.artifact.name.as_str(), d.reason))
+            .collect()
+    }
+
+    #[test]
+    fn test_keeps_the_newest_of_each_owner() {
+        let now = SystemTime::now();
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            ..RetentionPolicy::default()
+        };
+        // Learners of one school, who share a tenant root
+        let artifacts = vec![
+            artifact("ana-new", Some("user_ana"), 2 * HOUR, now),
+            artifact("ana-old", Some("user_ana"), 3 * HOUR, now),
+            artifact("ben-new", Some("user_ben"), 4 * HOUR, now),
+            artifact("ben-old", Some("user_ben"), 6 * HOUR, now),
+            artifact("cho-only", Some("user_cho"), 5 * HOUR, now),
+            artifact("anonymous", None, 7 * HOUR, now),
+            artifact("clip", None, 100 * HOUR, now),
+        ];
+
+        let plan = policy.plan(artifacts, now);
+
+        assert_eq!(plan.examined, 7);
+        assert_eq!(
+            deleted(&plan),
+            [
+                ("ben-old", DeletionReason::OverLimit),
+                ("ana-old", DeletionReason::OverLimit)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expires_old_artifacts_but_not_flagged_or_recent_ones() {
+        let now = SystemTime::now();
+        let policy = RetentionPolicy {
+            keep_last: Some(0),
+            max_age: Some(24 * HOUR),
+            ..RetentionPolicy::default()
+        };
+        let mut flagged = artifact("flagged", Some("a"), 48 * HOUR, now);
+        flagged.flagged = true;
+        let artifacts = vec![
+            artifact("in-progress", Some("a"), Duration::from_secs(60), now),
+            artifact("clip", None, 48 * HOUR, now),
+            artifact("recent", Some("a"), 2 * HOUR, now),
+            flagged,
+        ];
+
+        let plan = policy.plan(artifacts, now);
+
+        assert_eq!(plan.flagged, 1);
+        assert_eq!(
+            deleted(&plan),
+            [
+                ("clip", DeletionReason::Expired),
+                ("recent", DeletionReason::OverLimit)
+            ]
+        );
+        assert!(RetentionPolicy::default().is_empty());
+        assert!(
+            RetentionPolicy::default()
+                .plan(vec![artifact("old", Some("a"), 1000 * HOUR, now)], now)
+                .deletions
+                .is_empty()
+        );
+    }
+}
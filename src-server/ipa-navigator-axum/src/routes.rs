@@ -1,25 +1,289 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 use std::time::Duration;
 
-use axum::routing::{Router, get, post};
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware,
+    routing::{Router, any, delete, get, post, put},
+};
 use tower_http::{
-    compression::CompressionLayer, cors::CorsLayer, timeout::TimeoutLayer, trace::TraceLayer,
+    compression::CompressionLayer,
+    cors::{AllowOrigin, CorsLayer},
+    services::{ServeDir, ServeFile},
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
+use tracing::{info, warn};
+
+use crate::auth::require_admin;
+use crate::config::CONFIG_MANAGER;
+use crate::engines::AppState;
+use crate::handlers::{
+    admin, classroom, compare, exercises, gamification, health, ipa, jobs, lessons, mfa, narration,
+    privacy, progress, spell, text, tts, voices,
 };
+use crate::idempotency;
+use crate::identity::identify;
+use crate::limits::{self, ASSESS_ROUTES, RouteGroup, TTS_ROUTES};
+use crate::request_log::log_requests;
+use crate::tenants::resolve_tenant;
 
-use crate::handlers::{health, tts};
+/// Directory of the built frontend to serve, from `STATIC_DIR`
+///
+/// Unset by default, for deployments serving the frontend from its own server.
+static STATIC_DIR: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
+    env::var("STATIC_DIR")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+});
 
 /// Creates the router for the application.
 pub fn create_router() -> Router {
+    create_router_with(AppState::default())
+}
+
+/// Creates the router for the application, with handlers using the engines in `state`
+pub fn create_router_with(state: AppState) -> Router {
+    // Origins are checked against the settings in effect, so they can change while serving
     let cors = CorsLayer::new()
-        .allow_origin(tower_http::cors::Any)
+        .allow_origin(AllowOrigin::predicate(|origin, _| {
+            CONFIG_MANAGER.settings().allows_origin(origin)
+        }))
         .allow_methods(tower_http::cors::Any)
         .allow_headers(tower_http::cors::Any);
 
-    Router::new()
+    let router = Router::new()
+        .merge(tts_router())
+        .route("/api/voices", get(voices::list))
+        .route("/api/voices/{id}/similar", get(voices::similar))
+        .merge(assess_router())
+        .merge(idempotent(
+            Router::new().route("/api/assess/jobs", post(mfa::assess_job)),
+        ))
+        .route("/api/assess/jobs/{id}/report", get(jobs::report))
+        .route("/api/assess/jobs/{id}/segment", get(jobs::segment))
+        .route("/api/ipa/validate", post(ipa::validate))
+        .route("/api/ipa/similarity", get(ipa::similarity_matrix))
+        .route("/api/ipa/chart", get(ipa::chart))
+        .route("/api/ipa/{symbol}", get(ipa::symbol_info))
+        .route("/api/ipa/{symbol}/audio", get(ipa::symbol_audio))
+        .route("/api/ipa/{symbol}/sentences", get(ipa::symbol_sentences))
+        .route("/api/exercises/from-text", post(exercises::from_text))
+        .route("/api/exercises/transcription", post(exercises::grade))
+        .route("/api/text/difficulty", post(text::difficulty))
+        .route("/api/phonemize", post(text::phonemize))
+        .route(
+            "/api/classrooms/{id}/assignments",
+            post(classroom::create_assignment),
+        )
+        .route(
+            "/api/classrooms/{id}/weaknesses",
+            get(classroom::weaknesses),
+        )
+        .route("/api/assignments/{id}", get(classroom::assignment))
+        .route(
+            "/api/assignments/{id}/submissions",
+            get(classroom::submissions).post(classroom::submit),
+        )
+        .route(
+            "/api/users/{id}/gamification",
+            get(gamification::user_gamification),
+        )
+        .route("/api/progress/phonemes", get(progress::phoneme_trends))
+        .route("/api/users/{id}/export", get(privacy::export))
+        .route("/api/users/{id}/data", delete(privacy::delete_data))
+        .route("/api/lessons/audio/{id}", get(lessons::clip))
+        .route("/api/jobs/{id}", get(jobs::status))
+        .route("/api/jobs/{id}/events", get(jobs::events))
+        .route("/api/jobs/{id}/result", get(jobs::result))
+        .route("/api/jobs/{id}/chapters", get(jobs::chapters))
+        .route_layer(middleware::from_fn(identify))
+        // Tenants are resolved first, so sessions know which Convex deployment to use
+        .route_layer(middleware::from_fn_with_state(
+            state.tenants.clone(),
+            resolve_tenant,
+        ))
+        // Probes and admin routes serve the whole server rather than one tenant, so are merged after
         .route("/health", get(health::health_check))
-        .route("/api/tts", post(tts::synthesize_speech))
-        // .route("/api/pronunciation", post(mfa::assess)) // Changed to Python WhisperX API
+        .route("/ready", get(health::ready))
+        .merge(admin_router())
+        .with_state(state);
+
+    let router = match STATIC_DIR.as_deref() {
+        Some(dir) => with_frontend(router, dir),
+        None => router,
+    };
+
+    router
         .layer(cors)
-        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(log_requests))
+        // Spans name the path alone, since query strings can hold the learner's text
+        .layer(
+            TraceLayer::new_for_http().make_span_with(|request: &Request| {
+                tracing::debug_span!(
+                    "request",
+                    method = %request.method(),
+                    path = request.uri().path(),
+                    version = ?request.version(),
+                )
+            }),
+        )
         .layer(CompressionLayer::new())
         .layer(TimeoutLayer::new(Duration::from_secs(30)))
 }
+
+/// Serve the frontend built into `dir` for paths no route matches
+///
+/// Paths that are not files get `index.html`, so client-side routes load the
+/// app, while unknown `/api` paths still answer 404 rather than the page.
+fn with_frontend(router: Router, dir: &Path) -> Router {
+    let index = dir.join("index.html");
+    if index.is_file() {
+        info!("Serving frontend from {}", dir.display());
+    } else {
+        warn!(
+            "No index.html in {}, frontend routes will 404",
+            dir.display()
+        );
+    }
+
+    router
+        .route("/api/{*path}", any(|| async { StatusCode::NOT_FOUND }))
+        .fallback_service(ServeDir::new(dir).fallback(ServeFile::new(index)))
+}
+
+/// Synthesis routes, sharing the TTS concurrency limit
+fn tts_router() -> Router<AppState> {
+    limited(
+        Router::new()
+            .route(
+                "/api/tts",
+                get(tts::speech_audio).post(tts::synthesize_speech),
+            )
+            .route("/api/tts/narration", post(narration::narrate))
+            .route("/api/tts/peaks", get(tts::speech_peaks))
+            .route("/api/tts/prefetch", post(tts::prefetch))
+            .route("/api/tts/spell", post(spell::spell))
+            .route("/api/tts/tokens", post(tts::synthesize_tokens)),
+        &TTS_ROUTES,
+    )
+    .merge(idempotent(limited(
+        Router::new()
+            .route("/api/tts/batch", post(tts::synthesize_batch))
+            .route("/api/lessons/audio", post(lessons::generate)),
+        &TTS_ROUTES,
+    )))
+}
+
+/// Routes assessing a recording in the request, sharing the assessment concurrency limit
+///
+/// Assessment jobs take a slot when they start rather than when queued.
+fn assess_router() -> Router<AppState> {
+    limited(
+        Router::new().route("/api/assess/compare", post(compare::compare)),
+        &ASSESS_ROUTES,
+    )
+    .merge(idempotent(limited(
+        Router::new()
+            .route("/api/pronunciation", post(mfa::assess))
+            .route("/api/pronunciation/word", post(mfa::assess_word)),
+        &ASSESS_ROUTES,
+    )))
+}
+
+/// Replay responses to retries sending the same `Idempotency-Key`
+///
+/// Applied outside any concurrency limit, so replays never wait for a slot.
+fn idempotent(router: Router<AppState>) -> Router<AppState> {
+    router.route_layer(middleware::from_fn(idempotency::replay))
+}
+
+/// Limit how many of `router`'s requests are handled at once, recording how long each waits
+fn limited(router: Router<AppState>, group: &'static RouteGroup) -> Router<AppState> {
+    router
+        .layer(middleware::from_fn(move |request, next| {
+            limits::admit(group, request, next)
+        }))
+        .layer(group.limit_layer())
+        .layer(middleware::from_fn(move |request, next| {
+            limits::enqueue(group, request, next)
+        }))
+}
+
+/// Routes for operators, all requiring the admin token
+fn admin_router() -> Router<AppState> {
+    Router::new()
+        .route("/api/admin/mfa/status", get(admin::mfa_status))
+        .route("/api/admin/reload", post(admin::reload))
+        .route("/api/admin/config", get(admin::config))
+        .route("/api/admin/config/reload", post(admin::reload_config))
+        .route("/api/admin/jobs", get(admin::job_history))
+        .route("/api/admin/audit", get(admin::audit_log))
+        .route("/api/admin/queues", get(admin::queue_stats))
+        .route("/api/admin/phonemizer", get(admin::phonemizer_stats))
+        .route("/api/admin/storage", get(admin::storage))
+        .route("/api/admin/storage/sweep", post(admin::sweep_storage))
+        .route("/api/admin/retention", get(admin::retention_plan))
+        .route("/api/admin/retention/run", post(admin::enforce_retention))
+        .route("/api/admin/export", get(admin::export))
+        .route("/api/admin/selftest", post(admin::self_test))
+        .route(
+            "/api/admin/voices/calibration",
+            get(admin::voice_calibrations),
+        )
+        .route(
+            "/api/admin/voices/{voice}/calibration",
+            put(admin::set_voice_calibration).delete(admin::reset_voice_calibration),
+        )
+        .route_layer(middleware::from_fn(require_admin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use tower::ServiceExt;
+
+    async fn get_path(router: &Router, path: &str) -> (StatusCode, String) {
+        let request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_frontend_falls_back_to_index() {
+        let dir = env::temp_dir().join(format!("frontend-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("index.html"), "<div id=\"root\"></div>").unwrap();
+        std::fs::write(dir.join("assets/app.js"), "render()").unwrap();
+
+        let api = Router::new().route("/api/ping", get(|| async { "pong" }));
+        let router = with_frontend(api, &dir);
+
+        assert_eq!(
+            get_path(&router, "/api/ping").await,
+            (StatusCode::OK, "pong".to_string())
+        );
+        assert_eq!(
+            get_path(&router, "/assets/app.js").await,
+            (StatusCode::OK, "render()".to_string())
+        );
+        for path in ["/", "/lessons/3"] {
+            let (status, body) = get_path(&router, path).await;
+            assert_eq!(status, StatusCode::OK, "{}", path);
+            assert!(body.contains("root"), "{}", path);
+        }
+        assert_eq!(
+            get_path(&router, "/api/missing").await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
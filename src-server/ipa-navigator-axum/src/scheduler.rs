@@ -0,0 +1,376 @@
+//! Two-tier scheduling of TTS inference
+//!
+//! Interactive requests from the UI and batch lesson-generation jobs share the
+//! model. Batch jobs take an inference slot one sentence at a time, so a queued
+//! interactive request runs as soon as the current sentence finishes rather
+//! than after the whole job. Each tier admits a limited number of requests,
+//! and the rest are turned away with 503 and `Retry-After`.
+//!
+//! A batch job's sentences are independent, so [`run_chunks`] synthesizes up
+//! to `TTS_MAX_PARALLEL_INFERENCES` of them at once, one slot each, and puts
+//! the audio back in order.
+
+use std::env;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::thread;
+
+use ipa_navigator_kokoro::constants::MAX_PARALLEL_INFERENCES;
+
+use tokio::sync::Notify;
+
+/// Interactive requests admitted at once when `TTS_INTERACTIVE_QUEUE_DEPTH` is not set
+const DEFAULT_INTERACTIVE_DEPTH: usize = 32;
+
+/// Batch jobs admitted at once when `TTS_BATCH_QUEUE_DEPTH` is not set
+const DEFAULT_BATCH_DEPTH: usize = 8;
+
+/// Seconds clients are asked to wait when `TTS_RETRY_AFTER_SECS` is not set
+const DEFAULT_RETRY_AFTER_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Requests a user is waiting on, served first
+    Interactive,
+    /// Background jobs, served when no interactive request is waiting
+    Batch,
+}
+
+impl Priority {
+    fn index(self) -> usize {
+        match self {
+            Priority::Interactive => 0,
+            Priority::Batch => 1,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Interactive => "interactive",
+            Priority::Batch => "batch",
+        }
+    }
+}
+
+pub struct SchedulerConfig {
+    /// Inferences run at once
+    pub slots: usize,
+    /// Chunks of one batch job synthesized at once
+    pub max_parallel_inferences: usize,
+    /// Interactive requests admitted at once, running or waiting
+    pub interactive_depth: usize,
+    /// Batch jobs admitted at once, running or waiting
+    pub batch_depth: usize,
+    /// Seconds a turned-away client is asked to wait before retrying
+    pub retry_after_secs: u64,
+}
+
+impl SchedulerConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: usize| {
+            env::var(name)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default)
+        };
+
+        // The model has a session for each parallel inference, so by default each gets a slot
+        let max_parallel_inferences = *MAX_PARALLEL_INFERENCES;
+
+        Self {
+            slots: var("TTS_INFERENCE_SLOTS", max_parallel_inferences).max(1),
+            max_parallel_inferences,
+            interactive_depth: var("TTS_INTERACTIVE_QUEUE_DEPTH", DEFAULT_INTERACTIVE_DEPTH),
+            batch_depth: var("TTS_BATCH_QUEUE_DEPTH", DEFAULT_BATCH_DEPTH),
+            retry_after_secs: env::var("TTS_RETRY_AFTER_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_RETRY_AFTER_SECS),
+        }
+    }
+
+    fn depth(&self, priority: Priority) -> usize {
+        match priority {
+            Priority::Interactive => self.interactive_depth,
+            Priority::Batch => self.batch_depth,
+        }
+    }
+}
+
+/// A tier already has as many requests as it admits
+#[derive(Debug, Clone, Copy)]
+pub struct QueueFull {
+    pub priority: Priority,
+    pub retry_after_secs: u64,
+}
+
+#[derive(Default)]
+struct State {
+    running: usize,
+    /// Requests admitted to each tier and not yet finished
+    admitted: [usize; 2],
+    /// Requests in each tier waiting for a slot
+    waiting: [usize; 2],
+}
+
+/// Scheduler shared by all TTS requests
+pub static SCHEDULER: LazyLock<InferenceScheduler> =
+    LazyLock::new(|| InferenceScheduler::new(SchedulerConfig::from_env()));
+
+pub struct InferenceScheduler {
+    config: SchedulerConfig,
+    state: Mutex<State>,
+    /// Woken whenever a slot is freed or a waiter leaves
+    changed: Notify,
+}
+
+impl InferenceScheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Chunks of one batch job synthesized at once
+    pub fn max_parallel_inferences(&self) -> usize {
+        self.config.max_parallel_inferences
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Admit a request or job to its tier, unless the tier is full
+    ///
+    /// The admission is held until the request finishes, and takes a slot
+    /// for each inference it runs.
+    pub fn admit(&self, priority: Priority) -> Result<Admission<'_>, QueueFull> {
+        let mut state = self.state();
+        if state.admitted[priority.index()] >= self.config.depth(priority) {
+            return Err(QueueFull {
+                priority,
+                retry_after_secs: self.config.retry_after_secs,
+            });
+        }
+        state.admitted[priority.index()] += 1;
+
+        Ok(Admission {
+            scheduler: self,
+            priority,
+        })
+    }
+
+    /// Whether a request of this priority may take a free slot now
+    fn can_run(&self, state: &State, priority: Priority) -> bool {
+        state.running < self.config.slots
+            && match priority {
+                Priority::Interactive => true,
+                Priority::Batch => state.waiting[Priority::Interactive.index()] == 0,
+            }
+    }
+
+    async fn acquire(&self, priority: Priority) -> InferencePermit<'_> {
+        let _waiting = Waiting::new(self, priority);
+        loop {
+            // Registered before checking, so a slot freed in between still wakes us
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            {
+                let mut state = self.state();
+                if self.can_run(&state, priority) {
+                    state.running += 1;
+                    return InferencePermit { scheduler: self };
+                }
+            }
+            changed.await;
+        }
+    }
+}
+
+/// A request or job admitted to its tier
+pub struct Admission<'a> {
+    scheduler: &'a InferenceScheduler,
+    priority: Priority,
+}
+
+impl Admission<'_> {
+    /// Wait for an inference slot, behind any waiting interactive requests
+    pub async fn acquire(&self) -> InferencePermit<'_> {
+        self.scheduler.acquire(self.priority).await
+    }
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        self.scheduler.state().admitted[self.priority.index()] -= 1;
+    }
+}
+
+/// Counts a request as waiting until it gets a slot or gives up
+struct Waiting<'a> {
+    scheduler: &'a InferenceScheduler,
+    priority: Priority,
+}
+
+impl<'a> Waiting<'a> {
+    fn new(scheduler: &'a InferenceScheduler, priority: Priority) -> Self {
+        scheduler.state().waiting[priority.index()] += 1;
+        Self {
+            scheduler,
+            priority,
+        }
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.scheduler.state().waiting[self.priority.index()] -= 1;
+        // Batch requests may have been held back by this one
+        self.scheduler.changed.notify_waiters();
+    }
+}
+
+/// An inference slot, freed when dropped
+pub struct InferencePermit<'a> {
+    scheduler: &'a InferenceScheduler,
+}
+
+impl Drop for InferencePermit<'_> {
+    fn drop(&mut self) {
+        self.scheduler.state().running -= 1;
+        self.scheduler.changed.notify_waiters();
+    }
+}
+
+/// Run `run` on every chunk, up to `parallelism` at once, returning the results in order
+///
+/// Must be called from a blocking task. Each chunk waits for its own inference
+/// slot, so waiting interactive requests still run between a job's chunks.
+/// `on_done` is called with the number of chunks finished so far. Once a chunk
+/// fails no more are started, and the first failure in chunk order is returned.
+pub fn run_chunks<T, R, E>(
+    admission: &Admission,
+    chunks: &[T],
+    parallelism: usize,
+    run: impl Fn(&T) -> Result<R, E> + Sync,
+    on_done: impl Fn(usize) + Sync,
+) -> Result<Vec<R>, E>
+where
+    T: Sync,
+    R: Send,
+    E: Send,
+{
+    let runtime = tokio::runtime::Handle::current();
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Mutex<Vec<Option<Result<R, E>>>> =
+        Mutex::new(chunks.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..parallelism.clamp(1, chunks.len().max(1)) {
+            scope.spawn(|| {
+                while !failed.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(chunk) = chunks.get(index) else {
+                        break;
+                    };
+
+                    let permit = runtime.block_on(admission.acquire());
+                    let result = run(chunk);
+                    drop(permit);
+
+                    if result.is_ok() {
+                        on_done(done.fetch_add(1, Ordering::Relaxed) + 1);
+                    } else {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    // A chunk is only left unstarted after one fails, which is returned first
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn scheduler(slots: usize) -> InferenceScheduler {
+        InferenceScheduler::new(SchedulerConfig {
+            slots,
+            max_parallel_inferences: slots,
+            interactive_depth: 4,
+            batch_depth: 4,
+            retry_after_secs: 1,
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_chunks_in_parallel_keeps_order() {
+        let scheduler = scheduler(3);
+        let result = tokio::task::block_in_place(|| {
+            let admission = scheduler.admit(Priority::Batch).unwrap();
+            let running = AtomicUsize::new(0);
+            let most_running = AtomicUsize::new(0);
+            let finished = AtomicUsize::new(0);
+
+            let result = run_chunks(
+                &admission,
+                &[5u64, 30, 10, 20, 1, 15],
+                3,
+                |&millis| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most_running.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(millis));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok::<_, String>(millis * 2)
+                },
+                |done| {
+                    finished.fetch_max(done, Ordering::SeqCst);
+                },
+            );
+
+            assert!(most_running.load(Ordering::SeqCst) > 1);
+            assert!(most_running.load(Ordering::SeqCst) <= 3);
+            assert_eq!(finished.load(Ordering::SeqCst), 6);
+            result
+        });
+        assert_eq!(result, Ok(vec![10, 60, 20, 40, 2, 30]));
+        assert_eq!(scheduler.state().running, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_chunks_stops_after_a_failure() {
+        let scheduler = scheduler(1);
+        let started = AtomicUsize::new(0);
+        let result = tokio::task::block_in_place(|| {
+            let admission = scheduler.admit(Priority::Batch).unwrap();
+            run_chunks(
+                &admission,
+                &[1, 2, 3, 4],
+                1,
+                |&chunk| {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    if chunk == 2 { Err(chunk) } else { Ok(chunk) }
+                },
+                |_| {},
+            )
+        });
+        assert_eq!(result, Err(2));
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,262 @@
+//! End-to-end check of synthesis, alignment, and scoring, for provisioning new environments
+//!
+//! A short phrase is synthesized, then assessed against its own transcript.
+//! The model reading its own text should score well, so a score under
+//! `SELF_TEST_MIN_SCORE` means some stage is misconfigured even if none failed.
+
+use std::env;
+use std::time::Instant;
+
+use ipa_navigator_kokoro::{
+    normalize::NormalizeOptions,
+    voices::VoiceType,
+    wav::{WavFormat, encode_wav},
+};
+use ipa_navigator_mfa::{
+    docker::MfaDialect,
+    scoring::{Strictness, rubric, score_segments},
+};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::engines::{AssessmentEngine, TtsEngine};
+
+/// Phrase synthesized and assessed
+pub const SELF_TEST_TEXT: &str = "hello world";
+
+/// Voice reading the phrase, in the dialect it is assessed in
+pub const SELF_TEST_VOICE: &str = "american_female_bella";
+
+/// Score required to pass when `SELF_TEST_MIN_SCORE` is not set
+const DEFAULT_MIN_SCORE: f64 = 0.6;
+
+/// Read `SELF_TEST_MIN_SCORE`, between 0.0 and 1.0
+pub fn min_score_from_env() -> f64 {
+    env::var("SELF_TEST_MIN_SCORE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|score| (0.0..=1.0).contains(score))
+        .unwrap_or(DEFAULT_MIN_SCORE)
+}
+
+/// How one stage of the self-test went
+#[derive(Debug, Clone, Serialize)]
+pub struct StageReport {
+    pub stage: &'static str,
+    pub ok: bool,
+    pub millis: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of a self-test, with each stage run until one failed
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub text: &'static str,
+    pub voice: &'static str,
+    /// Overall score of the assessment, if it got that far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    pub min_score: f64,
+    pub total_millis: u64,
+    pub stages: Vec<StageReport>,
+}
+
+/// Synthesize [`SELF_TEST_TEXT`], assess it against itself, and check it scores at least `min_score`
+///
+/// Runs on the calling thread and blocks for the whole check.
+pub fn run_self_test(
+    tts: &dyn TtsEngine,
+    engine: &dyn AssessmentEngine,
+    strictness: Strictness,
+    min_score: f64,
+) -> SelfTestReport {
+    let started = Instant::now();
+    let mut stages = Vec::new();
+    let dialect = MfaDialect::AmericanEnglish;
+
+    let score = (|| {
+        let samples = stage(&mut stages, "synthesize", || {
+            let voice = VoiceType::from_name(SELF_TEST_VOICE)
+                .ok_or_else(|| anyhow::anyhow!("Voice {} is not available", SELF_TEST_VOICE))?;
+            Ok(tts.synthesize(SELF_TEST_TEXT, &voice, 1.0, &NormalizeOptions::default())?)
+        })?;
+        let wav = stage(&mut stages, "encode", || {
+            Ok(encode_wav(&samples, &WavFormat::default())?)
+        })?;
+        let segments = stage(&mut stages, "align", || {
+            engine.align(&wav, SELF_TEST_TEXT, dialect)
+        })?;
+        stage(&mut stages, "score", || {
+            let assessment =
+                score_segments(&segments, SELF_TEST_TEXT, dialect, &rubric(strictness))?;
+            Ok(assessment.overall_score)
+        })
+    })();
+
+    let passed = score.is_some_and(|score| score >= min_score);
+    let report = SelfTestReport {
+        passed,
+        text: SELF_TEST_TEXT,
+        voice: SELF_TEST_VOICE,
+        score,
+        min_score,
+        total_millis: started.elapsed().as_millis() as u64,
+        stages,
+    };
+
+    if passed {
+        info!(
+            "Self-test passed with score {:.2} in {} ms",
+            score.unwrap_or_default(),
+            report.total_millis
+        );
+    } else {
+        warn!("Self-test failed: {:?}", report);
+    }
+    report
+}
+
+/// Run one stage, recording how long it took and whether it failed
+fn stage<T>(
+    stages: &mut Vec<StageReport>,
+    name: &'static str,
+    run: impl FnOnce() -> anyhow::Result<T>,
+) -> Option<T> {
+    let started = Instant::now();
+    let result = run();
+    stages.push(StageReport {
+        stage: name,
+        ok: result.is_ok(),
+        millis: started.elapsed().as_millis() as u64,
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+    });
+    result.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipa_navigator_kokoro::{error::TtsError, tts::Synthesis};
+    use ipa_navigator_mfa::{asr::TranscriptCheck, mfa_parser::MfaSegment};
+
+    /// Engine reading every text as a second of silence
+    struct MockTts;
+
+    impl TtsEngine for MockTts {
+        fn synthesize_until(
+            &self,
+            text: &str,
+            voice: &VoiceType,
+            speed: f32,
+            options: &NormalizeOptions,
+            _deadline: Instant,
+        ) -> Result<Synthesis, TtsError> {
+            Ok(Synthesis {
+                samples: self.synthesize(text, voice, speed, options)?,
+                truncated: false,
+                sentences_synthesized: 1,
+                sentences_total: 1,
+            })
+        }
+
+        fn synthesize(
+            &self,
+            _text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<Vec<f32>, TtsError> {
+            Ok(vec![0.0; 24000])
+        }
+
+        fn is_cached(
+            &self,
+            _text: &str,
+            _voice: &VoiceType,
+            _speed: f32,
+            _options: &NormalizeOptions,
+        ) -> Result<bool, TtsError> {
+            Ok(false)
+        }
+
+        fn synthesize_tokens(
+            &self,
+            _tokens: &[i64],
+            _voice: &VoiceType,
+            _speed: f32,
+        ) -> Result<Vec<f32>, TtsError> {
+            Ok(vec![0.0; 24000])
+        }
+    }
+
+    /// Aligner hearing the given phones, or failing if there are none
+    struct MockAligner(&'static [&'static str]);
+
+    impl AssessmentEngine for MockAligner {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn verify(
+            &self,
+            _audio: &[u8],
+            _transcript: &str,
+        ) -> anyhow::Result<Option<TranscriptCheck>> {
+            Ok(None)
+        }
+
+        fn align(
+            &self,
+            _audio: &[u8],
+            _transcript: &str,
+            _dialect: MfaDialect,
+        ) -> anyhow::Result<Vec<MfaSegment>> {
+            if self.0.is_empty() {
+                anyhow::bail!("MFA is not installed");
+            }
+            Ok(self
+                .0
+                .iter()
+                .enumerate()
+                .map(|(i, label)| MfaSegment {
+                    begin: i as f64 * 0.1,
+                    end: (i + 1) as f64 * 0.1,
+                    label: label.to_string(),
+                    segment_type: "phone".to_string(),
+                    confidence: None,
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_self_test_passes_when_it_hears_itself() {
+        let aligner = MockAligner(&["h", "ə", "l", "ow", "w", "ɝ", "ɫ", "d"]);
+
+        let report = run_self_test(&MockTts, &aligner, Strictness::default(), 0.6);
+
+        assert!(report.passed, "{:?}", report);
+        let stages: Vec<&str> = report.stages.iter().map(|s| s.stage).collect();
+        assert_eq!(stages, ["synthesize", "encode", "align", "score"]);
+        assert!(report.stages.iter().all(|s| s.ok));
+    }
+
+    #[test]
+    fn test_self_test_reports_the_failing_stage() {
+        let report = run_self_test(&MockTts, &MockAligner(&[]), Strictness::default(), 0.6);
+
+        assert!(!report.passed);
+        assert_eq!(report.score, None);
+        let last = report.stages.last().unwrap();
+        assert_eq!(last.stage, "align");
+        assert!(last.error.as_deref().unwrap().contains("not installed"));
+
+        // Stages passing is not enough if the score is too low
+        let garbled = MockAligner(&["k", "æ", "t"]);
+        let report = run_self_test(&MockTts, &garbled, Strictness::default(), 0.6);
+        assert!(!report.passed);
+        assert!(report.score.is_some_and(|score| score < 0.6));
+    }
+}
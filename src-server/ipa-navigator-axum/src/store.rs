@@ -0,0 +1,387 @@
+//! Optional SQLite record of jobs and cached assessments
+//!
+//! Jobs are otherwise only kept in memory for an hour, and Convex may be
+//! unreachable. When `JOB_STORE_PATH` is set, each job's kind, outcome, and
+//! duration, each cached assessment's use, the phoneme scores of each
+//! assessment for research export, and the audit log of operator actions are
+//! written to an embedded database that survives restarts. Writes
+//! happen in the background, and a failed write is logged without affecting
+//! the request.
+
+use std::env;
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::{FromRow, raw_sql};
+use tokio::sync::OnceCell;
+use tracing::warn;
+
+use crate::handlers::mfa::PhonemeAssessmentDetail;
+use crate::jobs::{JobKind, JobStage};
+
+/// Connections kept open to the database
+const MAX_CONNECTIONS: u32 = 4;
+
+/// Most jobs returned by one query
+pub const MAX_QUERY_LIMIT: u32 = 1000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    finished_at INTEGER,
+    duration_ms INTEGER,
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS jobs_created_at ON jobs (created_at);
+CREATE TABLE IF NOT EXISTS cache_entries (
+    key TEXT PRIMARY KEY,
+    stored_at INTEGER NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 0,
+    last_hit_at INTEGER
+);
+CREATE TABLE IF NOT EXISTS phoneme_results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    assessment_id TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    dictionary TEXT NOT NULL,
+    strictness TEXT NOT NULL,
+    engine TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    expected TEXT NOT NULL,
+    actual TEXT NOT NULL,
+    score REAL NOT NULL,
+    start_time REAL NOT NULL,
+    end_time REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS phoneme_results_recorded_at ON phoneme_results (recorded_at);
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recorded_at INTEGER NOT NULL,
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT,
+    payload_digest TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS audit_log_recorded_at ON audit_log (recorded_at);
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN SELECT RAISE(ABORT, 'audit log is append-only'); END;
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN SELECT RAISE(ABORT, 'audit log is append-only'); END;
+";
+
+/// Database at `JOB_STORE_PATH`, or none if it is not set
+pub static JOB_STORE: LazyLock<Option<JobStore>> = LazyLock::new(|| {
+    let path = env::var("JOB_STORE_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())?;
+
+    Some(JobStore::open(&path))
+});
+
+/// A job as recorded in the store
+#[derive(Debug, Clone, FromRow)]
+pub struct JobRecord {
+    pub id: String,
+    pub kind: String,
+    /// Stage the job reached: "queued", "done", or "failed"
+    pub status: String,
+    /// Milliseconds since the Unix epoch
+    pub created_at: i64,
+    pub finished_at: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// An operator action as recorded in the audit log
+#[derive(Debug, Clone, FromRow)]
+pub struct AuditRecord {
+    pub id: i64,
+    /// Milliseconds since the Unix epoch
+    pub recorded_at: i64,
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>,
+    /// Hex SHA-256 of the action's JSON payload
+    pub payload_digest: String,
+}
+
+/// One phoneme of an assessment, as recorded for research export
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct PhonemeResultRecord {
+    pub id: i64,
+    /// Random id shared by the phonemes of one assessment
+    pub assessment_id: String,
+    /// Milliseconds since the Unix epoch
+    pub recorded_at: i64,
+    /// Pronunciation dictionary of the dialect, e.g. "english_us_mfa"
+    pub dictionary: String,
+    pub strictness: String,
+    /// Assessment backend, e.g. "mfa"
+    pub engine: String,
+    /// Index of the phoneme within the assessment, from 0
+    pub position: i64,
+    /// Empty for a phoneme that was added
+    pub expected: String,
+    /// Empty for a phoneme that was left out
+    pub actual: String,
+    pub score: f64,
+    /// Seconds from the start of the recording
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+pub struct JobStore {
+    pool: SqlitePool,
+    schema: OnceCell<()>,
+}
+
+impl JobStore {
+    /// Open the database at `path`, creating it if needed
+    ///
+    /// Connections are made on first use, so this does not touch the file.
+    pub fn open(path: &str) -> Self {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+
+        Self {
+            pool: SqlitePoolOptions::new()
+                .max_connections(MAX_CONNECTIONS)
+                .connect_lazy_with(options),
+            schema: OnceCell::new(),
+        }
+    }
+
+    /// The pool, once the tables exist
+    async fn pool(&self) -> Result<&SqlitePool, sqlx::Error> {
+        self.schema
+            .get_or_try_init(|| async {
+                raw_sql(SCHEMA).execute(&self.pool).await?;
+                Ok::<_, sqlx::Error>(())
+            })
+            .await?;
+        Ok(&self.pool)
+    }
+
+    pub async fn job_created(
+        &self,
+        id: &str,
+        kind: JobKind,
+        created_at: SystemTime,
+    ) -> Result<(), sqlx::Error> {
+        // Writes run concurrently, so a fast job's finish may already be recorded
+        sqlx::query(
+            "INSERT INTO jobs (id, kind, status, created_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(id)
+        .bind(kind.as_str())
+        .bind(JobStage::Queued.as_str())
+        .bind(millis(created_at))
+        .execute(self.pool().await?)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn job_finished(
+        &self,
+        id: &str,
+        kind: JobKind,
+        stage: JobStage,
+        created_at: SystemTime,
+        finished_at: SystemTime,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let duration_ms = finished_at
+            .duration_since(created_at)
+            .map_or(0, |duration| duration.as_millis() as i64);
+
+        sqlx::query(
+            "INSERT INTO jobs (id, kind, status, created_at, finished_at, duration_ms, error)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (id) DO UPDATE SET status = excluded.status,
+                 finished_at = excluded.finished_at, duration_ms = excluded.duration_ms,
+                 error = excluded.error",
+        )
+        .bind(id)
+        .bind(kind.as_str())
+        .bind(stage.as_str())
+        .bind(millis(created_at))
+        .bind(millis(finished_at))
+        .bind(duration_ms)
+        .bind(error)
+        .execute(self.pool().await?)
+        .await?;
+        Ok(())
+    }
+
+    /// Jobs created at or after `since`, in milliseconds since the Unix epoch, oldest first
+    pub async fn jobs_since(&self, since: i64, limit: u32) -> Result<Vec<JobRecord>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, kind, status, created_at, finished_at, duration_ms, error
+             FROM jobs WHERE created_at >= ? ORDER BY created_at LIMIT ?",
+        )
+        .bind(since)
+        .bind(limit.min(MAX_QUERY_LIMIT))
+        .fetch_all(self.pool().await?)
+        .await
+    }
+
+    pub async fn cache_stored(&self, key: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO cache_entries (key, stored_at) VALUES (?, ?)
+             ON CONFLICT (key) DO UPDATE SET stored_at = excluded.stored_at",
+        )
+        .bind(key)
+        .bind(millis(SystemTime::now()))
+        .execute(self.pool().await?)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn cache_hit(&self, key: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE cache_entries SET hits = hits + 1, last_hit_at = ? WHERE key = ?")
+            .bind(millis(SystemTime::now()))
+            .bind(key)
+            .execute(self.pool().await?)
+            .await?;
+        Ok(())
+    }
+
+    /// Record the phoneme scores of an assessment, one row per phoneme
+    pub async fn phonemes_recorded(
+        &self,
+        assessment_id: &str,
+        recorded_at: SystemTime,
+        dictionary: &str,
+        strictness: &str,
+        engine: &str,
+        details: &[PhonemeAssessmentDetail],
+    ) -> Result<(), sqlx::Error> {
+        let mut transaction = self.pool().await?.begin().await?;
+        for (position, detail) in details.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO phoneme_results (assessment_id, recorded_at, dictionary, strictness,
+                     engine, position, expected, actual, score, start_time, end_time)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(assessment_id)
+            .bind(millis(recorded_at))
+            .bind(dictionary)
+            .bind(strictness)
+            .bind(engine)
+            .bind(position as i64)
+            .bind(&detail.expected)
+            .bind(&detail.actual)
+            .bind(detail.score)
+            .bind(detail.start_time)
+            .bind(detail.end_time)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await
+    }
+
+    /// Phoneme results recorded from `from` up to `to`, in milliseconds since the Unix epoch
+    ///
+    /// Returns up to `limit` rows with ids above `after_id`, in id order, so
+    /// an export can page through any number of rows.
+    pub async fn phoneme_results_page(
+        &self,
+        from: i64,
+        to: i64,
+        after_id: i64,
+        limit: u32,
+    ) -> Result<Vec<PhonemeResultRecord>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, assessment_id, recorded_at, dictionary, strictness, engine, position,
+                 expected, actual, score, start_time, end_time
+             FROM phoneme_results WHERE recorded_at >= ? AND recorded_at < ? AND id > ?
+             ORDER BY id LIMIT ?",
+        )
+        .bind(from)
+        .bind(to)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(self.pool().await?)
+        .await
+    }
+
+    /// Append an entry to the audit log; entries can never be changed or removed
+    pub async fn audit_appended(
+        &self,
+        recorded_at: i64,
+        actor: &str,
+        action: &str,
+        target: Option<&str>,
+        payload_digest: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO audit_log (recorded_at, actor, action, target, payload_digest)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(recorded_at)
+        .bind(actor)
+        .bind(action)
+        .bind(target)
+        .bind(payload_digest)
+        .execute(self.pool().await?)
+        .await?;
+        Ok(())
+    }
+
+    /// Audit entries recorded at or after `since`, in milliseconds since the Unix epoch, oldest first
+    pub async fn audit_since(
+        &self,
+        since: i64,
+        action: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<AuditRecord>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, recorded_at, actor, action, target, payload_digest
+             FROM audit_log WHERE recorded_at >= ? AND (? IS NULL OR action = ?)
+             ORDER BY id LIMIT ?",
+        )
+        .bind(since)
+        .bind(action)
+        .bind(action)
+        .bind(limit.min(MAX_QUERY_LIMIT))
+        .fetch_all(self.pool().await?)
+        .await
+    }
+}
+
+/// Run a write against the store in the background, if it is configured
+///
+/// Does nothing outside a Tokio runtime, so jobs can finish on any thread.
+pub fn record<F, Fut>(write: F)
+where
+    F: FnOnce(&'static JobStore) -> Fut,
+    Fut: Future<Output = Result<(), sqlx::Error>> + Send + 'static,
+{
+    let Some(store) = JOB_STORE.as_ref() else {
+        return;
+    };
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+
+    let write = write(store);
+    runtime.spawn(async move {
+        if let Err(e) = write.await {
+            warn!("Failed to write to the job store: {}", e);
+        }
+    });
+}
+
+/// Milliseconds since the Unix epoch
+pub(crate) fn millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
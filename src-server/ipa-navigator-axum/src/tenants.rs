@@ -0,0 +1,428 @@
+//! Tenants sharing one server, each identified by an API key
+//!
+//! One server can serve several frontends, such as different schools. Each is
+//! a tenant listed in the JSON file named by `TENANTS_FILE`, with its own API
+//! key, pronunciation lexicon, allowed voices, request rate, and Convex
+//! deployment:
+//!
+//! ```json
+//! [
+//!   {
+//!     "id": "northside",
+//!     "api_key": "…",
+//!     "voices": ["british_female_emma"],
+//!     "lexicon": { "Ngaio": "Nye oh" },
+//!     "requests_per_minute": 600,
+//!     "convex_url": "https://northside.convex.cloud"
+//!   }
+//! ]
+//! ```
+//!
+//! Requests send the key as `X-Api-Key`, and handlers receive the [`Tenant`].
+//! Settings a tenant leaves out fall back to the server's own. Without a
+//! tenants file the server serves a single frontend and the header is ignored;
+//! with one that fails to load, every API request is refused rather than
+//! served without isolation.
+
+use std::collections::{BTreeMap, HashSet};
+use std::convert::Infallible;
+use std::env;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{OptionalFromRequestParts, Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipa_navigator_kokoro::{normalize::Lexicon, voices::VoiceType};
+use serde::Deserialize;
+
+use crate::auth::constant_time_eq;
+use crate::convex::ConvexHttp;
+use crate::error::Error;
+
+/// Header carrying a tenant's API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Length of the window requests per minute are counted in
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// A tenant as written in the tenants file
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    pub id: String,
+    pub api_key: String,
+    /// Voices the tenant may use, all of them if not set
+    #[serde(default)]
+    pub voices: Option<Vec<String>>,
+    /// Respellings of words, such as local place names
+    #[serde(default)]
+    pub lexicon: BTreeMap<String, String>,
+    /// Requests accepted each minute, unlimited if not set
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// The tenant's Convex deployment, the server's `CONVEX_DEPLOYMENT_URL` if not set
+    #[serde(default)]
+    pub convex_url: Option<String>,
+}
+
+/// A frontend sharing the server, resolved from a request's API key
+#[derive(Clone)]
+pub struct Tenant(Arc<TenantState>);
+
+struct TenantState {
+    id: String,
+    api_key: String,
+    voices: Option<Vec<VoiceType>>,
+    lexicon: Option<Arc<Lexicon>>,
+    rate: Option<RateLimit>,
+    convex: Option<ConvexHttp>,
+}
+
+impl fmt::Debug for Tenant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Tenant").field(&self.0.id).finish()
+    }
+}
+
+impl Tenant {
+    fn new(config: TenantConfig) -> Result<Self, String> {
+        if config.id.trim().is_empty() {
+            return Err("a tenant has no id".to_string());
+        }
+        if config.api_key.trim().is_empty() {
+            return Err(format!("tenant {} has no API key", config.id));
+        }
+
+        let voices = config
+            .voices
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| {
+                        VoiceType::from_name(name).ok_or_else(|| {
+                            format!("tenant {} allows unknown voice {}", config.id, name)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        if voices.as_ref().is_some_and(Vec::is_empty) {
+            return Err(format!("tenant {} allows no voices", config.id));
+        }
+
+        let convex = config
+            .convex_url
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| ConvexHttp::new(&url))
+            .transpose()
+            .map_err(|e| format!("tenant {} has an unusable Convex URL: {}", config.id, e))?;
+
+        let lexicon = Some(Lexicon::new(config.lexicon))
+            .filter(|lexicon| !lexicon.is_empty())
+            .map(Arc::new);
+
+        Ok(Self(Arc::new(TenantState {
+            id: config.id,
+            api_key: config.api_key,
+            voices,
+            lexicon,
+            rate: config
+                .requests_per_minute
+                .map(|per_minute| RateLimit::new(per_minute, RATE_WINDOW)),
+            convex,
+        })))
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    /// Whether the tenant may synthesize with `voice`
+    pub fn allows_voice(&self, voice: &VoiceType) -> bool {
+        self.0
+            .voices
+            .as_ref()
+            .is_none_or(|voices| voices.contains(voice))
+    }
+
+    /// `default` if the tenant may use it, otherwise its first voice in the
+    /// same language, or its first voice of all
+    pub fn voice_or(&self, default: VoiceType) -> VoiceType {
+        match &self.0.voices {
+            Some(voices) if !voices.contains(&default) => voices
+                .iter()
+                .find(|voice| voice.language() == default.language())
+                .or(voices.first())
+                .copied()
+                .unwrap_or(default),
+            _ => default,
+        }
+    }
+
+    pub fn lexicon(&self) -> Option<&Arc<Lexicon>> {
+        self.0.lexicon.as_ref()
+    }
+
+    /// The tenant's own Convex deployment, if it has one
+    pub fn convex(&self) -> Option<&ConvexHttp> {
+        self.0.convex.as_ref()
+    }
+
+    /// Count a request against the tenant's rate, or how long until it may make another
+    fn admit(&self) -> Result<(), Duration> {
+        self.0.rate.as_ref().map_or(Ok(()), RateLimit::admit)
+    }
+}
+
+/// Requests allowed in each fixed window
+struct RateLimit {
+    limit: u32,
+    window: Duration,
+    /// When the current window started and the requests made in it
+    current: Mutex<(Instant, u32)>,
+}
+
+impl RateLimit {
+    fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            current: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    fn admit(&self) -> Result<(), Duration> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let (started, count) = &mut *current;
+        if started.elapsed() >= self.window {
+            *started = Instant::now();
+            *count = 0;
+        }
+
+        if *count >= self.limit {
+            return Err(self.window.saturating_sub(started.elapsed()));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// The tenants a server is shared by
+pub struct Tenants {
+    tenants: Vec<Tenant>,
+    /// Whether requests must name a tenant, false when serving a single frontend
+    required: bool,
+}
+
+impl Tenants {
+    /// A server serving a single frontend
+    pub fn single() -> Self {
+        Self {
+            tenants: Vec::new(),
+            required: false,
+        }
+    }
+
+    /// Tenants from their configs, checking that ids and keys are unique
+    pub fn new(configs: Vec<TenantConfig>) -> Result<Self, String> {
+        let mut ids = HashSet::new();
+        let mut keys = HashSet::new();
+        for config in &configs {
+            if !ids.insert(config.id.as_str()) {
+                return Err(format!("tenant {} is listed twice", config.id));
+            }
+            if !keys.insert(config.api_key.as_str()) {
+                return Err(format!("tenant {} shares another's API key", config.id));
+            }
+        }
+
+        Ok(Self {
+            tenants: configs
+                .into_iter()
+                .map(Tenant::new)
+                .collect::<Result<_, _>>()?,
+            required: true,
+        })
+    }
+
+    /// Tenants from the JSON file named by `TENANTS_FILE`, or a single frontend if it is not set
+    pub fn from_env() -> Self {
+        let Some(path) = env::var("TENANTS_FILE")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+        else {
+            return Self::single();
+        };
+
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .and_then(Self::new);
+        match loaded {
+            Ok(tenants) => {
+                tracing::info!("Loaded {} tenants from {}", tenants.len(), path);
+                tenants
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to load tenants from {}, refusing API requests: {}",
+                    path,
+                    e
+                );
+                Self {
+                    tenants: Vec::new(),
+                    required: true,
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tenants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+
+    /// Whether requests must carry a tenant's API key
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// The tenant with this API key
+    pub fn find(&self, api_key: &str) -> Option<&Tenant> {
+        // Every key is compared, so the time taken does not hint at which matched
+        self.tenants.iter().fold(None, |found, tenant| {
+            let matches = constant_time_eq(api_key.as_bytes(), tenant.0.api_key.as_bytes());
+            found.or(matches.then_some(tenant))
+        })
+    }
+}
+
+impl Default for Tenants {
+    fn default() -> Self {
+        Self::single()
+    }
+}
+
+/// Middleware resolving the request's tenant from its API key and counting it
+/// against the tenant's rate
+pub async fn resolve_tenant(
+    State(tenants): State<Arc<Tenants>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !tenants.is_required() {
+        return next.run(request).await;
+    }
+
+    let tenant = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|key| tenants.find(key))
+        .cloned();
+    let Some(tenant) = tenant else {
+        return Error::Unauthorized("Invalid or missing API key".to_string()).into_response();
+    };
+
+    if let Err(wait) = tenant.admit() {
+        tracing::debug!("Tenant {} is over its request rate", tenant.id());
+        return Error::Busy {
+            message: "Too many requests for this API key, try again later".to_string(),
+            retry_after_secs: wait.as_secs().max(1),
+        }
+        .into_response();
+    }
+
+    request.extensions_mut().insert(tenant);
+    next.run(request).await
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for Tenant {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<Tenant>().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipa_navigator_kokoro::voices::{AmericanFemaleVoice, BritishFemaleVoice};
+
+    fn config(id: &str, api_key: &str) -> TenantConfig {
+        TenantConfig {
+            id: id.to_string(),
+            api_key: api_key.to_string(),
+            voices: None,
+            lexicon: BTreeMap::new(),
+            requests_per_minute: None,
+            convex_url: None,
+        }
+    }
+
+    #[test]
+    fn test_finds_tenants_by_key() {
+        let tenants =
+            Tenants::new(vec![config("north", "key-a"), config("south", "key-b")]).unwrap();
+
+        assert!(tenants.is_required());
+        assert_eq!(tenants.find("key-b").map(Tenant::id), Some("south"));
+        assert!(tenants.find("key-c").is_none());
+        assert!(tenants.find("").is_none());
+        assert!(!Tenants::single().is_required());
+    }
+
+    #[test]
+    fn test_rejects_ambiguous_tenants() {
+        assert!(Tenants::new(vec![config("north", "key"), config("north", "other")]).is_err());
+        assert!(Tenants::new(vec![config("north", "key"), config("south", "key")]).is_err());
+        assert!(Tenants::new(vec![config("north", " ")]).is_err());
+
+        let mut unknown_voice = config("north", "key");
+        unknown_voice.voices = Some(vec!["robot".to_string()]);
+        assert!(Tenants::new(vec![unknown_voice]).is_err());
+    }
+
+    #[test]
+    fn test_restricts_voices() {
+        let emma = VoiceType::BritishFemale(BritishFemaleVoice::Emma);
+        let bella = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
+        let mut restricted = config("north", "key");
+        restricted.voices = Some(vec![emma.name().to_string()]);
+        let tenant = Tenant::new(restricted).unwrap();
+
+        assert!(tenant.allows_voice(&emma));
+        assert!(!tenant.allows_voice(&bella));
+        assert_eq!(tenant.voice_or(bella), emma);
+        assert_eq!(tenant.voice_or(emma), emma);
+
+        let open = Tenant::new(config("south", "other")).unwrap();
+        assert!(open.allows_voice(&bella));
+        assert!(open.lexicon().is_none());
+    }
+
+    #[test]
+    fn test_limits_requests_per_window() {
+        let rate = RateLimit::new(2, Duration::from_secs(60));
+        assert!(rate.admit().is_ok());
+        assert!(rate.admit().is_ok());
+        let wait = rate.admit().unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(60));
+
+        let short = RateLimit::new(1, Duration::ZERO);
+        assert!(short.admit().is_ok());
+        assert!(short.admit().is_ok());
+    }
+}
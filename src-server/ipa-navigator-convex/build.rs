@@ -0,0 +1,448 @@
+//! Generates Rust types for the Convex tables from the frontend's `schema.ts`
+//!
+//! Each table becomes a `<Table>Document` struct with its system fields, and
+//! nested objects and literal unions become their own structs and enums. A
+//! field renamed or retyped in the schema changes the generated type, so Rust
+//! code still using the old shape fails to compile instead of failing at runtime.
+//!
+//! The schema is read from `CONVEX_SCHEMA_PATH`, or `src-web/convex/schema.ts`
+//! in the repository by default.
+
+use std::{env, fmt::Write as _, fs, path::PathBuf};
+
+fn main() {
+    let schema_path = env::var("CONVEX_SCHEMA_PATH").map_or_else(
+        |_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../src-web/convex/schema.ts"),
+        PathBuf::from,
+    );
+    println!("cargo:rerun-if-changed={}", schema_path.display());
+    println!("cargo:rerun-if-env-changed=CONVEX_SCHEMA_PATH");
+
+    let source = fs::read_to_string(&schema_path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read the Convex schema at {} (set CONVEX_SCHEMA_PATH): {}",
+            schema_path.display(),
+            e
+        )
+    });
+    let tables = parse_schema(&source)
+        .unwrap_or_else(|e| panic!("Failed to parse {}: {}", schema_path.display(), e));
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("schema.rs");
+    fs::write(&out, generate(&tables)).unwrap();
+}
+
+/// A Convex validator, e.g. `v.optional(v.id("users"))`
+#[derive(Debug, Clone, PartialEq)]
+enum Validator {
+    String,
+    Number,
+    Boolean,
+    Int64,
+    Null,
+    Any,
+    Bytes,
+    Id(String),
+    Literal(String),
+    Optional(Box<Validator>),
+    Array(Box<Validator>),
+    Union(Vec<Validator>),
+    Object(Vec<(String, Validator)>),
+}
+
+struct Table {
+    name: String,
+    fields: Vec<(String, Validator)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(String),
+    Punct(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '/' {
+            chars.next();
+            match chars.next() {
+                Some('/') => {
+                    for c in chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                Some('*') => {
+                    let mut previous = ' ';
+                    for c in chars.by_ref() {
+                        if previous == '*' && c == '/' {
+                            break;
+                        }
+                        previous = c;
+                    }
+                }
+                _ => tokens.push(Token::Punct('/')),
+            }
+        } else if c == '"' || c == '\'' || c == '`' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('\\') => value.extend(chars.next()),
+                    Some(end) if end == c => break,
+                    Some(other) => value.push(other),
+                    None => return Err("unterminated string".to_string()),
+                }
+            }
+            tokens.push(Token::Str(value));
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_' || c == '$') {
+                    break;
+                }
+                ident.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Ident(ident));
+        } else if c.is_ascii_digit() || c == '-' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.' || c == '-') {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Number(number));
+        } else {
+            tokens.push(Token::Punct(c));
+            chars.next();
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}', found {:?}", c, self.peek()))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            Some(Token::Str(name)) => Ok(name),
+            other => Err(format!("expected a name, found {:?}", other)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Str(value)) => Ok(value),
+            other => Err(format!("expected a string, found {:?}", other)),
+        }
+    }
+
+    /// `{ name: validator, ... }`, allowing a trailing comma
+    fn fields(&mut self) -> Result<Vec<(String, Validator)>, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        while !self.eat('}') {
+            let name = self.ident()?;
+            self.expect(':')?;
+            fields.push((name, self.validator()?));
+            if !self.eat(',') {
+                self.expect('}')?;
+                break;
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Validators separated by commas up to the closing parenthesis
+    fn arguments(&mut self) -> Result<Vec<Validator>, String> {
+        let mut arguments = Vec::new();
+        while !self.eat(')') {
+            arguments.push(self.validator()?);
+            if !self.eat(',') {
+                self.expect(')')?;
+                break;
+            }
+        }
+        Ok(arguments)
+    }
+
+    fn validator(&mut self) -> Result<Validator, String> {
+        let namespace = self.ident()?;
+        if namespace != "v" {
+            return Err(format!("expected a validator, found '{}'", namespace));
+        }
+        self.expect('.')?;
+        let kind = self.ident()?;
+        self.expect('(')?;
+
+        let validator = match kind.as_str() {
+            "id" => Validator::Id(self.string()?),
+            "literal" => Validator::Literal(match self.next() {
+                Some(Token::Str(value) | Token::Number(value) | Token::Ident(value)) => value,
+                other => return Err(format!("expected a literal, found {:?}", other)),
+            }),
+            "object" => Validator::Object(self.fields()?),
+            "optional" | "array" | "union" => {
+                let mut arguments = self.arguments()?;
+                return Ok(match kind.as_str() {
+                    "union" => Validator::Union(arguments),
+                    _ if arguments.len() != 1 => {
+                        return Err(format!("v.{} takes one validator", kind));
+                    }
+                    "optional" => Validator::Optional(Box::new(arguments.remove(0))),
+                    _ => Validator::Array(Box::new(arguments.remove(0))),
+                });
+            }
+            "string" => Validator::String,
+            "number" | "float64" => Validator::Number,
+            "boolean" => Validator::Boolean,
+            "int64" => Validator::Int64,
+            "null" => Validator::Null,
+            "any" => Validator::Any,
+            "bytes" => Validator::Bytes,
+            other => return Err(format!("unsupported validator v.{}", other)),
+        };
+        self.expect(')')?;
+        Ok(validator)
+    }
+}
+
+/// Find every `name: defineTable({ ... })` and parse its fields
+fn parse_schema(source: &str) -> Result<Vec<Table>, String> {
+    let tokens = tokenize(source)?;
+    let mut tables = Vec::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        if *token != Token::Ident("defineTable".to_string()) || index < 2 {
+            continue;
+        }
+        if tokens[index - 1] != Token::Punct(':') {
+            continue;
+        }
+        let name = match &tokens[index - 2] {
+            Token::Ident(name) | Token::Str(name) => name.clone(),
+            _ => continue,
+        };
+
+        let mut parser = Parser {
+            tokens: tokens[index + 1..].to_vec(),
+            position: 0,
+        };
+        parser.expect('(')?;
+        let fields = parser
+            .fields()
+            .map_err(|e| format!("table {}: {}", name, e))?;
+        tables.push(Table { name, fields });
+    }
+
+    if tables.is_empty() {
+        return Err("no tables found".to_string());
+    }
+    Ok(tables)
+}
+
+/// "excerpt_practice" or "phoneme_counts" as "ExcerptPractice" or "PhonemeCounts"
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect()
+}
+
+/// "tokenIdentifier" as "token_identifier"
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    match snake.as_str() {
+        "type" | "ref" | "match" | "move" | "self" | "struct" | "enum" => format!("r#{}", snake),
+        _ => snake,
+    }
+}
+
+/// Rust code for the types, with nested structs and enums collected in `items`
+struct Generator {
+    items: String,
+}
+
+impl Generator {
+    /// The Rust type of a validator, generating any struct or enum it needs named after `path`
+    fn rust_type(&mut self, validator: &Validator, path: &str) -> String {
+        match validator {
+            Validator::String => "String".to_string(),
+            Validator::Number => "f64".to_string(),
+            Validator::Boolean => "bool".to_string(),
+            Validator::Int64 => "i64".to_string(),
+            Validator::Null => "()".to_string(),
+            Validator::Any | Validator::Literal(_) => "serde_json::Value".to_string(),
+            Validator::Bytes => "Vec<u8>".to_string(),
+            Validator::Id(table) => format!("Id<tables::{}>", pascal_case(table)),
+            Validator::Optional(inner) => format!("Option<{}>", self.rust_type(inner, path)),
+            Validator::Array(inner) => format!("Vec<{}>", self.rust_type(inner, path)),
+            Validator::Union(variants) => {
+                let literals: Option<Vec<&String>> = variants
+                    .iter()
+                    .map(|variant| match variant {
+                        Validator::Literal(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect();
+                match literals {
+                    Some(literals) => {
+                        self.literal_enum(path, &literals);
+                        path.to_string()
+                    }
+                    // Unions of other shapes are left for the caller to interpret
+                    None => "serde_json::Value".to_string(),
+                }
+            }
+            Validator::Object(fields) => {
+                self.object_struct(path, fields, false);
+                path.to_string()
+            }
+        }
+    }
+
+    fn literal_enum(&mut self, name: &str, literals: &[&String]) {
+        let mut item = String::new();
+        writeln!(
+            item,
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]"
+        )
+        .unwrap();
+        writeln!(item, "pub enum {} {{", name).unwrap();
+        for literal in literals {
+            writeln!(item, "    #[serde(rename = {:?})]", literal).unwrap();
+            writeln!(item, "    {},", pascal_case(literal)).unwrap();
+        }
+        writeln!(item, "}}\n").unwrap();
+        self.items.push_str(&item);
+    }
+
+    fn object_struct(&mut self, name: &str, fields: &[(String, Validator)], document: bool) {
+        let mut item = String::new();
+        writeln!(
+            item,
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]"
+        )
+        .unwrap();
+        writeln!(item, "pub struct {} {{", name).unwrap();
+        if document {
+            let table = name.trim_end_matches("Document");
+            writeln!(item, "    #[serde(rename = \"_id\")]").unwrap();
+            writeln!(item, "    pub id: Id<tables::{}>,", table).unwrap();
+            writeln!(item, "    #[serde(rename = \"_creationTime\")]").unwrap();
+            writeln!(item, "    pub creation_time: f64,").unwrap();
+        }
+        for (field, validator) in fields {
+            let field_type = self.rust_type(
+                validator,
+                &format!(
+                    "{}{}",
+                    name.trim_end_matches("Document"),
+                    pascal_case(field)
+                ),
+            );
+            let rust_name = snake_case(field);
+            if rust_name.trim_start_matches("r#") != field {
+                writeln!(item, "    #[serde(rename = {:?})]", field).unwrap();
+            }
+            if matches!(validator, Validator::Optional(_)) {
+                writeln!(
+                    item,
+                    "    #[serde(default, skip_serializing_if = \"Option::is_none\")]"
+                )
+                .unwrap();
+            }
+            writeln!(item, "    pub {}: {},", rust_name, field_type).unwrap();
+        }
+        writeln!(item, "}}\n").unwrap();
+        self.items.push_str(&item);
+    }
+}
+
+fn generate(tables: &[Table]) -> String {
+    let mut generator = Generator {
+        items: String::new(),
+    };
+    for table in tables {
+        generator.object_struct(
+            &format!("{}Document", pascal_case(&table.name)),
+            &table.fields,
+            true,
+        );
+    }
+
+    let mut markers =
+        String::from("/// Marker types naming each table, for typed ids\npub mod tables {\n");
+    for table in tables {
+        writeln!(markers, "    /// The `{}` table", table.name).unwrap();
+        writeln!(
+            markers,
+            "    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\n    pub enum {} {{}}",
+            pascal_case(&table.name)
+        )
+        .unwrap();
+    }
+    markers.push_str("    /// File storage\n    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\n    pub enum Storage {}\n}\n\n");
+
+    format!(
+        "// Generated by build.rs from the Convex schema; do not edit.\n\n{}{}",
+        markers, generator.items
+    )
+}
@@ -0,0 +1,103 @@
+//! Typed wrappers for the Convex functions the server calls
+
+use anyhow::{Error, anyhow};
+use convex::{ConvexClient, FunctionResult, Value};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::schema::{Id, Lesson, tables};
+
+/// Convert arguments into the map of Convex values a function takes
+fn arguments(args: impl Serialize) -> Result<BTreeMap<String, Value>, Error> {
+    match serde_json::to_value(args)? {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(name, value)| Ok((name, Value::try_from(value)?)))
+            .collect(),
+        other => Err(anyhow!(
+            "Function arguments must be an object, got {}",
+            other
+        )),
+    }
+}
+
+/// Deserialize a function's result, or turn its error into ours
+fn output<T: DeserializeOwned>(name: &str, result: FunctionResult) -> Result<T, Error> {
+    match result {
+        FunctionResult::Value(value) => serde_json::from_value(serde_json::Value::from(value))
+            .map_err(|e| anyhow!("{} returned an unexpected value: {}", name, e)),
+        FunctionResult::ErrorMessage(message) => Err(anyhow!("{} failed: {}", name, message)),
+        FunctionResult::ConvexError(e) => Err(anyhow!("{} failed: {}", name, e.message)),
+    }
+}
+
+pub async fn query<T: DeserializeOwned>(
+    client: &mut ConvexClient,
+    name: &str,
+    args: impl Serialize,
+) -> Result<T, Error> {
+    let result = client.query(name, arguments(args)?).await?;
+    output(name, result)
+}
+
+pub async fn mutation<T: DeserializeOwned>(
+    client: &mut ConvexClient,
+    name: &str,
+    args: impl Serialize,
+) -> Result<T, Error> {
+    let result = client.mutation(name, arguments(args)?).await?;
+    output(name, result)
+}
+
+pub mod users {
+    use super::*;
+
+    /// Record the signed-in user, returning their id
+    pub async fn store(client: &mut ConvexClient) -> Result<Id<tables::Users>, Error> {
+        mutation(client, "functions/users:store", json!({})).await
+    }
+
+    pub async fn preferred_tts_voice(client: &mut ConvexClient) -> Result<Option<String>, Error> {
+        query(client, "functions/users:getPreferredTTSVoice", json!({})).await
+    }
+
+    pub async fn set_preferred_tts_voice(
+        client: &mut ConvexClient,
+        voice: &str,
+    ) -> Result<(), Error> {
+        mutation(
+            client,
+            "functions/users:setPreferredTTSVoice",
+            json!({ "voice": voice }),
+        )
+        .await
+    }
+}
+
+pub mod lessons {
+    use super::*;
+
+    pub async fn get(client: &mut ConvexClient, id: &Id<tables::Chapter>) -> Result<Lesson, Error> {
+        query(
+            client,
+            "functions/chapters:getChapter",
+            json!({ "chapterId": id }),
+        )
+        .await
+    }
+
+    /// Lessons that have not been revoked, by name
+    pub async fn list(client: &mut ConvexClient) -> Result<Vec<Lesson>, Error> {
+        query(client, "functions/chapters:getChapters", json!({})).await
+    }
+
+    pub async fn revoke(client: &mut ConvexClient, id: &Id<tables::Chapter>) -> Result<(), Error> {
+        mutation(
+            client,
+            "functions/chapters:revokeChapter",
+            json!({ "chapterId": id }),
+        )
+        .await
+    }
+}
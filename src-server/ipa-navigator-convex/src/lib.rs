@@ -1,4 +1,6 @@
 pub mod config;
+pub mod functions;
 pub mod routes;
+pub mod schema;
 
 pub use routes::create_client;
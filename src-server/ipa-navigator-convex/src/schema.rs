@@ -0,0 +1,68 @@
+//! Typed documents of the Convex tables
+//!
+//! The types are generated by `build.rs` from `src-web/convex/schema.ts`, so
+//! they always match the deployed schema.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Id of a document in the table `T`, e.g. `Id<tables::Users>`
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Id<T> {
+    id: String,
+    #[serde(skip)]
+    table: PhantomData<T>,
+}
+
+impl<T> Id<T> {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            table: PhantomData,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+}
+
+// Implemented by hand since the table markers are never instantiated
+impl<T> Clone for Id<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.id.clone())
+    }
+}
+
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> fmt::Debug for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Id").field(&self.id).finish()
+    }
+}
+
+impl<T> fmt::Display for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/schema.rs"));
+
+/// A learner's account
+pub type User = UsersDocument;
+
+/// A lesson, stored as a chapter of excerpts
+pub type Lesson = ChapterDocument;
+
+/// One attempt at reading an excerpt aloud
+pub type Attempt = ExcerptPracticeDocument;
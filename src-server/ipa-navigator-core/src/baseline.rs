@@ -0,0 +1,277 @@
+//! Personal baselines calibrated from a speaker's first attempts
+//!
+//! A learner's accent shows up as systematic deviations, such as always
+//! shortening /iː/ to /i/. Once enough attempts are scored, each phoneme's
+//! usual score and realization are recorded, and later attempts can be scored
+//! relative to them as well as to the absolute standard: 0.5 means a phoneme
+//! was pronounced as well as usual, 1 means it was pronounced perfectly, and
+//! 0 means it was missed entirely.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Attempts needed before a baseline is calibrated
+pub const MIN_CALIBRATION_ATTEMPTS: usize = 5;
+
+/// Times a phoneme must be heard before it gets a baseline of its own
+pub const MIN_PHONEME_OBSERVATIONS: usize = 3;
+
+/// Share of observations a realization needs to count as systematic
+pub const SYSTEMATIC_SHARE: f64 = 0.5;
+
+/// A phoneme scored in one attempt
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredPhoneme {
+    /// The phoneme that should have been said, empty for an insertion
+    pub expected: String,
+    /// The phoneme that was heard, empty for an omission
+    pub actual: String,
+    /// Score from 0 to 1
+    pub score: f64,
+}
+
+/// How a speaker usually pronounces one phoneme
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhonemeBaseline {
+    /// Times the phoneme was expected during calibration
+    pub observations: usize,
+    /// Mean score of those observations
+    pub mean_score: f64,
+    /// The phoneme usually heard instead, and the share of observations it was
+    /// heard in, when it was heard in at least [`SYSTEMATIC_SHARE`] of them
+    pub usual_realization: Option<(String, f64)>,
+}
+
+/// A phoneme the speaker systematically pronounces differently
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deviation<'a> {
+    pub phoneme: &'a str,
+    /// What is usually heard instead, empty when the phoneme is usually left out
+    pub usual_realization: &'a str,
+    /// Share of observations the realization was heard in
+    pub share: f64,
+    pub mean_score: f64,
+}
+
+/// An attempt scored against a speaker's baseline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PersonalizedScore {
+    /// Score relative to the baseline, where 0.5 is the speaker's usual level
+    pub score: f64,
+    /// Mean score the speaker usually gets for the attempt's phonemes
+    pub baseline: f64,
+}
+
+/// A speaker's usual scores, calibrated from their earliest attempts
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerBaseline {
+    /// Attempts the baseline was calibrated from
+    pub attempts: usize,
+    /// Mean score of every phoneme in those attempts
+    pub mean_score: f64,
+    /// Baselines of phonemes observed often enough, by expected phoneme
+    pub phonemes: BTreeMap<String, PhonemeBaseline>,
+}
+
+impl SpeakerBaseline {
+    /// Calibrate a baseline from scored attempts, or none if there are fewer
+    /// than [`MIN_CALIBRATION_ATTEMPTS`] or they contain no phonemes
+    pub fn calibrate(attempts: &[Vec<ScoredPhoneme>]) -> Option<Self> {
+        if attempts.len() < MIN_CALIBRATION_ATTEMPTS {
+            return None;
+        }
+
+        let mut total = 0.0;
+        let mut count = 0usize;
+        // Sum of scores, observations, and counts of each realization
+        let mut observed: BTreeMap<&str, (f64, usize, BTreeMap<&str, usize>)> = BTreeMap::new();
+        for phoneme in attempts.iter().flatten() {
+            total += phoneme.score;
+            count += 1;
+            if phoneme.expected.is_empty() {
+                continue;
+            }
+            let (sum, observations, realizations) = observed.entry(&phoneme.expected).or_default();
+            *sum += phoneme.score;
+            *observations += 1;
+            *realizations.entry(&phoneme.actual).or_default() += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+
+        let phonemes = observed
+            .into_iter()
+            .filter(|(_, (_, observations, _))| *observations >= MIN_PHONEME_OBSERVATIONS)
+            .map(|(expected, (sum, observations, realizations))| {
+                let usual_realization = realizations
+                    .into_iter()
+                    .filter(|(actual, _)| *actual != expected)
+                    .max_by_key(|(_, heard)| *heard)
+                    .map(|(actual, heard)| (actual, heard as f64 / observations as f64))
+                    .filter(|(_, share)| *share >= SYSTEMATIC_SHARE)
+                    .map(|(actual, share)| (String::from(actual), share));
+                let baseline = PhonemeBaseline {
+                    observations,
+                    mean_score: sum / observations as f64,
+                    usual_realization,
+                };
+                (String::from(expected), baseline)
+            })
+            .collect();
+
+        Some(Self {
+            attempts: attempts.len(),
+            mean_score: total / count as f64,
+            phonemes,
+        })
+    }
+
+    /// The score the speaker usually gets for a phoneme, falling back to their
+    /// mean score for phonemes observed too rarely
+    pub fn expected_score(&self, phoneme: &str) -> f64 {
+        self.phonemes
+            .get(phoneme)
+            .map_or(self.mean_score, |baseline| baseline.mean_score)
+    }
+
+    /// Phonemes the speaker systematically realizes as another, worst first
+    pub fn deviations(&self) -> Vec<Deviation<'_>> {
+        let mut deviations: Vec<_> = self
+            .phonemes
+            .iter()
+            .filter_map(|(phoneme, baseline)| {
+                let (actual, share) = baseline.usual_realization.as_ref()?;
+                Some(Deviation {
+                    phoneme,
+                    usual_realization: actual,
+                    share: *share,
+                    mean_score: baseline.mean_score,
+                })
+            })
+            .collect();
+        deviations.sort_by(|a, b| a.mean_score.total_cmp(&b.mean_score));
+        deviations
+    }
+
+    /// Score an attempt against the baseline, or none if it has no phonemes
+    pub fn score(&self, phonemes: &[ScoredPhoneme]) -> Option<PersonalizedScore> {
+        if phonemes.is_empty() {
+            return None;
+        }
+
+        let (credit, baseline) = phonemes.iter().fold((0.0, 0.0), |(credit, baseline), p| {
+            let usual = self.expected_score(&p.expected);
+            (credit + relative_credit(p.score, usual), baseline + usual)
+        });
+        let n = phonemes.len() as f64;
+
+        Some(PersonalizedScore {
+            score: credit / n,
+            baseline: baseline / n,
+        })
+    }
+}
+
+/// Credit for scoring `score` where `usual` is expected, rising linearly from
+/// 0 to 0.5 at `usual` and on to 1 at a perfect score
+fn relative_credit(score: f64, usual: f64) -> f64 {
+    let score = score.clamp(0.0, 1.0);
+    let usual = usual.clamp(0.0, 1.0);
+    if score >= usual {
+        if usual >= 1.0 {
+            1.0
+        } else {
+            0.5 + 0.5 * (score - usual) / (1.0 - usual)
+        }
+    } else {
+        0.5 * score / usual
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn scored(expected: &str, actual: &str, score: f64) -> ScoredPhoneme {
+        ScoredPhoneme {
+            expected: expected.into(),
+            actual: actual.into(),
+            score,
+        }
+    }
+
+    /// An attempt at "sheep" with the vowel shortened to /i/
+    fn short_vowel_attempt() -> Vec<ScoredPhoneme> {
+        vec![
+            scored("ʃ", "ʃ", 1.0),
+            scored("iː", "i", 0.6),
+            scored("p", "p", 1.0),
+        ]
+    }
+
+    #[test]
+    fn test_calibration_needs_enough_attempts() {
+        let attempts = vec![short_vowel_attempt(); MIN_CALIBRATION_ATTEMPTS - 1];
+        assert_eq!(SpeakerBaseline::calibrate(&attempts), None);
+
+        let attempts = vec![Vec::new(); MIN_CALIBRATION_ATTEMPTS];
+        assert_eq!(SpeakerBaseline::calibrate(&attempts), None);
+    }
+
+    #[test]
+    fn test_finds_systematic_deviations() {
+        let mut attempts = vec![short_vowel_attempt(); MIN_CALIBRATION_ATTEMPTS - 1];
+        attempts.push(vec![
+            scored("ʃ", "s", 0.7),
+            scored("iː", "iː", 1.0),
+            scored("p", "p", 1.0),
+        ]);
+        let baseline = SpeakerBaseline::calibrate(&attempts).unwrap();
+
+        assert_eq!(baseline.attempts, MIN_CALIBRATION_ATTEMPTS);
+        let deviations = baseline.deviations();
+        assert_eq!(deviations.len(), 1);
+        assert_eq!(deviations[0].phoneme, "iː");
+        assert_eq!(deviations[0].usual_realization, "i");
+        assert!((deviations[0].share - 0.8).abs() < 1e-9);
+        assert!((baseline.expected_score("iː") - 0.68).abs() < 1e-9);
+        // A one-off /s/ is not systematic
+        assert_eq!(baseline.phonemes["ʃ"].usual_realization, None);
+    }
+
+    #[test]
+    fn test_rare_phonemes_use_the_mean_score() {
+        let attempts = vec![short_vowel_attempt(); MIN_CALIBRATION_ATTEMPTS];
+        let baseline = SpeakerBaseline::calibrate(&attempts).unwrap();
+
+        assert!(!baseline.phonemes.contains_key("θ"));
+        assert!((baseline.expected_score("θ") - baseline.mean_score).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_scores_relative_to_the_baseline() {
+        let attempts = vec![short_vowel_attempt(); MIN_CALIBRATION_ATTEMPTS];
+        let baseline = SpeakerBaseline::calibrate(&attempts).unwrap();
+
+        // Pronouncing the vowel as usual scores the baseline's midpoint
+        let usual = baseline.score(&[scored("iː", "i", 0.6)]).unwrap();
+        assert!((usual.score - 0.5).abs() < 1e-9);
+        assert!((usual.baseline - 0.6).abs() < 1e-9);
+
+        // Closing half the gap to a perfect vowel shows progress
+        let better = baseline.score(&[scored("iː", "iː", 0.8)]).unwrap();
+        assert!((better.score - 0.75).abs() < 1e-9);
+
+        let worse = baseline.score(&[scored("iː", "", 0.0)]).unwrap();
+        assert_eq!(worse.score, 0.0);
+
+        // A perfect consonant the speaker always gets right stays perfect
+        let perfect = baseline.score(&[scored("p", "p", 1.0)]).unwrap();
+        assert_eq!(perfect.score, 1.0);
+
+        assert_eq!(baseline.score(&[]), None);
+    }
+}
@@ -0,0 +1,269 @@
+//! Layout of the IPA chart, derived from the phoneme feature table
+//!
+//! Consonants sit in a grid of manners by places, voiceless on the left of a
+//! cell and voiced on the right. Vowels sit on the trapezoid, with `x` running
+//! from front (0) to back (1) and `y` from close (0) to open (1); the feature
+//! table has three heights, so near-close and near-open vowels share the
+//! close and open rows. Since every position comes from the features the
+//! scorer compares, the chart cannot disagree with scoring.
+
+use alloc::vec::Vec;
+
+use crate::phoneme::{
+    Backness, DIPHTHONG_TARGETS, Height, IPA_PHONEME_FEATURES, Manner, PhonemeFeatures, Place,
+};
+
+/// Rows of the consonant grid, top to bottom, as a manner and whether it is lateral
+pub const CONSONANT_ROWS: [(Manner, bool); 6] = [
+    (Manner::Plosive, false),
+    (Manner::Nasal, false),
+    (Manner::Fricative, false),
+    (Manner::Affricate, false),
+    (Manner::Approximant, false),
+    (Manner::Approximant, true),
+];
+
+/// Columns of the consonant grid, front of the mouth to back
+pub const CONSONANT_COLUMNS: [Place; 8] = [
+    Place::Bilabial,
+    Place::Labiodental,
+    Place::Dental,
+    Place::Alveolar,
+    Place::Postalveolar,
+    Place::Palatal,
+    Place::Velar,
+    Place::Glottal,
+];
+
+/// Entries of the feature table that are not sounds of their own
+const UNCHARTED: &[&str] = &[
+    // ASCII g, charted as the IPA ɡ
+    "g", // Placeholder for phones the aligner could not identify
+    "unknown",
+];
+
+/// A consonant's place in the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsonantCell {
+    pub symbol: &'static str,
+    /// Index into [`CONSONANT_ROWS`]
+    pub row: usize,
+    /// Index into [`CONSONANT_COLUMNS`]
+    pub column: usize,
+    /// Voiced consonants go on the right of the cell
+    pub voiced: bool,
+}
+
+/// A vowel's position on the trapezoid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VowelPoint {
+    pub symbol: &'static str,
+    pub x: f64,
+    pub y: f64,
+    /// Rounded vowels go on the right of their point
+    pub rounded: bool,
+}
+
+/// A diphthong drawn as a glide between two vowels of the trapezoid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiphthongGlide {
+    pub symbol: &'static str,
+    pub onset: &'static str,
+    pub offset: &'static str,
+}
+
+/// A diacritic the scorer understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Diacritic {
+    pub mark: char,
+    /// What the mark adds, e.g. "aspirated"
+    pub name: &'static str,
+    /// The mark on a typical symbol
+    pub example: &'static str,
+}
+
+/// Diacritics read by [`crate::phoneme::parse_ipa`]
+pub const DIACRITICS: &[Diacritic] = &[
+    Diacritic {
+        mark: 'ː',
+        name: "long",
+        example: "iː",
+    },
+    Diacritic {
+        mark: 'ʰ',
+        name: "aspirated",
+        example: "pʰ",
+    },
+    Diacritic {
+        mark: '\u{0303}',
+        name: "nasalized",
+        example: "a\u{0303}",
+    },
+    Diacritic {
+        mark: 'ʲ',
+        name: "palatalized",
+        example: "tʲ",
+    },
+    Diacritic {
+        mark: 'ʷ',
+        name: "labialized",
+        example: "kʷ",
+    },
+    Diacritic {
+        mark: '\u{0329}',
+        name: "syllabic",
+        example: "n\u{0329}",
+    },
+    Diacritic {
+        mark: '\u{032A}',
+        name: "dental",
+        example: "t\u{032A}",
+    },
+    Diacritic {
+        mark: '\u{0325}',
+        name: "voiceless",
+        example: "n\u{0325}",
+    },
+    Diacritic {
+        mark: '\u{032C}',
+        name: "voiced",
+        example: "s\u{032C}",
+    },
+];
+
+/// Symbols of the feature table to chart, with their features
+fn charted() -> impl Iterator<Item = (&'static str, &'static PhonemeFeatures)> {
+    IPA_PHONEME_FEATURES
+        .iter()
+        .filter(|(symbol, _)| !UNCHARTED.contains(symbol))
+        .map(|(symbol, features)| (*symbol, features))
+}
+
+/// Every consonant in the feature table, in table order
+pub fn consonants() -> Vec<ConsonantCell> {
+    charted()
+        .filter(|(_, features)| !features.is_vowel())
+        .filter_map(|(symbol, features)| {
+            let (manner, place) = (features.manner()?, features.place()?);
+            let row = CONSONANT_ROWS
+                .iter()
+                .position(|&row| row == (manner, features.is_lateral()))?;
+            let column = CONSONANT_COLUMNS.iter().position(|&p| p == place)?;
+            Some(ConsonantCell {
+                symbol,
+                row,
+                column,
+                voiced: features.is_voiced(),
+            })
+        })
+        .collect()
+}
+
+/// Every monophthong in the feature table, in table order
+pub fn vowels() -> Vec<VowelPoint> {
+    charted()
+        .filter(|(_, features)| features.is_vowel())
+        .filter_map(|(symbol, features)| {
+            let (x, y) = vowel_position(features.height()?, features.backness()?);
+            Some(VowelPoint {
+                symbol,
+                x,
+                y,
+                rounded: features.is_rounded(),
+            })
+        })
+        .collect()
+}
+
+/// Diphthongs and the vowels they glide between
+pub fn diphthongs() -> Vec<DiphthongGlide> {
+    DIPHTHONG_TARGETS
+        .iter()
+        .map(|&(symbol, onset, offset)| DiphthongGlide {
+            symbol,
+            onset,
+            offset,
+        })
+        .collect()
+}
+
+/// Point on the trapezoid for a height and backness
+///
+/// The front edge slants in towards the bottom, so open vowels are half as
+/// far apart as close ones, while the back edge is vertical.
+pub fn vowel_position(height: Height, backness: Backness) -> (f64, f64) {
+    let y = match height {
+        Height::Close => 0.0,
+        Height::Mid => 0.5,
+        Height::Open => 1.0,
+    };
+    let across = match backness {
+        Backness::Front => 0.0,
+        Backness::Central => 0.5,
+        Backness::Back => 1.0,
+    };
+    let front_edge = 0.5 * y;
+
+    (front_edge + (1.0 - front_edge) * across, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phoneme::parse_ipa;
+    use alloc::string::String;
+
+    #[test]
+    fn test_places_consonants_in_the_grid() {
+        let cells = consonants();
+        let cell = |symbol: &str| cells.iter().find(|cell| cell.symbol == symbol).copied();
+
+        let theta = cell("θ").unwrap();
+        assert_eq!(CONSONANT_ROWS[theta.row], (Manner::Fricative, false));
+        assert_eq!(CONSONANT_COLUMNS[theta.column], Place::Dental);
+        assert!(!theta.voiced);
+
+        let l = cell("l").unwrap();
+        assert_eq!(CONSONANT_ROWS[l.row], (Manner::Approximant, true));
+
+        assert!(cell("ɡ").is_some());
+        assert_eq!(cell("g"), None);
+    }
+
+    #[test]
+    fn test_charts_every_phoneme_the_scorer_knows() {
+        let charted = consonants().len() + vowels().len() + UNCHARTED.len();
+        assert_eq!(charted, IPA_PHONEME_FEATURES.len());
+
+        for glide in diphthongs() {
+            assert!(vowels().iter().any(|vowel| vowel.symbol == glide.onset));
+            assert!(vowels().iter().any(|vowel| vowel.symbol == glide.offset));
+        }
+    }
+
+    #[test]
+    fn test_vowel_trapezoid() {
+        assert_eq!(vowel_position(Height::Close, Backness::Front), (0.0, 0.0));
+        assert_eq!(vowel_position(Height::Open, Backness::Front), (0.5, 1.0));
+        assert_eq!(vowel_position(Height::Open, Backness::Back), (1.0, 1.0));
+        assert_eq!(vowel_position(Height::Mid, Backness::Central), (0.625, 0.5));
+    }
+
+    #[test]
+    fn test_diacritics_are_understood_by_the_scorer() {
+        for diacritic in DIACRITICS {
+            let base: String = diacritic
+                .example
+                .chars()
+                .filter(|&c| c != diacritic.mark)
+                .collect();
+            assert!(diacritic.example.contains(diacritic.mark));
+            assert_eq!(
+                parse_ipa(diacritic.example).map(|parsed| parsed.base),
+                Some(base),
+                "{}",
+                diacritic.name
+            );
+        }
+    }
+}
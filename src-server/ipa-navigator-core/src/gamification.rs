@@ -0,0 +1,363 @@
+//! Practice streaks, weekly leaderboards, and badges computed from attempts
+//!
+//! Days run midnight to midnight in GMT+8, the time zone of the daily reward,
+//! and weeks start on Monday. Times are milliseconds since the Unix epoch.
+
+use alloc::vec::Vec;
+
+/// Milliseconds in a day
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Offset of GMT+8 from UTC, in milliseconds
+const UTC_OFFSET_MS: i64 = 8 * 60 * 60 * 1000;
+
+/// Attempts needed before average accuracy badges are awarded
+const MIN_ACCURACY_BADGE_ATTEMPTS: usize = 10;
+
+/// A scored practice attempt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attempt {
+    /// When the attempt was made, in milliseconds since the Unix epoch
+    pub timestamp_ms: i64,
+    /// Overall accuracy from 0 to 1
+    pub accuracy: f64,
+}
+
+/// Days since 1970-01-01 in GMT+8
+pub fn day_of(timestamp_ms: i64) -> i64 {
+    (timestamp_ms + UTC_OFFSET_MS).div_euclid(DAY_MS)
+}
+
+/// Start of a day, in milliseconds since the Unix epoch
+pub fn day_start(day: i64) -> i64 {
+    day * DAY_MS - UTC_OFFSET_MS
+}
+
+/// Weeks since the Monday before 1970-01-01, which was a Thursday
+pub fn week_of(day: i64) -> i64 {
+    (day + 3).div_euclid(7)
+}
+
+/// First day of a week
+pub fn week_start(week: i64) -> i64 {
+    week * 7 - 3
+}
+
+/// Year, month, and day of the month of a day
+pub fn civil_date(day: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's days-to-civil algorithm, with eras of 400 years
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day_of_month)
+}
+
+/// Consecutive days with at least one attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Streak {
+    /// Run ending today, or yesterday if today has no attempt yet
+    pub current: u32,
+    pub longest: u32,
+    pub last_practice_day: Option<i64>,
+}
+
+/// The streaks formed by the attempts, as of `today`
+pub fn streak(attempts: &[Attempt], today: i64) -> Streak {
+    let mut days: Vec<i64> = attempts
+        .iter()
+        .map(|attempt| day_of(attempt.timestamp_ms))
+        .filter(|&day| day <= today)
+        .collect();
+    days.sort_unstable();
+    days.dedup();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous = None;
+    for &day in &days {
+        run = if previous == Some(day - 1) {
+            run + 1
+        } else {
+            1
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    // A streak survives until a whole day passes without practice
+    let current = match previous {
+        Some(last) if last >= today - 1 => run,
+        _ => 0,
+    };
+
+    Streak {
+        current,
+        longest,
+        last_practice_day: previous,
+    }
+}
+
+/// A user's standing in a week's leaderboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry<K> {
+    /// Position from 1; users with equal accuracy share a rank
+    pub rank: usize,
+    pub user: K,
+    pub average_accuracy: f64,
+    pub attempts: usize,
+}
+
+/// Rank users by their average accuracy during `week`
+///
+/// Users with fewer than `min_attempts` attempts that week are left out, so a
+/// single lucky attempt cannot top the board. Equal averages are ordered by
+/// attempts, most first, then by user.
+pub fn weekly_leaderboard<K: Ord + Clone>(
+    users: &[(K, Vec<Attempt>)],
+    week: i64,
+    min_attempts: usize,
+) -> Vec<LeaderboardEntry<K>> {
+    let mut entries: Vec<LeaderboardEntry<K>> = users
+        .iter()
+        .filter_map(|(user, attempts)| {
+            let (total, count) = attempts
+                .iter()
+                .filter(|attempt| week_of(day_of(attempt.timestamp_ms)) == week)
+                .fold((0.0, 0), |(total, count), attempt| {
+                    (total + attempt.accuracy, count + 1)
+                });
+
+            (count >= min_attempts.max(1)).then(|| LeaderboardEntry {
+                rank: 0,
+                user: user.clone(),
+                average_accuracy: total / count as f64,
+                attempts: count,
+            })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| {
+        b.average_accuracy
+            .total_cmp(&a.average_accuracy)
+            .then_with(|| b.attempts.cmp(&a.attempts))
+            .then_with(|| a.user.cmp(&b.user))
+    });
+
+    for i in 0..entries.len() {
+        entries[i].rank = match i {
+            0 => 1,
+            _ if entries[i].average_accuracy == entries[i - 1].average_accuracy => {
+                entries[i - 1].rank
+            }
+            _ => i + 1,
+        };
+    }
+    entries
+}
+
+/// Badges that can be earned from practice alone
+///
+/// Ids and names match the frontend's `BADGES` in `convex/models/badges.ts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Badge {
+    FirstSteps,
+    DedicatedLearner,
+    CenturyClub,
+    Consistent,
+    Master,
+    WeekWarrior,
+    MonthMaster,
+    Unstoppable,
+}
+
+impl Badge {
+    pub const ALL: [Badge; 8] = [
+        Badge::FirstSteps,
+        Badge::DedicatedLearner,
+        Badge::CenturyClub,
+        Badge::Consistent,
+        Badge::Master,
+        Badge::WeekWarrior,
+        Badge::MonthMaster,
+        Badge::Unstoppable,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Badge::FirstSteps => "first_steps",
+            Badge::DedicatedLearner => "dedicated_learner",
+            Badge::CenturyClub => "century_club",
+            Badge::Consistent => "consistent",
+            Badge::Master => "master",
+            Badge::WeekWarrior => "week_warrior",
+            Badge::MonthMaster => "month_master",
+            Badge::Unstoppable => "unstoppable",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Badge::FirstSteps => "First Steps",
+            Badge::DedicatedLearner => "Dedicated Learner",
+            Badge::CenturyClub => "Century Club",
+            Badge::Consistent => "Consistent",
+            Badge::Master => "Master",
+            Badge::WeekWarrior => "Week Warrior",
+            Badge::MonthMaster => "Month Master",
+            Badge::Unstoppable => "Unstoppable",
+        }
+    }
+
+    /// "bronze", "silver", or "gold"
+    pub fn tier(&self) -> &'static str {
+        match self {
+            Badge::FirstSteps => "bronze",
+            Badge::DedicatedLearner | Badge::Consistent | Badge::WeekWarrior => "silver",
+            Badge::CenturyClub | Badge::Master | Badge::MonthMaster | Badge::Unstoppable => "gold",
+        }
+    }
+
+    fn is_earned(&self, attempts: usize, average_accuracy: f64, streak: &Streak) -> bool {
+        let accurate = |threshold: f64| {
+            attempts >= MIN_ACCURACY_BADGE_ATTEMPTS && average_accuracy >= threshold
+        };
+
+        match self {
+            Badge::FirstSteps => attempts >= 1,
+            Badge::DedicatedLearner => attempts >= 10,
+            Badge::CenturyClub => attempts >= 100,
+            Badge::Consistent => accurate(0.85),
+            Badge::Master => accurate(0.95),
+            // Once reached, a streak's badge is kept after the streak ends
+            Badge::WeekWarrior => streak.longest >= 7,
+            Badge::MonthMaster => streak.longest >= 30,
+            Badge::Unstoppable => streak.longest >= 60,
+        }
+    }
+}
+
+/// Badges earned by the attempts and their streak, in [`Badge::ALL`] order
+pub fn earned_badges(attempts: &[Attempt], streak: &Streak) -> Vec<Badge> {
+    let average_accuracy = match attempts.len() {
+        0 => 0.0,
+        count => attempts.iter().map(|attempt| attempt.accuracy).sum::<f64>() / count as f64,
+    };
+
+    Badge::ALL
+        .into_iter()
+        .filter(|badge| badge.is_earned(attempts.len(), average_accuracy, streak))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Noon in GMT+8 on the given day
+    fn at(day: i64, accuracy: f64) -> Attempt {
+        Attempt {
+            timestamp_ms: day * DAY_MS + 4 * 60 * 60 * 1000,
+            accuracy,
+        }
+    }
+
+    #[test]
+    fn test_days_start_at_midnight_gmt8() {
+        // 2024-01-01T15:59:59Z is 23:59:59 in GMT+8, a second before the next day
+        let before_midnight = 1_704_124_799_000;
+        assert_eq!(day_of(before_midnight) + 1, day_of(before_midnight + 1000));
+        assert_eq!(civil_date(day_of(before_midnight)), (2024, 1, 1));
+        assert_eq!(civil_date(day_of(before_midnight + 1000)), (2024, 1, 2));
+        assert_eq!(
+            day_start(day_of(before_midnight + 1000)),
+            before_midnight + 1000
+        );
+    }
+
+    #[test]
+    fn test_weeks_start_on_monday() {
+        // 2024-01-01 was a Monday
+        let monday = day_of(1_704_067_200_000);
+        assert_eq!(civil_date(monday), (2024, 1, 1));
+        assert_eq!(week_start(week_of(monday)), monday);
+        assert_eq!(week_of(monday + 6), week_of(monday));
+        assert_eq!(week_of(monday - 1), week_of(monday) - 1);
+    }
+
+    #[test]
+    fn test_civil_date_handles_leap_years() {
+        let day = day_of(1_709_208_000_000); // 2024-02-29T12:00:00Z
+        assert_eq!(civil_date(day), (2024, 2, 29));
+        assert_eq!(civil_date(day + 1), (2024, 3, 1));
+        assert_eq!(civil_date(0), (1970, 1, 1));
+    }
+
+    #[test]
+    fn test_streak_continues_until_a_day_is_missed() {
+        let attempts = [at(10, 0.5), at(11, 0.5), at(11, 0.9), at(12, 0.5)];
+
+        assert_eq!(streak(&attempts, 12).current, 3);
+        // Today has no attempt yet, but yesterday's keeps the streak alive
+        assert_eq!(streak(&attempts, 13).current, 3);
+        assert_eq!(streak(&attempts, 14).current, 0);
+        assert_eq!(streak(&attempts, 14).longest, 3);
+        assert_eq!(streak(&attempts, 14).last_practice_day, Some(12));
+    }
+
+    #[test]
+    fn test_longest_streak_outlives_a_break() {
+        let attempts = [at(1, 0.5), at(2, 0.5), at(3, 0.5), at(5, 0.5), at(6, 0.5)];
+        let streak = streak(&attempts, 6);
+
+        assert_eq!(streak.current, 2);
+        assert_eq!(streak.longest, 3);
+        assert_eq!(super::streak(&[], 6), Streak::default());
+    }
+
+    #[test]
+    fn test_leaderboard_ranks_by_weekly_accuracy() {
+        let monday = week_start(100);
+        let users = vec![
+            ("ann", vec![at(monday, 0.5), at(monday + 1, 1.0)]),
+            ("bob", vec![at(monday, 1.0), at(monday + 2, 0.875)]),
+            // Only one attempt this week; the other was the week before
+            ("cat", vec![at(monday, 1.0), at(monday - 1, 1.0)]),
+            ("dan", vec![at(monday, 0.75), at(monday + 6, 0.75)]),
+        ];
+
+        let board = weekly_leaderboard(&users, 100, 2);
+        let ranked: Vec<_> = board.iter().map(|entry| (entry.rank, entry.user)).collect();
+        assert_eq!(ranked, vec![(1, "bob"), (2, "ann"), (2, "dan")]);
+        assert_eq!(board[1].average_accuracy, 0.75);
+    }
+
+    #[test]
+    fn test_badges_follow_attempts_and_streaks() {
+        let attempts: Vec<Attempt> = (0..10).map(|day| at(day, 0.9)).collect();
+        let earned = earned_badges(&attempts, &streak(&attempts, 9));
+
+        assert_eq!(
+            earned,
+            vec![
+                Badge::FirstSteps,
+                Badge::DedicatedLearner,
+                Badge::Consistent,
+                Badge::WeekWarrior
+            ]
+        );
+        assert!(earned_badges(&[], &Streak::default()).is_empty());
+    }
+}
@@ -0,0 +1,444 @@
+//! Parsing and checking IPA typed by learners
+//!
+//! Typed transcriptions arrive with slashes or brackets, ASCII stand-ins such
+//! as ":" for "ː", and symbols outside the feature table. [`normalize_ipa`]
+//! cleans up the unambiguous substitutions, [`segment_ipa`] splits the result
+//! into phoneme symbols, and [`validate_ipa`] reports symbols that are unknown
+//! or cannot be spoken, suggesting the closest valid symbols by features.
+//! [`grade_transcription`] marks each symbol of a transcription exercise
+//! against the reference pronunciation.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::phoneme::{IPA_PHONEME_FEATURES, calculate_feature_similarity, parse_ipa};
+use crate::scoring::{Rhoticity, ScoringRubric, match_phonemes, overall_score};
+
+/// Most corrections suggested for one symbol
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Least feature similarity for a neighbouring symbol to be suggested
+const MIN_SUGGESTION_SIMILARITY: f64 = 0.6;
+
+/// Symbols spelled with two base characters, matched before their parts
+const MULTI_CHAR_SYMBOLS: &[&str] = &["tʃ", "dʒ", "aɪ", "aʊ", "eɪ", "oʊ", "ɔɪ"];
+
+/// Stress marks, kept with the symbol after them
+const STRESS_MARKS: &[char] = &['ˈ', 'ˌ'];
+
+/// Length marks, secondary articulations, and diacritics, kept with the symbol before them
+const MODIFIERS: &[char] = &[
+    'ː', 'ˑ', 'ʰ', 'ʱ', 'ʲ', 'ʷ', 'ˠ', 'ˤ', '˞', 'ʼ', '\u{0303}', '\u{0329}', '\u{030D}',
+    '\u{032A}', '\u{0325}', '\u{030A}', '\u{032C}', '\u{031A}', '\u{032F}',
+];
+
+/// Separators between words and syllables, which are not symbols
+const BOUNDARIES: &[char] = &[' ', '.', '|', '‿'];
+
+/// Stand-ins learners type for IPA symbols, and what they most likely meant
+///
+/// Covers X-SAMPA, ASCII lookalikes, and IPA symbols used in other languages
+/// or transcription styles that the feature table does not include.
+const LOOKALIKES: &[(&str, &[&str])] = &[
+    ("r", &["ɹ"]),
+    ("R", &["ɹ"]),
+    ("ʁ", &["ɹ"]),
+    ("ɾ", &["t", "d"]),
+    ("ʔ", &["t"]),
+    ("ɒ", &["ɑ", "ɔ"]),
+    ("ɐ", &["ʌ", "ə"]),
+    ("ɜ", &["ɝ", "ə"]),
+    ("ɜ˞", &["ɝ"]),
+    ("ə˞", &["ɚ"]),
+    ("ɨ", &["ɪ"]),
+    ("ʉ", &["u"]),
+    ("ɵ", &["o"]),
+    ("y", &["j"]),
+    ("c", &["k"]),
+    ("q", &["k"]),
+    ("x", &["k"]),
+    ("ç", &["h"]),
+    ("@", &["ə"]),
+    ("3", &["ɝ"]),
+    ("{", &["æ"]),
+    ("A", &["ɑ"]),
+    ("E", &["ɛ"]),
+    ("I", &["ɪ"]),
+    ("O", &["ɔ"]),
+    ("U", &["ʊ"]),
+    ("V", &["ʌ"]),
+    ("S", &["ʃ"]),
+    ("Z", &["ʒ"]),
+    ("T", &["θ"]),
+    ("D", &["ð"]),
+    ("N", &["ŋ"]),
+    (",", &["ˌ"]),
+    ("\"", &["ˈ"]),
+];
+
+/// A symbol of a typed transcription, at character offsets into the normalized text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpaSymbol {
+    pub symbol: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Why a typed symbol was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidReason {
+    /// Not a phoneme in the feature table
+    Unknown,
+    /// A known phoneme written with a character the synthesizer cannot speak
+    Unspeakable,
+}
+
+impl InvalidReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvalidReason::Unknown => "unknown",
+            InvalidReason::Unspeakable => "unspeakable",
+        }
+    }
+}
+
+/// A rejected symbol and the valid symbols the learner may have meant, closest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidSymbol {
+    pub symbol: IpaSymbol,
+    pub reason: InvalidReason,
+    pub suggestions: Vec<String>,
+}
+
+/// Result of checking a typed transcription
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpaValidation {
+    pub normalized: String,
+    pub symbols: Vec<IpaSymbol>,
+    pub invalid: Vec<InvalidSymbol>,
+}
+
+impl IpaValidation {
+    pub fn is_valid(&self) -> bool {
+        self.invalid.is_empty()
+    }
+}
+
+/// A reference phoneme paired with the symbol typed for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradedSymbol {
+    /// The reference phoneme, `None` for a typed symbol the reference lacks
+    pub expected: Option<String>,
+    /// The typed symbol, `None` for a reference phoneme left out
+    pub typed: Option<IpaSymbol>,
+    pub score: f64,
+}
+
+impl GradedSymbol {
+    /// Whether the symbol earned full credit
+    pub fn is_correct(&self) -> bool {
+        self.score >= 1.0
+    }
+}
+
+/// Result of grading a typed transcription against the reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptionGrade {
+    pub normalized: String,
+    pub symbols: Vec<GradedSymbol>,
+    pub score: f64,
+}
+
+/// Clean up a typed transcription without changing what it says
+///
+/// Drops enclosing slashes and brackets and tie bars, replaces ":" with "ː",
+/// an apostrophe with "ˈ", ASCII "g" with "ɡ", and the "ʧ" and "ʤ" ligatures
+/// with "tʃ" and "dʒ", and collapses whitespace.
+pub fn normalize_ipa(input: &str) -> String {
+    let mut normalized = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '/' | '[' | ']' | '\u{0361}' | '\u{035C}' => {}
+            ':' => normalized.push('ː'),
+            '\'' | '’' => normalized.push('ˈ'),
+            'g' => normalized.push('ɡ'),
+            'ǝ' => normalized.push('ə'),
+            'ʧ' => normalized.push_str("tʃ"),
+            'ʤ' => normalized.push_str("dʒ"),
+            c if c.is_whitespace() => {
+                if !normalized.is_empty() && !normalized.ends_with(' ') {
+                    normalized.push(' ');
+                }
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized.trim_end().to_string()
+}
+
+/// Split normalized IPA into symbols, leaving out word and syllable boundaries
+///
+/// Each symbol is a base character, or a pair such as "tʃ" or "aɪ", with the
+/// stress marks before it and the modifiers after it.
+pub fn segment_ipa(ipa: &str) -> Vec<IpaSymbol> {
+    let chars: Vec<char> = ipa.chars().collect();
+    let mut symbols = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        if BOUNDARIES.contains(&chars[index]) {
+            index += 1;
+            continue;
+        }
+
+        let start = index;
+        while index < chars.len() && STRESS_MARKS.contains(&chars[index]) {
+            index += 1;
+        }
+        if index < chars.len() && !BOUNDARIES.contains(&chars[index]) {
+            let pair: String = chars[index..chars.len().min(index + 2)].iter().collect();
+            let pair_len = if MULTI_CHAR_SYMBOLS.contains(&pair.as_str()) {
+                2
+            } else {
+                1
+            };
+            index += pair_len;
+        }
+        while index < chars.len() && MODIFIERS.contains(&chars[index]) {
+            index += 1;
+        }
+
+        symbols.push(IpaSymbol {
+            symbol: chars[start..index].iter().collect(),
+            start,
+            end: index,
+        });
+    }
+
+    symbols
+}
+
+/// Check a typed transcription, reporting symbols that are unknown or unspeakable
+///
+/// `speakable` tells whether the synthesizer reads a character; a known
+/// phoneme written with other characters is reported as unspeakable.
+pub fn validate_ipa(input: &str, speakable: impl Fn(char) -> bool) -> IpaValidation {
+    let normalized = normalize_ipa(input);
+    let symbols = segment_ipa(&normalized);
+
+    let invalid = symbols
+        .iter()
+        .filter_map(|symbol| {
+            let text = symbol.symbol.as_str();
+            let is_stress_only = text.chars().all(|c| STRESS_MARKS.contains(&c));
+            let reason = if !is_stress_only && parse_ipa(text).is_none() {
+                InvalidReason::Unknown
+            } else if !text.chars().all(&speakable) {
+                InvalidReason::Unspeakable
+            } else {
+                return None;
+            };
+
+            Some(InvalidSymbol {
+                symbol: symbol.clone(),
+                reason,
+                suggestions: suggestions(text, reason, &speakable),
+            })
+        })
+        .collect();
+
+    IpaValidation {
+        normalized,
+        symbols,
+        invalid,
+    }
+}
+
+/// Grade a typed transcription against the reference phonemes
+///
+/// Symbols are paired and scored as spoken phonemes are when scoring audio,
+/// so a near miss such as "d" for "t" earns partial credit under the rubric.
+/// Stress marks are not graded, and symbols come back in the order of
+/// [`match_phonemes`].
+pub fn grade_transcription(
+    input: &str,
+    reference: &[String],
+    rhoticity: Rhoticity,
+    rubric: &ScoringRubric,
+) -> TranscriptionGrade {
+    let normalized = normalize_ipa(input);
+    let typed: Vec<IpaSymbol> = segment_ipa(&normalized)
+        .into_iter()
+        .filter(|symbol| !symbol.symbol.chars().all(|c| STRESS_MARKS.contains(&c)))
+        .collect();
+    let unstressed: Vec<String> = typed
+        .iter()
+        .map(|symbol| symbol.symbol.replace(STRESS_MARKS, ""))
+        .collect();
+
+    let symbols: Vec<GradedSymbol> = match_phonemes(reference, &unstressed, rhoticity, rubric)
+        .into_iter()
+        .map(|matched| GradedSymbol {
+            expected: matched.expected.map(|i| reference[i].clone()),
+            typed: matched.actual.map(|j| typed[j].clone()),
+            score: matched.score,
+        })
+        .collect();
+    let score = overall_score(symbols.iter().map(|symbol| symbol.score));
+
+    TranscriptionGrade {
+        normalized,
+        symbols,
+        score,
+    }
+}
+
+/// Valid symbols the learner may have meant instead of `symbol`, closest first
+fn suggestions(
+    symbol: &str,
+    reason: InvalidReason,
+    speakable: &impl Fn(char) -> bool,
+) -> Vec<String> {
+    let is_valid =
+        |candidate: &str| parse_ipa(candidate).is_some() && candidate.chars().all(speakable);
+
+    // What the symbol most likely stands for
+    let stripped: String = symbol
+        .chars()
+        .filter(|&c| speakable(c) && !STRESS_MARKS.contains(&c))
+        .collect();
+    let mut suggestions: Vec<String> = match reason {
+        InvalidReason::Unspeakable => alloc::vec![stripped],
+        InvalidReason::Unknown => LOOKALIKES
+            .iter()
+            .find(|(lookalike, _)| *lookalike == stripped)
+            .map(|(_, meant)| meant.iter().map(|meant| meant.to_string()).collect())
+            .unwrap_or_default(),
+    };
+    suggestions.retain(|candidate| is_valid(candidate) || candidate == "ˈ" || candidate == "ˌ");
+
+    // Then the phonemes sounding most like the first of them
+    if let Some(features) = suggestions
+        .first()
+        .and_then(|first| parse_ipa(first))
+        .map(|parsed| parsed.features)
+    {
+        let mut neighbours: Vec<(&str, f64)> = IPA_PHONEME_FEATURES
+            .iter()
+            .filter(|(candidate, _)| *candidate != "unknown" && *candidate != "g")
+            .filter(|(candidate, _)| is_valid(candidate))
+            .map(|(candidate, other)| (*candidate, calculate_feature_similarity(&features, other)))
+            .filter(|&(_, similarity)| (MIN_SUGGESTION_SIMILARITY..1.0).contains(&similarity))
+            .collect();
+        neighbours.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        for (neighbour, _) in neighbours {
+            if !suggestions.iter().any(|existing| existing == neighbour) {
+                suggestions.push(neighbour.to_string());
+            }
+        }
+    }
+
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Everything except nasalization, standing in for the synthesizer's vocabulary
+    fn speakable(c: char) -> bool {
+        c != '\u{0303}'
+    }
+
+    fn symbols(ipa: &str) -> Vec<String> {
+        segment_ipa(ipa).into_iter().map(|s| s.symbol).collect()
+    }
+
+    #[test]
+    fn test_normalize_ipa() {
+        assert_eq!(normalize_ipa(" /ˈɡʊd  deɪ/ "), "ˈɡʊd deɪ");
+        assert_eq!(normalize_ipa("[bi:g]"), "biːɡ");
+        assert_eq!(normalize_ipa("'ʧɜ:ʧ"), "ˈtʃɜːtʃ");
+        assert_eq!(normalize_ipa("t͡ʃ"), "tʃ");
+    }
+
+    #[test]
+    fn test_segment_ipa() {
+        assert_eq!(symbols("ˈθɪŋk"), vec!["ˈθ", "ɪ", "ŋ", "k"]);
+        assert_eq!(symbols("tʃiːz"), vec!["tʃ", "iː", "z"]);
+        assert_eq!(symbols("maɪ.ˌtʰaɪm"), vec!["m", "aɪ", "ˌtʰ", "aɪ", "m"]);
+        assert_eq!(symbols("ə"), vec!["ə"]);
+        assert_eq!(symbols(""), Vec::<String>::new());
+
+        let segmented = segment_ipa("ʃi ɪz");
+        assert_eq!((segmented[2].start, segmented[2].end), (3, 4));
+    }
+
+    #[test]
+    fn test_validate_accepts_known_symbols() {
+        let validation = validate_ipa("/ˈhɛloʊ wɝld/", speakable);
+        assert!(validation.is_valid(), "{:?}", validation.invalid);
+        assert_eq!(validation.normalized, "ˈhɛloʊ wɝld");
+    }
+
+    #[test]
+    fn test_validate_suggests_corrections() {
+        let validation = validate_ipa("rɒ@", speakable);
+        let invalid: Vec<(&str, InvalidReason, Vec<String>)> = validation
+            .invalid
+            .iter()
+            .map(|i| (i.symbol.symbol.as_str(), i.reason, i.suggestions.clone()))
+            .collect();
+
+        assert_eq!(invalid.len(), 3);
+        assert_eq!(invalid[0].0, "r");
+        assert_eq!(invalid[0].2[0], "ɹ");
+        assert_eq!(&invalid[1].2[..2], ["ɑ", "ɔ"]);
+        assert_eq!(invalid[2].2[0], "ə");
+        assert!(invalid.iter().all(|i| i.1 == InvalidReason::Unknown));
+        assert!(invalid.iter().all(|i| i.2.len() <= MAX_SUGGESTIONS));
+    }
+
+    #[test]
+    fn test_validate_reports_unspeakable_diacritics() {
+        let validation = validate_ipa("kæ\u{0303}n", speakable);
+
+        assert_eq!(validation.invalid.len(), 1);
+        let invalid = &validation.invalid[0];
+        assert_eq!(invalid.reason, InvalidReason::Unspeakable);
+        assert_eq!(invalid.suggestions[0], "æ");
+    }
+
+    #[test]
+    fn test_validate_flags_unknown_characters() {
+        let validation = validate_ipa("k#t", speakable);
+
+        assert_eq!(validation.invalid.len(), 1);
+        assert_eq!(validation.invalid[0].symbol.symbol, "#");
+        assert!(validation.invalid[0].suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_grade_transcription() {
+        let reference: Vec<String> = ["kʰ", "æ", "t"].iter().map(|s| s.to_string()).collect();
+        let rubric = ScoringRubric::default();
+
+        let grade = grade_transcription("/ˈkæt/", &reference, Rhoticity::Rhotic, &rubric);
+        assert!(grade.symbols.iter().all(GradedSymbol::is_correct));
+        assert_eq!(grade.symbols[0].typed.as_ref().unwrap().symbol, "ˈk");
+        assert_eq!(grade.score, 1.0);
+
+        let grade = grade_transcription("kɛd", &reference, Rhoticity::Rhotic, &rubric);
+        let correct: Vec<bool> = grade.symbols.iter().map(GradedSymbol::is_correct).collect();
+        assert_eq!(correct, vec![true, false, false]);
+        assert!(grade.symbols[2].score > 0.0);
+
+        let grade = grade_transcription("kæ", &reference, Rhoticity::Rhotic, &rubric);
+        assert_eq!(grade.symbols[2].expected.as_deref(), Some("t"));
+        assert_eq!(grade.symbols[2].typed, None);
+        assert!(grade.score < 1.0);
+    }
+}
@@ -0,0 +1,22 @@
+//! Dictionary-free phonetics for IPA Navigator
+//!
+//! Phoneme features, similarity, and the scoring math used to compare spoken
+//! phonemes with expected ones, the layout of the IPA chart, personal
+//! baselines calibrated from a speaker's first attempts, rates of improvement,
+//! and the streaks and badges earned by practice.
+//! Without the default `std` feature the crate is `no_std`, needing only
+//! `alloc`; enable the `wasm` feature for JavaScript bindings.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod baseline;
+pub mod chart;
+pub mod gamification;
+pub mod ipa_input;
+pub mod phoneme;
+pub mod scoring;
+pub mod trend;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,1686 @@
+//! Phoneme conversion utilities (ARPAbet <-> IPA) and phonetic feature extraction.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scoring::Rhoticity;
+
+/// Phonetic features of a phoneme
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhonemeFeatures {
+    // Manner of articulation
+    is_plosive: bool,
+    is_fricative: bool,
+    is_affricate: bool,
+    is_nasal: bool,
+    is_approximant: bool,
+    is_lateral: bool,
+
+    // Place of articulation
+    is_bilabial: bool,
+    is_labiodental: bool,
+    is_dental: bool,
+    is_alveolar: bool,
+    is_postalveolar: bool,
+    is_palatal: bool,
+    is_velar: bool,
+    is_glottal: bool,
+
+    // Vowel features
+    is_vowel: bool,
+    is_front: bool,
+    is_central: bool,
+    is_back: bool,
+    is_close: bool,
+    is_mid: bool,
+    is_open: bool,
+    is_rounded: bool,
+
+    // Voicing
+    is_voiced: bool,
+}
+
+/// Place of articulation of a consonant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Place {
+    Bilabial,
+    Labiodental,
+    Dental,
+    Alveolar,
+    Postalveolar,
+    Palatal,
+    Velar,
+    Glottal,
+}
+
+impl Place {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Place::Bilabial => "bilabial",
+            Place::Labiodental => "labiodental",
+            Place::Dental => "dental",
+            Place::Alveolar => "alveolar",
+            Place::Postalveolar => "postalveolar",
+            Place::Palatal => "palatal",
+            Place::Velar => "velar",
+            Place::Glottal => "glottal",
+        }
+    }
+}
+
+/// Manner of articulation of a consonant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Manner {
+    Plosive,
+    Fricative,
+    Affricate,
+    Nasal,
+    Approximant,
+}
+
+impl Manner {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Manner::Plosive => "plosive",
+            Manner::Fricative => "fricative",
+            Manner::Affricate => "affricate",
+            Manner::Nasal => "nasal",
+            Manner::Approximant => "approximant",
+        }
+    }
+}
+
+/// Tongue height of a vowel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Height {
+    Close,
+    Mid,
+    Open,
+}
+
+impl Height {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Height::Close => "close",
+            Height::Mid => "mid",
+            Height::Open => "open",
+        }
+    }
+}
+
+/// Tongue backness of a vowel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backness {
+    Front,
+    Central,
+    Back,
+}
+
+impl Backness {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backness::Front => "front",
+            Backness::Central => "central",
+            Backness::Back => "back",
+        }
+    }
+}
+
+/// Generate a public getter for each feature flag
+macro_rules! feature_accessors {
+    ($($field:ident),* $(,)?) => {
+        $(
+            #[doc = concat!("Whether the `", stringify!($field), "` feature is set")]
+            pub fn $field(&self) -> bool {
+                self.$field
+            }
+        )*
+    };
+}
+
+impl PhonemeFeatures {
+    /// No features set, usable in constants unlike `Default`
+    pub const NONE: Self = Self {
+        is_plosive: false,
+        is_fricative: false,
+        is_affricate: false,
+        is_nasal: false,
+        is_approximant: false,
+        is_lateral: false,
+        is_bilabial: false,
+        is_labiodental: false,
+        is_dental: false,
+        is_alveolar: false,
+        is_postalveolar: false,
+        is_palatal: false,
+        is_velar: false,
+        is_glottal: false,
+        is_vowel: false,
+        is_front: false,
+        is_central: false,
+        is_back: false,
+        is_close: false,
+        is_mid: false,
+        is_open: false,
+        is_rounded: false,
+        is_voiced: false,
+    };
+
+    feature_accessors!(
+        is_plosive,
+        is_fricative,
+        is_affricate,
+        is_nasal,
+        is_approximant,
+        is_lateral,
+        is_bilabial,
+        is_labiodental,
+        is_dental,
+        is_alveolar,
+        is_postalveolar,
+        is_palatal,
+        is_velar,
+        is_glottal,
+        is_vowel,
+        is_front,
+        is_central,
+        is_back,
+        is_close,
+        is_mid,
+        is_open,
+        is_rounded,
+        is_voiced,
+    );
+
+    /// Features of an arbitrary IPA symbol, ignoring diacritics that do not change them
+    pub fn from_ipa(symbol: &str) -> Option<Self> {
+        parse_ipa(symbol).map(|parsed| parsed.features)
+    }
+
+    /// Place of articulation, for consonants
+    pub fn place(&self) -> Option<Place> {
+        [
+            (self.is_bilabial, Place::Bilabial),
+            (self.is_labiodental, Place::Labiodental),
+            (self.is_dental, Place::Dental),
+            (self.is_alveolar, Place::Alveolar),
+            (self.is_postalveolar, Place::Postalveolar),
+            (self.is_palatal, Place::Palatal),
+            (self.is_velar, Place::Velar),
+            (self.is_glottal, Place::Glottal),
+        ]
+        .into_iter()
+        .find_map(|(set, place)| set.then_some(place))
+    }
+
+    /// Manner of articulation, for consonants
+    pub fn manner(&self) -> Option<Manner> {
+        [
+            (self.is_plosive, Manner::Plosive),
+            (self.is_affricate, Manner::Affricate),
+            (self.is_fricative, Manner::Fricative),
+            (self.is_nasal, Manner::Nasal),
+            (self.is_approximant, Manner::Approximant),
+        ]
+        .into_iter()
+        .find_map(|(set, manner)| set.then_some(manner))
+    }
+
+    /// Tongue height, for vowels; diphthongs report their starting height
+    pub fn height(&self) -> Option<Height> {
+        [
+            (self.is_open, Height::Open),
+            (self.is_mid, Height::Mid),
+            (self.is_close, Height::Close),
+        ]
+        .into_iter()
+        .find_map(|(set, height)| set.then_some(height))
+    }
+
+    /// Tongue backness, for vowels
+    pub fn backness(&self) -> Option<Backness> {
+        [
+            (self.is_front, Backness::Front),
+            (self.is_central, Backness::Central),
+            (self.is_back, Backness::Back),
+        ]
+        .into_iter()
+        .find_map(|(set, backness)| set.then_some(backness))
+    }
+
+    /// Conventional phonetic description, e.g. "voiceless dental fricative"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+
+        if self.is_vowel {
+            parts.extend(self.height().map(|h| h.as_str()));
+            parts.extend(self.backness().map(|b| b.as_str()));
+            parts.push(if self.is_rounded {
+                "rounded"
+            } else {
+                "unrounded"
+            });
+            parts.push("vowel");
+        } else {
+            let Some(manner) = self.manner() else {
+                return "unknown sound".to_string();
+            };
+
+            parts.push(if self.is_voiced {
+                "voiced"
+            } else {
+                "voiceless"
+            });
+            parts.extend(self.place().map(|p| p.as_str()));
+            if self.is_lateral {
+                parts.push("lateral");
+            }
+            parts.push(manner.as_str());
+        }
+
+        parts.join(" ")
+    }
+}
+
+/// An IPA symbol decomposed into its base phoneme and secondary articulations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedPhoneme {
+    /// Base symbol found in the feature table
+    pub base: String,
+    /// Features of the base, adjusted by place and voicing diacritics
+    pub features: PhonemeFeatures,
+    pub long: bool,
+    pub aspirated: bool,
+    pub nasalized: bool,
+    pub palatalized: bool,
+    pub labialized: bool,
+    pub syllabic: bool,
+}
+
+impl ParsedPhoneme {
+    /// Description including secondary articulations, e.g. "aspirated voiceless velar plosive"
+    pub fn describe(&self) -> String {
+        let base = match diphthong_targets(&self.base) {
+            Some((onset, offset)) => format!("diphthong gliding from /{}/ to /{}/", onset, offset),
+            None => self.features.describe(),
+        };
+
+        let modifiers = [
+            (self.long, "long"),
+            (self.aspirated, "aspirated"),
+            (self.nasalized, "nasalized"),
+            (self.palatalized, "palatalized"),
+            (self.labialized, "labialized"),
+            (self.syllabic, "syllabic"),
+        ];
+
+        modifiers
+            .into_iter()
+            .filter_map(|(set, name)| set.then_some(name.to_string()))
+            .chain(iter::once(base))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// MFA phone set spellings of diphthongs that the feature table writes differently
+const DIPHTHONG_ALIASES: &[(&str, &str)] = &[
+    ("aj", "aɪ"),
+    ("aw", "aʊ"),
+    ("ej", "eɪ"),
+    ("ow", "oʊ"),
+    ("əw", "oʊ"),
+    ("əʊ", "oʊ"),
+    ("ɔj", "ɔɪ"),
+];
+
+/// Split an IPA symbol into its base phoneme and diacritics
+///
+/// Handles length marks, stress marks, tie bars, aspiration, nasalization,
+/// palatalization, labialization, syllabicity, and dental and voicing diacritics.
+/// Returns `None` when the base is not in the feature table.
+pub fn parse_ipa(symbol: &str) -> Option<ParsedPhoneme> {
+    let mut parsed = ParsedPhoneme {
+        base: String::new(),
+        features: PhonemeFeatures::default(),
+        long: false,
+        aspirated: false,
+        nasalized: false,
+        palatalized: false,
+        labialized: false,
+        syllabic: false,
+    };
+    let mut dental = false;
+    let mut voicing = None;
+
+    let mut base = String::new();
+    for c in symbol.trim().chars() {
+        match c {
+            'ː' | 'ˑ' => parsed.long = true,
+            'ʰ' => parsed.aspirated = true,
+            '\u{0303}' => parsed.nasalized = true,
+            'ʲ' => parsed.palatalized = true,
+            'ʷ' => parsed.labialized = true,
+            '\u{0329}' | '\u{030D}' => parsed.syllabic = true,
+            '\u{032A}' => dental = true,
+            '\u{0325}' | '\u{030A}' => voicing = Some(false),
+            '\u{032C}' => voicing = Some(true),
+            // Stress marks, tie bars, and the non-syllabic mark do not change the phoneme
+            'ˈ' | 'ˌ' | '\u{0361}' | '\u{035C}' | '\u{032F}' => {}
+            _ => base.push(c),
+        }
+    }
+
+    let key = DIPHTHONG_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == base)
+        .map_or(base, |(_, canonical)| canonical.to_string());
+    if key == "unknown" {
+        return None;
+    }
+    let features = phoneme_features(&key)?;
+
+    parsed.base = key;
+    parsed.features = features.clone();
+
+    if dental {
+        parsed.features.is_alveolar = false;
+        parsed.features.is_dental = true;
+    }
+    if let Some(voiced) = voicing {
+        parsed.features.is_voiced = voiced;
+    }
+
+    Some(parsed)
+}
+
+/// One way in which a produced phoneme differs from the expected one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureDifference {
+    /// A vowel was produced for a consonant or vice versa
+    VowelConsonant {
+        expected_vowel: bool,
+    },
+    Place {
+        expected: Place,
+        actual: Place,
+    },
+    Manner {
+        expected: Manner,
+        actual: Manner,
+    },
+    Voicing {
+        expected_voiced: bool,
+    },
+    Height {
+        expected: Height,
+        actual: Height,
+    },
+    Backness {
+        expected: Backness,
+        actual: Backness,
+    },
+    Rounding {
+        expected_rounded: bool,
+    },
+}
+
+/// List the articulatory differences between an expected and a produced phoneme
+pub fn feature_differences(
+    expected: &PhonemeFeatures,
+    actual: &PhonemeFeatures,
+) -> Vec<FeatureDifference> {
+    if expected.is_vowel != actual.is_vowel {
+        return vec![FeatureDifference::VowelConsonant {
+            expected_vowel: expected.is_vowel,
+        }];
+    }
+
+    let mut differences = Vec::new();
+
+    if expected.is_vowel {
+        if let (Some(e), Some(a)) = (expected.height(), actual.height())
+            && e != a
+        {
+            differences.push(FeatureDifference::Height {
+                expected: e,
+                actual: a,
+            });
+        }
+        if let (Some(e), Some(a)) = (expected.backness(), actual.backness())
+            && e != a
+        {
+            differences.push(FeatureDifference::Backness {
+                expected: e,
+                actual: a,
+            });
+        }
+        if expected.is_rounded != actual.is_rounded {
+            differences.push(FeatureDifference::Rounding {
+                expected_rounded: expected.is_rounded,
+            });
+        }
+        return differences;
+    }
+
+    if let (Some(e), Some(a)) = (expected.place(), actual.place())
+        && e != a
+    {
+        differences.push(FeatureDifference::Place {
+            expected: e,
+            actual: a,
+        });
+    }
+    if let (Some(e), Some(a)) = (expected.manner(), actual.manner())
+        && e != a
+    {
+        differences.push(FeatureDifference::Manner {
+            expected: e,
+            actual: a,
+        });
+    }
+    if expected.is_voiced != actual.is_voiced {
+        differences.push(FeatureDifference::Voicing {
+            expected_voiced: expected.is_voiced,
+        });
+    }
+
+    differences
+}
+
+/// How much each shared feature adds to the similarity of two phonemes
+///
+/// Vowels are compared by height, backness and rounding, consonants by
+/// manner, place, voicing and laterality. Neighbouring heights, backnesses,
+/// places and related manners earn the `adjacent_*` or `related_manner`
+/// weight instead of the full one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimilarityWeights {
+    /// Similarity of any vowel to any consonant
+    pub vowel_consonant: f64,
+    pub height: f64,
+    pub adjacent_height: f64,
+    pub backness: f64,
+    pub adjacent_backness: f64,
+    pub rounding: f64,
+    pub manner: f64,
+    pub related_manner: f64,
+    pub place: f64,
+    pub adjacent_place: f64,
+    pub voicing: f64,
+    pub laterality: f64,
+    /// Highest similarity of two phonemes whose features differ
+    pub max_different: f64,
+}
+
+impl Default for SimilarityWeights {
+    fn default() -> Self {
+        Self::ARTICULATORY
+    }
+}
+
+impl SimilarityWeights {
+    /// Weights following articulation, manner counting most among consonants
+    pub const ARTICULATORY: Self = Self {
+        vowel_consonant: 0.1,
+        height: 0.3,
+        adjacent_height: 0.15,
+        backness: 0.3,
+        adjacent_backness: 0.15,
+        rounding: 0.3,
+        manner: 0.4,
+        related_manner: 0.2,
+        place: 0.3,
+        adjacent_place: 0.15,
+        voicing: 0.2,
+        laterality: 0.1,
+        max_different: 0.9,
+    };
+
+    /// Weights following how often listeners confuse phonemes
+    ///
+    /// Listeners tell voicing and manner apart more reliably than place, so
+    /// a place error leaves phonemes sounding more alike than a voicing
+    /// error. Among vowels, height is heard more reliably than backness.
+    pub const PERCEPTUAL: Self = Self {
+        vowel_consonant: 0.05,
+        height: 0.4,
+        adjacent_height: 0.2,
+        backness: 0.25,
+        adjacent_backness: 0.15,
+        rounding: 0.25,
+        manner: 0.35,
+        related_manner: 0.15,
+        place: 0.15,
+        adjacent_place: 0.1,
+        voicing: 0.35,
+        laterality: 0.15,
+        max_different: 0.9,
+    };
+
+    /// Named presets, for configuration
+    pub const PRESETS: &[(&str, Self)] = &[
+        ("articulatory", Self::ARTICULATORY),
+        ("perceptual", Self::PERCEPTUAL),
+    ];
+
+    /// Look up a preset by name, case-insensitively
+    pub fn preset(name: &str) -> Option<Self> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, weights)| *weights)
+    }
+}
+
+/// Calculate similarity between two phonemes based on their features
+pub fn calculate_feature_similarity(a: &PhonemeFeatures, b: &PhonemeFeatures) -> f64 {
+    calculate_feature_similarity_with(a, b, &SimilarityWeights::default())
+}
+
+/// Calculate similarity between two phonemes, weighting their features by `weights`
+pub fn calculate_feature_similarity_with(
+    a: &PhonemeFeatures,
+    b: &PhonemeFeatures,
+    weights: &SimilarityWeights,
+) -> f64 {
+    // Check if features are completely identical
+    if a == b {
+        return 1.0; // Perfect match
+    }
+
+    // If one is a vowel and the other is a consonant, they're quite different
+    if a.is_vowel != b.is_vowel {
+        return weights.vowel_consonant;
+    }
+
+    let mut similarity: f64 = 0.0;
+
+    // For vowels, compare vowel features
+    if a.is_vowel && b.is_vowel {
+        // Check vowel height
+        if (a.is_close && b.is_close) || (a.is_mid && b.is_mid) || (a.is_open && b.is_open) {
+            similarity += weights.height;
+        } else if (a.is_close && b.is_mid)
+            || (a.is_mid && b.is_close)
+            || (a.is_mid && b.is_open)
+            || (a.is_open && b.is_mid)
+        {
+            // Adjacent heights get some similarity
+            similarity += weights.adjacent_height;
+        }
+
+        // Check vowel backness
+        if (a.is_front && b.is_front) || (a.is_central && b.is_central) || (a.is_back && b.is_back)
+        {
+            similarity += weights.backness;
+        } else if (a.is_front && b.is_central)
+            || (a.is_central && b.is_front)
+            || (a.is_central && b.is_back)
+            || (a.is_back && b.is_central)
+        {
+            // Adjacent backness gets some similarity
+            similarity += weights.adjacent_backness;
+        }
+
+        // Check roundedness
+        if a.is_rounded == b.is_rounded {
+            similarity += weights.rounding;
+        }
+
+        // Cap for non-identical vowels
+        return similarity.min(weights.max_different);
+    }
+
+    // For consonants, compare consonant features
+
+    // Manner of articulation (most important)
+    if (a.is_plosive && b.is_plosive)
+        || (a.is_fricative && b.is_fricative)
+        || (a.is_affricate && b.is_affricate)
+        || (a.is_nasal && b.is_nasal)
+        || (a.is_approximant && b.is_approximant)
+    {
+        similarity += weights.manner;
+    } else if (a.is_plosive && b.is_affricate)
+        || (a.is_affricate && b.is_plosive)
+        || (a.is_fricative && b.is_affricate)
+        || (a.is_affricate && b.is_fricative)
+    {
+        // Related manners get partial similarity
+        similarity += weights.related_manner;
+    }
+
+    // Place of articulation
+    if (a.is_bilabial && b.is_bilabial)
+        || (a.is_labiodental && b.is_labiodental)
+        || (a.is_dental && b.is_dental)
+        || (a.is_alveolar && b.is_alveolar)
+        || (a.is_postalveolar && b.is_postalveolar)
+        || (a.is_palatal && b.is_palatal)
+        || (a.is_velar && b.is_velar)
+        || (a.is_glottal && b.is_glottal)
+    {
+        similarity += weights.place;
+    } else if (a.is_bilabial && b.is_labiodental)
+        || (a.is_labiodental && b.is_bilabial)
+        || (a.is_dental && b.is_alveolar)
+        || (a.is_alveolar && b.is_dental)
+        || (a.is_alveolar && b.is_postalveolar)
+        || (a.is_postalveolar && b.is_alveolar)
+        || (a.is_postalveolar && b.is_palatal)
+        || (a.is_palatal && b.is_postalveolar)
+        || (a.is_palatal && b.is_velar)
+        || (a.is_velar && b.is_palatal)
+    {
+        // Adjacent places get partial similarity
+        similarity += weights.adjacent_place;
+    }
+
+    // Voicing
+    if a.is_voiced == b.is_voiced {
+        similarity += weights.voicing;
+    }
+
+    // Laterality
+    if a.is_lateral == b.is_lateral {
+        similarity += weights.laterality;
+    }
+
+    // Cap for non-identical consonants
+    similarity.min(weights.max_different)
+}
+
+/// Diphthongs as ordered pairs of onset and offset target vowels
+pub(crate) const DIPHTHONG_TARGETS: &[(&str, &str, &str)] = &[
+    ("aɪ", "a", "ɪ"),
+    ("aʊ", "a", "ʊ"),
+    ("eɪ", "e", "ɪ"),
+    ("oʊ", "o", "ʊ"),
+    ("ɔɪ", "ɔ", "ɪ"),
+];
+
+/// Weight of the onset when comparing vowel glides; the offset gets the rest
+const DIPHTHONG_ONSET_WEIGHT: f64 = 0.6;
+
+/// Penalty factor when a diphthong is compared with a monophthong, for the missing or extra glide
+const GLIDE_MISMATCH_FACTOR: f64 = 0.8;
+
+/// Onset and offset target vowels of a diphthong, accepting MFA spellings such as "aj"
+pub fn diphthong_targets(symbol: &str) -> Option<(&'static str, &'static str)> {
+    let base = parse_ipa(symbol)?.base;
+    DIPHTHONG_TARGETS
+        .iter()
+        .find(|(diphthong, _, _)| *diphthong == base)
+        .map(|&(_, onset, offset)| (onset, offset))
+}
+
+/// Similarity between two vowels when at least one is a diphthong
+///
+/// Each vowel is treated as a glide from onset to offset, a monophthong gliding
+/// nowhere, and the onsets and offsets are compared separately. Returns `None`
+/// when neither symbol is a diphthong or either is not a known vowel.
+pub fn diphthong_similarity(a: &str, b: &str) -> Option<f64> {
+    diphthong_similarity_with(a, b, &SimilarityWeights::default())
+}
+
+/// Similarity between two vowels when at least one is a diphthong, weighting features by `weights`
+pub fn diphthong_similarity_with(a: &str, b: &str, weights: &SimilarityWeights) -> Option<f64> {
+    let a_targets = diphthong_targets(a);
+    let b_targets = diphthong_targets(b);
+    if a_targets.is_none() && b_targets.is_none() {
+        return None;
+    }
+
+    // A monophthong glides from itself to itself
+    fn glide<'a>(
+        symbol: &'a str,
+        targets: Option<(&'a str, &'a str)>,
+    ) -> Option<(&'a str, &'a str)> {
+        let parsed = parse_ipa(symbol)?;
+        if !parsed.features.is_vowel {
+            return None;
+        }
+        Some(targets.unwrap_or((symbol, symbol)))
+    }
+
+    let (a_onset, a_offset) = glide(a, a_targets)?;
+    let (b_onset, b_offset) = glide(b, b_targets)?;
+
+    // Spellings of the same diphthong, such as MFA's "aj" for /aɪ/, match fully
+    if parse_ipa(a) == parse_ipa(b) {
+        return Some(1.0);
+    }
+
+    let target_similarity = |x: &str, y: &str| {
+        let x = PhonemeFeatures::from_ipa(x).unwrap_or_default();
+        let y = PhonemeFeatures::from_ipa(y).unwrap_or_default();
+        calculate_feature_similarity_with(&x, &y, weights)
+    };
+
+    let mut similarity = DIPHTHONG_ONSET_WEIGHT * target_similarity(a_onset, b_onset)
+        + (1.0 - DIPHTHONG_ONSET_WEIGHT) * target_similarity(a_offset, b_offset);
+
+    if a_targets.is_some() != b_targets.is_some() {
+        similarity *= GLIDE_MISMATCH_FACTOR;
+    }
+
+    // Different diphthongs never match perfectly, however close their targets
+    Some(similarity.min(0.9))
+}
+
+/// IPA phonemes and their phonetic features
+///
+/// Diphthongs are not listed; [`phoneme_features`] gives them the features of their onset.
+pub static IPA_PHONEME_FEATURES: &[(&str, PhonemeFeatures)] = &[
+    // Plosives (stops)
+    (
+        "p",
+        PhonemeFeatures {
+            is_plosive: true,
+            is_bilabial: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "b",
+        PhonemeFeatures {
+            is_plosive: true,
+            is_bilabial: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "t",
+        PhonemeFeatures {
+            is_plosive: true,
+            is_alveolar: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "d",
+        PhonemeFeatures {
+            is_plosive: true,
+            is_alveolar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "k",
+        PhonemeFeatures {
+            is_plosive: true,
+            is_velar: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "g",
+        PhonemeFeatures {
+            is_plosive: true,
+            is_velar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ɡ",
+        PhonemeFeatures {
+            is_plosive: true,
+            is_velar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Nasals
+    (
+        "m",
+        PhonemeFeatures {
+            is_nasal: true,
+            is_bilabial: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "n",
+        PhonemeFeatures {
+            is_nasal: true,
+            is_alveolar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ŋ",
+        PhonemeFeatures {
+            is_nasal: true,
+            is_velar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Fricatives
+    (
+        "f",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_labiodental: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "v",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_labiodental: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "θ",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_dental: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ð",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_dental: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "s",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_alveolar: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "z",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_alveolar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ʃ",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_postalveolar: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ʒ",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_postalveolar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "h",
+        PhonemeFeatures {
+            is_fricative: true,
+            is_glottal: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Affricates
+    (
+        "tʃ",
+        PhonemeFeatures {
+            is_affricate: true,
+            is_postalveolar: true,
+            is_voiced: false,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "dʒ",
+        PhonemeFeatures {
+            is_affricate: true,
+            is_postalveolar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Approximants
+    (
+        "ɹ",
+        PhonemeFeatures {
+            is_approximant: true,
+            is_alveolar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "j",
+        PhonemeFeatures {
+            is_approximant: true,
+            is_palatal: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "w",
+        PhonemeFeatures {
+            is_approximant: true,
+            is_bilabial: true,
+            is_velar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Lateral approximant
+    (
+        "l",
+        PhonemeFeatures {
+            is_approximant: true,
+            is_lateral: true,
+            is_alveolar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ɫ",
+        PhonemeFeatures {
+            is_approximant: true,
+            is_lateral: true,
+            is_alveolar: true,
+            is_velar: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Vowels - Front
+    (
+        "i",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_front: true,
+            is_close: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ɪ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_front: true,
+            is_close: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "e",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_front: true,
+            is_mid: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ɛ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_front: true,
+            is_mid: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "æ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_front: true,
+            is_open: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Vowels - Central
+    (
+        "ə",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_central: true,
+            is_mid: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ʌ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_central: true,
+            is_mid: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ɚ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_central: true,
+            is_mid: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ɝ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_central: true,
+            is_mid: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Vowels - Back
+    (
+        "u",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_back: true,
+            is_close: true,
+            is_rounded: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ʊ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_back: true,
+            is_close: true,
+            is_rounded: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "o",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_back: true,
+            is_mid: true,
+            is_rounded: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ɔ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_back: true,
+            is_mid: true,
+            is_rounded: true,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "ɑ",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_back: true,
+            is_open: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    (
+        "a",
+        PhonemeFeatures {
+            is_vowel: true,
+            is_front: true,
+            is_open: true,
+            is_rounded: false,
+            is_voiced: true,
+            ..PhonemeFeatures::NONE
+        },
+    ),
+    // Placeholder for phones the aligner could not identify
+    ("unknown", PhonemeFeatures::NONE),
+];
+
+/// Features of a phoneme in the table, without looking through diacritics
+pub fn phoneme_features(symbol: &str) -> Option<&'static PhonemeFeatures> {
+    // Diphthongs take the features of their onset; see `diphthong_targets` for the glide
+    let symbol = DIPHTHONG_TARGETS
+        .iter()
+        .find(|(diphthong, _, _)| *diphthong == symbol)
+        .map_or(symbol, |&(_, onset, _)| onset);
+
+    IPA_PHONEME_FEATURES
+        .iter()
+        .find(|(phoneme, _)| *phoneme == symbol)
+        .map(|(_, features)| features)
+}
+
+/// Why two different phoneme symbols are accepted as the same sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantKind {
+    /// Allophones of the same phoneme, acceptable in any accent
+    Allophone,
+    /// A full vowel for an expected schwa, which only occurs in unstressed syllables
+    Reduction,
+    /// A vowel of a dictionary with this rhoticity as realised in the other accent
+    Dialect(Rhoticity),
+}
+
+/// Pairs of expected and acceptable spoken phonemes; allophones also match the other way round
+static PHONEME_EQUIVALENCES: &[(&str, &str, VariantKind)] = &[
+    // Allophones
+    ("ɹ", "ɻ", VariantKind::Allophone),
+    ("ɫ", "ɫ̩", VariantKind::Allophone),
+    ("n", "n̩", VariantKind::Allophone),
+    ("m", "m̩", VariantKind::Allophone),
+    ("ʌ", "ɐ", VariantKind::Allophone),
+    ("ɒ", "ɒː", VariantKind::Allophone),
+    // Unstressed reductions, only from the expected schwa to the full vowel
+    ("ə", "ʌ", VariantKind::Reduction),
+    ("ə", "ɐ", VariantKind::Reduction),
+    // British realisations of American dictionary vowels
+    ("ɚ", "ə", VariantKind::Dialect(Rhoticity::Rhotic)),
+    ("ɝ", "ɜː", VariantKind::Dialect(Rhoticity::Rhotic)),
+    ("ɝ", "ɜ", VariantKind::Dialect(Rhoticity::Rhotic)),
+    ("ow", "əw", VariantKind::Dialect(Rhoticity::Rhotic)),
+    ("oʊ", "əʊ", VariantKind::Dialect(Rhoticity::Rhotic)),
+    ("ɑ", "ɒ", VariantKind::Dialect(Rhoticity::Rhotic)),
+    ("ɑ", "ɑː", VariantKind::Dialect(Rhoticity::Rhotic)),
+    ("ɛ", "ɛː", VariantKind::Dialect(Rhoticity::Rhotic)),
+    ("æ", "a", VariantKind::Dialect(Rhoticity::Rhotic)),
+    // The broad vowel of "bath" and "dance"
+    ("æ", "ɑː", VariantKind::Dialect(Rhoticity::Rhotic)),
+    // American realisations of British dictionary vowels
+    ("ə", "ɚ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("ɜː", "ɝ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("ɜ", "ɝ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("əw", "ow", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("əʊ", "oʊ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("ɒ", "ɑ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("ɑː", "ɑ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("ɛː", "ɛ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("a", "æ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+    ("ɑː", "æ", VariantKind::Dialect(Rhoticity::NonRhotic)),
+];
+
+/// Classify `actual` as an acceptable variant of `expected` from a dictionary with `rhoticity`, if it is one
+pub fn variant_kind(expected: &str, actual: &str, rhoticity: Rhoticity) -> Option<VariantKind> {
+    PHONEME_EQUIVALENCES
+        .iter()
+        .find(|&&(a, b, kind)| {
+            let forward = a == expected && b == actual;
+            match kind {
+                VariantKind::Allophone => forward || (a == actual && b == expected),
+                VariantKind::Reduction => forward,
+                VariantKind::Dialect(dictionary) => forward && dictionary == rhoticity,
+            }
+        })
+        .map(|&(_, _, kind)| kind)
+}
+
+/// Whether `actual` is the expected phoneme or an acceptable variant of it
+pub fn is_acceptable_variant(expected: &str, actual: &str, rhoticity: Rhoticity) -> bool {
+    expected == actual || variant_kind(expected, actual, rhoticity).is_some()
+}
+
+/// Whether an IPA or MFA phone symbol is a vowel, judged by its first character
+pub fn is_vowel(phoneme: &str) -> bool {
+    phoneme.chars().next().is_some_and(|c| {
+        matches!(
+            c,
+            'a' | 'e'
+                | 'i'
+                | 'o'
+                | 'u'
+                | 'y'
+                | 'æ'
+                | 'ɐ'
+                | 'ɑ'
+                | 'ɒ'
+                | 'ɔ'
+                | 'ə'
+                | 'ɘ'
+                | 'ɚ'
+                | 'ɛ'
+                | 'ɜ'
+                | 'ɝ'
+                | 'ɤ'
+                | 'ɨ'
+                | 'ɪ'
+                | 'ɯ'
+                | 'ɵ'
+                | 'ʉ'
+                | 'ʊ'
+                | 'ʌ'
+                | 'ø'
+                | 'œ'
+        )
+    })
+}
+
+/// Whether the phoneme at `index` is an /ɹ/ that non-rhotic accents drop
+///
+/// That is an /ɹ/ after a vowel and not before one, as in "car" or "hard".
+pub fn is_non_rhotic_r(phonemes: &[String], index: usize) -> bool {
+    let Some(phoneme) = phonemes.get(index) else {
+        return false;
+    };
+
+    let after_vowel = index > 0 && is_vowel(&phonemes[index - 1]);
+    let before_vowel = phonemes.get(index + 1).is_some_and(|p| is_vowel(p));
+
+    matches!(phoneme.as_str(), "ɹ" | "ɻ") && after_vowel && !before_vowel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phoneme_features_default() {
+        let features = PhonemeFeatures::default();
+
+        // Default should have all fields set to false
+        assert!(!features.is_plosive);
+        assert!(!features.is_fricative);
+        assert!(!features.is_affricate);
+        assert!(!features.is_nasal);
+        assert!(!features.is_approximant);
+        assert!(!features.is_lateral);
+
+        assert!(!features.is_bilabial);
+        assert!(!features.is_labiodental);
+        assert!(!features.is_dental);
+        assert!(!features.is_alveolar);
+        assert!(!features.is_postalveolar);
+        assert!(!features.is_palatal);
+        assert!(!features.is_velar);
+        assert!(!features.is_glottal);
+
+        assert!(!features.is_vowel);
+        assert!(!features.is_front);
+        assert!(!features.is_central);
+        assert!(!features.is_back);
+        assert!(!features.is_close);
+        assert!(!features.is_mid);
+        assert!(!features.is_open);
+        assert!(!features.is_rounded);
+
+        assert!(!features.is_voiced);
+    }
+
+    #[test]
+    fn test_calculate_feature_similarity() {
+        // Test identical features
+        let features_a = PhonemeFeatures {
+            is_plosive: true,
+            is_voiced: true,
+            is_bilabial: true,
+            ..PhonemeFeatures::default()
+        };
+
+        // Same features should have perfect similarity
+        assert_eq!(
+            calculate_feature_similarity(&features_a, &features_a),
+            1.0,
+            "Identical features should have similarity of 1.0"
+        );
+
+        // Test similar features with one difference
+        let features_b = PhonemeFeatures {
+            is_plosive: true,
+            is_voiced: false, // Only difference
+            is_bilabial: true,
+            ..PhonemeFeatures::default()
+        };
+
+        let similarity_with_one_diff = calculate_feature_similarity(&features_a, &features_b);
+        assert!(
+            similarity_with_one_diff < 1.0 && similarity_with_one_diff > 0.5,
+            "Similar features should have high but not perfect similarity: {}",
+            similarity_with_one_diff
+        );
+
+        // Test very different features
+        let features_c = PhonemeFeatures {
+            is_vowel: true,
+            is_open: true,
+            is_back: true,
+            ..PhonemeFeatures::default()
+        };
+
+        let similarity_different = calculate_feature_similarity(&features_a, &features_c);
+        assert!(
+            similarity_different < 0.5,
+            "Different feature types should have low similarity: {}",
+            similarity_different
+        );
+    }
+
+    #[test]
+    fn test_ipa_phoneme_features_map() {
+        // Test that common phonemes have entries in the map
+        let common_phonemes = ["p", "b", "t", "d", "k", "g", "a", "i", "u", "s", "z"];
+
+        for phoneme in common_phonemes {
+            assert!(
+                phoneme_features(phoneme).is_some(),
+                "Map should contain common phoneme '{}'",
+                phoneme
+            );
+        }
+
+        // Test some specific feature values
+
+        // 'p' should be a voiceless bilabial plosive
+        let p_features = phoneme_features("p").unwrap();
+        assert!(p_features.is_plosive);
+        assert!(p_features.is_bilabial);
+        assert!(!p_features.is_voiced);
+
+        // 'a' should be an open vowel
+        let a_features = phoneme_features("a").unwrap();
+        assert!(a_features.is_vowel);
+        assert!(a_features.is_open);
+        assert!(!a_features.is_plosive);
+    }
+
+    #[test]
+    fn test_calculate_feature_similarity_identical() {
+        // Test with identical features
+        let features_a = PhonemeFeatures {
+            is_plosive: true,
+            is_voiced: true,
+            is_bilabial: true,
+            ..PhonemeFeatures::default()
+        };
+
+        let similarity = calculate_feature_similarity(&features_a, &features_a);
+        assert_eq!(
+            similarity, 1.0,
+            "Identical features should have similarity of 1.0"
+        );
+    }
+
+    #[test]
+    fn test_calculate_feature_similarity_similar() {
+        // Test with similar features (one difference)
+        let features_a = PhonemeFeatures {
+            is_plosive: true,
+            is_voiced: true,
+            is_bilabial: true,
+            ..PhonemeFeatures::default()
+        };
+
+        let features_b = PhonemeFeatures {
+            is_plosive: true,
+            is_voiced: false, // Only difference
+            is_bilabial: true,
+            ..PhonemeFeatures::default()
+        };
+
+        let similarity = calculate_feature_similarity(&features_a, &features_b);
+        assert!(
+            similarity < 1.0 && similarity > 0.5,
+            "Similar features should have high but not perfect similarity: {}",
+            similarity
+        );
+    }
+
+    #[test]
+    fn test_calculate_feature_similarity_different() {
+        // Test with very different features
+        let consonant_features = PhonemeFeatures {
+            is_plosive: true,
+            is_bilabial: true,
+            ..PhonemeFeatures::default()
+        };
+
+        let vowel_features = PhonemeFeatures {
+            is_vowel: true,
+            is_open: true,
+            is_back: true,
+            ..PhonemeFeatures::default()
+        };
+
+        let similarity = calculate_feature_similarity(&consonant_features, &vowel_features);
+        assert!(
+            similarity < 0.5,
+            "Different feature types should have low similarity: {}",
+            similarity
+        );
+    }
+
+    #[test]
+    fn test_similarity_weight_presets() {
+        assert_eq!(
+            SimilarityWeights::preset(" Perceptual"),
+            Some(SimilarityWeights::PERCEPTUAL)
+        );
+        assert_eq!(SimilarityWeights::preset("acoustic"), None);
+
+        let p = phoneme_features("p").unwrap();
+        let b = phoneme_features("b").unwrap();
+        assert_eq!(
+            calculate_feature_similarity(p, b),
+            calculate_feature_similarity_with(p, b, &SimilarityWeights::ARTICULATORY)
+        );
+
+        // Weights left out of a configuration keep their defaults
+        let weights: SimilarityWeights = serde_json::from_str(r#"{"voicing": 0.5}"#).unwrap();
+        assert_eq!(weights.voicing, 0.5);
+        assert_eq!(weights.manner, SimilarityWeights::ARTICULATORY.manner);
+    }
+
+    #[test]
+    fn test_acceptable_variants() {
+        let us = Rhoticity::Rhotic;
+        let uk = Rhoticity::NonRhotic;
+        assert!(is_acceptable_variant("ɹ", "ɻ", us));
+        assert!(is_acceptable_variant("ɻ", "ɹ", uk));
+        assert!(is_acceptable_variant("ɚ", "ə", us));
+        assert!(is_acceptable_variant("ɜː", "ɝ", uk));
+        assert!(!is_acceptable_variant("p", "b", us));
+
+        // Schwa may surface as a full vowel, but a stressed vowel may not reduce
+        assert_eq!(variant_kind("ə", "ʌ", us), Some(VariantKind::Reduction));
+        assert_eq!(variant_kind("ʌ", "ə", us), None);
+
+        // Accent variants only stand in for the other accent's dictionary vowel
+        assert_eq!(
+            variant_kind("æ", "ɑː", us),
+            Some(VariantKind::Dialect(Rhoticity::Rhotic))
+        );
+        assert_eq!(variant_kind("æ", "ɑː", uk), None);
+        assert_eq!(variant_kind("ɑː", "æ", us), None);
+        assert!(!is_acceptable_variant("ɚ", "ə", uk));
+    }
+
+    #[test]
+    fn test_is_non_rhotic_r() {
+        let car: Vec<String> = ["kʰ", "ɑ", "ɹ"].iter().map(|s| s.to_string()).collect();
+        assert!(is_non_rhotic_r(&car, 2));
+
+        let hard: Vec<String> = ["h", "ɑ", "ɹ", "d"].iter().map(|s| s.to_string()).collect();
+        assert!(is_non_rhotic_r(&hard, 2));
+
+        // Linking /ɹ/ before a vowel and word-initial /ɹ/ are kept
+        let very: Vec<String> = ["v", "ɛ", "ɹ", "i"].iter().map(|s| s.to_string()).collect();
+        assert!(!is_non_rhotic_r(&very, 2));
+        let red: Vec<String> = ["ɹ", "ɛ", "d"].iter().map(|s| s.to_string()).collect();
+        assert!(!is_non_rhotic_r(&red, 0));
+    }
+
+    #[test]
+    fn test_feature_differences() {
+        let theta = phoneme_features("θ").unwrap();
+        let s = phoneme_features("s").unwrap();
+        assert_eq!(
+            feature_differences(theta, s),
+            vec![FeatureDifference::Place {
+                expected: Place::Dental,
+                actual: Place::Alveolar,
+            }]
+        );
+
+        let b = phoneme_features("b").unwrap();
+        let p = phoneme_features("p").unwrap();
+        assert_eq!(
+            feature_differences(b, p),
+            vec![FeatureDifference::Voicing {
+                expected_voiced: true
+            }]
+        );
+
+        let a = phoneme_features("a").unwrap();
+        assert_eq!(
+            feature_differences(a, p),
+            vec![FeatureDifference::VowelConsonant {
+                expected_vowel: true
+            }]
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let describe = |symbol: &str| phoneme_features(symbol).unwrap().describe();
+
+        assert_eq!(describe("θ"), "voiceless dental fricative");
+        assert_eq!(describe("l"), "voiced alveolar lateral approximant");
+        assert_eq!(describe("u"), "close back rounded vowel");
+        assert_eq!(describe("unknown"), "unknown sound");
+    }
+
+    #[test]
+    fn test_parse_ipa_diacritics() {
+        let aspirated = parse_ipa("kʰ").unwrap();
+        assert_eq!(aspirated.base, "k");
+        assert!(aspirated.aspirated);
+        assert_eq!(&aspirated.features, phoneme_features("k").unwrap());
+        assert_eq!(aspirated.describe(), "aspirated voiceless velar plosive");
+
+        let long = parse_ipa("ɑː").unwrap();
+        assert_eq!(long.base, "ɑ");
+        assert!(long.long);
+
+        let nasal = parse_ipa("æ\u{0303}").unwrap();
+        assert_eq!(nasal.base, "æ");
+        assert!(nasal.nasalized);
+
+        let dental = parse_ipa("t\u{032A}").unwrap();
+        assert_eq!(dental.features.place(), Some(Place::Dental));
+
+        let devoiced = parse_ipa("d\u{0325}").unwrap();
+        assert!(!devoiced.features.is_voiced());
+
+        assert_eq!(parse_ipa("ˈt͡ʃ").unwrap().base, "tʃ");
+        assert_eq!(parse_ipa("aj").unwrap().base, "aɪ");
+        assert!(parse_ipa("xyz").is_none());
+        assert!(parse_ipa("unknown").is_none());
+    }
+
+    #[test]
+    fn test_phoneme_features_round_trip() {
+        let features = PhonemeFeatures::from_ipa("ð").unwrap();
+        let json = serde_json::to_string(&features).unwrap();
+        let parsed: PhonemeFeatures = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, features);
+
+        // Missing flags default to false
+        let partial: PhonemeFeatures =
+            serde_json::from_str(r#"{"is_vowel": true, "is_open": true}"#).unwrap();
+        assert!(partial.is_vowel() && partial.is_open() && !partial.is_rounded());
+    }
+
+    #[test]
+    fn test_diphthong_targets() {
+        assert_eq!(diphthong_targets("aɪ"), Some(("a", "ɪ")));
+        assert_eq!(diphthong_targets("ej"), Some(("e", "ɪ")));
+        assert_eq!(diphthong_targets("a"), None);
+
+        // Diphthongs no longer carry contradictory height flags
+        let ai = phoneme_features("aɪ").unwrap();
+        assert!(ai.is_open() && !ai.is_close());
+    }
+
+    #[test]
+    fn test_diphthong_similarity() {
+        // Near onsets make /aɪ/ and /eɪ/ closer than /aɪ/ and /ɔɪ/
+        let ai_ei = diphthong_similarity("aɪ", "eɪ").unwrap();
+        let ai_oi = diphthong_similarity("aɪ", "ɔɪ").unwrap();
+        assert!(ai_ei > ai_oi, "{} should exceed {}", ai_ei, ai_oi);
+        assert!(ai_ei < 1.0);
+
+        // Dropping the glide keeps partial credit
+        let ei_e = diphthong_similarity("eɪ", "e").unwrap();
+        assert!(ei_e > 0.5 && ei_e < 0.9, "{}", ei_e);
+
+        // Diphthong against consonant, and two monophthongs, are not handled here
+        assert_eq!(diphthong_similarity("aɪ", "p"), None);
+        assert_eq!(diphthong_similarity("a", "e"), None);
+
+        // Aliases are the same diphthong
+        assert_eq!(diphthong_similarity("aj", "aɪ"), Some(1.0));
+        assert_eq!(diphthong_similarity("ow", "oʊ"), Some(1.0));
+        assert!(diphthong_similarity("aj", "eɪ").unwrap() < 1.0);
+    }
+}
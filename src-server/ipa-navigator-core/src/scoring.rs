@@ -0,0 +1,675 @@
+//! Scoring math for comparing spoken phonemes with expected pronunciations
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::phoneme::{
+    IPA_PHONEME_FEATURES, PhonemeFeatures, SimilarityWeights, VariantKind,
+    calculate_feature_similarity_with, diphthong_similarity_with, is_non_rhotic_r, variant_kind,
+};
+
+/// Allophonic variations a rubric can forgive as correct pronunciations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllophoneRule {
+    /// /t/ or /d/ realised as the flap [ɾ], as in American "water"
+    FlappedT,
+    /// /t/ realised as a glottal stop [ʔ], as in "button"
+    GlottalT,
+    /// Clear [l] and dark [ɫ] used interchangeably
+    DarkL,
+}
+
+impl AllophoneRule {
+    /// Whether this rule accepts `actual` as a realisation of `expected`
+    pub fn allows(&self, expected: &str, actual: &str) -> bool {
+        match self {
+            AllophoneRule::FlappedT => matches!(expected, "t" | "d") && actual == "ɾ",
+            AllophoneRule::GlottalT => expected == "t" && actual == "ʔ",
+            AllophoneRule::DarkL => matches!((expected, actual), ("l", "ɫ") | ("ɫ", "l")),
+        }
+    }
+}
+
+/// Named strictness profiles for learners at different levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    Beginner,
+    #[default]
+    Intermediate,
+    Strict,
+}
+
+impl Strictness {
+    /// Parse a profile name
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "beginner" | "easy" => Some(Strictness::Beginner),
+            "intermediate" | "normal" => Some(Strictness::Intermediate),
+            "strict" | "advanced" => Some(Strictness::Strict),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Strictness::Beginner => "beginner",
+            Strictness::Intermediate => "intermediate",
+            Strictness::Strict => "strict",
+        }
+    }
+
+    /// The scoring rubric for this profile
+    pub fn rubric(&self) -> ScoringRubric {
+        match self {
+            Strictness::Beginner => ScoringRubric {
+                full_credit_similarity: 0.7,
+                zero_credit_similarity: 0.2,
+                insertion_penalty: 0.5,
+                deletion_penalty: 0.8,
+                forgiven_allophones: vec![
+                    AllophoneRule::FlappedT,
+                    AllophoneRule::GlottalT,
+                    AllophoneRule::DarkL,
+                ],
+                accent_variants: true,
+                similarities: None,
+            },
+            Strictness::Intermediate => ScoringRubric {
+                full_credit_similarity: 0.9,
+                zero_credit_similarity: 0.3,
+                insertion_penalty: 0.8,
+                deletion_penalty: 1.0,
+                forgiven_allophones: vec![AllophoneRule::FlappedT, AllophoneRule::DarkL],
+                accent_variants: true,
+                similarities: None,
+            },
+            Strictness::Strict => ScoringRubric {
+                full_credit_similarity: 1.0,
+                zero_credit_similarity: 0.5,
+                insertion_penalty: 1.0,
+                deletion_penalty: 1.0,
+                forgiven_allophones: Vec::new(),
+                accent_variants: false,
+                similarities: None,
+            },
+        }
+    }
+}
+
+/// Thresholds and penalties used to turn phoneme similarity into a score
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringRubric {
+    /// Similarity at or above which a phoneme earns full credit
+    pub full_credit_similarity: f64,
+    /// Similarity at or below which a phoneme earns no credit
+    pub zero_credit_similarity: f64,
+    /// Credit lost for each phoneme spoken that was not expected
+    pub insertion_penalty: f64,
+    /// Credit lost for each expected phoneme that was not spoken
+    pub deletion_penalty: f64,
+    /// Allophonic variations scored as correct
+    pub forgiven_allophones: Vec<AllophoneRule>,
+    /// Whether the other accent's realisation of a dictionary vowel is scored as correct
+    pub accent_variants: bool,
+    /// Precomputed similarities to look up instead of comparing features
+    pub similarities: Option<&'static SimilarityMatrix>,
+}
+
+impl Default for ScoringRubric {
+    fn default() -> Self {
+        Strictness::default().rubric()
+    }
+}
+
+impl ScoringRubric {
+    /// Look similarities up in `matrix` rather than computing them for every pair scored
+    pub fn with_similarities(mut self, matrix: &'static SimilarityMatrix) -> Self {
+        self.similarities = Some(matrix);
+        self
+    }
+
+    /// Score a spoken phoneme against the expected one from a dictionary with `rhoticity`
+    ///
+    /// Allophones and reductions from the equivalence table are always
+    /// accepted, and the other accent's vowels unless the rubric excludes them.
+    pub fn score(&self, expected: &str, actual: &str, rhoticity: Rhoticity) -> f64 {
+        let variant = variant_kind(expected, actual, rhoticity)
+            .is_some_and(|kind| self.accent_variants || !matches!(kind, VariantKind::Dialect(_)));
+        if expected == actual
+            || variant
+            || self
+                .forgiven_allophones
+                .iter()
+                .any(|rule| rule.allows(expected, actual))
+        {
+            return 1.0;
+        }
+
+        let similarity = self.similarities.map_or_else(
+            || phoneme_similarity(expected, actual),
+            |matrix| matrix.similarity(expected, actual),
+        );
+        self.score_similarity(similarity)
+    }
+
+    /// Map a feature similarity onto a score, linearly between the two thresholds
+    pub fn score_similarity(&self, similarity: f64) -> f64 {
+        if similarity >= self.full_credit_similarity {
+            1.0
+        } else if similarity <= self.zero_credit_similarity {
+            0.0
+        } else {
+            (similarity - self.zero_credit_similarity)
+                / (self.full_credit_similarity - self.zero_credit_similarity)
+        }
+    }
+}
+
+/// Calculate phoneme similarity based on phonetic features
+pub fn phoneme_similarity(a: &str, b: &str) -> f64 {
+    phoneme_similarity_with(a, b, &SimilarityWeights::default())
+}
+
+/// Calculate phoneme similarity, weighting phonetic features by `weights`
+pub fn phoneme_similarity_with(a: &str, b: &str, weights: &SimilarityWeights) -> f64 {
+    // If strings are identical, return perfect score
+    if a == b {
+        return 1.0;
+    }
+
+    // Vowel glides are compared target by target
+    if let Some(similarity) = diphthong_similarity_with(a, b, weights) {
+        return similarity;
+    }
+
+    // Get features for each phoneme, looking through diacritics such as aspiration
+    let a_features = PhonemeFeatures::from_ipa(a).unwrap_or_default();
+    let b_features = PhonemeFeatures::from_ipa(b).unwrap_or_default();
+
+    // Calculate similarity based on shared features
+    calculate_feature_similarity_with(&a_features, &b_features, weights)
+}
+
+/// Similarity of every pair of phonemes in the feature table
+///
+/// Building the matrix compares each pair once, so scoring can look
+/// similarities up instead of comparing features again for every phoneme.
+/// Symbols with diacritics are not in the table and must still be compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarityMatrix {
+    weights: SimilarityWeights,
+    symbols: Vec<&'static str>,
+    index: BTreeMap<&'static str, usize>,
+    values: Vec<f64>,
+}
+
+impl SimilarityMatrix {
+    /// Compare every pair of phonemes in [`IPA_PHONEME_FEATURES`] with the default weights
+    pub fn from_features() -> Self {
+        Self::with_weights(SimilarityWeights::default())
+    }
+
+    /// Compare every pair of phonemes in [`IPA_PHONEME_FEATURES`], weighting features by `weights`
+    pub fn with_weights(weights: SimilarityWeights) -> Self {
+        let symbols: Vec<&'static str> = IPA_PHONEME_FEATURES
+            .iter()
+            .map(|(symbol, _)| *symbol)
+            .filter(|symbol| *symbol != "unknown")
+            .collect();
+        let index = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (*symbol, i))
+            .collect();
+        let values = symbols
+            .iter()
+            .flat_map(|a| {
+                symbols
+                    .iter()
+                    .map(move |b| phoneme_similarity_with(a, b, &weights))
+            })
+            .collect();
+
+        Self {
+            weights,
+            symbols,
+            index,
+            values,
+        }
+    }
+
+    /// The weights the similarities were computed with
+    pub fn weights(&self) -> &SimilarityWeights {
+        &self.weights
+    }
+
+    /// The phonemes labelling the rows and columns, in feature table order
+    pub fn symbols(&self) -> &[&'static str] {
+        &self.symbols
+    }
+
+    /// Similarities of `symbol` to every phoneme, in the order of [`symbols`](Self::symbols)
+    pub fn row(&self, symbol: &str) -> Option<&[f64]> {
+        let n = self.symbols.len();
+        let i = *self.index.get(symbol)?;
+        Some(&self.values[i * n..(i + 1) * n])
+    }
+
+    /// Similarity of two phonemes, or `None` if either is not in the table
+    pub fn get(&self, a: &str, b: &str) -> Option<f64> {
+        let j = *self.index.get(b)?;
+        self.row(a).map(|row| row[j])
+    }
+
+    /// Similarity of two phonemes, compared with the matrix's weights when not in the table
+    pub fn similarity(&self, a: &str, b: &str) -> f64 {
+        self.get(a, b)
+            .unwrap_or_else(|| phoneme_similarity_with(a, b, &self.weights))
+    }
+}
+
+/// Whether a pronunciation dictionary writes /ɹ/ after vowels, as in "car"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rhoticity {
+    /// Post-vocalic /ɹ/ is written, as in American English
+    Rhotic,
+    /// Post-vocalic /ɹ/ is omitted, as in British English
+    NonRhotic,
+}
+
+/// An expected phoneme paired with what was spoken in its place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhonemeMatch {
+    /// Index into the expected phonemes, `None` for an inserted phoneme
+    pub expected: Option<usize>,
+    /// Index into the spoken phonemes, `None` for a deleted phoneme
+    pub actual: Option<usize>,
+    pub score: f64,
+}
+
+/// Pair spoken phonemes with expected ones in order and score each pair under the rubric
+///
+/// Matched pairs come first, then expected phonemes that were not spoken, then
+/// spoken phonemes that were not expected. Post-vocalic /ɹ/ is not counted
+/// against speakers whose accent differs from the dictionary's `rhoticity`.
+pub fn match_phonemes(
+    expected: &[String],
+    actual: &[String],
+    rhoticity: Rhoticity,
+    rubric: &ScoringRubric,
+) -> Vec<PhonemeMatch> {
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    // Match phonemes one-by-one as best we can
+    while i < expected.len() && j < actual.len() {
+        let actual_is_r = matches!(actual[j].as_str(), "ɹ" | "ɻ");
+
+        // Non-rhotic speakers drop post-vocalic /ɹ/ a rhotic dictionary expects
+        if rhoticity == Rhoticity::Rhotic && !actual_is_r && is_non_rhotic_r(expected, i) {
+            i += 1;
+            continue;
+        }
+
+        // Rhotic speakers add post-vocalic /ɹ/ a non-rhotic dictionary omits
+        if rhoticity == Rhoticity::NonRhotic
+            && actual_is_r
+            && !matches!(expected[i].as_str(), "ɹ" | "ɻ")
+            && is_non_rhotic_r(actual, j)
+        {
+            j += 1;
+            continue;
+        }
+
+        matches.push(PhonemeMatch {
+            expected: Some(i),
+            actual: Some(j),
+            score: rubric.score(&expected[i], &actual[j], rhoticity),
+        });
+
+        i += 1;
+        j += 1;
+    }
+
+    // Handle remaining expected phonemes (missing in actual)
+    for k in i..expected.len() {
+        if rhoticity == Rhoticity::Rhotic && is_non_rhotic_r(expected, k) {
+            continue;
+        }
+
+        matches.push(PhonemeMatch {
+            expected: Some(k),
+            actual: None,
+            score: 1.0 - rubric.deletion_penalty,
+        });
+    }
+
+    // Handle additional actual phonemes (not expected)
+    for k in j..actual.len() {
+        if rhoticity == Rhoticity::NonRhotic && is_non_rhotic_r(actual, k) {
+            continue;
+        }
+
+        matches.push(PhonemeMatch {
+            expected: None,
+            actual: Some(k),
+            score: 1.0 - rubric.insertion_penalty,
+        });
+    }
+
+    matches
+}
+
+/// Mean of the phoneme scores, or 0 when nothing was scored
+pub fn overall_score(scores: impl IntoIterator<Item = f64>) -> f64 {
+    let (total, count) = scores
+        .into_iter()
+        .fold((0.0, 0usize), |(total, count), score| {
+            (total + score, count + 1)
+        });
+
+    if count == 0 {
+        0.0
+    } else {
+        total / count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    fn phonemes(symbols: &[&str]) -> Vec<String> {
+        symbols.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_phoneme_similarity_exact_match() {
+        // Test exact matches for different phonemes
+        assert_eq!(
+            phoneme_similarity("p", "p"),
+            1.0,
+            "Identical consonants should match exactly"
+        );
+        assert_eq!(
+            phoneme_similarity("a", "a"),
+            1.0,
+            "Identical vowels should match exactly"
+        );
+        assert_eq!(
+            phoneme_similarity("ŋ", "ŋ"),
+            1.0,
+            "Identical special symbols should match exactly"
+        );
+    }
+
+    #[test]
+    fn test_phoneme_similarity_consonant_pairs() {
+        // Test pairs of consonants with different relationships
+
+        // Test voice pairs (should be somewhat similar)
+        let voiced_voiceless = phoneme_similarity("b", "p");
+        assert!(
+            voiced_voiceless > 0.5,
+            "Voice pairs should be similar: {}",
+            voiced_voiceless
+        );
+        assert!(
+            voiced_voiceless < 1.0,
+            "Voice pairs should not be identical: {}",
+            voiced_voiceless
+        );
+
+        // Test same place but different manner
+        let plosive_nasal = phoneme_similarity("b", "m");
+        assert!(
+            plosive_nasal > 0.2,
+            "Same place phonemes should have some similarity: {}",
+            plosive_nasal
+        );
+
+        // Test different place but same manner
+        let different_place = phoneme_similarity("p", "t");
+        assert!(
+            different_place > 0.2,
+            "Same manner phonemes should have some similarity: {}",
+            different_place
+        );
+
+        // Test completely different consonants
+        let completely_different = phoneme_similarity("p", "z");
+        assert!(
+            completely_different < voiced_voiceless,
+            "Very different consonants should be less similar than voice pairs: {}",
+            completely_different
+        );
+    }
+
+    #[test]
+    fn test_phoneme_similarity_vowel_pairs() {
+        // Test pairs of vowels with different relationships
+
+        // Test height neighbors
+        let height_neighbors = phoneme_similarity("i", "e");
+        assert!(
+            height_neighbors > 0.4,
+            "Vowel height neighbors should be similar: {}",
+            height_neighbors
+        );
+
+        // Test backness neighbors
+        let backness_neighbors = phoneme_similarity("e", "ə");
+        assert!(
+            backness_neighbors > 0.4,
+            "Vowel backness neighbors should be similar: {}",
+            backness_neighbors
+        );
+
+        // Test rounding pairs
+        let rounded_unrounded = phoneme_similarity("u", "i");
+        assert!(
+            rounded_unrounded > 0.2,
+            "Vowels differing mainly in rounding should have some similarity: {}",
+            rounded_unrounded
+        );
+
+        // Test very different vowels
+        let very_different_vowels = phoneme_similarity("i", "ɑ");
+        assert!(
+            very_different_vowels < height_neighbors,
+            "Very different vowels should be less similar than height neighbors: {}",
+            very_different_vowels
+        );
+    }
+
+    #[test]
+    fn test_phoneme_similarity_vowel_consonant_pairs() {
+        // Vowels and consonants should have minimal similarity
+        let vowel_consonant = phoneme_similarity("a", "p");
+        assert!(
+            vowel_consonant < 0.3,
+            "Vowels and consonants should have low similarity: {}",
+            vowel_consonant
+        );
+    }
+
+    #[test]
+    fn test_rubric_similarity_mapping() {
+        let rubric = Strictness::Intermediate.rubric();
+        assert_eq!(rubric.score_similarity(0.95), 1.0);
+        assert_eq!(rubric.score_similarity(0.2), 0.0);
+        assert!((rubric.score_similarity(0.6) - 0.5).abs() < 1e-9);
+
+        // The same near miss earns more credit from a lenient rubric
+        assert!(
+            Strictness::Beginner
+                .rubric()
+                .score("b", "p", Rhoticity::Rhotic)
+                > Strictness::Strict
+                    .rubric()
+                    .score("b", "p", Rhoticity::Rhotic),
+            "Beginner should be more forgiving than strict"
+        );
+    }
+
+    #[test]
+    fn test_rubric_forgives_allophones() {
+        let us = Rhoticity::Rhotic;
+        assert_eq!(Strictness::Beginner.rubric().score("t", "ʔ", us), 1.0);
+        assert_eq!(Strictness::Intermediate.rubric().score("t", "ɾ", us), 1.0);
+        assert_eq!(Strictness::Intermediate.rubric().score("l", "ɫ", us), 1.0);
+        assert_eq!(Strictness::Strict.rubric().score("ɹ", "ɻ", us), 1.0);
+        assert!(Strictness::Strict.rubric().score("t", "ɾ", us) < 1.0);
+        assert!(Strictness::Strict.rubric().score("l", "ɫ", us) < 1.0);
+    }
+
+    #[test]
+    fn test_strict_rubric_penalises_accent_variants() {
+        let phonemes =
+            |list: &[&str]| -> Vec<String> { list.iter().map(|p| p.to_string()).collect() };
+        // "cat" from an American dictionary, said as "cart" without the /ɹ/
+        let cat = phonemes(&["k", "æ", "t"]);
+        let cart = phonemes(&["k", "ɑː", "t"]);
+        let vowel_score = |strictness: Strictness, rhoticity| {
+            match_phonemes(&cat, &cart, rhoticity, &strictness.rubric())[1].score
+        };
+
+        assert_eq!(
+            vowel_score(Strictness::Intermediate, Rhoticity::Rhotic),
+            1.0
+        );
+        assert!(vowel_score(Strictness::Strict, Rhoticity::Rhotic) < 1.0);
+        // The broad vowel is not how British speakers say "cat" either
+        assert!(vowel_score(Strictness::Intermediate, Rhoticity::NonRhotic) < 1.0);
+    }
+
+    #[test]
+    fn test_strictness_parse() {
+        assert_eq!(Strictness::parse("Beginner"), Some(Strictness::Beginner));
+        assert_eq!(Strictness::parse(" strict "), Some(Strictness::Strict));
+        assert_eq!(Strictness::parse("expert"), None);
+    }
+
+    #[test]
+    fn test_phoneme_similarity_unknown_phonemes() {
+        // Test with unknown phonemes
+        assert!(
+            phoneme_similarity("unknown", "p") < 0.5,
+            "Unknown phoneme should have low similarity to any real phoneme"
+        );
+        assert_eq!(
+            phoneme_similarity("unknown", "unknown"),
+            1.0,
+            "Unknown phoneme should match itself"
+        );
+
+        // Test with phoneme not in the features map
+        assert!(
+            phoneme_similarity("xyz", "p") < 0.5,
+            "Non-existent phoneme should have low similarity to any real phoneme"
+        );
+    }
+
+    #[test]
+    fn test_match_phonemes_insertions_and_deletions() {
+        let rubric = Strictness::Strict.rubric();
+        let matches = match_phonemes(
+            &phonemes(&["k", "æ", "t"]),
+            &phonemes(&["k", "æ"]),
+            Rhoticity::Rhotic,
+            &rubric,
+        );
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[2].expected, Some(2));
+        assert_eq!(matches[2].actual, None);
+        assert_eq!(matches[2].score, 1.0 - rubric.deletion_penalty);
+
+        let matches = match_phonemes(
+            &phonemes(&["k", "æ"]),
+            &phonemes(&["k", "æ", "t"]),
+            Rhoticity::Rhotic,
+            &rubric,
+        );
+        assert_eq!(matches[2].expected, None);
+        assert_eq!(matches[2].actual, Some(2));
+    }
+
+    #[test]
+    fn test_match_phonemes_rhoticity() {
+        let rubric = ScoringRubric::default();
+
+        // A non-rhotic "car" against a rhotic dictionary
+        let matches = match_phonemes(
+            &phonemes(&["k", "ɑ", "ɹ"]),
+            &phonemes(&["k", "ɑ"]),
+            Rhoticity::Rhotic,
+            &rubric,
+        );
+        assert_eq!(matches.len(), 2);
+        assert_eq!(overall_score(matches.iter().map(|m| m.score)), 1.0);
+
+        // A rhotic "far" against a non-rhotic dictionary
+        let matches = match_phonemes(
+            &phonemes(&["f", "ɑ"]),
+            &phonemes(&["f", "ɑ", "ɹ"]),
+            Rhoticity::NonRhotic,
+            &rubric,
+        );
+        assert_eq!(matches.len(), 2);
+    }
+
+    #[test]
+    fn test_similarity_matrix_matches_feature_comparison() {
+        let matrix = SimilarityMatrix::from_features();
+
+        for a in matrix.symbols() {
+            for b in matrix.symbols() {
+                assert_eq!(matrix.get(a, b), Some(phoneme_similarity(a, b)));
+            }
+        }
+        assert_eq!(matrix.get("p", "pʰ"), None);
+        assert_eq!(matrix.get("unknown", "p"), None);
+        assert_eq!(
+            matrix.row("p").map(<[f64]>::len),
+            Some(matrix.symbols().len())
+        );
+    }
+
+    #[test]
+    fn test_rubric_scores_with_similarity_matrix() {
+        let matrix: &'static SimilarityMatrix =
+            alloc::boxed::Box::leak(alloc::boxed::Box::new(SimilarityMatrix::from_features()));
+        let plain = Strictness::Intermediate.rubric();
+        let fast = plain.clone().with_similarities(matrix);
+
+        for (expected, actual) in [("b", "p"), ("i", "ɪ"), ("tʰ", "d"), ("t", "ɾ"), ("aɪ", "a")]
+        {
+            assert_eq!(
+                fast.score(expected, actual, Rhoticity::Rhotic),
+                plain.score(expected, actual, Rhoticity::Rhotic)
+            );
+        }
+    }
+
+    #[test]
+    fn test_similarity_matrix_uses_its_weights() {
+        let perceptual = SimilarityMatrix::with_weights(SimilarityWeights::PERCEPTUAL);
+        let articulatory = SimilarityMatrix::from_features();
+
+        // Place errors count for less, and voicing errors for more, perceptually
+        assert!(perceptual.similarity("p", "t") > articulatory.similarity("p", "t"));
+        assert!(perceptual.similarity("p", "b") < articulatory.similarity("p", "b"));
+        // Symbols outside the table are compared with the same weights
+        assert!(perceptual.similarity("pʰ", "t") > articulatory.similarity("pʰ", "t"));
+        assert_eq!(perceptual.weights(), &SimilarityWeights::PERCEPTUAL);
+    }
+
+    #[test]
+    fn test_overall_score() {
+        assert_eq!(overall_score([]), 0.0);
+        assert!((overall_score([1.0, 0.5]) - 0.75).abs() < 1e-9);
+    }
+}
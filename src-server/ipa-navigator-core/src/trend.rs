@@ -0,0 +1,200 @@
+//! Rates of improvement from a series of scores
+//!
+//! Scores are smoothed with an exponential moving average, and the rate is the
+//! least-squares slope of the smoothed scores over time, in score per week.
+//! Times are milliseconds since the Unix epoch.
+
+use alloc::vec::Vec;
+
+/// Weight of the newest score in the moving average
+pub const DEFAULT_SMOOTHING: f64 = 0.3;
+
+/// Scores needed before a trend is classified
+pub const MIN_TREND_SAMPLES: usize = 5;
+
+/// Change in score per week below which progress counts as a plateau
+pub const PLATEAU_RATE: f64 = 0.02;
+
+/// Milliseconds in a week
+const WEEK_MS: f64 = 7.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// A score at a point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub timestamp_ms: i64,
+    /// Score from 0 to 1
+    pub score: f64,
+}
+
+/// A score and the moving average up to and including it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothedSample {
+    pub timestamp_ms: i64,
+    pub score: f64,
+    pub smoothed: f64,
+}
+
+/// Which way a series of scores is heading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Improving,
+    Plateau,
+    Regressing,
+}
+
+impl Trend {
+    /// Classify a rate of change in score per week
+    pub fn from_rate(rate_per_week: f64) -> Self {
+        if rate_per_week >= PLATEAU_RATE {
+            Trend::Improving
+        } else if rate_per_week <= -PLATEAU_RATE {
+            Trend::Regressing
+        } else {
+            Trend::Plateau
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Trend::Improving => "improving",
+            Trend::Plateau => "plateau",
+            Trend::Regressing => "regressing",
+        }
+    }
+}
+
+/// A smoothed series and where it is heading
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreTrend {
+    /// Oldest first
+    pub points: Vec<SmoothedSample>,
+    /// Change in smoothed score per week, none with too few samples
+    pub rate_per_week: Option<f64>,
+    pub trend: Option<Trend>,
+}
+
+/// Smooth samples in time order, where `alpha` from 0 to 1 weights the newest score
+pub fn smooth(samples: &[Sample], alpha: f64) -> Vec<SmoothedSample> {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let mut sorted = samples.to_vec();
+    sorted.sort_by_key(|sample| sample.timestamp_ms);
+
+    let mut average = None;
+    sorted
+        .into_iter()
+        .map(|sample| {
+            let smoothed = match average {
+                Some(previous) => alpha * sample.score + (1.0 - alpha) * previous,
+                None => sample.score,
+            };
+            average = Some(smoothed);
+            SmoothedSample {
+                timestamp_ms: sample.timestamp_ms,
+                score: sample.score,
+                smoothed,
+            }
+        })
+        .collect()
+}
+
+/// Least-squares slope of the smoothed scores, in score per week
+///
+/// None with fewer than [`MIN_TREND_SAMPLES`] points or when they were all
+/// recorded at the same time.
+pub fn rate_per_week(points: &[SmoothedSample]) -> Option<f64> {
+    if points.len() < MIN_TREND_SAMPLES {
+        return None;
+    }
+
+    // Measured from the first point, so large timestamps keep their precision
+    let start = points[0].timestamp_ms;
+    let weeks = |point: &SmoothedSample| (point.timestamp_ms - start) as f64 / WEEK_MS;
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(weeks).sum::<f64>() / n;
+    let mean_y = points.iter().map(|point| point.smoothed).sum::<f64>() / n;
+
+    let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), point| {
+        let dx = weeks(point) - mean_x;
+        (cov + dx * (point.smoothed - mean_y), var + dx * dx)
+    });
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+/// Smooth samples and classify where they are heading
+pub fn score_trend(samples: &[Sample], alpha: f64) -> ScoreTrend {
+    let points = smooth(samples, alpha);
+    let rate_per_week = rate_per_week(&points);
+
+    ScoreTrend {
+        points,
+        rate_per_week,
+        trend: rate_per_week.map(Trend::from_rate),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+    /// One sample a day from the given scores
+    fn daily(scores: &[f64]) -> Vec<Sample> {
+        scores
+            .iter()
+            .enumerate()
+            .map(|(day, &score)| Sample {
+                timestamp_ms: 1_700_000_000_000 + day as i64 * DAY_MS,
+                score,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_smooths_in_time_order() {
+        let mut samples = daily(&[0.0, 1.0, 1.0]);
+        samples.reverse();
+        let points = smooth(&samples, 0.5);
+
+        let smoothed: Vec<f64> = points.iter().map(|point| point.smoothed).collect();
+        assert_eq!(smoothed, vec![0.0, 0.5, 0.75]);
+        assert!(
+            points
+                .windows(2)
+                .all(|w| w[0].timestamp_ms < w[1].timestamp_ms)
+        );
+    }
+
+    #[test]
+    fn test_classifies_trends() {
+        let improving = score_trend(&daily(&[0.4, 0.45, 0.5, 0.55, 0.6, 0.65]), 0.5);
+        assert_eq!(improving.trend, Some(Trend::Improving));
+        assert!(improving.rate_per_week.unwrap() > 0.0);
+
+        let plateau = score_trend(&daily(&[0.7, 0.72, 0.69, 0.71, 0.7, 0.7]), 0.3);
+        assert_eq!(plateau.trend, Some(Trend::Plateau));
+
+        let regressing = score_trend(&daily(&[0.9, 0.85, 0.8, 0.7, 0.65, 0.6]), 0.5);
+        assert_eq!(regressing.trend, Some(Trend::Regressing));
+    }
+
+    #[test]
+    fn test_needs_enough_samples_over_time() {
+        let few = score_trend(&daily(&[0.2, 0.9]), DEFAULT_SMOOTHING);
+        assert_eq!(few.points.len(), 2);
+        assert_eq!(few.trend, None);
+
+        let same_time = vec![
+            Sample {
+                timestamp_ms: 0,
+                score: 0.5
+            };
+            MIN_TREND_SAMPLES
+        ];
+        assert_eq!(
+            score_trend(&same_time, DEFAULT_SMOOTHING).rate_per_week,
+            None
+        );
+    }
+}
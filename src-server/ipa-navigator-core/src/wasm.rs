@@ -0,0 +1,102 @@
+//! JavaScript bindings for showing phoneme information and scoring in the browser
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use crate::phoneme::parse_ipa;
+use crate::scoring::{Rhoticity, Strictness, match_phonemes, overall_score};
+
+/// Articulatory description of an IPA symbol
+#[wasm_bindgen(getter_with_clone)]
+pub struct PhonemeInfo {
+    /// Base phoneme without diacritics
+    pub base: String,
+    /// Phonetic description, e.g. "voiceless dental fricative"
+    pub description: String,
+    #[wasm_bindgen(js_name = isVowel)]
+    pub is_vowel: bool,
+    pub voiced: bool,
+    pub rounded: bool,
+    /// Place of articulation, for consonants
+    pub place: Option<String>,
+    /// Manner of articulation, for consonants
+    pub manner: Option<String>,
+    /// Tongue height, for vowels
+    pub height: Option<String>,
+    /// Tongue backness, for vowels
+    pub backness: Option<String>,
+}
+
+/// A scored pair of expected and spoken phonemes
+#[wasm_bindgen(getter_with_clone)]
+pub struct PhonemeScore {
+    /// Expected phoneme, missing when an extra phoneme was spoken
+    pub expected: Option<String>,
+    /// Spoken phoneme, missing when an expected phoneme was dropped
+    pub actual: Option<String>,
+    pub score: f64,
+}
+
+/// Features of an IPA symbol, or `undefined` if it is not recognised
+#[wasm_bindgen(js_name = phonemeInfo)]
+pub fn phoneme_info(symbol: &str) -> Option<PhonemeInfo> {
+    let parsed = parse_ipa(symbol)?;
+    let features = &parsed.features;
+
+    Some(PhonemeInfo {
+        description: parsed.describe(),
+        is_vowel: features.is_vowel(),
+        voiced: features.is_voiced(),
+        rounded: features.is_rounded(),
+        place: features.place().map(|p| p.as_str().to_string()),
+        manner: features.manner().map(|m| m.as_str().to_string()),
+        height: features.height().map(|h| h.as_str().to_string()),
+        backness: features.backness().map(|b| b.as_str().to_string()),
+        base: parsed.base,
+    })
+}
+
+/// Similarity between two IPA symbols from 0 to 1
+#[wasm_bindgen(js_name = phonemeSimilarity)]
+pub fn phoneme_similarity(a: &str, b: &str) -> f64 {
+    crate::scoring::phoneme_similarity(a, b)
+}
+
+/// Pair spoken phonemes with expected ones and score them under a strictness profile
+///
+/// `rhotic` says whether the expected pronunciation writes post-vocalic /ɹ/,
+/// as American dictionaries do.
+#[wasm_bindgen(js_name = scorePhonemes)]
+pub fn score_phonemes(
+    expected: Vec<String>,
+    actual: Vec<String>,
+    strictness: &str,
+    rhotic: bool,
+) -> Result<Vec<PhonemeScore>, JsError> {
+    let strictness =
+        Strictness::parse(strictness).ok_or_else(|| JsError::new("Unsupported strictness"))?;
+    let rhoticity = if rhotic {
+        Rhoticity::Rhotic
+    } else {
+        Rhoticity::NonRhotic
+    };
+
+    Ok(
+        match_phonemes(&expected, &actual, rhoticity, &strictness.rubric())
+            .into_iter()
+            .map(|matched| PhonemeScore {
+                expected: matched.expected.map(|i| expected[i].clone()),
+                actual: matched.actual.map(|j| actual[j].clone()),
+                score: matched.score,
+            })
+            .collect(),
+    )
+}
+
+/// Mean of phoneme scores, or 0 for none
+#[wasm_bindgen(js_name = overallScore)]
+pub fn overall(scores: Vec<f64>) -> f64 {
+    overall_score(scores)
+}
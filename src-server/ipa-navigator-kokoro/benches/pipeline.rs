@@ -0,0 +1,124 @@
+//! Benchmarks of the TTS pipeline stages that run without the ONNX model
+
+use std::hint::black_box;
+use std::num::NonZeroUsize;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use ipa_navigator_kokoro::{
+    model::{STYLE_DIM, STYLE_FRAMES, style_vector},
+    normalize::normalize_text,
+    tokenize::tokenize,
+    tts::{CACHE_CAPACITY, KokoroTTS},
+    voices::{AmericanFemaleVoice, VoiceType},
+    wav::{WavFormat, encode_wav},
+};
+use lru::LruCache;
+
+const TEXTS: &[(&str, &str)] = &[
+    ("short", "Hello, Dr. Smith!"),
+    (
+        "medium",
+        "The quick brown fox jumps over the lazy dog, while Mr. Jones watches from 3 ft. away.",
+    ),
+    (
+        "long",
+        "Pronunciation practice works best in short, focused sessions. Listen to the reference \
+         recording, repeat it slowly, and compare the two. Pay attention to the vowels, which \
+         carry most of the accent, and to consonant clusters at the ends of words, which are \
+         easy to drop when speaking quickly. Over time, the differences become easier to hear.",
+    ),
+];
+
+/// Phonemes as produced by espeak for the medium text
+const PHONEMES: &str = "ðə kwˈɪk bɹˈaʊn fˈɑːks dʒˈʌmps ˌoʊvɚ ðə lˈeɪzi dˈɑːɡ, wˈaɪl mˈɪstɚ dʒˈoʊnz \
+                        wˈɑːtʃᵻz fɹʌm θɹˈiː fˈiːt ɐwˈeɪ.";
+
+fn bench_normalize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("normalize_text");
+    for (name, text) in TEXTS {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), text, |b, text| {
+            b.iter(|| normalize_text(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_tokenize(c: &mut Criterion) {
+    c.bench_function("tokenize", |b| b.iter(|| tokenize(black_box(PHONEMES))));
+}
+
+fn bench_style_vector(c: &mut Criterion) {
+    let embedding: Vec<f32> = (0..STYLE_FRAMES * STYLE_DIM)
+        .map(|i| (i % 97) as f32 / 97.0)
+        .collect();
+
+    c.bench_function("style_vector", |b| {
+        b.iter(|| style_vector(black_box(&embedding)))
+    });
+}
+
+fn bench_encode_wav(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_wav");
+    for seconds in [1, 5, 20] {
+        // Model output is 24 kHz
+        let samples: Vec<f32> = (0..seconds * 24000)
+            .map(|i| (i as f32 / 20.0).sin() * 0.5)
+            .collect();
+
+        group.throughput(Throughput::Elements(samples.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{}s", seconds)),
+            &samples,
+            |b, samples| b.iter(|| encode_wav(black_box(samples), &WavFormat::default()).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn bench_cache(c: &mut Criterion) {
+    let voice = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
+    let audio = vec![0.0f32; 24000 * 3];
+
+    c.bench_function("cache/key", |b| {
+        b.iter(|| KokoroTTS::generate_cache_key(black_box(PHONEMES), &voice, 1.0))
+    });
+
+    // Mirror the TTS cache: a full LRU of clips keyed by phonemes, voice, and speed
+    let mut cache = LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap());
+    let keys: Vec<String> = (0..CACHE_CAPACITY)
+        .map(|i| KokoroTTS::generate_cache_key(&format!("Sentence {}", i), &voice, 1.0))
+        .collect();
+    for key in &keys {
+        cache.put(key.clone(), audio.clone());
+    }
+
+    c.bench_function("cache/hit", |b| {
+        let mut i = 0;
+        b.iter(|| {
+            i = (i + 1) % keys.len();
+            black_box(cache.get(&keys[i]).cloned())
+        })
+    });
+
+    c.bench_function("cache/miss_and_insert", |b| {
+        let mut i = 0;
+        b.iter(|| {
+            i += 1;
+            let key = KokoroTTS::generate_cache_key(&format!("New sentence {}", i), &voice, 1.0);
+            if cache.get(&key).is_none() {
+                cache.put(key, audio.clone());
+            }
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_normalize,
+    bench_tokenize,
+    bench_style_vector,
+    bench_encode_wav,
+    bench_cache
+);
+criterion_main!(benches);
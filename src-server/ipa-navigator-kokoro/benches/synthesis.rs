@@ -0,0 +1,43 @@
+//! Benchmarks that need the ONNX model and voice files
+//!
+//! Run with `cargo bench -p ipa-navigator-kokoro --features model-benches`.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ipa_navigator_kokoro::{
+    phonemizer::text_to_phonemes_string,
+    tts::KokoroTTS,
+    voices::{AmericanFemaleVoice, VoiceType},
+};
+
+const TEXTS: &[(&str, &str)] = &[
+    ("short", "Hello there."),
+    (
+        "medium",
+        "The quick brown fox jumps over the lazy dog near the river bank.",
+    ),
+];
+
+fn bench_phonemize(c: &mut Criterion) {
+    c.bench_function("phonemize", |b| {
+        b.iter(|| text_to_phonemes_string(black_box(TEXTS[1].1), "en-us"))
+    });
+}
+
+fn bench_synthesize(c: &mut Criterion) {
+    let tts = KokoroTTS::new().expect("Kokoro model and voices should be in the assets directory");
+    let voice = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
+
+    let mut group = c.benchmark_group("synthesize");
+    group.sample_size(10);
+    for (name, text) in TEXTS {
+        group.bench_with_input(BenchmarkId::from_parameter(name), text, |b, text| {
+            b.iter(|| tts.synthesize(black_box(text), &voice, 1.0))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_phonemize, bench_synthesize);
+criterion_main!(benches);
@@ -0,0 +1,18 @@
+#![no_main]
+
+use ipa_navigator_kokoro::tokenize::{tokenize, tokens_to_phonemes};
+use ipa_navigator_kokoro::vocab::VOCABULARY;
+use libfuzzer_sys::fuzz_target;
+
+// Tokenizing keeps exactly the vocabulary characters of the input, and
+// decoding the tokens gives them back
+fuzz_target!(|phonemes: &str| {
+    let tokens = tokenize(phonemes);
+    let expected: String = phonemes
+        .chars()
+        .filter(|c| VOCABULARY.contains_key(c))
+        .collect();
+
+    assert_eq!(tokens_to_phonemes(&tokens), expected);
+    assert_eq!(tokenize(&expected), tokens);
+});
@@ -0,0 +1,166 @@
+//! End-to-end synthesis latency per text length and voice
+//!
+//! Usage: `cargo run --release --bin tts-bench -- [--runs N] [--voices af_bella,bf_emma|all]`
+
+use std::env;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use ipa_navigator_kokoro::{
+    tts::KokoroTTS,
+    voices::{ALL_VOICES, AmericanFemaleVoice, BritishFemaleVoice, VoiceType},
+};
+
+/// Sample rate of the model output
+const SAMPLE_RATE: f64 = 24000.0;
+
+const DEFAULT_RUNS: usize = 5;
+
+const TEXTS: &[(&str, &str)] = &[
+    ("tiny", "Hello."),
+    ("short", "Please read this sentence aloud."),
+    (
+        "medium",
+        "The quick brown fox jumps over the lazy dog while the farmer watches from the gate.",
+    ),
+    (
+        "long",
+        "Pronunciation practice works best in short, focused sessions. Listen to the reference \
+         recording, repeat it slowly, and compare the two. Pay attention to the vowels, which \
+         carry most of the accent, and to consonant clusters at the ends of words, which are \
+         easy to drop when speaking quickly.",
+    ),
+];
+
+struct Options {
+    runs: usize,
+    voices: Vec<VoiceType>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        runs: DEFAULT_RUNS,
+        voices: vec![
+            VoiceType::AmericanFemale(AmericanFemaleVoice::Bella),
+            VoiceType::BritishFemale(BritishFemaleVoice::Emma),
+        ],
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--runs" => {
+                options.runs = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or("--runs expects a positive number")?;
+            }
+            "--voices" => {
+                let names = args.next().ok_or("--voices expects a list of voices")?;
+                options.voices = parse_voices(&names)?;
+            }
+            "-h" | "--help" => {
+                return Err(
+                    "Usage: tts-bench [--runs N] [--voices af_bella,bf_emma|all]".to_string(),
+                );
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Parse voices by file stem, e.g. "af_bella"
+fn parse_voices(names: &str) -> Result<Vec<VoiceType>, String> {
+    if names == "all" {
+        return Ok(ALL_VOICES.to_vec());
+    }
+
+    names
+        .split(',')
+        .map(|name| {
+            ALL_VOICES
+                .iter()
+                .find(|voice| voice.file_name().trim_end_matches(".bin") == name.trim())
+                .copied()
+                .ok_or_else(|| format!("Unknown voice: {}", name))
+        })
+        .collect()
+}
+
+fn percentile(sorted: &[Duration], fraction: f64) -> Duration {
+    let index = ((sorted.len() - 1) as f64 * fraction).round() as usize;
+    sorted[index]
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let load_start = Instant::now();
+    let tts = match KokoroTTS::new() {
+        Ok(tts) => tts,
+        Err(e) => {
+            eprintln!("Failed to load Kokoro: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Model loaded in {:.0?}\n", load_start.elapsed());
+
+    println!(
+        "{:<12} {:<8} {:>6} {:>10} {:>10} {:>10} {:>8}",
+        "voice", "text", "chars", "mean", "p50", "max", "rtf"
+    );
+
+    for voice in &options.voices {
+        let name = voice.file_name().trim_end_matches(".bin");
+
+        // Warm up the session so the first measurement is not an outlier
+        if let Err(e) = tts.synthesize(TEXTS[0].1, voice, 1.0) {
+            eprintln!("{}: synthesis failed: {}", name, e);
+            return ExitCode::FAILURE;
+        }
+
+        for (label, text) in TEXTS {
+            let mut timings = Vec::with_capacity(options.runs);
+            let mut audio_seconds = 0.0;
+
+            for _ in 0..options.runs {
+                let start = Instant::now();
+                match tts.synthesize(text, voice, 1.0) {
+                    Ok(audio) => audio_seconds = audio.len() as f64 / SAMPLE_RATE,
+                    Err(e) => {
+                        eprintln!("{}: synthesis failed: {}", name, e);
+                        return ExitCode::FAILURE;
+                    }
+                }
+                timings.push(start.elapsed());
+            }
+
+            timings.sort();
+            let mean = timings.iter().sum::<Duration>() / timings.len() as u32;
+            // Real-time factor: seconds of compute per second of audio
+            let rtf = mean.as_secs_f64() / audio_seconds.max(f64::EPSILON);
+
+            println!(
+                "{:<12} {:<8} {:>6} {:>10.1?} {:>10.1?} {:>10.1?} {:>8.3}",
+                name,
+                label,
+                text.chars().count(),
+                mean,
+                percentile(&timings, 0.5),
+                timings[timings.len() - 1],
+                rtf
+            );
+        }
+    }
+
+    ExitCode::SUCCESS
+}
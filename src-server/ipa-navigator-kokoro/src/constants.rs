@@ -4,7 +4,7 @@ static DEFAULT_ASSETS_PATH: LazyLock<String> = LazyLock::new(|| {
     let manifest_dir = env!("CARGO_MANIFEST_DIR");
     let assets_path = PathBuf::from(manifest_dir)
         .parent()
-        .unwrap()
+        .expect("crate is in the workspace directory")
         .join("assets")
         .to_string_lossy()
         .to_string();
@@ -14,6 +14,18 @@ static DEFAULT_ASSETS_PATH: LazyLock<String> = LazyLock::new(|| {
 pub static ASSETS_PATH: LazyLock<String> =
     LazyLock::new(|| env::var("ASSETS_PATH").unwrap_or_else(|_| DEFAULT_ASSETS_PATH.to_string()));
 
+/// Inferences the model runs at once, each in its own ONNX session
+///
+/// Read from `TTS_MAX_PARALLEL_INFERENCES`. Every session holds a copy of the
+/// model's weights, so each one adds a few hundred megabytes of memory.
+pub static MAX_PARALLEL_INFERENCES: LazyLock<usize> = LazyLock::new(|| {
+    env::var("TTS_MAX_PARALLEL_INFERENCES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(1)
+        .max(1)
+});
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -9,21 +9,36 @@ pub enum TtsError {
     #[error("Failed to generate phonemes: {0}")]
     PhonemeError(String),
 
+    #[error("espeak-ng data not found: {0}")]
+    EspeakDataError(String),
+
     #[error("Failed to tokenize text: {0}")]
     TokenizationError(String),
 
     #[error("Inference error: {0}")]
     InferenceError(String),
 
+    #[error("Synthesis deadline passed before any audio was ready: {0}")]
+    DeadlineExceeded(String),
+
     #[error("Failed to load voice data: {0}")]
     VoiceDataError(String),
 
+    #[error("Voice file {file} is corrupt: {reason}")]
+    CorruptVoiceData { file: String, reason: String },
+
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
 
     #[error("ONNX Runtime error: {0}")]
     OrtError(#[from] Error),
 
+    #[error("Invalid normalization rule: {0}")]
+    RuleError(String),
+
     #[error("Download error: {0}")]
     DownloadError(String),
+
+    #[error("Failed to encode audio: {0}")]
+    EncodingError(#[from] hound::Error),
 }
@@ -0,0 +1,256 @@
+//! Location of the espeak-ng data directory
+//!
+//! espeak-ng reads its phoneme tables and dictionaries from an
+//! `espeak-ng-data` directory found through `ESPEAK_DATA_PATH` or a path
+//! compiled into the library. When neither points at the data, phonemization
+//! fails with an error that does not say why. The directory is resolved here
+//! instead, before espeak-ng is first initialized, so a missing directory is
+//! reported with the places that were searched and how to fix it.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::constants::ASSETS_PATH;
+use crate::error::TtsError;
+use crate::phonemizer::text_to_phonemes_string;
+
+/// Name of the data directory, both bundled under `ASSETS_PATH` and as installed
+pub const DATA_DIR_NAME: &str = "espeak-ng-data";
+
+/// Variable espeak-ng reads the data directory from
+const DATA_PATH_VAR: &str = "ESPEAK_DATA_PATH";
+
+/// Files every usable data directory contains; `en_dict` is needed for English
+const REQUIRED_FILES: [&str; 4] = ["phontab", "phonindex", "phondata", "en_dict"];
+
+/// Where distribution packages and Homebrew install the data
+const SYSTEM_DATA_DIRS: [&str; 6] = [
+    "/usr/share/espeak-ng-data",
+    "/usr/local/share/espeak-ng-data",
+    "/usr/lib/x86_64-linux-gnu/espeak-ng-data",
+    "/usr/lib/aarch64-linux-gnu/espeak-ng-data",
+    "/opt/homebrew/share/espeak-ng-data",
+    "/usr/local/opt/espeak-ng/share/espeak-ng-data",
+];
+
+/// Data directory espeak-ng was pointed at, set before its first use
+static CONFIGURED: OnceLock<PathBuf> = OnceLock::new();
+
+/// Why the default source found nothing, so it is not searched again on every call
+static AUTO_FAILURE: OnceLock<String> = OnceLock::new();
+
+/// Serializes configuration so only one source is ever resolved and applied
+static CONFIGURE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Where to look for the espeak-ng data directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum EspeakDataSource {
+    /// `ESPEAK_DATA_PATH` if set, else the bundled directory, else system locations
+    #[default]
+    Auto,
+    /// The directory bundled at `ASSETS_PATH/espeak-ng-data`
+    Bundled,
+    /// A directory given explicitly
+    Path(PathBuf),
+}
+
+impl EspeakDataSource {
+    /// Read `ESPEAK_DATA`, which is `auto`, `bundled`, or a path to the data directory
+    pub fn from_env() -> Self {
+        env::var("ESPEAK_DATA")
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "" | "auto" => EspeakDataSource::Auto,
+            "bundled" => EspeakDataSource::Bundled,
+            path => EspeakDataSource::Path(PathBuf::from(path)),
+        }
+    }
+
+    /// Directories to try, in order
+    fn candidates(&self) -> Vec<PathBuf> {
+        match self {
+            EspeakDataSource::Auto => env::var_os(DATA_PATH_VAR)
+                .map(PathBuf::from)
+                .into_iter()
+                .chain(std::iter::once(bundled_data_dir()))
+                .chain(SYSTEM_DATA_DIRS.iter().map(PathBuf::from))
+                .collect(),
+            EspeakDataSource::Bundled => vec![bundled_data_dir()],
+            EspeakDataSource::Path(path) => vec![path.clone()],
+        }
+    }
+}
+
+/// Path of the data directory bundled with the assets
+pub fn bundled_data_dir() -> PathBuf {
+    Path::new(ASSETS_PATH.as_str()).join(DATA_DIR_NAME)
+}
+
+/// Find the first usable data directory `source` names
+///
+/// A directory given as the parent of `espeak-ng-data` is accepted too, since
+/// that is what older espeak releases expected in `ESPEAK_DATA_PATH`.
+pub fn resolve_data_dir(source: &EspeakDataSource) -> Result<PathBuf, TtsError> {
+    let candidates = source.candidates();
+
+    for candidate in &candidates {
+        for dir in [candidate.clone(), candidate.join(DATA_DIR_NAME)] {
+            if missing_files(&dir).is_empty() {
+                return Ok(dir);
+            }
+        }
+    }
+
+    let searched: Vec<String> = candidates
+        .iter()
+        .map(|dir| match missing_files(dir) {
+            missing if missing.len() == REQUIRED_FILES.len() => format!("{}", dir.display()),
+            missing => format!("{} (missing {})", dir.display(), missing.join(", ")),
+        })
+        .collect();
+
+    Err(TtsError::EspeakDataError(format!(
+        "searched [{}]. Install espeak-ng-data, set ESPEAK_DATA to its directory, \
+         or copy it to {} and set ESPEAK_DATA=bundled",
+        searched.join(", "),
+        bundled_data_dir().display()
+    )))
+}
+
+/// Point espeak-ng at the data directory `source` names
+///
+/// Must happen before the first phonemization; afterwards espeak-ng has read
+/// its data and only the directory already configured is accepted. Calling
+/// this is optional, as phonemization falls back to [`EspeakDataSource::Auto`].
+pub fn configure_espeak(source: &EspeakDataSource) -> Result<&'static Path, TtsError> {
+    let _guard = CONFIGURE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let dir = resolve_data_dir(source)?;
+    if let Some(configured) = CONFIGURED.get() {
+        if *configured != dir {
+            return Err(TtsError::EspeakDataError(format!(
+                "espeak-ng already uses {}, cannot switch to {}",
+                configured.display(),
+                dir.display()
+            )));
+        }
+        return Ok(configured);
+    }
+    if AUTO_FAILURE.get().is_some() {
+        return Err(TtsError::EspeakDataError(format!(
+            "espeak-ng was started with its built-in path before {} was configured; \
+             configure it before the first phonemization",
+            dir.display()
+        )));
+    }
+
+    if env::var_os(DATA_PATH_VAR).is_none_or(|current| Path::new(&current) != dir) {
+        // SAFETY: espeak-ng reads the variable when it is first initialized, which
+        // cannot have happened yet, as phonemization configures through this lock
+        // first. Configuration is meant for startup, before other threads read
+        // the environment.
+        unsafe { env::set_var(DATA_PATH_VAR, &dir) };
+    }
+
+    Ok(CONFIGURED.get_or_init(|| dir))
+}
+
+/// Data directory espeak-ng uses, configuring the default source on first use
+///
+/// If the default source finds nothing, espeak-ng is left to its compiled-in
+/// path, which may still work; the error is kept to explain a failure.
+pub fn espeak_data_dir() -> Result<&'static Path, TtsError> {
+    if let Some(dir) = CONFIGURED.get() {
+        return Ok(dir);
+    }
+    if let Some(message) = AUTO_FAILURE.get() {
+        return Err(TtsError::EspeakDataError(message.clone()));
+    }
+
+    configure_espeak(&EspeakDataSource::Auto).inspect_err(|e| {
+        if let TtsError::EspeakDataError(message) = e {
+            let _ = AUTO_FAILURE.set(message.clone());
+        }
+    })
+}
+
+/// Check espeak-ng can phonemize English with the data directory `source` names
+///
+/// Meant for startup, so a missing or incomplete directory is reported before
+/// the first request rather than as a failed synthesis.
+pub fn check_espeak(source: &EspeakDataSource) -> Result<&'static Path, TtsError> {
+    let dir = configure_espeak(source)?;
+    text_to_phonemes_string("hello", "en-us").map_err(|e| {
+        TtsError::EspeakDataError(format!("espeak-ng failed with {}: {}", dir.display(), e))
+    })?;
+    Ok(dir)
+}
+
+fn missing_files(dir: &Path) -> Vec<&'static str> {
+    REQUIRED_FILES
+        .into_iter()
+        .filter(|file| !dir.join(file).is_file())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn data_dir(name: &str, files: &[&str]) -> PathBuf {
+        let root = env::temp_dir().join(format!("espeak-test-{}-{}", name, std::process::id()));
+        let dir = root.join(DATA_DIR_NAME);
+        fs::create_dir_all(&dir).unwrap();
+        for file in files {
+            fs::write(dir.join(file), b"").unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(EspeakDataSource::parse(""), EspeakDataSource::Auto);
+        assert_eq!(
+            EspeakDataSource::parse(" bundled "),
+            EspeakDataSource::Bundled
+        );
+        assert_eq!(
+            EspeakDataSource::parse("/opt/espeak-ng-data"),
+            EspeakDataSource::Path(PathBuf::from("/opt/espeak-ng-data"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_data_dir() {
+        let root = data_dir("complete", &REQUIRED_FILES);
+        let dir = root.join(DATA_DIR_NAME);
+
+        let source = EspeakDataSource::Path(dir.clone());
+        assert_eq!(resolve_data_dir(&source).unwrap(), dir);
+        // The parent of espeak-ng-data is accepted too
+        let source = EspeakDataSource::Path(root.clone());
+        assert_eq!(resolve_data_dir(&source).unwrap(), dir);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_missing_data_names_what_is_missing() {
+        let root = data_dir("partial", &["phontab", "phonindex", "phondata"]);
+        let dir = root.join(DATA_DIR_NAME);
+
+        let error = resolve_data_dir(&EspeakDataSource::Path(dir.clone()))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("missing en_dict"), "{}", error);
+        assert!(error.contains("ESPEAK_DATA=bundled"), "{}", error);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
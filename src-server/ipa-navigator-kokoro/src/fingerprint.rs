@@ -0,0 +1,403 @@
+//! Acoustic fingerprints for comparing synthesized audio
+//!
+//! A fingerprint summarizes audio by its MFCCs: their mean and spread over the
+//! whole clip, and a coarse contour of how they change over time. Unlike the
+//! samples themselves, it stays close under the small numerical differences
+//! between machines and ONNX Runtime builds, but moves when the words, voice,
+//! pacing or timbre change. The golden-audio tests compare against stored
+//! fingerprints to catch unintended changes to synthesis.
+
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// MFCCs kept per frame, including the energy coefficient c0
+pub const MFCC_COEFFICIENTS: usize = 13;
+
+/// Time bins in a fingerprint's contour
+pub const CONTOUR_BINS: usize = 24;
+
+const MEL_BANDS: usize = 26;
+const FRAME_SECS: f32 = 0.025;
+const HOP_SECS: f32 = 0.010;
+const MIN_FREQUENCY: f32 = 60.0;
+const MAX_FREQUENCY: f32 = 7600.0;
+const PRE_EMPHASIS: f32 = 0.97;
+
+/// Floor added to band energies so silence gives a finite log
+const ENERGY_FLOOR: f32 = 1e-10;
+
+/// Band energies further than this below the clip's loudest are raised to it,
+/// so noise in near-silent bands and pauses doesn't move the fingerprint
+const DYNAMIC_RANGE_DB: f32 = 60.0;
+
+/// Summary of a clip's spectral shape over time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcousticFingerprint {
+    pub duration_secs: f32,
+
+    /// Mean of each MFCC over all frames
+    pub mfcc_mean: Vec<f32>,
+
+    /// Standard deviation of each MFCC over all frames
+    pub mfcc_std: Vec<f32>,
+
+    /// Mean MFCCs in each of [`CONTOUR_BINS`] equal slices of the clip
+    pub contour: Vec<Vec<f32>>,
+}
+
+/// How far apart two fingerprints are
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FingerprintDistance {
+    /// Difference in duration as a fraction of the longer clip
+    pub duration: f32,
+
+    /// Root mean square difference of the MFCC means
+    pub mean: f32,
+
+    /// Root mean square difference of the MFCC spreads
+    pub spread: f32,
+
+    /// Root mean square difference of the contours
+    pub contour: f32,
+}
+
+/// Largest distances still counted as the same audio
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    pub duration: f32,
+    pub mean: f32,
+    pub spread: f32,
+    pub contour: f32,
+}
+
+impl Default for Tolerance {
+    /// Loose enough for differences between CPUs and runtime builds, tight
+    /// enough to notice a changed word, voice or speed
+    fn default() -> Self {
+        Self {
+            duration: 0.03,
+            mean: 0.6,
+            spread: 0.6,
+            contour: 1.5,
+        }
+    }
+}
+
+impl FingerprintDistance {
+    pub fn within(&self, tolerance: &Tolerance) -> bool {
+        self.duration <= tolerance.duration
+            && self.mean <= tolerance.mean
+            && self.spread <= tolerance.spread
+            && self.contour <= tolerance.contour
+    }
+}
+
+impl AcousticFingerprint {
+    /// Fingerprint mono samples at `sample_rate`
+    pub fn from_samples(samples: &[f32], sample_rate: u32) -> Self {
+        let frames = mfcc_frames(samples, sample_rate);
+        let duration_secs = samples.len() as f32 / sample_rate as f32;
+
+        if frames.is_empty() {
+            return Self {
+                duration_secs,
+                mfcc_mean: vec![0.0; MFCC_COEFFICIENTS],
+                mfcc_std: vec![0.0; MFCC_COEFFICIENTS],
+                contour: vec![vec![0.0; MFCC_COEFFICIENTS]; CONTOUR_BINS],
+            };
+        }
+
+        let mfcc_mean = mean(&frames);
+        let mfcc_std = (0..MFCC_COEFFICIENTS)
+            .map(|c| {
+                let variance = frames
+                    .iter()
+                    .map(|frame| (frame[c] - mfcc_mean[c]).powi(2))
+                    .sum::<f32>()
+                    / frames.len() as f32;
+                variance.sqrt()
+            })
+            .collect();
+
+        // Clips shorter than the contour repeat frames rather than leaving bins empty
+        let contour = (0..CONTOUR_BINS)
+            .map(|bin| {
+                let start = bin * frames.len() / CONTOUR_BINS;
+                let end = ((bin + 1) * frames.len() / CONTOUR_BINS).max(start + 1);
+                mean(&frames[start.min(frames.len() - 1)..end.min(frames.len())])
+            })
+            .collect();
+
+        Self {
+            duration_secs,
+            mfcc_mean,
+            mfcc_std,
+            contour,
+        }
+    }
+
+    pub fn distance(&self, other: &Self) -> FingerprintDistance {
+        let longer = self.duration_secs.max(other.duration_secs);
+        let duration = if longer > 0.0 {
+            (self.duration_secs - other.duration_secs).abs() / longer
+        } else {
+            0.0
+        };
+
+        let contour = rms_difference(
+            self.contour.iter().flatten().copied(),
+            other.contour.iter().flatten().copied(),
+        );
+
+        FingerprintDistance {
+            duration,
+            mean: rms_difference(
+                self.mfcc_mean.iter().copied(),
+                other.mfcc_mean.iter().copied(),
+            ),
+            spread: rms_difference(
+                self.mfcc_std.iter().copied(),
+                other.mfcc_std.iter().copied(),
+            ),
+            contour,
+        }
+    }
+}
+
+/// MFCCs of each 25 ms frame, 10 ms apart
+fn mfcc_frames(samples: &[f32], sample_rate: u32) -> Vec<Vec<f32>> {
+    let frame_len = (FRAME_SECS * sample_rate as f32) as usize;
+    let hop = (HOP_SECS * sample_rate as f32) as usize;
+    if frame_len == 0 || hop == 0 || samples.len() < frame_len {
+        return Vec::new();
+    }
+
+    let fft_len = frame_len.next_power_of_two();
+    let window: Vec<f32> = (0..frame_len)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / (frame_len - 1) as f32).cos())
+        .collect();
+    let filters = mel_filterbank(fft_len, sample_rate);
+
+    let emphasized: Vec<f32> = std::iter::once(samples[0])
+        .chain(samples.windows(2).map(|w| w[1] - PRE_EMPHASIS * w[0]))
+        .collect();
+
+    let mut real = vec![0.0; fft_len];
+    let mut imag = vec![0.0; fft_len];
+    let mut log_energies: Vec<Vec<f32>> = (0..=(emphasized.len() - frame_len) / hop)
+        .map(|frame| {
+            let start = frame * hop;
+            real.fill(0.0);
+            imag.fill(0.0);
+            for (i, (sample, weight)) in emphasized[start..start + frame_len]
+                .iter()
+                .zip(&window)
+                .enumerate()
+            {
+                real[i] = sample * weight;
+            }
+            fft(&mut real, &mut imag);
+
+            filters
+                .iter()
+                .map(|filter| {
+                    let energy: f32 = filter
+                        .iter()
+                        .map(|&(k, weight)| (real[k] * real[k] + imag[k] * imag[k]) * weight)
+                        .sum();
+                    (energy + ENERGY_FLOOR).ln()
+                })
+                .collect()
+        })
+        .collect();
+
+    let loudest = log_energies
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f32::NEG_INFINITY, f32::max);
+    let floor = loudest - DYNAMIC_RANGE_DB / 10.0 * std::f32::consts::LN_10;
+    for energy in log_energies.iter_mut().flatten() {
+        *energy = energy.max(floor);
+    }
+
+    log_energies.iter().map(|frame| dct(frame)).collect()
+}
+
+/// Triangular filters evenly spaced on the mel scale, as (bin, weight) pairs
+fn mel_filterbank(fft_len: usize, sample_rate: u32) -> Vec<Vec<(usize, f32)>> {
+    let to_mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+    let to_hz = |mel: f32| 700.0 * (10f32.powf(mel / 2595.0) - 1.0);
+
+    let max_frequency = MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+    let (low, high) = (to_mel(MIN_FREQUENCY), to_mel(max_frequency));
+    let bin_hz = sample_rate as f32 / fft_len as f32;
+    let edges: Vec<f32> = (0..MEL_BANDS + 2)
+        .map(|i| to_hz(low + (high - low) * i as f32 / (MEL_BANDS + 1) as f32) / bin_hz)
+        .collect();
+
+    edges
+        .windows(3)
+        .map(|edge| {
+            let (left, center, right) = (edge[0], edge[1], edge[2]);
+            (left.ceil() as usize..=(right.floor() as usize).min(fft_len / 2))
+                .filter_map(|k| {
+                    let k_f = k as f32;
+                    let weight = if k_f <= center {
+                        (k_f - left) / (center - left)
+                    } else {
+                        (right - k_f) / (right - center)
+                    };
+                    (weight > 0.0).then_some((k, weight))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Orthonormal DCT-II, keeping the first [`MFCC_COEFFICIENTS`] coefficients
+fn dct(values: &[f32]) -> Vec<f32> {
+    let n = values.len() as f32;
+    (0..MFCC_COEFFICIENTS)
+        .map(|k| {
+            let sum: f32 = values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| value * (PI * k as f32 * (i as f32 + 0.5) / n).cos())
+                .sum();
+            let scale = if k == 0 {
+                (1.0 / n).sqrt()
+            } else {
+                (2.0 / n).sqrt()
+            };
+            sum * scale
+        })
+        .collect()
+}
+
+/// In-place iterative radix-2 FFT; the length must be a power of two
+fn fft(real: &mut [f32], imag: &mut [f32]) {
+    let n = real.len();
+    debug_assert!(n.is_power_of_two() && imag.len() == n);
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            real.swap(i, j);
+            imag.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_real = real[b] * cos - imag[b] * sin;
+                let t_imag = real[b] * sin + imag[b] * cos;
+                real[b] = real[a] - t_real;
+                imag[b] = imag[a] - t_imag;
+                real[a] += t_real;
+                imag[a] += t_imag;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn mean(frames: &[Vec<f32>]) -> Vec<f32> {
+    (0..MFCC_COEFFICIENTS)
+        .map(|c| frames.iter().map(|frame| frame[c]).sum::<f32>() / frames.len() as f32)
+        .collect()
+}
+
+fn rms_difference(a: impl Iterator<Item = f32>, b: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = a.zip(b).fold((0.0, 0usize), |(sum, count), (a, b)| {
+        (sum + (a - b).powi(2), count + 1)
+    });
+    if count == 0 {
+        0.0
+    } else {
+        (sum / count as f32).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 24000;
+
+    fn tone(frequency: f32, secs: f32) -> Vec<f32> {
+        (0..(secs * RATE as f32) as usize)
+            .map(|i| 0.5 * (2.0 * PI * frequency * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_fft_matches_dft() {
+        let mut real: Vec<f32> = (0..16).map(|i| ((i * 7) % 5) as f32 - 2.0).collect();
+        let mut imag = vec![0.0; 16];
+        let input = real.clone();
+        fft(&mut real, &mut imag);
+
+        for k in 0..16 {
+            let (mut expected_real, mut expected_imag) = (0.0, 0.0);
+            for (i, x) in input.iter().enumerate() {
+                let angle = -2.0 * PI * (k * i) as f32 / 16.0;
+                expected_real += x * angle.cos();
+                expected_imag += x * angle.sin();
+            }
+            assert!((real[k] - expected_real).abs() < 1e-3);
+            assert!((imag[k] - expected_imag).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_small_noise() {
+        let clean = tone(220.0, 1.0);
+        let noisy: Vec<f32> = clean
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s + 1e-4 * ((i * 7919) % 13) as f32 / 13.0)
+            .collect();
+
+        let distance = AcousticFingerprint::from_samples(&clean, RATE)
+            .distance(&AcousticFingerprint::from_samples(&noisy, RATE));
+
+        assert!(distance.within(&Tolerance::default()), "{:?}", distance);
+    }
+
+    #[test]
+    fn test_fingerprint_notices_changed_audio() {
+        let original = AcousticFingerprint::from_samples(&tone(220.0, 1.0), RATE);
+
+        let higher = AcousticFingerprint::from_samples(&tone(880.0, 1.0), RATE);
+        assert!(!original.distance(&higher).within(&Tolerance::default()));
+
+        let longer = AcousticFingerprint::from_samples(&tone(220.0, 1.2), RATE);
+        assert!(!original.distance(&longer).within(&Tolerance::default()));
+    }
+
+    #[test]
+    fn test_fingerprint_of_short_audio() {
+        let fingerprint = AcousticFingerprint::from_samples(&[0.1; 10], RATE);
+        assert_eq!(fingerprint.contour.len(), CONTOUR_BINS);
+        assert!(fingerprint.mfcc_mean.iter().all(|c| *c == 0.0));
+
+        // Fewer frames than contour bins
+        let fingerprint = AcousticFingerprint::from_samples(&tone(220.0, 0.1), RATE);
+        assert_eq!(fingerprint.contour.len(), CONTOUR_BINS);
+        assert!(fingerprint.contour.iter().flatten().all(|c| c.is_finite()));
+    }
+}
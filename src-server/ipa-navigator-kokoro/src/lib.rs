@@ -1,9 +1,31 @@
+//! Kokoro text-to-speech for IPA Navigator
+//!
+//! Applications embedding synthesis should use [`prelude`], whose API is
+//! semver-stable. The remaining modules are used by the server and may change.
+//!
+//! Failures are returned as [`TtsError`](error::TtsError) rather than panicking,
+//! and shared state behind a poisoned lock is recovered rather than propagated.
+#![cfg_attr(not(test), deny(clippy::unwrap_used))]
+
 pub mod constants;
 pub mod error;
+pub mod espeak;
+pub mod fingerprint;
+pub mod manifest;
+#[doc(hidden)]
 pub mod model;
 pub mod normalize;
+pub mod padding;
 pub mod phonemizer;
+pub mod prelude;
+pub mod segment;
+pub mod similarity;
+pub mod stitch;
+pub mod symbols;
+pub mod teaching;
 pub mod tokenize;
 pub mod tts;
+#[doc(hidden)]
 pub mod vocab;
 pub mod voices;
+pub mod wav;
@@ -0,0 +1,134 @@
+//! Checksums of the voice embedding files
+//!
+//! `voices.manifest.json` sits next to the voice files and lists, for each file
+//! name, the SHA-256 digest and length in bytes it was published with. A
+//! truncated download or a file from another model release is rejected before
+//! it reaches inference, naming the file that failed.
+
+use crate::{constants::ASSETS_PATH, error::TtsError};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+/// File name of the manifest within the Kokoro assets directory
+pub const MANIFEST_FILE_NAME: &str = "voices.manifest.json";
+
+/// Expected digest and length of a voice file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManifestEntry {
+    /// Lowercase hex SHA-256 of the file
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// Expected contents of each voice file, keyed by file name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct VoiceManifest {
+    entries: HashMap<String, ManifestEntry>,
+}
+
+impl VoiceManifest {
+    /// Path of the manifest shipped with the assets
+    pub fn path() -> PathBuf {
+        PathBuf::from(format!("{}/Kokoro/{}", *ASSETS_PATH, MANIFEST_FILE_NAME))
+    }
+
+    /// Loads the manifest shipped with the assets
+    pub fn load() -> Result<Self, TtsError> {
+        let path = Self::path();
+        let json = fs::read_to_string(&path).map_err(|e| {
+            TtsError::VoiceDataError(format!(
+                "Failed to read voice manifest at {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::parse(&json)
+    }
+
+    pub fn parse(json: &str) -> Result<Self, TtsError> {
+        serde_json::from_str(json)
+            .map_err(|e| TtsError::VoiceDataError(format!("Invalid voice manifest: {}", e)))
+    }
+
+    pub fn entry(&self, file_name: &str) -> Option<&ManifestEntry> {
+        self.entries.get(file_name)
+    }
+
+    /// Checks a voice file's contents against its entry
+    pub fn verify(&self, file_name: &str, data: &[u8]) -> Result<(), TtsError> {
+        let corrupt = |reason: String| TtsError::CorruptVoiceData {
+            file: file_name.to_string(),
+            reason,
+        };
+
+        let entry = self
+            .entry(file_name)
+            .ok_or_else(|| corrupt(format!("not listed in {}", MANIFEST_FILE_NAME)))?;
+
+        // The length is checked first since it is cheap and explains truncation
+        if data.len() as u64 != entry.bytes {
+            return Err(corrupt(format!(
+                "expected {} bytes, got {}",
+                entry.bytes,
+                data.len()
+            )));
+        }
+
+        let digest = format!("{:x}", Sha256::digest(data));
+        if !digest.eq_ignore_ascii_case(&entry.sha256) {
+            return Err(corrupt(format!(
+                "expected SHA-256 {}, got {}",
+                entry.sha256, digest
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voices::ALL_VOICES;
+
+    const MANIFEST: &str = r#"{
+        "test.bin": {
+            "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+            "bytes": 4
+        }
+    }"#;
+
+    #[test]
+    fn test_verify() {
+        let manifest = VoiceManifest::parse(MANIFEST).unwrap();
+        assert!(manifest.verify("test.bin", b"test").is_ok());
+
+        for (file, data, reason) in [
+            ("test.bin", &b"tes"[..], "expected 4 bytes, got 3"),
+            ("test.bin", &b"TEST"[..], "expected SHA-256"),
+            ("other.bin", &b"test"[..], "not listed"),
+        ] {
+            match manifest.verify(file, data) {
+                Err(TtsError::CorruptVoiceData {
+                    file: failed,
+                    reason: got,
+                }) => {
+                    assert_eq!(failed, file);
+                    assert!(got.contains(reason), "{}", got);
+                }
+                other => panic!("expected corrupt voice data, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_shipped_manifest_lists_every_voice() {
+        let manifest = VoiceManifest::load().unwrap();
+        for voice in ALL_VOICES.iter() {
+            let data = fs::read(voice.path()).unwrap();
+            assert!(manifest.verify(voice.file_name(), &data).is_ok());
+        }
+    }
+}
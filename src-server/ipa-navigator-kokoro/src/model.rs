@@ -1,6 +1,12 @@
 use crate::error::TtsError;
+use crate::padding::PaddingStrategy;
+use crate::similarity::VoiceSimilarity;
 use crate::voices::VoiceType;
-use crate::{constants::ASSETS_PATH, voices::ALL_VOICES};
+use crate::{
+    constants::{ASSETS_PATH, MAX_PARALLEL_INFERENCES},
+    manifest::VoiceManifest,
+    voices::ALL_VOICES,
+};
 use ndarray::{ArrayBase, IxDyn, OwnedRepr};
 use ort::{
     session::{
@@ -10,15 +16,141 @@ use ort::{
 };
 use std::io::Read;
 
-use std::{borrow::Cow, collections::HashMap, fs::File, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+/// Size of the style vector the model is conditioned on
+pub const STYLE_DIM: usize = 256;
+
+/// Number of style frames stored in each voice file
+pub const STYLE_FRAMES: usize = 510;
+
+/// Reduce a voice embedding to the single style vector passed to the model
+///
+/// Voice files hold one style vector per frame, which are averaged. Embeddings
+/// of any other size fall back to their first `STYLE_DIM` values, zero-padded.
+pub fn style_vector(voice_embedding: &[f32]) -> Vec<f32> {
+    if voice_embedding.len() == STYLE_FRAMES * STYLE_DIM {
+        let mut style = vec![0.0f32; STYLE_DIM];
+        for frame in voice_embedding.chunks_exact(STYLE_DIM) {
+            for (sum, value) in style.iter_mut().zip(frame) {
+                *sum += value;
+            }
+        }
+        for value in &mut style {
+            *value /= STYLE_FRAMES as f32;
+        }
+        style
+    } else {
+        let mut style = voice_embedding[..STYLE_DIM.min(voice_embedding.len())].to_vec();
+        style.resize(STYLE_DIM, 0.0);
+        style
+    }
+}
+
+/// Voice embeddings, read by every synthesis and replaced only when voices are reloaded
+///
+/// Kept apart from the inference session so looking up a voice never waits
+/// for inference to finish, and many lookups can run at once.
+#[derive(Default)]
+pub struct VoiceEmbeddings {
+    loaded: RwLock<LoadedVoices>,
+}
+
+/// Embeddings that loaded, the reasons the others failed, and how alike the loaded ones are
+#[derive(Default)]
+struct LoadedVoices {
+    embeddings: HashMap<VoiceType, Arc<[f32]>>,
+    failed: HashMap<VoiceType, String>,
+    similarity: Arc<VoiceSimilarity>,
+}
+
+impl VoiceEmbeddings {
+    /// Gets a voice's embedding, reading it from disk if not already loaded
+    pub fn get(&self, voice_type: VoiceType) -> Result<Arc<[f32]>, TtsError> {
+        if let Some(embedding) = self.read().embeddings.get(&voice_type) {
+            return Ok(embedding.clone());
+        }
+
+        // Read the file without holding the lock, so other voices stay available meanwhile
+        let embedding: Arc<[f32]> = read_voice_embedding(voice_type)?.into();
+        let mut loaded = self.write();
+        loaded.failed.remove(&voice_type);
+        Ok(loaded
+            .embeddings
+            .entry(voice_type)
+            .or_insert(embedding)
+            .clone())
+    }
+
+    /// Replaces every embedding with those read from disk, returning how many loaded
+    ///
+    /// Voices that fail are skipped. If none load, the current embeddings are kept.
+    pub fn load_all(&self) -> Result<usize, TtsError> {
+        let loaded = read_all_voice_embeddings()?;
+        let count = loaded.embeddings.len();
+        *self.write() = loaded;
+        Ok(count)
+    }
+
+    /// Returns a list of all successfully loaded voices
+    pub fn available(&self) -> Vec<VoiceType> {
+        self.read().embeddings.keys().cloned().collect()
+    }
+
+    /// Returns the voices that failed to load with the reason, in `ALL_VOICES` order
+    pub fn failed(&self) -> Vec<(VoiceType, String)> {
+        let loaded = self.read();
+        ALL_VOICES
+            .iter()
+            .filter_map(|voice| Some((*voice, loaded.failed.get(voice)?.clone())))
+            .collect()
+    }
+
+    /// Similarity between the voices loaded by the last `load_all`
+    ///
+    /// Voices loaded one at a time by `get` are not compared until then.
+    pub fn similarity(&self) -> Arc<VoiceSimilarity> {
+        self.read().similarity.clone()
+    }
+
+    /// Embeddings are replaced whole, so a panic while holding the lock never leaves them half-written
+    fn read(&self) -> RwLockReadGuard<'_, LoadedVoices> {
+        self.loaded.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, LoadedVoices> {
+        self.loaded.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
 
 pub struct KokoroModel {
-    session: Session,
-    voice_embeddings: HashMap<VoiceType, Vec<f32>>,
+    /// ONNX Runtime needs exclusive access to run a session, so each inference holds one
+    sessions: Vec<Mutex<Session>>,
+    /// Session to wait for when all are busy, spreading waiters across them
+    next_session: AtomicUsize,
+    voices: VoiceEmbeddings,
+    /// Padding the model was trained with, added around every sequence
+    padding: PaddingStrategy,
 }
 
 impl KokoroModel {
+    /// Load the model with [`MAX_PARALLEL_INFERENCES`] sessions
     pub fn new() -> Result<Self, TtsError> {
+        Self::with_sessions(*MAX_PARALLEL_INFERENCES)
+    }
+
+    /// Load the model with `count` sessions, so up to `count` inferences run at once
+    pub fn with_sessions(count: usize) -> Result<Self, TtsError> {
         let model_path = PathBuf::from(format!("{}/Kokoro/model.onnx", *ASSETS_PATH));
 
         if !model_path.exists() {
@@ -28,116 +160,118 @@ impl KokoroModel {
             )));
         }
 
-        let session = Session::builder()?
-            .with_optimization_level(GraphOptimizationLevel::Level3)?
-            .with_intra_threads(4)?
-            .commit_from_file(model_path)?;
+        let sessions = (0..count.max(1))
+            .map(|_| {
+                let session = Session::builder()?
+                    .with_optimization_level(GraphOptimizationLevel::Level3)?
+                    .with_intra_threads(4)?
+                    .commit_from_file(&model_path)?;
+                Ok(Mutex::new(session))
+            })
+            .collect::<Result<Vec<_>, TtsError>>()?;
+
+        let padding = match PaddingStrategy::from_env()? {
+            Some(padding) => padding,
+            None => {
+                let session = sessions[0].lock().unwrap_or_else(|e| e.into_inner());
+                let metadata = session.metadata()?;
+                PaddingStrategy::from_metadata(|key| metadata.custom(key).ok().flatten())?
+                    .unwrap_or_default()
+            }
+        };
+        tracing::debug!("Padding token sequences with {:?}", padding);
 
         Ok(Self {
-            session: session,
-            voice_embeddings: HashMap::new(),
+            sessions,
+            next_session: AtomicUsize::new(0),
+            voices: VoiceEmbeddings::default(),
+            padding,
         })
     }
 
-    /// Loads a single voice embedding from a file
-    pub fn load_voice_embedding(&self, voice_type: VoiceType) -> Result<Vec<f32>, TtsError> {
-        let voice_file: PathBuf = voice_type.path();
-
-        if !voice_file.exists() {
-            return Err(TtsError::VoiceDataError(format!(
-                "Voice file not found at path: {}",
-                voice_file.display()
-            )));
-        }
-
-        // Read the binary file
-        let mut file = File::open(&voice_file)
-            .map_err(|e| TtsError::VoiceDataError(format!("Failed to open voice file: {}", e)))?;
-
-        // Read the file into a buffer
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)
-            .map_err(|e| TtsError::VoiceDataError(format!("Failed to read voice file: {}", e)))?;
-
-        // Convert bytes to f32 values
-        // The expected size is 510 * 1 * 256 * 4 bytes (since each f32 is 4 bytes)
-        if buffer.len() != 510 * 1 * 256 * 4 {
-            return Err(TtsError::VoiceDataError(format!(
-                "Vaoice file has unexpected size: expected {} bytes, got {} bytes",
-                510 * 1 * 256 * 4,
-                buffer.len()
-            )));
-        }
+    /// Padding added around every token sequence passed to `infer`
+    pub fn padding(&self) -> PaddingStrategy {
+        self.padding
+    }
 
-        // Create a vector to store the f32 values
-        let mut tensor = Vec::with_capacity(510 * 1 * 256);
+    /// Number of inferences that can run at once
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
 
-        // Convert bytes to f32 values
-        for chunk in buffer.chunks_exact(4) {
-            if chunk.len() == 4 {
-                let value = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
-                tensor.push(value);
+    /// A free session, or if all are busy, the next one in turn once it is free
+    fn session(&self) -> MutexGuard<'_, Session> {
+        // A session is left usable by a panicked inference, so poisoning is ignored
+        for session in &self.sessions {
+            match session.try_lock() {
+                Ok(guard) => return guard,
+                Err(TryLockError::Poisoned(e)) => return e.into_inner(),
+                Err(TryLockError::WouldBlock) => {}
             }
         }
 
-        Ok(tensor)
+        let index = self.next_session.fetch_add(1, Ordering::Relaxed) % self.sessions.len();
+        self.sessions[index]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
     }
 
-    /// Loads all available voice embeddings and caches them for later use
-    pub fn load_all_voice_embeddings(&mut self) -> Result<(), TtsError> {
-        let voices = *ALL_VOICES;
+    /// Loads a single voice embedding from a file
+    pub fn load_voice_embedding(&self, voice_type: VoiceType) -> Result<Vec<f32>, TtsError> {
+        read_voice_embedding(voice_type)
+    }
 
-        tracing::info!("Loading {} voice embeddings", voices.len());
+    /// Loads every voice's embedding in parallel
+    ///
+    /// Voices that fail to load are logged and listed by `failed_voices`, and
+    /// synthesis continues with the rest. Fails only if no voice loads.
+    pub fn load_all_voice_embeddings(&self) -> Result<(), TtsError> {
+        tracing::info!("Loading {} voice embeddings", ALL_VOICES.len());
 
-        let mut failed_voices = Vec::new();
+        let count = self.voices.load_all()?;
 
-        for voice in voices {
-            match self.load_voice_embedding(voice) {
-                Ok(embedding) => {
-                    tracing::debug!("Loaded voice embedding for {:?}", voice);
-                    self.voice_embeddings.insert(voice, embedding);
-                }
-                Err(err) => {
-                    tracing::warn!("Failed to load voice {:?}: {}", voice, err);
-                    failed_voices.push((voice, err));
-                }
-            }
-        }
+        tracing::info!("Successfully loaded {} voice embeddings", count);
+        Ok(())
+    }
 
-        if !failed_voices.is_empty() {
-            tracing::warn!("Failed to load {} voices", failed_voices.len());
-            // Return first error if any voices failed to load
-            return Err(TtsError::VoiceDataError(failed_voices[0].1.to_string()));
-        }
+    /// Re-reads every voice embedding from disk, replacing those in memory
+    ///
+    /// Like `load_all_voice_embeddings`, voices that fail are skipped. If none
+    /// load, the current embeddings are kept.
+    pub fn reload_voice_embeddings(&self) -> Result<usize, TtsError> {
+        let count = self.voices.load_all()?;
 
-        tracing::info!(
-            "Successfully loaded {} voice embeddings",
-            self.voice_embeddings.len()
-        );
-        Ok(())
+        tracing::info!("Reloaded {} voice embeddings", count);
+        Ok(count)
     }
 
     /// Gets a voice embedding from the cache, or loads it if not already loaded
-    pub fn get_voice_embedding(&mut self, voice_type: VoiceType) -> Result<Vec<f32>, TtsError> {
-        if !self.voice_embeddings.contains_key(&voice_type) {
-            let embedding = self.load_voice_embedding(voice_type)?;
-            self.voice_embeddings.insert(voice_type, embedding);
-        }
-
-        Ok(self.voice_embeddings.get(&voice_type).unwrap().clone())
+    pub fn get_voice_embedding(&self, voice_type: VoiceType) -> Result<Arc<[f32]>, TtsError> {
+        self.voices.get(voice_type)
     }
 
     /// Returns a list of all successfully loaded voices
     pub fn available_voices(&self) -> Vec<VoiceType> {
-        self.voice_embeddings.keys().cloned().collect()
+        self.voices.available()
+    }
+
+    /// Returns the voices that failed to load with the reason, in `ALL_VOICES` order
+    pub fn failed_voices(&self) -> Vec<(VoiceType, String)> {
+        self.voices.failed()
+    }
+
+    /// Similarity between the loaded voices, computed when they were loaded
+    pub fn voice_similarity(&self) -> Arc<VoiceSimilarity> {
+        self.voices.similarity()
     }
 
     /// Runs inference on the model with the given tokens, voice type, and speed.
     /// Returns the generated audio as an ndarray.
+    #[tracing::instrument(name = "tts.inference", skip_all, fields(tokens = tokens.len(), speed))]
     pub fn infer(
-        &mut self,
+        &self,
         tokens: Vec<i64>,
-        voice_embedding: Vec<f32>,
+        voice_embedding: &[f32],
         speed: f32,
         chunk_number: Option<usize>,
     ) -> Result<ArrayBase<OwnedRepr<f32>, IxDyn>, TtsError> {
@@ -151,26 +285,10 @@ impl KokoroModel {
         let tokens_tensor = Tensor::from_array((tokens_shape, tokens.clone()))?;
 
         // Process the voice embedding to match model's expected [1, 256] shape
-        // Our voice embedding is 510*1*256 (130560) elements
-        let mut style_data = vec![0.0f32; 256];
-
-        // Average across the 510 "frames" to get a single 256-dimensional vector
-        if voice_embedding.len() == 510 * 256 {
-            for i in 0..256 {
-                let mut sum = 0.0;
-                for j in 0..510 {
-                    sum += voice_embedding[j * 256 + i];
-                }
-                style_data[i] = sum / 510.0;
-            }
-        } else {
-            // Fallback - just use the first 256 elements
-            style_data.extend_from_slice(&voice_embedding[..256.min(voice_embedding.len())]);
-            style_data.resize(256, 0.0);
-        }
+        let style_data = style_vector(voice_embedding);
 
         // Create the style tensor - shape [1, 256] as expected by model
-        let style_shape = vec![1, 256];
+        let style_shape = vec![1, STYLE_DIM];
         let style_tensor = Tensor::from_array((style_shape, style_data))?;
 
         // Create the speed tensor - shape [1]
@@ -189,8 +307,8 @@ impl KokoroModel {
 
         // Run Inference
         tracing::debug!("Running inference with {} tokens", tokens.len());
-        let outputs: SessionOutputs = self
-            .session
+        let mut session = self.session();
+        let outputs: SessionOutputs = session
             .run(SessionInputs::from(inputs))
             .map_err(|e| TtsError::InferenceError(format!("Inference failed: {}", e)))?;
 
@@ -235,17 +353,115 @@ impl KokoroModel {
 
     /// Convenience method to run inference with a voice type instead of embedding
     pub fn infer_with_voice_type(
-        &mut self,
+        &self,
         tokens: Vec<i64>,
         voice_type: VoiceType,
         speed: f32,
         chunk_number: Option<usize>,
     ) -> Result<ArrayBase<OwnedRepr<f32>, IxDyn>, TtsError> {
         let voice_embedding = self.get_voice_embedding(voice_type)?;
-        self.infer(tokens, voice_embedding, speed, chunk_number)
+        self.infer(tokens, &voice_embedding, speed, chunk_number)
     }
 }
 
+/// Reads a single voice embedding from its file
+fn read_voice_embedding(voice_type: VoiceType) -> Result<Vec<f32>, TtsError> {
+    let voice_file: PathBuf = voice_type.path();
+
+    if !voice_file.exists() {
+        return Err(TtsError::VoiceDataError(format!(
+            "Voice file not found at path: {}",
+            voice_file.display()
+        )));
+    }
+
+    // Read the binary file
+    let mut file = File::open(&voice_file)
+        .map_err(|e| TtsError::VoiceDataError(format!("Failed to open voice file: {}", e)))?;
+
+    // Read the file into a buffer
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)
+        .map_err(|e| TtsError::VoiceDataError(format!("Failed to read voice file: {}", e)))?;
+
+    // Reject truncated or mismatched files before they reach inference
+    VoiceManifest::load()?.verify(voice_type.file_name(), &buffer)?;
+
+    // Create a vector to store the f32 values
+    let mut tensor = Vec::with_capacity(510 * 1 * 256);
+
+    // Convert bytes to f32 values
+    for chunk in buffer.chunks_exact(4) {
+        if chunk.len() == 4 {
+            let value = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            tensor.push(value);
+        }
+    }
+
+    Ok(tensor)
+}
+
+/// Reads every voice's embedding on its own thread
+///
+/// Returns the embeddings that loaded and the reasons the others failed, or
+/// an error if none loaded.
+fn read_all_voice_embeddings() -> Result<LoadedVoices, TtsError> {
+    let results: Vec<(VoiceType, Result<Vec<f32>, TtsError>)> = thread::scope(|scope| {
+        let handles: Vec<_> = ALL_VOICES
+            .iter()
+            .map(|&voice| (voice, scope.spawn(move || read_voice_embedding(voice))))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|(voice, handle)| {
+                let result = handle.join().unwrap_or_else(|_| {
+                    Err(TtsError::VoiceDataError(
+                        "Voice loading thread panicked".to_string(),
+                    ))
+                });
+                (voice, result)
+            })
+            .collect()
+    });
+
+    let mut embeddings = HashMap::new();
+    let mut failed_voices = HashMap::new();
+    for (voice, result) in results {
+        match result {
+            Ok(embedding) => {
+                tracing::debug!("Loaded voice embedding for {:?}", voice);
+                embeddings.insert(voice, embedding.into());
+            }
+            Err(err) => {
+                tracing::warn!("Failed to load voice {:?}: {}", voice, err);
+                failed_voices.insert(voice, err.to_string());
+            }
+        }
+    }
+
+    if embeddings.is_empty() {
+        return Err(TtsError::VoiceDataError(format!(
+            "No voices could be loaded, {} failed",
+            failed_voices.len()
+        )));
+    }
+    if !failed_voices.is_empty() {
+        tracing::warn!(
+            "Failed to load {} voices, continuing with {}",
+            failed_voices.len(),
+            embeddings.len()
+        );
+    }
+
+    let similarity = Arc::new(VoiceSimilarity::compute(&embeddings));
+    Ok(LoadedVoices {
+        embeddings,
+        failed: failed_voices,
+        similarity,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -258,6 +474,43 @@ mod tests {
     use std::path::PathBuf;
     use std::time::Instant;
 
+    #[test]
+    fn test_style_vector() {
+        // Averaging frames where frame `j` is filled with `j`
+        let embedding: Vec<f32> = (0..STYLE_FRAMES)
+            .flat_map(|j| std::iter::repeat_n(j as f32, STYLE_DIM))
+            .collect();
+        let style = style_vector(&embedding);
+        assert_eq!(style.len(), STYLE_DIM);
+        let mean = (STYLE_FRAMES - 1) as f32 / 2.0;
+        assert!(style.iter().all(|v| (v - mean).abs() < 1e-3));
+
+        // Unexpected sizes keep their leading values
+        let style = style_vector(&[1.0, 2.0, 3.0]);
+        assert_eq!(style.len(), STYLE_DIM);
+        assert_eq!(&style[..4], &[1.0, 2.0, 3.0, 0.0]);
+    }
+
+    #[test]
+    fn test_voice_embeddings_are_shared_between_readers() {
+        let voices = VoiceEmbeddings::default();
+        let voice = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
+        voices
+            .write()
+            .embeddings
+            .insert(voice, vec![0.5; STYLE_DIM].into());
+
+        // Another thread reads the embedding while this one holds the lock
+        let _reader = voices.read();
+        let embedding = std::thread::scope(|scope| scope.spawn(|| voices.get(voice)).join())
+            .unwrap()
+            .unwrap();
+
+        assert!(Arc::ptr_eq(&embedding, &voices.read().embeddings[&voice]));
+        assert_eq!(voices.available(), vec![voice]);
+        assert!(voices.failed().is_empty());
+    }
+
     #[test]
     fn test_model_initialization() {
         let model = KokoroModel::new();
@@ -299,7 +552,7 @@ mod tests {
 
     #[test]
     fn test_load_all_voice_embeddings() {
-        let mut model = match KokoroModel::new() {
+        let model = match KokoroModel::new() {
             Ok(m) => m,
             Err(e) => {
                 println!("Skipping test as model could not be loaded: {:?}", e);
@@ -316,12 +569,12 @@ mod tests {
 
         // Verify that voice embeddings were loaded
         assert!(
-            !model.voice_embeddings.is_empty(),
+            !model.available_voices().is_empty(),
             "No voice embeddings were loaded"
         );
 
         // Check if all voices from ALL_VOICES were loaded
-        let loaded_count = model.voice_embeddings.len();
+        let loaded_count = model.available_voices().len();
         let expected_count = ALL_VOICES.len();
         assert_eq!(
             loaded_count, expected_count,
@@ -331,7 +584,7 @@ mod tests {
 
     #[test]
     fn test_get_voice_embedding() {
-        let mut model = match KokoroModel::new() {
+        let model = match KokoroModel::new() {
             Ok(m) => m,
             Err(e) => {
                 println!("Skipping test as model could not be loaded: {:?}", e);
@@ -350,7 +603,7 @@ mod tests {
 
         // Verify that the embedding was cached
         assert!(
-            model.voice_embeddings.contains_key(&voice_type),
+            model.available_voices().contains(&voice_type),
             "Voice embedding was not cached after getting it"
         );
 
@@ -371,7 +624,7 @@ mod tests {
 
     #[test]
     fn test_multiple_voice_types() {
-        let mut model = match KokoroModel::new() {
+        let model = match KokoroModel::new() {
             Ok(m) => m,
             Err(e) => {
                 println!("Skipping test as model could not be loaded: {:?}", e);
@@ -400,7 +653,7 @@ mod tests {
         // Check that all voice embeddings were cached
         for voice_type in voice_types.iter() {
             assert!(
-                model.voice_embeddings.contains_key(voice_type),
+                model.available_voices().contains(voice_type),
                 "Voice embedding for {:?} was not cached",
                 voice_type
             );
@@ -416,7 +669,7 @@ mod tests {
 
     #[test]
     fn test_available_voices() {
-        let mut model = match KokoroModel::new() {
+        let model = match KokoroModel::new() {
             Ok(m) => m,
             Err(e) => {
                 println!("Skipping test as model could not be loaded: {:?}", e);
@@ -521,7 +774,7 @@ mod tests {
             output_file_name: &str,
         ) -> Result<(), Box<dyn std::error::Error>> {
             // Initialize the model
-            let mut model = match KokoroModel::new() {
+            let model = match KokoroModel::new() {
                 Ok(m) => m,
                 Err(e) => {
                     return Err(Box::new(TtsError::ModelLoadError(format!(
@@ -549,7 +802,7 @@ mod tests {
             let start_time = Instant::now();
 
             // Run inference
-            let result = model.infer(tokens, voice_embedding, speed, None);
+            let result = model.infer(tokens, &voice_embedding, speed, None);
 
             // Check if inference succeeded
             match result {
@@ -784,7 +1037,7 @@ mod tests {
             let padded_tokens = utils::add_padding_to_tokens(tokens, 3, 3);
 
             // Initialize the model
-            let mut model = match KokoroModel::new() {
+            let model = match KokoroModel::new() {
                 Ok(m) => m,
                 Err(e) => {
                     return Err(Box::new(TtsError::ModelLoadError(format!(
@@ -796,7 +1049,7 @@ mod tests {
 
             // Run inference
             let voice_embedding = model.get_voice_embedding(*voice)?;
-            match model.infer(padded_tokens, voice_embedding, 1.0, None) {
+            match model.infer(padded_tokens, &voice_embedding, 1.0, None) {
                 Ok(output) => {
                     // Save output
                     let filename = format!("test_{}.wav", name);
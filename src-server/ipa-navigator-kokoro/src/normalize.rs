@@ -1,51 +1,544 @@
+//! Text normalization before phonemization
+//!
+//! A [`Normalizer`] runs ordered rule stages over the text. The standard
+//! stages clean whitespace and typography, read symbols aloud, expand
+//! abbreviations, and format numbers. Deployments add their own regex replacements, such as "IPA" to
+//! "I P A", in a rules file named by `NORMALIZATION_RULES`, one per line:
+//!
+//! ```text
+//! # Spell out initialisms
+//! \bIPA\b => I P A
+//! ```
+//!
+//! A request may also carry a [`Lexicon`] of respellings for words the
+//! phonemizer gets wrong, such as a school's place names, applied before any
+//! stage.
+
+use crate::error::TtsError;
+use crate::symbols::{SymbolMode, Symbols};
+use crate::teaching::TeachingMode;
 use regex::Regex;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
 
-/// Normalizes text for text-to-speech processing by performing basic cleaning and expanding common abbreviations.
-pub fn normalize_text(text: &str) -> String {
-    if text.is_empty() {
-        return String::new();
+/// Separates the pattern from the replacement in a rules file
+const RULE_SEPARATOR: &str = " => ";
+
+/// Words a lexicon can respell, including contractions like "O'Neill"
+static LEXICON_WORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\p{L}\p{M}\p{N}]+(?:['’][\p{L}\p{M}\p{N}]+)*")
+        .expect("lexicon word pattern is valid")
+});
+
+/// Normalizer configured from the environment, used by [`normalize_text`]
+pub static NORMALIZER: LazyLock<Arc<Normalizer>> =
+    LazyLock::new(|| Arc::new(Normalizer::from_env()));
+
+/// One stage of text normalization
+pub trait NormalizationRule: Send + Sync {
+    /// Name used to refer to the rule, e.g. when inserting others around it
+    fn name(&self) -> &str;
+
+    fn apply(&self, text: &str) -> String;
+
+    /// Apply the rule, skipping the parts `options` disables
+    ///
+    /// Rules whose parts can be disabled individually override this.
+    fn apply_with(&self, text: &str, options: &NormalizeOptions) -> String {
+        let _ = options;
+        self.apply(text)
+    }
+}
+
+/// Per-request adjustments to normalization and phonemization
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NormalizeOptions {
+    disabled: Vec<String>,
+    teaching: Option<TeachingMode>,
+    lexicon: Option<Arc<Lexicon>>,
+}
+
+impl NormalizeOptions {
+    /// Skip a stage by name, e.g. "numbers", or one abbreviation's expansion, e.g. "St."
+    ///
+    /// Names match case-insensitively, with or without a trailing full stop.
+    pub fn disable(mut self, name: impl Into<String>) -> Self {
+        self.disabled.push(name.into());
+        self
+    }
+
+    pub fn is_disabled(&self, name: &str) -> bool {
+        let key = |name: &str| name.trim().trim_end_matches('.').to_lowercase();
+        self.disabled
+            .iter()
+            .any(|disabled| key(disabled) == key(name))
+    }
+
+    /// Pause between words or syllables of the phonemized text, for shadowing
+    pub fn with_teaching(mut self, mode: TeachingMode) -> Self {
+        self.teaching = Some(mode);
+        self
+    }
+
+    pub fn teaching(&self) -> Option<&TeachingMode> {
+        self.teaching.as_ref()
+    }
+
+    /// Respell the lexicon's words before normalizing
+    pub fn with_lexicon(mut self, lexicon: Arc<Lexicon>) -> Self {
+        self.lexicon = Some(lexicon);
+        self
+    }
+
+    pub fn lexicon(&self) -> Option<&Lexicon> {
+        self.lexicon.as_deref()
+    }
+}
+
+/// Respellings of whole words, matched case-insensitively
+///
+/// Respellings are plain text, e.g. "Ngaio" => "Nye oh", so they pass through
+/// normalization and phonemization like the rest of the text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lexicon {
+    /// Respellings by lowercase word, with straight apostrophes
+    entries: BTreeMap<String, String>,
+}
+
+impl Lexicon {
+    pub fn new(entries: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .map(|(word, respelling)| (lexicon_key(word.trim()), respelling))
+                .filter(|(word, _)| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Replace every word with an entry by its respelling
+    pub fn respell(&self, text: &str) -> String {
+        if self.entries.is_empty() {
+            return text.to_string();
+        }
+
+        LEXICON_WORD
+            .replace_all(text, |captures: &regex::Captures| {
+                let word = &captures[0];
+                self.entries
+                    .get(&lexicon_key(word))
+                    .cloned()
+                    .unwrap_or_else(|| word.to_string())
+            })
+            .to_string()
+    }
+}
+
+/// Collapses runs of whitespace into single spaces
+pub struct Whitespace {
+    pattern: Regex,
+}
+
+impl Default for Whitespace {
+    fn default() -> Self {
+        Self {
+            pattern: Regex::new(r"\s+").expect("whitespace pattern is valid"),
+        }
+    }
+}
+
+impl NormalizationRule for Whitespace {
+    fn name(&self) -> &str {
+        "whitespace"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        self.pattern.replace_all(text, " ").to_string()
+    }
+}
+
+/// Converts common typographic characters to ASCII equivalents
+#[derive(Default)]
+pub struct Typography;
+
+impl NormalizationRule for Typography {
+    fn name(&self) -> &str {
+        "unicode"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        // Curly single and double quotes
+        text.replace(['\u{2018}', '\u{2019}'], "'")
+            .replace(['\u{201C}', '\u{201D}'], "\"")
+    }
+}
+
+/// Preceding words searched for a house number when a place is followed by a name
+const PLACE_NUMBER_WINDOW: usize = 3;
+
+/// How an abbreviation is read aloud
+enum Reading {
+    Fixed(&'static str),
+    /// A place after a name or house number ("12 Main St."), or a title before
+    /// a name ("St. Mary's"), with the fallback when the context shows neither
+    PlaceOrTitle {
+        place: &'static str,
+        title: &'static str,
+        prefer_place: bool,
+    },
+}
+
+/// One abbreviation and how it is read
+struct Expansion {
+    abbreviation: &'static str,
+    pattern: Regex,
+    reading: Reading,
+}
+
+impl Expansion {
+    fn new(abbreviation: &'static str, reading: Reading) -> Self {
+        // Use word boundaries (\b) to ensure we only match full words
+        let pattern = Regex::new(&format!(r"\b{}", regex::escape(abbreviation)))
+            .expect("escaped abbreviation is a valid pattern");
+
+        Self {
+            abbreviation,
+            pattern,
+            reading,
+        }
+    }
+
+    fn expand(&self, text: &str) -> String {
+        self.pattern
+            .replace_all(text, |caps: &regex::Captures| {
+                let matched = caps.get(0).expect("group 0 is the whole match");
+                match self.reading {
+                    Reading::Fixed(replacement) => replacement,
+                    Reading::PlaceOrTitle {
+                        place,
+                        title,
+                        prefer_place,
+                    } => {
+                        let context =
+                            reads_as_place(&text[..matched.start()], &text[matched.end()..]);
+                        if context.unwrap_or(prefer_place) {
+                            place
+                        } else {
+                            title
+                        }
+                    }
+                }
+            })
+            .to_string()
+    }
+}
+
+/// Whether an abbreviation between `before` and `after` names a place, if the context shows it
+///
+/// Places follow a capitalized word that does not start a sentence, or a house
+/// number. Titles precede a capitalized name. A place followed by a name is
+/// still a place if a house number comes shortly before it ("12 Main St. Springfield").
+fn reads_as_place(before: &str, after: &str) -> Option<bool> {
+    let starts_with_digit = |word: &str| word.starts_with(|c: char| c.is_ascii_digit());
+    let precedes_name =
+        after.starts_with(' ') && after.trim_start().starts_with(char::is_uppercase);
+
+    let words: Vec<&str> = before.split_whitespace().collect();
+    let follows_name = before.ends_with(char::is_whitespace)
+        && words.split_last().is_some_and(|(word, earlier)| {
+            let starts_sentence = earlier
+                .last()
+                .is_none_or(|previous| previous.ends_with(['.', '!', '?']));
+            // Punctuation such as a comma separates the word from the abbreviation
+            word.ends_with(char::is_alphanumeric)
+                && (starts_with_digit(word)
+                    || (word.starts_with(char::is_uppercase) && !starts_sentence))
+        });
+
+    match (follows_name, precedes_name) {
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        (true, true) => Some(
+            words
+                .iter()
+                .rev()
+                .take(PLACE_NUMBER_WINDOW)
+                .any(|word| starts_with_digit(word)),
+        ),
+        (false, false) => None,
+    }
+}
+
+/// Expands common abbreviations that affect pronunciation
+///
+/// Each expansion can be disabled by its abbreviation, e.g. "St.", through
+/// [`NormalizeOptions`].
+pub struct Abbreviations {
+    expansions: Vec<Expansion>,
+}
+
+impl Default for Abbreviations {
+    fn default() -> Self {
+        let place_or_title = |place, title, prefer_place| Reading::PlaceOrTitle {
+            place,
+            title,
+            prefer_place,
+        };
+        let expansions = vec![
+            Expansion::new("Dr.", place_or_title("Drive", "Doctor", false)),
+            Expansion::new("Mr.", Reading::Fixed("Mister")),
+            Expansion::new("Mrs.", Reading::Fixed("Missus")),
+            Expansion::new("Ms.", Reading::Fixed("Miss")),
+            Expansion::new("St.", place_or_title("Street", "Saint", true)),
+            Expansion::new("Ave.", Reading::Fixed("Avenue")),
+            Expansion::new("Rd.", Reading::Fixed("Road")),
+            Expansion::new("Blvd.", Reading::Fixed("Boulevard")),
+            Expansion::new("etc.", Reading::Fixed("etcetera")),
+        ];
+
+        Self { expansions }
+    }
+}
+
+impl NormalizationRule for Abbreviations {
+    fn name(&self) -> &str {
+        "abbreviations"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        self.apply_with(text, &NormalizeOptions::default())
+    }
+
+    fn apply_with(&self, text: &str, options: &NormalizeOptions) -> String {
+        self.expansions
+            .iter()
+            .filter(|expansion| !options.is_disabled(expansion.abbreviation))
+            .fold(text.to_string(), |text, expansion| expansion.expand(&text))
+    }
+}
+
+/// Basic number formatting
+pub struct Numbers {
+    range: Regex,
+    thousands: Regex,
+}
+
+impl Default for Numbers {
+    fn default() -> Self {
+        Self {
+            range: Regex::new(r"(\d+)-(\d+)").expect("range pattern is valid"),
+            thousands: Regex::new(r"(\d),(\d)").expect("thousands pattern is valid"),
+        }
     }
+}
 
-    // First, normalize whitespace
-    let whitespace_re = Regex::new(r"\s+").unwrap();
-    let mut text = whitespace_re.replace_all(text, " ").to_string();
+impl NormalizationRule for Numbers {
+    fn name(&self) -> &str {
+        "numbers"
+    }
 
-    // Convert common typographic characters to ASCII equivalents
-    text = text
-        .replace('\u{2018}', "'") // Left single quote
-        .replace('\u{2019}', "'") // Right single quote
-        .replace('\u{201C}', "\"") // Left double quote
-        .replace('\u{201D}', "\""); // Right double quote
+    fn apply(&self, text: &str) -> String {
+        // Replace ranges with "to"
+        let text = self.range.replace_all(text, "$1 to $2");
+        // Remove commas between digits
+        self.thousands.replace_all(&text, "$1$2").to_string()
+    }
+}
+
+/// A user-defined replacement, named after its pattern
+///
+/// The replacement may refer to capture groups as `$1` or `${name}`.
+pub struct RegexRule {
+    pattern: Regex,
+    replacement: String,
+}
 
-    // Expand common abbreviations that affect pronunciation
-    // Use word boundaries (\b) to ensure we only match full words
-    let abbrev_expansions = [
-        (r"\bDr\.", "Doctor"),
-        (r"\bMr\.", "Mister"),
-        (r"\bMrs\.", "Missus"),
-        (r"\bMs\.", "Miss"),
-        (r"\bSt\.", "Street"),
-        (r"\bAve\.", "Avenue"),
-        (r"\bRd\.", "Road"),
-        (r"\bBlvd\.", "Boulevard"),
-        (r"\betc\.", "etcetera"),
-    ];
+impl RegexRule {
+    pub fn new(pattern: &str, replacement: impl Into<String>) -> Result<Self, TtsError> {
+        let pattern = Regex::new(pattern)
+            .map_err(|e| TtsError::RuleError(format!("Invalid pattern {:?}: {}", pattern, e)))?;
 
-    for (pattern, replacement) in abbrev_expansions.iter() {
-        let re = Regex::new(pattern).unwrap();
-        text = re.replace_all(&text, *replacement).to_string();
+        Ok(Self {
+            pattern,
+            replacement: replacement.into(),
+        })
     }
 
-    // Basic number formatting
-    // Replace ranges with "to"
-    let range_re = Regex::new(r"(\d+)-(\d+)").unwrap();
-    text = range_re.replace_all(&text, "$1 to $2").to_string();
+    /// Parse rules written one per line as `pattern => replacement`
+    ///
+    /// Blank lines and lines starting with `#` are skipped.
+    pub fn parse_rules(rules: &str) -> Result<Vec<Self>, TtsError> {
+        rules
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| {
+                let (pattern, replacement) = line.split_once(RULE_SEPARATOR).ok_or_else(|| {
+                    TtsError::RuleError(format!(
+                        "Line {} is not `pattern{}replacement`",
+                        index + 1,
+                        RULE_SEPARATOR
+                    ))
+                })?;
+                Self::new(pattern.trim(), replacement.trim())
+            })
+            .collect()
+    }
 
-    // Remove commas between digits
-    let comma_re = Regex::new(r"(\d),(\d)").unwrap();
-    text = comma_re.replace_all(&text, "$1$2").to_string();
+    /// Read rules from a file in the format of [`RegexRule::parse_rules`]
+    pub fn load_rules(path: impl AsRef<Path>) -> Result<Vec<Self>, TtsError> {
+        Self::parse_rules(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl NormalizationRule for RegexRule {
+    fn name(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    fn apply(&self, text: &str) -> String {
+        self.pattern
+            .replace_all(text, self.replacement.as_str())
+            .to_string()
+    }
+}
+
+/// Lowercase with straight apostrophes, so "O’Neill" finds "O'Neill"
+fn lexicon_key(word: &str) -> String {
+    word.to_lowercase().replace('’', "'")
+}
 
-    text.trim().to_string()
+/// Ordered normalization stages, applied one after another
+pub struct Normalizer {
+    rules: Vec<Box<dyn NormalizationRule>>,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl Normalizer {
+    /// A normalizer without any stages, which only trims the text
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Whitespace, typography, symbol, abbreviation, and number stages, in that order
+    pub fn standard() -> Self {
+        Self::empty()
+            .with_rule(Whitespace::default())
+            .with_rule(Typography)
+            .with_rule(Symbols::default())
+            .with_rule(Abbreviations::default())
+            .with_rule(Numbers::default())
+    }
+
+    /// The standard stages, with custom rules from the file named by
+    /// `NORMALIZATION_RULES` run before abbreviation expansion
+    ///
+    /// Symbols are verbalized unless `NORMALIZATION_SYMBOLS` is "strip". A
+    /// rules file that fails to load is logged and ignored.
+    pub fn from_env() -> Self {
+        let mut normalizer = Self::standard();
+        if let Some(mode) = env::var("NORMALIZATION_SYMBOLS")
+            .ok()
+            .and_then(|mode| SymbolMode::from_name(&mode))
+        {
+            normalizer = normalizer.with_rule_replaced(Symbols::new(mode));
+        }
+
+        let Ok(path) = env::var("NORMALIZATION_RULES") else {
+            return normalizer;
+        };
+
+        match RegexRule::load_rules(&path) {
+            Ok(rules) => {
+                tracing::info!("Loaded {} normalization rules from {}", rules.len(), path);
+                rules.into_iter().fold(normalizer, |normalizer, rule| {
+                    normalizer.with_rule_before("abbreviations", rule)
+                })
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load normalization rules from {}: {}", path, e);
+                normalizer
+            }
+        }
+    }
+
+    /// Add a stage after the existing ones
+    pub fn with_rule(mut self, rule: impl NormalizationRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Add a stage just before the one named `name`, or last if there is none
+    pub fn with_rule_before(mut self, name: &str, rule: impl NormalizationRule + 'static) -> Self {
+        let index = self
+            .rules
+            .iter()
+            .position(|existing| existing.name() == name)
+            .unwrap_or(self.rules.len());
+        self.rules.insert(index, Box::new(rule));
+        self
+    }
+
+    /// Replace the stage with the same name as `rule`, or add it last if there is none
+    pub fn with_rule_replaced(mut self, rule: impl NormalizationRule + 'static) -> Self {
+        match self
+            .rules
+            .iter()
+            .position(|existing| existing.name() == rule.name())
+        {
+            Some(index) => self.rules[index] = Box::new(rule),
+            None => self.rules.push(Box::new(rule)),
+        }
+        self
+    }
+
+    /// Names of the stages, in the order they run
+    pub fn rule_names(&self) -> Vec<&str> {
+        self.rules.iter().map(|rule| rule.name()).collect()
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        self.normalize_with(text, &NormalizeOptions::default())
+    }
+
+    /// Normalize text, skipping the stages and expansions `options` disables
+    #[tracing::instrument(name = "tts.normalize", skip_all)]
+    pub fn normalize_with(&self, text: &str, options: &NormalizeOptions) -> String {
+        if text.is_empty() {
+            return String::new();
+        }
+
+        let text = match options.lexicon() {
+            Some(lexicon) => lexicon.respell(text),
+            None => text.to_string(),
+        };
+        self.rules
+            .iter()
+            .filter(|rule| !options.is_disabled(rule.name()))
+            .fold(text, |text, rule| rule.apply_with(&text, options))
+            .trim()
+            .to_string()
+    }
+}
+
+/// Normalizes text for text-to-speech processing with the [`NORMALIZER`] configured from the environment.
+pub fn normalize_text(text: &str) -> String {
+    NORMALIZER.normalize(text)
 }
 
 #[cfg(test)]
@@ -78,4 +571,117 @@ mod tests {
         assert_eq!(normalize_text("Ages 5-12 welcome"), "Ages 5 to 12 welcome");
         assert_eq!(normalize_text("$1,000,000"), "$1000000");
     }
+
+    #[test]
+    fn test_regex_rules() {
+        let rules =
+            RegexRule::parse_rules("# Initialisms\n\n\\bIPA\\b => I P A\n(\\d+)x => $1 times\n")
+                .unwrap();
+        assert_eq!(rules.len(), 2);
+
+        let normalizer = rules.into_iter().fold(Normalizer::standard(), |n, rule| {
+            n.with_rule_before("abbreviations", rule)
+        });
+        assert_eq!(
+            normalizer.rule_names(),
+            vec![
+                "whitespace",
+                "unicode",
+                "symbols",
+                r"\bIPA\b",
+                r"(\d+)x",
+                "abbreviations",
+                "numbers"
+            ]
+        );
+        assert_eq!(
+            normalizer.normalize("Learn  the IPA 2x faster, Dr. Smith"),
+            "Learn the I P A 2 times faster, Doctor Smith"
+        );
+        assert_eq!(normalizer.normalize("IPAs"), "IPAs");
+
+        assert!(RegexRule::parse_rules("no separator").is_err());
+        assert!(RegexRule::parse_rules("(unclosed => x").is_err());
+    }
+
+    #[test]
+    fn test_place_or_title_abbreviations() {
+        let cases = [
+            ("She lives at 12 Main St.", "She lives at 12 Main Street"),
+            (
+                "We met at St. Mary's church.",
+                "We met at Saint Mary's church.",
+            ),
+            ("St. Patrick's Day is fun.", "Saint Patrick's Day is fun."),
+            (
+                "Go to 12 Main St. Springfield",
+                "Go to 12 Main Street Springfield",
+            ),
+            (
+                "Turn onto Elm St. and stop.",
+                "Turn onto Elm Street and stop.",
+            ),
+            ("The St. Lawrence river", "The Saint Lawrence river"),
+            ("Visit Mulholland Dr. today", "Visit Mulholland Drive today"),
+            (
+                "I saw Dr. Smith, then Dr. Jones.",
+                "I saw Doctor Smith, then Doctor Jones.",
+            ),
+            ("Call the Dr. now", "Call the Doctor now"),
+            (
+                "Ask Mary, Dr. Smith's wife.",
+                "Ask Mary, Doctor Smith's wife.",
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(normalize_text(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_disabled_expansions() {
+        let normalizer = Normalizer::standard();
+        let options = NormalizeOptions::default().disable("st").disable("Numbers");
+        assert!(options.is_disabled("St."));
+        assert_eq!(
+            normalizer.normalize_with("Dr. Smith of St. Kilda, ages 5-12", &options),
+            "Doctor Smith of St. Kilda, ages 5-12"
+        );
+    }
+
+    #[test]
+    fn test_normalize_symbols() {
+        assert_eq!(
+            normalize_text("Dr. Smith 👋 says: visit example.com, or email dr.smith@example.com!"),
+            "Doctor Smith waving hand says: visit example dot com, or email dr dot smith at example dot com!"
+        );
+
+        let normalizer = Normalizer::standard().with_rule_replaced(Symbols::new(SymbolMode::Strip));
+        assert_eq!(normalizer.rule_names().len(), 5);
+        assert_eq!(normalizer.normalize("Nice 🔥 work"), "Nice work");
+    }
+
+    #[test]
+    fn test_empty_normalizer() {
+        assert_eq!(Normalizer::empty().normalize("  Dr.  Smith "), "Dr.  Smith");
+    }
+
+    #[test]
+    fn test_lexicon_respells_whole_words() {
+        let lexicon = Lexicon::new([
+            ("Ngaio".to_string(), "Nye oh".to_string()),
+            ("O'Neill".to_string(), "Oh Neel".to_string()),
+        ]);
+        assert_eq!(lexicon.len(), 2);
+
+        let options = NormalizeOptions::default().with_lexicon(Arc::new(lexicon));
+        assert_eq!(
+            Normalizer::standard().normalize_with("NGAIO and O’Neill met in Ngaios.", &options),
+            "Nye oh and Oh Neel met in Ngaios."
+        );
+        assert_eq!(
+            Normalizer::standard().normalize_with("O'Neill, Dr. Ngaio", &options),
+            "Oh Neel, Doctor Nye oh"
+        );
+    }
 }
@@ -0,0 +1,222 @@
+//! How token sequences are padded before they reach the model
+//!
+//! Kokoro was trained with a single `$` (token 0) at each end, but exported
+//! models and fine-tunes differ, and padding other than what a model was
+//! trained with audibly clips or smears the first and last phonemes. The
+//! strategy is read from `TTS_PADDING` if set, else from the model file's
+//! metadata, else the Kokoro default is used.
+
+use std::env;
+
+use crate::error::TtsError;
+use crate::vocab::{REVERSE_VOCABULARY, VOCABULARY};
+
+/// Tokens the model reads in one pass, padding included
+pub const MODEL_CONTEXT: usize = 512;
+
+/// Metadata keys an exported model may set, holding the same values as `TTS_PADDING`
+pub const METADATA_KEYS: [&str; 4] = ["pad_count", "pad_token", "bos", "eos"];
+
+/// Tokens added around every sequence passed to the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaddingStrategy {
+    /// Pad tokens on each side
+    pub count: usize,
+    /// Token repeated as padding
+    pub pad_token: i64,
+    /// Token marking the start, inside the padding
+    pub bos: Option<i64>,
+    /// Token marking the end, inside the padding
+    pub eos: Option<i64>,
+}
+
+impl Default for PaddingStrategy {
+    /// One `$` on each side, as Kokoro was trained
+    fn default() -> Self {
+        Self {
+            count: 1,
+            pad_token: 0,
+            bos: None,
+            eos: None,
+        }
+    }
+}
+
+impl PaddingStrategy {
+    /// Read `TTS_PADDING`, e.g. `count=2,token=$,bos=.,eos=.`, or `None` if unset
+    pub fn from_env() -> Result<Option<Self>, TtsError> {
+        match env::var("TTS_PADDING") {
+            Ok(spec) if !spec.trim().is_empty() => Self::parse(&spec).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Parse comma-separated `key=value` settings, unset keys keeping their defaults
+    ///
+    /// Keys are `count`, `token` (or `pad_token`), `bos` and `eos`; tokens are
+    /// given as an ID or as the vocabulary symbol, and `none` leaves out a
+    /// start or end marker.
+    pub fn parse(spec: &str) -> Result<Self, TtsError> {
+        let settings = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|setting| !setting.is_empty())
+            .map(|setting| {
+                setting.split_once('=').ok_or_else(|| {
+                    TtsError::ModelLoadError(format!(
+                        "padding setting {} is not key=value",
+                        setting
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_settings(
+            settings
+                .into_iter()
+                .map(|(key, value)| (key.trim(), value.trim().to_string())),
+        )
+    }
+
+    /// Read the strategy from metadata keys, or `None` if the model sets none of them
+    pub fn from_metadata(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Self>, TtsError> {
+        let settings: Vec<(&str, String)> = METADATA_KEYS
+            .iter()
+            .filter_map(|&key| Some((key, lookup(key)?)))
+            .collect();
+        if settings.is_empty() {
+            return Ok(None);
+        }
+        Self::from_settings(settings).map(Some)
+    }
+
+    fn from_settings<'a>(
+        settings: impl IntoIterator<Item = (&'a str, String)>,
+    ) -> Result<Self, TtsError> {
+        let mut strategy = Self::default();
+        for (key, value) in settings {
+            match key {
+                "count" | "pad_count" => {
+                    strategy.count = value.parse().map_err(|_| {
+                        TtsError::ModelLoadError(format!("padding count {} is not a number", value))
+                    })?
+                }
+                "token" | "pad_token" => strategy.pad_token = parse_token(&value)?,
+                "bos" => strategy.bos = parse_marker(&value)?,
+                "eos" => strategy.eos = parse_marker(&value)?,
+                _ => {
+                    return Err(TtsError::ModelLoadError(format!(
+                        "unknown padding setting {}",
+                        key
+                    )));
+                }
+            }
+        }
+        if strategy.overhead() >= MODEL_CONTEXT {
+            return Err(TtsError::ModelLoadError(format!(
+                "padding of {} tokens leaves no room in the model's {}",
+                strategy.overhead(),
+                MODEL_CONTEXT
+            )));
+        }
+        Ok(strategy)
+    }
+
+    /// Tokens added to every sequence
+    pub fn overhead(&self) -> usize {
+        2 * self.count + usize::from(self.bos.is_some()) + usize::from(self.eos.is_some())
+    }
+
+    /// Most tokens of content that fit in one pass with this padding
+    pub fn max_content_tokens(&self) -> usize {
+        MODEL_CONTEXT - self.overhead()
+    }
+
+    /// `tokens` with the padding and markers around them
+    pub fn apply(&self, tokens: &[i64]) -> Vec<i64> {
+        let mut padded = Vec::with_capacity(tokens.len() + self.overhead());
+        padded.extend(std::iter::repeat_n(self.pad_token, self.count));
+        padded.extend(self.bos);
+        padded.extend_from_slice(tokens);
+        padded.extend(self.eos);
+        padded.extend(std::iter::repeat_n(self.pad_token, self.count));
+        padded
+    }
+}
+
+/// A token given as its ID or its symbol, which must be in the vocabulary
+fn parse_token(value: &str) -> Result<i64, TtsError> {
+    let id = match value.parse::<usize>() {
+        Ok(id) if REVERSE_VOCABULARY.contains_key(&id) => Some(id),
+        Ok(_) => None,
+        Err(_) => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(symbol), None) => VOCABULARY.get(&symbol).copied(),
+                _ => None,
+            }
+        }
+    };
+    id.map(|id| id as i64).ok_or_else(|| {
+        TtsError::ModelLoadError(format!("padding token {} is not in the vocabulary", value))
+    })
+}
+
+fn parse_marker(value: &str) -> Result<Option<i64>, TtsError> {
+    match value {
+        "" | "none" => Ok(None),
+        value => parse_token(value).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_matches_kokoro() {
+        let strategy = PaddingStrategy::default();
+        assert_eq!(strategy.apply(&[50, 47]), vec![0, 50, 47, 0]);
+        assert_eq!(strategy.max_content_tokens(), 510);
+    }
+
+    #[test]
+    fn test_parse() {
+        let strategy = PaddingStrategy::parse("count=3, token=$").unwrap();
+        assert_eq!(strategy.apply(&[50]), vec![0, 0, 0, 50, 0, 0, 0]);
+
+        // Markers go inside the padding, given by symbol or ID
+        let strategy = PaddingStrategy::parse("count=1,bos=.,eos=4").unwrap();
+        let dot = VOCABULARY[&'.'] as i64;
+        assert_eq!(strategy.apply(&[50]), vec![0, dot, 50, 4, 0]);
+        assert_eq!(strategy.max_content_tokens(), 508);
+
+        let strategy = PaddingStrategy::parse("count=0,bos=none").unwrap();
+        assert_eq!(strategy.apply(&[50]), vec![50]);
+
+        for bad in [
+            "count",
+            "count=many",
+            "token=9999",
+            "token=ab",
+            "width=2",
+            "count=300",
+        ] {
+            assert!(PaddingStrategy::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_from_metadata() {
+        assert_eq!(PaddingStrategy::from_metadata(|_| None).unwrap(), None);
+
+        let strategy = PaddingStrategy::from_metadata(|key| match key {
+            "pad_count" => Some("2".to_string()),
+            _ => None,
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(strategy.apply(&[50]), vec![0, 0, 50, 0, 0]);
+    }
+}
@@ -1,17 +1,128 @@
 use espeak_rs::text_to_phonemes;
-use std::sync::{LazyLock, Mutex};
+
+use crate::espeak::espeak_data_dir;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex, MutexGuard};
 
 static PHONEMIZER_MUTEX: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 
+/// Number of phonemized texts kept in [`PHONEME_CACHE`]
+pub const PHONEME_CACHE_CAPACITY: usize = 1000;
+
+/// Phonemizations shared by synthesis and the phonemize endpoint
+pub static PHONEME_CACHE: LazyLock<PhonemeCache> =
+    LazyLock::new(|| PhonemeCache::new(PHONEME_CACHE_CAPACITY));
+
+/// Hits and misses of a [`PhonemeCache`] since it was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhonemeCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+impl PhonemeCacheStats {
+    /// Share of lookups answered from the cache, or 0.0 before any lookup
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Least recently used cache of espeak's output, keyed by text and language
+///
+/// espeak calls are serialized behind a global lock, so text said again, such
+/// as a sentence replayed or phonemized for display and then synthesized, is
+/// answered from here instead. Texts differing only in whitespace share an
+/// entry. Failures are not cached.
+pub struct PhonemeCache {
+    entries: Mutex<LruCache<(String, String), String>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl PhonemeCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Phonemes of `text` in `lang`, phonemized with espeak on a miss
+    pub fn phonemize(&self, text: &str, lang: &str) -> Result<String, String> {
+        self.get_or_insert_with(text, lang, text_to_phonemes_string)
+    }
+
+    /// Phonemes of `text` in `lang`, computed with `phonemize` on a miss
+    ///
+    /// The cache is not locked while `phonemize` runs, so lookups of other
+    /// texts are not held up behind espeak.
+    pub fn get_or_insert_with(
+        &self,
+        text: &str,
+        lang: &str,
+        phonemize: impl FnOnce(&str, &str) -> Result<String, String>,
+    ) -> Result<String, String> {
+        let key = (
+            text.split_whitespace().collect::<Vec<_>>().join(" "),
+            lang.to_lowercase(),
+        );
+
+        if let Some(phonemes) = self.lock().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(phonemes.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let phonemes = phonemize(&key.0, lang)?;
+        self.lock().put(key, phonemes.clone());
+        Ok(phonemes)
+    }
+
+    /// Keep at most `capacity` phonemizations, dropping the least recently used
+    pub fn resize(&self, capacity: NonZeroUsize) {
+        self.lock().resize(capacity);
+    }
+
+    pub fn stats(&self) -> PhonemeCacheStats {
+        let entries = self.lock();
+        PhonemeCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: entries.len(),
+            capacity: entries.cap().get(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LruCache<(String, String), String>> {
+        // Entries are only ever whole, so a poisoned cache is still consistent
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
 /// Converts a string of text into a vector of phonemes using the specified language.
+#[tracing::instrument(name = "tts.phonemize", skip(text))]
 pub fn text_to_phonemes_string(text: &str, lang: &str) -> Result<String, String> {
-    let _guard = (*PHONEMIZER_MUTEX)
-        .lock()
-        .map_err(|_| "Failed to acquire phonemizer lock".to_string())?;
+    // The lock guards no data, only espeak's global state, so a poisoned lock is still usable
+    let _guard = PHONEMIZER_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+    let data_dir = espeak_data_dir();
 
     text_to_phonemes(text, lang, None, true, false)
         .map(|phonemes| phonemes.join(""))
-        .map_err(|err| format!("Phonemizer error: {}", err))
+        .map_err(|err| match data_dir {
+            Ok(_) => format!("Phonemizer error: {}", err),
+            Err(data_err) => format!("Phonemizer error: {} ({})", err, data_err),
+        })
 }
 
 #[cfg(test)]
@@ -34,6 +145,36 @@ mod tests {
         assert!(!phonemes.is_empty(), "Phonemes should not be empty");
     }
 
+    #[test]
+    fn test_phoneme_cache_shares_entries_across_whitespace() {
+        let cache = PhonemeCache::new(2);
+        let calls = std::cell::Cell::new(0);
+        let phonemize = |text: &str, _: &str| {
+            calls.set(calls.get() + 1);
+            Ok(text.to_uppercase())
+        };
+
+        assert_eq!(
+            cache.get_or_insert_with("hello  world", "en-us", phonemize),
+            Ok("HELLO WORLD".to_string())
+        );
+        assert_eq!(
+            cache.get_or_insert_with(" hello world\n", "EN-US", phonemize),
+            Ok("HELLO WORLD".to_string())
+        );
+        cache
+            .get_or_insert_with("hello world", "en", phonemize)
+            .unwrap();
+        assert_eq!(calls.get(), 2);
+
+        let failed = cache.get_or_insert_with("x", "en", |_, _| Err("no".to_string()));
+        assert!(failed.is_err());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 2));
+        assert_eq!(stats.hit_rate(), 0.25);
+    }
+
     #[test]
     fn test_invalid_language() {
         let text = "Hello, world!";
@@ -0,0 +1,134 @@
+//! Stable API for embedding speech synthesis in other applications
+//!
+//! Everything re-exported here follows semver. Other modules of this crate
+//! may change between minor versions.
+//!
+//! ```no_run
+//! use ipa_navigator_kokoro::prelude::*;
+//!
+//! let engine = TtsEngine::new()?;
+//! let voice = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
+//! let wav = engine.synthesize("Hello there", voice, 1.0)?.to_wav()?;
+//! # Ok::<(), TtsError>(())
+//! ```
+
+use crate::segment::split_sentences;
+use crate::tts::KokoroTTS;
+use crate::wav::encode_wav;
+
+pub use crate::error::TtsError;
+pub use crate::espeak::{EspeakDataSource, check_espeak, configure_espeak};
+pub use crate::normalize::{NormalizationRule, NormalizeOptions, Normalizer, RegexRule};
+pub use crate::voices::{
+    AmericanFemaleVoice, AmericanMaleVoice, BritishFemaleVoice, BritishMaleVoice, VoiceType,
+};
+pub use crate::wav::{SampleEncoding, WavFormat};
+
+/// Sample rate of all synthesized audio
+pub const SAMPLE_RATE: u32 = 24000;
+
+/// Synthesized mono audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Audio {
+    samples: Vec<f32>,
+}
+
+impl Audio {
+    /// Samples in `[-1, 1]` at [`SAMPLE_RATE`]
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn into_samples(self) -> Vec<f32> {
+        self.samples
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        self.samples.len() as f64 / SAMPLE_RATE as f64
+    }
+
+    /// Encode as a 16-bit PCM WAV file
+    pub fn to_wav(&self) -> Result<Vec<u8>, TtsError> {
+        self.to_wav_with(&WavFormat::default())
+    }
+
+    /// Encode as a WAV file with another sample encoding or rate, e.g. 24-bit at 48 kHz
+    pub fn to_wav_with(&self, format: &WavFormat) -> Result<Vec<u8>, TtsError> {
+        encode_wav(&self.samples, format)
+    }
+}
+
+/// Text-to-speech engine holding the Kokoro model and voices
+///
+/// Loading is expensive, so create one engine and share it; it is `Send + Sync`.
+/// Assets are read from the directory named by `ASSETS_PATH`, and espeak-ng's
+/// data from the directory passed to [`configure_espeak`] beforehand or else
+/// found as [`EspeakDataSource::Auto`] describes.
+pub struct TtsEngine {
+    tts: KokoroTTS,
+}
+
+impl TtsEngine {
+    /// Load the model and every voice
+    pub fn new() -> Result<Self, TtsError> {
+        Ok(Self {
+            tts: KokoroTTS::new()?,
+        })
+    }
+
+    /// Normalize text with `normalizer`, e.g. one with domain-specific rules
+    pub fn with_normalizer(self, normalizer: Normalizer) -> Self {
+        Self {
+            tts: self.tts.with_normalizer(normalizer),
+        }
+    }
+
+    /// Synthesize text, reusing recent results for identical requests
+    ///
+    /// `speed` scales the speaking rate and should be between 0.5 and 2.0.
+    pub fn synthesize(&self, text: &str, voice: VoiceType, speed: f32) -> Result<Audio, TtsError> {
+        let audio = self.tts.process_tts(text, &voice, speed)?;
+        Ok(Audio {
+            samples: audio.iter().copied().collect(),
+        })
+    }
+
+    /// Synthesize text, skipping the normalization `options` disables, e.g. expanding "St."
+    pub fn synthesize_with(
+        &self,
+        text: &str,
+        voice: VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+    ) -> Result<Audio, TtsError> {
+        let audio = self.tts.process_tts_with(text, &voice, speed, options)?;
+        Ok(Audio {
+            samples: audio.iter().copied().collect(),
+        })
+    }
+
+    /// Synthesize text a sentence at a time, yielding each sentence's audio as it is ready
+    ///
+    /// Lets long text start playing before all of it is synthesized. Sentences
+    /// are split with [`split_sentences`](crate::segment::split_sentences).
+    pub fn synthesize_sentences<'a>(
+        &'a self,
+        text: &str,
+        voice: VoiceType,
+        speed: f32,
+    ) -> impl Iterator<Item = Result<Audio, TtsError>> + 'a {
+        split_sentences(text)
+            .into_iter()
+            .map(move |sentence| self.synthesize(&sentence, voice, speed))
+    }
+
+    /// Voices that loaded successfully
+    pub fn voices(&self) -> Vec<VoiceType> {
+        self.tts.available_voices()
+    }
+
+    /// Re-read the voice files from disk
+    pub fn reload_voices(&self) -> Result<usize, TtsError> {
+        self.tts.reload_voices()
+    }
+}
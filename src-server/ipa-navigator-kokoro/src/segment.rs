@@ -0,0 +1,198 @@
+//! Rule-based sentence segmentation for chunked synthesis
+//!
+//! Long text is synthesized a sentence at a time, so a bad split is audible as
+//! a pause in the middle of a sentence. The rules here keep quoted speech,
+//! abbreviations, initials, and decimal numbers inside their sentence.
+
+/// Words that end in a full stop without ending the sentence, without their dots
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "vs", "eg", "ie", "ave", "rd", "blvd", "mt",
+    "no", "fig", "approx", "dept",
+];
+
+/// Characters that end a sentence
+fn is_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…')
+}
+
+/// Characters that close a sentence after its terminator, such as `?"` or `.)`
+fn is_closer(c: char) -> bool {
+    matches!(c, '"' | '\'' | ')' | ']' | '”' | '’' | '»')
+}
+
+/// Split text into sentences for synthesis
+///
+/// A sentence ends at a terminator followed by whitespace, unless:
+/// - it is inside double quotes, so `"Wait. Stop!" she said.` stays whole
+/// - the full stop belongs to an abbreviation such as "Dr." or an initial such as "J."
+/// - the next word starts in lowercase, as after "..." mid-sentence
+///
+/// Decimal numbers never split since no whitespace follows their point. A
+/// quote left open at the end of the text is treated as a stray character.
+/// Whitespace within each sentence is collapsed.
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let (mut sentences, open_quote) = segment(text, true);
+
+    // An unbalanced quote would otherwise swallow the rest of the text
+    if open_quote && let Some(last) = sentences.pop() {
+        sentences.extend(segment(&last, false).0);
+    }
+
+    sentences
+}
+
+/// Segment text, returning the sentences and whether a quote was left open
+fn segment(text: &str, respect_quotes: bool) -> (Vec<String>, bool) {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut in_quote = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        current.push(c);
+        match c {
+            '"' => in_quote = !in_quote,
+            '“' | '«' => in_quote = true,
+            '”' | '»' => in_quote = false,
+            _ => {}
+        }
+        if !is_terminator(c) {
+            continue;
+        }
+
+        // Keep runs such as "?!" and closing quotes with the sentence
+        while let Some(&next) = chars.peek()
+            && (is_terminator(next) || is_closer(next))
+        {
+            match next {
+                '"' => in_quote = !in_quote,
+                '”' | '»' => in_quote = false,
+                _ => {}
+            }
+            current.push(next);
+            chars.next();
+        }
+
+        if respect_quotes && in_quote {
+            continue;
+        }
+        if !chars.peek().is_none_or(|next| next.is_whitespace()) {
+            continue;
+        }
+        if c == '.' && ends_with_abbreviation(&current) {
+            continue;
+        }
+        if next_word_is_lowercase(chars.clone()) {
+            continue;
+        }
+
+        push_sentence(&mut sentences, &current);
+        current.clear();
+    }
+    push_sentence(&mut sentences, &current);
+
+    (sentences, respect_quotes && in_quote)
+}
+
+/// Add a sentence with its whitespace collapsed, if it has any letters or digits
+fn push_sentence(sentences: &mut Vec<String>, sentence: &str) {
+    if sentence.chars().any(|c| c.is_alphanumeric()) {
+        sentences.push(sentence.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+}
+
+/// Whether text ends with an abbreviation or initial followed by its full stop
+fn ends_with_abbreviation(text: &str) -> bool {
+    let Some(last) = text.split_whitespace().last() else {
+        return false;
+    };
+    // Closers were appended after the stop, so a quoted "Dr." is not an abbreviation
+    if !last.ends_with('.') {
+        return false;
+    }
+    let word: String = last
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect();
+
+    let is_initial = word.chars().count() == 1 && word.chars().all(|c| c.is_uppercase());
+    is_initial || ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// Whether the next word, skipping whitespace and opening punctuation, starts in lowercase
+fn next_word_is_lowercase(mut rest: impl Iterator<Item = char>) -> bool {
+    rest.find(|c| c.is_alphanumeric())
+        .is_some_and(|c| c.is_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split_sentences("Hello there.  How are you?I'm fine! Thanks"),
+            vec!["Hello there.", "How are you?I'm fine!", "Thanks"]
+        );
+        assert_eq!(split_sentences("  ...  "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_split_sentences_quotes() {
+        assert_eq!(
+            split_sentences("\"Wait. Stop right there!\" she said. He stopped."),
+            vec!["\"Wait. Stop right there!\" she said.", "He stopped."]
+        );
+        assert_eq!(
+            split_sentences("He asked, “Are you sure? Really sure?” Nobody answered."),
+            vec!["He asked, “Are you sure? Really sure?”", "Nobody answered."]
+        );
+        assert_eq!(
+            split_sentences("She said \"stop!\" Then she left."),
+            vec!["She said \"stop!\"", "Then she left."]
+        );
+    }
+
+    #[test]
+    fn test_split_sentences_unbalanced_quote() {
+        assert_eq!(
+            split_sentences("A stray \" mark. Then more. And more."),
+            vec!["A stray \" mark.", "Then more.", "And more."]
+        );
+    }
+
+    #[test]
+    fn test_split_sentences_abbreviations() {
+        assert_eq!(
+            split_sentences("Dr. Smith met J. R. Jones on Main St. at noon. Done."),
+            vec!["Dr. Smith met J. R. Jones on Main St. at noon.", "Done."]
+        );
+        // Normalization expands "Dr." before chunking, leaving nothing to trip on
+        assert_eq!(
+            split_sentences("Doctor Smith arrived. Mister Jones left."),
+            vec!["Doctor Smith arrived.", "Mister Jones left."]
+        );
+        assert_eq!(
+            split_sentences("Use tools, e.g. a saw. Then sand."),
+            vec!["Use tools, e.g. a saw.", "Then sand."]
+        );
+    }
+
+    #[test]
+    fn test_split_sentences_numbers_and_ellipses() {
+        assert_eq!(
+            split_sentences("It costs 3.50 today. Pi is 3.14159! Version 2.0.1 shipped."),
+            vec![
+                "It costs 3.50 today.",
+                "Pi is 3.14159!",
+                "Version 2.0.1 shipped."
+            ]
+        );
+        assert_eq!(
+            split_sentences("Well... maybe not. Wait… What?! (Really.) Yes."),
+            vec!["Well... maybe not.", "Wait…", "What?!", "(Really.)", "Yes."]
+        );
+    }
+}
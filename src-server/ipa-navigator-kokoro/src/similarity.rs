@@ -0,0 +1,128 @@
+//! Similarity between voices, from their averaged style vectors
+//!
+//! Computed once when the embeddings load, so suggesting a similar voice or
+//! choosing the parents of a blended voice is a lookup rather than a pass
+//! over every voice file.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::model::style_vector;
+use crate::voices::{ALL_VOICES, VoiceType};
+
+/// Another voice and how close it is, from -1 (opposite) to 1 (the same style)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimilarVoice {
+    pub voice: VoiceType,
+    pub similarity: f32,
+}
+
+/// Pairwise cosine similarity between the loaded voices
+#[derive(Debug, Default)]
+pub struct VoiceSimilarity {
+    /// For each voice, every other voice from most to least similar
+    ranked: HashMap<VoiceType, Vec<SimilarVoice>>,
+}
+
+impl VoiceSimilarity {
+    /// Compare every pair of voices in `embeddings`
+    pub fn compute(embeddings: &HashMap<VoiceType, Arc<[f32]>>) -> Self {
+        // Voices are compared in `ALL_VOICES` order, so ties rank the same way every time
+        let styles: Vec<(VoiceType, Vec<f32>)> = ALL_VOICES
+            .iter()
+            .filter_map(|voice| Some((*voice, style_vector(embeddings.get(voice)?))))
+            .collect();
+
+        let ranked = styles
+            .iter()
+            .map(|(voice, style)| {
+                let mut similar: Vec<SimilarVoice> = styles
+                    .iter()
+                    .filter(|(other, _)| other != voice)
+                    .map(|(other, other_style)| SimilarVoice {
+                        voice: *other,
+                        similarity: cosine_similarity(style, other_style),
+                    })
+                    .collect();
+                similar.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+                (*voice, similar)
+            })
+            .collect();
+
+        Self { ranked }
+    }
+
+    /// Other voices from most to least similar to `voice`, or `None` if it did not load
+    pub fn similar(&self, voice: &VoiceType) -> Option<&[SimilarVoice]> {
+        self.ranked.get(voice).map(Vec::as_slice)
+    }
+
+    /// Similarity between two loaded voices
+    pub fn between(&self, a: &VoiceType, b: &VoiceType) -> Option<f32> {
+        if a == b {
+            return self.ranked.contains_key(a).then_some(1.0);
+        }
+        self.similar(a)?
+            .iter()
+            .find(|similar| similar.voice == *b)
+            .map(|similar| similar.similarity)
+    }
+}
+
+/// Cosine of the angle between two vectors, or 0 if either is all zeros
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let (dot, norm_a, norm_b) =
+        a.iter()
+            .zip(b)
+            .fold((0.0f64, 0.0f64, 0.0f64), |(dot, na, nb), (&x, &y)| {
+                let (x, y) = (x as f64, y as f64);
+                (dot + x * y, na + x * x, nb + y * y)
+            });
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    (dot / (norm_a.sqrt() * norm_b.sqrt())) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::STYLE_DIM;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+
+    #[test]
+    fn test_voices_ranked_by_similarity() {
+        let style = |values: [f32; 2]| -> Arc<[f32]> {
+            let mut embedding = vec![0.0; STYLE_DIM];
+            embedding[..2].copy_from_slice(&values);
+            embedding.into()
+        };
+        let [a, b, c, unloaded] = [ALL_VOICES[0], ALL_VOICES[1], ALL_VOICES[2], ALL_VOICES[3]];
+        let embeddings = HashMap::from([
+            (a, style([1.0, 0.0])),
+            (b, style([0.0, 1.0])),
+            (c, style([1.0, 0.2])),
+        ]);
+
+        let similarity = VoiceSimilarity::compute(&embeddings);
+        let ranked: Vec<VoiceType> = similarity
+            .similar(&a)
+            .unwrap()
+            .iter()
+            .map(|similar| similar.voice)
+            .collect();
+        assert_eq!(ranked, vec![c, b]);
+
+        assert_eq!(similarity.between(&a, &a), Some(1.0));
+        assert_eq!(similarity.between(&a, &b), similarity.between(&b, &a));
+        assert!(similarity.similar(&unloaded).is_none());
+        assert!(similarity.between(&a, &unloaded).is_none());
+    }
+}
@@ -0,0 +1,273 @@
+//! Synthesis of phonemes too long for one pass of the model, in chunks
+//!
+//! The model reads at most [`MAX_TOKENS`](crate::tokenize::MAX_TOKENS) tokens
+//! at once with its default padding, so a longer sentence is split at word
+//! boundaries and its chunks joined. Each chunk starts its intonation afresh,
+//! which is heard as a reset in pitch and loudness at the junction. With
+//! overlap, each chunk also reads the last word of the one before, and the two
+//! readings of that word are blended, so the next chunk has already settled by
+//! the time it is heard alone.
+
+use std::env;
+use std::sync::LazyLock;
+
+use crate::error::TtsError;
+use crate::tokenize::tokenize;
+
+/// Whether chunks overlap by a word, from `TTS_CHUNK_OVERLAP`; on unless set to `false` or `0`
+pub static CHUNK_OVERLAP: LazyLock<bool> = LazyLock::new(|| {
+    env::var("TTS_CHUNK_OVERLAP")
+        .map(|value| !matches!(value.trim(), "false" | "0" | "off"))
+        .unwrap_or(true)
+});
+
+/// Longest blend between overlapping chunks, in samples (100 ms at 24 kHz)
+const MAX_BLEND_SAMPLES: usize = 2400;
+
+/// A piece of the phonemes read in one pass, and how many of its words repeat the previous chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub phonemes: String,
+    pub overlap_words: usize,
+}
+
+/// Split phonemes into chunks of at most `max_tokens` tokens, breaking between words
+///
+/// With `overlap`, every chunk after the first starts with the last word of
+/// the one before. A single word longer than `max_tokens` is its own chunk,
+/// and is cut short by the model.
+pub fn split_chunks(phonemes: &str, max_tokens: usize, overlap: bool) -> Vec<Chunk> {
+    let words: Vec<&str> = phonemes.split_whitespace().collect();
+    // Spaces are tokens too, so a word costs one more than its own tokens
+    let cost = |word: &str| tokenize(word).len() + 1;
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let carried = usize::from(overlap && start > 0);
+        let first = start - carried;
+
+        let mut tokens = 0;
+        let mut end = first;
+        while end < words.len() && (end <= start || tokens + cost(words[end]) <= max_tokens + 1) {
+            tokens += cost(words[end]);
+            end += 1;
+        }
+        // The carried word leaves no room for another, so drop it rather than stall
+        let (first, carried) = if end == start + 1 && carried > 0 && tokens > max_tokens + 1 {
+            (start, 0)
+        } else {
+            (first, carried)
+        };
+
+        chunks.push(Chunk {
+            phonemes: words[first..end].join(" "),
+            overlap_words: carried,
+        });
+        start = end;
+    }
+    chunks
+}
+
+/// Synthesize phonemes in chunks of at most `max_tokens` with `synthesize`, joining their audio
+///
+/// Phonemes within `max_tokens` are synthesized in one pass.
+pub fn synthesize_chunked(
+    phonemes: &str,
+    max_tokens: usize,
+    overlap: bool,
+    synthesize: impl FnMut(&str) -> Result<Vec<f32>, TtsError>,
+) -> Result<Vec<f32>, TtsError> {
+    synthesize_chunks(&split_chunks(phonemes, max_tokens, overlap), synthesize)
+}
+
+/// Synthesize each chunk and join them, blending the words they share
+pub fn synthesize_chunks(
+    chunks: &[Chunk],
+    mut synthesize: impl FnMut(&str) -> Result<Vec<f32>, TtsError>,
+) -> Result<Vec<f32>, TtsError> {
+    let mut samples: Vec<f32> = Vec::new();
+    // The previous chunk and the length of its own audio
+    let mut previous: Option<(&Chunk, usize)> = None;
+
+    for chunk in chunks {
+        let audio = synthesize(&chunk.phonemes)?;
+        match previous {
+            Some((previous, previous_len)) if chunk.overlap_words > 0 => {
+                let shared = last_words(&previous.phonemes, chunk.overlap_words);
+                // Where the shared words are is estimated from their share of each chunk's tokens
+                let tail = span_samples(previous_len, &previous.phonemes, shared);
+                let head = span_samples(audio.len(), &chunk.phonemes, shared);
+                join_overlapping(&mut samples, &audio, tail, head);
+            }
+            _ => samples.extend_from_slice(&audio),
+        }
+        previous = Some((chunk, audio.len()));
+    }
+    Ok(samples)
+}
+
+/// Append `next` to `samples`, where the last `tail` samples of one and the
+/// first `head` of the other read the same words
+///
+/// The two readings are cross-faded with equal-power curves over as much of
+/// the shared words as both cover, centred on their middle, so the junction
+/// keeps its loudness and the shared words keep their length.
+pub fn join_overlapping(samples: &mut Vec<f32>, next: &[f32], tail: usize, head: usize) {
+    let tail = tail.min(samples.len());
+    let head = head.min(next.len());
+    let blend = tail.min(head).min(MAX_BLEND_SAMPLES);
+
+    // Keep the previous reading up to the middle of the shared words and the next from there,
+    // each with half the blend to spare
+    samples.truncate(samples.len() - tail / 2 + blend / 2);
+    let next = &next[head / 2 - blend / 2..];
+    let blend = blend.min(samples.len()).min(next.len());
+    let offset = samples.len() - blend;
+    for (i, &incoming) in next[..blend].iter().enumerate() {
+        let t = (i as f32 + 0.5) / blend as f32 * std::f32::consts::FRAC_PI_2;
+        let outgoing = &mut samples[offset + i];
+        *outgoing = *outgoing * t.cos() + incoming * t.sin();
+    }
+    samples.extend_from_slice(&next[blend..]);
+}
+
+/// Samples of `audio_len` spent on `words`, in proportion to their tokens among the chunk's
+fn span_samples(audio_len: usize, chunk: &str, words: &str) -> usize {
+    let total = tokenize(chunk).len();
+    if total == 0 {
+        return 0;
+    }
+    // The space before the shared words is heard with them
+    let share = (tokenize(words).len() + 1).min(total);
+    audio_len * share / total
+}
+
+/// The last `count` words of `phonemes`
+fn last_words(phonemes: &str, count: usize) -> &str {
+    let mut start = phonemes.len();
+    for _ in 0..count {
+        let trimmed = phonemes[..start].trim_end();
+        start = trimmed.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    }
+    phonemes[start..].trim_end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::SAMPLE_RATE;
+    use crate::tokenize::MAX_TOKENS;
+
+    /// Largest change in loudness between adjacent 10 ms windows within 100 ms of `at`, in RMS
+    fn junction_discontinuity(samples: &[f32], at: usize) -> f32 {
+        let window = SAMPLE_RATE as usize / 100;
+        let rms = |start: usize| {
+            let range = &samples[start..start + window];
+            (range.iter().map(|s| s * s).sum::<f32>() / range.len() as f32).sqrt()
+        };
+        (at - 10 * window..at + 9 * window)
+            .step_by(window)
+            .map(|start| (rms(start) - rms(start + window)).abs())
+            .fold(0.0, f32::max)
+    }
+
+    /// A reading that starts loud and fades, as each chunk's intonation resets,
+    /// `samples_per_token` long for each token
+    fn reading(phonemes: &str, samples_per_token: usize) -> Vec<f32> {
+        let len = tokenize(phonemes).len() * samples_per_token;
+        (0..len)
+            .map(|i| {
+                let envelope = 1.0 - 0.8 * i as f32 / len as f32;
+                envelope * (i as f32 * 0.2).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_short_phonemes_are_one_chunk() {
+        let chunks = split_chunks("hˈɛloʊ wˈɜːld", MAX_TOKENS, true);
+        assert_eq!(
+            chunks,
+            vec![Chunk {
+                phonemes: "hˈɛloʊ wˈɜːld".to_string(),
+                overlap_words: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_chunks_fit_and_overlap_by_a_word() {
+        let phonemes = "ðə kˈæt sˈæt ɔn ðə mˈæt ænd lˈʊkt æt ðə dˈɔɡ";
+        for overlap in [false, true] {
+            let chunks = split_chunks(phonemes, 12, overlap);
+            assert!(chunks.len() > 1);
+            for chunk in &chunks {
+                assert!(tokenize(&chunk.phonemes).len() <= 12, "{:?}", chunk);
+            }
+
+            // Without the carried words, the chunks read the phonemes exactly once
+            let words: Vec<&str> = chunks
+                .iter()
+                .flat_map(|chunk| chunk.phonemes.split_whitespace().skip(chunk.overlap_words))
+                .collect();
+            assert_eq!(words.join(" "), phonemes);
+
+            let carried = chunks.iter().skip(1).all(|chunk| chunk.overlap_words == 1);
+            assert_eq!(carried, overlap);
+        }
+    }
+
+    #[test]
+    fn test_overlong_word_is_its_own_chunk() {
+        // Carrying the long word would leave no room, so the last chunk does without it
+        let chunks = split_chunks("ə ɹˈɛəliːlˈɔŋwɜːd ə", 4, true);
+        let phonemes: Vec<&str> = chunks.iter().map(|chunk| chunk.phonemes.as_str()).collect();
+        assert_eq!(phonemes, vec!["ə", "ɹˈɛəliːlˈɔŋwɜːd", "ə"]);
+        assert!(chunks.iter().all(|chunk| chunk.overlap_words == 0));
+    }
+
+    #[test]
+    fn test_overlap_smooths_junction_energy() {
+        let phonemes = "ðə kˈæt sˈæt ɔn ðə mˈæt ænd lˈʊkt æt ðə dˈɔɡ";
+        let per_token = 200;
+        let synthesize = |chunk: &str| Ok(reading(chunk, per_token));
+
+        let hard = split_chunks(phonemes, 24, false);
+        let hard_audio = synthesize_chunks(&hard, synthesize).unwrap();
+        let hard_junction = reading(&hard[0].phonemes, per_token).len();
+
+        let blended = split_chunks(phonemes, 24, true);
+        let blended_audio = synthesize_chunks(&blended, synthesize).unwrap();
+        // The junction is where the first chunk's reading of the shared word was cut
+        let first = &blended[0].phonemes;
+        let first_len = reading(first, per_token).len();
+        let tail = span_samples(first_len, first, last_words(first, 1));
+        let blended_junction = first_len - tail / 2;
+
+        let hard_jump = junction_discontinuity(&hard_audio, hard_junction);
+        let blended_jump = junction_discontinuity(&blended_audio, blended_junction);
+        assert!(
+            blended_jump < hard_jump / 2.0,
+            "blended {} vs hard {}",
+            blended_jump,
+            hard_jump
+        );
+    }
+
+    #[test]
+    fn test_join_keeps_constant_signal_level() {
+        let mut samples = vec![0.5; 4000];
+        join_overlapping(&mut samples, &[0.5; 4000], 1000, 1000);
+        assert_eq!(samples.len(), 4000 + 4000 - 1000);
+        // Equal-power curves add at most 3 dB where uncorrelated audio would be level
+        assert!(samples.iter().all(|&s| (0.5..=0.5 * 1.42).contains(&s)));
+    }
+
+    #[test]
+    fn test_last_words() {
+        assert_eq!(last_words("ðə kˈæt sˈæt", 1), "sˈæt");
+        assert_eq!(last_words("ðə kˈæt sˈæt ", 2), "kˈæt sˈæt");
+        assert_eq!(last_words("sˈæt", 3), "sˈæt");
+    }
+}
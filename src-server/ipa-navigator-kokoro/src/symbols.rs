@@ -0,0 +1,320 @@
+//! Reading emoji, symbols, URLs, and email addresses aloud
+//!
+//! The tokenizer has no phonemes for these, so pasted text containing them
+//! produced dropped tokens and stretches of silence. The [`Symbols`] stage
+//! turns them into words, or removes them entirely.
+
+use crate::normalize::NormalizationRule;
+use regex::{Captures, Regex};
+
+/// Emoji read aloud, named as learners would describe them
+const EMOJI_NAMES: &[(char, &str)] = &[
+    ('😀', "grinning face"),
+    ('😂', "face with tears of joy"),
+    ('😊', "smiling face"),
+    ('🙂', "slightly smiling face"),
+    ('😉', "winking face"),
+    ('😍', "heart eyes"),
+    ('😢', "crying face"),
+    ('😮', "surprised face"),
+    ('🤔', "thinking face"),
+    ('👍', "thumbs up"),
+    ('👎', "thumbs down"),
+    ('👋', "waving hand"),
+    ('👏', "clapping hands"),
+    ('🙏', "folded hands"),
+    ('❤', "red heart"),
+    ('🔥', "fire"),
+    ('🎉', "party popper"),
+    ('⭐', "star"),
+    ('✅', "check mark"),
+    ('✔', "check mark"),
+    ('❌', "cross mark"),
+    ('⚠', "warning"),
+    ('💡', "light bulb"),
+    ('📚', "books"),
+    ('🎧', "headphones"),
+    ('🎤', "microphone"),
+];
+
+/// Symbols read as words; those not listed are left for the tokenizer
+const SYMBOL_WORDS: &[(char, &str)] = &[
+    ('+', "plus"),
+    ('=', "equals"),
+    ('×', "times"),
+    ('÷', "divided by"),
+    ('±', "plus or minus"),
+    ('−', "minus"),
+    ('≠', "is not equal to"),
+    ('≈', "is approximately"),
+    ('≤', "is at most"),
+    ('≥', "is at least"),
+    ('<', "is less than"),
+    ('>', "is greater than"),
+    ('√', "the square root of"),
+    ('∞', "infinity"),
+    ('π', "pi"),
+    ('%', "percent"),
+    ('°', "degrees"),
+    ('&', "and"),
+    ('@', "at"),
+    ('~', "about"),
+    ('©', "copyright"),
+    ('®', "registered"),
+    ('™', "trademark"),
+];
+
+/// Separators within URLs and email addresses, read as words
+const SEPARATOR_WORDS: &[(char, &str)] = &[
+    ('.', "dot"),
+    ('/', "slash"),
+    ('-', "dash"),
+    ('_', "underscore"),
+    ('+', "plus"),
+];
+
+/// Whether symbols are read aloud or removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymbolMode {
+    /// "visit example.com 👍" becomes "visit example dot com thumbs up"
+    #[default]
+    Verbalize,
+    /// "visit example.com 👍" becomes "visit"
+    Strip,
+}
+
+impl SymbolMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "verbalize" => Some(SymbolMode::Verbalize),
+            "strip" => Some(SymbolMode::Strip),
+            _ => None,
+        }
+    }
+}
+
+/// Verbalizes or strips emoji, math symbols, URLs, and email addresses
+pub struct Symbols {
+    mode: SymbolMode,
+    email: Regex,
+    url: Regex,
+    spaces: Regex,
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Self::new(SymbolMode::default())
+    }
+}
+
+impl Symbols {
+    pub fn new(mode: SymbolMode) -> Self {
+        // URLs with a scheme or "www.", or bare domains with a common top-level
+        // domain, without trailing sentence punctuation
+        let url = concat!(
+            r#"(?i)\b(?:https?://|www\.)[^\s<>"]*[^\s<>".,;:!?)\]']"#,
+            r#"|\b[a-z0-9](?:[a-z0-9-]*[a-z0-9])?(?:\.[a-z0-9](?:[a-z0-9-]*[a-z0-9])?)*"#,
+            r#"\.(?:com|org|net|edu|gov|io|dev|app|co|uk|info)\b(?:/[^\s<>"]*[^\s<>".,;:!?)\]'])?"#,
+        );
+
+        Self {
+            mode,
+            email: Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b")
+                .expect("email pattern is valid"),
+            url: Regex::new(url).expect("URL pattern is valid"),
+            spaces: Regex::new(r" {2,}").expect("spaces pattern is valid"),
+        }
+    }
+
+    fn replace_symbols(&self, text: &str) -> String {
+        let verbalize = self.mode == SymbolMode::Verbalize;
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if is_emoji(c) {
+                // Skin tones, variation selectors, and joined emoji belong to this one
+                while let Some(&next) = chars.peek() {
+                    if next == ZERO_WIDTH_JOINER {
+                        chars.next();
+                        chars.next();
+                    } else if is_emoji_modifier(next) || is_regional_indicator(next) {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                push_word(&mut result, lookup(EMOJI_NAMES, c).filter(|_| verbalize));
+            } else if let Some(word) = lookup(SYMBOL_WORDS, c) {
+                push_word(&mut result, Some(word).filter(|_| verbalize));
+            } else if !is_emoji_modifier(c) && c != ZERO_WIDTH_JOINER {
+                result.push(c);
+            }
+        }
+
+        result
+    }
+}
+
+impl NormalizationRule for Symbols {
+    fn name(&self) -> &str {
+        "symbols"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let verbalize = self.mode == SymbolMode::Verbalize;
+
+        // Emails first, since their domains would otherwise be read as URLs
+        let text = self.email.replace_all(text, |caps: &Captures| {
+            if verbalize {
+                format!(" {} ", verbalize_email(&caps[0]))
+            } else {
+                " ".to_string()
+            }
+        });
+        let text = self.url.replace_all(&text, |caps: &Captures| {
+            if verbalize {
+                format!(" {} ", verbalize_url(&caps[0]))
+            } else {
+                " ".to_string()
+            }
+        });
+        let text = self.replace_symbols(&text);
+
+        // Words were padded with spaces, so tidy up around them
+        let text = self.spaces.replace_all(&text, " ");
+        text.replace(" .", ".")
+            .replace(" ,", ",")
+            .replace(" !", "!")
+            .replace(" ?", "?")
+    }
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+fn lookup(table: &[(char, &'static str)], c: char) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(symbol, _)| *symbol == c)
+        .map(|(_, word)| *word)
+}
+
+/// Append a word padded with spaces, or just a space in place of a removed symbol
+fn push_word(result: &mut String, word: Option<&str>) {
+    result.push(' ');
+    if let Some(word) = word {
+        result.push_str(word);
+        result.push(' ');
+    }
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF // Pictographs, emoticons, transport, and flags
+        | 0x2600..=0x27BF // Miscellaneous symbols and dingbats
+        | 0x2B00..=0x2BFF // Arrows and stars
+    ) && !is_emoji_modifier(c)
+}
+
+/// Characters that only modify the emoji before them
+fn is_emoji_modifier(c: char) -> bool {
+    matches!(c as u32,
+        0xFE0E | 0xFE0F // Variation selectors
+        | 0x1F3FB..=0x1F3FF // Skin tones
+        | 0x20E3 // Keycap
+        | 0xE0020..=0xE007F // Tags in subdivision flags
+    )
+}
+
+/// Flags are pairs of regional indicators
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+/// Read separators as words, e.g. "jane.doe" as "jane dot doe"
+fn spell_separators(part: &str) -> String {
+    let mut words = String::with_capacity(part.len());
+    for c in part.chars() {
+        match lookup(SEPARATOR_WORDS, c) {
+            Some(word) => {
+                words.push(' ');
+                words.push_str(word);
+                words.push(' ');
+            }
+            None => words.push(c),
+        }
+    }
+    words.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// "jane.doe@example.com" as "jane dot doe at example dot com"
+fn verbalize_email(email: &str) -> String {
+    let (local, domain) = email.split_once('@').unwrap_or((email, ""));
+    format!(
+        "{} at {}",
+        spell_separators(local),
+        spell_separators(domain)
+    )
+}
+
+/// "https://www.example.com/learn?x=1" as "example dot com slash learn"
+///
+/// The scheme, "www.", query, and fragment are not read.
+fn verbalize_url(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = if rest.to_lowercase().starts_with("www.") {
+        &rest[4..]
+    } else {
+        rest
+    };
+    let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+
+    spell_separators(rest.trim_end_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbalize_symbols() {
+        let symbols = Symbols::default();
+        let cases = [
+            ("Visit example.com today.", "Visit example dot com today."),
+            (
+                "See https://www.ipa-navigator.dev/learn/vowels?tab=2#top.",
+                "See ipa dash navigator dot dev slash learn slash vowels.",
+            ),
+            (
+                "Email jane.doe@example.co.uk for help",
+                "Email jane dot doe at example dot co dot uk for help",
+            ),
+            (
+                "2+2=4, nearly 50% of the time",
+                "2 plus 2 equals 4, nearly 50 percent of the time",
+            ),
+            ("Great job 👍🏽!", "Great job thumbs up!"),
+            ("Family 👨\u{200D}👩\u{200D}👧 time 🇬🇧", "Family time"),
+            (
+                "Version 3.5 is out, e.g. now",
+                "Version 3.5 is out, e.g. now",
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(symbols.apply(text).trim(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_strip_symbols() {
+        let symbols = Symbols::new(SymbolMode::Strip);
+        assert_eq!(
+            symbols
+                .apply("Visit example.com 👍 or mail me@example.com ❤️ now")
+                .trim(),
+            "Visit or mail now"
+        );
+        assert_eq!(SymbolMode::from_name("Strip"), Some(SymbolMode::Strip));
+        assert_eq!(SymbolMode::from_name("shout"), None);
+    }
+}
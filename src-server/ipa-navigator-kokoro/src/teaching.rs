@@ -0,0 +1,258 @@
+//! Teaching mode: pauses between words or syllables for shadowing
+//!
+//! Slowing the model down far enough to shadow distorts the pitch, so
+//! teaching mode keeps the speaking rate and instead inserts punctuation
+//! tokens between units of the phoneme string, which the model reads as
+//! pauses. Syllables are found with a vowel-nucleus heuristic over the IPA:
+//! adjacent vowels form one nucleus, and consonants between nuclei start the
+//! next syllable as far as English onsets allow, so "ɛkstɹə" splits as
+//! "ɛk.stɹə". Vowels in hiatus, as in "kˈeɪɑːs", stay in one syllable.
+
+/// Vowels in the phonemizer's IPA output
+const VOWELS: &str = "aeiouyæɐɑɒɔəɘɚɛɜɝɞɤɨɪɯɵøœɶʉʊʌʏᵻ";
+
+/// Marks modifying the preceding phoneme, such as length and aspiration
+const MODIFIERS: &str = "ːˑʰʱʲʷˠˤ˞ʼʴ\u{329}";
+
+/// Stress marks, placed before the stressed vowel
+const STRESS: &str = "ˈˌ";
+
+/// Punctuation already read as a pause, so no marker is added after it
+const PAUSING_PUNCTUATION: &str = ";:,.!?—…";
+
+/// Consonants that may follow another at the start of a syllable
+const SECOND_ONSET: &str = "ɹlwj";
+
+/// Consonants that may precede one of [`SECOND_ONSET`] at the start of a syllable
+const FIRST_ONSET: &str = "pbtdkɡfvθʃ";
+
+/// Consonants that may follow "s" at the start of a syllable
+const AFTER_S: &str = "ptkmnlwfj";
+
+/// What the pauses separate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseUnit {
+    Word,
+    Syllable,
+}
+
+impl PauseUnit {
+    /// Parse "word" or "syllable", or their plurals
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "word" | "words" => Some(PauseUnit::Word),
+            "syllable" | "syllables" => Some(PauseUnit::Syllable),
+            _ => None,
+        }
+    }
+}
+
+/// How long each pause is, approximately, since the model decides how to read the punctuation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PauseLength {
+    /// A comma's pause
+    Short,
+    #[default]
+    Medium,
+    Long,
+}
+
+impl PauseLength {
+    /// Parse "short", "medium" or "long"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "short" => Some(PauseLength::Short),
+            "medium" => Some(PauseLength::Medium),
+            "long" => Some(PauseLength::Long),
+            _ => None,
+        }
+    }
+
+    /// Punctuation tokens read as the pause
+    ///
+    /// Full stops would also pause, but make every word sound sentence-final.
+    fn marker(&self) -> &'static str {
+        match self {
+            PauseLength::Short => ",",
+            PauseLength::Medium => "…",
+            PauseLength::Long => "……",
+        }
+    }
+}
+
+/// Pauses inserted between words or syllables, keeping the speaking rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TeachingMode {
+    pub unit: PauseUnit,
+    pub pause: PauseLength,
+}
+
+impl TeachingMode {
+    pub fn new(unit: PauseUnit, pause: PauseLength) -> Self {
+        Self { unit, pause }
+    }
+
+    /// Insert pause tokens between the units of a phoneme string
+    ///
+    /// Units already followed by pausing punctuation are left as they are.
+    pub fn apply(&self, phonemes: &str) -> String {
+        let units: Vec<&str> = phonemes
+            .split_whitespace()
+            .flat_map(|word| match self.unit {
+                PauseUnit::Word => vec![word],
+                PauseUnit::Syllable => syllables(word),
+            })
+            .collect();
+
+        let mut paused = String::with_capacity(phonemes.len() + units.len() * 4);
+        for (index, unit) in units.iter().enumerate() {
+            if index > 0 {
+                paused.push(' ');
+            }
+            paused.push_str(unit);
+            let pauses_already = unit
+                .chars()
+                .last()
+                .is_some_and(|c| PAUSING_PUNCTUATION.contains(c));
+            if index + 1 < units.len() && !pauses_already {
+                paused.push_str(self.pause.marker());
+            }
+        }
+        paused
+    }
+}
+
+/// Split a phonemized word into syllables, one per vowel nucleus
+///
+/// Punctuation stays with the syllable it is next to, and a word without a
+/// vowel is one syllable.
+pub fn syllables(word: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    let is_vowel = |c: char| VOWELS.contains(c);
+
+    // Byte offsets where each syllable after the first begins
+    let mut boundaries = Vec::new();
+    let mut index = 0;
+    let mut seen_nucleus = false;
+    while index < chars.len() {
+        if !is_vowel(chars[index].1) {
+            index += 1;
+            continue;
+        }
+
+        if seen_nucleus {
+            boundaries.push(chars[onset_start(&chars, index)].0);
+        }
+        seen_nucleus = true;
+
+        // The nucleus: adjacent vowels and their modifiers
+        while index < chars.len()
+            && (is_vowel(chars[index].1) || MODIFIERS.contains(chars[index].1))
+        {
+            index += 1;
+        }
+    }
+
+    let mut syllables = Vec::with_capacity(boundaries.len() + 1);
+    let mut start = 0;
+    for boundary in boundaries {
+        syllables.push(&word[start..boundary]);
+        start = boundary;
+    }
+    syllables.push(&word[start..]);
+    syllables
+}
+
+/// Index where the syllable whose nucleus starts at `nucleus_start` begins
+///
+/// Takes the longest run of consonants before the nucleus that English
+/// allows at the start of a syllable, leaving the rest to close the
+/// syllable before.
+fn onset_start(chars: &[(usize, char)], nucleus_start: usize) -> usize {
+    // Stress marks sit right before the nucleus, and belong to it
+    let mut start = nucleus_start;
+    while start > 0 && STRESS.contains(chars[start - 1].1) {
+        start -= 1;
+    }
+
+    // Consonants back to the previous nucleus, as indices of their first character
+    let mut consonants = Vec::new();
+    let mut index = start;
+    while index > 0 {
+        let c = chars[index - 1].1;
+        if VOWELS.contains(c) || PAUSING_PUNCTUATION.contains(c) {
+            break;
+        }
+        index -= 1;
+        if !MODIFIERS.contains(c) && !STRESS.contains(c) {
+            consonants.push(index);
+        }
+    }
+    consonants.reverse();
+
+    let n = consonants.len();
+    let phoneme = |i: usize| chars[consonants[i]].1;
+    // "ŋ" never starts a syllable in English
+    if n == 0 || phoneme(n - 1) == 'ŋ' {
+        return start;
+    }
+
+    let mut onset = 1;
+    if n >= 2 && legal_pair(phoneme(n - 2), phoneme(n - 1)) {
+        onset = 2;
+        if n >= 3 && phoneme(n - 3) == 's' && "ptk".contains(phoneme(n - 2)) {
+            onset = 3;
+        }
+    }
+    consonants[n - onset]
+}
+
+/// Whether two consonants may start a syllable together
+fn legal_pair(first: char, second: char) -> bool {
+    (FIRST_ONSET.contains(first) && SECOND_ONSET.contains(second))
+        || (first == 's' && AFTER_S.contains(second))
+        || matches!((first, second), ('t', 'ʃ') | ('d', 'ʒ'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syllables() {
+        assert_eq!(syllables("həlˈoʊ"), vec!["hə", "lˈoʊ"]);
+        assert_eq!(syllables("ˈɛkstɹə"), vec!["ˈɛk", "stɹə"]);
+        assert_eq!(syllables("wˈɜːld!"), vec!["wˈɜːld!"]);
+        assert_eq!(syllables("sˈɪŋɪŋ"), vec!["sˈɪŋ", "ɪŋ"]);
+        assert_eq!(
+            syllables("pɹənˌʌnsiˈeɪʃən"),
+            vec!["pɹə", "nˌʌn", "si", "ˈeɪ", "ʃən"]
+        );
+        assert_eq!(syllables("ʃ"), vec!["ʃ"]);
+        assert_eq!(syllables(""), vec![""]);
+    }
+
+    #[test]
+    fn test_apply_pauses_between_words() {
+        let mode = TeachingMode::new(PauseUnit::Word, PauseLength::Medium);
+
+        assert_eq!(mode.apply("ðɪs ɪz ə tˈɛst."), "ðɪs… ɪz… ə… tˈɛst.");
+        // Punctuation already pauses
+        assert_eq!(mode.apply("həlˈoʊ, wˈɜːld!"), "həlˈoʊ, wˈɜːld!");
+    }
+
+    #[test]
+    fn test_apply_pauses_between_syllables() {
+        let mode = TeachingMode::new(PauseUnit::Syllable, PauseLength::Short);
+
+        assert_eq!(mode.apply("həlˈoʊ wˈɜːld"), "hə, lˈoʊ, wˈɜːld");
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(PauseUnit::from_name("Syllables"), Some(PauseUnit::Syllable));
+        assert_eq!(PauseUnit::from_name("phoneme"), None);
+        assert_eq!(PauseLength::from_name(" long "), Some(PauseLength::Long));
+        assert_eq!(PauseLength::from_name("forever"), None);
+    }
+}
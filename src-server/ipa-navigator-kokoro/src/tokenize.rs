@@ -1,6 +1,11 @@
+use crate::error::TtsError;
 use crate::vocab::{REVERSE_VOCABULARY, VOCABULARY};
 
+/// Most tokens the model reads in one pass, not counting the padding added on either side
+pub const MAX_TOKENS: usize = 510;
+
 /// Converts a string of phonemes into a vector of token IDs.
+#[tracing::instrument(name = "tts.tokenize", skip_all)]
 pub fn tokenize(phonemes: &str) -> Vec<i64> {
     phonemes
         .chars()
@@ -17,9 +22,51 @@ pub fn tokens_to_phonemes(tokens: &[i64]) -> String {
         .collect()
 }
 
+/// Check tokens given directly by a client can be passed to the model
+///
+/// Every ID must be in the vocabulary, as the model's behaviour for any other
+/// is undefined. Padding at either end is allowed, but not counted against
+/// [`MAX_TOKENS`].
+pub fn validate_tokens(tokens: &[i64]) -> Result<(), TtsError> {
+    let unknown: Vec<String> = tokens
+        .iter()
+        .filter(|&&t| !usize::try_from(t).is_ok_and(|t| REVERSE_VOCABULARY.contains_key(&t)))
+        .take(10)
+        .map(|t| t.to_string())
+        .collect();
+    if !unknown.is_empty() {
+        return Err(TtsError::TokenizationError(format!(
+            "token IDs not in the vocabulary: {}",
+            unknown.join(", ")
+        )));
+    }
+
+    let content = strip_padding(tokens);
+    if content.is_empty() {
+        return Err(TtsError::TokenizationError(
+            "no tokens to synthesize".to_string(),
+        ));
+    }
+    if content.len() > MAX_TOKENS {
+        return Err(TtsError::TokenizationError(format!(
+            "{} tokens is more than the {} the model reads at once",
+            content.len(),
+            MAX_TOKENS
+        )));
+    }
+    Ok(())
+}
+
+/// Tokens without the padding token at either end, if present
+pub fn strip_padding(tokens: &[i64]) -> &[i64] {
+    let tokens = tokens.strip_prefix(&[0]).unwrap_or(tokens);
+    tokens.strip_suffix(&[0]).unwrap_or(tokens)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_tokenize() {
@@ -61,4 +108,63 @@ mod test {
         let empty_tokens: Vec<i64> = vec![];
         assert_eq!(tokens_to_phonemes(&empty_tokens), "");
     }
+
+    #[test]
+    fn test_validate_tokens() {
+        assert!(validate_tokens(&[50, 47, 102]).is_ok());
+        // Padding is accepted and not counted
+        assert!(validate_tokens(&[0, 50, 47, 102, 0]).is_ok());
+        assert!(validate_tokens(&vec![50; MAX_TOKENS + 1]).is_err());
+        let mut longest = vec![50; MAX_TOKENS];
+        assert!(validate_tokens(&longest).is_ok());
+        longest.insert(0, 0);
+        longest.push(0);
+        assert!(validate_tokens(&longest).is_ok());
+
+        assert!(validate_tokens(&[]).is_err());
+        assert!(validate_tokens(&[0, 0]).is_err());
+
+        let error = validate_tokens(&[50, -1, 9999]).unwrap_err().to_string();
+        assert!(error.contains("-1, 9999"), "{}", error);
+    }
+
+    /// Any character in the vocabulary
+    fn vocabulary_char() -> impl Strategy<Value = char> {
+        let mut chars: Vec<char> = VOCABULARY.keys().copied().collect();
+        chars.sort_unstable();
+        prop::sample::select(chars)
+    }
+
+    proptest! {
+        #[test]
+        fn prop_vocabulary_text_round_trips(
+            chars in prop::collection::vec(vocabulary_char(), 0..64)
+        ) {
+            let phonemes: String = chars.into_iter().collect();
+            prop_assert_eq!(tokens_to_phonemes(&tokenize(&phonemes)), phonemes);
+        }
+
+        #[test]
+        fn prop_tokenize_keeps_only_vocabulary(text in ".*") {
+            let expected: String = text.chars().filter(|c| VOCABULARY.contains_key(c)).collect();
+            prop_assert_eq!(tokens_to_phonemes(&tokenize(&text)), expected);
+        }
+
+        #[test]
+        fn prop_tokens_round_trip(
+            chars in prop::collection::vec(vocabulary_char(), 0..64)
+        ) {
+            let tokens: Vec<i64> = chars.iter().map(|c| VOCABULARY[c] as i64).collect();
+            prop_assert_eq!(tokenize(&tokens_to_phonemes(&tokens)), tokens);
+        }
+
+        #[test]
+        fn prop_unknown_tokens_are_dropped(tokens in prop::collection::vec(any::<i64>(), 0..64)) {
+            let known = tokens
+                .iter()
+                .filter(|&&t| REVERSE_VOCABULARY.contains_key(&(t as usize)))
+                .count();
+            prop_assert_eq!(tokens_to_phonemes(&tokens).chars().count(), known);
+        }
+    }
 }
@@ -1,58 +1,169 @@
 use crate::error::TtsError;
 use crate::model::KokoroModel;
-use crate::normalize::normalize_text;
-use crate::phonemizer::text_to_phonemes_string;
-use crate::tokenize::tokenize;
+use crate::normalize::{NORMALIZER, NormalizeOptions, Normalizer};
+use crate::phonemizer::PHONEME_CACHE;
+use crate::segment::split_sentences;
+use crate::similarity::VoiceSimilarity;
+use crate::stitch::{CHUNK_OVERLAP, synthesize_chunked};
+use crate::tokenize::{strip_padding, tokenize, validate_tokens};
 use crate::voices::VoiceType;
-use hound::{WavSpec, WavWriter};
+use crate::wav::{WavFormat, encode_wav};
 use lru::LruCache;
 use ndarray::{ArrayBase, IxDyn, OwnedRepr};
-use std::io::Cursor;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::num::NonZeroUsize;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use std::time::{Duration, Instant};
 
 static CACHE_TTL: Duration = Duration::from_secs(60 * 60); // 1 hour
 
+/// Slowest speed passed to the model after calibration
+pub const MIN_SPEED: f32 = 0.5;
+
+/// Fastest speed passed to the model after calibration
+pub const MAX_SPEED: f32 = 2.0;
+
+/// Representation of cached audio, part of every cache key so a change invalidates old entries
+const CACHE_FORMAT: &str = "f32le@24000";
+
+/// Number of synthesized clips kept in the cache
+pub const CACHE_CAPACITY: usize = 50;
+
+/// Silence between sentences synthesized separately, in seconds
+pub const SENTENCE_PAUSE_SECS: f32 = 0.3;
+
+/// Sample rate of the model's output
+const OUTPUT_SAMPLE_RATE: f32 = 24000.0;
+
+/// Weight of the newest measurement in the running inference speed estimate
+const THROUGHPUT_SMOOTHING: f64 = 0.3;
+
 // Cache entry with timestamp for potential time-based eviction
 struct CacheEntry {
     audio: ArrayBase<OwnedRepr<f32>, IxDyn>,
     timestamp: Instant,
 }
 
+/// Audio synthesized before a deadline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Synthesis {
+    pub samples: Vec<f32>,
+    /// Whether later sentences were left out to meet the deadline
+    pub truncated: bool,
+    pub sentences_synthesized: usize,
+    pub sentences_total: usize,
+}
+
 pub struct KokoroTTS {
-    model: Mutex<KokoroModel>,
+    model: KokoroModel,
     cache: Mutex<LruCache<String, CacheEntry>>,
     cache_ttl: Duration,
+    normalizer: Arc<Normalizer>,
+    /// Running estimate of inference time per token, in seconds, once measured
+    seconds_per_token: Mutex<Option<f64>>,
 }
 
 impl KokoroTTS {
     pub fn new() -> Result<Self, TtsError> {
-        let mut model = KokoroModel::new()?;
+        let model = KokoroModel::new()?;
         model.load_all_voice_embeddings()?;
 
         // Initialize LRU cache with a capacity of 50 entries
-        let cache_size = NonZeroUsize::new(50).unwrap();
+        let cache_size = NonZeroUsize::new(CACHE_CAPACITY).unwrap_or(NonZeroUsize::MIN);
 
         Ok(Self {
-            model: Mutex::new(model),
+            model,
             cache: Mutex::new(LruCache::new(cache_size)),
             cache_ttl: CACHE_TTL,
+            normalizer: NORMALIZER.clone(),
+            seconds_per_token: Mutex::new(None),
         })
     }
 
+    /// Normalize text with `normalizer` instead of the one configured from the environment
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = Arc::new(normalizer);
+        self
+    }
+
     /// Lists all available voices with their display names
     pub fn available_voices(&self) -> Vec<VoiceType> {
-        let model = self.model.lock().unwrap_or_else(|e| {
-            panic!("Failed to acquire model lock: {:?}", e);
-        });
-        model.available_voices()
+        self.model.available_voices()
+    }
+
+    /// Lists the voices that failed to load, with the reason
+    pub fn failed_voices(&self) -> Vec<(VoiceType, String)> {
+        self.model.failed_voices()
+    }
+
+    /// Similarity between the loaded voices, recomputed when they are reloaded
+    pub fn voice_similarity(&self) -> Arc<VoiceSimilarity> {
+        self.model.voice_similarity()
+    }
+
+    /// Re-reads the voice embeddings from disk and drops audio synthesized with the old ones
+    pub fn reload_voices(&self) -> Result<usize, TtsError> {
+        let count = self.model.reload_voice_embeddings()?;
+        lock(&self.cache).clear();
+        Ok(count)
+    }
+
+    /// Keep at most `capacity` synthesized clips, dropping the least recently used
+    pub fn resize_cache(&self, capacity: NonZeroUsize) {
+        lock(&self.cache).resize(capacity);
+    }
+
+    /// Generate a cache key from the phonemes to speak, voice, speed, and the voice's calibration
+    ///
+    /// Keying by phonemes rather than text means words spelt alike but said
+    /// differently never share audio, whatever normalization produced them.
+    pub fn generate_cache_key(phonemes: &str, voice_type: &VoiceType, speed: f32) -> String {
+        let calibration = voice_type.calibration();
+
+        let mut hasher = DefaultHasher::new();
+        phonemes.hash(&mut hasher);
+        voice_type.file_name().hash(&mut hasher);
+        speed.to_bits().hash(&mut hasher);
+        calibration.speed.to_bits().hash(&mut hasher);
+        calibration.gain_db.to_bits().hash(&mut hasher);
+        CACHE_FORMAT.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
     }
 
-    /// Generate a cache key based on text, voice, and speed
-    fn generate_cache_key(text: &str, voice_type: &VoiceType, speed: f32) -> String {
-        format!("{}:{}:{}", text, voice_type.file_name(), speed)
+    /// Normalize text and convert it to the phonemes the voice will speak
+    ///
+    /// In teaching mode, the phonemes include the pauses between words or syllables.
+    pub fn phonemize(
+        &self,
+        text: &str,
+        voice_type: &VoiceType,
+        options: &NormalizeOptions,
+    ) -> Result<String, TtsError> {
+        let normalized_text = self.normalizer.normalize_with(text, options);
+        let phonemes = PHONEME_CACHE
+            .phonemize(&normalized_text, voice_type.language())
+            .map_err(|e| TtsError::PhonemeError(e.to_string()))?;
+        Ok(match options.teaching() {
+            Some(mode) => mode.apply(&phonemes),
+            None => phonemes,
+        })
+    }
+
+    /// Whether audio for the text is cached and unexpired, without marking it recently used
+    pub fn is_cached(
+        &self,
+        text: &str,
+        voice_type: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+    ) -> Result<bool, TtsError> {
+        let phonemes = self.phonemize(text, voice_type, options)?;
+        let cache_key = Self::generate_cache_key(&phonemes, voice_type, speed);
+
+        Ok(lock(&self.cache)
+            .peek(&cache_key)
+            .is_some_and(|entry| entry.timestamp.elapsed() < self.cache_ttl))
     }
 
     /// Process text into audio using the specified voice and speed
@@ -62,102 +173,279 @@ impl KokoroTTS {
         voice_type: &VoiceType,
         speed: f32,
     ) -> Result<ArrayBase<OwnedRepr<f32>, IxDyn>, TtsError> {
-        // Normalize the input text
-        let normalized_text = normalize_text(text);
+        self.process_tts_with(text, voice_type, speed, &NormalizeOptions::default())
+    }
 
-        // Generate cache key
-        let cache_key = Self::generate_cache_key(&normalized_text, voice_type, speed);
+    /// Process text into audio, normalizing it with `options`
+    #[tracing::instrument(
+        name = "tts.synthesize",
+        skip_all,
+        fields(voice = voice_type.file_name(), speed, cached = tracing::field::Empty)
+    )]
+    pub fn process_tts_with(
+        &self,
+        text: &str,
+        voice_type: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+    ) -> Result<ArrayBase<OwnedRepr<f32>, IxDyn>, TtsError> {
+        let phonemes = self.phonemize(text, voice_type, options)?;
+        let cache_key = Self::generate_cache_key(&phonemes, voice_type, speed);
 
-        // Try to get from cache first
-        {
-            let mut cache = self.cache.lock().map_err(|_| {
-                TtsError::InferenceError("Failed to acquire cache lock".to_string())
-            })?;
-
-            if let Some(entry) = cache.get(&cache_key) {
-                // Check if the entry is still valid (not expired)
-                if entry.timestamp.elapsed() < self.cache_ttl {
-                    return Ok(entry.audio.clone());
+        if let Some(audio) = self.cached(&cache_key)? {
+            tracing::Span::current().record("cached", true);
+            return Ok(audio);
+        }
+        tracing::Span::current().record("cached", false);
+
+        let audio_data = self.synthesize_phonemes(&phonemes, voice_type, speed)?;
+        self.store(cache_key, &audio_data);
+        Ok(audio_data)
+    }
+
+    /// Process text a sentence at a time, stopping before `deadline` would be missed
+    ///
+    /// A sentence is only started if the measured inference speed suggests it
+    /// will finish in time, and cached sentences cost nothing. Sentences are
+    /// joined with a short pause. Fails with [`TtsError::DeadlineExceeded`] if
+    /// no sentence could be synthesized in time.
+    #[tracing::instrument(
+        name = "tts.synthesize_until",
+        skip_all,
+        fields(voice = voice_type.file_name(), speed, truncated = tracing::field::Empty)
+    )]
+    pub fn process_tts_until(
+        &self,
+        text: &str,
+        voice_type: &VoiceType,
+        speed: f32,
+        options: &NormalizeOptions,
+        deadline: Instant,
+    ) -> Result<Synthesis, TtsError> {
+        let mut sentences = split_sentences(text);
+        if sentences.is_empty() {
+            sentences.push(text.to_string());
+        }
+        let pause = vec![0.0; (OUTPUT_SAMPLE_RATE * SENTENCE_PAUSE_SECS) as usize];
+
+        let mut samples = Vec::new();
+        let mut synthesized = 0;
+        for sentence in &sentences {
+            let phonemes = self.phonemize(sentence, voice_type, options)?;
+            let cache_key = Self::generate_cache_key(&phonemes, voice_type, speed);
+
+            let audio = match self.cached(&cache_key)? {
+                Some(audio) => audio,
+                None => {
+                    if !self.can_finish(&phonemes, deadline) {
+                        break;
+                    }
+                    let started = Instant::now();
+                    let audio = self.synthesize_phonemes(&phonemes, voice_type, speed)?;
+                    self.record_throughput(&phonemes, started.elapsed());
+                    self.store(cache_key, &audio);
+                    audio
                 }
-                // If expired, remove it and continue to regenerate
-                cache.pop(&cache_key);
+            };
+
+            if synthesized > 0 {
+                samples.extend_from_slice(&pause);
             }
+            samples.extend(audio.iter().copied());
+            synthesized += 1;
         }
 
-        let language = voice_type.language();
-        let phonemes = text_to_phonemes_string(&normalized_text, language)
-            .map_err(|e| TtsError::PhonemeError(e.to_string()))?;
+        if synthesized == 0 {
+            return Err(TtsError::DeadlineExceeded(format!(
+                "none of {} sentences could be synthesized in time",
+                sentences.len()
+            )));
+        }
+
+        let truncated = synthesized < sentences.len();
+        tracing::Span::current().record("truncated", truncated);
+        if truncated {
+            tracing::warn!(
+                "Synthesized {} of {} sentences before the deadline",
+                synthesized,
+                sentences.len()
+            );
+        }
+
+        Ok(Synthesis {
+            samples,
+            truncated,
+            sentences_synthesized: synthesized,
+            sentences_total: sentences.len(),
+        })
+    }
+
+    /// Unexpired cached audio for a key, marking it recently used
+    fn cached(
+        &self,
+        cache_key: &str,
+    ) -> Result<Option<ArrayBase<OwnedRepr<f32>, IxDyn>>, TtsError> {
+        let mut cache = lock(&self.cache);
+        if let Some(entry) = cache.get(cache_key) {
+            if entry.timestamp.elapsed() < self.cache_ttl {
+                return Ok(Some(entry.audio.clone()));
+            }
+            // Expired, so drop it and let the caller regenerate it
+            cache.pop(cache_key);
+        }
+        Ok(None)
+    }
 
-        let tokens = tokenize(&phonemes);
-        let mut padded_tokens = vec![0i64; 1];
-        padded_tokens.extend(tokens);
-        padded_tokens.extend(vec![0i64; 1]);
-        let tokens = padded_tokens;
+    fn store(&self, cache_key: String, audio: &ArrayBase<OwnedRepr<f32>, IxDyn>) {
+        lock(&self.cache).put(
+            cache_key,
+            CacheEntry {
+                audio: audio.clone(),
+                timestamp: Instant::now(),
+            },
+        );
+    }
 
-        // Lock the model to get the voice embedding and run inference
-        let mut model = self
-            .model
-            .lock()
-            .map_err(|_| TtsError::InferenceError("Failed to acquire model lock".to_string()))?;
+    /// Whether phonemes should finish synthesizing before the deadline
+    ///
+    /// Before any inference has been timed, only a passed deadline stops synthesis.
+    fn can_finish(&self, phonemes: &str, deadline: Instant) -> bool {
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
 
-        let voice_embedding = model
-            .get_voice_embedding(voice_type.clone())
-            .map_err(|_e| {
-                TtsError::VoiceDataError(format!("Voice embedding not found for {:?}", voice_type))
-            })?;
+        let seconds_per_token = *lock(&self.seconds_per_token);
+        seconds_per_token.is_none_or(|seconds_per_token| {
+            let estimate = seconds_per_token * self.token_count(phonemes) as f64;
+            Duration::try_from_secs_f64(estimate).is_ok_and(|estimate| now + estimate <= deadline)
+        })
+    }
 
-        // Generate audio
-        let audio_data = model.infer(tokens, voice_embedding, speed, None)?;
+    /// Tokens the model reads for phonemes, including the padding
+    fn token_count(&self, phonemes: &str) -> usize {
+        tokenize(phonemes).len() + self.model.padding().overhead()
+    }
 
-        // Store in cache
-        {
-            let mut cache = self.cache.lock().map_err(|_| {
-                TtsError::InferenceError("Failed to acquire cache lock".to_string())
-            })?;
-
-            cache.put(
-                cache_key,
-                CacheEntry {
-                    audio: audio_data.clone(),
-                    timestamp: Instant::now(),
-                },
-            );
+    fn record_throughput(&self, phonemes: &str, elapsed: Duration) {
+        let measured = elapsed.as_secs_f64() / self.token_count(phonemes) as f64;
+        let mut estimate = lock(&self.seconds_per_token);
+        *estimate = Some(match *estimate {
+            Some(previous) => previous + THROUGHPUT_SMOOTHING * (measured - previous),
+            None => measured,
+        });
+    }
+
+    /// Synthesize text without reading or filling the cache, e.g. to measure latency
+    pub fn synthesize(
+        &self,
+        text: &str,
+        voice_type: &VoiceType,
+        speed: f32,
+    ) -> Result<ArrayBase<OwnedRepr<f32>, IxDyn>, TtsError> {
+        let phonemes = self.phonemize(text, voice_type, &NormalizeOptions::default())?;
+        self.synthesize_phonemes(&phonemes, voice_type, speed)
+    }
+
+    fn synthesize_phonemes(
+        &self,
+        phonemes: &str,
+        voice_type: &VoiceType,
+        speed: f32,
+    ) -> Result<ArrayBase<OwnedRepr<f32>, IxDyn>, TtsError> {
+        let tokens = tokenize(phonemes);
+        let max_tokens = self.model.padding().max_content_tokens();
+        if tokens.len() <= max_tokens {
+            return self.infer_tokens(&tokens, voice_type, speed);
         }
 
-        Ok(audio_data)
+        // Too long for one pass, so read in chunks joined between words
+        let samples = synthesize_chunked(phonemes, max_tokens, *CHUNK_OVERLAP, |chunk| {
+            let audio = self.infer_tokens(&tokenize(chunk), voice_type, speed)?;
+            Ok(audio.iter().copied().collect())
+        })?;
+        ArrayBase::from_shape_vec(IxDyn(&[samples.len()]), samples)
+            .map_err(|e| TtsError::InferenceError(format!("Failed to join chunks: {}", e)))
     }
 
-    pub fn audio_to_wav(&self, audio_data: &[f32]) -> Vec<u8> {
-        let spec = WavSpec {
-            channels: 1,
-            sample_rate: 24000,
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
-        };
+    /// Synthesize token IDs given directly, without reading or filling the cache
+    ///
+    /// For clients that phonemize and tokenize themselves. Tokens are checked
+    /// against the vocabulary first, and may include a padding token at either
+    /// end, which is replaced by the model's own padding.
+    pub fn synthesize_tokens(
+        &self,
+        tokens: &[i64],
+        voice_type: &VoiceType,
+        speed: f32,
+    ) -> Result<ArrayBase<OwnedRepr<f32>, IxDyn>, TtsError> {
+        validate_tokens(tokens)?;
+        let tokens = strip_padding(tokens);
+        let max_tokens = self.model.padding().max_content_tokens();
+        if tokens.len() > max_tokens {
+            return Err(TtsError::TokenizationError(format!(
+                "{} tokens is more than the {} the model reads at once with its padding",
+                tokens.len(),
+                max_tokens
+            )));
+        }
+        self.infer_tokens(tokens, voice_type, speed)
+    }
 
-        // Create a buffer for the WAV data
-        let mut buffer = Vec::new();
+    /// Pad `tokens` as the model expects and run them through it with the voice's calibration
+    fn infer_tokens(
+        &self,
+        tokens: &[i64],
+        voice_type: &VoiceType,
+        speed: f32,
+    ) -> Result<ArrayBase<OwnedRepr<f32>, IxDyn>, TtsError> {
+        let tokens = self.model.padding().apply(tokens);
 
-        // Create a cursor that will write to the buffer
-        let mut cursor = Cursor::new(&mut buffer);
+        // Embeddings are read without waiting for inference, which takes the session's own lock
+        let voice_embedding = self.model.get_voice_embedding(*voice_type).map_err(|_e| {
+            TtsError::VoiceDataError(format!("Voice embedding not found for {:?}", voice_type))
+        })?;
 
-        // Create a WAV writer that writes to the cursor
-        let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+        // Voices differ in natural tempo and loudness, so apply the voice's calibration
+        let calibration = voice_type.calibration();
+        let speed = (speed * calibration.speed).clamp(MIN_SPEED, MAX_SPEED);
 
-        // Write the audio samples
-        for &sample in audio_data {
-            let amplitude = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
-            writer.write_sample(amplitude).unwrap();
+        // Generate audio
+        let mut audio = self.model.infer(tokens, &voice_embedding, speed, None)?;
+        if calibration.gain_db != 0.0 {
+            let gain = calibration.gain();
+            audio.mapv_inplace(|sample| (sample * gain).clamp(-1.0, 1.0));
         }
+        Ok(audio)
+    }
 
-        // Finalize the WAV writer
-        writer.finalize().unwrap();
+    /// Encode samples produced by the model as a mono 16-bit WAV file
+    pub fn audio_to_wav(&self, audio_data: &[f32]) -> Result<Vec<u8>, TtsError> {
+        self.audio_to_wav_with(audio_data, &WavFormat::default())
+    }
 
-        // Return the buffer containing the WAV data
-        buffer
+    /// Encode samples produced by the model as a WAV file in `format`
+    #[tracing::instrument(name = "tts.encode", skip_all, fields(samples = audio_data.len()))]
+    pub fn audio_to_wav_with(
+        &self,
+        audio_data: &[f32],
+        format: &WavFormat,
+    ) -> Result<Vec<u8>, TtsError> {
+        encode_wav(audio_data, format)
     }
 }
 
+/// Lock `mutex`, recovering its contents if a thread panicked while holding it
+///
+/// The cache and speed estimate are each left usable between statements,
+/// so one failed synthesis shouldn't stop all later ones.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| {
+        tracing::warn!("Recovering from a lock poisoned by a panicked synthesis");
+        e.into_inner()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,10 +459,12 @@ mod tests {
         let voice1 = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
         let voice2 = VoiceType::BritishFemale(BritishFemaleVoice::Emma);
 
-        let key1 = KokoroTTS::generate_cache_key("Hello world", &voice1, 1.0);
-        let key2 = KokoroTTS::generate_cache_key("Hello world", &voice1, 1.0);
-        let key3 = KokoroTTS::generate_cache_key("Hello world", &voice2, 1.0);
-        let key4 = KokoroTTS::generate_cache_key("Hello world", &voice1, 1.5);
+        let key1 = KokoroTTS::generate_cache_key("həlˈoʊ wˈɜːld", &voice1, 1.0);
+        let key2 = KokoroTTS::generate_cache_key("həlˈoʊ wˈɜːld", &voice1, 1.0);
+        let key3 = KokoroTTS::generate_cache_key("həlˈoʊ wˈɜːld", &voice2, 1.0);
+        let key4 = KokoroTTS::generate_cache_key("həlˈoʊ wˈɜːld", &voice1, 1.5);
+        let key5 = KokoroTTS::generate_cache_key("ɹˈiːd", &voice1, 1.0);
+        let key6 = KokoroTTS::generate_cache_key("ɹˈɛd", &voice1, 1.0);
 
         // Same inputs should produce the same key
         assert_eq!(key1, key2, "Same inputs should generate the same cache key");
@@ -190,6 +480,26 @@ mod tests {
             key1, key4,
             "Different speeds should produce different cache keys"
         );
+
+        // Homographs read differently should produce different keys
+        assert_ne!(
+            key5, key6,
+            "Different phonemes should produce different cache keys"
+        );
+    }
+
+    #[test]
+    fn test_lock_recovers_from_poisoning() {
+        let estimate = Arc::new(Mutex::new(Some(0.5)));
+        let poisoner = Arc::clone(&estimate);
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("synthesis failed while holding the lock");
+        })
+        .join();
+
+        assert!(estimate.is_poisoned());
+        assert_eq!(*lock(&estimate), Some(0.5));
     }
 
     #[test]
@@ -207,7 +517,7 @@ mod tests {
         // Create a simple audio array
         let audio_data = vec![0.0f32, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -0.5];
 
-        let wav_data = tts.audio_to_wav(&audio_data);
+        let wav_data = tts.audio_to_wav(&audio_data)?;
 
         // Basic validation of WAV format
         assert!(
@@ -234,7 +544,11 @@ mod tests {
 
         let voice = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
         let text = "This is a cache test";
-        let cache_key = KokoroTTS::generate_cache_key(text, &voice, 1.0);
+        let cache_key = KokoroTTS::generate_cache_key(
+            &tts.phonemize(text, &voice, &NormalizeOptions::default())?,
+            &voice,
+            1.0,
+        );
 
         // Initially the cache should be empty
         {
@@ -322,15 +636,19 @@ mod tests {
             assert_eq!(cache.len(), 2, "Cache should have reached capacity");
 
             // The first item should have been evicted
-            let first_key = KokoroTTS::generate_cache_key("Cache eviction test 1", &voice, 1.0);
+            let key = |text: &str| {
+                let phonemes = tts.phonemize(text, &voice, &NormalizeOptions::default());
+                KokoroTTS::generate_cache_key(&phonemes.unwrap(), &voice, 1.0)
+            };
+            let first_key = key("Cache eviction test 1");
             assert!(
                 !cache.contains(&first_key),
                 "First item should have been evicted"
             );
 
             // The newer items should still be in the cache
-            let second_key = KokoroTTS::generate_cache_key("Cache eviction test 2", &voice, 1.0);
-            let third_key = KokoroTTS::generate_cache_key("Cache eviction test 3", &voice, 1.0);
+            let second_key = key("Cache eviction test 2");
+            let third_key = key("Cache eviction test 3");
 
             assert!(
                 cache.contains(&second_key) || cache.contains(&third_key),
@@ -359,7 +677,11 @@ mod tests {
 
         let voice = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
         let text = "Cache expiration test";
-        let cache_key = KokoroTTS::generate_cache_key(text, &voice, 1.0);
+        let cache_key = KokoroTTS::generate_cache_key(
+            &tts.phonemize(text, &voice, &NormalizeOptions::default())?,
+            &voice,
+            1.0,
+        );
 
         // Process text to add to cache
         let result = tts.process_tts(text, &voice, 1.0);
@@ -440,6 +762,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_process_tts_until_deadline() -> Result<(), TtsError> {
+        let tts = KokoroTTS::new()?;
+        let voice = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
+        let options = NormalizeOptions::default();
+        let text = "The first sentence is short. The second one follows it.";
+
+        // Nothing is cached and the deadline has passed, so nothing can be synthesized
+        let result = tts.process_tts_until(text, &voice, 1.0, &options, Instant::now());
+        assert!(matches!(result, Err(TtsError::DeadlineExceeded(_))));
+
+        let deadline = Instant::now() + Duration::from_secs(300);
+        let full = tts.process_tts_until(text, &voice, 1.0, &options, deadline)?;
+        assert!(!full.truncated);
+        assert_eq!((full.sentences_synthesized, full.sentences_total), (2, 2));
+
+        // Cached sentences cost nothing, so they are returned even past the deadline
+        let cached = tts.process_tts_until(text, &voice, 1.0, &options, Instant::now())?;
+        assert_eq!(cached, full);
+
+        // Only the first sentence is cached, so the rest is left out
+        let longer = "The first sentence is short. Something new comes after it.";
+        let partial = tts.process_tts_until(longer, &voice, 1.0, &options, Instant::now())?;
+        assert!(partial.truncated);
+        assert_eq!(partial.sentences_synthesized, 1);
+        assert!(partial.samples.len() < full.samples.len());
+        Ok(())
+    }
+
     // Integration test for the full pipeline - marked as ignored
     #[test]
     fn test_full_pipeline() {
@@ -464,7 +815,7 @@ mod tests {
         assert!(!audio_data.is_empty(), "Audio data should not be empty");
 
         // Convert to WAV and save
-        let wav_data = tts.audio_to_wav(audio_data.as_slice().unwrap());
+        let wav_data = tts.audio_to_wav(audio_data.as_slice().unwrap()).unwrap();
         assert!(
             wav_data.len() > 44,
             "WAV data should be larger than header size"
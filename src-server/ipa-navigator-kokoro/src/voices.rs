@@ -1,5 +1,51 @@
 use crate::constants::ASSETS_PATH;
-use std::{path::PathBuf, sync::LazyLock};
+use std::{
+    collections::HashMap,
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+
+/// Accepted speed multipliers for a voice calibration
+pub const CALIBRATION_SPEED_RANGE: RangeInclusive<f32> = 0.5..=1.5;
+
+/// Accepted gain offsets for a voice calibration, in decibels
+pub const CALIBRATION_GAIN_RANGE: RangeInclusive<f32> = -12.0..=12.0;
+
+// Calibrations set at runtime, taking precedence over the defaults
+static CALIBRATION_OVERRIDES: LazyLock<Mutex<HashMap<VoiceType, VoiceCalibration>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Adjustments that bring a voice's tempo and loudness in line with the others
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoiceCalibration {
+    /// Multiplier applied to the requested speed
+    pub speed: f32,
+    /// Offset applied to the output level, in decibels
+    pub gain_db: f32,
+}
+
+impl Default for VoiceCalibration {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            gain_db: 0.0,
+        }
+    }
+}
+
+impl VoiceCalibration {
+    /// Linear factor for the gain offset
+    pub fn gain(&self) -> f32 {
+        10f32.powf(self.gain_db / 20.0)
+    }
+
+    /// Whether both adjustments are within the accepted ranges
+    pub fn is_valid(&self) -> bool {
+        CALIBRATION_SPEED_RANGE.contains(&self.speed)
+            && CALIBRATION_GAIN_RANGE.contains(&self.gain_db)
+    }
+}
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum AmericanFemaleVoice {
@@ -65,6 +111,40 @@ impl VoiceType {
         }
     }
 
+    /// Returns the name used for the voice in APIs, e.g. "american_female_bella".
+    pub fn name(&self) -> &'static str {
+        match self {
+            VoiceType::AmericanFemale(voice) => match voice {
+                AmericanFemaleVoice::Bella => "american_female_bella",
+                AmericanFemaleVoice::Nicole => "american_female_nicole",
+                AmericanFemaleVoice::Sky => "american_female_sky",
+            },
+            VoiceType::AmericanMale(voice) => match voice {
+                AmericanMaleVoice::Fenrir => "american_male_fenrir",
+                AmericanMaleVoice::Michael => "american_male_michael",
+                AmericanMaleVoice::Puck => "american_male_puck",
+            },
+            VoiceType::BritishFemale(voice) => match voice {
+                BritishFemaleVoice::Emma => "british_female_emma",
+                BritishFemaleVoice::Isabella => "british_female_isabella",
+                BritishFemaleVoice::Lily => "british_female_lily",
+            },
+            VoiceType::BritishMale(voice) => match voice {
+                BritishMaleVoice::Fable => "british_male_fable",
+                BritishMaleVoice::George => "british_male_george",
+                BritishMaleVoice::Lewis => "british_male_lewis",
+            },
+        }
+    }
+
+    /// Looks up a voice by the name returned from `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_VOICES
+            .iter()
+            .find(|voice| voice.name() == name)
+            .copied()
+    }
+
     /// Returns the language code associated with the voice type.
     pub fn language(&self) -> &'static str {
         match self {
@@ -73,6 +153,63 @@ impl VoiceType {
         }
     }
 
+    /// Returns the calibration shipped with the voice.
+    pub fn default_calibration(&self) -> VoiceCalibration {
+        let (speed, gain_db) = match self {
+            VoiceType::AmericanFemale(voice) => match voice {
+                AmericanFemaleVoice::Bella => (1.0, 0.0),
+                AmericanFemaleVoice::Nicole => (1.0, 3.0),
+                AmericanFemaleVoice::Sky => (1.0, 1.0),
+            },
+            VoiceType::AmericanMale(voice) => match voice {
+                AmericanMaleVoice::Fenrir => (1.0, 0.0),
+                AmericanMaleVoice::Michael => (1.0, 0.0),
+                AmericanMaleVoice::Puck => (0.95, -1.0),
+            },
+            VoiceType::BritishFemale(voice) => match voice {
+                BritishFemaleVoice::Emma => (1.0, 0.0),
+                BritishFemaleVoice::Isabella => (1.0, 0.0),
+                BritishFemaleVoice::Lily => (1.0, 1.0),
+            },
+            VoiceType::BritishMale(voice) => match voice {
+                BritishMaleVoice::Fable => (1.0, 0.0),
+                BritishMaleVoice::George => (1.05, 0.0),
+                BritishMaleVoice::Lewis => (1.0, 1.0),
+            },
+        };
+
+        VoiceCalibration { speed, gain_db }
+    }
+
+    /// Returns the calibration applied when synthesizing, the override if one is set.
+    pub fn calibration(&self) -> VoiceCalibration {
+        CALIBRATION_OVERRIDES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(self)
+            .copied()
+            .unwrap_or_else(|| self.default_calibration())
+    }
+
+    /// Overrides the voice's calibration, or restores the default when `None`.
+    pub fn set_calibration(&self, calibration: Option<VoiceCalibration>) {
+        let mut overrides = CALIBRATION_OVERRIDES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        match calibration {
+            Some(calibration) => overrides.insert(*self, calibration),
+            None => overrides.remove(self),
+        };
+    }
+
+    /// Returns whether the calibration has been overridden.
+    pub fn has_calibration_override(&self) -> bool {
+        CALIBRATION_OVERRIDES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(self)
+    }
+
     /// Returns the path to the voice file.
     pub fn path(&self) -> PathBuf {
         let voice_path = format!("{}/Kokoro/{}", *ASSETS_PATH, self.file_name());
@@ -121,6 +258,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_voice_names_round_trip() {
+        for voice in ALL_VOICES.iter() {
+            assert_eq!(VoiceType::from_name(voice.name()), Some(*voice));
+        }
+        assert_eq!(
+            VoiceType::from_name("british_male_george"),
+            Some(VoiceType::BritishMale(BritishMaleVoice::George))
+        );
+        assert_eq!(VoiceType::from_name("af_bella"), None);
+    }
+
+    #[test]
+    fn test_voice_calibration() {
+        assert!(
+            ALL_VOICES
+                .iter()
+                .all(|v| v.default_calibration().is_valid())
+        );
+        assert_eq!(VoiceCalibration::default().gain(), 1.0);
+        assert!(
+            (VoiceCalibration {
+                speed: 1.0,
+                gain_db: 6.0
+            }
+            .gain()
+                - 1.995)
+                .abs()
+                < 0.01
+        );
+        assert!(
+            !VoiceCalibration {
+                speed: 3.0,
+                gain_db: 0.0
+            }
+            .is_valid()
+        );
+
+        let voice = VoiceType::BritishMale(BritishMaleVoice::Fable);
+        let louder = VoiceCalibration {
+            speed: 1.1,
+            gain_db: 2.0,
+        };
+        voice.set_calibration(Some(louder));
+        assert_eq!(voice.calibration(), louder);
+        assert!(voice.has_calibration_override());
+
+        voice.set_calibration(None);
+        assert_eq!(voice.calibration(), voice.default_calibration());
+    }
+
     #[test]
     fn test_voice_paths() {
         let voice = VoiceType::AmericanFemale(AmericanFemaleVoice::Bella);
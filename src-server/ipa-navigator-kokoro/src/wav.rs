@@ -0,0 +1,500 @@
+//! Encoding synthesized audio as WAV files
+//!
+//! The server sends 16-bit clips at the model's 24 kHz. Audio bound for a DAW
+//! or further analysis can instead be dithered, kept at 24-bit or 32-bit float
+//! precision, and resampled.
+
+use crate::error::TtsError;
+use crate::prelude::SAMPLE_RATE;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::io::{self, Cursor};
+
+/// Seed of the dither noise, fixed so encoding the same audio twice gives the same file
+const DITHER_SEED: u32 = 0x2545_F491;
+
+/// How each sample is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleEncoding {
+    /// 16-bit integers, optionally with triangular (TPDF) dither to mask quantization distortion
+    Pcm16 { dither: bool },
+    /// 24-bit integers
+    Pcm24,
+    /// 32-bit floats, without any quantization
+    Float32,
+}
+
+impl SampleEncoding {
+    fn spec(&self, sample_rate: u32) -> WavSpec {
+        let (bits_per_sample, sample_format) = match self {
+            SampleEncoding::Pcm16 { .. } => (16, SampleFormat::Int),
+            SampleEncoding::Pcm24 => (24, SampleFormat::Int),
+            SampleEncoding::Float32 => (32, SampleFormat::Float),
+        };
+
+        WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        }
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        match self {
+            SampleEncoding::Pcm16 { .. } => 2,
+            SampleEncoding::Pcm24 => 3,
+            SampleEncoding::Float32 => 4,
+        }
+    }
+}
+
+/// Sample encoding and rate of a WAV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavFormat {
+    pub encoding: SampleEncoding,
+    /// Audio is resampled from [`SAMPLE_RATE`] when this differs
+    pub sample_rate: u32,
+}
+
+impl Default for WavFormat {
+    /// Undithered 16-bit audio at the model's sample rate
+    fn default() -> Self {
+        Self {
+            encoding: SampleEncoding::Pcm16 { dither: false },
+            sample_rate: SAMPLE_RATE,
+        }
+    }
+}
+
+/// Encode mono audio at [`SAMPLE_RATE`] as a WAV file in `format`
+pub fn encode_wav(audio_data: &[f32], format: &WavFormat) -> Result<Vec<u8>, TtsError> {
+    let mut encoder = WavStreamEncoder::new(*format);
+    let mut buffer = encoder.header(Some(audio_data.len()))?;
+    buffer.reserve(data_len(format, audio_data.len()));
+    encoder.encode_into(audio_data, &mut buffer);
+    encoder.finish_into(&mut buffer);
+    Ok(buffer)
+}
+
+/// Bytes of sample data in a WAV file in `format` of `input_samples` samples at [`SAMPLE_RATE`]
+pub fn data_len(format: &WavFormat, input_samples: usize) -> usize {
+    let frames = if format.sample_rate == SAMPLE_RATE || input_samples == 0 {
+        input_samples
+    } else {
+        (input_samples as f64 * format.sample_rate as f64 / SAMPLE_RATE as f64).floor() as usize
+    };
+    frames * format.encoding.bytes_per_sample()
+}
+
+/// Set the RIFF and data chunk sizes of a WAV header written with unknown length,
+/// once `data_len` bytes of samples have followed it
+pub fn set_data_len(header: &mut [u8], data_len: u32) {
+    let riff_len = (header.len() as u32 - 8).saturating_add(data_len);
+    header[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    let at = header.len() - 4;
+    header[at..].copy_from_slice(&data_len.to_le_bytes());
+}
+
+/// Encoder writing a WAV file piece by piece, so a long clip is never held
+/// encoded in full
+///
+/// The header comes first, then the bytes of each slice of samples passed to
+/// [`encode`](Self::encode), then those of [`finish`](Self::finish). When the
+/// length is not known up front, the header's sizes are left at their maximum,
+/// which players read as "until the end of the stream"; a file on disk can be
+/// fixed up afterwards with [`set_data_len`].
+pub struct WavStreamEncoder {
+    format: WavFormat,
+    noise: TriangularNoise,
+    resampler: LinearResampler,
+    /// Resampled samples waiting to be encoded, reused between slices
+    resampled: Vec<f32>,
+}
+
+impl WavStreamEncoder {
+    pub fn new(format: WavFormat) -> Self {
+        Self {
+            format,
+            noise: TriangularNoise::new(DITHER_SEED),
+            resampler: LinearResampler::new(SAMPLE_RATE, format.sample_rate),
+            resampled: Vec::new(),
+        }
+    }
+
+    /// The WAV header, sized for `input_samples` samples at [`SAMPLE_RATE`] if known
+    pub fn header(&self, input_samples: Option<usize>) -> Result<Vec<u8>, TtsError> {
+        // hound lays out the header, so streamed files match those it writes whole
+        let mut header = Vec::new();
+        WavWriter::new(
+            Cursor::new(&mut header),
+            self.format.encoding.spec(self.format.sample_rate),
+        )?
+        .finalize()?;
+
+        let data_len = match input_samples {
+            Some(samples) => u32::try_from(data_len(&self.format, samples)).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Audio too long for a WAV file")
+            })?,
+            None => u32::MAX,
+        };
+        set_data_len(&mut header, data_len);
+        Ok(header)
+    }
+
+    /// Encode the next slice of samples
+    ///
+    /// Some samples may be held back until the next slice when resampling.
+    pub fn encode(&mut self, samples: &[f32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode_into(samples, &mut bytes);
+        bytes
+    }
+
+    /// Encode the samples held back for resampling, after the last slice
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.finish_into(&mut bytes);
+        bytes
+    }
+
+    fn encode_into(&mut self, samples: &[f32], bytes: &mut Vec<u8>) {
+        let mut resampled = std::mem::take(&mut self.resampled);
+        self.resampler.push(samples, &mut resampled);
+        self.write_samples(&resampled, bytes);
+        resampled.clear();
+        self.resampled = resampled;
+    }
+
+    fn finish_into(&mut self, bytes: &mut Vec<u8>) {
+        let mut resampled = std::mem::take(&mut self.resampled);
+        self.resampler.finish(&mut resampled);
+        self.write_samples(&resampled, bytes);
+        resampled.clear();
+        self.resampled = resampled;
+    }
+
+    fn write_samples(&mut self, samples: &[f32], bytes: &mut Vec<u8>) {
+        bytes.reserve(samples.len() * self.format.encoding.bytes_per_sample());
+        match self.format.encoding {
+            SampleEncoding::Pcm16 { dither } => {
+                let max = i16::MAX as f32;
+                for &sample in samples {
+                    let amplitude = if dither {
+                        (sample.clamp(-1.0, 1.0) * max + self.noise.next())
+                            .round()
+                            .clamp(-max - 1.0, max) as i16
+                    } else {
+                        (sample.clamp(-1.0, 1.0) * max) as i16
+                    };
+                    bytes.extend_from_slice(&amplitude.to_le_bytes());
+                }
+            }
+            SampleEncoding::Pcm24 => {
+                let max = ((1 << 23) - 1) as f32;
+                for &sample in samples {
+                    let amplitude = (sample.clamp(-1.0, 1.0) * max).round() as i32;
+                    bytes.extend_from_slice(&amplitude.to_le_bytes()[..3]);
+                }
+            }
+            SampleEncoding::Float32 => {
+                for &sample in samples {
+                    bytes.extend_from_slice(&sample.to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+/// Linear interpolation over audio arriving in slices, giving the same samples
+/// as [`resample_linear`] over the whole clip
+struct LinearResampler {
+    ratio: f64,
+    /// Index of the next output sample
+    next_output: usize,
+    /// Index in the whole input of the first sample in `pending`
+    base: usize,
+    /// Input samples still needed to interpolate later output
+    pending: Vec<f32>,
+}
+
+impl LinearResampler {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            ratio: from_rate as f64 / to_rate as f64,
+            next_output: 0,
+            base: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Add input, appending the output samples whose neighbours have both arrived
+    fn push(&mut self, samples: &[f32], output: &mut Vec<f32>) {
+        if self.ratio == 1.0 {
+            output.extend_from_slice(samples);
+            return;
+        }
+        self.pending.extend_from_slice(samples);
+        let available = self.base + self.pending.len();
+        // Never past the output length of the input so far, which later input only extends
+        let out_len = self.out_len(available);
+        while self.next_output < out_len && self.index(self.next_output) + 1 < available {
+            output.push(self.interpolate(self.next_output, available));
+            self.next_output += 1;
+        }
+        // Drop what no later output sample reads
+        let keep_from = self.index(self.next_output).min(available);
+        self.pending.drain(..keep_from - self.base);
+        self.base = keep_from;
+    }
+
+    /// Append the remaining output samples, holding the last input sample past the end
+    fn finish(&mut self, output: &mut Vec<f32>) {
+        let total = self.base + self.pending.len();
+        if self.ratio == 1.0 || total == 0 {
+            return;
+        }
+        let out_len = self.out_len(total);
+        while self.next_output < out_len {
+            output.push(self.interpolate(self.next_output, total));
+            self.next_output += 1;
+        }
+        self.pending.clear();
+        self.base = total;
+    }
+
+    fn out_len(&self, input_len: usize) -> usize {
+        (input_len as f64 / self.ratio).floor() as usize
+    }
+
+    fn index(&self, output: usize) -> usize {
+        (output as f64 * self.ratio).floor() as usize
+    }
+
+    fn interpolate(&self, output: usize, available: usize) -> f32 {
+        let position = output as f64 * self.ratio;
+        let index = position.floor() as usize;
+        let fraction = (position - index as f64) as f32;
+        let sample = |i: usize| self.pending[i.min(available - 1) - self.base];
+        let current = sample(index);
+        let next = sample(index + 1);
+        current + (next - current) * fraction
+    }
+}
+
+/// Resample with linear interpolation
+pub fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio).floor() as usize;
+
+    (0..out_len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position.floor() as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index.min(samples.len() - 1)];
+            let next = samples[(index + 1).min(samples.len() - 1)];
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+/// Minimum and maximum sample of each window of a clip, for drawing its waveform
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaveformPeaks {
+    pub samples_per_peak: usize,
+    pub peaks: Vec<(f32, f32)>,
+}
+
+impl WaveformPeaks {
+    /// Split samples into at most `count` equal windows and take the extremes of each
+    pub fn from_samples(samples: &[f32], count: usize) -> Self {
+        if samples.is_empty() || count == 0 {
+            return Self {
+                samples_per_peak: 0,
+                peaks: Vec::new(),
+            };
+        }
+
+        let samples_per_peak = samples.len().div_ceil(count);
+        let peaks = samples
+            .chunks(samples_per_peak)
+            .map(|window| {
+                window
+                    .iter()
+                    .fold((f32::MAX, f32::MIN), |(min, max), &sample| {
+                        (min.min(sample), max.max(sample))
+                    })
+            })
+            .collect();
+
+        Self {
+            samples_per_peak,
+            peaks,
+        }
+    }
+}
+
+/// Noise with a triangular distribution between -1 and 1 least significant bits
+struct TriangularNoise {
+    state: u32,
+}
+
+impl TriangularNoise {
+    fn new(seed: u32) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    /// Xorshift, uniform in `[0, 1)`
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// The difference of two uniform values is triangular
+    fn next(&mut self) -> f32 {
+        self.uniform() - self.uniform()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::WavReader;
+
+    fn read_spec(wav: &[u8]) -> (WavSpec, u32) {
+        let reader = WavReader::new(Cursor::new(wav)).unwrap();
+        (reader.spec(), reader.duration())
+    }
+
+    #[test]
+    fn test_encode_wav_formats() {
+        let audio: Vec<f32> = (0..2400).map(|i| (i as f32 / 10.0).sin() * 0.5).collect();
+
+        let (spec, frames) = read_spec(&encode_wav(&audio, &WavFormat::default()).unwrap());
+        assert_eq!(
+            (spec.bits_per_sample, spec.sample_rate, frames),
+            (16, 24000, 2400)
+        );
+
+        let pcm24 = WavFormat {
+            encoding: SampleEncoding::Pcm24,
+            sample_rate: 48000,
+        };
+        let (spec, frames) = read_spec(&encode_wav(&audio, &pcm24).unwrap());
+        assert_eq!(
+            (spec.bits_per_sample, spec.sample_rate, frames),
+            (24, 48000, 4800)
+        );
+
+        let float = WavFormat {
+            encoding: SampleEncoding::Float32,
+            sample_rate: SAMPLE_RATE,
+        };
+        let wav = encode_wav(&audio, &float).unwrap();
+        let mut reader = WavReader::new(Cursor::new(&wav)).unwrap();
+        assert_eq!(reader.spec().sample_format, SampleFormat::Float);
+        let decoded: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+        assert_eq!(decoded, audio);
+    }
+
+    #[test]
+    fn test_dither_is_deterministic_and_small() {
+        let audio: Vec<f32> = (0..1000).map(|i| (i as f32 / 7.0).sin() * 0.25).collect();
+        let dithered = WavFormat {
+            encoding: SampleEncoding::Pcm16 { dither: true },
+            sample_rate: SAMPLE_RATE,
+        };
+        assert_eq!(
+            encode_wav(&audio, &dithered).unwrap(),
+            encode_wav(&audio, &dithered).unwrap()
+        );
+
+        let wav = encode_wav(&audio, &dithered).unwrap();
+        let mut reader = WavReader::new(Cursor::new(&wav)).unwrap();
+        for (sample, original) in reader.samples::<i16>().map(Result::unwrap).zip(&audio) {
+            assert!((sample as f32 - original * i16::MAX as f32).abs() <= 1.5);
+        }
+    }
+
+    #[test]
+    fn test_streamed_wav_matches_whole() {
+        let audio: Vec<f32> = (0..5000).map(|i| (i as f32 / 9.0).sin() * 0.7).collect();
+        let formats = [
+            WavFormat::default(),
+            WavFormat {
+                encoding: SampleEncoding::Pcm16 { dither: true },
+                sample_rate: 16000,
+            },
+            WavFormat {
+                encoding: SampleEncoding::Pcm24,
+                sample_rate: 44100,
+            },
+            WavFormat {
+                encoding: SampleEncoding::Float32,
+                sample_rate: 48000,
+            },
+        ];
+
+        for format in formats {
+            let whole = encode_wav(&audio, &format).unwrap();
+
+            // Uneven slices, including empty ones, give the same file
+            let mut encoder = WavStreamEncoder::new(format);
+            let mut streamed = encoder.header(Some(audio.len())).unwrap();
+            for slice in [&audio[..1], &audio[1..1], &audio[1..700], &audio[700..]] {
+                streamed.extend(encoder.encode(slice));
+            }
+            streamed.extend(encoder.finish());
+            assert_eq!(streamed, whole, "{:?}", format);
+
+            // A deferred header fixed up afterwards does too
+            let mut encoder = WavStreamEncoder::new(format);
+            let mut header = encoder.header(None).unwrap();
+            let mut data: Vec<u8> = audio
+                .chunks(333)
+                .flat_map(|slice| encoder.encode(slice))
+                .collect();
+            data.extend(encoder.finish());
+            set_data_len(&mut header, data.len() as u32);
+            assert_eq!([header, data].concat(), whole, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_streamed_resampling_matches_whole() {
+        let audio: Vec<f32> = (0..1001).map(|i| (i as f32 / 5.0).cos()).collect();
+        for to_rate in [8000, 22050, 48000] {
+            let mut resampler = LinearResampler::new(SAMPLE_RATE, to_rate);
+            let mut streamed = Vec::new();
+            for slice in audio.chunks(97) {
+                resampler.push(slice, &mut streamed);
+            }
+            resampler.finish(&mut streamed);
+            assert_eq!(streamed, resample_linear(&audio, SAMPLE_RATE, to_rate));
+        }
+    }
+
+    #[test]
+    fn test_waveform_peaks() {
+        let samples = [0.1, -0.5, 0.3, 0.9, -0.2, 0.0, 0.4];
+        let peaks = WaveformPeaks::from_samples(&samples, 3);
+        assert_eq!(peaks.samples_per_peak, 3);
+        assert_eq!(peaks.peaks, vec![(-0.5, 0.3), (-0.2, 0.9), (0.4, 0.4)]);
+
+        assert!(WaveformPeaks::from_samples(&[], 10).peaks.is_empty());
+        assert_eq!(WaveformPeaks::from_samples(&samples, 100).peaks.len(), 7);
+    }
+
+    #[test]
+    fn test_resample_linear() {
+        let samples = vec![0.0, 1.0, 2.0, 3.0];
+        assert_eq!(resample_linear(&samples, 32000, 16000), vec![0.0, 2.0]);
+        assert_eq!(resample_linear(&samples, 16000, 16000), samples);
+    }
+}
@@ -0,0 +1,117 @@
+//! Golden-audio regression tests
+//!
+//! Synthesizes a fixed corpus and compares each clip's acoustic fingerprint
+//! with the one stored in `tests/golden`, so changes to normalization,
+//! chunking or style vectors can't silently change what learners hear.
+//!
+//! Run with `cargo test -p ipa-navigator-kokoro --features model-tests`.
+//! Missing fingerprints are recorded on the first run. After an intended
+//! change to the audio, re-record them all with `UPDATE_GOLDEN=1` and commit
+//! the updated files with the change.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use ipa_navigator_kokoro::fingerprint::{AcousticFingerprint, Tolerance};
+use ipa_navigator_kokoro::prelude::*;
+
+struct Case {
+    name: &'static str,
+    text: &'static str,
+    voice: VoiceType,
+    speed: f32,
+}
+
+/// Covers each stage whose changes would reach the audio
+const CORPUS: &[Case] = &[
+    Case {
+        name: "short_sentence",
+        text: "The quick brown fox jumps over the lazy dog.",
+        voice: VoiceType::AmericanFemale(AmericanFemaleVoice::Bella),
+        speed: 1.0,
+    },
+    // Normalization of numbers, currency, abbreviations and dates
+    Case {
+        name: "normalization",
+        text: "Dr. Smith paid $3.50 for 12 apples on Jan. 5th at 221B Baker St.",
+        voice: VoiceType::AmericanMale(AmericanMaleVoice::Michael),
+        speed: 1.0,
+    },
+    // Sentence chunking and the pauses between chunks
+    Case {
+        name: "multiple_sentences",
+        text: "She sells seashells by the seashore. The shells she sells are surely seashells! \
+               So if she sells shells on the seashore, I'm sure she sells seashore shells?",
+        voice: VoiceType::BritishFemale(BritishFemaleVoice::Emma),
+        speed: 1.0,
+    },
+    // A long sentence selects a style vector further into the voice file
+    Case {
+        name: "long_sentence",
+        text: "Although the weather had been unpredictable for most of the week, the students \
+               gathered early on Saturday morning to rehearse their presentations on the \
+               history of phonetics, pronouncing every unfamiliar symbol slowly and carefully.",
+        voice: VoiceType::BritishMale(BritishMaleVoice::George),
+        speed: 1.0,
+    },
+    Case {
+        name: "slow_speech",
+        text: "Thought, though, through and tough.",
+        voice: VoiceType::AmericanFemale(AmericanFemaleVoice::Nicole),
+        speed: 0.7,
+    },
+    Case {
+        name: "fast_speech",
+        text: "Thought, though, through and tough.",
+        voice: VoiceType::AmericanFemale(AmericanFemaleVoice::Nicole),
+        speed: 1.5,
+    },
+];
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.json", name))
+}
+
+#[test]
+fn test_synthesis_matches_golden_fingerprints() {
+    let engine =
+        TtsEngine::new().expect("Kokoro model and voices should be in the assets directory");
+    let update = env::var("UPDATE_GOLDEN").is_ok_and(|value| value == "1");
+    let tolerance = Tolerance::default();
+
+    let mut failures = Vec::new();
+    for case in CORPUS {
+        let audio = engine
+            .synthesize(case.text, case.voice, case.speed)
+            .unwrap_or_else(|e| panic!("{}: synthesis failed: {}", case.name, e));
+        let fingerprint = AcousticFingerprint::from_samples(audio.samples(), SAMPLE_RATE);
+        let path = golden_path(case.name);
+
+        if update || !path.exists() {
+            let json = serde_json::to_string_pretty(&fingerprint).unwrap();
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, json + "\n").unwrap();
+            eprintln!("Recorded golden fingerprint {}", path.display());
+            continue;
+        }
+
+        let golden: AcousticFingerprint = serde_json::from_str(&fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|e| panic!("{}: unreadable golden fingerprint: {}", case.name, e));
+        let distance = golden.distance(&fingerprint);
+        if !distance.within(&tolerance) {
+            failures.push(format!("{}: {:?}", case.name, distance));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "Synthesized audio differs from the golden fingerprints (tolerance {:?}):\n{}\n\
+         If the change is intended, re-record with UPDATE_GOLDEN=1",
+        tolerance,
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,15 @@
+#![no_main]
+
+use ipa_navigator_mfa::mfa_json::parse_alignment_json_str;
+use libfuzzer_sys::fuzz_target;
+
+// As for TextGrids, any input must parse or be rejected with an error, and
+// anything accepted must have usable timestamps
+fuzz_target!(|contents: &str| {
+    if let Ok(segments) = parse_alignment_json_str(contents) {
+        for segment in segments {
+            assert!(segment.begin.is_finite() && segment.end.is_finite());
+            assert!(segment.begin <= segment.end);
+        }
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use ipa_navigator_mfa::mfa_parser::parse_textgrid_str;
+use libfuzzer_sys::fuzz_target;
+
+// Any input must parse or be rejected with an error, never panic, and
+// anything accepted must have usable timestamps
+fuzz_target!(|contents: &str| {
+    if let Ok(segments) = parse_textgrid_str(contents) {
+        for segment in segments {
+            assert!(segment.begin.is_finite() && segment.end.is_finite());
+            assert!(segment.begin <= segment.end);
+        }
+    }
+});
@@ -0,0 +1,107 @@
+# Learner-facing text in English, the source language
+#
+# Every message here must exist, since other catalogs fall back to English.
+
+## Pronunciation tips
+
+tip-omitted = You left out the /{ $expected }/ sound — make sure to pronounce it
+tip-added = You added an extra /{ $actual }/ sound — try to leave it out
+tip-substituted = Your /{ $expected }/ sounded like /{ $actual }/ — { $advice }
+tip-wrong-sentence = It sounds like you read a different sentence — try reading the prompt again
+tip-too-quiet = "{ $word }" was too quiet to assess — say it a little louder
+tip-trailing-off = Your voice trailed off at "{ $word }" — keep your volume up to the end of the sentence
+tip-muffled = "{ $word }" sounded muffled — open your mouth a little more and say it clearly
+tip-word-not-detected = We didn't hear "{ $word }" — try saying it again, closer to the microphone
+advice-listen = listen to /{ $expected }/ again and imitate it
+advice-and = { $first }, and { $second }
+
+## Advice for common confusions
+
+confusion-th-voiceless = place your tongue between your teeth and blow air gently
+confusion-th-voiced = place your tongue between your teeth and let your voice buzz as you blow
+confusion-r-for-l = curl your tongue back without letting it touch the roof of your mouth
+confusion-l-for-r = touch the tip of your tongue to the ridge behind your top teeth
+confusion-r-for-w = pull your tongue back and keep your lips relaxed rather than rounded
+confusion-v = touch your top teeth to your lower lip and let your voice buzz
+confusion-w-for-v = round your lips without letting your teeth touch them
+confusion-sh-for-s = round your lips slightly and pull your tongue a little further back
+confusion-short-i = keep the vowel short and relaxed, with your tongue a little lower
+confusion-long-i = spread your lips and hold the vowel a little longer
+confusion-ae = drop your jaw further and open your mouth wider
+confusion-short-u = keep the vowel short and your lips less tightly rounded
+
+## Advice for a differing feature
+
+difference-vowel = keep your mouth open so the air flows freely
+difference-consonant = close off or narrow the airflow instead of letting it flow freely
+difference-voiced = let your vocal cords vibrate
+difference-voiceless = keep your vocal cords still and just push air
+difference-lower = open your mouth wider and lower your tongue
+difference-higher = raise your tongue and close your mouth a little
+difference-backer = pull your tongue further back
+difference-fronter = push your tongue further forward
+difference-rounded = round your lips
+difference-unrounded = spread your lips instead of rounding them
+difference-glide = start at /{ $onset }/ and glide towards /{ $offset }/ within the same vowel
+difference-steady = hold the vowel steady without gliding
+
+## How to produce each place, manner, height and backness
+
+hint-bilabial = press both lips together
+hint-labiodental = touch your top teeth to your lower lip
+hint-dental = place your tongue between your teeth
+hint-alveolar = touch the tip of your tongue to the ridge behind your top teeth
+hint-postalveolar = pull your tongue just behind the ridge behind your top teeth
+hint-palatal = raise the middle of your tongue towards the roof of your mouth
+hint-velar = raise the back of your tongue against your soft palate
+hint-glottal = make the sound in your throat
+hint-plosive = stop the air completely, then release it in a burst
+hint-fricative = let the air hiss through a narrow gap without stopping it
+hint-affricate = start with a full stop and release it into a hiss
+hint-nasal = let the air flow out through your nose
+hint-approximant = bring your tongue close without creating any hiss
+hint-close = raise your tongue high, close to the roof of your mouth
+hint-mid = keep your tongue halfway between high and low
+hint-open = drop your jaw and keep your tongue low
+hint-front = push your tongue forward in your mouth
+hint-central = keep your tongue relaxed in the centre of your mouth
+hint-back = pull your tongue back in your mouth
+
+## Phoneme descriptions, e.g. "voiceless dental fricative"
+
+phoneme-consonant = { $voicing } { $place } { $manner }
+phoneme-lateral = { $voicing } { $place } lateral { $manner }
+phoneme-vowel = { $height } { $backness } { $rounding } vowel
+phoneme-diphthong = diphthong gliding from /{ $onset }/ to /{ $offset }/
+phoneme-modified = { $modifier } { $description }
+phoneme-unknown = unknown sound
+
+term-voiced = voiced
+term-voiceless = voiceless
+term-bilabial = bilabial
+term-labiodental = labiodental
+term-dental = dental
+term-alveolar = alveolar
+term-postalveolar = postalveolar
+term-palatal = palatal
+term-velar = velar
+term-glottal = glottal
+term-plosive = plosive
+term-fricative = fricative
+term-affricate = affricate
+term-nasal = nasal
+term-approximant = approximant
+term-close = close
+term-mid = mid
+term-open = open
+term-front = front
+term-central = central
+term-back = back
+term-rounded = rounded
+term-unrounded = unrounded
+term-long = long
+term-aspirated = aspirated
+term-nasalized = nasalized
+term-palatalized = palatalized
+term-labialized = labialized
+term-syllabic = syllabic
@@ -0,0 +1,105 @@
+# Learner-facing text in Malay
+
+## Pronunciation tips
+
+tip-omitted = Anda tertinggal bunyi /{ $expected }/ — pastikan anda menyebutnya
+tip-added = Anda menambah bunyi /{ $actual }/ — cuba tinggalkannya
+tip-substituted = Bunyi /{ $expected }/ anda kedengaran seperti /{ $actual }/ — { $advice }
+tip-wrong-sentence = Anda seperti membaca ayat yang lain — cuba baca ayat yang diberi sekali lagi
+tip-too-quiet = "{ $word }" terlalu perlahan untuk dinilai — sebut dengan lebih kuat sedikit
+tip-trailing-off = Suara anda semakin perlahan pada "{ $word }" — kekalkan kelantangan hingga akhir ayat
+tip-muffled = "{ $word }" kedengaran tidak jelas — buka mulut lebih sedikit dan sebut dengan jelas
+tip-word-not-detected = Kami tidak mendengar "{ $word }" — cuba sebut sekali lagi, lebih dekat dengan mikrofon
+advice-listen = dengar /{ $expected }/ sekali lagi dan tirunya
+advice-and = { $first }, dan { $second }
+
+## Advice for common confusions
+
+confusion-th-voiceless = letakkan lidah di antara gigi dan hembus udara perlahan-lahan
+confusion-th-voiced = letakkan lidah di antara gigi dan biarkan suara bergetar semasa menghembus
+confusion-r-for-l = gulung lidah ke belakang tanpa menyentuh lelangit
+confusion-l-for-r = sentuh hujung lidah pada gusi di belakang gigi atas
+confusion-r-for-w = tarik lidah ke belakang dan biarkan bibir santai, tidak dibundarkan
+confusion-v = sentuh gigi atas pada bibir bawah dan biarkan suara bergetar
+confusion-w-for-v = bundarkan bibir tanpa membiarkan gigi menyentuhnya
+confusion-sh-for-s = bundarkan bibir sedikit dan tarik lidah sedikit ke belakang
+confusion-short-i = sebut vokal dengan pendek dan santai, dengan lidah sedikit lebih rendah
+confusion-long-i = hamparkan bibir dan panjangkan vokal sedikit
+confusion-ae = turunkan rahang dan buka mulut lebih luas
+confusion-short-u = sebut vokal dengan pendek dan jangan bundarkan bibir terlalu ketat
+
+## Advice for a differing feature
+
+difference-vowel = buka mulut supaya udara mengalir bebas
+difference-consonant = sekat atau sempitkan aliran udara, jangan biarkan ia mengalir bebas
+difference-voiced = biarkan pita suara bergetar
+difference-voiceless = jangan getarkan pita suara, hembus udara sahaja
+difference-lower = buka mulut lebih luas dan rendahkan lidah
+difference-higher = tinggikan lidah dan kecilkan bukaan mulut sedikit
+difference-backer = tarik lidah lebih ke belakang
+difference-fronter = tolak lidah lebih ke hadapan
+difference-rounded = bundarkan bibir
+difference-unrounded = hamparkan bibir, jangan bundarkannya
+difference-glide = mula pada /{ $onset }/ dan luncur ke arah /{ $offset }/ dalam vokal yang sama
+difference-steady = kekalkan vokal tanpa meluncur
+
+## How to produce each place, manner, height and backness
+
+hint-bilabial = rapatkan kedua-dua bibir
+hint-labiodental = sentuh gigi atas pada bibir bawah
+hint-dental = letakkan lidah di antara gigi
+hint-alveolar = sentuh hujung lidah pada gusi di belakang gigi atas
+hint-postalveolar = tarik lidah ke belakang sedikit dari gusi di belakang gigi atas
+hint-palatal = angkat bahagian tengah lidah ke arah lelangit
+hint-velar = angkat bahagian belakang lidah ke lelangit lembut
+hint-glottal = hasilkan bunyi di dalam tekak
+hint-plosive = sekat udara sepenuhnya, kemudian lepaskannya dengan letupan
+hint-fricative = biarkan udara berdesis melalui celah sempit tanpa menyekatnya
+hint-affricate = mula dengan sekatan penuh dan lepaskannya menjadi desisan
+hint-nasal = biarkan udara keluar melalui hidung
+hint-approximant = dekatkan lidah tanpa menghasilkan desisan
+hint-close = angkat lidah tinggi, dekat dengan lelangit
+hint-mid = letakkan lidah di pertengahan antara tinggi dan rendah
+hint-open = turunkan rahang dan rendahkan lidah
+hint-front = tolak lidah ke hadapan mulut
+hint-central = biarkan lidah santai di tengah mulut
+hint-back = tarik lidah ke belakang mulut
+
+## Phoneme descriptions, e.g. "geseran gigi tak bersuara"
+
+phoneme-consonant = { $manner } { $place } { $voicing }
+phoneme-lateral = { $manner } sisian { $place } { $voicing }
+phoneme-vowel = vokal { $backness } { $height } { $rounding }
+phoneme-diphthong = diftong yang meluncur dari /{ $onset }/ ke /{ $offset }/
+phoneme-modified = { $description } { $modifier }
+phoneme-unknown = bunyi tidak diketahui
+
+term-voiced = bersuara
+term-voiceless = tak bersuara
+term-bilabial = dwibibir
+term-labiodental = bibir-gigi
+term-dental = gigi
+term-alveolar = gusi
+term-postalveolar = pasca-gusi
+term-palatal = lelangit keras
+term-velar = lelangit lembut
+term-glottal = glotis
+term-plosive = letupan
+term-fricative = geseran
+term-affricate = letusan
+term-nasal = sengauan
+term-approximant = hampiran
+term-close = sempit
+term-mid = separuh sempit
+term-open = luas
+term-front = depan
+term-central = tengah
+term-back = belakang
+term-rounded = bundar
+term-unrounded = hampar
+term-long = panjang
+term-aspirated = beraspirasi
+term-nasalized = disengaukan
+term-palatalized = dipalatalkan
+term-labialized = dilabialkan
+term-syllabic = bersuku kata
@@ -0,0 +1,105 @@
+# Learner-facing text in Mandarin (Simplified Chinese)
+
+## Pronunciation tips
+
+tip-omitted = 你漏掉了 /{ $expected }/ 音——请务必把它读出来
+tip-added = 你多读了一个 /{ $actual }/ 音——试着去掉它
+tip-substituted = 你的 /{ $expected }/ 听起来像 /{ $actual }/——{ $advice }
+tip-wrong-sentence = 你读的好像是另一个句子——请再读一遍题目中的句子
+tip-too-quiet = “{ $word }”声音太小，无法评估——请读得大声一点
+tip-trailing-off = 读到“{ $word }”时声音越来越小——请保持音量直到句子结束
+tip-muffled = “{ $word }”听起来含糊不清——嘴巴张大一点，读清楚
+tip-word-not-detected = 没有听到“{ $word }”——请靠近麦克风再读一遍
+advice-listen = 再听一遍 /{ $expected }/ 并模仿
+advice-and = { $first }，并且{ $second }
+
+## Advice for common confusions
+
+confusion-th-voiceless = 把舌尖放在上下齿之间，轻轻送气
+confusion-th-voiced = 把舌尖放在上下齿之间，送气时让声带振动
+confusion-r-for-l = 舌头向后卷，但不要碰到上腭
+confusion-l-for-r = 用舌尖抵住上齿后面的齿龈
+confusion-r-for-w = 舌头向后缩，嘴唇放松，不要撮圆
+confusion-v = 上齿轻触下唇，并让声带振动
+confusion-w-for-v = 撮圆嘴唇，不要让牙齿碰到嘴唇
+confusion-sh-for-s = 嘴唇稍微撮圆，舌头再往后缩一点
+confusion-short-i = 元音要短而放松，舌位稍低一些
+confusion-long-i = 嘴唇向两边展开，元音拉长一点
+confusion-ae = 下巴再往下放，嘴张得更大
+confusion-short-u = 元音要短，嘴唇不要撮得太紧
+
+## Advice for a differing feature
+
+difference-vowel = 张开嘴，让气流自由通过
+difference-consonant = 阻塞或收窄气流，不要让它自由通过
+difference-voiced = 让声带振动
+difference-voiceless = 声带不要振动，只送气
+difference-lower = 嘴张大一些，舌位放低
+difference-higher = 舌位抬高，嘴稍微合拢
+difference-backer = 舌头再往后缩
+difference-fronter = 舌头再往前伸
+difference-rounded = 撮圆嘴唇
+difference-unrounded = 嘴唇向两边展开，不要撮圆
+difference-glide = 从 /{ $onset }/ 开始，在同一个元音内滑向 /{ $offset }/
+difference-steady = 保持元音稳定，不要滑动
+
+## How to produce each place, manner, height and backness
+
+hint-bilabial = 双唇紧闭
+hint-labiodental = 上齿轻触下唇
+hint-dental = 把舌尖放在上下齿之间
+hint-alveolar = 用舌尖抵住上齿后面的齿龈
+hint-postalveolar = 舌头放在上齿龈稍后的位置
+hint-palatal = 把舌面中部抬向上腭
+hint-velar = 把舌根抬起抵住软腭
+hint-glottal = 在喉咙里发音
+hint-plosive = 先完全阻住气流，再突然放开
+hint-fricative = 让气流从窄缝中摩擦而出，不要阻断
+hint-affricate = 先完全阻住气流，再放开成摩擦音
+hint-nasal = 让气流从鼻腔流出
+hint-approximant = 舌头靠近但不要产生摩擦
+hint-close = 舌位抬高，靠近上腭
+hint-mid = 舌位保持在高低之间
+hint-open = 下巴放低，舌位放低
+hint-front = 舌头向口腔前部伸
+hint-central = 舌头放松，保持在口腔中部
+hint-back = 舌头向口腔后部缩
+
+## Phoneme descriptions, e.g. "清齿擦音"
+
+phoneme-consonant = { $voicing }{ $place }{ $manner }
+phoneme-lateral = { $voicing }{ $place }边{ $manner }
+phoneme-vowel = { $height }{ $backness }{ $rounding }元音
+phoneme-diphthong = 从 /{ $onset }/ 滑向 /{ $offset }/ 的双元音
+phoneme-modified = { $modifier }{ $description }
+phoneme-unknown = 未知音
+
+term-voiced = 浊
+term-voiceless = 清
+term-bilabial = 双唇
+term-labiodental = 唇齿
+term-dental = 齿
+term-alveolar = 齿龈
+term-postalveolar = 龈后
+term-palatal = 硬腭
+term-velar = 软腭
+term-glottal = 声门
+term-plosive = 塞音
+term-fricative = 擦音
+term-affricate = 塞擦音
+term-nasal = 鼻音
+term-approximant = 近音
+term-close = 闭
+term-mid = 中
+term-open = 开
+term-front = 前
+term-central = 央
+term-back = 后
+term-rounded = 圆唇
+term-unrounded = 不圆唇
+term-long = 长
+term-aspirated = 送气
+term-nasalized = 鼻化
+term-palatalized = 腭化
+term-labialized = 唇化
+term-syllabic = 成音节
@@ -0,0 +1,130 @@
+//! Pluggable forced-alignment backends
+
+use anyhow::Result;
+use std::env;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use crate::batch::BATCH_ALIGNER;
+use crate::ctc::CtcAligner;
+use crate::docker::MfaDialect;
+use crate::mfa_parser::MfaSegment;
+use crate::scoring::{PronunciationAssessment, ScoringRubric, score_segments};
+
+/// Forced-alignment backend producing word and phone segments for a recording
+pub trait Aligner: Send + Sync {
+    /// Short name of the backend, used in logs and status output
+    fn name(&self) -> &'static str;
+
+    /// Align a recording against its transcript
+    fn align(
+        &self,
+        audio_data: &[u8],
+        transcript: &str,
+        dialect: MfaDialect,
+    ) -> Result<Vec<MfaSegment>>;
+
+    /// Align a recording and score it against the dictionary pronunciation
+    fn assess(
+        &self,
+        audio_data: &[u8],
+        transcript: &str,
+        dialect: MfaDialect,
+        rubric: &ScoringRubric,
+    ) -> Result<PronunciationAssessment> {
+        let segments = self.align(audio_data, transcript, dialect)?;
+        Ok(score_segments(&segments, transcript, dialect, rubric)?
+            .with_audio_analysis(audio_data, &segments))
+    }
+}
+
+/// Montreal Forced Aligner running in Docker, batched across concurrent requests
+pub struct MfaAligner;
+
+impl Aligner for MfaAligner {
+    fn name(&self) -> &'static str {
+        "mfa"
+    }
+
+    fn align(
+        &self,
+        audio_data: &[u8],
+        transcript: &str,
+        dialect: MfaDialect,
+    ) -> Result<Vec<MfaSegment>> {
+        BATCH_ALIGNER.align(audio_data, transcript, dialect)
+    }
+}
+
+/// Available alignment backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignerBackend {
+    /// Montreal Forced Aligner in Docker (default)
+    Mfa,
+    /// In-process CTC segmentation over a wav2vec2 ONNX model
+    Ctc,
+}
+
+impl AlignerBackend {
+    /// Parse a backend name, as used by the `ALIGNER_BACKEND` variable
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "mfa" => Some(AlignerBackend::Mfa),
+            "ctc" | "wav2vec2" => Some(AlignerBackend::Ctc),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlignerBackend::Mfa => "mfa",
+            AlignerBackend::Ctc => "ctc",
+        }
+    }
+
+    /// Backend selected by the `ALIGNER_BACKEND` environment variable
+    pub fn from_env() -> Self {
+        match env::var("ALIGNER_BACKEND") {
+            Ok(name) => Self::parse(&name).unwrap_or_else(|| {
+                tracing::warn!("Unknown ALIGNER_BACKEND '{}', using mfa", name);
+                AlignerBackend::Mfa
+            }),
+            Err(_) => AlignerBackend::Mfa,
+        }
+    }
+}
+
+// Aligner selected at startup, initialized lazily since the CTC model is expensive to load
+static ALIGNER: LazyLock<Mutex<Option<Arc<dyn Aligner>>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Get the configured aligner, loading it on first use
+pub fn get_aligner() -> Result<Arc<dyn Aligner>> {
+    let mut guard = ALIGNER
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire aligner lock"))?;
+
+    if let Some(aligner) = guard.as_ref() {
+        return Ok(aligner.clone());
+    }
+
+    let aligner: Arc<dyn Aligner> = match AlignerBackend::from_env() {
+        AlignerBackend::Mfa => Arc::new(MfaAligner),
+        AlignerBackend::Ctc => Arc::new(CtcAligner::from_env()?),
+    };
+
+    tracing::info!("Using {} alignment backend", aligner.name());
+    *guard = Some(aligner.clone());
+    Ok(aligner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_parse() {
+        assert_eq!(AlignerBackend::parse("mfa"), Some(AlignerBackend::Mfa));
+        assert_eq!(AlignerBackend::parse(" CTC "), Some(AlignerBackend::Ctc));
+        assert_eq!(AlignerBackend::parse("wav2vec2"), Some(AlignerBackend::Ctc));
+        assert_eq!(AlignerBackend::parse("gentle"), None);
+    }
+}
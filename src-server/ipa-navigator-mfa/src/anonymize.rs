@@ -0,0 +1,276 @@
+//! Voice anonymization for recordings kept for later review
+//!
+//! The voice is shifted in pitch and its formants warped by an amount that
+//! varies over time, then stretched back to its original length, so the words
+//! stay intelligible while the speaker is hard to recognise. A random offset
+//! drawn for each recording keeps the shift from being undone by inverting a
+//! known setting.
+
+use std::f64::consts::TAU;
+
+use anyhow::Result;
+
+use crate::audio::{read_wav_mono, write_wav_mono};
+
+/// Pitch shift applied when none is configured, in semitones
+pub const DEFAULT_PITCH_SEMITONES: f32 = 3.0;
+
+/// Frame length for stretching the shifted voice back to length, in seconds
+const FRAME_SECS: f64 = 0.03;
+
+/// Period of the formant warp's variation, in seconds
+const WARP_PERIOD_SECS: f64 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnonymizeOptions {
+    /// Pitch shift in semitones; negative values lower the voice
+    pub pitch_semitones: f32,
+    /// Largest random change to the shift, in semitones either way
+    pub pitch_jitter_semitones: f32,
+    /// Depth of the time-varying formant warp, as a fraction of the shift
+    pub formant_variation: f32,
+    /// Seed for the random offset and warp phase, different for each recording
+    pub seed: u64,
+}
+
+impl AnonymizeOptions {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            pitch_semitones: DEFAULT_PITCH_SEMITONES,
+            pitch_jitter_semitones: 1.0,
+            formant_variation: 0.02,
+            seed,
+        }
+    }
+}
+
+/// Anonymize a WAV recording, returning a mono 16-bit WAV at the same rate
+pub fn anonymize_wav(audio_data: &[u8], options: &AnonymizeOptions) -> Result<Vec<u8>> {
+    let (samples, sample_rate) = read_wav_mono(audio_data)?;
+    write_wav_mono(&anonymize(&samples, sample_rate, options), sample_rate)
+}
+
+/// Shift and warp the voice in `samples`, keeping their length
+pub fn anonymize(samples: &[f32], sample_rate: u32, options: &AnonymizeOptions) -> Vec<f32> {
+    let mut random = SplitMix64(options.seed);
+    let semitones = options.pitch_semitones
+        + options.pitch_jitter_semitones * (random.next_unit() as f32 * 2.0 - 1.0);
+    let ratio = 2f64.powf(semitones as f64 / 12.0);
+
+    let warped = warp_resample(
+        samples,
+        ratio,
+        options.formant_variation as f64,
+        WARP_PERIOD_SECS * sample_rate as f64,
+        random.next_unit() * TAU,
+    );
+    let frame = ((FRAME_SECS * sample_rate as f64) as usize).max(16) & !1;
+    time_stretch(&warped, samples.len(), frame)
+}
+
+/// Read through the samples `ratio` times faster, with the rate varying
+/// sinusoidally by `variation` so the formants move over time
+fn warp_resample(samples: &[f32], ratio: f64, variation: f64, period: f64, phase: f64) -> Vec<f32> {
+    if samples.len() < 2 {
+        return samples.to_vec();
+    }
+
+    let last = (samples.len() - 1) as f64;
+    let mut output = Vec::with_capacity((samples.len() as f64 / ratio) as usize + 1);
+    let mut position = 0.0;
+    let mut step = 0.0;
+    while position < last {
+        let index = position as usize;
+        let fraction = (position - index as f64) as f32;
+        let current = samples[index];
+        let next = samples[index + 1];
+        output.push(current + (next - current) * fraction);
+
+        position += ratio * (1.0 + variation * (TAU * step / period + phase).sin());
+        step += 1.0;
+    }
+    output
+}
+
+/// Stretch `input` to `output_len` samples without changing its pitch
+///
+/// Uses waveform-similarity overlap-add: each Hann-windowed frame is taken
+/// from near its nominal position, wherever it best continues the previous
+/// frame, so periodic sounds stay in phase.
+fn time_stretch(input: &[f32], output_len: usize, frame: usize) -> Vec<f32> {
+    if input.len() < frame * 2 || output_len == 0 {
+        return stretch_linear(input, output_len);
+    }
+
+    let hop = frame / 2;
+    let tolerance = frame / 4;
+    let max_start = input.len() - frame;
+    let scale = input.len() as f64 / output_len as f64;
+    let window: Vec<f32> = (0..frame)
+        .map(|i| (0.5 - 0.5 * (TAU * i as f64 / frame as f64).cos()) as f32)
+        .collect();
+
+    let mut output = vec![0.0f32; output_len + frame];
+    let mut weights = vec![0.0f32; output_len + frame];
+    let mut previous: Option<usize> = None;
+    let mut out_pos = 0;
+    while out_pos < output_len {
+        let nominal = ((out_pos as f64 * scale) as usize).min(max_start);
+        let start = match previous {
+            Some(previous) => best_start(input, previous + hop, nominal, tolerance, hop, max_start),
+            None => nominal,
+        };
+
+        for (i, &weight) in window.iter().enumerate() {
+            output[out_pos + i] += input[start + i] * weight;
+            weights[out_pos + i] += weight;
+        }
+        previous = Some(start);
+        out_pos += hop;
+    }
+
+    output.truncate(output_len);
+    for (sample, weight) in output.iter_mut().zip(&weights) {
+        if *weight > 1e-3 {
+            *sample /= weight;
+        }
+    }
+    output
+}
+
+/// Start near `nominal` whose first `length` samples best match those at `natural`
+fn best_start(
+    input: &[f32],
+    natural: usize,
+    nominal: usize,
+    tolerance: usize,
+    length: usize,
+    max_start: usize,
+) -> usize {
+    if natural + length > input.len() {
+        return nominal;
+    }
+
+    let target = &input[natural..natural + length];
+    let low = nominal.saturating_sub(tolerance);
+    let high = (nominal + tolerance).min(max_start);
+    (low..=high)
+        .max_by(|&a, &b| {
+            let score = |start: usize| -> f32 {
+                target
+                    .iter()
+                    .zip(&input[start..start + length])
+                    .map(|(x, y)| x * y)
+                    .sum()
+            };
+            score(a).total_cmp(&score(b))
+        })
+        .unwrap_or(nominal)
+}
+
+/// Stretch by interpolation, for clips too short to split into frames
+fn stretch_linear(input: &[f32], output_len: usize) -> Vec<f32> {
+    if input.is_empty() {
+        return vec![0.0; output_len];
+    }
+
+    let scale = input.len() as f64 / output_len.max(1) as f64;
+    (0..output_len)
+        .map(|i| input[((i as f64 * scale) as usize).min(input.len() - 1)])
+        .collect()
+}
+
+/// Small seeded generator, so a recording's anonymization can be reproduced in tests
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`
+    fn next_unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+
+    fn sine(frequency: f64, secs: f64) -> Vec<f32> {
+        (0..(secs * SAMPLE_RATE as f64) as usize)
+            .map(|i| (TAU * frequency * i as f64 / SAMPLE_RATE as f64).sin() as f32 * 0.5)
+            .collect()
+    }
+
+    /// Frequency of a tone, from its rising zero crossings
+    fn frequency(samples: &[f32]) -> f64 {
+        let crossings = samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count();
+        crossings as f64 * SAMPLE_RATE as f64 / samples.len() as f64
+    }
+
+    fn fixed(semitones: f32) -> AnonymizeOptions {
+        AnonymizeOptions {
+            pitch_semitones: semitones,
+            pitch_jitter_semitones: 0.0,
+            formant_variation: 0.0,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_anonymize_keeps_length_and_shifts_pitch() {
+        let tone = sine(200.0, 1.0);
+
+        let raised = anonymize(&tone, SAMPLE_RATE, &fixed(4.0));
+        assert_eq!(raised.len(), tone.len());
+        let expected = 200.0 * 2f64.powf(4.0 / 12.0);
+        assert!((frequency(&raised) - expected).abs() < expected * 0.05);
+
+        let lowered = anonymize(&tone, SAMPLE_RATE, &fixed(-3.0));
+        assert_eq!(lowered.len(), tone.len());
+        let expected = 200.0 * 2f64.powf(-3.0 / 12.0);
+        assert!((frequency(&lowered) - expected).abs() < expected * 0.05);
+    }
+
+    #[test]
+    fn test_anonymize_varies_with_seed() {
+        let tone = sine(220.0, 0.5);
+        let first = anonymize(&tone, SAMPLE_RATE, &AnonymizeOptions::new(1));
+        let again = anonymize(&tone, SAMPLE_RATE, &AnonymizeOptions::new(1));
+        let other = anonymize(&tone, SAMPLE_RATE, &AnonymizeOptions::new(2));
+
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_anonymize_short_and_empty_clips() {
+        assert!(anonymize(&[], SAMPLE_RATE, &AnonymizeOptions::new(1)).is_empty());
+        let short = sine(200.0, 0.01);
+        assert_eq!(
+            anonymize(&short, SAMPLE_RATE, &AnonymizeOptions::new(1)).len(),
+            short.len()
+        );
+    }
+
+    #[test]
+    fn test_anonymize_wav() {
+        let wav = write_wav_mono(&sine(200.0, 0.5), SAMPLE_RATE).unwrap();
+        let (samples, sample_rate) =
+            read_wav_mono(&anonymize_wav(&wav, &AnonymizeOptions::new(3)).unwrap()).unwrap();
+
+        assert_eq!(sample_rate, SAMPLE_RATE);
+        assert_eq!(samples.len(), 8000);
+    }
+}
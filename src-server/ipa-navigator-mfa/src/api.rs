@@ -2,11 +2,12 @@
 
 use anyhow::{Context, Result};
 use std::fs;
-use tempfile::{TempDir, tempdir};
+use tempfile::TempDir;
 use uuid::Uuid;
 
 use crate::docker::{MfaDialect, run_mfa_align};
 use crate::scoring::{PronunciationAssessment, score_phoneme_accuracy};
+use crate::storage::STORAGE;
 
 /// Represents an MFA job to process audio
 pub struct MfaJob {
@@ -31,9 +32,12 @@ impl MfaJob {
     ///
     /// # Returns
     /// A new MfaJob instance
+    #[tracing::instrument(name = "mfa.corpus", skip_all)]
     pub fn new(audio_data: &[u8], transcript: &str, dialect: MfaDialect) -> Result<Self> {
-        // Create a temporary directory for this job
-        let job_dir = tempdir().context("Failed to create temporary directory for MFA job")?;
+        // Create a temporary directory for this job inside the directory mounted into the container
+        let job_dir = STORAGE
+            .create_job_dir("job-")
+            .context("Failed to create temporary directory for MFA job")?;
 
         let job_id = Uuid::new_v4().to_string();
 
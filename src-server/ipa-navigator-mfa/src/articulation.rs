@@ -0,0 +1,178 @@
+//! Articulation hints and example words for the interactive IPA chart
+
+use anyhow::Result;
+
+use crate::docker::MfaDialect;
+use crate::localization::{Locale, describe_phoneme, localize};
+use crate::phoneme::{Backness, Height, Manner, PhonemeFeatures, Place, parse_ipa};
+use crate::scoring::cached_dictionary;
+
+/// Maximum number of example words listed per dialect
+pub const MAX_EXAMPLE_WORDS: usize = 5;
+
+/// Common words searched for examples, covering every sound in the chart
+pub(crate) const EXAMPLE_CANDIDATES: &[&str] = &[
+    "pen", "spin", "cup", "happy", "bed", "cab", "baby", "ten", "stop", "bat", "day", "dog", "red",
+    "cat", "key", "back", "go", "bag", "big", "man", "come", "summer", "no", "sun", "dinner",
+    "sing", "long", "finger", "think", "fish", "off", "phone", "van", "very", "love", "thin",
+    "bath", "this", "mother", "see", "bus", "city", "zoo", "rose", "ship", "wish", "sure",
+    "measure", "vision", "hat", "hello", "church", "watch", "judge", "jump", "run", "sorry", "yes",
+    "you", "we", "one", "light", "leaf", "feel", "ball", "sea", "green", "sit", "kit", "get",
+    "head", "trap", "apple", "about", "sofa", "strut", "butter", "letter", "bird", "nurse", "word",
+    "food", "blue", "put", "good", "book", "thought", "law", "north", "hot", "lot", "father",
+    "start", "palm", "dance", "price", "my", "now", "mouth", "face", "goat", "home", "choice",
+    "boy", "near", "square", "car", "here", "there", "water", "world",
+];
+
+/// A word containing a sound, with its dictionary pronunciation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExampleWord {
+    pub word: String,
+    pub phonemes: Vec<String>,
+}
+
+/// Everything the IPA chart shows for one symbol
+#[derive(Debug, Clone)]
+pub struct ArticulationInfo {
+    pub symbol: String,
+    pub features: PhonemeFeatures,
+    /// Conventional description, e.g. "voiceless dental fricative"
+    pub description: String,
+    pub place: Option<(Place, String)>,
+    pub manner: Option<(Manner, String)>,
+    pub height: Option<(Height, String)>,
+    pub backness: Option<(Backness, String)>,
+}
+
+/// Look up the articulation of a symbol, which may carry diacritics
+pub fn articulation_info(symbol: &str) -> Option<ArticulationInfo> {
+    articulation_info_in(symbol, Locale::English)
+}
+
+/// Look up the articulation of a symbol, described and explained in the locale
+pub fn articulation_info_in(symbol: &str, locale: Locale) -> Option<ArticulationInfo> {
+    let parsed = parse_ipa(symbol)?;
+    let features = parsed.features.clone();
+
+    Some(ArticulationInfo {
+        symbol: symbol.to_string(),
+        description: describe_phoneme(&parsed, locale),
+        place: features.place().map(|p| (p, place_hint(p, locale))),
+        manner: features.manner().map(|m| (m, manner_hint(m, locale))),
+        height: features.height().map(|h| (h, height_hint(h, locale))),
+        backness: features.backness().map(|b| (b, backness_hint(b, locale))),
+        features,
+    })
+}
+
+/// Common words whose pronunciation in the dialect's dictionary contains the symbol
+pub fn example_words(symbol: &str, dialect: MfaDialect) -> Result<Vec<ExampleWord>> {
+    let dictionary = cached_dictionary(dialect)?;
+
+    let examples = EXAMPLE_CANDIDATES
+        .iter()
+        .filter_map(|&word| {
+            let phonemes = dictionary.get(word)?;
+            phonemes
+                .iter()
+                .any(|phone| matches_symbol(phone, symbol))
+                .then(|| ExampleWord {
+                    word: word.to_string(),
+                    phonemes: phonemes.clone(),
+                })
+        })
+        .take(MAX_EXAMPLE_WORDS)
+        .collect();
+
+    Ok(examples)
+}
+
+/// Whether a dictionary phone is a realisation of a chart symbol
+///
+/// MFA dictionaries write aspirated, palatalised, and labialised consonants,
+/// long vowels, and diphthongs with their own symbols.
+pub(crate) fn matches_symbol(phone: &str, symbol: &str) -> bool {
+    if phone == symbol {
+        return true;
+    }
+
+    match (parse_ipa(phone), parse_ipa(symbol)) {
+        (Some(phone), Some(symbol)) => phone.base == symbol.base,
+        _ => false,
+    }
+}
+
+/// How to reach a place of articulation
+pub fn place_hint(place: Place, locale: Locale) -> String {
+    localize(locale, &format!("hint-{}", place.as_str()), &[])
+}
+
+/// How to produce a manner of articulation
+pub fn manner_hint(manner: Manner, locale: Locale) -> String {
+    localize(locale, &format!("hint-{}", manner.as_str()), &[])
+}
+
+/// Where to hold the tongue for a vowel height
+pub fn height_hint(height: Height, locale: Locale) -> String {
+    localize(locale, &format!("hint-{}", height.as_str()), &[])
+}
+
+/// Where to hold the tongue for a vowel backness
+pub fn backness_hint(backness: Backness, locale: Locale) -> String {
+    localize(locale, &format!("hint-{}", backness.as_str()), &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_articulation_info() {
+        let info = articulation_info("θ").expect("θ should be in the chart");
+        assert_eq!(info.description, "voiceless dental fricative");
+        assert_eq!(info.place.map(|(p, _)| p), Some(Place::Dental));
+        assert_eq!(info.manner.map(|(m, _)| m), Some(Manner::Fricative));
+        assert!(info.height.is_none());
+
+        let aspirated = articulation_info("pʰ").expect("Diacritics should be understood");
+        assert_eq!(
+            aspirated.description,
+            "aspirated voiceless bilabial plosive"
+        );
+
+        let malay = articulation_info_in("θ", Locale::Malay).expect("θ should be in the chart");
+        assert_eq!(malay.description, "geseran gigi tak bersuara");
+        assert_eq!(
+            malay.place.map(|(_, hint)| hint).as_deref(),
+            Some("letakkan lidah di antara gigi")
+        );
+
+        assert!(articulation_info("unknown").is_none());
+        assert!(articulation_info("xyz").is_none());
+    }
+
+    #[test]
+    fn test_matches_symbol() {
+        assert!(matches_symbol("pʰ", "p"));
+        assert!(matches_symbol("iː", "i"));
+        assert!(matches_symbol("aj", "aɪ"));
+        assert!(!matches_symbol("p", "b"));
+    }
+
+    #[test]
+    fn test_example_words_per_dialect() -> Result<()> {
+        let us = example_words("ɹ", MfaDialect::AmericanEnglish)?;
+        let uk = example_words("ɹ", MfaDialect::BritishEnglish)?;
+
+        assert!(!us.is_empty() && us.len() <= MAX_EXAMPLE_WORDS);
+        assert!(
+            us.iter()
+                .all(|e| e.phonemes.iter().any(|p| matches_symbol(p, "ɹ")))
+        );
+
+        // "car" keeps its /ɹ/ only in the rhotic accent
+        let has_car = |examples: &[ExampleWord]| examples.iter().any(|e| e.word == "car");
+        assert!(!has_car(&uk));
+        Ok(())
+    }
+}
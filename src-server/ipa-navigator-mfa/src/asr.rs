@@ -0,0 +1,412 @@
+//! Optional speech recognition pass that checks a recording matches its prompt
+//!
+//! Scoring a recording of a different sentence produces meaningless phoneme scores,
+//! so when a transcriber is configured the upload is transcribed first and compared
+//! word by word against the expected transcript.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use crate::audio::{read_wav_mono, resample_linear, write_wav_mono};
+use crate::constants::ASSETS_PATH;
+
+/// Sample rate expected by whisper.cpp
+const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Default minimum word similarity for a recording to count as the right sentence
+pub const DEFAULT_MATCH_THRESHOLD: f64 = 0.6;
+
+/// Speech recognition backend producing a plain text transcript
+pub trait Transcriber: Send + Sync {
+    /// Short name of the backend, used in logs
+    fn name(&self) -> &'static str;
+
+    /// Transcribe a WAV recording
+    fn transcribe(&self, audio_data: &[u8]) -> Result<String>;
+}
+
+/// Available speech recognition backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsrBackend {
+    /// No verification pass (default)
+    None,
+    /// whisper.cpp command line tool
+    WhisperCpp,
+}
+
+impl AsrBackend {
+    /// Parse a backend name, as used by the `ASR_BACKEND` variable
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "" | "none" | "off" => Some(AsrBackend::None),
+            "whisper" | "whisper-cpp" | "whisper.cpp" => Some(AsrBackend::WhisperCpp),
+            _ => None,
+        }
+    }
+}
+
+/// Settings for the verification pass
+#[derive(Debug, Clone)]
+pub struct AsrConfig {
+    pub backend: AsrBackend,
+    /// Path to the whisper.cpp CLI binary
+    pub whisper_bin: PathBuf,
+    /// Path to the ggml whisper model
+    pub whisper_model: PathBuf,
+    /// Minimum word similarity below which the recording is flagged
+    pub match_threshold: f64,
+}
+
+impl AsrConfig {
+    /// Read `ASR_BACKEND`, `ASR_WHISPER_BIN`, `ASR_WHISPER_MODEL`, and `ASR_MATCH_THRESHOLD`
+    pub fn from_env() -> Self {
+        let backend = match env::var("ASR_BACKEND") {
+            Ok(name) => AsrBackend::parse(&name).unwrap_or_else(|| {
+                tracing::warn!("Unknown ASR_BACKEND '{}', disabling verification", name);
+                AsrBackend::None
+            }),
+            Err(_) => AsrBackend::None,
+        };
+
+        let whisper_bin = env::var("ASR_WHISPER_BIN")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("whisper-cli"));
+
+        let whisper_model = env::var("ASR_WHISPER_MODEL")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                PathBuf::from(format!("{}/Whisper/ggml-base.en.bin", *ASSETS_PATH))
+            });
+
+        let match_threshold = env::var("ASR_MATCH_THRESHOLD")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|t: &f64| (0.0..=1.0).contains(t))
+            .unwrap_or(DEFAULT_MATCH_THRESHOLD);
+
+        Self {
+            backend,
+            whisper_bin,
+            whisper_model,
+            match_threshold,
+        }
+    }
+}
+
+/// Transcriber shelling out to the whisper.cpp CLI
+pub struct WhisperCppTranscriber {
+    bin: PathBuf,
+    model: PathBuf,
+}
+
+impl WhisperCppTranscriber {
+    /// Create a transcriber from the configured binary and model
+    pub fn new(config: &AsrConfig) -> Result<Self> {
+        if !config.whisper_model.exists() {
+            return Err(anyhow::anyhow!(
+                "Whisper model not found at path: {}",
+                config.whisper_model.display()
+            ));
+        }
+
+        Ok(Self {
+            bin: config.whisper_bin.clone(),
+            model: config.whisper_model.clone(),
+        })
+    }
+}
+
+impl Transcriber for WhisperCppTranscriber {
+    fn name(&self) -> &'static str {
+        "whisper-cpp"
+    }
+
+    fn transcribe(&self, audio_data: &[u8]) -> Result<String> {
+        // whisper.cpp only accepts 16 kHz input
+        let (mono, sample_rate) = read_wav_mono(audio_data)?;
+        let samples = resample_linear(&mono, sample_rate, WHISPER_SAMPLE_RATE);
+        let wav = write_wav_mono(&samples, WHISPER_SAMPLE_RATE)?;
+
+        let dir = tempfile::tempdir().context("Failed to create temporary directory for ASR")?;
+        let audio_path = dir.path().join("input.wav");
+        fs::write(&audio_path, wav)
+            .with_context(|| format!("Failed to write audio file to {:?}", audio_path))?;
+
+        let output = Command::new(&self.bin)
+            .arg("-m")
+            .arg(&self.model)
+            .arg("-f")
+            .arg(&audio_path)
+            .args(["--no-timestamps", "--no-prints", "-l", "en"])
+            .output()
+            .with_context(|| format!("Failed to run whisper.cpp at {:?}", self.bin))?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "whisper.cpp failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let transcript = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(transcript)
+    }
+}
+
+/// Configured transcriber, `None` when verification is disabled
+type SharedTranscriber = Option<Arc<dyn Transcriber>>;
+
+// Transcriber selected at startup, initialized lazily on first verification
+static TRANSCRIBER: LazyLock<Mutex<Option<SharedTranscriber>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Get the configured transcriber, or `None` when verification is disabled
+pub fn get_transcriber() -> Result<SharedTranscriber> {
+    let mut guard = TRANSCRIBER
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire transcriber lock"))?;
+
+    if let Some(transcriber) = guard.as_ref() {
+        return Ok(transcriber.clone());
+    }
+
+    let config = AsrConfig::from_env();
+    let transcriber: SharedTranscriber = match config.backend {
+        AsrBackend::None => None,
+        AsrBackend::WhisperCpp => Some(Arc::new(WhisperCppTranscriber::new(&config)?)),
+    };
+
+    if let Some(transcriber) = &transcriber {
+        tracing::info!("Verifying transcripts with {}", transcriber.name());
+    }
+
+    *guard = Some(transcriber.clone());
+    Ok(transcriber)
+}
+
+/// How a word of the recognised transcript relates to the expected one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordDiffKind {
+    Match,
+    Substitution,
+    /// Word heard but not in the expected transcript
+    Insertion,
+    /// Expected word that was not heard
+    Deletion,
+}
+
+impl WordDiffKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WordDiffKind::Match => "match",
+            WordDiffKind::Substitution => "substitution",
+            WordDiffKind::Insertion => "insertion",
+            WordDiffKind::Deletion => "deletion",
+        }
+    }
+}
+
+/// One step of the word alignment between expected and recognised transcripts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordDiff {
+    pub kind: WordDiffKind,
+    pub expected: Option<String>,
+    pub heard: Option<String>,
+}
+
+/// Outcome of comparing the recognised transcript against the expected one
+#[derive(Debug, Clone)]
+pub struct TranscriptCheck {
+    /// What the recogniser heard
+    pub heard: String,
+    /// Word-level similarity between 0.0 and 1.0
+    pub similarity: f64,
+    /// Whether the similarity fell below the threshold
+    pub wrong_sentence: bool,
+    pub diff: Vec<WordDiff>,
+}
+
+/// Transcribe a recording and compare it against the expected transcript
+///
+/// Returns `None` when no transcriber is configured.
+pub fn verify_transcript(audio_data: &[u8], expected: &str) -> Result<Option<TranscriptCheck>> {
+    let Some(transcriber) = get_transcriber()? else {
+        return Ok(None);
+    };
+
+    let heard = transcriber.transcribe(audio_data)?;
+    let threshold = AsrConfig::from_env().match_threshold;
+
+    Ok(Some(compare_transcripts(expected, &heard, threshold)))
+}
+
+/// Compare two transcripts word by word, ignoring case and punctuation
+pub fn compare_transcripts(expected: &str, heard: &str, threshold: f64) -> TranscriptCheck {
+    let expected_words = normalize_words(expected);
+    let heard_words = normalize_words(heard);
+
+    let diff = diff_words(&expected_words, &heard_words);
+    let edits = diff
+        .iter()
+        .filter(|d| d.kind != WordDiffKind::Match)
+        .count();
+    let longest = expected_words.len().max(heard_words.len());
+
+    let similarity = if longest == 0 {
+        1.0
+    } else {
+        1.0 - edits as f64 / longest as f64
+    };
+
+    TranscriptCheck {
+        heard: heard.trim().to_string(),
+        similarity,
+        wrong_sentence: similarity < threshold,
+        diff,
+    }
+}
+
+/// Lowercase words with surrounding punctuation removed
+fn normalize_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '\'')
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Minimum edit alignment between two word sequences
+fn diff_words(expected: &[String], heard: &[String]) -> Vec<WordDiff> {
+    let (n, m) = (expected.len(), heard.len());
+
+    // distance[i][j] is the edit distance between expected[..i] and heard[..j]
+    let mut distance = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in distance.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in distance[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=n {
+        for j in 1..=m {
+            let cost = usize::from(expected[i - 1] != heard[j - 1]);
+            distance[i][j] = (distance[i - 1][j - 1] + cost)
+                .min(distance[i - 1][j] + 1)
+                .min(distance[i][j - 1] + 1);
+        }
+    }
+
+    let mut diff = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let cost = usize::from(expected[i - 1] != heard[j - 1]);
+            if distance[i][j] == distance[i - 1][j - 1] + cost {
+                diff.push(WordDiff {
+                    kind: if cost == 0 {
+                        WordDiffKind::Match
+                    } else {
+                        WordDiffKind::Substitution
+                    },
+                    expected: Some(expected[i - 1].clone()),
+                    heard: Some(heard[j - 1].clone()),
+                });
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+
+        if i > 0 && distance[i][j] == distance[i - 1][j] + 1 {
+            diff.push(WordDiff {
+                kind: WordDiffKind::Deletion,
+                expected: Some(expected[i - 1].clone()),
+                heard: None,
+            });
+            i -= 1;
+        } else {
+            diff.push(WordDiff {
+                kind: WordDiffKind::Insertion,
+                expected: None,
+                heard: Some(heard[j - 1].clone()),
+            });
+            j -= 1;
+        }
+    }
+
+    diff.reverse();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_parse() {
+        assert_eq!(AsrBackend::parse("none"), Some(AsrBackend::None));
+        assert_eq!(
+            AsrBackend::parse(" Whisper-CPP "),
+            Some(AsrBackend::WhisperCpp)
+        );
+        assert_eq!(AsrBackend::parse("vosk"), None);
+    }
+
+    #[test]
+    fn test_matching_transcript_ignores_case_and_punctuation() {
+        let check = compare_transcripts("The quick brown fox.", "the quick, brown fox", 0.6);
+        assert_eq!(check.similarity, 1.0);
+        assert!(!check.wrong_sentence);
+        assert!(check.diff.iter().all(|d| d.kind == WordDiffKind::Match));
+    }
+
+    #[test]
+    fn test_small_slip_is_not_flagged() {
+        let check = compare_transcripts("the quick brown fox", "the quick brown box", 0.6);
+        assert_eq!(check.similarity, 0.75);
+        assert!(!check.wrong_sentence);
+        assert_eq!(check.diff[3].kind, WordDiffKind::Substitution);
+        assert_eq!(check.diff[3].expected.as_deref(), Some("fox"));
+        assert_eq!(check.diff[3].heard.as_deref(), Some("box"));
+    }
+
+    #[test]
+    fn test_different_sentence_is_flagged() {
+        let check = compare_transcripts("the quick brown fox", "she sells sea shells", 0.6);
+        assert_eq!(check.similarity, 0.0);
+        assert!(check.wrong_sentence);
+    }
+
+    #[test]
+    fn test_diff_reports_insertions_and_deletions() {
+        let check = compare_transcripts("hello big world", "hello world", 0.0);
+        let kinds: Vec<_> = check.diff.iter().map(|d| d.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                WordDiffKind::Match,
+                WordDiffKind::Deletion,
+                WordDiffKind::Match
+            ]
+        );
+
+        let check = compare_transcripts("hello world", "hello world again", 0.0);
+        let last = check.diff.last().unwrap();
+        assert_eq!(last.kind, WordDiffKind::Insertion);
+        assert_eq!(last.heard.as_deref(), Some("again"));
+    }
+}
@@ -0,0 +1,170 @@
+//! WAV decoding and encoding helpers shared by the in-process analysis backends
+
+use anyhow::{Context, Result};
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::io::Cursor;
+
+/// Sample rate of clips prepared for playback, matching the Kokoro output rate
+pub const PLAYBACK_SAMPLE_RATE: u32 = 24000;
+
+/// Peak amplitude clips are normalized to for playback, leaving some headroom
+pub const PLAYBACK_PEAK: f32 = 0.9;
+
+/// Decode WAV bytes into mono samples in `[-1, 1]`, returning them with the sample rate
+pub fn read_wav_mono(audio_data: &[u8]) -> Result<(Vec<f32>, u32)> {
+    let reader =
+        WavReader::new(Cursor::new(audio_data)).context("Audio is not a valid WAV file")?;
+    let spec = reader.spec();
+    let channels = spec.channels.max(1) as usize;
+
+    let interleaved: Vec<f32> = match spec.sample_format {
+        SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .collect::<Result<_, _>>()
+            .context("Failed to read WAV samples")?,
+        SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+                .context("Failed to read WAV samples")?
+        }
+    };
+
+    let mono = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    Ok((mono, spec.sample_rate))
+}
+
+/// Resample with linear interpolation
+pub fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio).floor() as usize;
+
+    (0..out_len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position.floor() as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index.min(samples.len() - 1)];
+            let next = samples[(index + 1).min(samples.len() - 1)];
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+/// Encode mono samples as a 16-bit PCM WAV file
+pub fn write_wav_mono(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    let mut buffer = Vec::new();
+    let mut writer =
+        WavWriter::new(Cursor::new(&mut buffer), spec).context("Failed to create WAV writer")?;
+
+    for &sample in samples {
+        writer
+            .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .context("Failed to write WAV sample")?;
+    }
+
+    writer.finalize().context("Failed to finalize WAV file")?;
+    Ok(buffer)
+}
+
+/// Remove any DC offset and scale samples so the loudest one reaches `peak`
+///
+/// Silent input is left untouched.
+pub fn normalize_peak(samples: &mut [f32], peak: f32) {
+    if samples.is_empty() {
+        return;
+    }
+
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+    for sample in samples.iter_mut() {
+        *sample -= mean;
+    }
+
+    let loudest = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    if loudest > f32::EPSILON {
+        let gain = peak / loudest;
+        for sample in samples.iter_mut() {
+            *sample *= gain;
+        }
+    }
+}
+
+/// Re-encode a WAV clip as mono 16-bit PCM at the playback rate and level
+///
+/// Recordings and synthesized references go through the same normalization
+/// so they can be compared side by side at a matching volume.
+pub fn normalize_wav(audio_data: &[u8]) -> Result<Vec<u8>> {
+    let (mono, sample_rate) = read_wav_mono(audio_data)?;
+    let mut samples = resample_linear(&mono, sample_rate, PLAYBACK_SAMPLE_RATE);
+    normalize_peak(&mut samples, PLAYBACK_PEAK);
+    write_wav_mono(&samples, PLAYBACK_SAMPLE_RATE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample_linear() {
+        let samples = vec![0.0, 1.0, 2.0, 3.0];
+        assert_eq!(resample_linear(&samples, 32000, 16000), vec![0.0, 2.0]);
+        assert_eq!(resample_linear(&samples, 16000, 16000), samples);
+    }
+
+    #[test]
+    fn test_wav_round_trip() {
+        let samples = vec![0.0, 0.5, -0.5, 0.25];
+        let wav = write_wav_mono(&samples, 16000).unwrap();
+
+        let (decoded, sample_rate) = read_wav_mono(&wav).unwrap();
+        assert_eq!(sample_rate, 16000);
+        assert_eq!(decoded.len(), samples.len());
+        for (a, b) in decoded.iter().zip(&samples) {
+            assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_normalize_peak() {
+        let mut samples = vec![0.1, 0.3, 0.1, -0.1];
+        normalize_peak(&mut samples, 0.9);
+
+        let loudest = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        assert!((loudest - 0.9).abs() < 1e-6);
+        assert!(samples.iter().sum::<f32>().abs() < 1e-6);
+
+        let mut silence = vec![0.0; 4];
+        normalize_peak(&mut silence, 0.9);
+        assert_eq!(silence, vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_normalize_wav() {
+        let samples: Vec<f32> = (0..1600).map(|i| (i as f32 / 10.0).sin() * 0.2).collect();
+        let wav = write_wav_mono(&samples, 16000).unwrap();
+
+        let (normalized, sample_rate) = read_wav_mono(&normalize_wav(&wav).unwrap()).unwrap();
+        assert_eq!(sample_rate, PLAYBACK_SAMPLE_RATE);
+        assert_eq!(normalized.len(), 2400);
+
+        let loudest = normalized.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        assert!((loudest - PLAYBACK_PEAK).abs() < 1e-2, "{}", loudest);
+    }
+}
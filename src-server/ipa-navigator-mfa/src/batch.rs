@@ -0,0 +1,358 @@
+//! Batching layer that aligns jobs arriving close together as a single MFA corpus
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::confidence::{LIKELIHOOD_SCALE, alignment_scores, apply_confidence};
+use crate::docker::{MfaDialect, run_mfa_align_corpus};
+use crate::mfa_parser::{MfaSegment, find_alignment, parse_alignment};
+use crate::scoring::{PronunciationAssessment, ScoringRubric, score_segments};
+use crate::storage::{QuotaExceeded, STORAGE, current_tenant, current_user, with_owner};
+
+/// Shared batch aligner configured from the environment
+pub static BATCH_ALIGNER: LazyLock<BatchAligner> =
+    LazyLock::new(|| BatchAligner::new(BatchConfig::from_env()));
+
+/// Settings controlling how jobs are grouped into corpora
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// How long to wait for more jobs after the first one of a batch arrives
+    pub window: Duration,
+    /// Maximum number of jobs aligned in one MFA run
+    pub max_batch_size: usize,
+    /// Maximum number of MFA runs in flight at once
+    pub max_concurrent_batches: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(250),
+            max_batch_size: 16,
+            max_concurrent_batches: 2,
+        }
+    }
+}
+
+impl BatchConfig {
+    /// Read `MFA_BATCH_WINDOW_MS`, `MFA_BATCH_MAX_SIZE`, and `MFA_BATCH_CONCURRENCY`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let window = env::var("MFA_BATCH_WINDOW_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.window);
+
+        let max_batch_size = env::var("MFA_BATCH_MAX_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(defaults.max_batch_size);
+
+        let max_concurrent_batches = env::var("MFA_BATCH_CONCURRENCY")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(defaults.max_concurrent_batches);
+
+        Self {
+            window,
+            max_batch_size,
+            max_concurrent_batches,
+        }
+    }
+}
+
+/// A queued alignment job waiting for its batch to run
+struct BatchJob {
+    audio_data: Vec<u8>,
+    transcript: String,
+    dialect: MfaDialect,
+    reply: Sender<Result<Vec<MfaSegment>>>,
+    /// Span of the request that queued the job, so batch work can be traced back to it
+    span: tracing::Span,
+    /// Tenant of the request, whose jobs are never aligned in another tenant's corpus
+    tenant: Option<String>,
+    /// Signed-in user who made the request
+    user: Option<String>,
+}
+
+/// Groups concurrent assessment requests into shared MFA runs
+pub struct BatchAligner {
+    sender: Mutex<Sender<BatchJob>>,
+}
+
+impl BatchAligner {
+    /// Start the background collector thread
+    pub fn new(config: BatchConfig) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("mfa-batcher".to_string())
+            .spawn(move || collect_batches(receiver, config))
+            .expect("Failed to spawn MFA batch collector thread");
+
+        Self {
+            sender: Mutex::new(sender),
+        }
+    }
+
+    /// Queue a job and block until its batch has been aligned and scored
+    pub fn assess(
+        &self,
+        audio_data: &[u8],
+        transcript: &str,
+        dialect: MfaDialect,
+        rubric: &ScoringRubric,
+    ) -> Result<PronunciationAssessment> {
+        let segments = self.align(audio_data, transcript, dialect)?;
+        Ok(score_segments(&segments, transcript, dialect, rubric)?
+            .with_audio_analysis(audio_data, &segments))
+    }
+
+    /// Queue a job and block until its batch has been aligned
+    pub fn align(
+        &self,
+        audio_data: &[u8],
+        transcript: &str,
+        dialect: MfaDialect,
+    ) -> Result<Vec<MfaSegment>> {
+        let (reply, result) = mpsc::channel();
+
+        let job = BatchJob {
+            audio_data: audio_data.to_vec(),
+            transcript: transcript.to_string(),
+            dialect,
+            reply,
+            span: tracing::Span::current(),
+            tenant: current_tenant(),
+            user: current_user(),
+        };
+
+        self.sender
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire batch queue lock"))?
+            .send(job)
+            .map_err(|_| anyhow::anyhow!("MFA batch collector has stopped"))?;
+
+        result
+            .recv()
+            .context("MFA batch was dropped before producing a result")?
+    }
+}
+
+/// Collect jobs into batches and hand each batch to a worker thread
+fn collect_batches(receiver: Receiver<BatchJob>, config: BatchConfig) {
+    // Tokens bound the number of batches aligning at once
+    let (token_tx, token_rx) = mpsc::sync_channel::<()>(config.max_concurrent_batches);
+    let token_rx = Arc::new(Mutex::new(token_rx));
+
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + config.window;
+
+        while batch.len() < config.max_batch_size {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match receiver.recv_timeout(remaining) {
+                Ok(job) => batch.push(job),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        // One MFA run per dictionary and tenant
+        let mut by_corpus: HashMap<(&'static str, Option<String>), Vec<BatchJob>> = HashMap::new();
+        for job in batch {
+            by_corpus
+                .entry((job.dialect.dictionary_name(), job.tenant.clone()))
+                .or_default()
+                .push(job);
+        }
+
+        for jobs in by_corpus.into_values() {
+            spawn_batch(jobs, &token_tx, &token_rx);
+        }
+    }
+}
+
+/// Run one batch on its own thread once a concurrency token is available
+fn spawn_batch(
+    jobs: Vec<BatchJob>,
+    token_tx: &SyncSender<()>,
+    token_rx: &Arc<Mutex<Receiver<()>>>,
+) {
+    // Blocks while `max_concurrent_batches` batches are already running
+    if token_tx.send(()).is_err() {
+        return;
+    }
+
+    let token_rx = token_rx.clone();
+    thread::spawn(move || {
+        run_batch(jobs);
+        if let Ok(rx) = token_rx.lock() {
+            let _ = rx.recv();
+        }
+    });
+}
+
+/// Write every job into one corpus, align it once, and reply to each job
+fn run_batch(jobs: Vec<BatchJob>) {
+    let Some(dialect) = jobs.first().map(|job| job.dialect) else {
+        return;
+    };
+
+    tracing::debug!("Aligning MFA batch of {} jobs", jobs.len());
+
+    // The batch serves several requests, so it links to each of their spans
+    let batch_span = tracing::info_span!("mfa.batch", jobs = jobs.len());
+    for job in &jobs {
+        batch_span.follows_from(&job.span);
+    }
+    let _batch = batch_span.enter();
+
+    let corpus = match prepare_corpus(&jobs) {
+        Ok(corpus) => corpus,
+        Err(e) => {
+            // Kept as its own error so callers can tell a full disk from a failed alignment
+            let quota = e.downcast_ref::<QuotaExceeded>().copied();
+            let message = format!("{:#}", e);
+            for job in jobs {
+                let error = match quota {
+                    Some(quota) => anyhow::Error::new(quota),
+                    None => anyhow::anyhow!(message.clone()),
+                };
+                let _ = job.reply.send(Err(error));
+            }
+            return;
+        }
+    };
+
+    if let Err(e) = run_mfa_align_corpus(corpus.dir.path(), dialect) {
+        let message = format!("{:#}", e);
+        for job in jobs {
+            let _ = job.reply.send(Err(anyhow::anyhow!(message.clone())));
+        }
+        return;
+    }
+
+    let scores = alignment_scores(corpus.dir.path());
+    for (job, job_id) in jobs.into_iter().zip(&corpus.job_ids) {
+        let result = job
+            .span
+            .in_scope(|| split_result(corpus.dir.path(), job_id, &scores));
+        let _ = job.reply.send(result);
+    }
+}
+
+/// Corpus directory holding every job of a batch
+struct Corpus {
+    dir: tempfile::TempDir,
+    job_ids: Vec<String>,
+}
+
+/// Write each job's audio and transcript into a fresh corpus directory
+#[tracing::instrument(name = "mfa.corpus", skip_all, fields(jobs = jobs.len()))]
+fn prepare_corpus(jobs: &[BatchJob]) -> Result<Corpus> {
+    // Jobs are batched by tenant, so the batch's directory is under theirs;
+    // it is only recorded as a user's if every job is theirs
+    let tenant = jobs.first().and_then(|job| job.tenant.as_deref());
+    let user = jobs.first().and_then(|job| job.user.as_deref());
+    let user = user.filter(|&user| jobs.iter().all(|job| job.user.as_deref() == Some(user)));
+    let dir = with_owner(tenant, user, || STORAGE.create_job_dir("batch-"))
+        .context("Failed to create corpus directory for MFA batch")?;
+
+    let mut job_ids = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let job_id = Uuid::new_v4().to_string();
+
+        let audio_path = dir.path().join(format!("{}.wav", job_id));
+        fs::write(&audio_path, &job.audio_data)
+            .with_context(|| format!("Failed to write audio file to {:?}", audio_path))?;
+
+        let transcript_path = dir.path().join(format!("{}.lab", job_id));
+        fs::write(&transcript_path, &job.transcript)
+            .with_context(|| format!("Failed to write transcript file to {:?}", transcript_path))?;
+
+        job_ids.push(job_id);
+    }
+
+    Ok(Corpus { dir, job_ids })
+}
+
+/// Parse the alignment MFA produced for one job of the corpus, with the
+/// confidence of its alignment score among `scores`, if MFA gave one
+fn split_result(
+    corpus_dir: &Path,
+    job_id: &str,
+    scores: &HashMap<String, f64>,
+) -> Result<Vec<MfaSegment>> {
+    let Some(alignment_path) = find_alignment(corpus_dir, job_id) else {
+        return Err(anyhow::anyhow!(
+            "MFA could not align the recording; check that it matches the transcript"
+        ));
+    };
+
+    let mut segments = parse_alignment(&alignment_path)?;
+    if let Some(&log_likelihood) = scores.get(job_id) {
+        apply_confidence(&mut segments, log_likelihood, &LIKELIHOOD_SCALE);
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_corpus_writes_one_pair_per_job() {
+        let (reply, _result) = mpsc::channel();
+        let jobs = vec![
+            BatchJob {
+                audio_data: b"first".to_vec(),
+                transcript: "hello".to_string(),
+                dialect: MfaDialect::AmericanEnglish,
+                reply: reply.clone(),
+                span: tracing::Span::none(),
+                tenant: None,
+                user: None,
+            },
+            BatchJob {
+                audio_data: b"second".to_vec(),
+                transcript: "world".to_string(),
+                dialect: MfaDialect::AmericanEnglish,
+                reply,
+                span: tracing::Span::none(),
+                tenant: None,
+                user: None,
+            },
+        ];
+
+        let corpus = prepare_corpus(&jobs).expect("Should prepare corpus");
+        assert_eq!(corpus.job_ids.len(), 2);
+
+        for (job, job_id) in jobs.iter().zip(&corpus.job_ids) {
+            let wav = fs::read(corpus.dir.path().join(format!("{}.wav", job_id))).unwrap();
+            let lab =
+                fs::read_to_string(corpus.dir.path().join(format!("{}.lab", job_id))).unwrap();
+            assert_eq!(wav, job.audio_data);
+            assert_eq!(lab, job.transcript);
+        }
+    }
+
+    #[test]
+    fn test_split_result_missing_textgrid() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = split_result(dir.path(), "missing", &HashMap::new());
+        assert!(result.is_err(), "Unaligned jobs should report an error");
+    }
+}
@@ -0,0 +1,310 @@
+//! Loudness and clarity of each aligned word of a recording
+//!
+//! A word said too quietly, or swallowed at the end of a sentence, aligns to
+//! something but scores as if mispronounced. Measuring each word's level and
+//! spectral tilt against the rest of the recording lets such words be told
+//! apart, so feedback can ask the learner to speak up rather than correct a
+//! sound they may have said right.
+//!
+//! Both measures are relative to the recording's median word, so microphone
+//! gain and the speaker's voice do not matter, only how a word stands out
+//! from the others.
+
+use anyhow::Result;
+
+use crate::audio::{read_wav_mono, resample_linear};
+use crate::mfa_parser::MfaSegment;
+
+/// Rate recordings are resampled to, so spectral tilt does not depend on the upload's rate
+const ANALYSIS_SAMPLE_RATE: u32 = 16000;
+
+/// Words below this clarity are too quiet or muffled for their phonemes to be assessed
+pub const CLARITY_THRESHOLD: f64 = 0.5;
+
+/// Lowest level reported, for digital silence
+const FLOOR_DB: f64 = -120.0;
+
+/// Level in dBFS below which a word is taken as silence, whatever the rest of the recording
+const SILENCE_DBFS: f64 = -50.0;
+
+/// Levels, relative to the median word, at which loudness scores nothing and in full
+const QUIET_DB: f64 = -15.0;
+const AUDIBLE_DB: f64 = -6.0;
+
+/// Spectral tilts, relative to the median word, at which tilt scores nothing and in full
+///
+/// Words without fricatives are naturally duller than the median, so the
+/// range is generous and only flags words that lost most of their highs.
+const MUFFLED_TILT_DB: f64 = -12.0;
+const CLEAR_TILT_DB: f64 = -6.0;
+
+/// Why a word could not be assessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClarityIssue {
+    TooQuiet,
+    /// Too quiet, as were all the words after it to the end of the recording
+    TrailingOff,
+    /// Loud enough, but with too little high-frequency energy to make out its sounds
+    Muffled,
+}
+
+impl ClarityIssue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClarityIssue::TooQuiet => "too_quiet",
+            ClarityIssue::TrailingOff => "trailing_off",
+            ClarityIssue::Muffled => "muffled",
+        }
+    }
+
+    /// Message id of the tip for words with this issue
+    pub fn tip_id(&self) -> &'static str {
+        match self {
+            ClarityIssue::TooQuiet => "tip-too-quiet",
+            ClarityIssue::TrailingOff => "tip-trailing-off",
+            ClarityIssue::Muffled => "tip-muffled",
+        }
+    }
+}
+
+/// How clearly one aligned word was said
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordClarity {
+    pub word: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// RMS level in dBFS
+    pub rms_db: f64,
+    /// Level relative to the median word, in dB
+    pub relative_db: f64,
+    /// Energy of the signal's first difference relative to the signal, in dB
+    ///
+    /// Differencing boosts high frequencies, so this falls as the word gets duller.
+    pub spectral_tilt: f64,
+    /// 0.0 (inaudible or muffled) to 1.0 (clear)
+    pub clarity: f64,
+    /// Why the word is below [`CLARITY_THRESHOLD`], if it is
+    pub issue: Option<ClarityIssue>,
+}
+
+impl WordClarity {
+    /// Whether the word was clear enough for its phoneme scores to be trusted
+    pub fn is_assessable(&self) -> bool {
+        self.issue.is_none()
+    }
+}
+
+/// Measure the clarity of each aligned word in a WAV recording
+pub fn analyze_clarity(audio_wav: &[u8], segments: &[MfaSegment]) -> Result<Vec<WordClarity>> {
+    let (samples, sample_rate) = read_wav_mono(audio_wav)?;
+    Ok(measure_clarity(&samples, sample_rate, segments))
+}
+
+/// Measure the clarity of each word segment over mono samples
+pub fn measure_clarity(
+    samples: &[f32],
+    sample_rate: u32,
+    segments: &[MfaSegment],
+) -> Vec<WordClarity> {
+    let samples = resample_linear(samples, sample_rate, ANALYSIS_SAMPLE_RATE);
+    let rate = ANALYSIS_SAMPLE_RATE as f64;
+
+    let measured: Vec<(&MfaSegment, f64, f64)> = segments
+        .iter()
+        .filter(|segment| segment.segment_type == "word" && !segment.label.is_empty())
+        .map(|segment| {
+            let start = ((segment.begin * rate) as usize).min(samples.len());
+            let end = ((segment.end * rate) as usize).clamp(start, samples.len());
+            let (level, tilt) = level_and_tilt(&samples[start..end]);
+            (segment, level, tilt)
+        })
+        .collect();
+    if measured.is_empty() {
+        return Vec::new();
+    }
+
+    let median_level = median(measured.iter().map(|m| m.1));
+    let median_tilt = median(measured.iter().map(|m| m.2));
+
+    let mut words: Vec<WordClarity> = measured
+        .into_iter()
+        .map(|(segment, rms_db, spectral_tilt)| {
+            let relative_db = rms_db - median_level;
+            let loudness = if rms_db < SILENCE_DBFS {
+                0.0
+            } else {
+                ramp(relative_db, QUIET_DB, AUDIBLE_DB)
+            };
+            let tilt = ramp(spectral_tilt - median_tilt, MUFFLED_TILT_DB, CLEAR_TILT_DB);
+            let clarity = loudness.min(tilt);
+
+            WordClarity {
+                word: segment.label.clone(),
+                start_time: segment.begin,
+                end_time: segment.end,
+                rms_db,
+                relative_db,
+                spectral_tilt,
+                clarity,
+                issue: (clarity < CLARITY_THRESHOLD).then_some(if loudness <= tilt {
+                    ClarityIssue::TooQuiet
+                } else {
+                    ClarityIssue::Muffled
+                }),
+            }
+        })
+        .collect();
+
+    // Quiet words running to the end are the voice trailing off, unless nothing was audible
+    let trailing = words
+        .iter()
+        .rev()
+        .take_while(|word| word.issue == Some(ClarityIssue::TooQuiet))
+        .count();
+    if trailing < words.len() {
+        let start = words.len() - trailing;
+        for word in &mut words[start..] {
+            word.issue = Some(ClarityIssue::TrailingOff);
+        }
+    }
+
+    words
+}
+
+/// RMS level in dBFS and spectral tilt in dB of a stretch of samples
+fn level_and_tilt(samples: &[f32]) -> (f64, f64) {
+    if samples.len() < 2 {
+        return (FLOOR_DB, 0.0);
+    }
+
+    let energy: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let difference: f64 = samples
+        .windows(2)
+        .map(|pair| {
+            let d = (pair[1] - pair[0]) as f64;
+            d * d
+        })
+        .sum();
+
+    let level = decibels(energy / samples.len() as f64);
+    let tilt = if energy > 0.0 {
+        decibels(difference / energy)
+    } else {
+        0.0
+    };
+    (level, tilt)
+}
+
+fn decibels(power_ratio: f64) -> f64 {
+    if power_ratio > 0.0 {
+        (10.0 * power_ratio.log10()).max(FLOOR_DB)
+    } else {
+        FLOOR_DB
+    }
+}
+
+/// 0.0 at or below `low`, 1.0 at or above `high`, linear in between
+fn ramp(value: f64, low: f64, high: f64) -> f64 {
+    ((value - low) / (high - low)).clamp(0.0, 1.0)
+}
+
+fn median(values: impl Iterator<Item = f64>) -> f64 {
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    /// Deterministic broadband noise, standing in for speech with fricatives
+    fn noise(seconds: f64, amplitude: f32, seed: &mut u32) -> Vec<f32> {
+        (0..(seconds * ANALYSIS_SAMPLE_RATE as f64) as usize)
+            .map(|_| {
+                *seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (*seed as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    /// A low hum, standing in for a word with its high frequencies lost
+    fn hum(seconds: f64, amplitude: f32) -> Vec<f32> {
+        (0..(seconds * ANALYSIS_SAMPLE_RATE as f64) as usize)
+            .map(|i| {
+                (TAU * 120.0 * i as f64 / ANALYSIS_SAMPLE_RATE as f64).sin() as f32 * amplitude
+            })
+            .collect()
+    }
+
+    fn words(labels: &[&str]) -> Vec<MfaSegment> {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| MfaSegment {
+                begin: i as f64 * 0.5,
+                end: (i + 1) as f64 * 0.5,
+                label: label.to_string(),
+                segment_type: "word".to_string(),
+                confidence: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flags_quiet_and_muffled_words() {
+        let mut seed = 1;
+        let mut samples = noise(0.5, 0.5, &mut seed);
+        samples.extend(noise(0.5, 0.02, &mut seed));
+        samples.extend(hum(0.5, 0.5));
+        samples.extend(noise(0.5, 0.5, &mut seed));
+        samples.extend(noise(0.5, 0.5, &mut seed));
+
+        let clarity = measure_clarity(
+            &samples,
+            ANALYSIS_SAMPLE_RATE,
+            &words(&["she", "sells", "more", "sea", "shells"]),
+        );
+        let issues: Vec<_> = clarity.iter().map(|word| word.issue).collect();
+        assert_eq!(
+            issues,
+            [
+                None,
+                Some(ClarityIssue::TooQuiet),
+                Some(ClarityIssue::Muffled),
+                None,
+                None
+            ]
+        );
+        assert!(clarity[0].clarity > 0.99);
+        assert!(clarity[1].relative_db < QUIET_DB);
+    }
+
+    #[test]
+    fn test_quiet_words_at_the_end_are_trailing_off() {
+        let mut seed = 7;
+        let mut samples = noise(1.0, 0.5, &mut seed);
+        samples.extend(noise(0.5, 0.01, &mut seed));
+        samples.extend(noise(0.5, 0.005, &mut seed));
+
+        let clarity = measure_clarity(
+            &samples,
+            ANALYSIS_SAMPLE_RATE,
+            &words(&["the", "cat", "sat", "down"]),
+        );
+        assert!(clarity[..2].iter().all(WordClarity::is_assessable));
+        assert!(
+            clarity[2..]
+                .iter()
+                .all(|word| word.issue == Some(ClarityIssue::TrailingOff))
+        );
+
+        let silence = measure_clarity(&[0.0; 16000], ANALYSIS_SAMPLE_RATE, &words(&["a", "b"]));
+        assert!(
+            silence
+                .iter()
+                .all(|word| word.issue == Some(ClarityIssue::TooQuiet))
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Confidence in aligned segments, from the scores MFA reports with its alignments
+//!
+//! Besides its alignments, MFA writes `alignment_analysis.csv`, giving each
+//! aligned file the average log-likelihood of its speech frames under the
+//! acoustic model. Recordings the model fits poorly, through noise, a strong
+//! accent, or speech that strays from the transcript, score low, and their
+//! alignments are less trustworthy. The likelihood is mapped onto 0 to 1
+//! between the bounds of `ALIGNMENT_LIKELIHOOD_RANGE`, and the scorer weights
+//! each phoneme by the confidence of its segment rather than treating every
+//! alignment as ground truth.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::mfa_parser::MfaSegment;
+use crate::scoring::PhonemeAccuracy;
+
+/// Name of the file MFA writes its alignment scores to, beside the alignments
+pub const ALIGNMENT_SCORES_FILE: &str = "alignment_analysis.csv";
+
+/// Weight of a phoneme the aligner has no confidence in, so no phoneme is ignored outright
+pub const MIN_CONFIDENCE_WEIGHT: f64 = 0.2;
+
+/// Likelihoods mapped to no confidence and to full confidence when
+/// `ALIGNMENT_LIKELIHOOD_RANGE` is not set
+const DEFAULT_LIKELIHOOD_RANGE: (f64, f64) = (-80.0, -40.0);
+
+/// Scale configured by `ALIGNMENT_LIKELIHOOD_RANGE`
+pub static LIKELIHOOD_SCALE: LazyLock<LikelihoodScale> = LazyLock::new(LikelihoodScale::from_env);
+
+/// Per-frame log-likelihoods at which confidence is 0 and 1, linear between them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LikelihoodScale {
+    pub floor: f64,
+    pub ceiling: f64,
+}
+
+impl Default for LikelihoodScale {
+    fn default() -> Self {
+        let (floor, ceiling) = DEFAULT_LIKELIHOOD_RANGE;
+        Self { floor, ceiling }
+    }
+}
+
+impl LikelihoodScale {
+    /// Read `ALIGNMENT_LIKELIHOOD_RANGE`, e.g. `-80,-40`, falling back to the default
+    pub fn from_env() -> Self {
+        match env::var("ALIGNMENT_LIKELIHOOD_RANGE") {
+            Ok(range) => Self::parse(&range).unwrap_or_else(|| {
+                tracing::warn!(
+                    "Invalid ALIGNMENT_LIKELIHOOD_RANGE '{}', using {:?}",
+                    range,
+                    DEFAULT_LIKELIHOOD_RANGE
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Parse `floor,ceiling`, where the floor must be below the ceiling
+    pub fn parse(range: &str) -> Option<Self> {
+        let (floor, ceiling) = range.split_once(',')?;
+        let floor: f64 = floor.trim().parse().ok()?;
+        let ceiling: f64 = ceiling.trim().parse().ok()?;
+        (floor.is_finite() && ceiling.is_finite() && floor < ceiling)
+            .then_some(Self { floor, ceiling })
+    }
+
+    /// Confidence in an alignment with this per-frame log-likelihood, from 0.0 to 1.0
+    pub fn confidence(&self, log_likelihood: f64) -> f64 {
+        ((log_likelihood - self.floor) / (self.ceiling - self.floor)).clamp(0.0, 1.0)
+    }
+}
+
+/// Read the alignment scores MFA wrote to a directory, keyed by file stem
+///
+/// Returns an empty map if MFA wrote none, as older versions do not.
+pub fn read_alignment_scores(dir: &Path) -> Result<HashMap<String, f64>> {
+    let path = dir.join(ALIGNMENT_SCORES_FILE);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read alignment scores from {:?}", path))?;
+    parse_alignment_scores(&contents).with_context(|| format!("Malformed {:?}", path))
+}
+
+/// Alignment scores in a directory as [`read_alignment_scores`] reads them, or none if
+/// they cannot be read, since scoring goes ahead without them
+pub fn alignment_scores(dir: &Path) -> HashMap<String, f64> {
+    read_alignment_scores(dir).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring alignment scores: {:#}", e);
+        HashMap::new()
+    })
+}
+
+/// Per-frame speech log-likelihood of each file in MFA's alignment analysis CSV
+///
+/// The overall log-likelihood, which includes silence, is used if the speech
+/// column is missing. Rows whose score is not a number, as for files MFA
+/// could not align, are left out.
+pub fn parse_alignment_scores(contents: &str) -> Result<HashMap<String, f64>> {
+    let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<&str> = lines
+        .next()
+        .context("Alignment scores have no header")?
+        .split(',')
+        .map(str::trim)
+        .collect();
+    let column = |name: &str| header.iter().position(|&column| column == name);
+
+    let file = column("file").context("Alignment scores have no file column")?;
+    let score = column("speech_log_likelihood")
+        .or_else(|| column("overall_log_likelihood"))
+        .context("Alignment scores have no log-likelihood column")?;
+
+    let mut scores = HashMap::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let (Some(name), Some(value)) = (fields.get(file), fields.get(score)) else {
+            continue;
+        };
+        if let Ok(value) = value.parse::<f64>()
+            && value.is_finite()
+        {
+            // MFA names files with or without their extension depending on the version
+            let stem = Path::new(name)
+                .file_stem()
+                .map_or(*name, |stem| stem.to_str().unwrap_or(name));
+            scores.insert(stem.to_string(), value);
+        }
+    }
+    Ok(scores)
+}
+
+/// Add the scores of a realigned directory to those of the corpus it was taken from
+pub fn merge_alignment_scores(from_dir: &Path, into_dir: &Path) -> Result<()> {
+    let from = from_dir.join(ALIGNMENT_SCORES_FILE);
+    if !from.exists() {
+        return Ok(());
+    }
+    let into = into_dir.join(ALIGNMENT_SCORES_FILE);
+    if !into.exists() {
+        fs::copy(&from, &into)?;
+        return Ok(());
+    }
+
+    // The rows are appended under the corpus's own header, so both must agree
+    let realigned = fs::read_to_string(&from)?;
+    let existing = fs::read_to_string(&into)?;
+    if realigned.lines().next() != existing.lines().next() {
+        tracing::warn!("Realigned files were scored in different columns, dropping their scores");
+        return Ok(());
+    }
+
+    let mut merged = existing;
+    if !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+    for line in realigned.lines().skip(1) {
+        merged.push_str(line);
+        merged.push('\n');
+    }
+    fs::write(&into, merged)?;
+    Ok(())
+}
+
+/// Give the segments of one aligned file the confidence of its alignment score,
+/// keeping any confidence segments already carry from the aligner
+pub fn apply_confidence(segments: &mut [MfaSegment], log_likelihood: f64, scale: &LikelihoodScale) {
+    let confidence = scale.confidence(log_likelihood);
+    for segment in segments {
+        segment.confidence.get_or_insert(confidence);
+    }
+}
+
+/// Mean of the phoneme scores, each weighted by the confidence of its alignment
+///
+/// Phonemes without a confidence, such as those the speaker left out, count
+/// fully, and none counts for less than [`MIN_CONFIDENCE_WEIGHT`]. With no
+/// confidences at all this is the plain mean.
+pub fn weighted_overall_score(details: &[PhonemeAccuracy]) -> f64 {
+    let (total, weights) = details.iter().fold((0.0, 0.0), |(total, weights), detail| {
+        let weight = detail
+            .confidence
+            .map_or(1.0, |confidence| confidence.max(MIN_CONFIDENCE_WEIGHT));
+        (total + detail.score * weight, weights + weight)
+    });
+
+    if weights == 0.0 { 0.0 } else { total / weights }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANALYSIS: &str = "\
+file,begin,end,speaker,overall_log_likelihood,speech_log_likelihood,phone_duration_deviation,snr
+job-a,0.0,1.52,corpus,-61.2,-48.5,1.3,22.1
+job-b.wav,0.0,2.10,corpus,-80.4,-74.0,3.9,8.2
+job-c,0.0,0.80,corpus,-70.0,nan,0.0,0.0
+";
+
+    fn accuracy(score: f64, confidence: Option<f64>) -> PhonemeAccuracy {
+        PhonemeAccuracy {
+            expected: "æ".to_string(),
+            actual: "æ".to_string(),
+            score,
+            start_time: 0.0,
+            end_time: 0.1,
+            char_span: None,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_parse_alignment_scores() {
+        let scores = parse_alignment_scores(ANALYSIS).unwrap();
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores["job-a"], -48.5);
+        assert_eq!(scores["job-b"], -74.0);
+
+        // Older versions only report the overall likelihood
+        let overall = parse_alignment_scores("file,overall_log_likelihood\njob-a,-55\n").unwrap();
+        assert_eq!(overall["job-a"], -55.0);
+
+        assert!(parse_alignment_scores("file,begin,end\njob-a,0,1\n").is_err());
+        assert!(parse_alignment_scores("").is_err());
+    }
+
+    #[test]
+    fn test_likelihood_scale() {
+        let scale = LikelihoodScale::default();
+        assert_eq!(scale.confidence(-90.0), 0.0);
+        assert_eq!(scale.confidence(-60.0), 0.5);
+        assert_eq!(scale.confidence(-10.0), 1.0);
+
+        assert_eq!(
+            LikelihoodScale::parse(" -100, -50"),
+            Some(LikelihoodScale {
+                floor: -100.0,
+                ceiling: -50.0
+            })
+        );
+        assert_eq!(LikelihoodScale::parse("-40,-80"), None);
+        assert_eq!(LikelihoodScale::parse("-40"), None);
+    }
+
+    #[test]
+    fn test_low_confidence_phonemes_count_less() {
+        let trusted = [accuracy(1.0, None), accuracy(0.0, None)];
+        assert_eq!(weighted_overall_score(&trusted), 0.5);
+
+        // A miss the aligner was unsure of counts for less than a sure match
+        let unsure_miss = [accuracy(1.0, Some(1.0)), accuracy(0.0, Some(0.25))];
+        assert!((weighted_overall_score(&unsure_miss) - 0.8).abs() < 1e-9);
+
+        // But never for nothing
+        let no_confidence = [accuracy(1.0, Some(1.0)), accuracy(0.0, Some(0.0))];
+        let floor = 1.0 / (1.0 + MIN_CONFIDENCE_WEIGHT);
+        assert!((weighted_overall_score(&no_confidence) - floor).abs() < 1e-9);
+
+        assert_eq!(weighted_overall_score(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_merge_alignment_scores() {
+        let corpus = tempfile::tempdir().unwrap();
+        let retry = tempfile::tempdir().unwrap();
+        let header = ANALYSIS.lines().next().unwrap();
+
+        fs::write(
+            corpus.path().join(ALIGNMENT_SCORES_FILE),
+            format!("{}\njob-a,0,1,c,-60,-50,1,20\n", header),
+        )
+        .unwrap();
+        fs::write(
+            retry.path().join(ALIGNMENT_SCORES_FILE),
+            format!("{}\njob-b,0,1,c,-70,-65,2,10\n", header),
+        )
+        .unwrap();
+        merge_alignment_scores(retry.path(), corpus.path()).unwrap();
+
+        let scores = read_alignment_scores(corpus.path()).unwrap();
+        assert_eq!(scores["job-a"], -50.0);
+        assert_eq!(scores["job-b"], -65.0);
+
+        let unscored = tempfile::tempdir().unwrap();
+        assert!(read_alignment_scores(unscored.path()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,741 @@
+//! Lifecycle management for the MFA Docker container
+
+use anyhow::{Context, Result};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::platform::{CONTAINER_CLI, ContainerRuntime};
+use crate::volume::{VolumeMapping, host_to_container};
+
+/// Default image used when no image is configured
+pub const DEFAULT_MFA_IMAGE: &str = "mmcauliffe/montreal-forced-aligner:latest";
+
+/// Default name of the managed container
+pub const DEFAULT_CONTAINER_NAME: &str = "ipa-mfa";
+
+/// Directory inside the container where job directories are mounted by default
+pub const DEFAULT_CONTAINER_JOBS_DIR: &str = "/data/jobs";
+
+/// Variables of a captured shell environment that describe the shell rather than the aligner
+const SHELL_VARIABLES: &[&str] = &["_", "HOSTNAME", "OLDPWD", "PWD", "SHLVL"];
+
+/// Shared container manager configured from the environment
+pub static CONTAINER_MANAGER: LazyLock<ContainerManager> =
+    LazyLock::new(|| ContainerManager::new(ContainerConfig::from_env()));
+
+/// Compose-like definition of the MFA container
+#[derive(Debug, Clone)]
+pub struct ContainerConfig {
+    /// Whether alignment on the host should go through the managed container
+    ///
+    /// Off unless `MFA_CONTAINER_ENABLED` is set, so the host's own `mfa` is used by default.
+    pub enabled: bool,
+    /// Container name
+    pub name: String,
+    /// Image to create the container from
+    pub image: String,
+    /// Host directory in which job directories are created
+    pub jobs_dir: PathBuf,
+    /// Bind mounts from host directories into the container
+    pub volumes: Vec<VolumeMapping>,
+    /// Environment variables passed to the container
+    pub environment: Vec<(String, String)>,
+    /// Command that keeps the container alive between alignments
+    pub command: Vec<String>,
+    /// Shell command run inside the container to check that MFA is usable
+    pub health_check: String,
+    /// Maximum number of restarts attempted by a single `ensure_running` call
+    pub max_restarts: u32,
+    /// Whether commands reuse the environment of one login shell per container start,
+    /// rather than each sourcing the profile and activating conda
+    pub session: bool,
+    /// How long a passed health check is trusted before checking again
+    pub health_ttl: Duration,
+}
+
+impl Default for ContainerConfig {
+    fn default() -> Self {
+        let jobs_dir = env::temp_dir().join("ipa-navigator-mfa");
+
+        Self {
+            enabled: false,
+            name: DEFAULT_CONTAINER_NAME.to_string(),
+            image: DEFAULT_MFA_IMAGE.to_string(),
+            volumes: vec![VolumeMapping::new(&jobs_dir, DEFAULT_CONTAINER_JOBS_DIR)],
+            jobs_dir,
+            environment: Vec::new(),
+            command: vec!["sleep".to_string(), "infinity".to_string()],
+            health_check: "mfa version".to_string(),
+            max_restarts: 2,
+            session: true,
+            health_ttl: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ContainerConfig {
+    /// Build the container definition from `MFA_CONTAINER_*` environment variables
+    ///
+    /// Volumes are a comma-separated list of `host:container` pairs and environment
+    /// variables a comma-separated list of `KEY=VALUE` pairs. Malformed volume
+    /// entries are skipped with a warning; `validate_mounts` reports what is missing.
+    /// `MFA_CONTAINER_SESSION` turns off the reused shell environment and
+    /// `MFA_CONTAINER_HEALTH_TTL` sets in seconds how long a health check holds.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let enabled = env::var("MFA_CONTAINER_ENABLED")
+            .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(defaults.enabled);
+
+        let name = env::var("MFA_CONTAINER_NAME").unwrap_or(defaults.name);
+        let image = env::var("MFA_CONTAINER_IMAGE").unwrap_or(defaults.image);
+
+        let jobs_dir = env::var("MFA_JOBS_DIR")
+            .map(PathBuf::from)
+            .unwrap_or(defaults.jobs_dir);
+
+        let volumes = match env::var("MFA_CONTAINER_VOLUMES") {
+            Ok(v) => split_list(&v)
+                .iter()
+                .filter_map(|spec| match VolumeMapping::parse(spec) {
+                    Ok(mapping) => Some(mapping),
+                    Err(e) => {
+                        tracing::warn!("Ignoring MFA volume: {}", e);
+                        None
+                    }
+                })
+                .collect(),
+            Err(_) => vec![VolumeMapping::new(&jobs_dir, DEFAULT_CONTAINER_JOBS_DIR)],
+        };
+
+        let environment = env::var("MFA_CONTAINER_ENV")
+            .map(|v| {
+                split_list(&v)
+                    .into_iter()
+                    .filter_map(|pair| {
+                        pair.split_once('=')
+                            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or(defaults.environment);
+
+        let health_check = env::var("MFA_CONTAINER_HEALTH_CHECK").unwrap_or(defaults.health_check);
+
+        let max_restarts = env::var("MFA_CONTAINER_MAX_RESTARTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(defaults.max_restarts);
+
+        let session = env::var("MFA_CONTAINER_SESSION")
+            .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "no"))
+            .unwrap_or(defaults.session);
+
+        let health_ttl = env::var("MFA_CONTAINER_HEALTH_TTL")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.health_ttl);
+
+        Self {
+            enabled,
+            name,
+            image,
+            jobs_dir,
+            volumes,
+            environment,
+            command: defaults.command,
+            health_check,
+            max_restarts,
+            session,
+            health_ttl,
+        }
+    }
+
+    /// Arguments passed to `create` on `runtime` for this definition
+    pub fn create_args(&self, runtime: ContainerRuntime) -> Vec<String> {
+        let mut args = vec![
+            "create".to_string(),
+            "--name".to_string(),
+            self.name.clone(),
+        ];
+
+        for volume in &self.volumes {
+            args.push("-v".to_string());
+            args.push(match runtime.mount_options() {
+                Some(options) => format!("{}:{}", volume, options),
+                None => volume.to_string(),
+            });
+        }
+
+        for (key, value) in &self.environment {
+            args.push("-e".to_string());
+            args.push(format!("{}={}", key, value));
+        }
+
+        args.push(runtime.qualify_image(&self.image));
+        args.extend(self.command.iter().cloned());
+        args
+    }
+
+    /// Translate a host path into the path the container sees
+    pub fn to_container_path(&self, host_path: &Path) -> Result<PathBuf> {
+        host_to_container(&self.volumes, host_path)
+    }
+}
+
+/// Variables of an `env -0` listing, leaving out those describing the shell itself
+///
+/// Exported shell functions, whose names are not valid variable names, are left out too.
+pub fn parse_env(listing: &[u8]) -> Vec<(String, String)> {
+    String::from_utf8_lossy(listing)
+        .split('\0')
+        .filter_map(|entry| entry.split_once('='))
+        .filter(|(name, _)| {
+            !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !SHELL_VARIABLES.contains(name)
+        })
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Split a comma-separated list, dropping empty items
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// State of the container as reported by `inspect`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerState {
+    /// No container with the configured name exists
+    Missing,
+    /// Created but never started
+    Created,
+    /// Running
+    Running,
+    /// Paused
+    Paused,
+    /// Restarting
+    Restarting,
+    /// Exited or dead
+    Stopped,
+}
+
+impl ContainerState {
+    /// Parse the `.State.Status` field reported by Docker or Podman
+    fn from_status(status: &str) -> Self {
+        match status.trim() {
+            "created" | "configured" | "initialized" => ContainerState::Created,
+            "running" => ContainerState::Running,
+            "paused" => ContainerState::Paused,
+            "restarting" => ContainerState::Restarting,
+            _ => ContainerState::Stopped,
+        }
+    }
+
+    /// Lowercase name of the state
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContainerState::Missing => "missing",
+            ContainerState::Created => "created",
+            ContainerState::Running => "running",
+            ContainerState::Paused => "paused",
+            ContainerState::Restarting => "restarting",
+            ContainerState::Stopped => "stopped",
+        }
+    }
+}
+
+/// Snapshot of the managed container for status reporting
+#[derive(Debug, Clone)]
+pub struct ContainerReport {
+    pub runtime: ContainerRuntime,
+    pub name: String,
+    pub image: String,
+    pub state: ContainerState,
+    pub healthy: bool,
+    pub restart_count: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct ManagerState {
+    restart_count: u32,
+    last_error: Option<String>,
+    session: Option<Session>,
+}
+
+/// Login shell environment captured in the container, reused until it restarts
+#[derive(Debug, Clone)]
+struct Session {
+    /// Start time the container reported, which changes whenever it is restarted
+    started_at: String,
+    env: Vec<(String, String)>,
+    /// When the container last passed its health check in this session
+    healthy_at: Option<Instant>,
+}
+
+impl Session {
+    /// Whether the container is still the one started at `started_at` and
+    /// passed its health check within `ttl`
+    fn is_warm(&self, started_at: &str, ttl: Duration) -> bool {
+        self.started_at == started_at
+            && self
+                .healthy_at
+                .is_some_and(|healthy_at| healthy_at.elapsed() < ttl)
+    }
+}
+
+/// Creates, starts, health-checks, and restarts the MFA container
+pub struct ContainerManager {
+    config: ContainerConfig,
+    state: Mutex<ManagerState>,
+}
+
+impl ContainerManager {
+    /// Create a manager for the given container definition
+    pub fn new(config: ContainerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(ManagerState::default()),
+        }
+    }
+
+    /// The container definition this manager works with
+    pub fn config(&self) -> &ContainerConfig {
+        &self.config
+    }
+
+    /// Check that the jobs directory is mounted and that the container's mounts match
+    ///
+    /// Host directories are created if missing. If the container already exists, its
+    /// bind mounts are compared against the configuration, since Docker cannot change
+    /// the mounts of an existing container.
+    pub fn validate_mounts(&self) -> Result<()> {
+        for volume in &self.config.volumes {
+            volume.ensure_host_dir()?;
+        }
+
+        self.config
+            .to_container_path(&self.config.jobs_dir)
+            .context("MFA jobs directory is not mounted into the container")?;
+
+        if self.state()? == ContainerState::Missing {
+            return Ok(());
+        }
+
+        let mounts = self.mounts()?;
+        let missing: Vec<String> = self
+            .config
+            .volumes
+            .iter()
+            .filter(|v| !mounts.iter().any(|mount| v.same_mount(mount)))
+            .map(|v| v.to_string())
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Container {} is missing mounts [{}]; remove it so it can be recreated",
+                self.config.name,
+                missing.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Bind mounts of the existing container
+    pub fn mounts(&self) -> Result<Vec<VolumeMapping>> {
+        let output = cli(&[
+            "inspect",
+            "-f",
+            "{{range .Mounts}}{{.Source}}:{{.Destination}}\n{{end}}",
+            &self.config.name,
+        ])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        Ok(stdout
+            .lines()
+            .filter_map(|line| VolumeMapping::parse(line).ok())
+            .collect())
+    }
+
+    /// Query the current state of the container
+    pub fn state(&self) -> Result<ContainerState> {
+        self.inspect().map(|(state, _)| state)
+    }
+
+    /// State of the container and the time it was last started
+    fn inspect(&self) -> Result<(ContainerState, String)> {
+        let output = cli(&[
+            "inspect",
+            "-f",
+            "{{.State.Status}} {{.State.StartedAt}}",
+            &self.config.name,
+        ])?;
+
+        if !output.status.success() {
+            return Ok((ContainerState::Missing, String::new()));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (status, started_at) = stdout.trim().split_once(' ').unwrap_or((&stdout, ""));
+        Ok((
+            ContainerState::from_status(status),
+            started_at.trim().to_string(),
+        ))
+    }
+
+    /// Create the container from its definition
+    pub fn create(&self) -> Result<()> {
+        tracing::info!("Creating MFA container {}", self.config.name);
+        let args = self.config.create_args(CONTAINER_CLI.runtime);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        check(cli(&args)?, "create")
+    }
+
+    /// Start the (already created) container
+    pub fn start(&self) -> Result<()> {
+        check(cli(&["start", &self.config.name])?, "start")
+    }
+
+    /// Restart the container
+    pub fn restart(&self) -> Result<()> {
+        let result = check(cli(&["restart", &self.config.name])?, "restart");
+
+        let mut state = self.lock_state();
+        state.restart_count += 1;
+        state.session = None;
+        if let Err(e) = &result {
+            state.last_error = Some(e.to_string());
+        }
+
+        result
+    }
+
+    /// Run the configured health check inside the container
+    pub fn health_check(&self) -> Result<bool> {
+        let output = self.exec(&self.config.health_check)?;
+        Ok(output.status.success())
+    }
+
+    /// Run a shell command inside the container
+    ///
+    /// With a session open the command runs in a plain shell given the
+    /// session's environment, otherwise in a login shell.
+    pub fn exec(&self, command: &str) -> Result<Output> {
+        let Some(env) = self.lock_state().session.as_ref().map(|s| s.env.clone()) else {
+            return cli(&["exec", &self.config.name, "bash", "-lc", command]);
+        };
+
+        let variables: Vec<String> = env
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        let mut args = vec!["exec"];
+        for variable in &variables {
+            args.extend(["-e", variable.as_str()]);
+        }
+        args.extend([self.config.name.as_str(), "bash", "-c", command]);
+        let output = cli(&args)?;
+
+        // Not finding a command means the environment no longer matches the container
+        if output.status.code() == Some(127) {
+            tracing::warn!("MFA container command not found, dropping its session");
+            self.lock_state().session = None;
+        }
+        Ok(output)
+    }
+
+    /// Capture the environment of a login shell in the container, started at `started_at`
+    ///
+    /// Sessions are best-effort: if the environment cannot be captured,
+    /// commands keep starting login shells.
+    fn open_session(&self, started_at: &str) {
+        if !self.config.session {
+            return;
+        }
+
+        let started = Instant::now();
+        let output = match cli(&["exec", &self.config.name, "bash", "-lc", "env -0"]) {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                tracing::warn!(
+                    "Failed to capture the MFA container environment: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                return;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to capture the MFA container environment: {:#}", e);
+                return;
+            }
+        };
+
+        let env = parse_env(&output.stdout);
+        tracing::info!(
+            "Opened MFA container session with {} variables in {:?}",
+            env.len(),
+            started.elapsed()
+        );
+        self.lock_state().session = Some(Session {
+            started_at: started_at.to_string(),
+            env,
+            healthy_at: None,
+        });
+    }
+
+    /// Run the health check, remembering a pass in the open session
+    fn check_health(&self) -> Result<bool> {
+        let healthy = self.health_check()?;
+        if healthy && let Some(session) = self.lock_state().session.as_mut() {
+            session.healthy_at = Some(Instant::now());
+        }
+        Ok(healthy)
+    }
+
+    /// Make sure the container exists, is running, and passes its health check
+    ///
+    /// Missing containers are created, stopped ones are started, and unhealthy
+    /// ones are restarted up to `max_restarts` times. The health check is
+    /// skipped while the open session passed one within `health_ttl`.
+    pub fn ensure_running(&self) -> Result<()> {
+        let result = self.ensure_running_inner();
+
+        let mut state = self.lock_state();
+        state.last_error = result.as_ref().err().map(|e| e.to_string());
+
+        result
+    }
+
+    fn ensure_running_inner(&self) -> Result<()> {
+        let (state, mut started_at) = self.inspect()?;
+        match state {
+            ContainerState::Missing => {
+                self.create()?;
+                self.start()?;
+            }
+            ContainerState::Created | ContainerState::Stopped => self.start()?,
+            ContainerState::Paused => check(cli(&["unpause", &self.config.name])?, "unpause")?,
+            ContainerState::Running | ContainerState::Restarting => {}
+        }
+        if !matches!(state, ContainerState::Running | ContainerState::Restarting) {
+            started_at = self.inspect()?.1;
+        }
+
+        let session = self.lock_state().session.clone();
+        match session {
+            Some(session) if session.is_warm(&started_at, self.config.health_ttl) => {
+                return Ok(());
+            }
+            Some(session) if session.started_at == started_at => {}
+            _ => self.open_session(&started_at),
+        }
+
+        if self.check_health()? {
+            return Ok(());
+        }
+
+        for attempt in 1..=self.config.max_restarts {
+            tracing::warn!(
+                "MFA container {} unhealthy, restart attempt {}",
+                self.config.name,
+                attempt
+            );
+            self.restart()?;
+            self.open_session(&self.inspect()?.1);
+            if self.check_health()? {
+                return Ok(());
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "MFA container {} is unhealthy after {} restarts",
+            self.config.name,
+            self.config.max_restarts
+        ))
+    }
+
+    /// Report the container state and health without attempting to repair it
+    pub fn report(&self) -> ContainerReport {
+        let (container_state, healthy, error) = match self.state() {
+            Ok(ContainerState::Running) => match self.health_check() {
+                Ok(healthy) => (ContainerState::Running, healthy, None),
+                Err(e) => (ContainerState::Running, false, Some(e.to_string())),
+            },
+            Ok(other) => (other, false, None),
+            Err(e) => (ContainerState::Missing, false, Some(e.to_string())),
+        };
+
+        let state = self.lock_state();
+        ContainerReport {
+            runtime: CONTAINER_CLI.runtime,
+            name: self.config.name.clone(),
+            image: self.config.image.clone(),
+            state: container_state,
+            healthy,
+            restart_count: state.restart_count,
+            last_error: error.or_else(|| state.last_error.clone()),
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, ManagerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Run a container CLI command and capture its output
+fn cli(args: &[&str]) -> Result<Output> {
+    CONTAINER_CLI
+        .command()
+        .args(args)
+        .output()
+        .with_context(|| {
+            format!(
+                "Failed to execute {} {}",
+                CONTAINER_CLI.name(),
+                args.join(" ")
+            )
+        })
+}
+
+/// Turn a non-zero exit status into an error carrying stderr
+fn check(output: Output, action: &str) -> Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(anyhow::anyhow!(
+        "{} {} failed: {}",
+        CONTAINER_CLI.name(),
+        action,
+        stderr.trim()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_args() {
+        let config = ContainerConfig {
+            volumes: vec![VolumeMapping::new("/tmp/jobs", "/data/jobs")],
+            environment: vec![("MFA_ROOT_DIR".to_string(), "/mfa".to_string())],
+            ..ContainerConfig::default()
+        };
+
+        let args = config.create_args(ContainerRuntime::Docker);
+        assert_eq!(
+            args,
+            vec![
+                "create",
+                "--name",
+                DEFAULT_CONTAINER_NAME,
+                "-v",
+                "/tmp/jobs:/data/jobs",
+                "-e",
+                "MFA_ROOT_DIR=/mfa",
+                DEFAULT_MFA_IMAGE,
+                "sleep",
+                "infinity",
+            ]
+        );
+
+        let podman = config.create_args(ContainerRuntime::Podman);
+        assert_eq!(podman[4], "/tmp/jobs:/data/jobs:z");
+        assert_eq!(podman[7], format!("docker.io/{}", DEFAULT_MFA_IMAGE));
+    }
+
+    #[test]
+    fn test_container_state_parsing() {
+        assert_eq!(
+            ContainerState::from_status("running\n"),
+            ContainerState::Running
+        );
+        assert_eq!(
+            ContainerState::from_status("exited"),
+            ContainerState::Stopped
+        );
+        assert_eq!(
+            ContainerState::from_status("created"),
+            ContainerState::Created
+        );
+        assert_eq!(
+            ContainerState::from_status("configured"),
+            ContainerState::Created
+        );
+    }
+
+    #[test]
+    fn test_to_container_path() {
+        let config = ContainerConfig {
+            jobs_dir: PathBuf::from("/srv/jobs"),
+            volumes: vec![VolumeMapping::new("/srv/jobs", "/data/jobs")],
+            ..ContainerConfig::default()
+        };
+
+        assert_eq!(
+            config
+                .to_container_path(Path::new("/srv/jobs/1234"))
+                .unwrap(),
+            PathBuf::from("/data/jobs/1234")
+        );
+        assert!(config.to_container_path(Path::new("/tmp/1234")).is_err());
+    }
+
+    #[test]
+    fn test_parse_env_keeps_the_aligner_environment() {
+        let listing = b"PATH=/opt/conda/envs/aligner/bin:/usr/bin\0CONDA_DEFAULT_ENV=aligner\0\
+PWD=/root\0SHLVL=1\0BASH_FUNC_module%%=() {  eval\n}\0MULTI=a=b\nc\0";
+
+        assert_eq!(
+            parse_env(listing),
+            vec![
+                (
+                    "PATH".to_string(),
+                    "/opt/conda/envs/aligner/bin:/usr/bin".to_string()
+                ),
+                ("CONDA_DEFAULT_ENV".to_string(), "aligner".to_string()),
+                ("MULTI".to_string(), "a=b\nc".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_session_stays_warm_until_restart_or_ttl() {
+        let session = Session {
+            started_at: "2024-05-01T10:00:00Z".to_string(),
+            env: Vec::new(),
+            healthy_at: Some(Instant::now()),
+        };
+        let ttl = Duration::from_secs(30);
+
+        assert!(session.is_warm("2024-05-01T10:00:00Z", ttl));
+        assert!(!session.is_warm("2024-05-01T11:00:00Z", ttl));
+        assert!(!session.is_warm("2024-05-01T10:00:00Z", Duration::ZERO));
+
+        let unchecked = Session {
+            healthy_at: None,
+            ..session
+        };
+        assert!(!unchecked.is_warm("2024-05-01T10:00:00Z", ttl));
+    }
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(split_list("a:b, c:d,,"), vec!["a:b", "c:d"]);
+        assert!(split_list("").is_empty());
+    }
+}
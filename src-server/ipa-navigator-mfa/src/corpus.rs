@@ -0,0 +1,276 @@
+//! Practice sentences dense in a target sound, for focused drilling
+
+use anyhow::Result;
+
+use crate::articulation::{EXAMPLE_CANDIDATES, matches_symbol};
+use crate::docker::MfaDialect;
+use crate::scoring::{Dictionary, cached_dictionary, expected_word_phonemes};
+
+/// Most sentences returned for one sound
+pub const MAX_DRILL_SENTENCES: usize = 20;
+
+/// Words filled into each generated sentence
+const WORDS_PER_TEMPLATE: usize = 3;
+
+/// Sentences chosen to cover the sounds of English, many of them repeating one sound
+const CORPUS: &[&str] = &[
+    // Dental fricatives
+    "This is the thing that they thought of there.",
+    "The three brothers gathered together on Thursday.",
+    "Their father and mother breathe the smooth southern air.",
+    "Think through the theory with thirty thin threads.",
+    "Those clothes are rather worth the wealth.",
+    "Both of them are healthy and thankful.",
+    // Approximants
+    "Red roses really are rare in rural regions.",
+    "Rory ran around the rocky river.",
+    "Lovely little lambs leap along the lane.",
+    "Laura will feel well after a long sleep.",
+    "We were wondering where the wet wool went.",
+    "Why would Will wait in the wild woods?",
+    "Yesterday the young yak yawned at you.",
+    // Labiodentals and bilabials
+    "Vivian values every visit to the village.",
+    "Five very vivid violets were in the vase.",
+    "Fred found four fresh fish for Friday.",
+    "Peter picked a pack of purple peppers.",
+    "Bobby bought a big blue ball for the baby.",
+    "My mother made marmalade in May.",
+    // Sibilants
+    "She sells seashells by the seashore.",
+    "The sunny season seems so pleasant.",
+    "Zoe's zebras were dozing at the zoo.",
+    "Sharon should wash the shiny dishes.",
+    "I measure the usual pleasure of leisure.",
+    "The vision was a casual illusion.",
+    // Affricates
+    "Charlie chose cheap cheese and chips.",
+    "Each child watched the church bells.",
+    "George enjoyed the jam and the juice in June.",
+    "The judge urged the jury to be just.",
+    // Stops
+    "Tom took ten tiny tables to town.",
+    "Did Dan dig deep enough to find the dog?",
+    "Kate kept the cake in the kitchen cupboard.",
+    "Greg gave the girl a good green bag.",
+    "Better butter makes a better batter.",
+    // Nasals and velars
+    "Nine nice nurses need new notebooks.",
+    "The king is singing a long song.",
+    "Running and jumping are amazing things.",
+    "Hungry people are thinking about dinner.",
+    // Glottal
+    "Harry hurried home to help his happy horse.",
+    "How high is the hill behind the house?",
+    // Front vowels
+    "Pete sees the green trees by the sea.",
+    "Please keep the key in the leaf of the book.",
+    "Bill will fix the little ship in six minutes.",
+    "The big fish swims in the river with its fins.",
+    "Ted said the red bed was ten pence.",
+    "Every friend went to rest at the hotel.",
+    "The black cat sat on a flat mat.",
+    "Sam has a bad habit of packing his hat.",
+    // Central vowels
+    "The sun comes up but the bus is under the hut.",
+    "My brother loves his lovely summer jumper.",
+    "The first bird heard the early word.",
+    "Her nurse learned to turn the third curve.",
+    "A banana is a popular fruit around the world.",
+    // Back vowels
+    "Sue knew the blue moon would soon rise.",
+    "Who threw the shoe into the pool?",
+    "Look at the good book that the cook took.",
+    "Put the wool in the full bush.",
+    "Paul saw the tall wall in the fall.",
+    "Laura brought a small straw ball.",
+    "The doctor got a lot of hot coffee.",
+    "Father parked the car far from the farm.",
+    // Diphthongs
+    "My bright kite flies high in the sky.",
+    "I like to ride my bike at night.",
+    "Kay may play the game again today.",
+    "The rain in Spain stays mainly on the plain.",
+    "Joe showed the old road to the boat.",
+    "Don't go home alone in the snow.",
+    "The boy enjoyed the noisy toy.",
+    "Roy found a coin in the soil.",
+    "Now the brown cow is out of the house.",
+    "How loud is the crowd in the town?",
+    "Here is a clear idea near the pier.",
+    "Where is the chair and the spare pair?",
+    "Sure, the tourists are poor.",
+    // Mixed everyday speech
+    "Could you tell me the way to the station?",
+    "I would like a cup of tea with milk.",
+    "What time does the next train leave?",
+    "The weather is nicer than it was yesterday.",
+];
+
+/// Sentence templates filled with words containing the target sound
+const TEMPLATES: &[&str] = &[
+    "Say {}, {}, and {} slowly.",
+    "Repeat after me: {}, {}, {}.",
+    "Now try {}, then {}, then {}.",
+    "Listen for the sound in {}, {}, and {}.",
+];
+
+/// A sentence for drilling one sound
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrillSentence {
+    pub text: String,
+    /// Dictionary pronunciation of each word, in order
+    pub words: Vec<(String, Vec<String>)>,
+    /// Occurrences of the target sound
+    pub target_count: usize,
+    /// Total phonemes in the sentence
+    pub phoneme_count: usize,
+    /// Whether the sentence was filled from a template rather than taken from the corpus
+    pub generated: bool,
+}
+
+impl DrillSentence {
+    fn new(dictionary: &Dictionary, symbol: &str, text: String, generated: bool) -> Self {
+        let words = expected_word_phonemes(dictionary, &text);
+        let phonemes = words.iter().flat_map(|(_, phonemes)| phonemes);
+
+        Self {
+            target_count: phonemes
+                .clone()
+                .filter(|phone| matches_symbol(phone, symbol))
+                .count(),
+            phoneme_count: phonemes.count(),
+            text,
+            words,
+            generated,
+        }
+    }
+
+    /// Share of the sentence's phonemes that are the target sound
+    pub fn density(&self) -> f64 {
+        if self.phoneme_count == 0 {
+            0.0
+        } else {
+            self.target_count as f64 / self.phoneme_count as f64
+        }
+    }
+}
+
+/// Sentences densest in a sound, as pronounced in the dialect's dictionary
+pub fn drill_sentences(
+    symbol: &str,
+    dialect: MfaDialect,
+    count: usize,
+) -> Result<Vec<DrillSentence>> {
+    let dictionary = cached_dictionary(dialect)?;
+    Ok(rank_sentences(&dictionary, symbol, count))
+}
+
+/// Rank corpus sentences by density of the sound, topping up from templates
+///
+/// Corpus sentences come first. When too few contain the sound, templates are
+/// filled with the common words densest in it, each word used once.
+pub fn rank_sentences(dictionary: &Dictionary, symbol: &str, count: usize) -> Vec<DrillSentence> {
+    let count = count.min(MAX_DRILL_SENTENCES);
+
+    let mut sentences: Vec<DrillSentence> = CORPUS
+        .iter()
+        .map(|text| DrillSentence::new(dictionary, symbol, text.to_string(), false))
+        .filter(|sentence| sentence.target_count > 0)
+        .collect();
+    sentences.sort_by(|a, b| {
+        b.density()
+            .total_cmp(&a.density())
+            .then(b.target_count.cmp(&a.target_count))
+    });
+    sentences.truncate(count);
+
+    if sentences.len() < count {
+        let words = filler_words(dictionary, symbol);
+        let generated = words
+            .chunks_exact(WORDS_PER_TEMPLATE)
+            .zip(TEMPLATES.iter().cycle())
+            .map(|(chunk, template)| {
+                let text = chunk.iter().fold(template.to_string(), |text, word| {
+                    text.replacen("{}", word, 1)
+                });
+                DrillSentence::new(dictionary, symbol, text, true)
+            })
+            .take(count - sentences.len());
+        sentences.extend(generated);
+    }
+
+    sentences
+}
+
+/// Common words containing the sound, densest first
+fn filler_words(dictionary: &Dictionary, symbol: &str) -> Vec<String> {
+    let mut words: Vec<(String, f64)> = CORPUS
+        .iter()
+        .flat_map(|text| text.split_whitespace())
+        .chain(EXAMPLE_CANDIDATES.iter().copied())
+        .flat_map(|text| expected_word_phonemes(dictionary, text))
+        .filter_map(|(word, phonemes)| {
+            let hits = phonemes
+                .iter()
+                .filter(|phone| matches_symbol(phone, symbol))
+                .count();
+            (hits > 0).then(|| (word, hits as f64 / phonemes.len() as f64))
+        })
+        .collect();
+
+    words.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    words.dedup_by(|a, b| a.0 == b.0);
+    words.into_iter().map(|(word, _)| word).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_sentences_prefers_dense_sentences() -> Result<()> {
+        let dictionary = cached_dictionary(MfaDialect::AmericanEnglish)?;
+
+        let sentences = rank_sentences(&dictionary, "ð", 3);
+        assert_eq!(sentences.len(), 3);
+        assert!(sentences.iter().all(|s| s.target_count > 0 && !s.generated));
+        assert!(sentences[0].density() >= sentences[2].density());
+        assert!(sentences[0].text.contains("th"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rank_sentences_fills_templates() {
+        // Only two corpus sentences have /ʒ/, so the rest are generated
+        let dictionary = Dictionary::from_pairs(&[
+            ("measure", "m ɛ ʒ ɚ"),
+            ("pleasure", "p l ɛ ʒ ɚ"),
+            ("leisure", "l iː ʒ ɚ"),
+            ("usual", "j uː ʒ u ə l"),
+            ("casual", "k æ ʒ u ə l"),
+            ("vision", "v ɪ ʒ ə n"),
+            ("illusion", "ɪ l uː ʒ ə n"),
+        ]);
+
+        let sentences = rank_sentences(&dictionary, "ʒ", 5);
+        assert_eq!(sentences.len(), 4);
+        assert!(sentences[..2].iter().all(|s| !s.generated));
+
+        let generated = &sentences[2..];
+        assert!(generated.iter().all(|s| s.generated));
+        assert!(
+            generated
+                .iter()
+                .all(|s| s.target_count == WORDS_PER_TEMPLATE)
+        );
+        assert!(!generated[0].text.contains("{}"));
+    }
+
+    #[test]
+    fn test_rank_sentences_caps_count() {
+        let dictionary = Dictionary::from_pairs(&[("the", "ð ə")]);
+        assert!(rank_sentences(&dictionary, "ð", 100).len() <= MAX_DRILL_SENTENCES);
+        assert!(rank_sentences(&dictionary, "ʒ", 5).is_empty());
+    }
+}
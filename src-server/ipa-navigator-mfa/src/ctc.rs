@@ -0,0 +1,397 @@
+//! In-process CTC segmentation aligner over a wav2vec2 phoneme recognition model
+//!
+//! The model is expected to be a wav2vec2 CTC model exported to ONNX with a single
+//! `input_values` input of 16 kHz mono samples and a `logits` output, together with
+//! the `vocab.json` of its tokenizer. Boundaries come from forced alignment of the
+//! dictionary pronunciation; the label reported for each phone is what the model
+//! recognised most strongly inside that span.
+
+use anyhow::{Context, Result};
+use ort::{
+    session::{Session, builder::GraphOptimizationLevel},
+    value::Tensor,
+};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::aligner::Aligner;
+use crate::audio::{read_wav_mono, resample_linear};
+use crate::constants::ASSETS_PATH;
+use crate::docker::MfaDialect;
+use crate::mfa_parser::MfaSegment;
+use crate::scoring::{cached_dictionary, expected_word_phonemes};
+
+/// Sample rate expected by wav2vec2 models
+pub const CTC_SAMPLE_RATE: u32 = 16000;
+
+/// Locations of the CTC model and its vocabulary
+#[derive(Debug, Clone)]
+pub struct CtcConfig {
+    pub model_path: PathBuf,
+    pub vocab_path: PathBuf,
+}
+
+impl CtcConfig {
+    /// Read `CTC_MODEL_PATH` and `CTC_VOCAB_PATH`, defaulting to `assets/Wav2Vec2`
+    pub fn from_env() -> Self {
+        let model_path = env::var("CTC_MODEL_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(format!("{}/Wav2Vec2/model.onnx", *ASSETS_PATH)));
+
+        let vocab_path = env::var("CTC_VOCAB_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(format!("{}/Wav2Vec2/vocab.json", *ASSETS_PATH)));
+
+        Self {
+            model_path,
+            vocab_path,
+        }
+    }
+}
+
+/// Forced aligner running a wav2vec2 CTC model in-process
+pub struct CtcAligner {
+    session: Mutex<Session>,
+    vocab: HashMap<String, usize>,
+    symbols: Vec<String>,
+    blank: usize,
+}
+
+impl CtcAligner {
+    /// Load the aligner from the configured model and vocabulary
+    pub fn from_env() -> Result<Self> {
+        Self::new(&CtcConfig::from_env())
+    }
+
+    /// Load the aligner from a model and vocabulary on disk
+    pub fn new(config: &CtcConfig) -> Result<Self> {
+        if !config.model_path.exists() {
+            return Err(anyhow::anyhow!(
+                "CTC model not found at path: {}",
+                config.model_path.display()
+            ));
+        }
+
+        let vocab_json = fs::read_to_string(&config.vocab_path)
+            .with_context(|| format!("Failed to read CTC vocabulary {:?}", config.vocab_path))?;
+        let vocab = parse_vocab(&vocab_json)?;
+
+        let mut symbols = vec![String::new(); vocab.values().max().map_or(0, |&id| id + 1)];
+        for (symbol, &id) in &vocab {
+            symbols[id] = symbol.clone();
+        }
+
+        let blank = vocab.get("<pad>").copied().unwrap_or(0);
+
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .commit_from_file(&config.model_path)?;
+
+        Ok(Self {
+            session: Mutex::new(session),
+            vocab,
+            symbols,
+            blank,
+        })
+    }
+
+    /// Run the model and return per-frame log probabilities over the vocabulary
+    fn log_probs(&self, samples: Vec<f32>) -> Result<Vec<Vec<f32>>> {
+        let input = Tensor::from_array(([1, samples.len()], samples))?;
+
+        let mut session = self
+            .session
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire CTC session lock"))?;
+        let outputs = session.run(ort::inputs!["input_values" => input])?;
+
+        let (shape, data) = outputs[0].try_extract_tensor::<f32>()?;
+        let vocab_size = *shape.last().unwrap_or(&0) as usize;
+        if vocab_size == 0 {
+            return Err(anyhow::anyhow!("CTC model returned empty logits"));
+        }
+
+        Ok(data.chunks_exact(vocab_size).map(log_softmax).collect())
+    }
+
+    /// Find the vocabulary id for a dictionary phone
+    fn token_id(&self, phone: &str) -> Option<usize> {
+        self.vocab
+            .get(phone)
+            .or_else(|| self.vocab.get(phone.trim_end_matches('ː')))
+            .copied()
+    }
+}
+
+impl Aligner for CtcAligner {
+    fn name(&self) -> &'static str {
+        "ctc"
+    }
+
+    #[tracing::instrument(name = "mfa.align", skip_all, fields(backend = "ctc"))]
+    fn align(
+        &self,
+        audio_data: &[u8],
+        transcript: &str,
+        dialect: MfaDialect,
+    ) -> Result<Vec<MfaSegment>> {
+        let samples = decode_wav(audio_data)?;
+        let duration = samples.len() as f64 / CTC_SAMPLE_RATE as f64;
+
+        let dictionary = cached_dictionary(dialect)?;
+        let words = expected_word_phonemes(&dictionary, transcript);
+
+        // Phones the model cannot represent are left out of the alignment
+        let mut phones = Vec::new();
+        for (word_index, (_, phonemes)) in words.iter().enumerate() {
+            for phone in phonemes {
+                match self.token_id(phone) {
+                    Some(id) => phones.push((word_index, phone.clone(), id)),
+                    None => tracing::debug!("Phone '{}' is not in the CTC vocabulary", phone),
+                }
+            }
+        }
+
+        let log_probs = self.log_probs(samples)?;
+        let tokens: Vec<usize> = phones.iter().map(|(_, _, id)| *id).collect();
+        let spans = ctc_forced_align(&log_probs, &tokens, self.blank)?;
+
+        let frame_duration = duration / log_probs.len().max(1) as f64;
+        let mut word_spans: Vec<Option<(f64, f64)>> = vec![None; words.len()];
+        let mut segments = Vec::with_capacity(phones.len() + words.len());
+
+        for ((word_index, phone, id), (start, end)) in phones.iter().zip(&spans) {
+            let begin = *start as f64 * frame_duration;
+            let finish = (*end + 1) as f64 * frame_duration;
+
+            let recognised = strongest_token(&log_probs[*start..=*end], self.blank);
+            let label = match recognised {
+                Some(token) if token != *id => self.symbols[token].clone(),
+                _ => phone.clone(),
+            };
+
+            segments.push(MfaSegment {
+                begin,
+                end: finish,
+                label,
+                segment_type: "phone".to_string(),
+                confidence: None,
+            });
+
+            let span = word_spans[*word_index].get_or_insert((begin, finish));
+            span.1 = finish;
+        }
+
+        for ((word, _), span) in words.iter().zip(word_spans) {
+            if let Some((begin, end)) = span {
+                segments.push(MfaSegment {
+                    begin,
+                    end,
+                    label: word.clone(),
+                    segment_type: "word".to_string(),
+                    confidence: None,
+                });
+            }
+        }
+
+        Ok(segments)
+    }
+}
+
+/// Parse a HuggingFace `vocab.json` mapping of token to id
+fn parse_vocab(json: &str) -> Result<HashMap<String, usize>> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).context("CTC vocabulary is not valid JSON")?;
+
+    let object = value
+        .as_object()
+        .ok_or_else(|| anyhow::anyhow!("CTC vocabulary must be a JSON object"))?;
+
+    object
+        .iter()
+        .map(|(symbol, id)| {
+            id.as_u64()
+                .map(|id| (symbol.clone(), id as usize))
+                .ok_or_else(|| anyhow::anyhow!("Invalid id for CTC token '{}'", symbol))
+        })
+        .collect()
+}
+
+/// Decode WAV bytes into normalised 16 kHz mono samples
+fn decode_wav(audio_data: &[u8]) -> Result<Vec<f32>> {
+    let (mono, sample_rate) = read_wav_mono(audio_data)?;
+    let mut samples = resample_linear(&mono, sample_rate, CTC_SAMPLE_RATE);
+
+    // wav2vec2 expects zero-mean, unit-variance input
+    let len = samples.len().max(1) as f32;
+    let mean = samples.iter().sum::<f32>() / len;
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / len;
+    let std_dev = (variance + 1e-7).sqrt();
+    for sample in &mut samples {
+        *sample = (*sample - mean) / std_dev;
+    }
+
+    Ok(samples)
+}
+
+/// Numerically stable log-softmax of one frame of logits
+fn log_softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let log_sum = logits.iter().map(|x| (x - max).exp()).sum::<f32>().ln() + max;
+    logits.iter().map(|x| x - log_sum).collect()
+}
+
+/// Most probable non-blank token over a span of frames
+fn strongest_token(frames: &[Vec<f32>], blank: usize) -> Option<usize> {
+    let vocab_size = frames.first()?.len();
+    (0..vocab_size)
+        .filter(|&token| token != blank)
+        .map(|token| {
+            let best = frames
+                .iter()
+                .map(|frame| frame[token])
+                .fold(f32::NEG_INFINITY, f32::max);
+            (token, best)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(token, _)| token)
+}
+
+/// Viterbi forced alignment of a token sequence to CTC log probabilities
+///
+/// Returns the inclusive first and last frame of every token.
+fn ctc_forced_align(
+    log_probs: &[Vec<f32>],
+    tokens: &[usize],
+    blank: usize,
+) -> Result<Vec<(usize, usize)>> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let frames = log_probs.len();
+    if frames < tokens.len() {
+        return Err(anyhow::anyhow!(
+            "Recording is too short to align {} phones",
+            tokens.len()
+        ));
+    }
+
+    // Blank-interleaved label sequence: blank, t0, blank, t1, ..., blank
+    let mut labels = vec![blank; tokens.len() * 2 + 1];
+    for (i, &token) in tokens.iter().enumerate() {
+        labels[i * 2 + 1] = token;
+    }
+    let states = labels.len();
+
+    let mut score = vec![f32::NEG_INFINITY; states];
+    let mut back = vec![0u8; frames * states];
+
+    score[0] = log_probs[0][labels[0]];
+    score[1] = log_probs[0][labels[1]];
+
+    for t in 1..frames {
+        let mut next = vec![f32::NEG_INFINITY; states];
+        for s in 0..states {
+            let mut best = score[s];
+            let mut step = 0u8;
+
+            if s >= 1 && score[s - 1] > best {
+                best = score[s - 1];
+                step = 1;
+            }
+
+            if s >= 2 && labels[s] != blank && labels[s] != labels[s - 2] && score[s - 2] > best {
+                best = score[s - 2];
+                step = 2;
+            }
+
+            if best > f32::NEG_INFINITY {
+                next[s] = best + log_probs[t][labels[s]];
+                back[t * states + s] = step;
+            }
+        }
+        score = next;
+    }
+
+    // The path must end on the last token or the trailing blank
+    let mut state = if score[states - 1] >= score[states - 2] {
+        states - 1
+    } else {
+        states - 2
+    };
+
+    // Walk the path backwards, so the first frame seen for a token is its last
+    let mut spans = vec![(usize::MAX, usize::MAX); tokens.len()];
+    for t in (0..frames).rev() {
+        if state % 2 == 1 {
+            let span = &mut spans[state / 2];
+            if span.1 == usize::MAX {
+                span.1 = t;
+            }
+            span.0 = t;
+        }
+        state -= back[t * states + state] as usize;
+    }
+
+    if spans.iter().any(|span| span.0 == usize::MAX) {
+        return Err(anyhow::anyhow!("CTC alignment failed to place every phone"));
+    }
+
+    Ok(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build log probabilities where `best[t]` dominates frame `t`
+    fn frames(best: &[usize], vocab_size: usize) -> Vec<Vec<f32>> {
+        best.iter()
+            .map(|&b| {
+                let logits: Vec<f32> = (0..vocab_size)
+                    .map(|i| if i == b { 5.0 } else { 0.0 })
+                    .collect();
+                log_softmax(&logits)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_forced_align_spans() {
+        // blank, a, a, blank, b, blank
+        let log_probs = frames(&[0, 1, 1, 0, 2, 0], 3);
+        let spans = ctc_forced_align(&log_probs, &[1, 2], 0).unwrap();
+        assert_eq!(spans, vec![(1, 2), (4, 4)]);
+    }
+
+    #[test]
+    fn test_forced_align_repeated_token() {
+        // Repeated tokens need a blank between them
+        let log_probs = frames(&[1, 0, 1], 2);
+        let spans = ctc_forced_align(&log_probs, &[1, 1], 0).unwrap();
+        assert_eq!(spans, vec![(0, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn test_forced_align_too_short() {
+        let log_probs = frames(&[1], 3);
+        assert!(ctc_forced_align(&log_probs, &[1, 2], 0).is_err());
+    }
+
+    #[test]
+    fn test_strongest_token_ignores_blank() {
+        let log_probs = frames(&[0, 2, 0], 3);
+        assert_eq!(strongest_token(&log_probs, 0), Some(2));
+    }
+
+    #[test]
+    fn test_parse_vocab() {
+        let vocab = parse_vocab(r#"{"<pad>": 0, "a": 1, "tʃ": 2}"#).unwrap();
+        assert_eq!(vocab.get("tʃ"), Some(&2));
+        assert!(parse_vocab("[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! Estimating how hard a text is to pronounce, for leveling lesson content
+
+use crate::exercise::build_exercises;
+use crate::phoneme::{is_vowel, parse_ipa};
+use crate::scoring::Dictionary;
+
+/// Approximate share of English speech sounds, in percent
+///
+/// Symbols are phonemes; dictionary phones are mapped to them by
+/// [`frequency_key`]. Phones missing from the table count as rare.
+const PHONEME_FREQUENCIES: &[(&str, f64)] = &[
+    ("ə", 10.7),
+    ("ɪ", 8.3),
+    ("n", 7.1),
+    ("t", 6.9),
+    ("s", 4.8),
+    ("ɹ", 4.4),
+    ("d", 4.3),
+    ("l", 4.0),
+    ("i", 3.6),
+    ("k", 3.2),
+    ("ð", 3.1),
+    ("ɛ", 3.0),
+    ("m", 3.0),
+    ("z", 2.8),
+    ("w", 2.5),
+    ("æ", 2.1),
+    ("p", 2.0),
+    ("v", 1.9),
+    ("b", 1.8),
+    ("eɪ", 1.8),
+    ("aɪ", 1.8),
+    ("f", 1.7),
+    ("h", 1.6),
+    ("u", 1.6),
+    ("ʌ", 1.5),
+    ("ɚ", 1.5),
+    ("ɾ", 1.5),
+    ("oʊ", 1.3),
+    ("ɑ", 1.3),
+    ("ŋ", 1.0),
+    ("ɔ", 0.9),
+    ("j", 0.9),
+    ("ɡ", 0.8),
+    ("ʃ", 0.8),
+    ("aʊ", 0.6),
+    ("ɝ", 0.5),
+    ("ɜ", 0.5),
+    ("ʊ", 0.5),
+    ("dʒ", 0.5),
+    ("θ", 0.4),
+    ("tʃ", 0.4),
+    ("ʔ", 0.3),
+    ("ʒ", 0.1),
+    ("ɔɪ", 0.1),
+];
+
+/// Allophones written by MFA dictionaries, and the phoneme learners hear them as
+const ALLOPHONES: &[(&str, &str)] = &[
+    ("c", "k"),
+    ("ɟ", "ɡ"),
+    ("ç", "h"),
+    ("ɲ", "n"),
+    ("ʎ", "l"),
+    ("ɫ", "l"),
+    ("ɾ̃", "n"),
+    ("ɐ", "ə"),
+    ("ʉ", "u"),
+    ("ɒ", "ɑ"),
+];
+
+/// Frequency at or above which a phoneme has no rarity, in percent
+const COMMON_FREQUENCY: f64 = 3.0;
+
+/// Frequency at or below which a phoneme is fully rare, in percent
+const RARE_FREQUENCY: f64 = 0.3;
+
+/// Rarity at which a phoneme is reported as rare
+const RARE_THRESHOLD: f64 = 0.5;
+
+/// Average phoneme rarity at which a text counts as fully rare; everyday
+/// English averages around 0.1
+const RARITY_SATURATION: f64 = 0.25;
+
+/// Consonants beyond the first in a run, per word, that count as fully complex
+const CLUSTER_SATURATION: f64 = 1.5;
+
+/// Words per sentence below which length adds no difficulty
+const EASY_SENTENCE_WORDS: f64 = 6.0;
+
+/// Words per sentence at which length is fully difficult
+const HARD_SENTENCE_WORDS: f64 = 20.0;
+
+// Weights of phoneme rarity, cluster complexity, and sentence length in the overall score
+const RARITY_WEIGHT: f64 = 0.35;
+const CLUSTER_WEIGHT: f64 = 0.35;
+const LENGTH_WEIGHT: f64 = 0.3;
+
+/// Lesson level a text suits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifficultyLevel {
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+impl DifficultyLevel {
+    /// Level for an overall score between 0 and 1
+    pub fn from_score(score: f64) -> Self {
+        if score < 0.3 {
+            DifficultyLevel::Beginner
+        } else if score < 0.5 {
+            DifficultyLevel::Intermediate
+        } else {
+            DifficultyLevel::Advanced
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DifficultyLevel::Beginner => "beginner",
+            DifficultyLevel::Intermediate => "intermediate",
+            DifficultyLevel::Advanced => "advanced",
+        }
+    }
+}
+
+/// Difficulty of a text, with the score and phoneme components between 0 and 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextDifficulty {
+    /// Weighted combination of the components
+    pub score: f64,
+    pub level: DifficultyLevel,
+    /// Average rarity of the text's phonemes in English speech, scaled
+    pub phoneme_rarity: f64,
+    /// Consonants in clusters beyond the first per word, scaled
+    pub cluster_complexity: f64,
+    /// Average words per sentence
+    pub sentence_length: f64,
+    pub words: usize,
+    pub sentences: usize,
+    /// Rare phonemes in the text, rarest first
+    pub rare_phonemes: Vec<String>,
+    /// Words without a dictionary pronunciation, which only count toward sentence length
+    pub missing_words: Vec<String>,
+}
+
+/// Score a text by phoneme rarity, consonant clusters, and sentence length
+pub fn estimate_difficulty(dictionary: &Dictionary, text: &str) -> TextDifficulty {
+    let exercises = build_exercises(dictionary, text);
+
+    let sentences = exercises.len();
+    let words: usize = exercises
+        .iter()
+        .map(|exercise| exercise.words.len() + exercise.missing_words.len())
+        .sum();
+    let pronounced: Vec<&Vec<String>> = exercises
+        .iter()
+        .flat_map(|exercise| exercise.words.iter().map(|(_, phonemes)| phonemes))
+        .collect();
+
+    let phonemes: Vec<String> = pronounced
+        .iter()
+        .flat_map(|phonemes| phonemes.iter())
+        .map(|phone| frequency_key(phone))
+        .collect();
+    let phoneme_rarity =
+        (mean(phonemes.iter().map(|phone| rarity(phone))) / RARITY_SATURATION).min(1.0);
+
+    let extra_consonants: usize = pronounced
+        .iter()
+        .map(|phonemes| extra_cluster_consonants(phonemes))
+        .sum();
+    let cluster_complexity = if pronounced.is_empty() {
+        0.0
+    } else {
+        (extra_consonants as f64 / pronounced.len() as f64 / CLUSTER_SATURATION).min(1.0)
+    };
+
+    let sentence_length = if sentences == 0 {
+        0.0
+    } else {
+        words as f64 / sentences as f64
+    };
+    let length_score = ((sentence_length - EASY_SENTENCE_WORDS)
+        / (HARD_SENTENCE_WORDS - EASY_SENTENCE_WORDS))
+        .clamp(0.0, 1.0);
+
+    let score = RARITY_WEIGHT * phoneme_rarity
+        + CLUSTER_WEIGHT * cluster_complexity
+        + LENGTH_WEIGHT * length_score;
+
+    let mut rare_phonemes: Vec<String> = phonemes
+        .into_iter()
+        .filter(|phone| rarity(phone) >= RARE_THRESHOLD)
+        .collect();
+    rare_phonemes.sort_by(|a, b| frequency(a).total_cmp(&frequency(b)).then(a.cmp(b)));
+    rare_phonemes.dedup();
+
+    TextDifficulty {
+        score,
+        level: DifficultyLevel::from_score(score),
+        phoneme_rarity,
+        cluster_complexity,
+        sentence_length,
+        words,
+        sentences,
+        rare_phonemes,
+        missing_words: exercises
+            .into_iter()
+            .flat_map(|exercise| exercise.missing_words)
+            .collect(),
+    }
+}
+
+/// The phoneme a dictionary phone realises, without length, aspiration, or secondary articulation
+fn frequency_key(phone: &str) -> String {
+    let base = match parse_ipa(phone) {
+        Some(parsed) => parsed.base,
+        None => phone.trim_end_matches(['ː', 'ʰ', 'ʲ', 'ʷ']).to_string(),
+    };
+
+    ALLOPHONES
+        .iter()
+        .find(|(allophone, _)| *allophone == base)
+        .map_or(base, |(_, phoneme)| phoneme.to_string())
+}
+
+fn frequency(phone: &str) -> f64 {
+    PHONEME_FREQUENCIES
+        .iter()
+        .find(|(symbol, _)| *symbol == phone)
+        .map_or(0.0, |&(_, frequency)| frequency)
+}
+
+/// Rarity of a phoneme, from 0 for common to 1 for rare, on a log scale
+fn rarity(phone: &str) -> f64 {
+    let frequency = frequency(phone).max(RARE_FREQUENCY);
+    ((COMMON_FREQUENCY.ln() - frequency.ln()) / (COMMON_FREQUENCY.ln() - RARE_FREQUENCY.ln()))
+        .clamp(0.0, 1.0)
+}
+
+/// Consonants beyond the first in each run of consonants in a word
+fn extra_cluster_consonants(phonemes: &[String]) -> usize {
+    phonemes
+        .split(|phone| is_vowel(phone))
+        .map(|run| run.len().saturating_sub(1))
+        .sum()
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> Dictionary {
+        [
+            ("the", "ð ə"),
+            ("cat", "kʰ æ t"),
+            ("sat", "s æ t"),
+            ("on", "ɑ n"),
+            ("mat", "m æ t"),
+            ("i", "aj"),
+            ("see", "s iː"),
+            ("it", "ɪ t"),
+            ("strengths", "s t ɹ ɛ ŋ k θ s"),
+            ("of", "ʌ v"),
+            ("sixth", "s ɪ k s θ"),
+            ("judge", "dʒ ʌ dʒ"),
+            ("measured", "m ɛ ʒ ɚ d"),
+            ("twelfth", "t w ɛ l f θ"),
+            ("rhythms", "ɹ ɪ ð ə m z"),
+            ("thoroughly", "θ ɝ ɹ oʊ l i"),
+        ]
+        .into_iter()
+        .map(|(word, phones)| {
+            (
+                word.to_string(),
+                phones.split(' ').map(String::from).collect(),
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_rarity() {
+        assert_eq!(rarity("ə"), 0.0);
+        assert_eq!(rarity("ʒ"), 1.0);
+        assert_eq!(rarity("ʘ"), 1.0);
+        assert!(rarity("θ") > rarity("f"));
+        assert_eq!(frequency_key("tʰ"), "t");
+        assert_eq!(frequency_key("aj"), "aɪ");
+    }
+
+    #[test]
+    fn test_extra_cluster_consonants() {
+        let phones = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(extra_cluster_consonants(&phones("kʰ æ t")), 0);
+        assert_eq!(extra_cluster_consonants(&phones("s t ɹ ɛ ŋ k θ s")), 5);
+    }
+
+    #[test]
+    fn test_estimate_difficulty_levels() {
+        let dictionary = dictionary();
+
+        let easy = estimate_difficulty(&dictionary, "The cat sat on the mat. I see it.");
+        assert_eq!(easy.level, DifficultyLevel::Beginner);
+        assert_eq!((easy.words, easy.sentences), (9, 2));
+        assert!(easy.missing_words.is_empty());
+
+        let hard = estimate_difficulty(
+            &dictionary,
+            "The judge thoroughly measured the strengths of the twelfth and sixth rhythms.",
+        );
+        assert_eq!(hard.level, DifficultyLevel::Advanced);
+        assert!(hard.score > easy.score);
+        assert!(hard.cluster_complexity > easy.cluster_complexity);
+        assert_eq!(hard.rare_phonemes[0], "ʒ");
+        assert_eq!(hard.missing_words, vec!["and"]);
+    }
+
+    #[test]
+    fn test_estimate_difficulty_empty() {
+        let difficulty = estimate_difficulty(&dictionary(), "  ...  ");
+        assert_eq!(difficulty.score, 0.0);
+        assert_eq!(difficulty.level, DifficultyLevel::Beginner);
+    }
+}
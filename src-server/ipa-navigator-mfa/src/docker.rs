@@ -1,7 +1,13 @@
 //! Functions for interacting with MFA Docker container
 
+use crate::confidence::merge_alignment_scores;
 use crate::constants::ASSETS_PATH;
+use crate::container::{CONTAINER_MANAGER, ContainerManager, parse_env};
+use crate::mfa_parser::{AlignmentFormat, find_alignment};
+use crate::platform::container_path;
+use crate::scoring::Rhoticity;
 use anyhow::{Context, Result};
+use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -33,6 +39,9 @@ pub enum MfaDialect {
 }
 
 impl MfaDialect {
+    /// Every supported dialect
+    pub const ALL: [MfaDialect; 2] = [MfaDialect::AmericanEnglish, MfaDialect::BritishEnglish];
+
     /// Get the MFA dictionary name for this dialect
     pub fn dictionary_name(&self) -> &'static str {
         match self {
@@ -48,11 +57,156 @@ impl MfaDialect {
             MfaDialect::BritishEnglish => &UK_DICTIONARY_PATH,
         }
     }
+
+    /// Whether this dialect's dictionary writes post-vocalic /ɹ/
+    pub fn rhoticity(&self) -> Rhoticity {
+        match self {
+            MfaDialect::AmericanEnglish => Rhoticity::Rhotic,
+            MfaDialect::BritishEnglish => Rhoticity::NonRhotic,
+        }
+    }
 }
 
 /// Standard acoustic model to use for all alignments
 pub const DEFAULT_ACOUSTIC_MODEL: &str = "english_mfa";
 
+/// Alignment options read from the environment, see [`AlignOptions::from_env`]
+pub static ALIGN_OPTIONS: LazyLock<AlignOptions> = LazyLock::new(AlignOptions::from_env);
+
+/// Beam widths for one MFA alignment pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Beams {
+    /// Beam used to decode each utterance
+    pub beam: u32,
+    /// Wider beam MFA retries an utterance with before giving up on it
+    pub retry_beam: u32,
+}
+
+impl Beams {
+    /// Parse "beam:retry_beam", e.g. "100:400"
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (beam, retry_beam) = spec.split_once(':')?;
+        let beam = beam.trim().parse().ok().filter(|&n| n > 0)?;
+        let retry_beam = retry_beam.trim().parse().ok().filter(|&n| n >= beam)?;
+        Some(Self { beam, retry_beam })
+    }
+}
+
+/// Options passed to `mfa align`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlignOptions {
+    /// Beams of the first pass
+    pub beams: Beams,
+    /// Wider beams to realign utterances that failed with, in order
+    pub fallback_beams: Vec<Beams>,
+    /// Whether alignments keep the transcript's original spelling of each word
+    pub include_original_text: bool,
+    /// Format MFA writes alignments in
+    pub output_format: AlignmentFormat,
+}
+
+impl Default for AlignOptions {
+    fn default() -> Self {
+        Self {
+            // MFA's own defaults
+            beams: Beams {
+                beam: 10,
+                retry_beam: 40,
+            },
+            fallback_beams: vec![
+                Beams {
+                    beam: 100,
+                    retry_beam: 400,
+                },
+                Beams {
+                    beam: 1000,
+                    retry_beam: 4000,
+                },
+            ],
+            include_original_text: true,
+            output_format: AlignmentFormat::TextGrid,
+        }
+    }
+}
+
+impl AlignOptions {
+    /// Read `MFA_BEAM`, `MFA_RETRY_BEAM`, `MFA_FALLBACK_BEAMS`,
+    /// `MFA_INCLUDE_ORIGINAL_TEXT`, and `MFA_OUTPUT_FORMAT`
+    ///
+    /// `MFA_FALLBACK_BEAMS` is a comma-separated list of "beam:retry_beam"
+    /// pairs, or empty to report failures without retrying. `MFA_OUTPUT_FORMAT`
+    /// is "textgrid" or "json".
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let beam = env::var("MFA_BEAM")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(defaults.beams.beam);
+        let retry_beam = env::var("MFA_RETRY_BEAM")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n| n >= beam)
+            .unwrap_or(defaults.beams.retry_beam.max(beam));
+
+        let fallback_beams = match env::var("MFA_FALLBACK_BEAMS") {
+            Ok(v) => v
+                .split(',')
+                .map(str::trim)
+                .filter(|spec| !spec.is_empty())
+                .filter_map(|spec| {
+                    let beams = Beams::parse(spec);
+                    if beams.is_none() {
+                        tracing::warn!("Ignoring MFA fallback beams: {}", spec);
+                    }
+                    beams
+                })
+                .collect(),
+            Err(_) => defaults.fallback_beams,
+        };
+
+        let include_original_text = env::var("MFA_INCLUDE_ORIGINAL_TEXT")
+            .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "no"))
+            .unwrap_or(defaults.include_original_text);
+
+        let output_format = match env::var("MFA_OUTPUT_FORMAT") {
+            Ok(v) => AlignmentFormat::parse(&v).unwrap_or_else(|| {
+                tracing::warn!("Ignoring unknown MFA output format: {}", v);
+                defaults.output_format
+            }),
+            Err(_) => defaults.output_format,
+        };
+
+        Self {
+            beams: Beams { beam, retry_beam },
+            fallback_beams,
+            include_original_text,
+            output_format,
+        }
+    }
+
+    /// Beams of every pass, the first pass followed by the fallbacks
+    pub fn passes(&self) -> impl Iterator<Item = Beams> + '_ {
+        std::iter::once(self.beams).chain(self.fallback_beams.iter().copied())
+    }
+
+    /// Flags appended to `mfa align` for a pass with `beams`
+    pub fn flags(&self, beams: Beams) -> String {
+        let mut flags = format!(
+            "--clean --beam {} --retry_beam {}",
+            beams.beam, beams.retry_beam
+        );
+        if self.include_original_text {
+            flags.push_str(" --include_original_text");
+        }
+        if self.output_format == AlignmentFormat::Json {
+            flags.push_str(" --output_format json");
+        }
+        flags
+    }
+}
+
 /// Run MFA align using Docker or direct command to process audio
 ///
 /// # Arguments
@@ -60,48 +214,193 @@ pub const DEFAULT_ACOUSTIC_MODEL: &str = "english_mfa";
 /// * `dialect` - Dialect to use for pronunciation
 ///
 /// # Returns
-/// Path to the generated TextGrid file
+/// Path to the generated alignment file, a TextGrid unless configured otherwise
 pub fn run_mfa_align(job_dir: impl AsRef<Path>, dialect: MfaDialect) -> Result<PathBuf> {
+    run_mfa_align_with(job_dir, dialect, &ALIGN_OPTIONS)
+}
+
+/// Run MFA align as [`run_mfa_align`] does, with the given options
+pub fn run_mfa_align_with(
+    job_dir: impl AsRef<Path>,
+    dialect: MfaDialect,
+    options: &AlignOptions,
+) -> Result<PathBuf> {
     let job_dir = job_dir.as_ref();
+    run_mfa_align_corpus_with(job_dir, dialect, options)?;
+    find_alignment_file(job_dir)
+}
+
+/// Run MFA align over a corpus directory holding any number of `.wav`/`.lab` pairs
+///
+/// One alignment per aligned file is written next to its audio, named after the
+/// audio file's stem. Files MFA could not align have no alignment.
+pub fn run_mfa_align_corpus(corpus_dir: impl AsRef<Path>, dialect: MfaDialect) -> Result<()> {
+    run_mfa_align_corpus_with(corpus_dir, dialect, &ALIGN_OPTIONS)
+}
+
+/// Run MFA align over a corpus as [`run_mfa_align_corpus`] does, with the given options
+///
+/// Files left unaligned by a pass, or by a pass that failed outright, are
+/// aligned again with each of the fallback beams in turn. An error is only
+/// returned if the last pass failed and left files unaligned.
+#[tracing::instrument(name = "mfa.align", skip_all, fields(dictionary = dialect.dictionary_name()))]
+pub fn run_mfa_align_corpus_with(
+    corpus_dir: impl AsRef<Path>,
+    dialect: MfaDialect,
+    options: &AlignOptions,
+) -> Result<()> {
+    let corpus_dir = corpus_dir.as_ref();
+
+    let mut failure = None;
+    for (pass, beams) in options.passes().enumerate() {
+        let unaligned = unaligned_stems(corpus_dir)?;
+        if unaligned.is_empty() {
+            return Ok(());
+        }
+
+        let flags = options.flags(beams);
+        if pass == 0 {
+            failure = run_mfa_align_dir(corpus_dir, dialect, &flags).err();
+        } else {
+            tracing::warn!(
+                "Realigning {} unaligned files with beam {} and retry beam {}",
+                unaligned.len(),
+                beams.beam,
+                beams.retry_beam
+            );
+            // Only the failed files are aligned again, in a corpus of their own
+            let retry_dir = stage_retry(corpus_dir, &unaligned)?;
+            failure = run_mfa_align_dir(retry_dir.path(), dialect, &flags).err();
+            collect_alignments(retry_dir.path(), corpus_dir)?;
+        }
+
+        if let Some(e) = &failure {
+            tracing::warn!("MFA pass with beam {} failed: {:#}", beams.beam, e);
+        }
+    }
 
+    match failure {
+        Some(e) if !unaligned_stems(corpus_dir)?.is_empty() => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Run one `mfa align` pass with `flags` wherever MFA is available
+fn run_mfa_align_dir(corpus_dir: &Path, dialect: MfaDialect, flags: &str) -> Result<()> {
     // Check if we're inside a Docker container
     if is_running_in_docker() {
-        dbg!("Running in Docker container");
+        tracing::debug!("Running MFA inside this container");
         // Running inside Docker - try to run MFA directly if it's installed
-        run_mfa_align_container(job_dir, dialect)
-    } else {
-        dbg!("Running on host machine");
+        run_mfa_align_container(corpus_dir, dialect, flags)
+    } else if CONTAINER_MANAGER.config().enabled {
+        tracing::debug!("Running MFA in the managed container");
         // Running on host - use Docker exec approach
-        run_mfa_align_local(job_dir, dialect)
+        run_mfa_align_managed(&CONTAINER_MANAGER, corpus_dir, dialect, flags)
+    } else {
+        tracing::debug!("Running MFA on the host");
+        run_mfa_align_local(corpus_dir, dialect, flags)
+    }
+}
+
+/// Stems of the `.wav` files in a corpus without an alignment, in name order
+fn unaligned_stems(corpus_dir: &Path) -> Result<Vec<String>> {
+    let mut stems = Vec::new();
+    for entry in fs::read_dir(corpus_dir).context("Failed to read corpus directory")? {
+        let path = entry.context("Failed to read corpus entry")?.path();
+        if path.extension().is_some_and(|ext| ext == "wav")
+            && let Some(stem) = path.file_stem()
+            && find_alignment(corpus_dir, &stem.to_string_lossy()).is_none()
+        {
+            stems.push(stem.to_string_lossy().to_string());
+        }
+    }
+    stems.sort();
+    Ok(stems)
+}
+
+/// Copy the audio and transcript of each stem into a fresh directory inside the corpus
+///
+/// The directory stays under the corpus so it is visible to a managed container.
+fn stage_retry(corpus_dir: &Path, stems: &[String]) -> Result<tempfile::TempDir> {
+    let retry_dir = tempfile::Builder::new()
+        .prefix("retry-")
+        .tempdir_in(corpus_dir)
+        .context("Failed to create directory to realign in")?;
+
+    for stem in stems {
+        for extension in ["wav", "lab"] {
+            let name = format!("{}.{}", stem, extension);
+            let source = corpus_dir.join(&name);
+            if source.exists() {
+                fs::copy(&source, retry_dir.path().join(&name))
+                    .with_context(|| format!("Failed to copy {:?} to realign", source))?;
+            }
+        }
+    }
+
+    Ok(retry_dir)
+}
+
+/// Move the alignments of a realigned directory back into the corpus, with their scores
+fn collect_alignments(retry_dir: &Path, corpus_dir: &Path) -> Result<()> {
+    merge_alignment_scores(retry_dir, corpus_dir)
+        .context("Failed to merge the realigned files' scores")?;
+    for entry in fs::read_dir(retry_dir).context("Failed to read realigned directory")? {
+        let path = entry.context("Failed to read realigned entry")?.path();
+        if AlignmentFormat::of_path(&path).is_some()
+            && let Some(name) = path.file_name()
+        {
+            fs::rename(&path, corpus_dir.join(name))
+                .with_context(|| format!("Failed to move {:?} into the corpus", path))?;
+        }
     }
+    Ok(())
 }
 
-/// Check if we're running inside a Docker container
+/// Check if we're running inside a Docker or Podman container
 fn is_running_in_docker() -> bool {
     Path::new("/.dockerenv").exists()
+        || Path::new("/run/.containerenv").exists()
         || fs::read_to_string("/proc/1/cgroup")
             .map(|s| s.contains("/docker/"))
             .unwrap_or(false)
 }
 
+/// Shell command activating the aligner's conda environment inside the container
+const CONTAINER_ACTIVATION: &str =
+    "source ~/miniconda3/etc/profile.d/conda.sh && conda activate aligner";
+
+/// Environment of the activated aligner inside the container, captured on first use
+/// so each alignment skips activating conda
+static ALIGNER_ENV: LazyLock<Option<Vec<(String, String)>>> = LazyLock::new(|| {
+    let output = Command::new("bash")
+        .args(["-c", &format!("{} && env -0", CONTAINER_ACTIVATION)])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => Some(parse_env(&output.stdout)),
+        _ => {
+            tracing::warn!("Failed to capture the aligner environment, activating per alignment");
+            None
+        }
+    }
+});
+
 /// Run MFA align directly (when inside the container)
-fn run_mfa_align_container(job_dir: &Path, dialect: MfaDialect) -> Result<PathBuf> {
+fn run_mfa_align_container(job_dir: &Path, dialect: MfaDialect, flags: &str) -> Result<()> {
     let dictionary = dialect.dictionary_name();
 
     // Assume MFA is installed and in PATH
-    let mfa_cmd = format!(
-        "source ~/miniconda3/etc/profile.d/conda.sh && \
-        conda activate aligner && \
-        mfa align {} {} {} {} --include-original-text --clean",
+    let align = format!(
+        "mfa align {} {} {} {} {}",
         job_dir.display(),
         dictionary,
         DEFAULT_ACOUSTIC_MODEL,
-        job_dir.display()
+        job_dir.display(),
+        flags
     );
 
-    // Execute the command directly
-    let output = Command::new("bash")
-        .args(["-c", &mfa_cmd])
+    // Execute the command directly, in the captured environment if there is one
+    let output = container_mfa_command(&align)
         .output()
         .context("Failed to execute MFA command directly")?;
 
@@ -110,27 +409,112 @@ fn run_mfa_align_container(job_dir: &Path, dialect: MfaDialect) -> Result<PathBu
         return Err(anyhow::anyhow!("MFA align failed: {}", stderr));
     }
 
-    find_textgrid_file(job_dir)
+    Ok(())
+}
+
+/// Shell running `mfa_cmd` inside this container, in the captured environment if there is one
+fn container_mfa_command(mfa_cmd: &str) -> Command {
+    let mut command = Command::new("bash");
+    match ALIGNER_ENV.as_ref() {
+        Some(env) => {
+            command
+                .args(["-c", mfa_cmd])
+                .env_clear()
+                .envs(env.iter().cloned());
+        }
+        None => {
+            command.args(["-c", &format!("{} && {}", CONTAINER_ACTIVATION, mfa_cmd)]);
+        }
+    }
+    command
+}
+
+/// Shell running `mfa_cmd` on the host in the aligner's conda environment
+fn local_mfa_command(mfa_cmd: &str) -> Command {
+    let mut command = Command::new("zsh");
+    command.args([
+        "-c",
+        &format!("source ~/.zshrc && conda activate aligner && {}", mfa_cmd),
+    ]);
+    command
+}
+
+/// Version of the MFA run without a managed container, inside this container or on the host
+///
+/// The aligner environment is activated as it is for alignments, so a
+/// version found means alignments can start.
+pub fn unmanaged_mfa_version() -> Result<String> {
+    let mut command = if is_running_in_docker() {
+        container_mfa_command("mfa version")
+    } else {
+        local_mfa_command("mfa version")
+    };
+    let output = command.output().context("Failed to run mfa")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("mfa version failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether alignments run through the managed container rather than an MFA this server runs itself
+pub fn uses_managed_container() -> bool {
+    !is_running_in_docker() && CONTAINER_MANAGER.config().enabled
+}
+
+/// Run MFA align through `docker exec` in the managed container (when on host)
+///
+/// The container is created, started, or restarted as needed before aligning.
+/// `job_dir` is a host path and must live under one of the container's volumes.
+pub fn run_mfa_align_managed(
+    manager: &ContainerManager,
+    job_dir: &Path,
+    dialect: MfaDialect,
+    flags: &str,
+) -> Result<()> {
+    let container_dir = manager.config().to_container_path(job_dir)?;
+
+    manager
+        .ensure_running()
+        .context("MFA container is not available")?;
+
+    let container_dir = container_path(&container_dir);
+    let mfa_cmd = format!(
+        "mfa align {} {} {} {} {}",
+        container_dir,
+        dialect.dictionary_name(),
+        DEFAULT_ACOUSTIC_MODEL,
+        container_dir,
+        flags
+    );
+
+    let output = manager.exec(&mfa_cmd)?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("MFA align failed: {}", stderr));
+    }
+
+    Ok(())
 }
 
 /// Run MFA align locally (when on host)
-fn run_mfa_align_local(job_dir: &Path, dialect: MfaDialect) -> Result<PathBuf> {
+fn run_mfa_align_local(job_dir: &Path, dialect: MfaDialect, flags: &str) -> Result<()> {
     let dictionary = dialect.dictionary_name();
 
     // Prepare MFA command to run locally
     let mfa_cmd = format!(
-        "source ~/.zshrc && \
-        conda activate aligner && \
-        mfa align {} {} {} {} --clean --include-original-text",
+        "mfa align {} {} {} {} {}",
         job_dir.display(),
         dictionary,
         DEFAULT_ACOUSTIC_MODEL,
-        job_dir.display()
+        job_dir.display(),
+        flags
     );
 
     // Execute the command locally
-    let output = Command::new("zsh")
-        .args(["-c", &mfa_cmd])
+    let output = local_mfa_command(&mfa_cmd)
         .output()
         .context("Failed to execute MFA command locally")?;
 
@@ -139,21 +523,21 @@ fn run_mfa_align_local(job_dir: &Path, dialect: MfaDialect) -> Result<PathBuf> {
         return Err(anyhow::anyhow!("MFA align failed: {}", stderr));
     }
 
-    find_textgrid_file(job_dir)
+    Ok(())
 }
 
-/// Helper function to find the TextGrid file in a directory
-fn find_textgrid_file(dir: &Path) -> Result<PathBuf> {
+/// Helper function to find the alignment file in a directory
+fn find_alignment_file(dir: &Path) -> Result<PathBuf> {
     for entry in fs::read_dir(dir).context("Failed to read directory")? {
         let entry = entry.context("Failed to read directory entry")?;
         let path = entry.path();
-        if path.extension().map_or(false, |ext| ext == "TextGrid") {
+        if AlignmentFormat::of_path(&path).is_some() {
             return Ok(path);
         }
     }
 
     Err(anyhow::anyhow!(
-        "Expected TextGrid file not found in {}",
+        "Expected alignment file not found in {}",
         dir.display()
     ))
 }
@@ -163,6 +547,67 @@ mod tests {
     use super::*;
     use std::path::PathBuf;
 
+    #[test]
+    fn test_parses_beams() {
+        assert_eq!(
+            Beams::parse(" 100 : 400 "),
+            Some(Beams {
+                beam: 100,
+                retry_beam: 400
+            })
+        );
+        assert_eq!(Beams::parse("100"), None);
+        assert_eq!(Beams::parse("0:40"), None);
+        // The retry beam cannot be narrower than the beam
+        assert_eq!(Beams::parse("400:100"), None);
+    }
+
+    #[test]
+    fn test_flags_for_each_pass() {
+        let mut options = AlignOptions::default();
+        let passes: Vec<Beams> = options.passes().collect();
+        assert_eq!(passes.len(), 1 + options.fallback_beams.len());
+        assert_eq!(passes[0], options.beams);
+
+        assert_eq!(
+            options.flags(passes[1]),
+            "--clean --beam 100 --retry_beam 400 --include_original_text"
+        );
+        options.include_original_text = false;
+        assert_eq!(
+            options.flags(passes[0]),
+            "--clean --beam 10 --retry_beam 40"
+        );
+        options.output_format = AlignmentFormat::Json;
+        assert_eq!(
+            options.flags(passes[0]),
+            "--clean --beam 10 --retry_beam 40 --output_format json"
+        );
+    }
+
+    #[test]
+    fn test_realigns_only_unaligned_files() {
+        let corpus = tempfile::tempdir().unwrap();
+        for stem in ["aligned", "failed"] {
+            fs::write(corpus.path().join(format!("{}.wav", stem)), stem).unwrap();
+            fs::write(corpus.path().join(format!("{}.lab", stem)), stem).unwrap();
+        }
+        fs::write(corpus.path().join("aligned.TextGrid"), "").unwrap();
+
+        let unaligned = unaligned_stems(corpus.path()).unwrap();
+        assert_eq!(unaligned, vec!["failed".to_string()]);
+
+        let retry_dir = stage_retry(corpus.path(), &unaligned).unwrap();
+        assert!(retry_dir.path().join("failed.wav").exists());
+        assert!(retry_dir.path().join("failed.lab").exists());
+        assert!(!retry_dir.path().join("aligned.wav").exists());
+
+        // As MFA would write after a successful pass, in either format
+        fs::write(retry_dir.path().join("failed.json"), "").unwrap();
+        collect_alignments(retry_dir.path(), corpus.path()).unwrap();
+        assert!(unaligned_stems(corpus.path()).unwrap().is_empty());
+    }
+
     #[test]
     #[ignore]
     fn test_run_mfa_align() {
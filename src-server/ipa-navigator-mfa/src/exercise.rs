@@ -0,0 +1,146 @@
+//! Turning free text into sentence-level speaking exercises
+
+use crate::scoring::{Dictionary, dictionary_word};
+
+/// Words that end in a full stop without ending the sentence
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "vs", "eg", "ie", "no", "fig",
+];
+
+/// One sentence to read aloud
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exercise {
+    pub text: String,
+    /// Dictionary pronunciation of each word that has one, in order
+    pub words: Vec<(String, Vec<String>)>,
+    /// Words without a dictionary pronunciation, which cannot be scored
+    pub missing_words: Vec<String>,
+}
+
+/// Split text into one exercise per sentence, skipping sentences without words
+pub fn build_exercises(dictionary: &Dictionary, text: &str) -> Vec<Exercise> {
+    split_sentences(text)
+        .into_iter()
+        .map(|sentence| {
+            let mut words = Vec::new();
+            let mut missing_words = Vec::new();
+
+            for word in sentence.split_whitespace().map(dictionary_word) {
+                if word.is_empty() {
+                    continue;
+                }
+                match dictionary.get(&word) {
+                    Some(phonemes) => words.push((word, phonemes.clone())),
+                    None => missing_words.push(word),
+                }
+            }
+
+            Exercise {
+                text: sentence,
+                words,
+                missing_words,
+            }
+        })
+        .collect()
+}
+
+/// Split text into sentences at `.`, `!`, and `?` followed by whitespace
+///
+/// Closing quotes and brackets stay with their sentence. Abbreviations such as
+/// "Dr." and initials such as "J." do not end a sentence, and neither do
+/// decimal points since no whitespace follows them.
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        current.push(c);
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+
+        // Keep runs such as "?!" and closing quotes with the sentence
+        while let Some(&next) = chars.peek()
+            && matches!(next, '.' | '!' | '?' | '"' | '\'' | ')' | '”' | '’')
+        {
+            current.push(next);
+            chars.next();
+        }
+
+        let at_break = chars.peek().is_none_or(|next| next.is_whitespace());
+        if at_break && !(c == '.' && ends_with_abbreviation(&current)) {
+            push_sentence(&mut sentences, &current);
+            current.clear();
+        }
+    }
+    push_sentence(&mut sentences, &current);
+
+    sentences
+}
+
+/// Add a sentence with its whitespace collapsed, if it has any letters
+fn push_sentence(sentences: &mut Vec<String>, sentence: &str) {
+    if sentence.chars().any(|c| c.is_alphabetic()) {
+        sentences.push(sentence.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+}
+
+/// Whether text ends with an abbreviation or initial followed by its full stop
+fn ends_with_abbreviation(text: &str) -> bool {
+    let Some(last) = text.split_whitespace().last() else {
+        return false;
+    };
+    let word: String = last.chars().filter(|c| c.is_alphabetic()).collect();
+
+    let is_initial = word.chars().count() == 1 && word.chars().all(|c| c.is_uppercase());
+    is_initial || ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split_sentences("Hello there.  How are you?I'm fine! Thanks"),
+            vec!["Hello there.", "How are you?I'm fine!", "Thanks"]
+        );
+        assert_eq!(
+            split_sentences("She said \"stop!\" Then she left...  Really?!"),
+            vec!["She said \"stop!\"", "Then she left...", "Really?!"]
+        );
+    }
+
+    #[test]
+    fn test_split_sentences_abbreviations() {
+        assert_eq!(
+            split_sentences("Dr. Smith met J. R. Jones at 3.5 km, e.g. near the\nriver. Done."),
+            vec![
+                "Dr. Smith met J. R. Jones at 3.5 km, e.g. near the river.",
+                "Done."
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_exercises() {
+        let dictionary: Dictionary = [("the", "ð ə"), ("cat", "k æ t"), ("sat", "s æ t")]
+            .into_iter()
+            .map(|(word, phones)| {
+                (
+                    word.to_string(),
+                    phones.split(' ').map(String::from).collect(),
+                )
+            })
+            .collect();
+
+        let exercises = build_exercises(&dictionary, "The cat sat. The zorb sat! ...");
+        assert_eq!(exercises.len(), 2);
+        assert_eq!(exercises[0].text, "The cat sat.");
+        assert_eq!(exercises[0].words.len(), 3);
+        assert!(exercises[0].missing_words.is_empty());
+        assert_eq!(exercises[1].missing_words, vec!["zorb"]);
+    }
+}
@@ -0,0 +1,331 @@
+//! Learner-facing tips for mispronounced phonemes
+
+use std::collections::HashSet;
+
+use crate::articulation::{manner_hint, place_hint};
+use crate::clarity::{ClarityIssue, WordClarity};
+use crate::localization::{Locale, localize};
+use crate::phoneme::{FeatureDifference, PhonemeFeatures, diphthong_targets, feature_differences};
+use crate::scoring::PhonemeAccuracy;
+
+/// Phonemes scoring below this get a tip
+pub const FEEDBACK_SCORE_THRESHOLD: f64 = 0.7;
+
+/// Maximum number of tips returned for one recording
+pub const MAX_FEEDBACK_TIPS: usize = 5;
+
+/// Turn the weakest phonemes of an assessment into actionable tips
+///
+/// Tips are ordered from the lowest score up, and each expected/actual pair
+/// is only mentioned once.
+pub fn generate_feedback(details: &[PhonemeAccuracy]) -> Vec<String> {
+    generate_feedback_in(details, Locale::English)
+}
+
+/// Tips for the weakest phonemes, written in the locale
+pub fn generate_feedback_in(details: &[PhonemeAccuracy], locale: Locale) -> Vec<String> {
+    let mut weak: Vec<&PhonemeAccuracy> = details
+        .iter()
+        .filter(|d| d.score < FEEDBACK_SCORE_THRESHOLD)
+        .collect();
+    weak.sort_by(|a, b| a.score.total_cmp(&b.score));
+
+    let mut seen = HashSet::new();
+    weak.into_iter()
+        .filter(|d| seen.insert((d.expected.as_str(), d.actual.as_str())))
+        .map(|d| phoneme_tip_in(&d.expected, &d.actual, locale))
+        .take(MAX_FEEDBACK_TIPS)
+        .collect()
+}
+
+/// Tips for words too quiet or muffled to assess, then for the weakest phonemes of the rest
+///
+/// Phonemes said during an unclear word are left out, since their low
+/// scores say more about the recording than the pronunciation. A voice
+/// trailing off over several words gets one tip, at the first of them.
+pub fn generate_feedback_with_clarity(
+    details: &[PhonemeAccuracy],
+    clarity: &[WordClarity],
+    locale: Locale,
+) -> Vec<String> {
+    let unclear: Vec<&WordClarity> = clarity.iter().filter(|w| !w.is_assessable()).collect();
+    let mut tips: Vec<String> = unclear
+        .iter()
+        .enumerate()
+        .filter(|(i, word)| {
+            *i == 0
+                || word.issue != Some(ClarityIssue::TrailingOff)
+                || unclear[i - 1].issue != Some(ClarityIssue::TrailingOff)
+        })
+        .filter_map(|(_, word)| {
+            let issue = word.issue?;
+            Some(localize(locale, issue.tip_id(), &[("word", &word.word)]))
+        })
+        .take(MAX_FEEDBACK_TIPS)
+        .collect();
+
+    let assessable: Vec<PhonemeAccuracy> = details
+        .iter()
+        .filter(|d| {
+            !unclear
+                .iter()
+                .any(|w| d.start_time < w.end_time && d.end_time > w.start_time)
+        })
+        .cloned()
+        .collect();
+    let remaining = MAX_FEEDBACK_TIPS - tips.len();
+    tips.extend(
+        generate_feedback_in(&assessable, locale)
+            .into_iter()
+            .take(remaining),
+    );
+    tips
+}
+
+/// Describe how to turn the sound produced into the one expected
+pub fn phoneme_tip(expected: &str, actual: &str) -> String {
+    phoneme_tip_in(expected, actual, Locale::English)
+}
+
+/// Describe how to turn the sound produced into the one expected, in the locale
+pub fn phoneme_tip_in(expected: &str, actual: &str, locale: Locale) -> String {
+    if actual.is_empty() {
+        return localize(locale, "tip-omitted", &[("expected", expected)]);
+    }
+
+    if expected.is_empty() {
+        return localize(locale, "tip-added", &[("actual", actual)]);
+    }
+
+    let advice = known_confusion(expected, actual)
+        .map(|id| localize(locale, id, &[]))
+        .or_else(|| feature_advice(expected, actual, locale))
+        .unwrap_or_else(|| localize(locale, "advice-listen", &[("expected", expected)]));
+
+    localize(
+        locale,
+        "tip-substituted",
+        &[
+            ("expected", expected),
+            ("actual", actual),
+            ("advice", &advice),
+        ],
+    )
+}
+
+/// Message id of hand-written advice for confusions common among English learners
+fn known_confusion(expected: &str, actual: &str) -> Option<&'static str> {
+    let id = match (expected, actual) {
+        ("θ", "s" | "t" | "f") => "confusion-th-voiceless",
+        ("ð", "d" | "z" | "v") => "confusion-th-voiced",
+        ("ɹ", "l") => "confusion-r-for-l",
+        ("l", "ɹ" | "ɻ") => "confusion-l-for-r",
+        ("ɹ", "w") => "confusion-r-for-w",
+        ("v", "w" | "b") => "confusion-v",
+        ("w", "v") => "confusion-w-for-v",
+        ("ʃ", "s") => "confusion-sh-for-s",
+        ("ɪ", "i" | "iː") => "confusion-short-i",
+        ("i" | "iː", "ɪ") => "confusion-long-i",
+        ("æ", "ɛ" | "e") => "confusion-ae",
+        ("ʊ", "u" | "uː") => "confusion-short-u",
+        _ => return None,
+    };
+
+    Some(id)
+}
+
+/// Advice derived from the articulatory features that differ
+fn feature_advice(expected: &str, actual: &str, locale: Locale) -> Option<String> {
+    match (diphthong_targets(expected), diphthong_targets(actual)) {
+        (Some((onset, offset)), None) => {
+            return Some(localize(
+                locale,
+                "difference-glide",
+                &[("onset", onset), ("offset", offset)],
+            ));
+        }
+        (None, Some(_)) => {
+            return Some(localize(locale, "difference-steady", &[]));
+        }
+        _ => {}
+    }
+
+    let expected_features = PhonemeFeatures::from_ipa(expected)?;
+    let actual_features = PhonemeFeatures::from_ipa(actual)?;
+
+    feature_differences(&expected_features, &actual_features)
+        .into_iter()
+        .map(|difference| difference_advice(difference, locale))
+        .reduce(|first, second| {
+            localize(
+                locale,
+                "advice-and",
+                &[("first", &first), ("second", &second)],
+            )
+        })
+}
+
+/// Advice correcting a single feature difference
+fn difference_advice(difference: FeatureDifference, locale: Locale) -> String {
+    let id = match difference {
+        FeatureDifference::VowelConsonant {
+            expected_vowel: true,
+        } => "difference-vowel",
+        FeatureDifference::VowelConsonant {
+            expected_vowel: false,
+        } => "difference-consonant",
+        FeatureDifference::Place { expected, .. } => return place_hint(expected, locale),
+        FeatureDifference::Manner { expected, .. } => return manner_hint(expected, locale),
+        FeatureDifference::Voicing {
+            expected_voiced: true,
+        } => "difference-voiced",
+        FeatureDifference::Voicing {
+            expected_voiced: false,
+        } => "difference-voiceless",
+        // Heights are ordered close to open and backness front to back
+        FeatureDifference::Height { expected, actual } => {
+            if expected > actual {
+                "difference-lower"
+            } else {
+                "difference-higher"
+            }
+        }
+        FeatureDifference::Backness { expected, actual } => {
+            if expected > actual {
+                "difference-backer"
+            } else {
+                "difference-fronter"
+            }
+        }
+        FeatureDifference::Rounding {
+            expected_rounded: true,
+        } => "difference-rounded",
+        FeatureDifference::Rounding {
+            expected_rounded: false,
+        } => "difference-unrounded",
+    };
+
+    localize(locale, id, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accuracy(expected: &str, actual: &str, score: f64) -> PhonemeAccuracy {
+        PhonemeAccuracy {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+            score,
+            start_time: 0.0,
+            end_time: 0.0,
+            char_span: None,
+            confidence: None,
+        }
+    }
+
+    #[test]
+    fn test_known_confusion_tip() {
+        assert_eq!(
+            phoneme_tip("θ", "s"),
+            "Your /θ/ sounded like /s/ — place your tongue between your teeth and blow air gently"
+        );
+    }
+
+    #[test]
+    fn test_feature_based_tip() {
+        let tip = phoneme_tip("b", "p");
+        assert!(tip.contains("vocal cords vibrate"), "{}", tip);
+
+        let tip = phoneme_tip("ɑ", "i");
+        assert!(tip.contains("open your mouth wider"), "{}", tip);
+    }
+
+    #[test]
+    fn test_diphthong_tip() {
+        let tip = phoneme_tip("eɪ", "e");
+        assert!(tip.contains("glide towards /ɪ/"), "{}", tip);
+    }
+
+    #[test]
+    fn test_missing_and_extra_phonemes() {
+        assert!(phoneme_tip("d", "").contains("left out the /d/"));
+        assert!(phoneme_tip("", "ə").contains("extra /ə/"));
+    }
+
+    #[test]
+    fn test_generate_feedback_filters_and_deduplicates() {
+        let details = vec![
+            accuracy("h", "h", 1.0),
+            accuracy("θ", "s", 0.4),
+            accuracy("b", "p", 0.6),
+            accuracy("θ", "s", 0.4),
+        ];
+
+        let feedback = generate_feedback(&details);
+        assert_eq!(feedback.len(), 2);
+        assert!(feedback[0].starts_with("Your /θ/"));
+        assert!(feedback[1].starts_with("Your /b/"));
+    }
+
+    #[test]
+    fn test_unclear_words_replace_their_phoneme_tips() {
+        let timed = |expected, actual, score, start_time, end_time| PhonemeAccuracy {
+            start_time,
+            end_time,
+            ..accuracy(expected, actual, score)
+        };
+        let clear = |word: &str, start_time, end_time, issue: Option<ClarityIssue>| WordClarity {
+            word: word.to_string(),
+            start_time,
+            end_time,
+            rms_db: -20.0,
+            relative_db: 0.0,
+            spectral_tilt: 0.0,
+            clarity: if issue.is_some() { 0.0 } else { 1.0 },
+            issue,
+        };
+        let details = vec![
+            timed("θ", "s", 0.4, 0.1, 0.2),
+            timed("b", "p", 0.3, 0.6, 0.7),
+            timed("d", "", 0.0, 0.0, 0.0),
+        ];
+        let clarity = vec![
+            clear("think", 0.0, 0.5, None),
+            clear("big", 0.5, 1.0, Some(ClarityIssue::TrailingOff)),
+            clear("dog", 1.0, 1.5, Some(ClarityIssue::TrailingOff)),
+        ];
+
+        let feedback = generate_feedback_with_clarity(&details, &clarity, Locale::English);
+        assert_eq!(feedback.len(), 3, "{:?}", feedback);
+        assert!(feedback[0].contains("trailed off at \"big\""));
+        assert!(feedback[1].contains("left out the /d/"));
+        assert!(feedback[2].starts_with("Your /θ/"));
+    }
+
+    #[test]
+    fn test_localized_tips() {
+        assert_eq!(
+            phoneme_tip_in("θ", "s", Locale::Malay),
+            localize(
+                Locale::Malay,
+                "tip-substituted",
+                &[
+                    ("expected", "θ"),
+                    ("actual", "s"),
+                    (
+                        "advice",
+                        &localize(Locale::Malay, "confusion-th-voiceless", &[])
+                    ),
+                ]
+            )
+        );
+
+        let tip = phoneme_tip_in("d", "", Locale::Mandarin);
+        assert!(tip.contains("/d/") && !tip.contains("left out"), "{}", tip);
+
+        let feedback = generate_feedback_in(&[accuracy("b", "p", 0.5)], Locale::Malay);
+        assert_eq!(feedback, vec![phoneme_tip_in("b", "p", Locale::Malay)]);
+        assert_ne!(feedback[0], phoneme_tip("b", "p"));
+    }
+}
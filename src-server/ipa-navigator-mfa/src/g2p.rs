@@ -0,0 +1,387 @@
+//! Alignment of expected phonemes back to the letters of the transcript
+//!
+//! Each word's dictionary pronunciation is aligned to its spelling with a small
+//! dynamic program over known English spellings of each sound, so the UI can
+//! highlight the letters behind a mispronounced phoneme.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::phoneme::{diphthong_targets, is_vowel, parse_ipa};
+
+/// Longest run of letters a single phoneme may be spelled with, as in "eigh"
+const MAX_SPELLING_LEN: usize = 4;
+
+/// Cost of a phoneme spelled in an unrecognised way
+const UNKNOWN_SPELLING_COST: f64 = 3.0;
+
+/// Cost of a phoneme with no letters of its own, as the second sound of "x"
+const UNSPELLED_PHONEME_COST: f64 = 2.0;
+
+/// Half-open range of character indices in the transcript
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl CharSpan {
+    /// The span moved `offset` characters later, as when a fragment's spans
+    /// are placed back in the whole transcript
+    pub fn shifted(self, offset: usize) -> Self {
+        Self {
+            start: self.start + offset,
+            end: self.end + offset,
+        }
+    }
+}
+
+/// A run of whole words cut from a transcript
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TranscriptFragment<'a> {
+    /// From the first letter of the first word to the last letter of the last
+    pub text: &'a str,
+    /// Character index in the transcript that `text` starts at
+    pub char_offset: usize,
+}
+
+/// The words `words` of a transcript, counting every whitespace-separated word
+///
+/// None if the range is empty or ends past the last word.
+pub fn transcript_fragment(
+    transcript: &str,
+    words: Range<usize>,
+) -> Option<TranscriptFragment<'_>> {
+    if words.is_empty() {
+        return None;
+    }
+
+    // Byte and character index of the start of each word, and the byte index of its end
+    let mut bounds = Vec::new();
+    let mut in_word = false;
+    for (char_index, (byte_index, c)) in transcript.char_indices().enumerate() {
+        match (in_word, c.is_whitespace()) {
+            (false, false) => bounds.push((byte_index, char_index, transcript.len())),
+            (true, true) => {
+                if let Some(last) = bounds.last_mut() {
+                    last.2 = byte_index;
+                }
+            }
+            _ => {}
+        }
+        in_word = !c.is_whitespace();
+    }
+
+    let &(start, char_offset, _) = bounds.get(words.start)?;
+    let &(_, _, end) = bounds.get(words.end - 1)?;
+    Some(TranscriptFragment {
+        text: &transcript[start..end],
+        char_offset,
+    })
+}
+
+/// An expected phoneme together with the letters that spell it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlignedPhoneme {
+    pub phoneme: String,
+    /// Index of the word within the transcript, counting every whitespace-separated word
+    pub word_index: usize,
+    pub span: CharSpan,
+}
+
+/// Look up every transcript word and align its phonemes to character spans
+///
+/// Produces the same phoneme sequence as `expected_phonemes`, skipping words
+/// missing from the dictionary. Spans are in Unicode scalar values, not bytes.
+pub fn align_transcript(
+    dictionary: &HashMap<String, Vec<String>>,
+    transcript: &str,
+) -> Vec<AlignedPhoneme> {
+    let mut aligned = Vec::new();
+    let mut chars = transcript.char_indices().peekable();
+    let mut char_index = 0;
+    let mut word_index = 0;
+
+    // Walk whitespace-separated words while tracking character offsets
+    loop {
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {
+            char_index += 1;
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut letters = Vec::new();
+        while let Some((_, c)) = chars.next_if(|(_, c)| !c.is_whitespace()) {
+            if c.is_alphabetic() {
+                letters.push((char_index, c.to_lowercase().next().unwrap_or(c)));
+            }
+            char_index += 1;
+        }
+
+        let word: String = letters.iter().map(|&(_, c)| c).collect();
+        if let Some(phonemes) = dictionary.get(&word) {
+            for (phoneme, span) in phonemes.iter().zip(align_word(&letters, phonemes)) {
+                aligned.push(AlignedPhoneme {
+                    phoneme: phoneme.clone(),
+                    word_index,
+                    span,
+                });
+            }
+        }
+
+        word_index += 1;
+    }
+
+    aligned
+}
+
+/// Back pointer of the alignment table
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    /// Phoneme spelled by this many letters
+    Spelled(usize),
+    /// Letter with no sound of its own
+    Silent,
+    /// Phoneme with no letters of its own
+    Unspelled,
+}
+
+/// Align one word's phonemes to its letters, given as (char index, lowercase letter)
+fn align_word(letters: &[(usize, char)], phonemes: &[String]) -> Vec<CharSpan> {
+    let (n, m) = (letters.len(), phonemes.len());
+    let letter_at: Vec<char> = letters.iter().map(|&(_, c)| c).collect();
+
+    // cost[i][j] is the cheapest alignment of the first i letters with the first j phonemes
+    let mut cost = vec![vec![f64::INFINITY; m + 1]; n + 1];
+    let mut step = vec![vec![None; m + 1]; n + 1];
+    cost[0][0] = 0.0;
+
+    for i in 0..=n {
+        for j in 0..=m {
+            let current = cost[i][j];
+            if current.is_infinite() {
+                continue;
+            }
+
+            if j < m {
+                for len in 1..=MAX_SPELLING_LEN.min(n - i) {
+                    let chunk: String = letter_at[i..i + len].iter().collect();
+                    let next = current + spelling_cost(&phonemes[j], &chunk);
+                    if next < cost[i + len][j + 1] {
+                        cost[i + len][j + 1] = next;
+                        step[i + len][j + 1] = Some(Step::Spelled(len));
+                    }
+                }
+
+                let next = current + UNSPELLED_PHONEME_COST;
+                if next < cost[i][j + 1] {
+                    cost[i][j + 1] = next;
+                    step[i][j + 1] = Some(Step::Unspelled);
+                }
+            }
+
+            if i < n {
+                let next = current + silent_cost(letter_at[i]);
+                if next < cost[i + 1][j] {
+                    cost[i + 1][j] = next;
+                    step[i + 1][j] = Some(Step::Silent);
+                }
+            }
+        }
+    }
+
+    // Recover the letter range of each phoneme
+    let mut ranges: Vec<Option<(usize, usize)>> = vec![None; m];
+    let (mut i, mut j) = (n, m);
+    while let Some(last) = step[i][j] {
+        match last {
+            Step::Spelled(len) => {
+                ranges[j - 1] = Some((i - len, i));
+                i -= len;
+                j -= 1;
+            }
+            Step::Silent => i -= 1,
+            Step::Unspelled => j -= 1,
+        }
+    }
+
+    // Unspelled phonemes share the letters of their neighbour
+    for k in 0..m {
+        if ranges[k].is_none() {
+            ranges[k] = ranges[k + 1..]
+                .iter()
+                .chain(ranges[..k].iter().rev())
+                .find_map(|r| *r);
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|range| match range {
+            Some((start, end)) => CharSpan {
+                start: letters[start].0,
+                end: letters[end - 1].0 + 1,
+            },
+            // A word whose letters were all silent; highlight the whole word
+            None => CharSpan {
+                start: letters.first().map_or(0, |l| l.0),
+                end: letters.last().map_or(0, |l| l.0 + 1),
+            },
+        })
+        .collect()
+}
+
+/// Cost of spelling a phoneme with a run of letters
+fn spelling_cost(phoneme: &str, chunk: &str) -> f64 {
+    let base = parse_ipa(phoneme).map_or_else(|| phoneme.to_string(), |p| p.base);
+
+    if consonant_spellings(&base).contains(&chunk) {
+        return 0.0;
+    }
+
+    let vowel_sound = is_vowel(&base) || diphthong_targets(&base).is_some();
+    if vowel_sound && is_vowel_spelling(chunk, is_rhotic(&base)) {
+        // Prefer the shortest spelling that covers the vowel letters
+        return 0.1 * chunk.len() as f64;
+    }
+
+    UNKNOWN_SPELLING_COST * chunk.chars().count() as f64
+}
+
+/// Whether a vowel sound includes the r-colouring spelled with "r"
+fn is_rhotic(phoneme: &str) -> bool {
+    matches!(phoneme, "ɚ" | "ɝ")
+}
+
+/// Whether letters can spell a vowel: vowel letters, optionally with a glide or "r"
+fn is_vowel_spelling(chunk: &str, rhotic: bool) -> bool {
+    let vowel_letters = |s: &str| !s.is_empty() && s.chars().all(|c| "aeiouy".contains(c));
+
+    if vowel_letters(chunk) {
+        return true;
+    }
+
+    if rhotic && let Some(rest) = chunk.strip_suffix('r') {
+        return vowel_letters(rest) || rest.is_empty();
+    }
+
+    ["w", "gh", "ugh"]
+        .iter()
+        .any(|glide| chunk.strip_suffix(glide).is_some_and(vowel_letters))
+}
+
+/// Known spellings of consonant sounds
+fn consonant_spellings(phoneme: &str) -> &'static [&'static str] {
+    match phoneme {
+        "p" => &["p", "pp"],
+        "b" => &["b", "bb"],
+        "t" => &["t", "tt", "ed", "th"],
+        "d" => &["d", "dd", "ed"],
+        "k" | "c" => &["c", "k", "ck", "ch", "q", "qu", "cc", "x"],
+        "g" | "ɡ" | "ɟ" => &["g", "gg", "gu", "gh"],
+        "m" => &["m", "mm", "mb", "mn"],
+        "n" | "ɲ" => &["n", "nn", "kn", "gn"],
+        "ŋ" => &["ng", "n"],
+        "f" => &["f", "ff", "ph", "gh"],
+        "v" => &["v", "f"],
+        "θ" | "ð" => &["th"],
+        "s" => &["s", "ss", "c", "sc", "ce", "x"],
+        "z" => &["z", "zz", "s", "ss", "x"],
+        "ʃ" => &["sh", "ti", "ci", "s", "ch", "ss", "si"],
+        "ʒ" => &["s", "si", "g", "z"],
+        "h" | "ç" => &["h", "wh"],
+        "tʃ" => &["ch", "tch", "t"],
+        "dʒ" => &["j", "g", "dg", "dge", "ge"],
+        "ɹ" | "ɻ" => &["r", "rr", "wr", "rh"],
+        "j" => &["y", "i", "u"],
+        "w" => &["w", "wh", "u", "o"],
+        "l" | "ɫ" | "ʎ" => &["l", "ll"],
+        "ɾ" | "ʔ" => &["t", "tt", "d", "dd"],
+        _ => &[],
+    }
+}
+
+/// Cost of a letter with no sound of its own
+fn silent_cost(letter: char) -> f64 {
+    match letter {
+        'e' => 0.5,
+        'h' | 'g' | 'k' | 'w' | 'b' | 'l' | 't' | 'u' => 1.5,
+        _ => 2.5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::Dictionary;
+
+    fn spans(aligned: &[AlignedPhoneme], transcript: &str) -> Vec<String> {
+        let chars: Vec<char> = transcript.chars().collect();
+        aligned
+            .iter()
+            .map(|a| chars[a.span.start..a.span.end].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_transcript_fragment_keeps_character_offsets() {
+        let transcript = " Ça  marche, the cat sat";
+        let fragment = transcript_fragment(transcript, 1..3).unwrap();
+        assert_eq!(fragment.text, "marche, the");
+        assert_eq!(fragment.char_offset, 5);
+
+        let dict = Dictionary::from_pairs(&[("the", "ð ə")]);
+        let shifted: Vec<CharSpan> = align_transcript(&dict, fragment.text)
+            .into_iter()
+            .map(|aligned| aligned.span.shifted(fragment.char_offset))
+            .collect();
+        let chars: Vec<char> = transcript.chars().collect();
+        let letters: String = chars[shifted[0].start..shifted[1].end].iter().collect();
+        assert_eq!(letters, "the");
+
+        assert_eq!(transcript_fragment(transcript, 4..5).unwrap().text, "sat");
+        assert_eq!(transcript_fragment(transcript, 2..2), None);
+        assert_eq!(transcript_fragment(transcript, 3..6), None);
+    }
+
+    #[test]
+    fn test_align_digraphs_and_silent_letters() {
+        let dict = Dictionary::from_pairs(&[("think", "θ ɪ ŋ k"), ("make", "m ej k")]);
+
+        let aligned = align_transcript(&dict, "think");
+        assert_eq!(spans(&aligned, "think"), vec!["th", "i", "n", "k"]);
+
+        let aligned = align_transcript(&dict, "make");
+        assert_eq!(spans(&aligned, "make"), vec!["m", "a", "k"]);
+    }
+
+    #[test]
+    fn test_align_offsets_across_words_and_punctuation() {
+        let dict = Dictionary::from_pairs(&[("the", "ð ə"), ("cat", "kʰ æ t")]);
+        let transcript = "The  cat!";
+
+        let aligned = align_transcript(&dict, transcript);
+        assert_eq!(aligned.len(), 5);
+        assert_eq!(aligned[2].word_index, 1);
+        assert_eq!(aligned[2].span, CharSpan { start: 5, end: 6 });
+        assert_eq!(spans(&aligned, transcript), vec!["Th", "e", "c", "a", "t"]);
+    }
+
+    #[test]
+    fn test_unspelled_phoneme_shares_letters() {
+        let dict = Dictionary::from_pairs(&[("box", "b ɑ k s")]);
+
+        let aligned = align_transcript(&dict, "box");
+        assert_eq!(spans(&aligned, "box"), vec!["b", "o", "x", "x"]);
+    }
+
+    #[test]
+    fn test_unknown_words_are_skipped() {
+        let dict = Dictionary::from_pairs(&[("cat", "kʰ æ t")]);
+
+        let aligned = align_transcript(&dict, "zzyzx cat");
+        assert_eq!(aligned.len(), 3);
+        assert_eq!(aligned[0].word_index, 1);
+    }
+}
@@ -0,0 +1,417 @@
+//! Intonation of a recording, from its pitch contour
+//!
+//! The fundamental frequency (F0) is tracked with the YIN estimator, then
+//! averaged over each aligned word. The pitch movement over the last word
+//! tells whether the sentence ended rising or falling, which is compared with
+//! the pattern expected for its punctuation: statements and wh-questions
+//! fall, yes/no questions rise.
+
+use anyhow::Result;
+
+use crate::audio::{read_wav_mono, resample_linear};
+use crate::mfa_parser::MfaSegment;
+
+/// Rate recordings are resampled to before tracking pitch
+const ANALYSIS_SAMPLE_RATE: u32 = 16000;
+
+/// Lowest and highest F0 tracked, covering adult and children's voices
+const MIN_F0: f64 = 60.0;
+const MAX_F0: f64 = 500.0;
+
+/// Samples compared at each lag, 25 ms at the analysis rate
+const WINDOW: usize = 400;
+
+/// Samples between frames, 10 ms at the analysis rate
+const HOP: usize = 160;
+
+/// Highest normalized difference at which a frame is considered voiced
+const YIN_THRESHOLD: f32 = 0.15;
+
+/// Frames quieter than this share of the loudest frame are treated as silence
+const SILENCE_RATIO: f32 = 0.05;
+
+/// Voiced frames a word needs for its pitch movement to be measured
+const MIN_VOICED_FRAMES: usize = 3;
+
+/// Semitones the final word has to move to count as rising or falling
+pub const CONTOUR_THRESHOLD: f64 = 1.5;
+
+/// Words opening a question that is answered with information rather than yes or no
+const WH_WORDS: [&str; 9] = [
+    "who", "whom", "whose", "what", "which", "when", "where", "why", "how",
+];
+
+/// Pitch estimate for one analysis frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PitchFrame {
+    /// Centre of the frame, in seconds
+    pub time: f64,
+    /// Fundamental frequency in Hz, or `None` for silent and unvoiced frames
+    pub f0: Option<f64>,
+}
+
+/// Kind of sentence, as told by its punctuation and first word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SentenceType {
+    Statement,
+    YesNoQuestion,
+    WhQuestion,
+}
+
+impl SentenceType {
+    /// Classify a transcript, treating anything not ending in `?` as a statement
+    pub fn of(transcript: &str) -> Self {
+        let trimmed = transcript
+            .trim()
+            .trim_end_matches(|c: char| c == '"' || c == '\'' || c.is_whitespace());
+        if !trimmed.ends_with('?') {
+            return SentenceType::Statement;
+        }
+
+        let first = trimmed
+            .split_whitespace()
+            .next()
+            .map(|word| {
+                word.trim_matches(|c: char| !c.is_alphanumeric())
+                    .to_lowercase()
+            })
+            .unwrap_or_default();
+        if WH_WORDS.contains(&first.as_str()) {
+            SentenceType::WhQuestion
+        } else {
+            SentenceType::YesNoQuestion
+        }
+    }
+
+    /// Pitch movement a sentence of this kind usually ends with
+    pub fn expected_contour(&self) -> Contour {
+        match self {
+            SentenceType::Statement | SentenceType::WhQuestion => Contour::Fall,
+            SentenceType::YesNoQuestion => Contour::Rise,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SentenceType::Statement => "statement",
+            SentenceType::YesNoQuestion => "yes_no_question",
+            SentenceType::WhQuestion => "wh_question",
+        }
+    }
+}
+
+/// Direction of a pitch movement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Contour {
+    Rise,
+    Level,
+    Fall,
+}
+
+impl Contour {
+    /// Classify a change in pitch, in semitones
+    pub fn of(change: f64) -> Self {
+        if change >= CONTOUR_THRESHOLD {
+            Contour::Rise
+        } else if change <= -CONTOUR_THRESHOLD {
+            Contour::Fall
+        } else {
+            Contour::Level
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Contour::Rise => "rise",
+            Contour::Level => "level",
+            Contour::Fall => "fall",
+        }
+    }
+}
+
+/// Pitch of one aligned word, in Hz
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordPitch {
+    pub word: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// Mean, lowest, and highest F0 over the word's voiced frames, `None` if none were voiced
+    pub mean_f0: Option<f64>,
+    pub min_f0: Option<f64>,
+    pub max_f0: Option<f64>,
+}
+
+/// Intonation of a recording compared with the pattern its transcript calls for
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntonationAnalysis {
+    pub sentence_type: SentenceType,
+    pub expected_contour: Contour,
+    /// Movement over the last word with enough voiced frames to measure
+    pub final_contour: Contour,
+    /// Pitch change over that word, in semitones, positive when rising
+    pub final_change: f64,
+    /// Median F0 of the whole recording, in Hz
+    pub median_f0: f64,
+    pub words: Vec<WordPitch>,
+}
+
+impl IntonationAnalysis {
+    /// Whether the sentence ended the way its kind usually does
+    pub fn matches_expected(&self) -> bool {
+        self.final_contour == self.expected_contour
+    }
+}
+
+/// Track the pitch of a recording, one frame every 10 ms
+pub fn pitch_track(samples: &[f32], sample_rate: u32) -> Vec<PitchFrame> {
+    let samples = resample_linear(samples, sample_rate, ANALYSIS_SAMPLE_RATE);
+    let rate = ANALYSIS_SAMPLE_RATE as f64;
+    let min_lag = (rate / MAX_F0).floor() as usize;
+    let max_lag = (rate / MIN_F0).ceil() as usize;
+    let frame_length = WINDOW + max_lag;
+    if samples.len() < frame_length {
+        return Vec::new();
+    }
+
+    let frames: Vec<&[f32]> = (0..=(samples.len() - frame_length) / HOP)
+        .map(|i| &samples[i * HOP..i * HOP + frame_length])
+        .collect();
+    let loudness: Vec<f32> = frames.iter().map(|frame| rms(&frame[..WINDOW])).collect();
+    let floor = loudness.iter().fold(0.0f32, |max, &l| max.max(l)) * SILENCE_RATIO;
+
+    frames
+        .iter()
+        .zip(&loudness)
+        .enumerate()
+        .map(|(i, (frame, &loudness))| PitchFrame {
+            time: (i * HOP + frame_length / 2) as f64 / rate,
+            f0: if loudness > floor.max(f32::EPSILON) {
+                yin(frame, min_lag, max_lag).map(|lag| rate / lag)
+            } else {
+                None
+            },
+        })
+        .collect()
+}
+
+/// Period of a frame in samples, by the YIN cumulative mean normalized difference
+fn yin(frame: &[f32], min_lag: usize, max_lag: usize) -> Option<f64> {
+    let difference: Vec<f32> = (0..=max_lag)
+        .map(|lag| {
+            frame[..WINDOW]
+                .iter()
+                .zip(&frame[lag..lag + WINDOW])
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum()
+        })
+        .collect();
+
+    let mut normalized = vec![1.0f32; max_lag + 1];
+    let mut running = 0.0f32;
+    for lag in 1..=max_lag {
+        running += difference[lag];
+        normalized[lag] = if running > 0.0 {
+            difference[lag] * lag as f32 / running
+        } else {
+            1.0
+        };
+    }
+
+    // The first dip under the threshold, followed down to its minimum
+    let mut lag = (min_lag.max(1)..max_lag).find(|&lag| normalized[lag] < YIN_THRESHOLD)?;
+    while lag + 1 < max_lag && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+
+    // Parabolic interpolation between neighbouring lags for sub-sample precision
+    let (before, at, after) = (
+        normalized[lag - 1] as f64,
+        normalized[lag] as f64,
+        normalized[lag + 1] as f64,
+    );
+    let curvature = before - 2.0 * at + after;
+    let offset = if curvature.abs() > f64::EPSILON {
+        (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some(lag as f64 + offset)
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+fn semitones(f0: f64, reference: f64) -> f64 {
+    12.0 * (f0 / reference).log2()
+}
+
+/// Analyse the intonation of a WAV recording aligned against `transcript`
+///
+/// Returns `None` if too little of the recording was voiced to tell how it ended.
+pub fn analyze_intonation(
+    audio_wav: &[u8],
+    segments: &[MfaSegment],
+    transcript: &str,
+) -> Result<Option<IntonationAnalysis>> {
+    let (samples, sample_rate) = read_wav_mono(audio_wav)?;
+    Ok(analyze_pitch(
+        &pitch_track(&samples, sample_rate),
+        segments,
+        transcript,
+    ))
+}
+
+/// Analyse a pitch track against the word segments of its alignment
+pub fn analyze_pitch(
+    track: &[PitchFrame],
+    segments: &[MfaSegment],
+    transcript: &str,
+) -> Option<IntonationAnalysis> {
+    let mut voiced: Vec<f64> = track.iter().filter_map(|frame| frame.f0).collect();
+    if voiced.is_empty() {
+        return None;
+    }
+    voiced.sort_by(f64::total_cmp);
+    let median_f0 = voiced[voiced.len() / 2];
+
+    let words: Vec<(&MfaSegment, Vec<(f64, f64)>)> = segments
+        .iter()
+        .filter(|segment| segment.segment_type == "word" && !segment.label.is_empty())
+        .map(|segment| {
+            let frames = track
+                .iter()
+                .filter(|frame| frame.time >= segment.begin && frame.time < segment.end)
+                .filter_map(|frame| frame.f0.map(|f0| (frame.time, f0)))
+                .collect();
+            (segment, frames)
+        })
+        .collect();
+
+    let final_change = words
+        .iter()
+        .rev()
+        .find(|(_, frames)| frames.len() >= MIN_VOICED_FRAMES)
+        .map(|(_, frames)| pitch_change(frames, median_f0))?;
+
+    let sentence_type = SentenceType::of(transcript);
+    Some(IntonationAnalysis {
+        sentence_type,
+        expected_contour: sentence_type.expected_contour(),
+        final_contour: Contour::of(final_change),
+        final_change,
+        median_f0,
+        words: words
+            .into_iter()
+            .map(|(segment, frames)| {
+                let f0s = frames.iter().map(|&(_, f0)| f0);
+                WordPitch {
+                    word: segment.label.clone(),
+                    start_time: segment.begin,
+                    end_time: segment.end,
+                    mean_f0: (!frames.is_empty())
+                        .then(|| f0s.clone().sum::<f64>() / frames.len() as f64),
+                    min_f0: f0s.clone().reduce(f64::min),
+                    max_f0: f0s.reduce(f64::max),
+                }
+            })
+            .collect(),
+    })
+}
+
+/// Semitones the pitch moves over `frames`, by a least-squares line through them
+///
+/// Fitting a line rather than comparing the first and last frames keeps a
+/// single octave error from deciding the contour.
+fn pitch_change(frames: &[(f64, f64)], reference: f64) -> f64 {
+    let points: Vec<(f64, f64)> = frames
+        .iter()
+        .map(|&(time, f0)| (time, semitones(f0, reference)))
+        .collect();
+    let count = points.len() as f64;
+    let mean_time = points.iter().map(|p| p.0).sum::<f64>() / count;
+    let mean_pitch = points.iter().map(|p| p.1).sum::<f64>() / count;
+
+    let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), &(t, p)| {
+        (
+            cov + (t - mean_time) * (p - mean_pitch),
+            var + (t - mean_time) * (t - mean_time),
+        )
+    });
+    if variance <= f64::EPSILON {
+        return 0.0;
+    }
+
+    let span = points.last().map_or(0.0, |p| p.0) - points.first().map_or(0.0, |p| p.0);
+    covariance / variance * span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    /// A tone gliding from `from` to `to` Hz over `seconds`
+    fn glide(from: f64, to: f64, seconds: f64, sample_rate: u32) -> Vec<f32> {
+        let count = (seconds * sample_rate as f64) as usize;
+        let mut phase = 0.0;
+        (0..count)
+            .map(|i| {
+                let f0 = from + (to - from) * i as f64 / count as f64;
+                phase += TAU * f0 / sample_rate as f64;
+                (0.5 * phase.sin()) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tracks_the_pitch_of_a_tone() {
+        let track = pitch_track(&glide(200.0, 200.0, 0.5, 24000), 24000);
+        assert!(!track.is_empty());
+        for frame in &track {
+            let f0 = frame.f0.expect("tone should be voiced");
+            assert!((f0 - 200.0).abs() < 4.0, "{} != 200", f0);
+        }
+
+        let silence = pitch_track(&vec![0.0; 8000], 16000);
+        assert!(silence.iter().all(|frame| frame.f0.is_none()));
+    }
+
+    #[test]
+    fn test_classifies_sentences_by_punctuation() {
+        assert_eq!(SentenceType::of("The cat sat."), SentenceType::Statement);
+        assert_eq!(
+            SentenceType::of("Is it raining? "),
+            SentenceType::YesNoQuestion
+        );
+        assert_eq!(
+            SentenceType::of("\"Where are you?\""),
+            SentenceType::WhQuestion
+        );
+        assert_eq!(SentenceType::WhQuestion.expected_contour(), Contour::Fall);
+    }
+
+    #[test]
+    fn test_compares_the_final_contour_with_the_sentence_type() {
+        let mut samples = glide(180.0, 180.0, 0.5, 16000);
+        samples.extend(glide(180.0, 300.0, 0.5, 16000));
+        let track = pitch_track(&samples, 16000);
+        let segments = [
+            MfaSegment::word("is", 0.0, 0.5),
+            MfaSegment::word("it", 0.5, 1.0),
+        ];
+
+        let question = analyze_pitch(&track, &segments, "Is it?").unwrap();
+        assert_eq!(question.final_contour, Contour::Rise);
+        assert!(question.final_change > 5.0, "{}", question.final_change);
+        assert!(question.matches_expected());
+        let level = question.words[0].mean_f0.unwrap();
+        assert!((level - 180.0).abs() < 4.0, "{} != 180", level);
+
+        let statement = analyze_pitch(&track, &segments, "It is.").unwrap();
+        assert_eq!(statement.expected_contour, Contour::Fall);
+        assert!(!statement.matches_expected());
+
+        assert!(analyze_pitch(&[], &segments, "It is.").is_none());
+    }
+}
@@ -0,0 +1,164 @@
+//! Assessment of a single word said on its own, as in flashcard drills
+//!
+//! The word is the whole transcript the recording is aligned against. Forced
+//! alignment places the word somewhere even in silence or a different word,
+//! so before scoring, the alignment is checked for signs the word was never
+//! said: no aligned word, a word too short to have been spoken, or phones
+//! squeezed to the aligner's minimum length.
+
+use anyhow::Result;
+
+use crate::docker::MfaDialect;
+use crate::mfa_parser::MfaSegment;
+use crate::scoring::{PronunciationAssessment, ScoringRubric, score_segments};
+
+/// Alignments less confident than this are reported as the word not being detected
+pub const MIN_DETECTION_CONFIDENCE: f64 = 0.4;
+
+/// Shortest time a word can plausibly be said in, in seconds
+const MIN_WORD_DURATION: f64 = 0.1;
+
+/// Phones shorter than this were squeezed in by the aligner rather than heard, in seconds
+const MIN_PHONE_DURATION: f64 = 0.03;
+
+/// Labels aligners give to stretches they could not match to the transcript
+const UNMATCHED_LABELS: [&str; 4] = ["<unk>", "spn", "sil", "sp"];
+
+/// Outcome of assessing a recording of a single word
+#[derive(Debug, Clone)]
+pub struct WordAttempt {
+    pub word: String,
+    /// 0.0 (the word was not heard) to 1.0 (clearly aligned and matched)
+    pub confidence: f64,
+    /// The word's assessment, or `None` if it was not detected in the recording
+    pub assessment: Option<PronunciationAssessment>,
+}
+
+impl WordAttempt {
+    pub fn detected(&self) -> bool {
+        self.assessment.is_some()
+    }
+}
+
+/// The target word as it is aligned, without surrounding punctuation
+///
+/// Returns `None` unless the text is exactly one word.
+pub fn target_word(text: &str) -> Option<&str> {
+    let word = text
+        .trim()
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
+    (!word.is_empty() && !word.contains(char::is_whitespace)).then_some(word)
+}
+
+/// Score a recording of `word` from its alignment, unless the word was not detected
+pub fn assess_word(
+    segments: &[MfaSegment],
+    word: &str,
+    dialect: MfaDialect,
+    rubric: &ScoringRubric,
+) -> Result<WordAttempt> {
+    let assessment = score_segments(segments, word, dialect, rubric)?;
+    let confidence = alignment_confidence(segments) * assessment.overall_score;
+
+    Ok(WordAttempt {
+        word: word.to_string(),
+        confidence,
+        assessment: (confidence >= MIN_DETECTION_CONFIDENCE).then_some(assessment),
+    })
+}
+
+/// How plausibly an alignment of a single word matches speech, from 0.0 to 1.0
+///
+/// The share of the word's phones long enough to have been heard, or 0.0 if
+/// no word was aligned or it is too short to have been said.
+pub fn alignment_confidence(segments: &[MfaSegment]) -> f64 {
+    let matched = |segment: &&MfaSegment| {
+        !segment.label.is_empty() && !UNMATCHED_LABELS.contains(&segment.label.as_str())
+    };
+
+    let Some(word) = segments
+        .iter()
+        .filter(|segment| segment.segment_type == "word")
+        .find(matched)
+    else {
+        return 0.0;
+    };
+    if word.end - word.begin < MIN_WORD_DURATION {
+        return 0.0;
+    }
+
+    let phones: Vec<&MfaSegment> = segments
+        .iter()
+        .filter(|segment| segment.segment_type == "phone")
+        .filter(matched)
+        .filter(|phone| phone.begin < word.end && phone.end > word.begin)
+        .collect();
+    if phones.is_empty() {
+        return 0.0;
+    }
+
+    let heard = phones
+        .iter()
+        .filter(|phone| phone.end - phone.begin >= MIN_PHONE_DURATION)
+        .count();
+    heard as f64 / phones.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::{Strictness, rubric};
+
+    #[test]
+    fn test_target_word() {
+        assert_eq!(target_word("  Think! "), Some("Think"));
+        assert_eq!(target_word("don't"), Some("don't"));
+        assert_eq!(target_word("think about"), None);
+        assert_eq!(target_word("?!"), None);
+    }
+
+    #[test]
+    fn test_alignment_confidence() {
+        let said = [
+            MfaSegment::word("this", 0.2, 0.5),
+            MfaSegment::phone("ð", 0.2, 0.3),
+            MfaSegment::phone("ɪ", 0.3, 0.4),
+            MfaSegment::phone("s", 0.4, 0.5),
+        ];
+        assert_eq!(alignment_confidence(&said), 1.0);
+
+        let squeezed = [
+            MfaSegment::word("this", 0.0, 0.12),
+            MfaSegment::phone("ð", 0.0, 0.01),
+            MfaSegment::phone("ɪ", 0.01, 0.02),
+            MfaSegment::phone("s", 0.02, 0.12),
+        ];
+        assert!((alignment_confidence(&squeezed) - 1.0 / 3.0).abs() < 1e-9);
+
+        let unknown = [
+            MfaSegment::word("<unk>", 0.0, 0.5),
+            MfaSegment::phone("spn", 0.0, 0.5),
+        ];
+        assert_eq!(alignment_confidence(&unknown), 0.0);
+        assert_eq!(alignment_confidence(&said[..1]), 0.0);
+    }
+
+    #[test]
+    fn test_assess_word_reports_undetected_words() {
+        let rubric = rubric(Strictness::default());
+        let said = [
+            MfaSegment::word("this", 0.2, 0.5),
+            MfaSegment::phone("ð", 0.2, 0.3),
+            MfaSegment::phone("ɪ", 0.3, 0.4),
+            MfaSegment::phone("s", 0.4, 0.5),
+        ];
+        let attempt = assess_word(&said, "this", MfaDialect::AmericanEnglish, &rubric).unwrap();
+        assert!(attempt.detected());
+        assert!(attempt.confidence > MIN_DETECTION_CONFIDENCE);
+
+        let silence = [MfaSegment::word("", 0.0, 1.0)];
+        let attempt = assess_word(&silence, "this", MfaDialect::AmericanEnglish, &rubric).unwrap();
+        assert!(!attempt.detected());
+        assert_eq!(attempt.confidence, 0.0);
+    }
+}
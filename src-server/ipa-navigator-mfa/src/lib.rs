@@ -1,6 +1,38 @@
+//! Pronunciation assessment for IPA Navigator
+//!
+//! Applications embedding assessment should use [`prelude`], whose API is
+//! semver-stable. The remaining modules are used by the server and may change.
+
+pub mod aligner;
+pub mod anonymize;
 pub mod api;
+pub mod articulation;
+pub mod asr;
+pub mod audio;
+pub mod batch;
+pub mod clarity;
+pub mod confidence;
 pub mod constants;
+pub mod container;
+pub mod corpus;
+pub mod ctc;
+pub mod difficulty;
 pub mod docker;
+pub mod exercise;
+pub mod feedback;
+pub mod g2p;
+pub mod intonation;
+pub mod isolated;
+pub mod localization;
+pub mod mfa_json;
 pub mod mfa_parser;
-pub mod phoneme;
+pub mod pace;
+pub mod platform;
+pub mod prelude;
 pub mod scoring;
+pub mod storage;
+pub mod syllables;
+pub mod timeline;
+pub mod volume;
+
+pub use ipa_navigator_core::phoneme;
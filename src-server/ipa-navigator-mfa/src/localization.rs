@@ -0,0 +1,291 @@
+//! Learner-facing text in English, Malay and Mandarin
+//!
+//! Messages live in the Fluent catalogs under `locales/`, which are compiled
+//! into the binary. English is the source language: a message missing from
+//! another catalog is shown in English instead.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use fluent_bundle::{FluentArgs, FluentResource, concurrent::FluentBundle};
+use unic_langid::LanguageIdentifier;
+
+use crate::phoneme::{ParsedPhoneme, PhonemeFeatures, diphthong_targets};
+
+/// A language feedback and phoneme descriptions can be given in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Locale {
+    #[default]
+    English,
+    Malay,
+    Mandarin,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::English, Locale::Malay, Locale::Mandarin];
+
+    /// BCP 47 language subtag
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Malay => "ms",
+            Locale::Mandarin => "zh",
+        }
+    }
+
+    /// Parse a language tag such as "ms" or "zh-Hans-CN", by its language alone
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.code() == language)
+    }
+
+    /// The supported locale an `Accept-Language` header prefers, if any
+    ///
+    /// Ranges are ranked by quality, then by order; wildcards and ranges
+    /// with zero quality are ignored.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut ranked: Vec<(f32, usize, Locale)> = header
+            .split(',')
+            .enumerate()
+            .filter_map(|(position, range)| {
+                let mut params = range.split(';');
+                let locale = Locale::parse(params.next()?)?;
+                let quality = match params.find_map(|param| param.trim().strip_prefix("q=")) {
+                    Some(quality) => quality.trim().parse().ok()?,
+                    None => 1.0,
+                };
+                (quality > 0.0).then_some((quality, position, locale))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        ranked.first().map(|&(_, _, locale)| locale)
+    }
+
+    fn catalog(&self) -> &'static str {
+        match self {
+            Locale::English => include_str!("../locales/en.ftl"),
+            Locale::Malay => include_str!("../locales/ms.ftl"),
+            Locale::Mandarin => include_str!("../locales/zh.ftl"),
+        }
+    }
+}
+
+// Catalogs are parsed once and shared between requests
+static BUNDLES: LazyLock<HashMap<Locale, FluentBundle<FluentResource>>> = LazyLock::new(|| {
+    Locale::ALL
+        .into_iter()
+        .map(|locale| (locale, bundle(locale)))
+        .collect()
+});
+
+fn bundle(locale: Locale) -> FluentBundle<FluentResource> {
+    let language: LanguageIdentifier = locale
+        .code()
+        .parse()
+        .expect("locale codes are valid language tags");
+
+    let resource = FluentResource::try_new(locale.catalog().to_string()).unwrap_or_else(
+        |(resource, errors)| {
+            tracing::error!("Errors in the {} catalog: {:?}", locale.code(), errors);
+            resource
+        },
+    );
+
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Isolation marks around arguments would end up in API responses
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        tracing::error!("Errors in the {} catalog: {:?}", locale.code(), errors);
+    }
+    bundle
+}
+
+/// Format a message with its arguments, falling back to English if the locale's catalog lacks it
+///
+/// Returns the message id itself when no catalog has the message, so a
+/// missing translation shows up rather than failing the request.
+pub fn localize(locale: Locale, id: &str, args: &[(&str, &str)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for &(name, value) in args {
+        fluent_args.set(name, value);
+    }
+
+    [locale, Locale::English]
+        .into_iter()
+        .find_map(|locale| {
+            let bundle = &BUNDLES[&locale];
+            let pattern = bundle.get_message(id)?.value()?;
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+            if !errors.is_empty() {
+                tracing::warn!(
+                    "Errors formatting {} in {}: {:?}",
+                    id,
+                    locale.code(),
+                    errors
+                );
+            }
+            Some(text.into_owned())
+        })
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Conventional description of a phoneme's features, as [`PhonemeFeatures::describe`] in English
+pub fn describe_features(features: &PhonemeFeatures, locale: Locale) -> String {
+    let term = |name: &str| localize(locale, &format!("term-{}", name), &[]);
+
+    let description = if features.is_vowel() {
+        let height = features.height().map(|h| term(h.as_str()));
+        let backness = features.backness().map(|b| term(b.as_str()));
+        let rounding = term(if features.is_rounded() {
+            "rounded"
+        } else {
+            "unrounded"
+        });
+        localize(
+            locale,
+            "phoneme-vowel",
+            &[
+                ("height", height.as_deref().unwrap_or_default()),
+                ("backness", backness.as_deref().unwrap_or_default()),
+                ("rounding", &rounding),
+            ],
+        )
+    } else {
+        let Some(manner) = features.manner() else {
+            return localize(locale, "phoneme-unknown", &[]);
+        };
+        let voicing = term(if features.is_voiced() {
+            "voiced"
+        } else {
+            "voiceless"
+        });
+        let place = features.place().map(|p| term(p.as_str()));
+        localize(
+            locale,
+            if features.is_lateral() {
+                "phoneme-lateral"
+            } else {
+                "phoneme-consonant"
+            },
+            &[
+                ("voicing", &voicing),
+                ("place", place.as_deref().unwrap_or_default()),
+                ("manner", &term(manner.as_str())),
+            ],
+        )
+    };
+
+    // A missing height, backness or place leaves a gap
+    description.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Description including secondary articulations, as [`ParsedPhoneme::describe`] in English
+pub fn describe_phoneme(parsed: &ParsedPhoneme, locale: Locale) -> String {
+    let mut description = match diphthong_targets(&parsed.base) {
+        Some((onset, offset)) => localize(
+            locale,
+            "phoneme-diphthong",
+            &[("onset", onset), ("offset", offset)],
+        ),
+        None => describe_features(&parsed.features, locale),
+    };
+
+    // Applied innermost first, so the first reads outermost as in "long nasalized ..."
+    let modifiers = [
+        (parsed.syllabic, "syllabic"),
+        (parsed.labialized, "labialized"),
+        (parsed.palatalized, "palatalized"),
+        (parsed.nasalized, "nasalized"),
+        (parsed.aspirated, "aspirated"),
+        (parsed.long, "long"),
+    ];
+    for (_, modifier) in modifiers.into_iter().filter(|(set, _)| *set) {
+        let modifier = localize(locale, &format!("term-{}", modifier), &[]);
+        description = localize(
+            locale,
+            "phoneme-modified",
+            &[("modifier", &modifier), ("description", &description)],
+        );
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phoneme::parse_ipa;
+
+    #[test]
+    fn test_parse_and_negotiate_locales() {
+        assert_eq!(Locale::parse("zh-Hans-CN"), Some(Locale::Mandarin));
+        assert_eq!(Locale::parse("MS_my"), Some(Locale::Malay));
+        assert_eq!(Locale::parse("fr"), None);
+
+        assert_eq!(
+            Locale::from_accept_language("fr-FR, ms;q=0.5, zh;q=0.8, *;q=0.1"),
+            Some(Locale::Mandarin)
+        );
+        assert_eq!(
+            Locale::from_accept_language("en-GB;q=0, ms"),
+            Some(Locale::Malay)
+        );
+        assert_eq!(Locale::from_accept_language("de, fr"), None);
+    }
+
+    #[test]
+    fn test_every_catalog_has_every_message() {
+        let ids = |locale: Locale| {
+            let mut ids: Vec<&str> = locale
+                .catalog()
+                .lines()
+                .filter_map(|line| line.split_once(" = ").map(|(id, _)| id))
+                .filter(|id| !id.starts_with([' ', '#']))
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        for locale in Locale::ALL {
+            assert_eq!(ids(locale), ids(Locale::English), "{:?}", locale);
+        }
+    }
+
+    #[test]
+    fn test_localize_fills_arguments() {
+        assert_eq!(
+            localize(Locale::Malay, "advice-listen", &[("expected", "θ")]),
+            "dengar /θ/ sekali lagi dan tirunya"
+        );
+        assert_eq!(
+            localize(Locale::Mandarin, "no-such-message", &[]),
+            "no-such-message"
+        );
+    }
+
+    #[test]
+    fn test_describe_phoneme() {
+        let describe = |symbol: &str, locale| describe_phoneme(&parse_ipa(symbol).unwrap(), locale);
+
+        assert_eq!(describe("θ", Locale::English), "voiceless dental fricative");
+        assert_eq!(describe("θ", Locale::Malay), "geseran gigi tak bersuara");
+        assert_eq!(describe("θ", Locale::Mandarin), "清齿擦音");
+        assert_eq!(describe("i", Locale::Mandarin), "闭前不圆唇元音");
+        assert_eq!(
+            describe("l", Locale::English),
+            "voiced alveolar lateral approximant"
+        );
+
+        // Matches the English descriptions of the phoneme table
+        for symbol in ["pʰ", "iː", "aɪ", "n̩", "ɫ", "ʔ"] {
+            if let Some(parsed) = parse_ipa(symbol) {
+                assert_eq!(
+                    describe_phoneme(&parsed, Locale::English),
+                    parsed.describe()
+                );
+            }
+        }
+    }
+}
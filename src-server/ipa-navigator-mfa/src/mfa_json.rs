@@ -0,0 +1,210 @@
+//! Parser for the alignments `mfa align --output_format json` writes
+//!
+//! Each file holds the aligned duration and a map of tiers, each a list of
+//! `[begin, end, label]` entries:
+//!
+//! ```json
+//! {"start": 0, "end": 1.2, "tiers": {
+//!     "words": {"type": "interval", "entries": [[0.1, 0.5, "say"]]},
+//!     "phones": {"type": "interval", "entries": [[0.1, 0.2, "s"], [0.2, 0.5, "eɪ"]]}}}
+//! ```
+//!
+//! With several speakers in a corpus MFA names the tiers `"<speaker> - words"`
+//! and `"<speaker> - phones"`.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::mfa_parser::MfaSegment;
+
+/// Why a JSON alignment could not be parsed, with the tier and 0-based entry it was found in
+#[derive(Debug, Error)]
+pub enum JsonAlignmentError {
+    #[error("not an MFA alignment: {0}")]
+    Syntax(#[from] serde_json::Error),
+
+    #[error("{tier} entry {index}: expected [begin, end, label]")]
+    MalformedEntry { tier: String, index: usize },
+
+    #[error("{tier} entry {index}: {field} is not a finite number")]
+    InvalidTime {
+        tier: String,
+        index: usize,
+        field: &'static str,
+    },
+
+    #[error("{tier} entry {index}: interval ends at {end} before it begins at {begin}")]
+    InvertedInterval {
+        tier: String,
+        index: usize,
+        begin: f64,
+        end: f64,
+    },
+}
+
+#[derive(Deserialize)]
+struct JsonAlignment {
+    tiers: BTreeMap<String, JsonTier>,
+}
+
+#[derive(Deserialize)]
+struct JsonTier {
+    entries: Vec<Vec<Value>>,
+}
+
+/// Parse an MFA JSON alignment file
+#[tracing::instrument(name = "mfa.parse_json", skip_all)]
+pub fn parse_alignment_json(path: impl AsRef<Path>) -> Result<Vec<MfaSegment>> {
+    let contents =
+        std::fs::read_to_string(path.as_ref()).context("Failed to open JSON alignment file")?;
+
+    parse_alignment_json_str(&contents).context("Malformed JSON alignment file")
+}
+
+/// Parse an MFA JSON alignment, keeping the words and phones tiers
+///
+/// Segments come out as [`parse_textgrid_str`](crate::mfa_parser::parse_textgrid_str)
+/// returns them: every word, then every phone. Entries must give finite times
+/// in order and a text label; anything after the label is ignored.
+pub fn parse_alignment_json_str(contents: &str) -> Result<Vec<MfaSegment>, JsonAlignmentError> {
+    let alignment: JsonAlignment = serde_json::from_str(contents)?;
+
+    let mut segments = Vec::new();
+    for (suffix, segment_type) in [("words", "word"), ("phones", "phone")] {
+        let tiers = alignment.tiers.iter().filter(|(name, _)| {
+            *name == suffix
+                || name
+                    .strip_suffix(suffix)
+                    .is_some_and(|speaker| speaker.ends_with(" - "))
+        });
+        for (name, tier) in tiers {
+            for (index, entry) in tier.entries.iter().enumerate() {
+                segments.push(parse_entry(name, index, entry, segment_type)?);
+            }
+        }
+    }
+    Ok(segments)
+}
+
+fn parse_entry(
+    tier: &str,
+    index: usize,
+    entry: &[Value],
+    segment_type: &str,
+) -> Result<MfaSegment, JsonAlignmentError> {
+    let [begin, end, label, ..] = entry else {
+        return Err(JsonAlignmentError::MalformedEntry {
+            tier: tier.to_string(),
+            index,
+        });
+    };
+    let time = |value: &Value, field| {
+        value
+            .as_f64()
+            .filter(|time| time.is_finite())
+            .ok_or_else(|| JsonAlignmentError::InvalidTime {
+                tier: tier.to_string(),
+                index,
+                field,
+            })
+    };
+    let begin = time(begin, "begin")?;
+    let end = time(end, "end")?;
+    if end < begin {
+        return Err(JsonAlignmentError::InvertedInterval {
+            tier: tier.to_string(),
+            index,
+            begin,
+            end,
+        });
+    }
+    let label = label
+        .as_str()
+        .ok_or_else(|| JsonAlignmentError::MalformedEntry {
+            tier: tier.to_string(),
+            index,
+        })?;
+
+    Ok(MfaSegment {
+        begin,
+        end,
+        label: label.to_string(),
+        segment_type: segment_type.to_string(),
+        confidence: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alignment_json_str_keeps_words_and_phones() {
+        let contents = r#"{
+            "start": 0, "end": 1.2,
+            "tiers": {
+                "phones": {"type": "interval", "entries": [[0.1, 0.2, "s"], [0.2, 0.5, "eɪ"]]},
+                "words": {"type": "interval", "entries": [[0.1, 0.5, "say \"hi\"", "extra"]]},
+                "notes": {"type": "interval", "entries": [[0, 1, "ignored"]]}
+            }
+        }"#;
+
+        let segments = parse_alignment_json_str(contents).unwrap();
+
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].label, "say \"hi\"");
+        assert_eq!(segments[0].segment_type, "word");
+        assert_eq!((segments[0].begin, segments[0].end), (0.1, 0.5));
+        assert_eq!(segments[1].label, "s");
+        assert_eq!(segments[2].label, "eɪ");
+        assert_eq!(segments[2].segment_type, "phone");
+    }
+
+    #[test]
+    fn test_parse_alignment_json_str_reads_speaker_tiers() {
+        let contents = r#"{"tiers": {
+            "corpus - words": {"type": "interval", "entries": [[0, 0.4, "hi"]]},
+            "corpus - phones": {"type": "interval", "entries": [[0, 0.1, "h"], [0.1, 0.4, "aɪ"]]},
+            "keywords": {"type": "interval", "entries": [[0, 1, "ignored"]]}
+        }}"#;
+
+        let segments = parse_alignment_json_str(contents).unwrap();
+        let labels: Vec<&str> = segments.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["hi", "h", "aɪ"]);
+    }
+
+    #[test]
+    fn test_parse_alignment_json_str_rejects_malformed_entries() {
+        let words = |entries: &str| {
+            format!(
+                r#"{{"tiers": {{"words": {{"type": "interval", "entries": [{}]}}}}}}"#,
+                entries
+            )
+        };
+
+        assert!(matches!(
+            parse_alignment_json_str("<TextGrid>"),
+            Err(JsonAlignmentError::Syntax(_))
+        ));
+        assert!(matches!(
+            parse_alignment_json_str(&words(r#"[0, 1]"#)),
+            Err(JsonAlignmentError::MalformedEntry { index: 0, .. })
+        ));
+        assert!(matches!(
+            parse_alignment_json_str(&words(r#"[0, 1, "a"], [0, 1, 2]"#)),
+            Err(JsonAlignmentError::MalformedEntry { index: 1, .. })
+        ));
+        assert!(matches!(
+            parse_alignment_json_str(&words(r#"["zero", 1, "a"]"#)),
+            Err(JsonAlignmentError::InvalidTime { field: "begin", .. })
+        ));
+        assert!(matches!(
+            parse_alignment_json_str(&words(r#"[0.5, 0.1, "a"]"#)),
+            Err(JsonAlignmentError::InvertedInterval { .. })
+        ));
+    }
+}
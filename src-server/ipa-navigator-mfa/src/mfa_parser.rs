@@ -1,7 +1,8 @@
 use anyhow::{Context, Result};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::mfa_json::parse_alignment_json;
 
 /// Represents a segment from MFA output (either a word or phoneme)
 #[derive(Debug, Clone)]
@@ -10,85 +11,253 @@ pub struct MfaSegment {
     pub end: f64,
     pub label: String,
     pub segment_type: String, // "word" or "phone"
+    /// How sure the aligner is of the segment, from 0.0 to 1.0, if it reported a score
+    pub confidence: Option<f64>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl MfaSegment {
+    /// Word segment without a confidence score, for tests
+    pub fn word(label: &str, begin: f64, end: f64) -> Self {
+        Self::new("word", label, begin, end)
+    }
+
+    /// Phone segment without a confidence score, for tests
+    pub fn phone(label: &str, begin: f64, end: f64) -> Self {
+        Self::new("phone", label, begin, end)
+    }
+
+    fn new(segment_type: &str, label: &str, begin: f64, end: f64) -> Self {
+        MfaSegment {
+            begin,
+            end,
+            label: label.to_string(),
+            segment_type: segment_type.to_string(),
+            confidence: None,
+        }
+    }
+}
+
+/// Why a TextGrid could not be parsed, with the 1-based line it was found on
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TextGridError {
+    #[error("line {line}: interval {field} is not a finite number: {value:?}")]
+    InvalidTime {
+        line: usize,
+        field: &'static str,
+        value: String,
+    },
+
+    #[error("line {line}: interval text given before its {field}")]
+    MissingTime { line: usize, field: &'static str },
+
+    #[error("line {line}: interval ends at {xmax} before it begins at {xmin}")]
+    InvertedInterval { line: usize, xmin: f64, xmax: f64 },
+
+    #[error("line {line}: text is not enclosed in quotes")]
+    UnquotedText { line: usize },
+
+    #[error("line {line}: interval started here has no text")]
+    UnterminatedInterval { line: usize },
+}
+
+/// An interval whose text has not been read yet
+struct OpenInterval {
+    line: usize,
+    xmin: Option<f64>,
+    xmax: Option<f64>,
 }
 
 /// Parse MFA TextGrid output file
+#[tracing::instrument(name = "mfa.parse", skip_all)]
 pub fn parse_textgrid(path: impl AsRef<Path>) -> Result<Vec<MfaSegment>> {
-    let file = File::open(path.as_ref()).context("Failed to open TextGrid file")?;
-    let reader = BufReader::new(file);
+    let contents =
+        std::fs::read_to_string(path.as_ref()).context("Failed to open TextGrid file")?;
 
-    let mut lines = reader.lines();
-    let mut segments = Vec::new();
+    parse_textgrid_str(&contents).context("Malformed TextGrid file")
+}
+
+/// File formats MFA can write its alignments in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlignmentFormat {
+    /// Praat TextGrids, MFA's default
+    #[default]
+    TextGrid,
+    /// JSON, see [`crate::mfa_json`]
+    Json,
+}
 
-    // Parse TextGrid format
+impl AlignmentFormat {
+    /// Every format, in the order alignments are looked for
+    pub const ALL: [AlignmentFormat; 2] = [AlignmentFormat::TextGrid, AlignmentFormat::Json];
+
+    /// Parse "textgrid" or "json", as given to `--output_format`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "textgrid" | "long_textgrid" => Some(Self::TextGrid),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Extension MFA gives files in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AlignmentFormat::TextGrid => "TextGrid",
+            AlignmentFormat::Json => "json",
+        }
+    }
+
+    /// Format of an alignment file, judged by its extension
+    pub fn of_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?;
+        Self::ALL
+            .into_iter()
+            .find(|format| extension == format.extension())
+    }
+}
+
+/// Path of the alignment MFA wrote for `stem` in `dir`, in whichever format it is
+pub fn find_alignment(dir: &Path, stem: &str) -> Option<PathBuf> {
+    AlignmentFormat::ALL
+        .into_iter()
+        .map(|format| dir.join(format!("{}.{}", stem, format.extension())))
+        .find(|path| path.exists())
+}
+
+/// Parse an MFA alignment file in the format its extension names
+pub fn parse_alignment(path: impl AsRef<Path>) -> Result<Vec<MfaSegment>> {
+    let path = path.as_ref();
+    match AlignmentFormat::of_path(path) {
+        Some(AlignmentFormat::Json) => parse_alignment_json(path),
+        Some(AlignmentFormat::TextGrid) => parse_textgrid(path),
+        None => Err(anyhow::anyhow!("Unknown alignment format: {:?}", path)),
+    }
+}
+
+/// Parse the long text format of a TextGrid, keeping the words and phones tiers
+///
+/// Every interval must give finite `xmin` and `xmax` times, in order, before
+/// its quoted `text`; anything else is rejected rather than guessed at.
+pub fn parse_textgrid_str(contents: &str) -> Result<Vec<MfaSegment>, TextGridError> {
+    let mut segments = Vec::new();
     let mut current_tier = None;
-    let mut parsing_interval = false;
-    let mut xmin = 0.0;
-    let mut xmax = 0.0;
+    let mut interval: Option<OpenInterval> = None;
 
-    while let Some(Ok(line)) = lines.next() {
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
         let line = line.trim();
 
-        // Track which tier we're in
-        if line.contains("name = ") {
-            if line.contains("\"words\"") {
-                current_tier = Some("word");
-            } else if line.contains("\"phones\"") {
-                current_tier = Some("phone");
-            } else {
-                current_tier = None;
+        // Start of an interval, or of the next tier
+        if line.starts_with("intervals [") || line.starts_with("item [") {
+            if let Some(open) = interval {
+                return Err(TextGridError::UnterminatedInterval { line: open.line });
+            }
+            if line.starts_with("intervals [") {
+                interval = Some(OpenInterval {
+                    line: line_number,
+                    xmin: None,
+                    xmax: None,
+                });
             }
             continue;
         }
 
-        // Start of an interval
-        if line.contains("intervals [") {
-            parsing_interval = true;
-            xmin = 0.0;
-            xmax = 0.0;
+        let Some((key, value)) = line.split_once('=') else {
             continue;
-        }
+        };
+        let value = value.trim();
+
+        match (key.trim(), interval.as_mut()) {
+            // Track which tier we're in
+            ("name", None) => {
+                current_tier = match value {
+                    "\"words\"" => Some("word"),
+                    "\"phones\"" => Some("phone"),
+                    _ => None,
+                };
+            }
+            ("xmin", Some(open)) => open.xmin = Some(parse_time(line_number, "xmin", value)?),
+            ("xmax", Some(open)) => open.xmax = Some(parse_time(line_number, "xmax", value)?),
+            ("text", Some(open)) => {
+                let xmin = open.xmin.ok_or(TextGridError::MissingTime {
+                    line: line_number,
+                    field: "xmin",
+                })?;
+                let xmax = open.xmax.ok_or(TextGridError::MissingTime {
+                    line: line_number,
+                    field: "xmax",
+                })?;
+                if xmax < xmin {
+                    return Err(TextGridError::InvertedInterval {
+                        line: open.line,
+                        xmin,
+                        xmax,
+                    });
+                }
+                let label = parse_text(line_number, value)?;
 
-        // Parse interval properties
-        if parsing_interval {
-            if line.contains("xmin = ") {
-                xmin = line
-                    .split('=')
-                    .nth(1)
-                    .and_then(|s| s.trim().parse::<f64>().ok())
-                    .unwrap_or(0.0);
-            } else if line.contains("xmax = ") {
-                xmax = line
-                    .split('=')
-                    .nth(1)
-                    .and_then(|s| s.trim().parse::<f64>().ok())
-                    .unwrap_or(0.0);
-            } else if line.contains("text = ") {
-                // Extract text between quotes
-                let text = line.split('"').nth(1).unwrap_or("").to_string();
-
-                // End of an interval - create segment if in words or phones tier
+                // Include all segments in the words and phones tiers, even empty ones
                 if let Some(tier_type) = current_tier {
-                    // Include all segments, even empty ones
                     segments.push(MfaSegment {
                         begin: xmin,
                         end: xmax,
-                        label: text,
+                        label,
                         segment_type: tier_type.to_string(),
+                        confidence: None,
                     });
                 }
-
-                parsing_interval = false;
+                interval = None;
             }
+            _ => {}
         }
     }
 
-    Ok(segments)
+    match interval {
+        Some(open) => Err(TextGridError::UnterminatedInterval { line: open.line }),
+        None => Ok(segments),
+    }
+}
+
+fn parse_time(line: usize, field: &'static str, value: &str) -> Result<f64, TextGridError> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|time| time.is_finite())
+        .ok_or_else(|| TextGridError::InvalidTime {
+            line,
+            field,
+            value: value.to_string(),
+        })
+}
+
+/// Unquote a text value, where Praat writes a quote inside the text as `""`
+fn parse_text(line: usize, value: &str) -> Result<String, TextGridError> {
+    value
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .map(|text| text.replace("\"\"", "\""))
+        .ok_or(TextGridError::UnquotedText { line })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    fn interval(xmin: &str, xmax: &str, text: &str) -> String {
+        format!(
+            "        intervals [1]:\n            xmin = {}\n            xmax = {}\n            text = {}\n",
+            xmin, xmax, text
+        )
+    }
+
+    fn tier(name: &str, intervals: &str) -> String {
+        format!(
+            "    item [1]:\n        class = \"IntervalTier\"\n        name = \"{}\"\n{}",
+            name, intervals
+        )
+    }
 
     #[test]
     fn test_parse_textgrid() -> Result<()> {
@@ -120,4 +289,161 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_textgrid_str_keeps_words_and_phones() {
+        let contents = [
+            tier("words", &interval("0.1", "0.5", "\"say \"\"hi\"\"\"")),
+            tier("phones", &interval("0.1", "0.2", "\"s\"")),
+            tier("notes", &interval("0", "1", "\"ignored\"")),
+        ]
+        .concat();
+
+        let segments = parse_textgrid_str(&contents).unwrap();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].label, "say \"hi\"");
+        assert_eq!(segments[0].segment_type, "word");
+        assert_eq!((segments[0].begin, segments[0].end), (0.1, 0.5));
+        assert_eq!(segments[1].label, "s");
+        assert_eq!(segments[1].segment_type, "phone");
+    }
+
+    #[test]
+    fn test_parse_alignment_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("job.json");
+        std::fs::write(
+            &json,
+            r#"{"tiers": {"phones": {"type": "interval", "entries": [[0, 0.1, "s"]]}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(find_alignment(dir.path(), "job"), Some(json.clone()));
+        assert_eq!(find_alignment(dir.path(), "other"), None);
+        assert_eq!(parse_alignment(&json).unwrap()[0].label, "s");
+        assert!(parse_alignment(dir.path().join("job.lab")).is_err());
+
+        assert_eq!(
+            AlignmentFormat::parse(" JSON "),
+            Some(AlignmentFormat::Json)
+        );
+        assert_eq!(
+            AlignmentFormat::parse("textgrid"),
+            Some(AlignmentFormat::TextGrid)
+        );
+        assert_eq!(AlignmentFormat::parse("csv"), None);
+    }
+
+    #[test]
+    fn test_parse_textgrid_str_rejects_malformed_intervals() {
+        let cases = [
+            (
+                interval("zero", "0.5", "\"a\""),
+                TextGridError::InvalidTime {
+                    line: 5,
+                    field: "xmin",
+                    value: "zero".to_string(),
+                },
+            ),
+            (
+                interval("0", "inf", "\"a\""),
+                TextGridError::InvalidTime {
+                    line: 6,
+                    field: "xmax",
+                    value: "inf".to_string(),
+                },
+            ),
+            (
+                interval("0.5", "0.1", "\"a\""),
+                TextGridError::InvertedInterval {
+                    line: 4,
+                    xmin: 0.5,
+                    xmax: 0.1,
+                },
+            ),
+            (
+                interval("0", "0.5", "a"),
+                TextGridError::UnquotedText { line: 7 },
+            ),
+            (
+                "        intervals [1]:\n            xmin = 0\n            text = \"a\"\n"
+                    .to_string(),
+                TextGridError::MissingTime {
+                    line: 6,
+                    field: "xmax",
+                },
+            ),
+            (
+                "        intervals [1]:\n            xmin = 0\n            xmax = 1\n".to_string(),
+                TextGridError::UnterminatedInterval { line: 4 },
+            ),
+            (
+                "        intervals [1]:\n            xmin = 0\n".to_string()
+                    + &interval("0", "1", "\"a\""),
+                TextGridError::UnterminatedInterval { line: 4 },
+            ),
+        ];
+
+        for (intervals, expected) in cases {
+            let contents = tier("words", &intervals);
+            assert_eq!(parse_textgrid_str(&contents).unwrap_err(), expected);
+        }
+    }
+
+    /// Lines resembling TextGrid syntax, to reach the parser's states more often than random text
+    fn textgrid_line() -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just("item [1]:".to_string()),
+            Just("intervals [1]:".to_string()),
+            prop_oneof![Just("words"), Just("phones"), Just("other")]
+                .prop_map(|name| format!("name = \"{}\"", name)),
+            (prop_oneof![Just("xmin"), Just("xmax")], any::<f64>())
+                .prop_map(|(key, time)| format!("{} = {}", key, time)),
+            (prop_oneof![Just("xmin"), Just("xmax"), Just("text")], ".*")
+                .prop_map(|(key, value)| format!("{} = {}", key, value)),
+            "text = \"[^\"]*\"",
+            ".*",
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_textgrid_str_never_panics(contents in ".*") {
+            let _ = parse_textgrid_str(&contents);
+        }
+
+        #[test]
+        fn prop_parsed_segments_are_finite_and_ordered(
+            lines in prop::collection::vec(textgrid_line(), 0..40)
+        ) {
+            if let Ok(segments) = parse_textgrid_str(&lines.join("\n")) {
+                for segment in segments {
+                    prop_assert!(segment.begin.is_finite() && segment.end.is_finite());
+                    prop_assert!(segment.begin <= segment.end);
+                }
+            }
+        }
+
+        #[test]
+        fn prop_well_formed_intervals_round_trip(
+            intervals in prop::collection::vec((0.0..100.0f64, 0.0..10.0f64, "[^\"\n\r]*"), 0..10)
+        ) {
+            let body: String = intervals
+                .iter()
+                .map(|(start, length, text)| {
+                    interval(&start.to_string(), &(start + length).to_string(), &format!("\"{}\"", text))
+                })
+                .collect();
+
+            let segments = parse_textgrid_str(&tier("phones", &body)).unwrap();
+
+            prop_assert_eq!(segments.len(), intervals.len());
+            for (segment, (start, length, text)) in segments.iter().zip(&intervals) {
+                prop_assert_eq!(segment.begin, *start);
+                prop_assert_eq!(segment.end, start + length);
+                prop_assert_eq!(&segment.label, text);
+            }
+        }
+    }
 }
@@ -0,0 +1,176 @@
+//! Speaking pace of a recording, word by word, compared with a reference reading
+//!
+//! Both recordings are aligned against the same transcript, so their words
+//! pair up in order. Each of the learner's words is judged against the time
+//! the reference took to say it.
+
+use crate::mfa_parser::MfaSegment;
+
+/// Share a word's duration may differ from the reference's before it is too fast or too slow
+pub const DEFAULT_PACE_TOLERANCE: f64 = 0.3;
+
+/// How a word's duration compares with the reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaceVerdict {
+    TooFast,
+    OnPace,
+    TooSlow,
+}
+
+impl PaceVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaceVerdict::TooFast => "too_fast",
+            PaceVerdict::OnPace => "on_pace",
+            PaceVerdict::TooSlow => "too_slow",
+        }
+    }
+}
+
+/// One word's duration in the recording and in the reference, in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordPace {
+    pub word: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub duration: f64,
+    pub reference_duration: f64,
+    /// `duration - reference_duration`, negative when the word was said faster
+    pub delta: f64,
+    pub verdict: PaceVerdict,
+}
+
+/// The recording's pace compared with the reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaceComparison {
+    pub words: Vec<WordPace>,
+    /// Time spent on the paired words in the recording and in the reference
+    pub duration: f64,
+    pub reference_duration: f64,
+}
+
+impl PaceComparison {
+    /// Recording's speaking time over the reference's, above 1.0 when slower
+    pub fn tempo_ratio(&self) -> f64 {
+        if self.reference_duration > 0.0 {
+            self.duration / self.reference_duration
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Words with their start and end times, in the order spoken
+pub fn word_times(segments: &[MfaSegment]) -> Vec<(&str, f64, f64)> {
+    segments
+        .iter()
+        .filter(|segment| segment.segment_type == "word" && !segment.label.is_empty())
+        .map(|segment| (segment.label.as_str(), segment.begin, segment.end))
+        .collect()
+}
+
+/// Compare the words of a recording with those of a reference reading of the same transcript
+///
+/// Words are paired in order and skipped where the aligners disagree on the
+/// word, e.g. one marked it unknown. Returns `None` if no words pair up.
+pub fn compare_pace(
+    segments: &[MfaSegment],
+    reference: &[MfaSegment],
+    tolerance: f64,
+) -> Option<PaceComparison> {
+    let words: Vec<WordPace> = word_times(segments)
+        .into_iter()
+        .zip(word_times(reference))
+        .filter(|(word, reference_word)| word.0.eq_ignore_ascii_case(reference_word.0))
+        .map(
+            |((word, start, end), (_, reference_start, reference_end))| {
+                let duration = end - start;
+                let reference_duration = reference_end - reference_start;
+                WordPace {
+                    word: word.to_string(),
+                    start_time: start,
+                    end_time: end,
+                    duration,
+                    reference_duration,
+                    delta: duration - reference_duration,
+                    verdict: verdict(duration, reference_duration, tolerance),
+                }
+            },
+        )
+        .collect();
+
+    if words.is_empty() {
+        return None;
+    }
+
+    Some(PaceComparison {
+        duration: words.iter().map(|word| word.duration).sum(),
+        reference_duration: words.iter().map(|word| word.reference_duration).sum(),
+        words,
+    })
+}
+
+fn verdict(duration: f64, reference_duration: f64, tolerance: f64) -> PaceVerdict {
+    if reference_duration <= 0.0 {
+        PaceVerdict::OnPace
+    } else if duration < reference_duration * (1.0 - tolerance) {
+        PaceVerdict::TooFast
+    } else if duration > reference_duration * (1.0 + tolerance) {
+        PaceVerdict::TooSlow
+    } else {
+        PaceVerdict::OnPace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compares_each_word_with_the_reference() {
+        let learner = [
+            MfaSegment::word("the", 0.0, 0.1),
+            MfaSegment::phone("ð", 0.0, 0.05),
+            MfaSegment::word("", 0.1, 0.3),
+            MfaSegment::word("quick", 0.3, 1.0),
+            MfaSegment::word("fox", 1.0, 1.4),
+        ];
+        let reference = [
+            MfaSegment::word("the", 0.0, 0.2),
+            MfaSegment::word("quick", 0.2, 0.6),
+            MfaSegment::word("fox", 0.6, 1.0),
+        ];
+
+        let pace = compare_pace(&learner, &reference, DEFAULT_PACE_TOLERANCE).unwrap();
+        let verdicts: Vec<_> = pace.words.iter().map(|word| word.verdict).collect();
+        assert_eq!(
+            verdicts,
+            [
+                PaceVerdict::TooFast,
+                PaceVerdict::TooSlow,
+                PaceVerdict::OnPace
+            ]
+        );
+        assert!((pace.words[1].delta - 0.3).abs() < 1e-9);
+        assert!((pace.duration - 1.2).abs() < 1e-9);
+        assert!((pace.tempo_ratio() - 1.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_skips_words_the_alignments_disagree_on() {
+        let learner = [
+            MfaSegment::word("<unk>", 0.0, 0.5),
+            MfaSegment::word("fox", 0.5, 0.9),
+        ];
+        let reference = [
+            MfaSegment::word("quick", 0.0, 0.4),
+            MfaSegment::word("FOX", 0.4, 0.8),
+        ];
+
+        let pace = compare_pace(&learner, &reference, DEFAULT_PACE_TOLERANCE).unwrap();
+        assert_eq!(pace.words.len(), 1);
+        assert_eq!(pace.words[0].word, "fox");
+
+        assert!(compare_pace(&learner[..1], &reference[..1], DEFAULT_PACE_TOLERANCE).is_none());
+    }
+}
@@ -0,0 +1,463 @@
+//! Differences between the platforms and container runtimes the MFA pipeline is run with
+//!
+//! On Linux the docker CLI reaches the daemon through a local socket and bind
+//! mounts take host paths as they are. Rootless Docker's socket is in the
+//! user's runtime directory instead, and Podman needs no daemon at all. Docker Desktop on Windows listens on a
+//! named pipe and takes host paths with drive letters, and on Windows and macOS
+//! reports mount sources under its VM's own paths. Whatever the host, paths
+//! inside the container are `/`-separated.
+//!
+//! Path handling here goes by the syntax of a path rather than the OS the
+//! server runs on, so Windows paths are recognised (and tested) anywhere.
+
+use anyhow::Result;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::LazyLock;
+
+/// Container CLI configured from the environment
+pub static CONTAINER_CLI: LazyLock<ContainerCli> = LazyLock::new(ContainerCli::from_env);
+
+/// Endpoint of Docker Desktop's engine on Windows
+pub const DEFAULT_NPIPE_HOST: &str = "npipe:////./pipe/docker_engine";
+
+/// Schemes the container CLIs accept for their endpoint
+const HOST_SCHEMES: [&str; 5] = ["unix://", "npipe://", "tcp://", "ssh://", "fd://"];
+
+/// Prefixes under which Docker Desktop reports the sources of bind mounts
+const DESKTOP_MOUNT_PREFIXES: [&str; 2] = ["/run/desktop/mnt/host", "/host_mnt"];
+
+/// Registry Podman resolves unqualified image names against
+const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// Container engine the MFA container runs on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContainerRuntime {
+    /// Docker with its daemon running as root
+    #[default]
+    Docker,
+    /// Docker with its daemon running as the current user
+    RootlessDocker,
+    /// Podman, daemonless and rootless by default
+    Podman,
+}
+
+impl ContainerRuntime {
+    /// Parse a runtime name as given in `MFA_CONTAINER_RUNTIME`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('_', "-").as_str() {
+            "docker" => Some(ContainerRuntime::Docker),
+            "rootless-docker" | "docker-rootless" => Some(ContainerRuntime::RootlessDocker),
+            "podman" => Some(ContainerRuntime::Podman),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::RootlessDocker => "rootless-docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+
+    /// Binary looked up on `PATH` when none is configured
+    pub fn default_binary(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker | ContainerRuntime::RootlessDocker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+
+    /// Endpoint used when none is configured
+    ///
+    /// The rootless Docker daemon listens in the user's runtime directory rather
+    /// than on `/var/run/docker.sock`. Docker and Podman are left to their own
+    /// defaults, which for Podman means running containers without a service.
+    pub fn default_host(&self) -> Option<String> {
+        match self {
+            ContainerRuntime::RootlessDocker => env::var("XDG_RUNTIME_DIR")
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| format!("unix://{}/docker.sock", dir.trim_end_matches('/'))),
+            ContainerRuntime::Docker | ContainerRuntime::Podman => None,
+        }
+    }
+
+    /// Global flag the CLI takes its endpoint with
+    fn host_flag(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker | ContainerRuntime::RootlessDocker => "--host",
+            ContainerRuntime::Podman => "--url",
+        }
+    }
+
+    /// Image reference the runtime pulls for `image`
+    ///
+    /// Podman refuses to guess the registry of a short name when run
+    /// non-interactively, so names without one are qualified with Docker Hub.
+    pub fn qualify_image(&self, image: &str) -> String {
+        let first = image.split('/').next().unwrap_or_default();
+        let has_registry = image.contains('/')
+            && (first.contains('.') || first.contains(':') || first == "localhost");
+
+        match self {
+            ContainerRuntime::Podman if !has_registry => {
+                format!("{}/{}", DEFAULT_REGISTRY, image)
+            }
+            _ => image.to_string(),
+        }
+    }
+
+    /// Options appended to each bind mount
+    ///
+    /// Podman is mostly found on SELinux hosts, where a mount must be relabelled
+    /// (`z`, shared between containers) before the container may write to it.
+    pub fn mount_options(&self) -> Option<&'static str> {
+        match self {
+            ContainerRuntime::Podman => Some("z"),
+            ContainerRuntime::Docker | ContainerRuntime::RootlessDocker => None,
+        }
+    }
+}
+
+/// How the container CLI is invoked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerCli {
+    pub runtime: ContainerRuntime,
+    /// Path to the CLI binary, or a name looked up on `PATH`
+    pub binary: PathBuf,
+    /// Endpoint passed to the CLI, or `None` for the CLI's own default
+    pub host: Option<String>,
+}
+
+impl Default for ContainerCli {
+    fn default() -> Self {
+        Self::for_runtime(ContainerRuntime::default())
+    }
+}
+
+impl ContainerCli {
+    /// The runtime's default binary and endpoint
+    pub fn for_runtime(runtime: ContainerRuntime) -> Self {
+        Self {
+            runtime,
+            binary: PathBuf::from(runtime.default_binary()),
+            host: runtime.default_host(),
+        }
+    }
+
+    /// Read `MFA_CONTAINER_RUNTIME`, `MFA_CONTAINER_BIN` and `MFA_CONTAINER_HOST`
+    ///
+    /// `MFA_DOCKER_BIN` and `MFA_DOCKER_HOST` are read when the `MFA_CONTAINER_`
+    /// variables are not set. Without an endpoint the CLI picks its own, from
+    /// `DOCKER_HOST`, `CONTAINER_HOST` or the current context. An unknown
+    /// runtime or invalid endpoint is ignored with a warning.
+    pub fn from_env() -> Self {
+        let runtime = match env::var("MFA_CONTAINER_RUNTIME") {
+            Ok(name) => ContainerRuntime::parse(&name).unwrap_or_else(|| {
+                tracing::warn!("Unknown MFA_CONTAINER_RUNTIME '{}', using docker", name);
+                ContainerRuntime::default()
+            }),
+            Err(_) => ContainerRuntime::default(),
+        };
+        let defaults = Self::for_runtime(runtime);
+
+        let setting = |name: &str, legacy: &str| {
+            env::var(name)
+                .or_else(|_| env::var(legacy))
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+
+        let binary = setting("MFA_CONTAINER_BIN", "MFA_DOCKER_BIN")
+            .map(PathBuf::from)
+            .unwrap_or(defaults.binary);
+
+        let host = match setting("MFA_CONTAINER_HOST", "MFA_DOCKER_HOST") {
+            Some(spec) => match parse_docker_host(&spec) {
+                Ok(host) => Some(host),
+                Err(e) => {
+                    tracing::warn!("Ignoring MFA_CONTAINER_HOST: {}", e);
+                    defaults.host
+                }
+            },
+            None => defaults.host,
+        };
+
+        Self {
+            runtime,
+            binary,
+            host,
+        }
+    }
+
+    /// Name of the CLI for messages
+    pub fn name(&self) -> &'static str {
+        self.runtime.default_binary()
+    }
+
+    /// A CLI command with the configured endpoint, ready for its arguments
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.binary);
+        if let Some(host) = &self.host {
+            command.args([self.runtime.host_flag(), host]);
+        }
+        command
+    }
+}
+
+/// Normalise a daemon endpoint into the URL form the container CLIs accept
+///
+/// Windows named pipes may be given as `\\.\pipe\name` or `//./pipe/name`.
+pub fn parse_docker_host(spec: &str) -> Result<String> {
+    let spec = spec.trim();
+
+    let pipe = spec
+        .strip_prefix(r"\\.\pipe\")
+        .or_else(|| spec.strip_prefix("//./pipe/"));
+    if let Some(name) = pipe {
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Named pipe '{}' has no name", spec));
+        }
+        return Ok(format!("npipe:////./pipe/{}", name));
+    }
+
+    if HOST_SCHEMES.iter().any(|scheme| spec.starts_with(scheme)) {
+        return Ok(spec.to_string());
+    }
+
+    Err(anyhow::anyhow!(
+        "Unsupported docker endpoint '{}', expected one of {}",
+        spec,
+        HOST_SCHEMES.join(", ")
+    ))
+}
+
+/// Whether a path starts with a drive letter (`C:\`, `C:/`) or is a UNC path
+pub fn is_windows_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && matches!(bytes[2], b'\\' | b'/');
+    drive || path.starts_with(r"\\")
+}
+
+/// Whether a host path is absolute, on this platform or as a Windows path
+pub fn is_absolute_host_path(path: &str) -> bool {
+    Path::new(path).is_absolute() || is_windows_path(path)
+}
+
+/// Whether a container path is absolute, which for Linux containers means `/`-rooted
+pub fn is_absolute_container_path(path: &str) -> bool {
+    path.starts_with('/')
+}
+
+/// Split Docker's `host:container[:mode]` bind syntax, allowing a drive letter in the host path
+pub fn split_bind_spec(spec: &str) -> (&str, &str) {
+    let spec = spec.trim();
+    let skip = if is_windows_path(spec) { 2 } else { 0 };
+
+    match spec[skip..].find(':') {
+        Some(i) => {
+            let (host, rest) = spec.split_at(skip + i);
+            let container = rest[1..].split(':').next().unwrap_or_default();
+            (host, container)
+        }
+        None => (spec, ""),
+    }
+}
+
+/// A host path as given to `docker -v`
+///
+/// Windows paths get `/` separators, which Docker Desktop accepts and which
+/// keep the backslashes out of the bind syntax.
+pub fn mount_source(host_path: &Path) -> String {
+    let path = host_path.to_string_lossy();
+    let path = path.strip_prefix(r"\\?\").unwrap_or(&path);
+
+    if is_windows_path(path) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
+}
+
+/// A container path as a string, with `/` separators whatever the host
+pub fn container_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Whether the host path of a configured mount is the source `docker inspect` reports
+///
+/// Docker Desktop reports sources under its VM (`/run/desktop/mnt/host/c/...`
+/// for `C:\...`, `/host_mnt/Users/...` on macOS) and Windows paths compare
+/// without regard to case. The configured path is also compared once symlinks
+/// are resolved, since macOS's temporary directories live behind `/var`.
+pub fn same_host_path(configured: &Path, reported: &Path) -> bool {
+    let reported = reported.to_string_lossy();
+    let matches = |configured: &Path| {
+        let configured = mount_source(configured);
+        let windows = is_windows_path(&configured);
+        normalize(&configured, windows) == normalize(&desktop_source(&reported, windows), windows)
+    };
+
+    matches(configured) || fs::canonicalize(configured).is_ok_and(|resolved| matches(&resolved))
+}
+
+/// The host path behind a source reported by Docker Desktop, or the source unchanged
+fn desktop_source(reported: &str, windows: bool) -> String {
+    let Some(rest) = DESKTOP_MOUNT_PREFIXES
+        .iter()
+        .find_map(|prefix| reported.strip_prefix(prefix))
+    else {
+        return reported.to_string();
+    };
+
+    let mut parts = rest.trim_start_matches('/').splitn(2, '/');
+    match (windows, parts.next(), parts.next()) {
+        (true, Some(drive), rest) if drive.len() == 1 => {
+            format!("{}:/{}", drive, rest.unwrap_or_default())
+        }
+        _ => rest.to_string(),
+    }
+}
+
+fn normalize(path: &str, windows: bool) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_end_matches('/');
+    if windows {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_docker_host() {
+        assert_eq!(
+            parse_docker_host(r"\\.\pipe\docker_engine").unwrap(),
+            DEFAULT_NPIPE_HOST
+        );
+        assert_eq!(
+            parse_docker_host("//./pipe/dockerDesktopLinuxEngine").unwrap(),
+            "npipe:////./pipe/dockerDesktopLinuxEngine"
+        );
+        assert_eq!(
+            parse_docker_host(" unix:///var/run/docker.sock ").unwrap(),
+            "unix:///var/run/docker.sock"
+        );
+        assert!(parse_docker_host(r"\\.\pipe\").is_err());
+        assert!(parse_docker_host("localhost:2375").is_err());
+    }
+
+    #[test]
+    fn test_command_passes_host() {
+        let cli = ContainerCli {
+            runtime: ContainerRuntime::Docker,
+            binary: PathBuf::from(r"C:\Program Files\Docker\docker.exe"),
+            host: Some(DEFAULT_NPIPE_HOST.to_string()),
+        };
+        let command = cli.command();
+
+        assert_eq!(command.get_program(), cli.binary.as_os_str());
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["--host", DEFAULT_NPIPE_HOST]
+        );
+
+        let podman = ContainerCli {
+            host: Some("unix:///run/user/1000/podman/podman.sock".to_string()),
+            ..ContainerCli::for_runtime(ContainerRuntime::Podman)
+        };
+        assert_eq!(podman.command().get_program(), "podman");
+        assert_eq!(podman.command().get_args().next().unwrap(), "--url");
+    }
+
+    #[test]
+    fn test_container_runtime() {
+        assert_eq!(
+            ContainerRuntime::parse("Rootless_Docker"),
+            Some(ContainerRuntime::RootlessDocker)
+        );
+        assert_eq!(
+            ContainerRuntime::parse("podman"),
+            Some(ContainerRuntime::Podman)
+        );
+        assert_eq!(ContainerRuntime::parse("lxc"), None);
+
+        let podman = ContainerRuntime::Podman;
+        assert_eq!(
+            podman.qualify_image("mmcauliffe/montreal-forced-aligner:latest"),
+            "docker.io/mmcauliffe/montreal-forced-aligner:latest"
+        );
+        assert_eq!(
+            podman.qualify_image("ghcr.io/lab/mfa:3"),
+            "ghcr.io/lab/mfa:3"
+        );
+        assert_eq!(
+            podman.qualify_image("localhost:5000/mfa"),
+            "localhost:5000/mfa"
+        );
+        assert_eq!(
+            ContainerRuntime::Docker.qualify_image("mmcauliffe/montreal-forced-aligner"),
+            "mmcauliffe/montreal-forced-aligner"
+        );
+    }
+
+    #[test]
+    fn test_split_bind_spec() {
+        assert_eq!(
+            split_bind_spec("/srv/jobs:/data/jobs:ro"),
+            ("/srv/jobs", "/data/jobs")
+        );
+        assert_eq!(
+            split_bind_spec(r"C:\Users\ana\jobs:/data/jobs"),
+            (r"C:\Users\ana\jobs", "/data/jobs")
+        );
+        assert_eq!(split_bind_spec("C:/jobs"), ("C:/jobs", ""));
+        assert!(is_absolute_host_path(r"D:\mfa"));
+        assert!(!is_absolute_container_path(r"C:\data"));
+    }
+
+    #[test]
+    fn test_mount_paths() {
+        assert_eq!(
+            mount_source(Path::new(r"\\?\C:\Users\ana\AppData\Local\Temp")),
+            "C:/Users/ana/AppData/Local/Temp"
+        );
+        assert_eq!(mount_source(Path::new("/srv/jobs")), "/srv/jobs");
+        assert_eq!(
+            container_path(Path::new(r"/data/jobs\job-1234")),
+            "/data/jobs/job-1234"
+        );
+    }
+
+    #[test]
+    fn test_same_host_path_through_docker_desktop() {
+        assert!(same_host_path(
+            Path::new(r"C:\Users\Ana\jobs\"),
+            Path::new("/run/desktop/mnt/host/c/Users/ana/jobs")
+        ));
+        assert!(same_host_path(
+            Path::new("/Users/ana/jobs"),
+            Path::new("/host_mnt/Users/ana/jobs")
+        ));
+        assert!(same_host_path(
+            Path::new("/srv/jobs"),
+            Path::new("/srv/jobs")
+        ));
+        assert!(!same_host_path(
+            Path::new("/srv/jobs"),
+            Path::new("/srv/other")
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+//! Stable API for embedding pronunciation assessment in other applications
+//!
+//! Everything re-exported here follows semver. Other modules of this crate
+//! may change between minor versions.
+//!
+//! ```no_run
+//! use ipa_navigator_mfa::prelude::*;
+//!
+//! let engine = AssessmentEngine::from_env()?;
+//! let wav = std::fs::read("attempt.wav")?;
+//! let assessment = engine.assess(&wav, "think about it", Dialect::AmericanEnglish, Strictness::Beginner)?;
+//! for tip in engine.feedback(&assessment) {
+//!     println!("{}", tip);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::sync::Arc;
+
+use anyhow::Result;
+
+pub use crate::aligner::Aligner;
+pub use crate::clarity::{ClarityIssue, WordClarity};
+pub use crate::docker::MfaDialect as Dialect;
+pub use crate::g2p::CharSpan;
+pub use crate::intonation::{Contour, IntonationAnalysis, SentenceType, WordPitch};
+pub use crate::mfa_parser::MfaSegment;
+pub use crate::scoring::{
+    PhonemeAccuracy, PronunciationAssessment, ScoringRubric, Strictness, phoneme_similarity,
+};
+
+use crate::aligner::get_aligner;
+use crate::feedback::generate_feedback_with_clarity;
+use crate::localization::Locale;
+use crate::scoring::rubric;
+
+/// Aligns recordings against their transcript and scores each phoneme
+///
+/// Cheap to clone; clones share the aligner.
+#[derive(Clone)]
+pub struct AssessmentEngine {
+    aligner: Arc<dyn Aligner>,
+}
+
+impl AssessmentEngine {
+    /// Use the alignment backend selected by `ALIGNER_BACKEND` (MFA by default)
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            aligner: get_aligner()?,
+        })
+    }
+
+    /// Use a custom alignment backend
+    pub fn with_aligner(aligner: Arc<dyn Aligner>) -> Self {
+        Self { aligner }
+    }
+
+    /// Name of the alignment backend in use
+    pub fn backend(&self) -> &'static str {
+        self.aligner.name()
+    }
+
+    /// Score a WAV recording of `transcript` at a preset strictness
+    pub fn assess(
+        &self,
+        audio_wav: &[u8],
+        transcript: &str,
+        dialect: Dialect,
+        strictness: Strictness,
+    ) -> Result<PronunciationAssessment> {
+        self.assess_with_rubric(audio_wav, transcript, dialect, &rubric(strictness))
+    }
+
+    /// Score a WAV recording of `transcript` with custom thresholds and penalties
+    pub fn assess_with_rubric(
+        &self,
+        audio_wav: &[u8],
+        transcript: &str,
+        dialect: Dialect,
+        rubric: &ScoringRubric,
+    ) -> Result<PronunciationAssessment> {
+        self.aligner.assess(audio_wav, transcript, dialect, rubric)
+    }
+
+    /// Tips for words too quiet to assess, then for the weakest phonemes of an assessment, worst first
+    pub fn feedback(&self, assessment: &PronunciationAssessment) -> Vec<String> {
+        generate_feedback_with_clarity(
+            &assessment.phoneme_details,
+            &assessment.clarity,
+            Locale::English,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Aligner returning the phones it was constructed with
+    struct FixedAligner(Vec<String>);
+
+    impl Aligner for FixedAligner {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn align(&self, _: &[u8], _: &str, _: Dialect) -> Result<Vec<MfaSegment>> {
+            Ok(self
+                .0
+                .iter()
+                .enumerate()
+                .map(|(i, phone)| MfaSegment {
+                    begin: i as f64 * 0.1,
+                    end: (i + 1) as f64 * 0.1,
+                    label: phone.clone(),
+                    segment_type: "phone".to_string(),
+                    confidence: None,
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_engine_with_custom_aligner() -> Result<()> {
+        let dictionary = crate::scoring::cached_dictionary(Dialect::AmericanEnglish)?;
+        let phones = dictionary["think"].clone();
+
+        let engine = AssessmentEngine::with_aligner(Arc::new(FixedAligner(phones)));
+        assert_eq!(engine.backend(), "fixed");
+
+        let assessment =
+            engine.assess(b"", "think", Dialect::AmericanEnglish, Strictness::Strict)?;
+        assert!((assessment.overall_score - 1.0).abs() < 1e-9);
+        assert!(engine.feedback(&assessment).is_empty());
+        Ok(())
+    }
+}
@@ -1,14 +1,71 @@
 //! Functions for scoring phoneme accuracy by comparing MFA results with expected pronunciations
 
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::ops::{Deref, Range};
 use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex};
 
+use crate::audio::read_wav_mono;
+use crate::clarity::{WordClarity, measure_clarity};
+use crate::confidence::{
+    LIKELIHOOD_SCALE, alignment_scores, apply_confidence, weighted_overall_score,
+};
 use crate::docker::MfaDialect;
-use crate::mfa_parser::{MfaSegment, parse_textgrid};
-use crate::phoneme::{IPA_PHONEME_FEATURES, PhonemeFeatures, calculate_feature_similarity};
+use crate::g2p::{CharSpan, align_transcript};
+use crate::intonation::{IntonationAnalysis, analyze_pitch, pitch_track};
+use crate::mfa_parser::{MfaSegment, parse_alignment};
+use crate::phoneme::SimilarityWeights;
+use crate::timeline::{AlignmentTiers, alignment_tiers};
+
+pub use ipa_navigator_core::scoring::{
+    AllophoneRule, PhonemeMatch, Rhoticity, ScoringRubric, SimilarityMatrix, Strictness,
+    match_phonemes, overall_score, phoneme_similarity,
+};
+
+/// Similarity of every pair of phonemes in the feature table, computed once with the configured weights
+pub static SIMILARITY_MATRIX: LazyLock<SimilarityMatrix> =
+    LazyLock::new(|| SimilarityMatrix::with_weights(similarity_weights_from_env()));
+
+/// Weights selected by `SIMILARITY_WEIGHTS`: a preset name, or the path of a JSON file of weights
+///
+/// Falls back to the articulatory preset, with a warning if the setting is
+/// neither a preset nor a readable weights file.
+pub fn similarity_weights_from_env() -> SimilarityWeights {
+    match env::var("SIMILARITY_WEIGHTS") {
+        Ok(setting) => load_similarity_weights(&setting).unwrap_or_else(|e| {
+            tracing::warn!("{:#}, using articulatory similarity weights", e);
+            SimilarityWeights::default()
+        }),
+        Err(_) => SimilarityWeights::default(),
+    }
+}
+
+/// Weights of the named preset, or read from the JSON file at `setting`
+///
+/// Weights missing from the file keep their articulatory values.
+pub fn load_similarity_weights(setting: &str) -> Result<SimilarityWeights> {
+    if let Some(weights) = SimilarityWeights::preset(setting) {
+        return Ok(weights);
+    }
+
+    let json = std::fs::read_to_string(setting).with_context(|| {
+        format!(
+            "'{}' is neither a similarity weights preset nor a readable file",
+            setting
+        )
+    })?;
+    serde_json::from_str(&json)
+        .with_context(|| format!("Invalid similarity weights in {}", setting))
+}
+
+/// The rubric for a strictness, looking similarities up in [`SIMILARITY_MATRIX`]
+pub fn rubric(strictness: Strictness) -> ScoringRubric {
+    strictness.rubric().with_similarities(&SIMILARITY_MATRIX)
+}
 
 /// Dictionary entry mapping a word to its phonemes
 #[derive(Debug, Clone)]
@@ -25,6 +82,10 @@ pub struct PhonemeAccuracy {
     pub score: f64,
     pub start_time: f64,
     pub end_time: f64,
+    /// Letters of the transcript spelling the expected phoneme, if any
+    pub char_span: Option<CharSpan>,
+    /// How sure the aligner is of the phoneme's segment, from 0.0 to 1.0, if it reported a score
+    pub confidence: Option<f64>,
 }
 
 /// Overall pronunciation assessment result
@@ -33,127 +94,415 @@ pub struct PronunciationAssessment {
     pub overall_score: f64,
     pub phoneme_details: Vec<PhonemeAccuracy>,
     pub transcript: String, // The original text being spoken
+    /// Sentence-final pitch movement against the expected pattern, when measured
+    pub intonation: Option<IntonationAnalysis>,
+    /// Loudness and clarity of each aligned word, empty when not measured
+    pub clarity: Vec<WordClarity>,
+    /// Expected pronunciation and aligned phones on one time axis
+    pub tiers: AlignmentTiers,
+}
+
+impl PronunciationAssessment {
+    /// Add the intonation and word clarity of the recording `segments` were aligned from
+    ///
+    /// Both are best-effort: if the recording cannot be decoded, the
+    /// assessment is returned without them.
+    pub fn with_audio_analysis(mut self, audio_wav: &[u8], segments: &[MfaSegment]) -> Self {
+        match read_wav_mono(audio_wav) {
+            Ok((samples, sample_rate)) => {
+                self.intonation = analyze_pitch(
+                    &pitch_track(&samples, sample_rate),
+                    segments,
+                    &self.transcript,
+                );
+                self.clarity = measure_clarity(&samples, sample_rate, segments);
+            }
+            Err(e) => tracing::warn!("Audio analysis failed: {:#}", e),
+        }
+        self
+    }
 }
 
-/// Score the pronunciation accuracy based on phonemes in a TextGrid file
+/// Score the pronunciation accuracy based on phonemes in an MFA alignment file,
+/// a TextGrid or JSON
 pub fn score_phoneme_accuracy(
-    textgrid_path: impl AsRef<Path>,
+    alignment_path: impl AsRef<Path>,
     dialect: MfaDialect,
 ) -> Result<PronunciationAssessment> {
-    // Load the dictionary
-    let dictionary = load_dictionary(dialect)?;
+    // Parse the alignment, with the score MFA gave it
+    let mut segments = parse_alignment(alignment_path.as_ref())?;
+    if let (Some(dir), Some(stem)) = (
+        alignment_path.as_ref().parent(),
+        alignment_path
+            .as_ref()
+            .file_stem()
+            .and_then(|stem| stem.to_str()),
+    ) && let Some(&log_likelihood) = alignment_scores(dir).get(stem)
+    {
+        apply_confidence(&mut segments, log_likelihood, &LIKELIHOOD_SCALE);
+    }
 
-    // Parse the TextGrid file
-    let segments = parse_textgrid(textgrid_path.as_ref())?;
+    // Get expected phonemes from dictionary based on transcript words
+    let transcript_path = alignment_path.as_ref().with_extension("lab");
+    let transcript = std::fs::read_to_string(&transcript_path)
+        .with_context(|| format!("Failed to read transcript file: {:?}", transcript_path))?;
 
-    // Extract actual phonemes from MFA output
+    score_segments(
+        &segments,
+        &transcript,
+        dialect,
+        &rubric(Strictness::default()),
+    )
+}
+
+/// Score aligned word and phone segments against the transcript's dictionary pronunciation
+#[tracing::instrument(name = "mfa.score", skip_all, fields(segments = segments.len()))]
+pub fn score_segments(
+    segments: &[MfaSegment],
+    transcript: &str,
+    dialect: MfaDialect,
+    rubric: &ScoringRubric,
+) -> Result<PronunciationAssessment> {
+    // Load the dictionary
+    let dictionary = cached_dictionary(dialect)?;
+
+    // Extract actual phonemes from the alignment
     let actual_phonemes: Vec<&MfaSegment> = segments
         .iter()
         .filter(|s| s.segment_type == "phone" && !s.label.is_empty())
         .collect();
 
-    // Get expected phonemes from dictionary based on transcript words
-    let transcript_path = textgrid_path.as_ref().with_extension("lab");
-    let transcript = std::fs::read_to_string(&transcript_path)
-        .with_context(|| format!("Failed to read transcript file: {:?}", transcript_path))?;
+    let actual_labels: Vec<String> = actual_phonemes.iter().map(|s| s.label.clone()).collect();
+
+    // Score against the dictionary variants closest to what was said
+    let pronunciations = best_pronunciations(
+        &dictionary,
+        transcript,
+        &actual_labels,
+        dialect.rhoticity(),
+        rubric,
+    );
+    let expected_words = expected_word_phonemes(&pronunciations, transcript);
+    let expected_phonemes: Vec<String> = expected_words
+        .iter()
+        .flat_map(|(_, phonemes)| phonemes.iter().cloned())
+        .collect();
+    let letter_spans: Vec<CharSpan> = align_transcript(&pronunciations, transcript)
+        .into_iter()
+        .map(|aligned| aligned.span)
+        .collect();
+
+    let phoneme_details: Vec<PhonemeAccuracy> = match_phonemes(
+        &expected_phonemes,
+        &actual_labels,
+        dialect.rhoticity(),
+        rubric,
+    )
+    .into_iter()
+    .map(|matched| {
+        let actual = matched.actual.map(|j| actual_phonemes[j]);
+        PhonemeAccuracy {
+            expected: matched
+                .expected
+                .map_or_else(String::new, |i| expected_phonemes[i].clone()),
+            actual: actual.map_or_else(String::new, |s| s.label.clone()),
+            score: matched.score,
+            start_time: actual.map_or(0.0, |s| s.begin),
+            end_time: actual.map_or(0.0, |s| s.end),
+            char_span: matched.expected.and_then(|i| letter_spans.get(i).copied()),
+            confidence: actual.and_then(|s| s.confidence),
+        }
+    })
+    .collect();
+
+    // Phonemes the aligner was unsure of count for less
+    let overall_score = weighted_overall_score(&phoneme_details);
+
+    Ok(PronunciationAssessment {
+        overall_score,
+        phoneme_details,
+        transcript: transcript.to_string(),
+        intonation: None,
+        clarity: Vec::new(),
+        tiers: alignment_tiers(&expected_words, segments),
+    })
+}
 
-    let mut expected_phonemes = Vec::new();
+/// Look up the dictionary pronunciation of every transcript word, in order
+///
+/// Words missing from the dictionary are skipped.
+pub fn expected_phonemes(
+    dictionary: &HashMap<String, Vec<String>>,
+    transcript: &str,
+) -> Vec<String> {
+    expected_word_phonemes(dictionary, transcript)
+        .into_iter()
+        .flat_map(|(_, phonemes)| phonemes)
+        .collect()
+}
+
+/// Look up the dictionary pronunciation of every transcript word, keeping the word
+pub fn expected_word_phonemes(
+    dictionary: &HashMap<String, Vec<String>>,
+    transcript: &str,
+) -> Vec<(String, Vec<String>)> {
+    let mut words = Vec::new();
     for word in transcript.split_whitespace() {
-        let word_lower = word.to_lowercase();
-        // Remove any non-alphabetic characters
-        let word_clean: String = word_lower.chars().filter(|c| c.is_alphabetic()).collect();
+        let word_clean = dictionary_word(word);
 
         if let Some(phonemes) = dictionary.get(&word_clean) {
-            expected_phonemes.extend(phonemes.clone());
+            words.push((word_clean, phonemes.clone()));
         }
     }
+    words
+}
+
+/// Pronunciations of the transcript's words, taking the dictionary variants closest to `actual`
+///
+/// The transcript is aligned once with the usual pronunciations, and each
+/// word's variants are scored only against the spoken phonemes aligned to
+/// that word. Ties keep the dictionary's usual pronunciation, so another
+/// variant is only chosen when it fits the recording better. Words that were
+/// not heard keep their usual pronunciation.
+pub fn best_pronunciations(
+    dictionary: &Dictionary,
+    transcript: &str,
+    actual: &[String],
+    rhoticity: Rhoticity,
+    rubric: &ScoringRubric,
+) -> HashMap<String, Vec<String>> {
+    let words = expected_word_phonemes(dictionary, transcript);
+    let mut chosen: HashMap<String, Vec<String>> = words.iter().cloned().collect();
+
+    let mut spans = word_spans(&chosen, transcript, actual, rhoticity, rubric);
+    let mut settled = HashSet::new();
+    for (index, (word, _)) in words.iter().enumerate() {
+        if dictionary.variants(word).len() < 2 || settled.contains(word.as_str()) {
+            continue;
+        }
+        let Some(span) = spans[index].clone() else {
+            continue;
+        };
+        settled.insert(word.as_str());
+
+        let heard = &actual[span];
+        let score = |phonemes: &[String]| {
+            overall_score(
+                match_phonemes(phonemes, heard, rhoticity, rubric)
+                    .into_iter()
+                    .map(|matched| matched.score),
+            )
+        };
+
+        let usual = &dictionary[word];
+        let mut best = (score(usual), usual);
+        for variant in dictionary.variants(word) {
+            if variant == usual {
+                continue;
+            }
+            let variant_score = score(variant);
+            if variant_score > best.0 {
+                best = (variant_score, variant);
+            }
+        }
 
-    // Compare expected vs. actual phonemes
-    let mut phoneme_details = Vec::new();
-    let min_len = expected_phonemes.len().min(actual_phonemes.len());
-
-    // Match phonemes one-by-one as best we can
-    for i in 0..min_len {
-        let expected_ipa = &expected_phonemes[i];
-        let actual_segment = actual_phonemes[i];
-        let actual_ipa = actual_segment.label.clone();
-
-        // Calculate similarity between IPA phonemes
-        let similarity = phoneme_similarity(expected_ipa, &actual_ipa);
-
-        phoneme_details.push(PhonemeAccuracy {
-            expected: expected_ipa.clone(),
-            actual: actual_ipa,
-            score: similarity,
-            start_time: actual_segment.begin,
-            end_time: actual_segment.end,
-        });
+        // A longer or shorter variant moves the words after it
+        let realign = best.1.len() != usual.len();
+        chosen.insert(word.clone(), best.1.clone());
+        if realign {
+            spans = word_spans(&chosen, transcript, actual, rhoticity, rubric);
+        }
     }
 
-    // Handle remaining expected phonemes (missing in actual)
-    for i in min_len..expected_phonemes.len() {
-        phoneme_details.push(PhonemeAccuracy {
-            expected: expected_phonemes[i].clone(),
-            actual: String::new(),
-            score: 0.0, // Missing phoneme = 0 score
-            start_time: 0.0,
-            end_time: 0.0,
-        });
+    chosen
+}
+
+/// The spoken phonemes aligned to each transcript word, `None` for words not heard
+///
+/// A word runs from its first aligned phoneme until the next heard word
+/// starts, so phonemes inserted between words go to the word before them.
+fn word_spans(
+    pronunciations: &HashMap<String, Vec<String>>,
+    transcript: &str,
+    actual: &[String],
+    rhoticity: Rhoticity,
+    rubric: &ScoringRubric,
+) -> Vec<Option<Range<usize>>> {
+    let words = expected_word_phonemes(pronunciations, transcript);
+    let expected: Vec<String> = words
+        .iter()
+        .flat_map(|(_, phonemes)| phonemes.iter().cloned())
+        .collect();
+
+    let mut aligned = vec![None; expected.len()];
+    for matched in match_phonemes(&expected, actual, rhoticity, rubric) {
+        if let (Some(i), Some(j)) = (matched.expected, matched.actual) {
+            aligned[i] = Some(j);
+        }
     }
 
-    // Handle additional actual phonemes (not expected)
-    for i in min_len..actual_phonemes.len() {
-        let actual_segment = actual_phonemes[i];
-        let actual_ipa = actual_segment.label.clone();
-
-        phoneme_details.push(PhonemeAccuracy {
-            expected: String::new(),
-            actual: actual_ipa,
-            score: 0.0, // Extra phoneme = 0 score
-            start_time: actual_segment.begin,
-            end_time: actual_segment.end,
-        });
+    let mut offset = 0;
+    let starts: Vec<Option<usize>> = words
+        .iter()
+        .map(|(_, phonemes)| {
+            let range = offset..offset + phonemes.len();
+            offset = range.end;
+            aligned[range].iter().find_map(|&j| j)
+        })
+        .collect();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, start)| {
+            let end = starts[index + 1..]
+                .iter()
+                .find_map(|&next| next)
+                .unwrap_or(actual.len());
+            start.map(|start| start..end)
+        })
+        .collect()
+}
+
+/// A transcript word as spelled in the dictionary: lowercase, letters only
+pub fn dictionary_word(word: &str) -> String {
+    word.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect()
+}
+
+/// Pronunciation dictionary mapping lowercase words to their phonemes
+///
+/// Dereferences to each word's usual pronunciation, the one listed last.
+/// Words listed more than once, as "either" and "tomato", keep every
+/// pronunciation in [`variants`](Self::variants).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dictionary {
+    primary: HashMap<String, Vec<String>>,
+    variants: HashMap<String, Vec<Vec<String>>>,
+}
+
+impl Dictionary {
+    /// Add a pronunciation, after any the word already has, making it the usual one
+    pub fn insert(&mut self, word: String, phonemes: Vec<String>) {
+        if let Some(usual) = self.primary.insert(word.clone(), phonemes.clone())
+            && usual != phonemes
+        {
+            let variants = self.variants.entry(word).or_insert_with(|| vec![usual]);
+            if !variants.contains(&phonemes) {
+                variants.push(phonemes);
+            }
+        }
     }
 
-    // Calculate overall score
-    let overall_score = if phoneme_details.is_empty() {
-        0.0
-    } else {
-        phoneme_details.iter().map(|p| p.score).sum::<f64>() / phoneme_details.len() as f64
-    };
+    /// Every pronunciation of a word, in the order listed, or none if it is not in the dictionary
+    pub fn variants(&self, word: &str) -> &[Vec<String>] {
+        match self.variants.get(word) {
+            Some(variants) => variants,
+            None => self
+                .primary
+                .get_key_value(word)
+                .map_or(&[], |(_, phonemes)| std::slice::from_ref(phonemes)),
+        }
+    }
+}
 
-    Ok(PronunciationAssessment {
-        overall_score,
-        phoneme_details,
-        transcript: transcript.to_string(),
-    })
+impl Deref for Dictionary {
+    type Target = HashMap<String, Vec<String>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.primary
+    }
+}
+
+impl FromIterator<(String, Vec<String>)> for Dictionary {
+    fn from_iter<I: IntoIterator<Item = (String, Vec<String>)>>(entries: I) -> Self {
+        let mut dictionary = Dictionary::default();
+        for (word, phonemes) in entries {
+            dictionary.insert(word, phonemes);
+        }
+        dictionary
+    }
 }
 
-/// Calculate phoneme similarity based on phonetic features
-pub fn phoneme_similarity(a: &str, b: &str) -> f64 {
-    // If strings are identical, return perfect score
-    if a == b {
-        return 1.0;
+#[cfg(test)]
+impl Dictionary {
+    /// Dictionary of words and their space-separated phonemes, for tests
+    pub fn from_pairs(entries: &[(&str, &str)]) -> Self {
+        entries
+            .iter()
+            .map(|(word, phonemes)| {
+                (
+                    word.to_string(),
+                    phonemes.split_whitespace().map(str::to_string).collect(),
+                )
+            })
+            .collect()
     }
+}
+
+// Dictionaries are large, so each is read once and shared between requests
+static DICTIONARIES: LazyLock<Mutex<HashMap<&'static str, Arc<Dictionary>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
-    // Get features for each phoneme
-    let default_features = PhonemeFeatures::default();
-    let a_features = IPA_PHONEME_FEATURES.get(a).unwrap_or(&default_features);
-    let b_features = IPA_PHONEME_FEATURES.get(b).unwrap_or(&default_features);
+/// Get the pronunciation dictionary for the given dialect, loading it on first use
+pub fn cached_dictionary(dialect: MfaDialect) -> Result<Arc<Dictionary>> {
+    let mut dictionaries = DICTIONARIES
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire dictionary cache lock"))?;
 
-    // Calculate similarity based on shared features
-    calculate_feature_similarity(a_features, b_features)
+    if let Some(dictionary) = dictionaries.get(dialect.dictionary_name()) {
+        return Ok(dictionary.clone());
+    }
+
+    let dictionary = Arc::new(load_dictionary(dialect)?);
+    dictionaries.insert(dialect.dictionary_name(), dictionary.clone());
+    Ok(dictionary)
+}
+
+/// Re-read every cached dictionary from disk, returning the names of those reloaded
+///
+/// All dictionaries are read before any is replaced, so a broken file leaves
+/// the cache untouched. Dictionaries not yet used are loaded on first use as usual.
+pub fn reload_dictionaries() -> Result<Vec<&'static str>> {
+    let cached: Vec<MfaDialect> = {
+        let dictionaries = DICTIONARIES
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Failed to acquire dictionary cache lock"))?;
+        MfaDialect::ALL
+            .into_iter()
+            .filter(|dialect| dictionaries.contains_key(dialect.dictionary_name()))
+            .collect()
+    };
+
+    let reloaded = cached
+        .into_iter()
+        .map(|dialect| {
+            Ok((
+                dialect.dictionary_name(),
+                Arc::new(load_dictionary(dialect)?),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut dictionaries = DICTIONARIES
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Failed to acquire dictionary cache lock"))?;
+    let names = reloaded.iter().map(|(name, _)| *name).collect();
+    dictionaries.extend(reloaded);
+    Ok(names)
 }
 
 /// Load the pronunciation dictionary for the given dialect
-pub fn load_dictionary(dialect: MfaDialect) -> Result<HashMap<String, Vec<String>>> {
+pub fn load_dictionary(dialect: MfaDialect) -> Result<Dictionary> {
     let dict_path = dialect.dictionary_path();
 
     let file = File::open(dict_path)
         .with_context(|| format!("Failed to open dictionary file: {:?}", dict_path))?;
 
     let reader = BufReader::new(file);
-    let mut dictionary = HashMap::new();
+    let mut dictionary = Dictionary::default();
 
     for line in reader.lines() {
         let line = line?;
@@ -240,133 +589,169 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn test_phoneme_similarity_exact_match() {
-        // Test exact matches for different phonemes
-        assert_eq!(
-            phoneme_similarity("p", "p"),
-            1.0,
-            "Identical consonants should match exactly"
-        );
-        assert_eq!(
-            phoneme_similarity("a", "a"),
-            1.0,
-            "Identical vowels should match exactly"
-        );
-        assert_eq!(
-            phoneme_similarity("ŋ", "ŋ"),
-            1.0,
-            "Identical special symbols should match exactly"
-        );
+    fn phones(labels: &[&str]) -> Vec<MfaSegment> {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| MfaSegment {
+                begin: i as f64 * 0.1,
+                end: (i + 1) as f64 * 0.1,
+                label: label.to_string(),
+                segment_type: "phone".to_string(),
+                confidence: None,
+            })
+            .collect()
     }
 
     #[test]
-    fn test_phoneme_similarity_consonant_pairs() {
-        // Test pairs of consonants with different relationships
+    fn test_non_rhotic_speaker_against_us_dictionary() -> Result<()> {
+        // British "car" scored against the American dictionary's rhotic pronunciation
+        let assessment = score_segments(
+            &phones(&["kʰ", "ɑː"]),
+            "car",
+            MfaDialect::AmericanEnglish,
+            &ScoringRubric::default(),
+        )?;
+
+        assert_eq!(assessment.phoneme_details.len(), 2);
+        assert_eq!(assessment.overall_score, 1.0);
+        Ok(())
+    }
 
-        // Test voice pairs (should be somewhat similar)
-        let voiced_voiceless = phoneme_similarity("b", "p");
-        assert!(
-            voiced_voiceless > 0.5,
-            "Voice pairs should be similar: {}",
-            voiced_voiceless
-        );
-        assert!(
-            voiced_voiceless < 1.0,
-            "Voice pairs should not be identical: {}",
-            voiced_voiceless
-        );
+    #[test]
+    fn test_rhotic_speaker_against_uk_dictionary() -> Result<()> {
+        // American "far" scored against the British dictionary's non-rhotic pronunciation
+        let assessment = score_segments(
+            &phones(&["f", "ɑ", "ɹ"]),
+            "far",
+            MfaDialect::BritishEnglish,
+            &ScoringRubric::default(),
+        )?;
+
+        assert_eq!(assessment.phoneme_details.len(), 2);
+        assert_eq!(assessment.overall_score, 1.0);
+        Ok(())
+    }
 
-        // Test same place but different manner
-        let plosive_nasal = phoneme_similarity("b", "m");
-        assert!(
-            plosive_nasal > 0.2,
-            "Same place phonemes should have some similarity: {}",
-            plosive_nasal
-        );
+    #[test]
+    fn test_reload_dictionaries_replaces_cached() -> Result<()> {
+        let before = cached_dictionary(MfaDialect::AmericanEnglish)?;
 
-        // Test different place but same manner
-        let different_place = phoneme_similarity("p", "t");
-        assert!(
-            different_place > 0.2,
-            "Same manner phonemes should have some similarity: {}",
-            different_place
-        );
+        let reloaded = reload_dictionaries()?;
+        assert!(reloaded.contains(&MfaDialect::AmericanEnglish.dictionary_name()));
 
-        // Test completely different consonants
-        let completely_different = phoneme_similarity("p", "z");
-        assert!(
-            completely_different < voiced_voiceless,
-            "Very different consonants should be less similar than voice pairs: {}",
-            completely_different
-        );
+        let after = cached_dictionary(MfaDialect::AmericanEnglish)?;
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert_eq!(before.len(), after.len());
+        Ok(())
     }
 
     #[test]
-    fn test_phoneme_similarity_vowel_pairs() {
-        // Test pairs of vowels with different relationships
-
-        // Test height neighbors
-        let height_neighbors = phoneme_similarity("i", "e");
-        assert!(
-            height_neighbors > 0.4,
-            "Vowel height neighbors should be similar: {}",
-            height_neighbors
+    fn test_load_similarity_weights() -> Result<()> {
+        assert_eq!(
+            load_similarity_weights("perceptual")?,
+            SimilarityWeights::PERCEPTUAL
         );
 
-        // Test backness neighbors
-        let backness_neighbors = phoneme_similarity("e", "ə");
-        assert!(
-            backness_neighbors > 0.4,
-            "Vowel backness neighbors should be similar: {}",
-            backness_neighbors
-        );
+        let path = std::env::temp_dir().join(format!("weights-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"place": 0.1, "voicing": 0.4}"#)?;
+        let weights = load_similarity_weights(path.to_str().unwrap())?;
+        std::fs::remove_file(&path)?;
+        assert_eq!((weights.place, weights.voicing), (0.1, 0.4));
+        assert_eq!(weights.manner, SimilarityWeights::default().manner);
 
-        // Test rounding pairs
-        let rounded_unrounded = phoneme_similarity("u", "i");
-        assert!(
-            rounded_unrounded > 0.2,
-            "Vowels differing mainly in rounding should have some similarity: {}",
-            rounded_unrounded
-        );
+        assert!(load_similarity_weights("no-such-preset").is_err());
+        Ok(())
+    }
 
-        // Test very different vowels
-        let very_different_vowels = phoneme_similarity("i", "ɑ");
-        assert!(
-            very_different_vowels < height_neighbors,
-            "Very different vowels should be less similar than height neighbors: {}",
-            very_different_vowels
-        );
+    fn phonemes(ipa: &str) -> Vec<String> {
+        ipa.split_whitespace().map(str::to_string).collect()
     }
 
     #[test]
-    fn test_phoneme_similarity_vowel_consonant_pairs() {
-        // Vowels and consonants should have minimal similarity
-        let vowel_consonant = phoneme_similarity("a", "p");
-        assert!(
-            vowel_consonant < 0.3,
-            "Vowels and consonants should have low similarity: {}",
-            vowel_consonant
+    fn test_dictionary_keeps_variants() {
+        let dictionary: Dictionary = [
+            ("either", "iː ð ɚ"),
+            ("either", "aj ð ɚ"),
+            ("either", "iː ð ɚ"),
+            ("cat", "kʰ æ t"),
+        ]
+        .into_iter()
+        .map(|(word, ipa)| (word.to_string(), phonemes(ipa)))
+        .collect();
+
+        assert_eq!(dictionary["either"], phonemes("iː ð ɚ"));
+        assert_eq!(
+            dictionary.variants("either"),
+            [phonemes("iː ð ɚ"), phonemes("aj ð ɚ")]
         );
+        assert_eq!(dictionary.variants("cat"), [phonemes("kʰ æ t")]);
+        assert!(dictionary.variants("dog").is_empty());
     }
 
     #[test]
-    fn test_phoneme_similarity_unknown_phonemes() {
-        // Test with unknown phonemes
-        assert!(
-            phoneme_similarity("unknown", "p") < 0.5,
-            "Unknown phoneme should have low similarity to any real phoneme"
-        );
-        assert_eq!(
-            phoneme_similarity("unknown", "unknown"),
-            1.0,
-            "Unknown phoneme should match itself"
+    fn test_best_pronunciations_follow_the_recording() {
+        let dictionary: Dictionary = [
+            ("i", "aj"),
+            ("like", "l aj k"),
+            ("either", "aj ð ɚ"),
+            ("either", "iː ð ɚ"),
+        ]
+        .into_iter()
+        .map(|(word, ipa)| (word.to_string(), phonemes(ipa)))
+        .collect();
+        let rubric = ScoringRubric::default();
+        let best = |actual: &str| {
+            best_pronunciations(
+                &dictionary,
+                "I like either",
+                &phonemes(actual),
+                Rhoticity::Rhotic,
+                &rubric,
+            )
+            .remove("either")
+            .unwrap()
+        };
+
+        assert_eq!(best("aj l aj k aj ð ɚ"), phonemes("aj ð ɚ"));
+        assert_eq!(best("aj l aj k iː ð ɚ"), phonemes("iː ð ɚ"));
+        // Neither fits, so the usual pronunciation is kept
+        assert_eq!(best("aj l aj k"), phonemes("iː ð ɚ"));
+    }
+
+    #[test]
+    fn test_best_pronunciations_score_each_word_on_its_own_phonemes() {
+        let dictionary: Dictionary = [
+            ("either", "aj ð ɚ"),
+            ("either", "iː ð ɚ"),
+            ("tomato", "t ə m ɑː t ow"),
+            ("tomato", "t ə m ej t ow"),
+            ("or", "ɔ ɹ"),
+        ]
+        .into_iter()
+        .map(|(word, ipa)| (word.to_string(), phonemes(ipa)))
+        .collect();
+        let actual = phonemes("aj ð ɚ t ə m ɑː t ow ɔ ɹ");
+
+        let mut best = best_pronunciations(
+            &dictionary,
+            "either tomato or",
+            &actual,
+            Rhoticity::Rhotic,
+            &ScoringRubric::default(),
         );
 
-        // Test with phoneme not in the features map
-        assert!(
-            phoneme_similarity("xyz", "p") < 0.5,
-            "Non-existent phoneme should have low similarity to any real phoneme"
+        assert_eq!(best.remove("either").unwrap(), phonemes("aj ð ɚ"));
+        assert_eq!(best.remove("tomato").unwrap(), phonemes("t ə m ɑː t ow"));
+        assert_eq!(
+            word_spans(
+                &dictionary,
+                "either tomato or",
+                &actual,
+                Rhoticity::Rhotic,
+                &ScoringRubric::default(),
+            ),
+            [Some(0..3), Some(3..9), Some(9..11)]
         );
     }
 }
@@ -0,0 +1,451 @@
+//! Disk quota and clean-up for MFA job directories
+//!
+//! Every corpus written for MFA lives in a job directory under the jobs
+//! directory mounted into the container. Directories are removed when their
+//! job finishes, but a crash or a killed container can leave them behind, so
+//! directories older than a maximum age are swept before new ones are made,
+//! and no new job starts while the jobs directory is over its quota.
+//!
+//! Each tenant's jobs are kept under their own root, so one tenant's
+//! recordings never share a corpus with another's. The tenant is taken from
+//! the calling thread, set with [`with_tenant`] around the alignment work.
+//! The signed-in user, set with [`with_user`], is recorded in an
+//! [`OWNER_FILE`] inside each directory, so retention can count jobs per user.
+
+use anyhow::{Context, Result};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+use crate::container::CONTAINER_MANAGER;
+
+/// Largest total size of the jobs directory when `MFA_JOBS_QUOTA_MB` is not set
+const DEFAULT_QUOTA_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Age after which job directories are removed when `MFA_JOBS_MAX_AGE_SECS` is not set
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Directory under the jobs directory holding one root per tenant
+const TENANTS_DIR: &str = "tenants";
+
+/// Root of the jobs of requests made without a tenant
+const SHARED_DIR: &str = "shared";
+
+/// File inside a job directory naming the user who made the request
+pub const OWNER_FILE: &str = ".owner";
+
+/// Shared storage manager for the configured jobs directory
+pub static STORAGE: LazyLock<StorageManager> = LazyLock::new(|| {
+    StorageManager::new(
+        CONTAINER_MANAGER.config().jobs_dir.clone(),
+        StorageConfig::from_env(),
+    )
+});
+
+thread_local! {
+    static TENANT: RefCell<Option<String>> = const { RefCell::new(None) };
+    static USER: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with job directories it creates on this thread kept under `tenant`'s root
+pub fn with_tenant<T>(tenant: Option<&str>, f: impl FnOnce() -> T) -> T {
+    let previous = TENANT.with(|current| current.replace(tenant.map(str::to_string)));
+    let result = f();
+    TENANT.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// Tenant job directories created on this thread belong to
+pub fn current_tenant() -> Option<String> {
+    TENANT.with(|current| current.borrow().clone())
+}
+
+/// Run `f` with job directories it creates on this thread recorded as `user`'s
+pub fn with_user<T>(user: Option<&str>, f: impl FnOnce() -> T) -> T {
+    let previous = USER.with(|current| current.replace(user.map(str::to_string)));
+    let result = f();
+    USER.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// User job directories created on this thread are recorded as belonging to
+pub fn current_user() -> Option<String> {
+    USER.with(|current| current.borrow().clone())
+}
+
+/// Run `f` with job directories it creates on this thread kept under `tenant`'s root and recorded as `user`'s
+pub fn with_owner<T>(tenant: Option<&str>, user: Option<&str>, f: impl FnOnce() -> T) -> T {
+    with_tenant(tenant, || with_user(user, f))
+}
+
+/// Settings for the jobs directory
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    /// Size the jobs directory may reach before new jobs are refused
+    pub quota_bytes: u64,
+    /// Age after which a job directory is assumed abandoned and removed
+    pub max_age: Duration,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            quota_bytes: DEFAULT_QUOTA_BYTES,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Read `MFA_JOBS_QUOTA_MB` and `MFA_JOBS_MAX_AGE_SECS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let quota_bytes = env::var("MFA_JOBS_QUOTA_MB")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|&mb| mb > 0)
+            .map(|mb| mb * 1024 * 1024)
+            .unwrap_or(defaults.quota_bytes);
+
+        let max_age = env::var("MFA_JOBS_MAX_AGE_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.max_age);
+
+        Self {
+            quota_bytes,
+            max_age,
+        }
+    }
+}
+
+/// The jobs directory is full, even after abandoned jobs were removed
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("MFA job storage is full ({used_bytes} of {quota_bytes} bytes used); try again shortly")]
+pub struct QuotaExceeded {
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+}
+
+/// Space taken by one tenant's jobs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TenantUsage {
+    pub used_bytes: u64,
+    pub job_dirs: usize,
+}
+
+/// Space taken by the jobs directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+    pub job_dirs: usize,
+    /// Usage by tenant root: a tenant id, or "shared" for requests without one
+    pub tenants: BTreeMap<String, TenantUsage>,
+}
+
+/// A job directory found on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredJobDir {
+    /// Tenant root the directory is under: a tenant id, or "shared"
+    pub tenant: String,
+    /// User who made the request, none if they were not signed in
+    pub user: Option<String>,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    pub bytes: u64,
+}
+
+/// Creates job directories within a quota, and removes abandoned ones
+pub struct StorageManager {
+    root: PathBuf,
+    config: StorageConfig,
+    /// Held while checking the quota and creating a directory, so concurrent jobs cannot overshoot it together
+    lock: Mutex<()>,
+}
+
+impl StorageManager {
+    pub fn new(root: PathBuf, config: StorageConfig) -> Self {
+        Self {
+            root,
+            config,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config(&self) -> &StorageConfig {
+        &self.config
+    }
+
+    /// Root of a tenant's job directories
+    ///
+    /// Characters other than ASCII letters, digits, `-` and `_` are replaced,
+    /// so a tenant id can never name a path outside the jobs directory.
+    pub fn tenant_root(&self, tenant: Option<&str>) -> PathBuf {
+        match tenant {
+            Some(tenant) => {
+                let name: String = tenant
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                self.root.join(TENANTS_DIR).join(name)
+            }
+            None => self.root.join(SHARED_DIR),
+        }
+    }
+
+    /// Create a job directory under the current tenant's root, removed when dropped
+    ///
+    /// Abandoned directories are swept first; fails with [`QuotaExceeded`] if
+    /// the jobs directory is still over its quota. The current user, if any,
+    /// is written to the directory's [`OWNER_FILE`].
+    pub fn create_job_dir(&self, prefix: &str) -> Result<TempDir> {
+        let tenant_root = self.tenant_root(current_tenant().as_deref());
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Err(e) = self.sweep() {
+            tracing::warn!("Failed to sweep MFA job directories: {:#}", e);
+        }
+        let used_bytes = dir_size(&self.root);
+        if used_bytes >= self.config.quota_bytes {
+            return Err(QuotaExceeded {
+                used_bytes,
+                quota_bytes: self.config.quota_bytes,
+            }
+            .into());
+        }
+
+        fs::create_dir_all(&tenant_root)
+            .with_context(|| format!("Failed to create MFA jobs directory {:?}", tenant_root))?;
+        let dir = tempfile::Builder::new()
+            .prefix(prefix)
+            .tempdir_in(&tenant_root)
+            .context("Failed to create MFA job directory")?;
+        if let Some(user) = current_user() {
+            fs::write(dir.path().join(OWNER_FILE), user)
+                .context("Failed to record the owner of the MFA job directory")?;
+        }
+        Ok(dir)
+    }
+
+    /// Remove job directories older than the maximum age, returning how many were removed
+    pub fn sweep(&self) -> Result<usize> {
+        let cutoff = SystemTime::now()
+            .checked_sub(self.config.max_age)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let mut removed = 0;
+        for (_, dir) in self.job_dirs()? {
+            let modified = fs::metadata(&dir).and_then(|metadata| metadata.modified());
+            if modified.is_ok_and(|modified| modified < cutoff) {
+                match fs::remove_dir_all(&dir) {
+                    Ok(()) => removed += 1,
+                    Err(e) => tracing::warn!("Failed to remove MFA job directory {:?}: {}", dir, e),
+                }
+            }
+        }
+
+        if removed > 0 {
+            tracing::info!("Removed {} abandoned MFA job directories", removed);
+        }
+        Ok(removed)
+    }
+
+    /// Space taken by the jobs directory, by tenant
+    pub fn usage(&self) -> Result<StorageUsage> {
+        let mut tenants: BTreeMap<String, TenantUsage> = BTreeMap::new();
+        let job_dirs = self.job_dirs()?;
+        for (tenant, dir) in &job_dirs {
+            let usage = tenants.entry(tenant.clone()).or_default();
+            usage.used_bytes += dir_size(dir);
+            usage.job_dirs += 1;
+        }
+
+        Ok(StorageUsage {
+            used_bytes: dir_size(&self.root),
+            quota_bytes: self.config.quota_bytes,
+            job_dirs: job_dirs.len(),
+            tenants,
+        })
+    }
+
+    /// Every job directory with its tenant, user, age, and size, for a retention policy to judge
+    ///
+    /// Directories whose modification time cannot be read are left out.
+    pub fn stored_job_dirs(&self) -> Result<Vec<StoredJobDir>> {
+        Ok(self
+            .job_dirs()?
+            .into_iter()
+            .filter_map(|(tenant, path)| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                let bytes = dir_size(&path);
+                let user = fs::read_to_string(path.join(OWNER_FILE))
+                    .ok()
+                    .filter(|user| !user.is_empty());
+                Some(StoredJobDir {
+                    tenant,
+                    user,
+                    path,
+                    modified,
+                    bytes,
+                })
+            })
+            .collect())
+    }
+
+    /// Every job directory, with the tenant root it is under
+    ///
+    /// Directories left directly in the jobs directory, from before jobs were
+    /// kept by tenant, count as shared.
+    fn job_dirs(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut dirs = Vec::new();
+        for dir in subdirectories(&self.root)? {
+            match dir.file_name().and_then(|name| name.to_str()) {
+                Some(TENANTS_DIR) => {
+                    for tenant_root in subdirectories(&dir)? {
+                        let tenant = tenant_root
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        for job in subdirectories(&tenant_root)? {
+                            dirs.push((tenant.clone(), job));
+                        }
+                    }
+                }
+                Some(SHARED_DIR) => {
+                    for job in subdirectories(&dir)? {
+                        dirs.push((SHARED_DIR.to_string(), job));
+                    }
+                }
+                _ => dirs.push((SHARED_DIR.to_string(), dir)),
+            }
+        }
+        Ok(dirs)
+    }
+}
+
+/// Directories directly inside `dir`, none if it does not exist
+fn subdirectories(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to list {:?}", dir)),
+    };
+
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .map(|entry| entry.path())
+        .collect())
+}
+
+/// Total size of the files under `path`, skipping anything that cannot be read
+fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| dir_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(root: &Path, quota_bytes: u64, max_age: Duration) -> StorageManager {
+        StorageManager::new(
+            root.to_path_buf(),
+            StorageConfig {
+                quota_bytes,
+                max_age,
+            },
+        )
+    }
+
+    #[test]
+    fn test_job_dirs_are_kept_by_tenant() {
+        let root = tempfile::tempdir().unwrap();
+        let storage = manager(root.path(), 1024 * 1024, DEFAULT_MAX_AGE);
+
+        let shared = storage.create_job_dir("job-").unwrap();
+        let tenant = with_owner(Some("acme/../x"), Some("user_1"), || {
+            storage.create_job_dir("job-")
+        })
+        .unwrap();
+        fs::write(tenant.path().join("a.wav"), [0u8; 100]).unwrap();
+
+        assert!(shared.path().starts_with(root.path().join(SHARED_DIR)));
+        assert!(
+            tenant
+                .path()
+                .starts_with(root.path().join(TENANTS_DIR).join("acme____x"))
+        );
+        assert_eq!((current_tenant(), current_user()), (None, None));
+
+        let usage = storage.usage().unwrap();
+        assert_eq!(usage.job_dirs, 2);
+        assert_eq!(usage.used_bytes, 100 + "user_1".len() as u64);
+        assert_eq!(usage.tenants["acme____x"].used_bytes, usage.used_bytes);
+        assert_eq!(usage.tenants[SHARED_DIR].job_dirs, 1);
+
+        let mut stored = storage.stored_job_dirs().unwrap();
+        stored.sort_by(|a, b| a.tenant.cmp(&b.tenant));
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].tenant, "acme____x");
+        assert_eq!(stored[0].path, tenant.path());
+        assert_eq!(stored[0].bytes, usage.used_bytes);
+        assert_eq!(stored[0].user.as_deref(), Some("user_1"));
+        assert_eq!(stored[1].tenant, SHARED_DIR);
+        assert_eq!(stored[1].user, None);
+    }
+
+    #[test]
+    fn test_refuses_jobs_over_quota_and_sweeps_old_ones() {
+        let root = tempfile::tempdir().unwrap();
+        let storage = manager(root.path(), 50, DEFAULT_MAX_AGE);
+
+        let job = storage.create_job_dir("job-").unwrap();
+        fs::write(job.path().join("a.wav"), [0u8; 100]).unwrap();
+        let error = storage.create_job_dir("job-").unwrap_err();
+        assert!(
+            error.downcast_ref::<QuotaExceeded>().is_some(),
+            "{:#}",
+            error
+        );
+
+        // Left behind by a crash, and already past the maximum age
+        let abandoned = job.keep();
+        let storage = manager(root.path(), 50, Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(storage.create_job_dir("job-").is_ok());
+        assert!(!abandoned.exists());
+    }
+}
@@ -0,0 +1,170 @@
+//! Syllabification of dictionary pronunciations
+//!
+//! A word's dictionary phonemes are split into syllables by the maximal onset
+//! principle: each vowel or syllabic consonant is a nucleus, and the
+//! consonants between two nuclei start the second syllable as far as English
+//! allows a syllable to start with them. The syllables are then mapped back to
+//! the letters that spell them, so "extra" becomes "ex" and "tra".
+
+use std::ops::Range;
+
+use crate::g2p::align_transcript;
+use crate::phoneme::is_vowel;
+use crate::scoring::{Dictionary, dictionary_word};
+
+/// Marks modifying a phone without changing which phoneme it is, e.g. aspiration
+const MODIFIERS: &[char] = &['ʰ', 'ʷ', 'ʲ', 'ː', 'ˑ', '̚', '̩', 'ˈ', 'ˌ'];
+
+/// Consonants that may start a syllable before one of [`SECOND_ONSETS`]
+const FIRST_ONSETS: &[&str] = &["p", "b", "t", "d", "k", "ɡ", "g", "f", "v", "θ", "ʃ"];
+
+/// Consonants that may follow another at the start of a syllable
+const SECOND_ONSETS: &[&str] = &["ɹ", "r", "l", "w", "j"];
+
+/// Consonants that may follow "s" at the start of a syllable
+const AFTER_S: &[&str] = &["p", "t", "k", "m", "n", "l", "w", "f", "j"];
+
+/// One syllable of a word, with the letters that spell it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Syllable {
+    pub spelling: String,
+    pub phonemes: Vec<String>,
+}
+
+/// Split phonemes into syllables, as index ranges with one nucleus each
+///
+/// Phonemes without a nucleus make one syllable.
+pub fn syllabify(phonemes: &[String]) -> Vec<Range<usize>> {
+    let nuclei: Vec<usize> = (0..phonemes.len())
+        .filter(|&i| is_nucleus(&phonemes[i]))
+        .collect();
+    if nuclei.len() < 2 {
+        // One syllable spanning every phoneme, not a list of indices
+        #[allow(clippy::single_range_in_vec_init)]
+        return vec![0..phonemes.len()];
+    }
+
+    let mut starts = vec![0];
+    for pair in nuclei.windows(2) {
+        let consonants: Vec<&str> = phonemes[pair[0] + 1..pair[1]]
+            .iter()
+            .map(|phoneme| base(phoneme))
+            .collect();
+        starts.push(pair[1] - onset_len(&consonants));
+    }
+
+    let mut ends = starts[1..].to_vec();
+    ends.push(phonemes.len());
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(|(start, end)| start..end)
+        .collect()
+}
+
+/// Syllables of a dictionary word and the letters spelling each, or `None` if the word is not in the dictionary
+///
+/// Letters spelling no phoneme, such as a silent "e", stay with the syllable
+/// before them.
+pub fn spell_syllables(dictionary: &Dictionary, word: &str) -> Option<Vec<Syllable>> {
+    let word = dictionary_word(word);
+    let phonemes = dictionary.get(&word)?;
+    let aligned = align_transcript(dictionary, &word);
+    if aligned.len() != phonemes.len() {
+        return None;
+    }
+
+    let letters: Vec<char> = word.chars().collect();
+    let ranges = syllabify(phonemes);
+
+    // Where each syllable's letters start; letters spelling phonemes on both
+    // sides of a boundary, as "x" in "extra", go to the earlier syllable
+    let mut starts: Vec<usize> = ranges
+        .iter()
+        .skip(1)
+        .map(|range| {
+            aligned[range.start]
+                .span
+                .start
+                .max(aligned[range.start - 1].span.end)
+        })
+        .collect();
+    starts.insert(0, 0);
+    let mut ends = starts[1..].to_vec();
+    ends.push(letters.len());
+
+    let syllables = ranges
+        .into_iter()
+        .zip(starts.into_iter().zip(ends))
+        .map(|(range, (start, end))| Syllable {
+            spelling: letters[start.min(end)..end].iter().collect(),
+            phonemes: phonemes[range].to_vec(),
+        })
+        .collect();
+    Some(syllables)
+}
+
+fn is_nucleus(phoneme: &str) -> bool {
+    is_vowel(phoneme) || phoneme.contains('\u{329}')
+}
+
+/// A phone without its modifiers, e.g. "p" for "pʰ"
+fn base(phoneme: &str) -> &str {
+    phoneme.trim_end_matches(MODIFIERS)
+}
+
+/// How many of the consonants before a nucleus start its syllable
+fn onset_len(consonants: &[&str]) -> usize {
+    let n = consonants.len();
+    // "ŋ" never starts a syllable in English
+    if n == 0 || consonants[n - 1] == "ŋ" {
+        return 0;
+    }
+
+    let legal_pair = |first: &str, second: &str| {
+        (FIRST_ONSETS.contains(&first) && SECOND_ONSETS.contains(&second))
+            || (first == "s" && AFTER_S.contains(&second))
+    };
+    if n >= 2 && legal_pair(consonants[n - 2], consonants[n - 1]) {
+        if n >= 3 && consonants[n - 3] == "s" && ["p", "t", "k"].contains(&consonants[n - 2]) {
+            return 3;
+        }
+        return 2;
+    }
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phonemes(ipa: &str) -> Vec<String> {
+        ipa.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_syllabify_takes_maximal_onsets() {
+        assert_eq!(syllabify(&phonemes("ɛ k s t ɹ ə")), vec![0..2, 2..6]);
+        assert_eq!(syllabify(&phonemes("h ə l ow")), vec![0..2, 2..4]);
+        assert_eq!(syllabify(&phonemes("s ɪ ŋ ɪ ŋ")), vec![0..3, 3..5]);
+        assert_eq!(syllabify(&phonemes("b ʌ tʰ ə n̩")), vec![0..2, 2..4, 4..5]);
+        assert_eq!(syllabify(&phonemes("θ ɪ ŋ k")), vec![0..4]);
+        assert_eq!(syllabify(&[]), vec![0..0]);
+    }
+
+    #[test]
+    fn test_spell_syllables() {
+        let dict = Dictionary::from_pairs(&[("extra", "ɛ k s t ɹ ə"), ("cake", "kʰ ej k")]);
+
+        let syllables = spell_syllables(&dict, "Extra!").unwrap();
+        let spellings: Vec<&str> = syllables.iter().map(|s| s.spelling.as_str()).collect();
+        assert_eq!(spellings, vec!["ex", "tra"]);
+        assert_eq!(syllables[1].phonemes, phonemes("s t ɹ ə"));
+
+        let syllables = spell_syllables(&dict, "cake").unwrap();
+        assert_eq!(syllables.len(), 1);
+        assert_eq!(syllables[0].spelling, "cake");
+
+        assert_eq!(spell_syllables(&dict, "unknown"), None);
+    }
+}
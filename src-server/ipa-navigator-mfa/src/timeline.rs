@@ -0,0 +1,201 @@
+//! Expected and actual phoneme tiers of an attempt, for a "what you said vs
+//! what was expected" timeline
+//!
+//! The expected tier lays the dictionary pronunciation out with a nominal
+//! duration for each phoneme, stretched over the span the speaker actually
+//! took, so both tiers share one time axis without the client realigning them.
+
+use crate::mfa_parser::MfaSegment;
+use crate::phoneme::{Manner, diphthong_targets, is_vowel, parse_ipa};
+
+/// Nominal duration of a phoneme whose class is unknown, in seconds
+const DEFAULT_DURATION: f64 = 0.08;
+
+/// A phoneme of the dictionary pronunciation, placed on the recording's time axis
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedSegment {
+    pub phoneme: String,
+    /// Transcript word, as spelled in the dictionary
+    pub word: String,
+    /// Typical duration of the phoneme in read speech, in seconds
+    pub nominal_duration: f64,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+/// A phone as the aligner placed it in the recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActualSegment {
+    pub phoneme: String,
+    /// Word the phone was aligned within, empty if none
+    pub word: String,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// How sure the aligner is of the segment, from 0.0 to 1.0, if it reported a score
+    pub confidence: Option<f64>,
+}
+
+/// The expected and the actual phoneme tiers of one recording
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlignmentTiers {
+    pub expected: Vec<ExpectedSegment>,
+    pub actual: Vec<ActualSegment>,
+}
+
+/// Typical duration of a phoneme in read English speech, in seconds, by its class
+pub fn nominal_duration(phoneme: &str) -> f64 {
+    let Some(parsed) = parse_ipa(phoneme) else {
+        return DEFAULT_DURATION;
+    };
+    if diphthong_targets(&parsed.base).is_some() {
+        return 0.15;
+    }
+    if is_vowel(&parsed.base) {
+        return if parsed.long { 0.12 } else { 0.08 };
+    }
+    match parsed.features.manner() {
+        Some(Manner::Plosive) => 0.07,
+        Some(Manner::Affricate) => 0.10,
+        Some(Manner::Fricative) => 0.09,
+        Some(Manner::Nasal) | Some(Manner::Approximant) => 0.06,
+        None => DEFAULT_DURATION,
+    }
+}
+
+/// Tiers of a recording aligned as `segments`, against the pronunciation of each transcript word
+///
+/// The expected phonemes fill the span from the first to the last aligned
+/// phone in proportion to their nominal durations. With nothing aligned they
+/// start at zero at their nominal pace.
+pub fn alignment_tiers(
+    expected_words: &[(String, Vec<String>)],
+    segments: &[MfaSegment],
+) -> AlignmentTiers {
+    let words: Vec<&MfaSegment> = segments
+        .iter()
+        .filter(|s| s.segment_type == "word" && !s.label.is_empty())
+        .collect();
+    let actual: Vec<ActualSegment> = segments
+        .iter()
+        .filter(|s| s.segment_type == "phone" && !s.label.is_empty())
+        .map(|phone| {
+            let middle = (phone.begin + phone.end) / 2.0;
+            let word = words
+                .iter()
+                .find(|word| word.begin <= middle && middle <= word.end)
+                .map_or_else(String::new, |word| word.label.clone());
+            ActualSegment {
+                phoneme: phone.label.clone(),
+                word,
+                start_time: phone.begin,
+                end_time: phone.end,
+                confidence: phone.confidence,
+            }
+        })
+        .collect();
+
+    let nominal: Vec<(&str, &str, f64)> = expected_words
+        .iter()
+        .flat_map(|(word, phonemes)| {
+            phonemes
+                .iter()
+                .map(move |phoneme| (word.as_str(), phoneme.as_str(), nominal_duration(phoneme)))
+        })
+        .collect();
+    let nominal_total: f64 = nominal.iter().map(|&(_, _, duration)| duration).sum();
+
+    let (start, scale) = match (actual.first(), actual.last()) {
+        (Some(first), Some(last)) if nominal_total > 0.0 && last.end_time > first.start_time => (
+            first.start_time,
+            (last.end_time - first.start_time) / nominal_total,
+        ),
+        _ => (0.0, 1.0),
+    };
+
+    let mut time = start;
+    let expected = nominal
+        .into_iter()
+        .map(|(word, phoneme, nominal_duration)| {
+            let start_time = time;
+            time += nominal_duration * scale;
+            ExpectedSegment {
+                phoneme: phoneme.to_string(),
+                word: word.to_string(),
+                nominal_duration,
+                start_time,
+                end_time: time,
+            }
+        })
+        .collect();
+
+    AlignmentTiers { expected, actual }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nominal_durations_follow_phoneme_class() {
+        assert!(nominal_duration("aɪ") > nominal_duration("iː"));
+        assert!(nominal_duration("iː") > nominal_duration("ɪ"));
+        assert!(nominal_duration("s") > nominal_duration("t"));
+        assert_eq!(nominal_duration("??"), DEFAULT_DURATION);
+    }
+
+    #[test]
+    fn test_expected_tier_spans_the_speech() {
+        let expected = vec![
+            ("hi".to_string(), vec!["h".to_string(), "aɪ".to_string()]),
+            ("sue".to_string(), vec!["s".to_string(), "uː".to_string()]),
+        ];
+        let segments = vec![
+            MfaSegment::word("hi", 0.5, 0.9),
+            MfaSegment::word("", 0.9, 1.0),
+            MfaSegment::word("sue", 1.0, 1.5),
+            MfaSegment::phone("h", 0.5, 0.6),
+            MfaSegment::phone("aɪ", 0.6, 0.9),
+            MfaSegment::phone("", 0.9, 1.0),
+            MfaSegment::phone("s", 1.0, 1.2),
+            MfaSegment::phone("uː", 1.2, 1.5),
+        ];
+
+        let tiers = alignment_tiers(&expected, &segments);
+
+        let actual: Vec<(&str, &str)> = tiers
+            .actual
+            .iter()
+            .map(|s| (s.phoneme.as_str(), s.word.as_str()))
+            .collect();
+        assert_eq!(
+            actual,
+            [("h", "hi"), ("aɪ", "hi"), ("s", "sue"), ("uː", "sue")]
+        );
+
+        assert_eq!(tiers.expected.len(), 4);
+        assert_eq!(tiers.expected[0].start_time, 0.5);
+        assert!((tiers.expected[3].end_time - 1.5).abs() < 1e-9);
+        for pair in tiers.expected.windows(2) {
+            assert_eq!(pair[0].end_time, pair[1].start_time);
+        }
+        // The diphthong is given longer than the consonant before it
+        let length = |s: &ExpectedSegment| s.end_time - s.start_time;
+        assert!(length(&tiers.expected[1]) > length(&tiers.expected[0]));
+        assert_eq!(tiers.expected[2].word, "sue");
+    }
+
+    #[test]
+    fn test_expected_tier_without_alignment_keeps_nominal_pace() {
+        let expected = vec![("at".to_string(), vec!["æ".to_string(), "t".to_string()])];
+
+        let tiers = alignment_tiers(&expected, &[]);
+
+        assert!(tiers.actual.is_empty());
+        assert_eq!(tiers.expected[0].start_time, 0.0);
+        assert_eq!(tiers.expected[0].end_time, nominal_duration("æ"));
+        assert_eq!(
+            tiers.expected[1].end_time,
+            nominal_duration("æ") + nominal_duration("t")
+        );
+    }
+}
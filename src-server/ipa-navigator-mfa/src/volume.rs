@@ -0,0 +1,171 @@
+//! Translation between host paths and paths inside the MFA container
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::platform::{
+    container_path, is_absolute_container_path, is_absolute_host_path, mount_source,
+    same_host_path, split_bind_spec,
+};
+
+/// A bind mount from a host directory to a container directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeMapping {
+    pub host: PathBuf,
+    pub container: PathBuf,
+}
+
+impl VolumeMapping {
+    /// Create a mapping between a host directory and a container directory
+    pub fn new(host: impl Into<PathBuf>, container: impl Into<PathBuf>) -> Self {
+        Self {
+            host: host.into(),
+            container: container.into(),
+        }
+    }
+
+    /// Parse a mapping from Docker's `host:container` bind syntax
+    ///
+    /// A trailing mount mode such as `:ro` is ignored, and the host path may
+    /// start with a Windows drive letter.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (host, container) = split_bind_spec(spec);
+
+        if host.is_empty() || container.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid volume mapping '{}', expected host:container",
+                spec
+            ));
+        }
+
+        if !is_absolute_host_path(host) || !is_absolute_container_path(container) {
+            return Err(anyhow::anyhow!(
+                "Volume mapping '{}' must use absolute paths",
+                spec
+            ));
+        }
+
+        Ok(Self::new(host, container))
+    }
+
+    /// Translate a host path under this mount into the container path
+    pub fn to_container_path(&self, host_path: &Path) -> Option<PathBuf> {
+        host_path
+            .strip_prefix(&self.host)
+            .ok()
+            .map(|rest| self.container.join(rest))
+    }
+
+    /// Translate a container path under this mount back into the host path
+    pub fn to_host_path(&self, container_path: &Path) -> Option<PathBuf> {
+        container_path
+            .strip_prefix(&self.container)
+            .ok()
+            .map(|rest| self.host.join(rest))
+    }
+
+    /// Whether this is the mount `docker inspect` reported as `reported`
+    pub fn same_mount(&self, reported: &VolumeMapping) -> bool {
+        container_path(&self.container) == container_path(&reported.container)
+            && same_host_path(&self.host, &reported.host)
+    }
+
+    /// Make sure the host side of the mount exists and is a directory
+    pub fn ensure_host_dir(&self) -> Result<()> {
+        fs::create_dir_all(&self.host)
+            .with_context(|| format!("Failed to create host volume directory {:?}", self.host))?;
+
+        if !self.host.is_dir() {
+            return Err(anyhow::anyhow!(
+                "Host volume path {:?} is not a directory",
+                self.host
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for VolumeMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            mount_source(&self.host),
+            container_path(&self.container)
+        )
+    }
+}
+
+/// Translate a host path using the most specific mapping that covers it
+pub fn host_to_container(mappings: &[VolumeMapping], host_path: &Path) -> Result<PathBuf> {
+    mappings
+        .iter()
+        .filter(|m| host_path.starts_with(&m.host))
+        .max_by_key(|m| m.host.components().count())
+        .and_then(|m| m.to_container_path(host_path))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} is not inside any volume mounted into the MFA container",
+                host_path.display()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_volume_mapping() {
+        let mapping = VolumeMapping::parse("/srv/jobs:/data/jobs").unwrap();
+        assert_eq!(mapping.host, PathBuf::from("/srv/jobs"));
+        assert_eq!(mapping.container, PathBuf::from("/data/jobs"));
+
+        let read_only = VolumeMapping::parse("/srv/dicts:/data/dicts:ro").unwrap();
+        assert_eq!(read_only.container, PathBuf::from("/data/dicts"));
+
+        assert!(VolumeMapping::parse("/srv/jobs").is_err());
+        assert!(VolumeMapping::parse("jobs:/data/jobs").is_err());
+
+        let windows = VolumeMapping::parse(r"C:\Users\ana\jobs:/data/jobs").unwrap();
+        assert_eq!(windows.host, PathBuf::from(r"C:\Users\ana\jobs"));
+        assert_eq!(windows.to_string(), "C:/Users/ana/jobs:/data/jobs");
+        assert!(VolumeMapping::parse(r"C:\Users\ana\jobs:data").is_err());
+    }
+
+    #[test]
+    fn test_path_translation() {
+        let mapping = VolumeMapping::new("/srv/jobs", "/data/jobs");
+
+        assert_eq!(
+            mapping.to_container_path(Path::new("/srv/jobs/abc")),
+            Some(PathBuf::from("/data/jobs/abc"))
+        );
+        assert_eq!(
+            mapping.to_host_path(Path::new("/data/jobs/abc/out.TextGrid")),
+            Some(PathBuf::from("/srv/jobs/abc/out.TextGrid"))
+        );
+        assert_eq!(mapping.to_container_path(Path::new("/tmp/abc")), None);
+    }
+
+    #[test]
+    fn test_host_to_container_prefers_most_specific() {
+        let mappings = vec![
+            VolumeMapping::new("/srv", "/data"),
+            VolumeMapping::new("/srv/jobs", "/jobs"),
+        ];
+
+        assert_eq!(
+            host_to_container(&mappings, Path::new("/srv/jobs/abc")).unwrap(),
+            PathBuf::from("/jobs/abc")
+        );
+        assert_eq!(
+            host_to_container(&mappings, Path::new("/srv/other")).unwrap(),
+            PathBuf::from("/data/other")
+        );
+        assert!(host_to_container(&mappings, Path::new("/tmp/abc")).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Command-line access to synthesis and assessment without the HTTP server
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use ipa_navigator_axum::{
+    engines::{Kokoro, Mfa},
+    selftest::{min_score_from_env, run_self_test},
+};
+use ipa_navigator_kokoro::{
+    phonemizer::text_to_phonemes_string,
+    prelude::SAMPLE_RATE,
+    tts::KokoroTTS,
+    voices::{ALL_VOICES, VoiceType},
+    wav::{SampleEncoding, WavFormat},
+};
+use ipa_navigator_mfa::{
+    aligner::get_aligner,
+    docker::MfaDialect,
+    feedback::generate_feedback,
+    scoring::{Strictness, cached_dictionary, expected_word_phonemes, rubric},
+};
+use serde::Serialize;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "ipa-navigator",
+    about = "Offline synthesis and pronunciation assessment"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Synthesize text to a WAV file
+    Tts {
+        /// Text to speak
+        text: String,
+
+        /// Where to write the WAV file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Voice name, see `voices list`
+        #[arg(long, default_value = "american_female_bella")]
+        voice: String,
+
+        /// Speaking rate between 0.5 and 2.0
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+
+        /// How samples are stored in the WAV file
+        #[arg(long, value_enum, default_value_t = Encoding::Pcm16)]
+        encoding: Encoding,
+
+        /// Add TPDF dither when writing 16-bit samples
+        #[arg(long)]
+        dither: bool,
+
+        /// Sample rate of the WAV file, resampling from the model's 24 kHz
+        #[arg(long, default_value_t = SAMPLE_RATE)]
+        sample_rate: u32,
+    },
+
+    /// Print the phonemes of text
+    Phonemize {
+        /// Text to transcribe
+        text: String,
+
+        #[arg(long, value_enum, default_value_t = Dialect::Us)]
+        dialect: Dialect,
+
+        /// Look words up in the MFA dictionary used for scoring instead of using espeak
+        #[arg(long)]
+        dictionary: bool,
+    },
+
+    /// Assess a recording against its transcript and print a JSON report
+    Assess {
+        /// WAV recording
+        audio: PathBuf,
+
+        /// Text that was read in the recording
+        transcript: String,
+
+        #[arg(long, value_enum, default_value_t = Dialect::Us)]
+        dialect: Dialect,
+
+        /// "beginner", "intermediate", or "strict"
+        #[arg(long, default_value = "intermediate")]
+        strictness: String,
+    },
+
+    /// Synthesize "hello world", assess it against itself, and print per-stage timings as JSON
+    SelfTest {
+        /// Lowest overall score that passes, defaulting to `SELF_TEST_MIN_SCORE` or 0.6
+        #[arg(long)]
+        min_score: Option<f64>,
+
+        /// "beginner", "intermediate", or "strict"
+        #[arg(long, default_value = "intermediate")]
+        strictness: String,
+    },
+
+    /// Inspect the available voices
+    Voices {
+        #[command(subcommand)]
+        command: VoicesCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum VoicesCommand {
+    /// List every voice name with its language
+    List,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Encoding {
+    Pcm16,
+    Pcm24,
+    Float32,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Dialect {
+    Us,
+    Uk,
+}
+
+impl Dialect {
+    fn mfa(self) -> MfaDialect {
+        match self {
+            Dialect::Us => MfaDialect::AmericanEnglish,
+            Dialect::Uk => MfaDialect::BritishEnglish,
+        }
+    }
+
+    fn espeak_language(self) -> &'static str {
+        match self {
+            Dialect::Us => "en-us",
+            Dialect::Uk => "en",
+        }
+    }
+}
+
+/// JSON report printed by `assess`
+#[derive(Debug, Serialize)]
+struct AssessmentReport {
+    transcript: String,
+    overall_score: f64,
+    phonemes: Vec<PhonemeReport>,
+    feedback: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PhonemeReport {
+    expected: String,
+    actual: String,
+    score: f64,
+    start_time: f64,
+    end_time: f64,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Tts {
+            text,
+            output,
+            voice,
+            speed,
+            encoding,
+            dither,
+            sample_rate,
+        } => {
+            let voice = VoiceType::from_name(&voice)
+                .ok_or_else(|| format!("Unsupported voice: {}", voice))?;
+            if !(0.5..=2.0).contains(&speed) {
+                return Err("Speed must be between 0.5 and 2.0".to_string());
+            }
+            if !(8000..=192_000).contains(&sample_rate) {
+                return Err("Sample rate must be between 8000 and 192000".to_string());
+            }
+            let format = WavFormat {
+                encoding: match encoding {
+                    Encoding::Pcm16 => SampleEncoding::Pcm16 { dither },
+                    Encoding::Pcm24 => SampleEncoding::Pcm24,
+                    Encoding::Float32 => SampleEncoding::Float32,
+                },
+                sample_rate,
+            };
+
+            let tts = KokoroTTS::new().map_err(|e| format!("Failed to load Kokoro: {}", e))?;
+            let audio = tts
+                .synthesize(&text, &voice, speed)
+                .map_err(|e| format!("Synthesis failed: {}", e))?;
+            let samples = audio.as_slice().ok_or("Failed to convert audio data")?;
+
+            let wav = tts
+                .audio_to_wav_with(samples, &format)
+                .map_err(|e| format!("Failed to encode audio: {}", e))?;
+            fs::write(&output, wav)
+                .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+            eprintln!("Wrote {}", output.display());
+        }
+
+        Command::Phonemize {
+            text,
+            dialect,
+            dictionary,
+        } => {
+            if dictionary {
+                let dictionary = cached_dictionary(dialect.mfa())
+                    .map_err(|e| format!("Failed to load dictionary: {}", e))?;
+                for (word, phonemes) in expected_word_phonemes(&dictionary, &text) {
+                    println!("{}\t{}", word, phonemes.join(" "));
+                }
+            } else {
+                let phonemes = text_to_phonemes_string(&text, dialect.espeak_language())?;
+                println!("{}", phonemes);
+            }
+        }
+
+        Command::Assess {
+            audio,
+            transcript,
+            dialect,
+            strictness,
+        } => {
+            let strictness = Strictness::parse(&strictness)
+                .ok_or_else(|| format!("Unsupported strictness: {}", strictness))?;
+            let audio_data = fs::read(&audio)
+                .map_err(|e| format!("Failed to read {}: {}", audio.display(), e))?;
+
+            let assessment = get_aligner()
+                .and_then(|aligner| {
+                    aligner.assess(&audio_data, &transcript, dialect.mfa(), &rubric(strictness))
+                })
+                .map_err(|e| format!("Assessment failed: {:#}", e))?;
+
+            let report = AssessmentReport {
+                transcript,
+                overall_score: assessment.overall_score,
+                feedback: generate_feedback(&assessment.phoneme_details),
+                phonemes: assessment
+                    .phoneme_details
+                    .into_iter()
+                    .map(|detail| PhonemeReport {
+                        expected: detail.expected,
+                        actual: detail.actual,
+                        score: detail.score,
+                        start_time: detail.start_time,
+                        end_time: detail.end_time,
+                    })
+                    .collect(),
+            };
+
+            let json = serde_json::to_string_pretty(&report)
+                .map_err(|e| format!("Failed to encode report: {}", e))?;
+            println!("{}", json);
+        }
+
+        Command::SelfTest {
+            min_score,
+            strictness,
+        } => {
+            let strictness = Strictness::parse(&strictness)
+                .ok_or_else(|| format!("Unsupported strictness: {}", strictness))?;
+            let min_score = min_score.unwrap_or_else(min_score_from_env);
+
+            let report = run_self_test(&Kokoro, &Mfa, strictness, min_score);
+
+            let json = serde_json::to_string_pretty(&report)
+                .map_err(|e| format!("Failed to encode report: {}", e))?;
+            println!("{}", json);
+            if !report.passed {
+                return Err(match report.stages.iter().find(|stage| !stage.ok) {
+                    Some(stage) => format!("Self-test failed at {}", stage.stage),
+                    None => format!(
+                        "Self-test scored {:.2}, below {:.2}",
+                        report.score.unwrap_or_default(),
+                        min_score
+                    ),
+                });
+            }
+        }
+
+        Command::Voices {
+            command: VoicesCommand::List,
+        } => {
+            for voice in ALL_VOICES.iter() {
+                println!("{}\t{}", voice.name(), voice.language());
+            }
+        }
+    }
+
+    Ok(())
+}
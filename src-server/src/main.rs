@@ -1,28 +1,153 @@
-use ipa_navigator_axum::{Config as server_config, create_router};
-use tracing::{error, info};
+use ipa_navigator_axum::{
+    Config as server_config,
+    config::{CONFIG_FILE_ACTOR, CONFIG_MANAGER},
+    create_router,
+    diagnostics::{StartupPolicy, run_startup_checks},
+    handlers::tts::preload_tts,
+    retention,
+};
+use ipa_navigator_mfa::scoring::SIMILARITY_MATRIX;
+use std::sync::LazyLock;
+use telemetry::{TelemetryConfig, otlp_layer};
+use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod telemetry;
+
 #[tokio::main]
 async fn main() {
+    // Export spans over OTLP when a collector endpoint is configured
+    let telemetry_config = TelemetryConfig::from_env();
+    let (otlp, tracer_provider) = match otlp_layer(&telemetry_config) {
+        Ok(Some((layer, provider))) => (Some(layer), Some(provider)),
+        Ok(None) => (None, None),
+        Err(e) => {
+            eprintln!("{}, continuing without trace export", e);
+            (None, None)
+        }
+    };
+
     // Initialize logging
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
             std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
         ))
         .with(tracing_subscriber::fmt::layer())
+        .with(otlp)
         .init();
 
+    if let Some(endpoint) = &telemetry_config.endpoint
+        && tracer_provider.is_some()
+    {
+        info!("Exporting traces to {}", endpoint);
+    }
+
     // Get server configuration
     let config = server_config::from_env();
     let addr = format!("{}:{}", config.host, config.port);
 
+    // Check every subsystem now rather than on the first request that needs it. This also
+    // points espeak-ng at its data before anything phonemizes
+    let policy = StartupPolicy::from_env();
+    let diagnostics = run_startup_checks(&config, &policy).await;
+    diagnostics.log();
+    let blocking = diagnostics.blocking();
+    if !blocking.is_empty() {
+        let names: Vec<&str> = blocking.iter().map(|check| check.subsystem).collect();
+        if policy.fail_fast {
+            error!(
+                "Refusing to start, required checks failed: {}",
+                names.join(", ")
+            );
+            std::process::exit(1);
+        }
+        warn!("Starting with failed required checks: {}", names.join(", "));
+    }
+
+    // Compare every pair of phonemes now rather than during the first assessment
+    LazyLock::force(&SIMILARITY_MATRIX);
+
+    // Load the model and voices in the background; /ready reports when they are loaded
+    tokio::task::spawn_blocking(|| match preload_tts() {
+        Ok(tts) => {
+            let failed = tts.failed_voices();
+            if failed.is_empty() {
+                info!("TTS ready with {} voices", tts.available_voices().len());
+            } else {
+                error!(
+                    "TTS ready with {} voices, {} failed to load",
+                    tts.available_voices().len(),
+                    failed.len()
+                );
+            }
+        }
+        Err(e) => error!("Failed to load TTS: {}", e),
+    });
+
+    // Apply the settings file, and again whenever it changes
+    if let Some(path) = CONFIG_MANAGER.path() {
+        match CONFIG_MANAGER.reload(CONFIG_FILE_ACTOR) {
+            Ok(changes) => info!("Applied {} settings from {}", changes.len(), path.display()),
+            Err(e) => error!("Failed to apply settings, using the environment's: {}", e),
+        }
+        if let Err(e) = CONFIG_MANAGER.watch() {
+            error!("{}", e);
+        }
+    }
+
+    // Delete stored job artifacts the retention policy no longer keeps
+    retention::spawn_enforcer();
+
     // Create the router
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     let router = create_router();
 
     // Create the server
     info!("Starting server on {}", listener.local_addr().unwrap());
-    if let Err(e) = axum::serve(listener, router).await {
+    if let Err(e) = axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+    {
         error!("Server error: {}", e);
     }
+
+    // Flush spans still waiting in the batch exporter
+    if let Some(provider) = tracer_provider
+        && let Err(e) = provider.shutdown()
+    {
+        error!("Failed to flush traces: {}", e);
+    }
+}
+
+/// Resolve on Ctrl+C or, on Unix, SIGTERM as sent by `docker stop`
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("Shutting down");
 }
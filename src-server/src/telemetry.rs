@@ -0,0 +1,67 @@
+use std::env;
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+use tracing::Subscriber;
+use tracing_subscriber::{Layer, registry::LookupSpan};
+
+/// Service name reported to the collector when `OTEL_SERVICE_NAME` is unset
+const DEFAULT_SERVICE_NAME: &str = "ipa-navigator";
+
+/// OTLP export settings, enabled by setting `OTEL_EXPORTER_OTLP_ENDPOINT`
+pub struct TelemetryConfig {
+    pub endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl TelemetryConfig {
+    pub fn from_env() -> Self {
+        let endpoint = env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .ok()
+            .filter(|e| !e.trim().is_empty());
+
+        let service_name =
+            env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+
+        Self {
+            endpoint,
+            service_name,
+        }
+    }
+}
+
+/// Build the tracing layer exporting spans over OTLP/HTTP, if an endpoint is configured
+///
+/// The exporter reads the endpoint and any `OTEL_EXPORTER_OTLP_*` headers or
+/// timeouts from the environment itself. The returned provider must be shut
+/// down on exit to flush pending spans.
+pub fn otlp_layer<S>(
+    config: &TelemetryConfig,
+) -> Result<Option<(impl Layer<S>, SdkTracerProvider)>, String>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    if config.endpoint.is_none() {
+        return Ok(None);
+    }
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .build()
+        .map_err(|e| format!("Failed to create OTLP exporter: {}", e))?;
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+
+    let tracer = provider.tracer(DEFAULT_SERVICE_NAME);
+    let layer = tracing_opentelemetry::layer().with_tracer(tracer);
+
+    Ok(Some((layer, provider)))
+}
@@ -14,16 +14,20 @@ import type {
   FunctionReference,
 } from "convex/server";
 import type * as crons from "../crons.js";
+import type * as functions_assignments from "../functions/assignments.js";
+import type * as functions_assessmentCache from "../functions/assessmentCache.js";
 import type * as functions_badgeSvgGenerator from "../functions/badgeSvgGenerator.js";
 import type * as functions_chapters from "../functions/chapters.js";
 import type * as functions_classrooms from "../functions/classrooms.js";
 import type * as functions_dashboard from "../functions/dashboard.js";
 import type * as functions_excerpts from "../functions/excerpts.js";
+import type * as functions_exercises from "../functions/exercises.js";
 import type * as functions_files from "../functions/files.js";
 import type * as functions_gamification from "../functions/gamification.js";
 import type * as functions_ml from "../functions/ml.js";
 import type * as functions_performance from "../functions/performance.js";
 import type * as functions_phonemes from "../functions/phonemes.js";
+import type * as functions_privacy from "../functions/privacy.js";
 import type * as functions_social from "../functions/social.js";
 import type * as functions_users from "../functions/users.js";
 import type * as models_api from "../models/api.js";
@@ -42,16 +46,20 @@ import type * as models_users from "../models/users.js";
  */
 declare const fullApi: ApiFromModules<{
   crons: typeof crons;
+  "functions/assignments": typeof functions_assignments;
+  "functions/assessmentCache": typeof functions_assessmentCache;
   "functions/badgeSvgGenerator": typeof functions_badgeSvgGenerator;
   "functions/chapters": typeof functions_chapters;
   "functions/classrooms": typeof functions_classrooms;
   "functions/dashboard": typeof functions_dashboard;
   "functions/excerpts": typeof functions_excerpts;
+  "functions/exercises": typeof functions_exercises;
   "functions/files": typeof functions_files;
   "functions/gamification": typeof functions_gamification;
   "functions/ml": typeof functions_ml;
   "functions/performance": typeof functions_performance;
   "functions/phonemes": typeof functions_phonemes;
+  "functions/privacy": typeof functions_privacy;
   "functions/social": typeof functions_social;
   "functions/users": typeof functions_users;
   "models/api": typeof models_api;
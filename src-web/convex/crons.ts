@@ -9,4 +9,16 @@ crons.daily(
   internal.functions.gamification.selectDailyChapter,
 );
 
+crons.daily(
+  "Purge expired assessment cache entries",
+  { hourUTC: 17, minuteUTC: 0 },
+  internal.functions.assessmentCache.purgeExpired,
+);
+
+crons.daily(
+  "Delete expired submission recordings",
+  { hourUTC: 17, minuteUTC: 30 },
+  internal.functions.assignments.purgeExpiredAudio,
+);
+
 export default crons;
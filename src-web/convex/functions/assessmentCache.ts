@@ -0,0 +1,74 @@
+import { internalMutation, mutation, query } from "../_generated/server.js";
+import { v } from "convex/values";
+
+// Cached assessments are dropped after a week
+const CACHE_TTL_MS = 7 * 24 * 60 * 60 * 1000;
+
+// Only the Rust server may read and write cached assessments
+function checkToken(token: string) {
+  const expected = process.env.ASSESSMENT_CACHE_TOKEN;
+  if (!expected || token !== expected) {
+    throw new Error("Invalid assessment cache token");
+  }
+}
+
+export const get = query({
+  args: {
+    token: v.string(),
+    key: v.string(),
+  },
+  handler: async (ctx, args) => {
+    checkToken(args.token);
+
+    const entry = await ctx.db
+      .query("assessment_cache")
+      .withIndex("by_key", (q) => q.eq("key", args.key))
+      .first();
+
+    return entry?.response ?? null;
+  },
+});
+
+export const put = mutation({
+  args: {
+    token: v.string(),
+    key: v.string(),
+    response: v.string(),
+  },
+  handler: async (ctx, args) => {
+    checkToken(args.token);
+
+    const existing = await ctx.db
+      .query("assessment_cache")
+      .withIndex("by_key", (q) => q.eq("key", args.key))
+      .first();
+
+    if (existing) {
+      await ctx.db.patch(existing._id, {
+        response: args.response,
+        created_at: Date.now(),
+      });
+    } else {
+      await ctx.db.insert("assessment_cache", {
+        key: args.key,
+        response: args.response,
+        created_at: Date.now(),
+      });
+    }
+  },
+});
+
+export const purgeExpired = internalMutation({
+  args: {},
+  handler: async (ctx) => {
+    const cutoff = Date.now() - CACHE_TTL_MS;
+    const expired = await ctx.db
+      .query("assessment_cache")
+      .filter((q) => q.lt(q.field("created_at"), cutoff))
+      .collect();
+
+    for (const entry of expired) {
+      await ctx.db.delete(entry._id);
+    }
+  },
+});
@@ -0,0 +1,220 @@
+import { internalMutation, mutation, query } from "../_generated/server.js";
+import { v } from "convex/values";
+import type { Id } from "../_generated/dataModel.d.ts";
+import type { QueryCtx } from "../_generated/server.d.ts";
+import { getUserIdFromContext } from "../models/users.ts";
+
+const phonemeScore = v.object({
+  expected: v.string(),
+  actual: v.string(),
+  score: v.number(),
+});
+
+async function requireTeacher(
+  ctx: QueryCtx,
+  classroomId: Id<"classroom">,
+  userId: Id<"users">,
+) {
+  const classroom = await ctx.db.get(classroomId);
+  if (!classroom) {
+    throw new Error("Classroom not found");
+  }
+  if (classroom.teacherId.toString() !== userId.toString()) {
+    throw new Error("You do not have permission to manage this classroom");
+  }
+  return classroom;
+}
+
+async function isEnrolled(
+  ctx: QueryCtx,
+  classroomId: Id<"classroom">,
+  userId: Id<"users">,
+) {
+  const enrollment = await ctx.db
+    .query("classroom_enrollment")
+    .withIndex(
+      "unique_enrollment",
+      (q) => q.eq("classroomId", classroomId).eq("userId", userId),
+    )
+    .filter((q) => q.eq(q.field("removed_at"), undefined))
+    .first();
+  return enrollment !== null;
+}
+
+// Called by the Rust server with the teacher's token after validating the sentences
+export const createAssignment = mutation({
+  args: {
+    classroomId: v.id("classroom"),
+    title: v.string(),
+    dialect: v.string(),
+    sentences: v.array(v.string()),
+    due_date: v.optional(v.number()),
+  },
+  handler: async (ctx, args) => {
+    const userId = await getUserIdFromContext(ctx);
+    await requireTeacher(ctx, args.classroomId, userId);
+
+    return await ctx.db.insert("sentence_assignment", {
+      classroomId: args.classroomId,
+      assignedBy: userId,
+      title: args.title,
+      dialect: args.dialect,
+      sentences: args.sentences,
+      due_date: args.due_date,
+      created_at: Date.now(),
+    });
+  },
+});
+
+// Visible to the classroom's teacher and enrolled students
+export const getAssignment = query({
+  args: { assignmentId: v.id("sentence_assignment") },
+  handler: async (ctx, args) => {
+    const userId = await getUserIdFromContext(ctx);
+    const assignment = await ctx.db.get(args.assignmentId);
+    if (!assignment) {
+      return null;
+    }
+
+    const classroom = await ctx.db.get(assignment.classroomId);
+    const isTeacher = classroom?.teacherId.toString() === userId.toString();
+    if (!isTeacher && !(await isEnrolled(ctx, assignment.classroomId, userId))) {
+      return null;
+    }
+    return assignment;
+  },
+});
+
+// Called by the Rust server with the student's token after scoring the recording
+export const submitAttempt = mutation({
+  args: {
+    assignmentId: v.id("sentence_assignment"),
+    sentence_index: v.number(),
+    overall_score: v.number(),
+    phonemes: v.array(phonemeScore),
+    audioId: v.optional(v.id("_storage")),
+    audio_expires_at: v.optional(v.number()),
+  },
+  handler: async (ctx, args) => {
+    const userId = await getUserIdFromContext(ctx);
+    const assignment = await ctx.db.get(args.assignmentId);
+    if (!assignment) {
+      throw new Error("Assignment not found");
+    }
+    if (!(await isEnrolled(ctx, assignment.classroomId, userId))) {
+      throw new Error("You are not enrolled in this classroom");
+    }
+    if (
+      !Number.isInteger(args.sentence_index) || args.sentence_index < 0 ||
+      args.sentence_index >= assignment.sentences.length
+    ) {
+      throw new Error("Sentence is not part of this assignment");
+    }
+
+    const now = Date.now();
+    // Recordings are only kept with an expiry, so none outlives its retention period
+    if (
+      args.audioId !== undefined &&
+      (args.audio_expires_at === undefined || args.audio_expires_at <= now)
+    ) {
+      throw new Error("Recordings must expire in the future");
+    }
+
+    return await ctx.db.insert("assignment_submission", {
+      assignmentId: args.assignmentId,
+      classroomId: assignment.classroomId,
+      userId,
+      sentence_index: args.sentence_index,
+      overall_score: args.overall_score,
+      phonemes: args.phonemes,
+      late: assignment.due_date !== undefined && now > assignment.due_date,
+      submitted_at: now,
+      audioId: args.audioId,
+      audio_expires_at: args.audioId ? args.audio_expires_at : undefined,
+    });
+  },
+});
+
+// Every submission to an assignment with the student's name, for its teacher
+export const getSubmissions = query({
+  args: { assignmentId: v.id("sentence_assignment") },
+  handler: async (ctx, args) => {
+    const userId = await getUserIdFromContext(ctx);
+    const assignment = await ctx.db.get(args.assignmentId);
+    if (!assignment) {
+      throw new Error("Assignment not found");
+    }
+    await requireTeacher(ctx, assignment.classroomId, userId);
+
+    const submissions = await ctx.db
+      .query("assignment_submission")
+      .withIndex("by_assignment", (q) => q.eq("assignmentId", args.assignmentId))
+      .collect();
+
+    const names = new Map<string, string>();
+    for (const submission of submissions) {
+      const id = submission.userId.toString();
+      if (!names.has(id)) {
+        const user = await ctx.db.get(submission.userId);
+        names.set(id, user?.name ?? "Unknown");
+      }
+    }
+
+    return await Promise.all(submissions.map(async (submission) => ({
+      ...submission,
+      student_name: names.get(submission.userId.toString()) ?? "Unknown",
+      audio_url: submission.audioId
+        ? await ctx.storage.getUrl(submission.audioId)
+        : null,
+    })));
+  },
+});
+
+// A classroom's submissions since a time, for its teacher to aggregate
+export const getClassroomSubmissions = query({
+  args: {
+    classroomId: v.id("classroom"),
+    since: v.optional(v.number()),
+  },
+  handler: async (ctx, args) => {
+    const userId = await getUserIdFromContext(ctx);
+    await requireTeacher(ctx, args.classroomId, userId);
+
+    return await ctx.db
+      .query("assignment_submission")
+      .withIndex(
+        "by_classroom",
+        (q) =>
+          q.eq("classroomId", args.classroomId).gte(
+            "submitted_at",
+            args.since ?? 0,
+          ),
+      )
+      .collect();
+  },
+});
+
+// Delete recordings past their retention period, keeping the scores
+export const purgeExpiredAudio = internalMutation({
+  args: {},
+  handler: async (ctx) => {
+    const expired = await ctx.db
+      .query("assignment_submission")
+      // Submissions without a recording sort first, so skip them with a lower bound
+      .withIndex(
+        "by_audio_expiry",
+        (q) => q.gt("audio_expires_at", 0).lt("audio_expires_at", Date.now()),
+      )
+      .collect();
+
+    for (const submission of expired) {
+      if (submission.audioId) {
+        await ctx.storage.delete(submission.audioId);
+      }
+      await ctx.db.patch(submission._id, {
+        audioId: undefined,
+        audio_expires_at: undefined,
+      });
+    }
+  },
+});
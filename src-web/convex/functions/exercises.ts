@@ -0,0 +1,75 @@
+import { mutation, query } from "../_generated/server.js";
+import { v } from "convex/values";
+import { getUserIdFromContext } from "../models/users.ts";
+
+const deckItem = v.object({
+  text: v.string(),
+  ipa: v.string(),
+  words: v.array(v.object({
+    word: v.string(),
+    ipa: v.string(),
+  })),
+  missing_words: v.array(v.string()),
+  reference_audio_url: v.string(),
+});
+
+// Called by the Rust server with the user's token after building the items
+export const createDeck = mutation({
+  args: {
+    title: v.string(),
+    dialect: v.string(),
+    source_text: v.string(),
+    items: v.array(deckItem),
+  },
+  handler: async (ctx, args) => {
+    const userId = await getUserIdFromContext(ctx);
+
+    return await ctx.db.insert("practice_deck", {
+      userId,
+      title: args.title,
+      dialect: args.dialect,
+      source_text: args.source_text,
+      items: args.items,
+      created_at: Date.now(),
+    });
+  },
+});
+
+export const getMyDecks = query({
+  args: {},
+  handler: async (ctx) => {
+    const userId = await getUserIdFromContext(ctx);
+
+    return await ctx.db
+      .query("practice_deck")
+      .withIndex("by_user", (q) => q.eq("userId", userId))
+      .order("desc")
+      .collect();
+  },
+});
+
+export const getDeck = query({
+  args: { deckId: v.id("practice_deck") },
+  handler: async (ctx, args) => {
+    const userId = await getUserIdFromContext(ctx);
+
+    const deck = await ctx.db.get(args.deckId);
+    if (!deck || deck.userId !== userId) {
+      return null;
+    }
+    return deck;
+  },
+});
+
+export const deleteDeck = mutation({
+  args: { deckId: v.id("practice_deck") },
+  handler: async (ctx, args) => {
+    const userId = await getUserIdFromContext(ctx);
+
+    const deck = await ctx.db.get(args.deckId);
+    if (!deck || deck.userId !== userId) {
+      throw new Error("Deck not found");
+    }
+    await ctx.db.delete(args.deckId);
+  },
+});
@@ -1,4 +1,6 @@
 import { v } from "convex/values";
+import type { Id } from "../_generated/dataModel.d.ts";
+import type { QueryCtx } from "../_generated/server.d.ts";
 import { internalMutation, mutation, query } from "../_generated/server.js";
 import { getUserIdFromContext } from "../models/users.ts";
 
@@ -293,3 +295,138 @@ export const getUserBadges = query({
       .collect();
   },
 });
+
+// A user's attempts and stored badges, for the Rust server to compute streaks and badges
+export const getPracticeHistory = query({
+  args: { userId: v.id("users") },
+  handler: async (ctx, args) => {
+    await getUserIdFromContext(ctx);
+
+    const user = await ctx.db.get(args.userId);
+    if (!user) {
+      return null;
+    }
+
+    const attempts = await ctx.db
+      .query("excerpt_practice")
+      .withIndex("by_user_and_time", (q) => q.eq("userId", args.userId))
+      .collect();
+    const badges = await ctx.db
+      .query("user_badges")
+      .withIndex("by_user", (q) => q.eq("userId", args.userId))
+      .collect();
+
+    return {
+      name: user.name,
+      attempts: attempts.map((attempt) => ({
+        overall_accuracy: attempt.overall_accuracy,
+        created_at: attempt.created_at,
+      })),
+      badge_ids: badges.map((badge) => badge.badgeId),
+    };
+  },
+});
+
+// Every user's attempts since a time, for the Rust server to rank
+export const getPracticeSince = query({
+  args: { since: v.number() },
+  handler: async (ctx, args) => {
+    await getUserIdFromContext(ctx);
+
+    const attempts = await ctx.db
+      .query("excerpt_practice")
+      .withIndex("by_time", (q) => q.gte("created_at", args.since))
+      .collect();
+
+    const users = new Map<string, {
+      userId: string;
+      name: string;
+      attempts: { overall_accuracy: number; created_at: number }[];
+    }>();
+    for (const attempt of attempts) {
+      const id = attempt.userId.toString();
+      let entry = users.get(id);
+      if (!entry) {
+        const user = await ctx.db.get(attempt.userId);
+        entry = { userId: id, name: user?.name ?? "Unknown", attempts: [] };
+        users.set(id, entry);
+      }
+      entry.attempts.push({
+        overall_accuracy: attempt.overall_accuracy,
+        created_at: attempt.created_at,
+      });
+    }
+
+    return [...users.values()];
+  },
+});
+
+// The phonemes an attempt was scored on
+async function practicePhonemes(
+  ctx: QueryCtx,
+  practiceId: Id<"excerpt_practice">,
+) {
+  const words = await ctx.db
+    .query("word_result")
+    .withIndex("by_practice", (q) => q.eq("practiceId", practiceId))
+    .collect();
+
+  const phonemes = [];
+  for (const word of words) {
+    const results = await ctx.db
+      .query("phoneme_result")
+      .withIndex("by_word_result", (q) => q.eq("wordResultId", word._id))
+      .collect();
+    for (const result of results) {
+      phonemes.push({
+        target_phoneme: result.target_phoneme ?? "",
+        detected_phoneme: result.detected_phoneme ?? "",
+        accuracy: result.accuracy,
+      });
+    }
+  }
+  return phonemes;
+}
+
+// The signed-in user's first scored attempts, phoneme by phoneme, for the
+// Rust server to calibrate a personal baseline from
+export const getCalibrationAttempts = query({
+  args: { limit: v.number() },
+  handler: async (ctx, args) => {
+    const user = await getUserIdFromContext(ctx);
+
+    const attempts = await ctx.db
+      .query("excerpt_practice")
+      .withIndex("by_user_and_time", (q) => q.eq("userId", user))
+      .order("asc")
+      .take(args.limit);
+
+    return await Promise.all(attempts.map(async (attempt) => ({
+      created_at: attempt.created_at,
+      phonemes: await practicePhonemes(ctx, attempt._id),
+    })));
+  },
+});
+
+// The signed-in user's attempts since a time, phoneme by phoneme, for the
+// Rust server to chart their progress
+export const getPhonemeHistory = query({
+  args: { since: v.number() },
+  handler: async (ctx, args) => {
+    const user = await getUserIdFromContext(ctx);
+
+    const attempts = await ctx.db
+      .query("excerpt_practice")
+      .withIndex(
+        "by_user_and_time",
+        (q) => q.eq("userId", user).gte("created_at", args.since),
+      )
+      .collect();
+
+    return await Promise.all(attempts.map(async (attempt) => ({
+      created_at: attempt.created_at,
+      overall_accuracy: attempt.overall_accuracy,
+      phonemes: await practicePhonemes(ctx, attempt._id),
+    })));
+  },
+});
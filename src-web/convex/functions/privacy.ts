@@ -0,0 +1,238 @@
+import { mutation, query } from "../_generated/server.js";
+import type { Id } from "../_generated/dataModel.d.ts";
+import type { QueryCtx } from "../_generated/server.d.ts";
+import { getUserIdFromContext } from "../models/users.ts";
+
+// Documents removed by one deleteMyData call, keeping it within mutation limits
+const DELETE_BATCH_SIZE = 500;
+
+// Practice attempts removed per call, since each has word and phoneme results
+const PRACTICE_BATCH_SIZE = 20;
+
+type Readable<T> = { collect(): Promise<T[]>; take(n: number): Promise<T[]> };
+
+// Everything stored about a user outside their practice attempts, up to
+// `limit` documents of each kind
+async function personalRecords(
+  ctx: QueryCtx,
+  userId: Id<"users">,
+  limit?: number,
+) {
+  const read = <T>(query: Readable<T>) =>
+    limit === undefined ? query.collect() : query.take(limit);
+
+  return {
+    excerpt_progress: await read(
+      ctx.db.query("user_excerpt_progress")
+        .withIndex("by_user_excerpt", (q) => q.eq("userId", userId)),
+    ),
+    chapter_progress: await read(
+      ctx.db.query("user_chapter_progress")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    phoneme_stats: await read(
+      ctx.db.query("user_phoneme_accuracy_stats")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    activity: await read(
+      ctx.db.query("activity_log")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    following: await read(
+      ctx.db.query("user_follow")
+        .withIndex("by_follower", (q) => q.eq("followerId", userId)),
+    ),
+    followers: await read(
+      ctx.db.query("user_follow")
+        .withIndex("by_following", (q) => q.eq("followingId", userId)),
+    ),
+    likes: await read(
+      ctx.db.query("chapter_like")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    bookmarks: await read(
+      ctx.db.query("chapter_bookmark")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    shares: await read(
+      ctx.db.query("chapter_share")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    enrollments: await read(
+      ctx.db.query("classroom_enrollment")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    submissions: await read(
+      ctx.db.query("assignment_submission")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    practice_decks: await read(
+      ctx.db.query("practice_deck")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    streaks: await read(
+      ctx.db.query("user_streak")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    daily_practice: await read(
+      ctx.db.query("daily_practice_log")
+        .withIndex("by_user_date", (q) => q.eq("userId", userId)),
+    ),
+    notifications: await read(
+      ctx.db.query("notifications")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+    badges: await read(
+      ctx.db.query("user_badges")
+        .withIndex("by_user", (q) => q.eq("userId", userId)),
+    ),
+  };
+}
+
+// Everything stored about the caller, for them to download
+export const exportMyData = query({
+  args: {},
+  handler: async (ctx) => {
+    const userId = await getUserIdFromContext(ctx);
+    const user = await ctx.db.get(userId);
+
+    const attempts = await ctx.db
+      .query("excerpt_practice")
+      .withIndex("by_user_and_time", (q) => q.eq("userId", userId))
+      .collect();
+    const practice = await Promise.all(attempts.map(async (attempt) => {
+      const words = await ctx.db
+        .query("word_result")
+        .withIndex("by_practice", (q) => q.eq("practiceId", attempt._id))
+        .collect();
+
+      return {
+        ...attempt,
+        words: await Promise.all(words.map(async (word) => ({
+          ...word,
+          phonemes: await ctx.db
+            .query("phoneme_result")
+            .withIndex("by_word_result", (q) => q.eq("wordResultId", word._id))
+            .collect(),
+        }))),
+      };
+    }));
+
+    const records = await personalRecords(ctx, userId);
+    const submissions = await Promise.all(
+      records.submissions.map(async (submission) => ({
+        ...submission,
+        audio_url: submission.audioId
+          ? await ctx.storage.getUrl(submission.audioId)
+          : null,
+      })),
+    );
+
+    return {
+      user: {
+        _id: userId,
+        name: user?.name ?? "Unknown",
+        picture_url: user?.picture_url ?? null,
+        preferred_tts_voice: user?.preferred_tts_voice ?? null,
+      },
+      practice,
+      ...records,
+      submissions,
+      chapters: await ctx.db
+        .query("chapter")
+        .withIndex("by_created_by", (q) => q.eq("created_by", userId))
+        .collect(),
+      classrooms: await ctx.db
+        .query("classroom")
+        .withIndex("by_teacher", (q) => q.eq("teacherId", userId))
+        .collect(),
+      exported_at: Date.now(),
+    };
+  },
+});
+
+// Delete part of what is stored about the caller, returning whether it is
+// all gone. Call until done: the user itself is deleted last, so later calls
+// can still find them. Chapters and classrooms they created are shared with
+// others, so they are revoked and archived rather than deleted.
+export const deleteMyData = mutation({
+  args: {},
+  handler: async (ctx) => {
+    const userId = await getUserIdFromContext(ctx);
+    const now = Date.now();
+    let deleted = 0;
+
+    const attempts = await ctx.db
+      .query("excerpt_practice")
+      .withIndex("by_user_and_time", (q) => q.eq("userId", userId))
+      .take(PRACTICE_BATCH_SIZE);
+    for (const attempt of attempts) {
+      const words = await ctx.db
+        .query("word_result")
+        .withIndex("by_practice", (q) => q.eq("practiceId", attempt._id))
+        .collect();
+      for (const word of words) {
+        const phonemes = await ctx.db
+          .query("phoneme_result")
+          .withIndex("by_word_result", (q) => q.eq("wordResultId", word._id))
+          .collect();
+        for (const phoneme of phonemes) {
+          await ctx.db.delete(phoneme._id);
+        }
+        await ctx.db.delete(word._id);
+        deleted += phonemes.length + 1;
+      }
+      await ctx.db.delete(attempt._id);
+      deleted += 1;
+    }
+    if (deleted >= DELETE_BATCH_SIZE) {
+      return { done: false, deleted };
+    }
+
+    const records = await personalRecords(
+      ctx,
+      userId,
+      DELETE_BATCH_SIZE - deleted,
+    );
+    for (const submission of records.submissions) {
+      if (submission.audioId) {
+        await ctx.storage.delete(submission.audioId);
+      }
+    }
+    for (const documents of Object.values(records)) {
+      for (const document of documents) {
+        await ctx.db.delete(document._id);
+      }
+      deleted += documents.length;
+    }
+
+    const chapters = await ctx.db
+      .query("chapter")
+      .withIndex(
+        "by_created_by",
+        (q) => q.eq("created_by", userId).eq("revoked_at", undefined),
+      )
+      .take(DELETE_BATCH_SIZE);
+    for (const chapter of chapters) {
+      await ctx.db.patch(chapter._id, { revoked_at: now, updated_at: now });
+    }
+    const classrooms = await ctx.db
+      .query("classroom")
+      .withIndex(
+        "by_teacher",
+        (q) => q.eq("teacherId", userId).eq("archived_at", undefined),
+      )
+      .take(DELETE_BATCH_SIZE);
+    for (const classroom of classrooms) {
+      await ctx.db.patch(classroom._id, { archived_at: now, updated_at: now });
+    }
+    deleted += chapters.length + classrooms.length;
+
+    if (deleted > 0) {
+      return { done: false, deleted };
+    }
+
+    await ctx.db.delete(userId);
+    return { done: true, deleted: 1 };
+  },
+});
@@ -82,7 +82,8 @@ const performanceSchema = {
     .index("by_user_excerpt", ["userId", "excerptId", "created_at"])
     .index("by_user_chapter", ["userId", "chapterId", "created_at"])
     .index("by_excerpt", ["excerptId", "created_at"])
-    .index("by_user_and_time", ["userId", "created_at"]),
+    .index("by_user_and_time", ["userId", "created_at"])
+    .index("by_time", ["created_at"]),
 
   word_result: defineTable({
     practiceId: v.id("excerpt_practice"),
@@ -265,6 +266,39 @@ const classroomSchema = {
   })
     .index("by_classroom", ["classroomId", "order"])
     .index("by_chapter", ["chapterId"]),
+
+  // Sentence sets assigned by a teacher through the Rust server
+  sentence_assignment: defineTable({
+    classroomId: v.id("classroom"),
+    assignedBy: v.id("users"),
+    title: v.string(),
+    dialect: v.string(), // "us" or "uk"
+    sentences: v.array(v.string()),
+    due_date: v.optional(v.number()),
+    created_at: v.number(),
+  }).index("by_classroom", ["classroomId", "created_at"]),
+
+  // A student's scored recording of one sentence of an assignment
+  assignment_submission: defineTable({
+    assignmentId: v.id("sentence_assignment"),
+    classroomId: v.id("classroom"),
+    userId: v.id("users"),
+    sentence_index: v.number(),
+    overall_score: v.number(),
+    phonemes: v.array(v.object({
+      expected: v.string(),
+      actual: v.string(),
+      score: v.number(),
+    })),
+    late: v.boolean(), // Submitted after the due date
+    submitted_at: v.number(),
+    audioId: v.optional(v.id("_storage")), // Recording kept for the teacher, anonymized if enabled
+    audio_expires_at: v.optional(v.number()), // When the recording is deleted
+  })
+    .index("by_assignment", ["assignmentId", "userId"])
+    .index("by_classroom", ["classroomId", "submitted_at"])
+    .index("by_user", ["userId", "submitted_at"])
+    .index("by_audio_expiry", ["audio_expires_at"]),
 };
 
 const mlSchema = {
@@ -287,6 +321,34 @@ const mlSchema = {
   })
     .index("by_user", ["userId"])
     .index("by_user_phoneme", ["userId", "phoneme"]),
+
+  // Assessment responses from the Rust server, keyed by a hash of the request
+  assessment_cache: defineTable({
+    key: v.string(),
+    response: v.string(), // JSON-encoded response body
+    created_at: v.number(),
+  }).index("by_key", ["key"]),
+};
+
+const exerciseSchema = {
+  // Decks of sentences built from pasted text by the Rust server
+  practice_deck: defineTable({
+    userId: v.id("users"),
+    title: v.string(),
+    dialect: v.string(), // "us" or "uk"
+    source_text: v.string(),
+    items: v.array(v.object({
+      text: v.string(),
+      ipa: v.string(),
+      words: v.array(v.object({
+        word: v.string(),
+        ipa: v.string(),
+      })),
+      missing_words: v.array(v.string()), // Not in the dictionary, so not scored
+      reference_audio_url: v.string(),
+    })),
+    created_at: v.number(),
+  }).index("by_user", ["userId", "created_at"]),
 };
 
 const gamificationSchema = {
@@ -346,5 +408,6 @@ export default defineSchema({
   ...socialSchema,
   ...classroomSchema,
   ...mlSchema,
+  ...exerciseSchema,
   ...gamificationSchema,
 });